@@ -0,0 +1,184 @@
+//! Differential testing of `ddb_convert` against this converter
+//!
+//! The reference is the conversion of this crate: it parses each record
+//! with `serde_json` into memory and converts the in-memory value. Both converters
+//! get the same input, and the compact outputs are compared record by record.
+
+use ddb_convert::{convert_ddb_to_normal, convert_normal_to_ddb, Direction, ItemWrapperMode};
+use serde_json::Value;
+
+// Adapter to write the output of `ddb_convert` into a vector
+struct VecWriter<'a>(&'a mut Vec<u8>);
+
+impl embedded_io::ErrorType for VecWriter<'_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl embedded_io::Write for VecWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// The first record where the outputs of the converters differ, see `diff_with_reference`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the record in the input, starting from 0
+    pub record: usize,
+    /// The byte offset of the record in the input
+    pub input_offset: usize,
+    /// The byte offset of the first difference in the output of the record,
+    /// 0 if one of the converters has failed
+    pub output_offset: usize,
+    /// The output of the reference converter for the record, or its error message
+    pub expected: Result<String, String>,
+    /// The output of `ddb_convert` for the record, or its error message
+    pub actual: Result<String, String>,
+}
+
+impl core::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let show = |output: &Result<String, String>| match output {
+            Ok(json) => json.clone(),
+            Err(message) => format!("error: {message}"),
+        };
+        write!(
+            f,
+            "record {} at input offset {} differs at output offset {}\n  expected: {}\n  actual:   {}",
+            self.record,
+            self.input_offset,
+            self.output_offset,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// Convert `input` with `ddb_convert` and with the reference converter,
+/// and compare the outputs record by record.
+///
+/// The input is a sequence of records, such as JSON Lines. The outputs are compact JSON.
+/// For `Direction::DdbToNormal`, the "Item" wrapper is optional, for
+/// `Direction::NormalToDdb`, `with_item_wrapper` tells whether to add it.
+/// If both converters fail on the same record, the outputs agree, and the comparison stops.
+///
+/// Returns the number of compared records.
+///
+/// # Errors
+/// The first record where the outputs or the success of the converters differ
+pub fn diff_with_reference(
+    input: &[u8],
+    direction: Direction,
+    with_item_wrapper: bool,
+) -> Result<usize, Divergence> {
+    let (actual_output, actual_error) =
+        convert_with_ddb_convert(input, direction, with_item_wrapper);
+    // A failed conversion can leave an incomplete record after the last line break
+    let complete_len = actual_output
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |pos| pos + 1);
+    let mut actual_records = actual_output
+        .get(..complete_len)
+        .unwrap_or(&[])
+        .split(|&b| b == b'\n');
+
+    let mut records = serde_json::Deserializer::from_slice(input).into_iter::<Value>();
+    let mut record = 0;
+    loop {
+        let input_offset = skip_whitespace(input, records.byte_offset());
+        let expected = match records.next() {
+            Some(Ok(value)) => convert_with_reference(value, direction, with_item_wrapper),
+            Some(Err(err)) => Err(err.to_string()),
+            None => Err("end of input".to_string()),
+        };
+        let actual = match actual_records.next() {
+            Some(json) if !json.is_empty() => Ok(String::from_utf8_lossy(json).into_owned()),
+            _ => Err(actual_error
+                .clone()
+                .unwrap_or_else(|| "end of input".to_string())),
+        };
+        match (&expected, &actual) {
+            (Ok(expected_json), Ok(actual_json)) if expected_json == actual_json => {}
+            (Err(_), Err(_)) => return Ok(record),
+            _ => {
+                let output_offset = match (&expected, &actual) {
+                    (Ok(expected_json), Ok(actual_json)) => expected_json
+                        .bytes()
+                        .zip(actual_json.bytes())
+                        .take_while(|(e, a)| e == a)
+                        .count(),
+                    _ => 0,
+                };
+                return Err(Divergence {
+                    record,
+                    input_offset,
+                    output_offset,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        record += 1;
+    }
+}
+
+// The output of `ddb_convert`, one record per line, and the error message, if any
+fn convert_with_ddb_convert(
+    input: &[u8],
+    direction: Direction,
+    with_item_wrapper: bool,
+) -> (Vec<u8>, Option<String>) {
+    let mut reader = input;
+    let mut output = Vec::new();
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+    let result = match direction {
+        Direction::DdbToNormal => convert_ddb_to_normal(
+            &mut reader,
+            &mut VecWriter(&mut output),
+            &mut rjiter_buffer,
+            &mut context_buffer,
+            false,
+            false,
+            ItemWrapperMode::AsWrapper,
+        ),
+        Direction::NormalToDdb => convert_normal_to_ddb(
+            &mut reader,
+            &mut VecWriter(&mut output),
+            &mut rjiter_buffer,
+            &mut context_buffer,
+            false,
+            false,
+            with_item_wrapper,
+        ),
+    };
+    let error = result
+        .err()
+        .map(|(err, position)| format!("{err} at position {position}"));
+    (output, error)
+}
+
+fn convert_with_reference(
+    value: Value,
+    direction: Direction,
+    with_item_wrapper: bool,
+) -> Result<String, String> {
+    let converted = match direction {
+        Direction::DdbToNormal => crate::from_dynamodb(value),
+        Direction::NormalToDdb => crate::to_dynamodb(value, with_item_wrapper),
+    };
+    converted
+        .map(|value| value.to_string())
+        .map_err(|err| err.to_string())
+}
+
+fn skip_whitespace(input: &[u8], offset: usize) -> usize {
+    let rest = input.get(offset..).unwrap_or(&[]);
+    offset + rest.iter().take_while(|b| b.is_ascii_whitespace()).count()
+}
@@ -0,0 +1,162 @@
+//! The conversion of one record, shared by the command line tool and
+//! by the differential tester of `ddb_convert`, see `differential`
+
+mod differential;
+
+pub use differential::{diff_with_reference, Divergence};
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+/// Convert a `DynamoDB` JSON record to normal JSON, with or without the "Item" wrapper
+pub fn from_dynamodb(value: Value) -> Result<Value> {
+    let mut obj = match value {
+        Value::Object(o) => o,
+        _ => anyhow::bail!("Expected JSON object"),
+    };
+
+    // Check if it has "Item" wrapper - consume instead of clone
+    let obj = if obj.len() == 1 && obj.contains_key("Item") {
+        match obj.remove("Item") {
+            Some(Value::Object(o)) => o,
+            _ => anyhow::bail!("Expected Item to be an object"),
+        }
+    } else {
+        obj
+    };
+
+    // Unmarshall DynamoDB format
+    let mut result = Map::new();
+    for (key, value) in obj {
+        result.insert(key, unmarshall_value(value)?);
+    }
+
+    Ok(Value::Object(result))
+}
+
+/// Convert a normal JSON record to `DynamoDB` JSON
+pub fn to_dynamodb(value: Value, wrap_item: bool) -> Result<Value> {
+    let obj = match value {
+        Value::Object(o) => o,
+        _ => anyhow::bail!("Expected JSON object"),
+    };
+
+    // Marshall to DynamoDB format
+    let mut result = Map::new();
+    for (key, value) in obj {
+        result.insert(key, marshall_value(value)?);
+    }
+
+    if wrap_item {
+        let mut wrapped = Map::new();
+        wrapped.insert("Item".to_string(), Value::Object(result));
+        Ok(Value::Object(wrapped))
+    } else {
+        Ok(Value::Object(result))
+    }
+}
+
+fn unmarshall_value(value: Value) -> Result<Value> {
+    let obj = match value {
+        Value::Object(o) => o,
+        _ => anyhow::bail!("Expected DynamoDB type object"),
+    };
+
+    if obj.len() != 1 {
+        anyhow::bail!("DynamoDB type object must have exactly one key");
+    }
+
+    let (type_key, type_value) = obj.into_iter().next().unwrap();
+
+    match type_key.as_str() {
+        "S" => Ok(type_value),
+        "N" => {
+            let s = type_value.as_str().context("N type must be string")?;
+            // Parse the number string into a JSON number
+            let num: serde_json::Number = s.parse().context("Invalid number format")?;
+            Ok(Value::Number(num))
+        }
+        "BOOL" => Ok(type_value),
+        "NULL" => Ok(Value::Null),
+        "M" => {
+            let map = match type_value {
+                Value::Object(o) => o,
+                _ => anyhow::bail!("M type must be object"),
+            };
+            let mut result = Map::new();
+            for (k, v) in map {
+                result.insert(k, unmarshall_value(v)?);
+            }
+            Ok(Value::Object(result))
+        }
+        "L" => {
+            let list = match type_value {
+                Value::Array(a) => a,
+                _ => anyhow::bail!("L type must be array"),
+            };
+            let mut result = Vec::new();
+            for item in list {
+                result.push(unmarshall_value(item)?);
+            }
+            Ok(Value::Array(result))
+        }
+        "SS" => Ok(type_value),
+        "NS" => {
+            let arr = type_value.as_array().context("NS type must be array")?;
+            let mut result = Vec::new();
+            for item in arr {
+                let s = item.as_str().context("NS items must be strings")?;
+                let num: serde_json::Number = s.parse().context("Invalid number format")?;
+                result.push(Value::Number(num));
+            }
+            Ok(Value::Array(result))
+        }
+        "BS" => Ok(type_value),
+        "B" => Ok(type_value),
+        _ => anyhow::bail!("Unknown DynamoDB type: {}", type_key),
+    }
+}
+
+fn marshall_value(value: Value) -> Result<Value> {
+    match value {
+        Value::Null => {
+            let mut map = Map::new();
+            map.insert("NULL".to_string(), Value::Bool(true));
+            Ok(Value::Object(map))
+        }
+        Value::Bool(b) => {
+            let mut map = Map::new();
+            map.insert("BOOL".to_string(), Value::Bool(b));
+            Ok(Value::Object(map))
+        }
+        Value::Number(n) => {
+            let mut map = Map::new();
+            map.insert("N".to_string(), Value::String(n.to_string()));
+            Ok(Value::Object(map))
+        }
+        Value::String(s) => {
+            let mut map = Map::new();
+            map.insert("S".to_string(), Value::String(s));
+            Ok(Value::Object(map))
+        }
+        Value::Array(arr) => {
+            // Always use generic List type (L)
+            let mut items = Vec::new();
+            for item in arr {
+                items.push(marshall_value(item)?);
+            }
+            let mut map = Map::new();
+            map.insert("L".to_string(), Value::Array(items));
+            Ok(Value::Object(map))
+        }
+        Value::Object(obj) => {
+            let mut map = Map::new();
+            for (k, v) in obj {
+                map.insert(k, marshall_value(v)?);
+            }
+            let mut result = Map::new();
+            result.insert("M".to_string(), Value::Object(map));
+            Ok(Value::Object(result))
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use ddb_convert::Direction;
+use ddb_convert_rust::{diff_with_reference, Divergence};
+
+#[test]
+fn test_fixtures_agree_with_reference() {
+    for (fixture, n_records) in [
+        (
+            &include_bytes!("../../../../examples/dynamodb/fixture/users-dynamodb.jsonl")[..],
+            2,
+        ),
+        (
+            include_bytes!("../../../../examples/dynamodb/fixture/products-dynamodb.jsonl"),
+            2,
+        ),
+        (
+            include_bytes!("../../../../examples/dynamodb/fixture/book-dynamodb.json"),
+            1,
+        ),
+        (
+            include_bytes!("../../../../examples/dynamodb/fixture/all-types-dynamodb.json"),
+            1,
+        ),
+    ] {
+        assert_eq!(
+            diff_with_reference(fixture, Direction::DdbToNormal, true),
+            Ok(n_records)
+        );
+    }
+}
+
+#[test]
+fn test_normal_to_ddb_agrees_with_reference() {
+    let input = br#"{"name": "Alice", "tags": ["a", "b"], "address": {"city": "Paris"}}
+{"active": true, "nothing": null, "age": 42}
+"#;
+    for with_item_wrapper in [true, false] {
+        assert_eq!(
+            diff_with_reference(input, Direction::NormalToDdb, with_item_wrapper),
+            Ok(2)
+        );
+    }
+}
+
+#[test]
+fn test_reports_first_different_record() {
+    let input = br#"{"a": 1}
+{"n": 1e2}
+{"m": 2e3}
+"#;
+    let divergence = diff_with_reference(input, Direction::NormalToDdb, true).unwrap_err();
+    assert_eq!(
+        divergence,
+        Divergence {
+            record: 1,
+            input_offset: 9,
+            output_offset: 20,
+            expected: Ok(r#"{"Item":{"n":{"N":"100.0"}}}"#.to_string()),
+            actual: Ok(r#"{"Item":{"n":{"N":"1e2"}}}"#.to_string()),
+        }
+    );
+    assert!(divergence
+        .to_string()
+        .starts_with("record 1 at input offset 9 differs at output offset 20"));
+}
+
+#[test]
+fn test_reports_failure_of_one_converter() {
+    let input = b"{\"a\": 1}\n[1]\n";
+    let divergence = diff_with_reference(input, Direction::NormalToDdb, false).unwrap_err();
+    assert_eq!(divergence.record, 1);
+    assert_eq!(divergence.input_offset, 9);
+    assert_eq!(divergence.output_offset, 0);
+    assert!(divergence.expected.is_err());
+    assert_eq!(divergence.actual, Ok(r#"{"L":[{"N":"1"}]}"#.to_string()));
+}
+
+#[test]
+fn test_both_converters_fail_on_same_record() {
+    let input = br#"{"a": {"S": "x"}}
+{"b": 1}
+{"c": {"S": "y"}}
+"#;
+    assert_eq!(
+        diff_with_reference(input, Direction::DdbToNormal, true),
+        Ok(1)
+    );
+}
@@ -0,0 +1,192 @@
+//! Conversion of an input of unknown direction, see `convert_auto`
+
+use crate::ddb_to_normal::{is_known_type_descriptor, MAX_DETECTION_LOOKAHEAD};
+use crate::{
+    convert_ddb_to_normal_with_options, convert_normal_to_ddb_with_options, ConversionError,
+    DdbToNormalOptions, ItemWrapperMode, NormalToDdbOptions,
+};
+use embedded_io::{Error as IoError, Read as IoRead, Write as IoWrite};
+use rjiter::jiter::{Jiter, Peek};
+
+/// The direction of the conversion chosen by `convert_auto`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The input is `DynamoDB` JSON, converted to normal JSON
+    DdbToNormal,
+    /// The input is normal JSON, converted to `DynamoDB` JSON
+    NormalToDdb,
+}
+
+/// The keys which decide the direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyKind {
+    Item,
+    TypeDescriptor,
+    Other,
+}
+
+impl KeyKind {
+    fn of(key: &[u8]) -> Self {
+        match key {
+            b"Item" => KeyKind::Item,
+            _ if is_known_type_descriptor(key) => KeyKind::TypeDescriptor,
+            _ => KeyKind::Other,
+        }
+    }
+}
+
+/// Decide the direction by the value of the first attribute: `DynamoDB` JSON
+/// if it is an object with a type descriptor. The "Item" wrapper is not
+/// an attribute, records without attributes are skipped.
+///
+/// Returns `None` if the bytes end before the decision.
+fn detect(bytes: &[u8], item_wrapper_mode: ItemWrapperMode) -> Option<Direction> {
+    let mut jiter = Jiter::new(bytes);
+    loop {
+        if jiter.peek().ok()? != Peek::Object {
+            return Some(Direction::DdbToNormal);
+        }
+        let mut key = jiter.next_object_bytes().ok()?.map(KeyKind::of);
+        let mut in_wrapper = false;
+        loop {
+            let Some(kind) = key else {
+                if !in_wrapper {
+                    // A record without attributes, look at the next one
+                    break;
+                }
+                in_wrapper = false;
+                key = jiter.next_key_bytes().ok()?.map(KeyKind::of);
+                continue;
+            };
+            let peek = jiter.peek().ok()?;
+            if !in_wrapper
+                && kind == KeyKind::Item
+                && item_wrapper_mode == ItemWrapperMode::AsWrapper
+                && peek == Peek::Object
+            {
+                in_wrapper = true;
+                key = jiter.next_object_bytes().ok()?.map(KeyKind::of);
+            } else if peek != Peek::Object {
+                return Some(Direction::NormalToDdb);
+            } else {
+                let value_key = jiter.next_object_bytes().ok()?.map(KeyKind::of);
+                return Some(if value_key == Some(KeyKind::TypeDescriptor) {
+                    Direction::DdbToNormal
+                } else {
+                    Direction::NormalToDdb
+                });
+            }
+        }
+    }
+}
+
+/// A reader which returns the already read beginning of the input, then the rest
+struct PrefixReader<'p, 'r, R: IoRead> {
+    prefix: &'p [u8],
+    reader: &'r mut R,
+}
+
+impl<R: IoRead> embedded_io::ErrorType for PrefixReader<'_, '_, R> {
+    type Error = R::Error;
+}
+
+impl<R: IoRead> IoRead for PrefixReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.prefix.is_empty() {
+            return self.reader.read(buf);
+        }
+        let len = buf.len().min(self.prefix.len());
+        let (head, rest) = self.prefix.split_at(len);
+        if let Some(dst) = buf.get_mut(..len) {
+            dst.copy_from_slice(head);
+        }
+        self.prefix = rest;
+        Ok(len)
+    }
+}
+
+/// Convert an input of unknown direction: `DynamoDB` JSON to normal JSON,
+/// or normal JSON to `DynamoDB` JSON.
+///
+/// The direction is decided by the value of the first attribute of the first
+/// record with attributes: an object with a type descriptor (`{"S": ...}`,
+/// `{"M": ...}` and so on) means `DynamoDB` JSON, any other value means normal JSON.
+/// The "Item" wrapper (see `ItemWrapperMode`) is skipped. If the first 512 bytes of the input are not enough to decide,
+/// or there are no attributes, the input is converted as `DynamoDB` JSON.
+///
+/// The bytes read for the decision are kept on the stack and given to the converter,
+/// therefore the reader is read only once and the error positions are in the whole input.
+///
+/// # Arguments
+///
+/// * `reader` - Input stream
+/// * `writer` - Output stream
+/// * `rjiter_buffer` - Buffer for `RJiter` to use
+/// * `context_buffer` - Buffer for the `scan_json` context
+/// * `to_normal` - Options for the `DynamoDB` JSON input, see `convert_ddb_to_normal_with_options`.
+///   `item_wrapper_mode` is also used for the decision.
+/// * `to_ddb` - Options for the normal JSON input, see `convert_normal_to_ddb_with_options`
+///
+/// # Returns
+///
+/// The chosen direction
+///
+/// # Errors
+///
+/// `IOError` if reading the beginning of the input fails,
+/// otherwise the errors of the chosen converter
+pub fn convert_auto<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    to_normal: &DdbToNormalOptions,
+    to_ddb: &NormalToDdbOptions,
+) -> Result<Direction, (ConversionError, usize)> {
+    let mut head = [0u8; MAX_DETECTION_LOOKAHEAD];
+    let mut len = 0;
+    let direction = loop {
+        let bytes = head.get(..len).unwrap_or(&[]);
+        if let Some(direction) = detect(bytes, to_normal.item_wrapper_mode) {
+            break direction;
+        }
+        let Some(free) = head.get_mut(len..).filter(|free| !free.is_empty()) else {
+            break Direction::DdbToNormal;
+        };
+        let n = reader.read(free).map_err(|e| {
+            (
+                ConversionError::IOError {
+                    kind: e.kind(),
+                    context: "reading the input to detect the direction",
+                },
+                len,
+            )
+        })?;
+        if n == 0 {
+            break Direction::DdbToNormal;
+        }
+        len += n;
+    };
+
+    let mut reader = PrefixReader {
+        prefix: head.get(..len).unwrap_or(&[]),
+        reader,
+    };
+    match direction {
+        Direction::DdbToNormal => convert_ddb_to_normal_with_options(
+            &mut reader,
+            writer,
+            rjiter_buffer,
+            context_buffer,
+            to_normal,
+        ),
+        Direction::NormalToDdb => convert_normal_to_ddb_with_options(
+            &mut reader,
+            writer,
+            rjiter_buffer,
+            context_buffer,
+            to_ddb,
+        ),
+    }?;
+    Ok(direction)
+}
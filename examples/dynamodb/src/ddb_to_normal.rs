@@ -1,11 +1,16 @@
-use crate::ConversionError;
-use core::cell::RefCell;
+use crate::fmt_writer::FmtWriteAdapter;
+use crate::framing::{Framing, FramingWriter};
+use crate::metrics::{AttributeCounts, CountingReader, CountingWriter, Metrics};
+use crate::number::NumberNormalizer;
+use crate::route::{RoutingWriter, TableRouter};
+use crate::{ConversionError, InvalidUtf8Policy, UnknownTypePolicy, TAGGED_TYPE_PREFIX};
+use core::cell::{Cell, RefCell};
 use embedded_io::{Error as IoError, Read as IoRead, Write as IoWrite};
 use rjiter::jiter::Peek;
 use rjiter::RJiter;
 use scan_json::matcher::StructuralPseudoname;
 use scan_json::stack::ContextIter;
-use scan_json::{scan, Action, EndAction, Options, StreamOp};
+use scan_json::{expect_type, scan, Action, EndAction, Options, ScanReport, StreamOp, ValueType};
 use u8pool::U8Pool;
 
 
@@ -61,6 +66,239 @@ pub enum ItemWrapperMode {
     AsField,
 }
 
+/// Target type of a coercion, see `DdbToNormalOptions::type_coercions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoerceTo {
+    /// Write the string as a number if it is a valid JSON number
+    Number,
+    /// Write the string as a boolean if it is `true` or `false`
+    Bool,
+}
+
+/// How to write `N` values and the elements of `NS` sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberOutput {
+    /// Write the number as in the input
+    AsIs,
+    /// Remove the leading zeros and the trailing zeros of the fraction,
+    /// therefore an integral value is written as an integer:
+    /// `42.0` becomes `42`, `007` becomes `7`, `1.50` becomes `1.5`.
+    /// The exponent is kept, for example, `1.0e3` becomes `1e3`.
+    NormalizeIntegers,
+}
+
+/// How to handle records which are not `DynamoDB` JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tolerance {
+    /// Convert every record as `DynamoDB` JSON, fail on other records
+    Strict,
+    /// Copy a record as is if it looks like normal JSON: the first attribute
+    /// has no type descriptor. Useful for inputs which mix both formats.
+    /// The copy is compact, also with `pretty`.
+    PassThroughNormal,
+}
+
+/// What to write for a scalar attribute, the result of `ScalarHook`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// Write the value as usual
+    Keep,
+    /// Write the first `n` bytes of the replacement buffer as the value.
+    /// The bytes are written as is, therefore they should be valid JSON, for example, `"***"`.
+    Replace(usize),
+    /// Don't write the attribute. In a list, the element is removed.
+    Drop,
+}
+
+/// Maximum length of a replacement value written by `ScalarHook`
+pub const MAX_SCALAR_REPLACEMENT: usize = 256;
+
+/// Hook for scalar attributes (`S`, `N`, `B`, `BOOL`, `NULL`), see `DdbToNormalOptions::on_scalar`.
+///
+/// Arguments:
+/// * `path` - Dot-separated field names from the top of the item, for example, `user.email`.
+///   An element of a list is addressed with `[]`, for example, `tags[]` or `orders[].price`.
+/// * `type_key` - The type descriptor
+/// * `value` - The string content for `S`, `N` and `B` (as in the input, without quotes),
+///   `true` or `false` for `BOOL`, `null` for `NULL`
+/// * `replacement` - Buffer for `Emit::Replace`, `MAX_SCALAR_REPLACEMENT` bytes
+pub type ScalarHook =
+    fn(path: &[u8], type_key: &[u8], value: &[u8], replacement: &mut [u8]) -> Emit;
+
+/// Maximum number of attributes in `DdbToNormalOptions::presence_fields`
+pub const MAX_PRESENCE_FIELDS: usize = 64;
+
+/// Name of the field with the presence of attributes, see `PresenceOutput::AppendField`
+pub const PRESENCE_FIELD: &[u8] = b"$present";
+
+/// Callback for the presence of attributes, see `PresenceOutput::Callback`.
+///
+/// Arguments:
+/// * `record` - The number of the record, starting from 1
+/// * `present` - Bit `i` is set if the attribute `presence_fields[i]` is in the record
+pub type PresenceCallback = fn(record: u64, present: u64);
+
+/// How to deliver the presence of attributes, see `DdbToNormalOptions::presence_fields`
+#[derive(Debug, Clone, Copy)]
+pub enum PresenceOutput {
+    /// Append the field `PRESENCE_FIELD` to each record, an array of booleans
+    /// in the order of `presence_fields`, for example, `"$present":[true,false]`
+    AppendField,
+    /// Call the function after each record
+    Callback(PresenceCallback),
+}
+
+/// Callback after each converted record, see `DdbToNormalOptions::on_record`.
+///
+/// Arguments:
+/// * `start` - Offset of the opening brace of the record in the input
+/// * `end` - Offset after the closing brace of the record in the input
+/// * `bytes_out` - Number of bytes written to the output so far, including the record
+///   and its final newline, therefore the end of the record in the output
+pub type RecordCallback = fn(start: usize, end: usize, bytes_out: u64);
+
+/// Maximum depth of `DdbToNormalOptions::flatten`, the nesting limit of `DynamoDB`
+pub const MAX_FLATTEN_DEPTH: usize = 32;
+
+/// Maximum length of a flattened key, such as `address.city` or `tags[0]`
+pub const MAX_FLAT_KEY: usize = 256;
+
+/// An `M` or `L` value which is flattened into the keys of the record
+#[derive(Debug, Clone, Copy, Default)]
+struct FlatLevel {
+    prefix_len: usize, // Length of the flattened key of the container itself
+    is_list: bool,
+    children: usize, // Number of fields or elements, the next list index
+}
+
+/// Limit of the conversion to a prefix of the input, see `DdbToNormalOptions::limit`.
+///
+/// The conversion stops at a record boundary: a record is either converted
+/// completely or not started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    /// Stop after this number of records, no limit if `None`
+    pub max_records: Option<u64>,
+    /// Don't start a new record after this number of input bytes, no limit if `None`.
+    /// The record which crosses the limit is converted completely.
+    pub max_bytes: Option<usize>,
+}
+
+impl Limit {
+    /// No limit, convert the whole input
+    pub const NONE: Limit = Limit {
+        max_records: None,
+        max_bytes: None,
+    };
+
+    fn is_reached(&self, records: u64, position: usize) -> bool {
+        self.max_records.is_some_and(|max| records >= max)
+            || self.max_bytes.is_some_and(|max| position >= max)
+    }
+}
+
+/// The result of `convert_ddb_to_normal_prefix`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefixReport {
+    /// The number of converted records
+    pub records: u64,
+    /// The input bytes up to the end of the last converted record.
+    /// To resume, start a new conversion from this offset.
+    pub consumed: usize,
+    /// The conversion stopped because of the limit, the input can have more records
+    pub is_limit_reached: bool,
+}
+
+/// Options for `convert_ddb_to_normal_with_options`
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct DdbToNormalOptions<'a> {
+    /// Whether to pretty-print the output
+    pub pretty: bool,
+    /// Whether to flush after every write
+    pub unbuffered: bool,
+    /// How to handle "Item" key at top level
+    pub item_wrapper_mode: ItemWrapperMode,
+    /// How to handle unknown type descriptors
+    pub unknown_type_policy: UnknownTypePolicy,
+    /// Reject empty sets (SS, NS, BS) like `DynamoDB` does
+    pub reject_empty_sets: bool,
+    /// Maximum number of elements in a set (SS, NS, BS), no limit if `None`
+    pub max_set_elements: Option<usize>,
+    /// Write `S` values at the given paths as numbers or booleans.
+    ///
+    /// A path is a dot-separated list of field names from the top of the item,
+    /// for example, `user.age`. Values in lists are not addressed.
+    /// A string which doesn't look like the target type is kept as a string.
+    /// The escapes are decoded before the check, for example, `"\u0034\u0032"` is the number 42.
+    /// A value with a coercion should fit into the `RJiter` buffer.
+    pub type_coercions: &'a [(&'a str, CoerceTo)],
+    /// Hook to keep, replace or drop scalar attributes during the conversion,
+    /// for example, to redact personal data. No hook if `None`.
+    ///
+    /// A scalar value with a hook should fit into the `RJiter` buffer,
+    /// otherwise the conversion fails with `ConversionError::AttributeTooLarge`.
+    pub on_scalar: Option<ScalarHook>,
+    /// How to handle records which are not `DynamoDB` JSON
+    pub tolerance: Tolerance,
+    /// Top-level attributes to report as present or absent for each record,
+    /// at most `MAX_PRESENCE_FIELDS`. No report if empty.
+    ///
+    /// An attribute dropped by `on_scalar` is reported as present.
+    /// Records copied by `Tolerance::PassThroughNormal` are not reported.
+    pub presence_fields: &'a [&'a str],
+    /// How to deliver the presence of `presence_fields`
+    pub presence_output: PresenceOutput,
+    /// Flatten the nested `M` and `L` values into the top-level keys
+    /// `address.city` and `tags[0]`, up to the given depth (at most `MAX_FLATTEN_DEPTH`).
+    /// The values below the depth are written as nested JSON. No flattening if `None`.
+    ///
+    /// An empty map or list is written as `{}` or `[]`. A flattened key
+    /// should fit into `MAX_FLAT_KEY` bytes.
+    pub flatten: Option<usize>,
+    /// How to handle attribute names which are not valid UTF-8
+    pub invalid_utf8_names: InvalidUtf8Policy,
+    /// How to write `N` values and the elements of `NS` sets.
+    /// The value for `on_scalar` is as in the input.
+    pub number_output: NumberOutput,
+    /// Convert only the first records or bytes of the input, see `Limit`.
+    /// Use `convert_ddb_to_normal_prefix` to get the offset to resume from.
+    pub limit: Limit,
+    /// Callback after each record with its boundaries in the input and the output,
+    /// for example, to build an offset map of the export without a second pass.
+    /// Not called for the failed record. In the routed conversion, the output bytes
+    /// are summed over the tables, with `Framing::LengthPrefixed` they include the prefixes.
+    pub on_record: Option<RecordCallback>,
+}
+
+impl DdbToNormalOptions<'_> {
+    /// Creates default options: compact output, buffered, "Item" as a wrapper,
+    /// unknown type descriptors are errors, no validation of sets, only `DynamoDB` records,
+    /// no limit
+    #[allow(clippy::new_without_default)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            pretty: false,
+            unbuffered: false,
+            item_wrapper_mode: ItemWrapperMode::AsWrapper,
+            unknown_type_policy: UnknownTypePolicy::Error,
+            reject_empty_sets: false,
+            max_set_elements: None,
+            type_coercions: &[],
+            on_scalar: None,
+            tolerance: Tolerance::Strict,
+            presence_fields: &[],
+            presence_output: PresenceOutput::AppendField,
+            flatten: None,
+            invalid_utf8_names: InvalidUtf8Policy::Copy,
+            number_output: NumberOutput::AsIs,
+            limit: Limit::NONE,
+            on_record: None,
+        }
+    }
+}
+
 /// Type descriptor being processed (only for container types)
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum TypeDesc {
@@ -70,6 +308,7 @@ enum TypeDesc {
     M, // Nested containers
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct DdbConverter<'a, 'workbuf, W: IoWrite> {
     writer: &'a mut W,
     pending_comma: bool,
@@ -78,23 +317,89 @@ pub struct DdbConverter<'a, 'workbuf, W: IoWrite> {
     output_depth: usize, // JSON output nesting depth (for pretty-printing indentation and root level detection)
     current_field: Option<&'workbuf [u8]>,
     item_wrapper_mode: ItemWrapperMode, // How to handle "Item" key at top level
-    last_error: Option<ConversionError>, // Stores detailed error information
+    unknown_type_policy: UnknownTypePolicy,
+    reject_empty_sets: bool,
+    max_set_elements: Option<usize>,
+    set_element_count: usize, // Number of elements in the current SS/NS/BS
+    type_coercions: &'a [(&'a str, CoerceTo)],
+    coerce_to: Option<CoerceTo>, // Coercion for the current S value
+    on_scalar: Option<ScalarHook>,
+    pending_field: Option<&'workbuf [u8]>, // Field name to write with the value, when the hook is set
+    hook_path: [u8; MAX_HOOK_PATH],        // Path of the current scalar for the hook
+    hook_path_len: usize,
+    tolerance: Tolerance,
+    presence_fields: &'a [&'a str],
+    presence_output: PresenceOutput,
+    present: u64, // Bit `i` is set if `presence_fields[i]` is in the current record
+    flatten: Option<usize>,
+    flat_key: [u8; MAX_FLAT_KEY], // Flattened key of the current attribute
+    flat_key_len: usize,
+    flat_levels: [FlatLevel; MAX_FLATTEN_DEPTH],
+    n_flat_levels: usize,
+    written_depth: usize, // Containers written as nested JSON below the flattened ones
+    flat_pending: bool,   // The flattened key is to be written with the value
+    invalid_utf8_names: InvalidUtf8Policy,
+    number_output: NumberOutput,
+    select_table: Option<SelectTable<W>>, // Set for `convert_ddb_to_normal_routed`
+    has_table: bool,                      // The table of the current record is selected
+    end_record: Option<EndRecord<W>>,     // Set for `convert_ddb_to_normal_framed`
+    last_error: Option<ConversionError>,  // Stores detailed error information
+    records: u64,
+    record_start: usize, // Input offset of the current record
+    wrapped_records: u64,
+    attributes: AttributeCounts,
+    set_elements: u64,
+    max_set_len: u64,
+    max_string_len: u64, // Input bytes between the quotes
 
     phase: Phase,
     current_type: Option<TypeDesc>,
 }
 
 impl<'a, W: IoWrite> DdbConverter<'a, '_, W> {
-    fn new(writer: &'a mut W, pretty: bool, unbuffered: bool, item_wrapper_mode: ItemWrapperMode) -> Self {
+    fn new(writer: &'a mut W, options: &DdbToNormalOptions<'a>) -> Self {
         Self {
             writer,
             pending_comma: false,
-            pretty,
-            unbuffered,
+            pretty: options.pretty,
+            unbuffered: options.unbuffered,
             output_depth: 0,
             current_field: None,
-            item_wrapper_mode,
+            item_wrapper_mode: options.item_wrapper_mode,
+            unknown_type_policy: options.unknown_type_policy,
+            reject_empty_sets: options.reject_empty_sets,
+            max_set_elements: options.max_set_elements,
+            set_element_count: 0,
+            type_coercions: options.type_coercions,
+            coerce_to: None,
+            on_scalar: options.on_scalar,
+            pending_field: None,
+            hook_path: [0; MAX_HOOK_PATH],
+            hook_path_len: 0,
+            tolerance: options.tolerance,
+            presence_fields: options.presence_fields,
+            presence_output: options.presence_output,
+            present: 0,
+            flatten: options.flatten.map(|depth| depth.min(MAX_FLATTEN_DEPTH)),
+            flat_key: [0; MAX_FLAT_KEY],
+            flat_key_len: 0,
+            flat_levels: [FlatLevel::default(); MAX_FLATTEN_DEPTH],
+            n_flat_levels: 0,
+            written_depth: 0,
+            flat_pending: false,
+            invalid_utf8_names: options.invalid_utf8_names,
+            number_output: options.number_output,
+            select_table: None,
+            has_table: false,
+            end_record: None,
             last_error: None,
+            records: 0,
+            record_start: 0,
+            wrapped_records: 0,
+            attributes: AttributeCounts::default(),
+            set_elements: 0,
+            max_set_len: 0,
+            max_string_len: 0,
             phase: Phase::ExpectingField,
             current_type: None,
         }
@@ -123,9 +428,32 @@ impl<'a, W: IoWrite> DdbConverter<'a, '_, W> {
         self.last_error = Some(ConversionError::ParseError {
             context,
             unknown_type,
+            invalid_utf8: None,
         });
     }
 
+    /// Check the attribute name if the policy is `InvalidUtf8Policy::Error`
+    fn check_name_utf8(&mut self, field_name: &[u8]) -> Result<(), &'static str> {
+        if self.invalid_utf8_names != InvalidUtf8Policy::Error {
+            return Ok(());
+        }
+        let Err(e) = core::str::from_utf8(field_name) else {
+            return Ok(());
+        };
+        let invalid = field_name.get(e.valid_up_to()..).unwrap_or(&[]);
+        let mut buffer = [0u8; 16];
+        let len = invalid.len().min(buffer.len());
+        if let (Some(dst), Some(src)) = (buffer.get_mut(..len), invalid.get(..len)) {
+            dst.copy_from_slice(src);
+        }
+        self.last_error = Some(ConversionError::ParseError {
+            context: "Attribute name is not valid UTF-8",
+            unknown_type: None,
+            invalid_utf8: Some((buffer, len)),
+        });
+        Err("Attribute name is not valid UTF-8")
+    }
+
     fn write(&mut self, bytes: &[u8]) -> Result<(), embedded_io::ErrorKind> {
         self.writer.write_all(bytes).map_err(|e| e.kind())?;
         if self.unbuffered {
@@ -143,6 +471,58 @@ impl<'a, W: IoWrite> DdbConverter<'a, '_, W> {
         })
     }
 
+    /// Write a number, normalized if `NumberOutput::NormalizeIntegers`
+    fn try_write_number(&mut self, bytes: &[u8]) -> Result<(), &'static str> {
+        if self.number_output == NumberOutput::AsIs {
+            return self.try_write_any(bytes, "writing number");
+        }
+        let mut normalizer = NumberNormalizer::new(&mut *self.writer);
+        let result = normalizer
+            .write_all(bytes)
+            .and_then(|()| normalizer.finish());
+        let result = result.and_then(|()| {
+            if self.unbuffered {
+                self.writer.flush()
+            } else {
+                Ok(())
+            }
+        });
+        result.map_err(|e| {
+            self.last_error = Some(ConversionError::IOError {
+                kind: e.kind(),
+                context: "writing normalized number",
+            });
+            "Write failed"
+        })
+    }
+
+    /// Tell the routing and framing writers that the record is written
+    fn finish_record(&mut self) -> Result<(), &'static str> {
+        if let Some(select_table) = self.select_table {
+            if let Err(e) = select_table(self.writer, None) {
+                self.last_error = Some(e);
+                return Err("Failed to end the record of the table");
+            }
+        }
+        if let Some(end_record) = self.end_record {
+            if let Err(e) = end_record(self.writer) {
+                self.last_error = Some(e);
+                return Err("Failed to write the framed record");
+            }
+            // The record is written only now
+            if self.unbuffered {
+                if let Err(e) = self.writer.flush() {
+                    self.last_error = Some(ConversionError::IOError {
+                        kind: e.kind(),
+                        context: "flushing the framed record",
+                    });
+                    return Err("Failed to flush writer");
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn write_comma_if_pending(&mut self) -> Result<(), &'static str> {
         if self.pending_comma {
             self.try_write_any(b",", "writing comma")?;
@@ -152,6 +532,193 @@ impl<'a, W: IoWrite> DdbConverter<'a, '_, W> {
         Ok(())
     }
 
+    fn write_field_name(&mut self, field_name: &[u8]) -> Result<(), &'static str> {
+        self.write_comma_if_pending()?;
+        self.indent_if_pretty()?;
+        self.try_write_any(b"\"", "writing field name opening quote")?;
+        if self.invalid_utf8_names == InvalidUtf8Policy::Replace {
+            for chunk in field_name.utf8_chunks() {
+                self.try_write_any(chunk.valid().as_bytes(), "writing field name")?;
+                if !chunk.invalid().is_empty() {
+                    self.try_write_any("\u{fffd}".as_bytes(), "writing field name")?;
+                }
+            }
+        } else {
+            self.try_write_any(field_name, "writing field name")?;
+        }
+        self.try_write_any(b"\":", "writing field name closing quote and colon")?;
+        self.pending_comma = false;
+        Ok(())
+    }
+
+    fn write_pending_field(&mut self) -> Result<(), &'static str> {
+        if self.flat_pending {
+            self.flat_pending = false;
+            return self.write_flat_key();
+        }
+        match self.pending_field.take() {
+            Some(field_name) => self.write_field_name(field_name),
+            None => Ok(()),
+        }
+    }
+
+    fn write_flat_key(&mut self) -> Result<(), &'static str> {
+        let flat_key = self.flat_key;
+        self.write_field_name(flat_key.get(..self.flat_key_len).unwrap_or(&[]))
+    }
+
+    /// Whether the keys are flattened at the current position
+    fn is_flattening(&self) -> bool {
+        self.flatten.is_some() && self.written_depth == 0
+    }
+
+    fn top_flat_level(&mut self) -> Option<&mut FlatLevel> {
+        let index = self.n_flat_levels.checked_sub(1)?;
+        self.flat_levels.get_mut(index)
+    }
+
+    fn push_flat_key(&mut self, bytes: &[u8]) -> Result<(), &'static str> {
+        let end = self.flat_key_len + bytes.len();
+        let Some(dst) = self.flat_key.get_mut(self.flat_key_len..end) else {
+            self.store_parse_error("Flattened key is too long", None);
+            return Err("Flattened key is too long");
+        };
+        dst.copy_from_slice(bytes);
+        self.flat_key_len = end;
+        Ok(())
+    }
+
+    /// Start the flattened key of a field: the key of the map and `.field_name`
+    fn begin_flat_field(&mut self, field_name: &[u8]) -> Result<(), &'static str> {
+        let prefix_len = self.top_flat_level().map_or(0, |level| {
+            level.children += 1;
+            level.prefix_len
+        });
+        self.flat_key_len = prefix_len;
+        if prefix_len > 0 {
+            self.push_flat_key(b".")?;
+        }
+        self.push_flat_key(field_name)?;
+        self.flat_pending = true;
+        Ok(())
+    }
+
+    /// Start the flattened key of a list element: the key of the list and `[index]`
+    fn begin_flat_element(&mut self) -> Result<(), &'static str> {
+        let Some(level) = self.top_flat_level().filter(|level| level.is_list) else {
+            return Ok(());
+        };
+        let (prefix_len, index) = (level.prefix_len, level.children);
+        level.children += 1;
+
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        let mut rest = index;
+        loop {
+            start -= 1;
+            if let Some(digit) = digits.get_mut(start) {
+                // The remainder is less than 10
+                #[allow(clippy::cast_possible_truncation)]
+                let remainder = (rest % 10) as u8;
+                *digit = b'0' + remainder;
+            }
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        self.flat_key_len = prefix_len;
+        self.push_flat_key(b"[")?;
+        self.push_flat_key(digits.get(start..).unwrap_or(&[]))?;
+        self.push_flat_key(b"]")?;
+        self.flat_pending = true;
+        Ok(())
+    }
+
+    /// Flatten the current `M` or `L` value if the depth allows. Otherwise,
+    /// write the pending key, the value is written as nested JSON.
+    fn begin_flat_container(&mut self, is_list: bool) -> Result<bool, &'static str> {
+        let depth = self.flatten.unwrap_or(0);
+        if self.written_depth > 0 || self.n_flat_levels >= depth {
+            self.write_pending_field()?;
+            self.written_depth += 1;
+            return Ok(false);
+        }
+        if let Some(level) = self.flat_levels.get_mut(self.n_flat_levels) {
+            *level = FlatLevel {
+                prefix_len: self.flat_key_len,
+                is_list,
+                children: 0,
+            };
+        }
+        self.n_flat_levels += 1;
+        self.flat_pending = false;
+        Ok(true)
+    }
+
+    /// End an `M` or `L` value. Returns `true` if the value was flattened,
+    /// then an empty value is written as `{}` or `[]`.
+    fn end_flat_container(&mut self) -> Result<bool, &'static str> {
+        if self.flatten.is_none() {
+            return Ok(false);
+        }
+        if self.written_depth > 0 {
+            self.written_depth -= 1;
+            return Ok(false);
+        }
+        let Some(level) = self.top_flat_level().copied() else {
+            return Ok(false);
+        };
+        self.n_flat_levels -= 1;
+        if level.children == 0 {
+            self.flat_key_len = level.prefix_len;
+            self.write_flat_key()?;
+            let empty: &[u8] = if level.is_list { b"[]" } else { b"{}" };
+            self.try_write_any(empty, "writing empty flattened value")?;
+            self.pending_comma = true;
+        }
+        Ok(true)
+    }
+
+    fn mark_present(&mut self, field_name: &[u8]) {
+        let index = self
+            .presence_fields
+            .iter()
+            .take(MAX_PRESENCE_FIELDS)
+            .position(|name| name.as_bytes() == field_name);
+        if let Some(index) = index {
+            self.present |= 1 << index;
+        }
+    }
+
+    fn write_presence(&mut self) -> Result<(), &'static str> {
+        if self.presence_fields.is_empty() {
+            return Ok(());
+        }
+        match self.presence_output {
+            PresenceOutput::Callback(callback) => callback(self.records, self.present),
+            PresenceOutput::AppendField => {
+                self.write_field_name(PRESENCE_FIELD)?;
+                self.try_write_any(b"[", "writing presence array")?;
+                let n_fields = self.presence_fields.len().min(MAX_PRESENCE_FIELDS);
+                for index in 0..n_fields {
+                    if index > 0 {
+                        self.try_write_any(b",", "writing presence array")?;
+                    }
+                    let flag: &[u8] = if self.present & (1 << index) == 0 {
+                        b"false"
+                    } else {
+                        b"true"
+                    };
+                    self.try_write_any(flag, "writing presence array")?;
+                }
+                self.try_write_any(b"]", "writing presence array")?;
+                self.pending_comma = true;
+            }
+        }
+        Ok(())
+    }
+
     fn newline_if_pretty(&mut self) -> Result<(), &'static str> {
         if self.pretty {
             self.try_write_any(b"\n", "writing newline")
@@ -172,12 +739,44 @@ impl<'a, W: IoWrite> DdbConverter<'a, '_, W> {
 
 type DdbBaton<'a, 'workbuf, W> = &'a RefCell<DdbConverter<'a, 'workbuf, W>>;
 
+/// Select the output for the table of the current record, or end the record with `None`
+type SelectTable<W> = fn(&mut W, Option<&[u8]>) -> Result<(), ConversionError>;
+
+/// Write the collected record to the output
+type EndRecord<W> = fn(&mut W) -> Result<(), ConversionError>;
+
+/// Number of bytes written to the output, for `DdbToNormalOptions::on_record`
+type BytesWritten<W> = fn(&W) -> u64;
+
+/// Callbacks of the routing and framing writers at the record boundaries
+struct RecordHooks<W> {
+    select_table: Option<SelectTable<W>>,
+    end_record: Option<EndRecord<W>>,
+    bytes_written: Option<BytesWritten<W>>,
+}
+
+impl<W> RecordHooks<W> {
+    fn none() -> Self {
+        Self {
+            select_table: None,
+            end_record: None,
+            bytes_written: None,
+        }
+    }
+}
+
 /// Handle root object beginning - write opening brace
 fn on_root_object_begin<R: embedded_io::Read, W: IoWrite>(
-    _rjiter: &mut RJiter<R>,
+    rjiter: &mut RJiter<R>,
     baton: DdbBaton<'_, '_, W>,
 ) -> StreamOp {
     let mut conv = baton.borrow_mut();
+    conv.record_start = rjiter.current_index();
+    if conv.tolerance == Tolerance::PassThroughNormal
+        && looks_like_normal_json(rjiter, conv.item_wrapper_mode)
+    {
+        return copy_normal_record(rjiter, &mut conv);
+    }
     if let Err(e) = conv.try_write_any(b"{", "writing root object opening brace") {
         return StreamOp::Error(e);
     }
@@ -185,9 +784,121 @@ fn on_root_object_begin<R: embedded_io::Read, W: IoWrite>(
         return StreamOp::Error(e);
     }
     conv.output_depth = 1;
+    conv.records += 1;
+    conv.has_table = false;
+    conv.present = 0;
+    conv.n_flat_levels = 0;
+    conv.written_depth = 0;
+    conv.flat_pending = false;
     StreamOp::None
 }
 
+/// Maximum number of bytes to look at to detect a normal JSON record
+pub(crate) const MAX_DETECTION_LOOKAHEAD: usize = 512;
+
+/// The type descriptors of `DynamoDB`, without the tagged unknown types
+pub(crate) fn is_known_type_descriptor(key: &[u8]) -> bool {
+    matches!(
+        key,
+        b"S" | b"N" | b"B" | b"BOOL" | b"NULL" | b"M" | b"L" | b"SS" | b"NS" | b"BS"
+    )
+}
+
+fn is_type_descriptor(key: &[u8]) -> bool {
+    is_known_type_descriptor(key) || key.starts_with(TAGGED_TYPE_PREFIX)
+}
+
+/// Check the beginning of the record without consuming it: normal JSON
+/// if the value of the first attribute is not an object with a type descriptor.
+/// If the lookahead is not enough to decide, the record is `DynamoDB` JSON.
+fn looks_like_normal_json<R: embedded_io::Read>(
+    rjiter: &mut RJiter<R>,
+    item_wrapper_mode: ItemWrapperMode,
+) -> bool {
+    // `None` if the bytes are not enough
+    fn detect(bytes: &[u8], item_wrapper_mode: ItemWrapperMode) -> Option<bool> {
+        let mut jiter = rjiter::jiter::Jiter::new(bytes);
+        let Some(key) = jiter.next_object_bytes().ok()? else {
+            return Some(false);
+        };
+        let mut in_wrapper = key == b"Item" && item_wrapper_mode == ItemWrapperMode::AsWrapper;
+        loop {
+            if jiter.peek().ok()? != Peek::Object {
+                return Some(true);
+            }
+            let Some(key) = jiter.known_object().ok()? else {
+                return Some(!in_wrapper);
+            };
+            if !in_wrapper {
+                return Some(!is_type_descriptor(key.as_bytes()));
+            }
+            in_wrapper = false;
+        }
+    }
+
+    let mut lookahead_len = 64;
+    while lookahead_len <= MAX_DETECTION_LOOKAHEAD {
+        let Ok(bytes) = rjiter.lookahead_n(lookahead_len) else {
+            return false;
+        };
+        if let Some(is_normal) = detect(bytes, item_wrapper_mode) {
+            return is_normal;
+        }
+        if bytes.len() < lookahead_len {
+            return false;
+        }
+        lookahead_len *= 2;
+    }
+    false
+}
+
+/// Copy a normal JSON record as is
+fn copy_normal_record<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    conv: &mut DdbConverter<'_, '_, W>,
+) -> StreamOp {
+    let mut record_buffer = [0u8; 2048];
+    let Ok(mut record_context) = U8Pool::new(&mut record_buffer, 64) else {
+        return StreamOp::Error("Failed to create context pool for normal record");
+    };
+    if let Err(e) = scan_json::idtransform(rjiter, conv.writer, &mut record_context) {
+        conv.last_error = Some(ConversionError::ScanError(e));
+        return StreamOp::Error("Failed to copy normal record");
+    }
+    if let Err(e) = conv.try_write_any(b"\n", "writing final newline") {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.finish_record() {
+        return StreamOp::Error(e);
+    }
+    conv.records += 1;
+    StreamOp::ValueIsConsumed
+}
+
+/// Handle `TableName` at the top level of a routed conversion - select the output of the record
+fn on_table_name<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    baton: DdbBaton<'_, '_, W>,
+) -> StreamOp {
+    let mut conv = baton.borrow_mut();
+    let Some(select_table) = conv.select_table else {
+        return StreamOp::Error("Internal error: select_table not set (impossible)");
+    };
+    let table_name = match rjiter.next_str() {
+        Ok(table_name) => table_name,
+        Err(e) => {
+            conv.store_rjiter_error(e, "reading TableName");
+            return StreamOp::Error("Failed to read TableName");
+        }
+    };
+    if let Err(e) = select_table(conv.writer, Some(table_name.as_bytes())) {
+        conv.last_error = Some(e);
+        return StreamOp::Error("Failed to select the table output");
+    }
+    conv.has_table = true;
+    StreamOp::ValueIsConsumed
+}
+
 /// Handle a field key - write the field name and prepare for type descriptor
 fn on_field_key<R: embedded_io::Read, W: IoWrite>(
     _rjiter: &mut RJiter<R>,
@@ -197,47 +908,43 @@ fn on_field_key<R: embedded_io::Read, W: IoWrite>(
     let Some(field_name) = conv.current_field else {
         return StreamOp::Error("Internal error: current_field not set (impossible)");
     };
-
-    if let Err(e) = conv.write_comma_if_pending() {
+    if let Err(e) = conv.check_name_utf8(field_name) {
         return StreamOp::Error(e);
     }
-    if let Err(e) = conv.indent_if_pretty() {
-        return StreamOp::Error(e);
-    }
-    if let Err(e) = conv.try_write_any(b"\"", "writing field name opening quote") {
-        return StreamOp::Error(e);
+    if conv.output_depth == 1 && conv.n_flat_levels == 0 {
+        conv.mark_present(field_name);
     }
-    if let Err(e) = conv.try_write_any(field_name, "writing field name") {
-        return StreamOp::Error(e);
-    }
-    if let Err(e) = conv.try_write_any(b"\":", "writing field name closing quote and colon") {
+
+    if conv.is_flattening() {
+        // Write the flattened key together with the value, the value can be flattened further
+        if let Err(e) = conv.begin_flat_field(field_name) {
+            return StreamOp::Error(e);
+        }
+    } else if conv.on_scalar.is_some() {
+        // Write the name together with the value, the hook can drop the attribute
+        conv.pending_field = Some(field_name);
+    } else if let Err(e) = conv.write_field_name(field_name) {
         return StreamOp::Error(e);
     }
-    conv.pending_comma = false;
     conv.phase = Phase::ExpectingTypeKey;
 
     StreamOp::None
 }
 
 /// Generic helper for writing string-based values (S/B/N types and set elements)
-/// Handles peeking, comma writing, quotes, and error reporting
+/// Handles peeking, comma writing, quotes, and error reporting.
+/// A number is normalized if `NumberOutput::NormalizeIntegers`.
 fn write_string_value<R: embedded_io::Read, W: IoWrite>(
     rjiter: &mut RJiter<R>,
     conv: &mut DdbConverter<'_, '_, W>,
     with_quotes: bool,
     write_comma_if_pending: bool,
+    is_number: bool,
     peek_context: &'static str,
     write_context: &'static str,
 ) -> StreamOp {
-    let peek = match rjiter.peek() {
-        Ok(p) => p,
-        Err(e) => {
-            conv.store_rjiter_error(e, peek_context);
-            return StreamOp::Error("Failed to peek string value");
-        }
-    };
-    if peek != Peek::String {
-        return StreamOp::Error("Expected string value");
+    if let Err(op) = expect_string_value(rjiter, conv, peek_context) {
+        return op;
     }
 
     if write_comma_if_pending {
@@ -251,7 +958,20 @@ fn write_string_value<R: embedded_io::Read, W: IoWrite>(
             return StreamOp::Error(e);
         }
     }
-    if let Err(e) = rjiter.write_long_bytes(conv.writer) {
+    if is_number && conv.number_output == NumberOutput::NormalizeIntegers {
+        let mut normalizer = NumberNormalizer::new(&mut *conv.writer);
+        if let Err(e) = rjiter.write_long_bytes(&mut normalizer) {
+            conv.store_rjiter_error(e, write_context);
+            return StreamOp::Error("Failed to write value");
+        }
+        if let Err(e) = normalizer.finish() {
+            conv.last_error = Some(ConversionError::IOError {
+                kind: e.kind(),
+                context: write_context,
+            });
+            return StreamOp::Error("Failed to write value");
+        }
+    } else if let Err(e) = rjiter.write_long_bytes(conv.writer) {
         conv.store_rjiter_error(e, write_context);
         return StreamOp::Error("Failed to write value");
     }
@@ -274,6 +994,20 @@ fn write_string_value<R: embedded_io::Read, W: IoWrite>(
     StreamOp::ValueIsConsumed
 }
 
+/// Peek the value and check that it is a string, see `scan_json::expect_type`.
+/// A read error is stored with `context`.
+fn expect_string_value<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    conv: &mut DdbConverter<'_, '_, W>,
+    context: &'static str,
+) -> Result<(), StreamOp> {
+    if let Err(e) = rjiter.peek() {
+        conv.store_rjiter_error(e, context);
+        return Err(StreamOp::Error("Failed to peek string value"));
+    }
+    expect_type(rjiter, ValueType::String).map(|_| ())
+}
+
 /// Helper for boolean-based types (BOOL/NULL): peek bool, consume with `known_bool`, write output
 fn handle_bool_based_type<R: embedded_io::Read, W: IoWrite>(
     rjiter: &mut RJiter<R>,
@@ -318,19 +1052,79 @@ fn handle_bool_based_type<R: embedded_io::Read, W: IoWrite>(
 fn on_type_key<R: embedded_io::Read, W: IoWrite>(
     rjiter: &mut RJiter<R>,
     baton: DdbBaton<'_, '_, W>,
+) -> StreamOp {
+    let is_string = matches!(baton.borrow().current_field, Some(b"S" | b"B"));
+    if is_string {
+        return measure_string(rjiter, baton, write_type_value);
+    }
+    write_type_value(rjiter, baton)
+}
+
+/// Call `write_value` for a string value and note the length of the string
+fn measure_string<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    baton: DdbBaton<'_, '_, W>,
+    write_value: fn(&mut RJiter<R>, DdbBaton<'_, '_, W>) -> StreamOp,
+) -> StreamOp {
+    // A peek error is reported by `write_value`
+    let start = match rjiter.peek() {
+        Ok(Peek::String) => Some(rjiter.current_index()),
+        _ => None,
+    };
+    let result = write_value(rjiter, baton);
+    if let (Some(start), StreamOp::ValueIsConsumed) = (start, &result) {
+        // Without the quotes
+        let len = (rjiter.current_index() - start).saturating_sub(2) as u64;
+        let mut conv = baton.borrow_mut();
+        conv.max_string_len = conv.max_string_len.max(len);
+    }
+    result
+}
+
+fn write_type_value<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    baton: DdbBaton<'_, '_, W>,
 ) -> StreamOp {
     let mut conv = baton.borrow_mut();
     let Some(type_key) = conv.current_field else {
         return StreamOp::Error("current_field should be set for type key");
     };
+    conv.attributes.count(type_key);
+
+    if conv.flatten.is_some() {
+        if let Some(result) = flatten_type_key(&mut conv, type_key) {
+            return result;
+        }
+    }
+
+    if let Some(on_scalar) = conv.on_scalar {
+        if is_scalar_type(type_key) {
+            let result = write_hooked_scalar(rjiter, &mut conv, type_key, on_scalar);
+            conv.coerce_to = None;
+            conv.current_type = None;
+            conv.phase = Phase::ExpectingValue;
+            return result;
+        }
+        if let Err(e) = conv.write_pending_field() {
+            return StreamOp::Error(e);
+        }
+    }
 
     match type_key {
+        b"S" if conv.coerce_to.is_some() => {
+            let result = write_coerced_value(rjiter, &mut conv);
+            conv.coerce_to = None;
+            conv.current_type = None;
+            conv.phase = Phase::ExpectingValue;
+            result
+        }
         b"S" | b"B" => {
             let result = write_string_value(
                 rjiter,
                 &mut conv,
                 true,
                 true,
+                false,
                 "S/B (string) type",
                 "S/B (string) type",
             );
@@ -344,6 +1138,7 @@ fn on_type_key<R: embedded_io::Read, W: IoWrite>(
                 &mut conv,
                 false,
                 true,
+                true,
                 "N (number) type",
                 "N (number) type",
             );
@@ -358,7 +1153,7 @@ fn on_type_key<R: embedded_io::Read, W: IoWrite>(
                 |peek| match peek {
                     Peek::True => Ok(b"true"),
                     Peek::False => Ok(b"false"),
-                    _ => Err("Expected boolean value for BOOL type"),
+                    _ => Err(ValueType::Bool.error_message()),
                 },
                 "BOOL type",
             );
@@ -384,6 +1179,7 @@ fn on_type_key<R: embedded_io::Read, W: IoWrite>(
                 return StreamOp::Error(e);
             }
             conv.pending_comma = false;
+            conv.set_element_count = 0;
             conv.current_type = Some(TypeDesc::SS);
             conv.phase = Phase::ExpectingValue; // Stay in ExpectingValue, SS elements are atoms
             StreamOp::None
@@ -394,6 +1190,7 @@ fn on_type_key<R: embedded_io::Read, W: IoWrite>(
                 return StreamOp::Error(e);
             }
             conv.pending_comma = false;
+            conv.set_element_count = 0;
             conv.current_type = Some(TypeDesc::NS);
             conv.phase = Phase::ExpectingValue; // Stay in ExpectingValue, NS elements are atoms
             StreamOp::None
@@ -429,6 +1226,12 @@ fn on_type_key<R: embedded_io::Read, W: IoWrite>(
             StreamOp::None
         }
         _ => {
+            if conv.unknown_type_policy == UnknownTypePolicy::PassthroughTagged {
+                let result = write_tagged_value(rjiter, &mut conv, type_key);
+                conv.current_type = None;
+                conv.phase = Phase::ExpectingValue;
+                return result;
+            }
             conv.store_parse_error(
                 "Invalid DynamoDB JSON format: unknown type descriptor",
                 Some(type_key),
@@ -438,18 +1241,401 @@ fn on_type_key<R: embedded_io::Read, W: IoWrite>(
     }
 }
 
+/// Flatten an `M` or `L` value, or write the flattened key of another value.
+/// Returns the result if the type key is handled.
+fn flatten_type_key<W: IoWrite>(
+    conv: &mut DdbConverter<'_, '_, W>,
+    type_key: &[u8],
+) -> Option<StreamOp> {
+    if conv.is_flattening() && !conv.flat_pending {
+        if let Err(e) = conv.begin_flat_element() {
+            return Some(StreamOp::Error(e));
+        }
+    }
+    let is_list = match type_key {
+        b"M" => false,
+        b"L" => true,
+        _ => {
+            // The hook writes the key itself, it can drop the attribute
+            if conv.on_scalar.is_some() && is_scalar_type(type_key) {
+                return None;
+            }
+            return conv.write_pending_field().err().map(StreamOp::Error);
+        }
+    };
+    match conv.begin_flat_container(is_list) {
+        Err(e) => Some(StreamOp::Error(e)),
+        Ok(false) => None,
+        Ok(true) => {
+            if is_list {
+                conv.current_type = Some(TypeDesc::L);
+                conv.phase = Phase::ExpectingTypeKey;
+            } else {
+                conv.current_type = Some(TypeDesc::M);
+                conv.phase = Phase::ExpectingField;
+            }
+            Some(StreamOp::None)
+        }
+    }
+}
+
+/// Write an `S` value as a number or a boolean if it looks like one, otherwise as a string.
+/// The value should fit into the `RJiter` buffer, see `ConversionError::AttributeTooLarge`.
+fn write_coerced_value<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    conv: &mut DdbConverter<'_, '_, W>,
+) -> StreamOp {
+    if let Err(op) = expect_string_value(rjiter, conv, "S (string) type with coercion") {
+        return op;
+    }
+    if let Err(e) = conv.write_comma_if_pending() {
+        return StreamOp::Error(e);
+    }
+
+    let bytes = match rjiter.known_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            conv.store_rjiter_error(e, "S (string) type with coercion");
+            return StreamOp::Error("Failed to read value");
+        }
+    };
+    let mut decoded = [0u8; MAX_ESCAPED_COERCION];
+    let result = if let Some(value) = coerced_value(conv.coerce_to, bytes, &mut decoded) {
+        conv.try_write_any(value, "writing coerced value")
+    } else {
+        conv.try_write_any(b"\"", "writing opening quote")
+            .and_then(|()| conv.try_write_any(bytes, "writing string value"))
+            .and_then(|()| conv.try_write_any(b"\"", "writing closing quote"))
+    };
+    if let Err(e) = result {
+        return StreamOp::Error(e);
+    }
+
+    conv.pending_comma = true;
+    StreamOp::ValueIsConsumed
+}
+
+/// Maximum decoded length of an `S` value with escapes to check for a coercion,
+/// a longer value stays a string
+const MAX_ESCAPED_COERCION: usize = 64;
+
+/// The bytes to write for an `S` value with a coercion, `None` if the value stays a string.
+/// `raw` is the value as in the input, a value with escapes is decoded to `buf` first.
+fn coerced_value<'v>(
+    coerce_to: Option<CoerceTo>,
+    raw: &'v [u8],
+    buf: &'v mut [u8; MAX_ESCAPED_COERCION],
+) -> Option<&'v [u8]> {
+    let value = if raw.contains(&b'\\') {
+        decode_ascii_escapes(raw, buf)?
+    } else {
+        raw
+    };
+    let is_coerced = match coerce_to? {
+        CoerceTo::Number => is_json_number(value),
+        CoerceTo::Bool => value == b"true" || value == b"false",
+    };
+    is_coerced.then_some(value)
+}
+
+/// Decode the escapes of a JSON string to `buf`. `None` if an escape is not
+/// an ASCII character (such a value is never coerced) or `buf` is too small.
+fn decode_ascii_escapes<'b>(raw: &[u8], buf: &'b mut [u8]) -> Option<&'b [u8]> {
+    let mut len = 0;
+    let mut rest = raw;
+    while let Some((&byte, tail)) = rest.split_first() {
+        let (byte, tail) = if byte == b'\\' {
+            match tail.split_first()? {
+                (b'u', hex) => {
+                    let digits = core::str::from_utf8(hex.get(..4)?).ok()?;
+                    let code = u8::from_str_radix(digits, 16).ok().filter(u8::is_ascii)?;
+                    (code, hex.get(4..)?)
+                }
+                (&escaped @ (b'"' | b'\\' | b'/'), tail) => (escaped, tail),
+                _ => return None,
+            }
+        } else {
+            (byte, tail)
+        };
+        *buf.get_mut(len)? = byte;
+        len += 1;
+        rest = tail;
+    }
+    buf.get(..len)
+}
+
+fn is_scalar_type(type_key: &[u8]) -> bool {
+    matches!(type_key, b"S" | b"N" | b"B" | b"BOOL" | b"NULL")
+}
+
+/// Read a scalar value, pass it to the hook and write the value, its replacement or nothing
+fn write_hooked_scalar<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    conv: &mut DdbConverter<'_, '_, W>,
+    type_key: &[u8],
+    on_scalar: ScalarHook,
+) -> StreamOp {
+    let peek = match rjiter.peek() {
+        Ok(p) => p,
+        Err(e) => {
+            conv.store_rjiter_error(e, "peeking scalar value for the hook");
+            return StreamOp::Error("Failed to peek value");
+        }
+    };
+    let value: &[u8] = match (type_key, peek) {
+        (b"S" | b"N" | b"B", Peek::String) => match rjiter.known_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                conv.store_rjiter_error(e, "reading scalar value for the hook");
+                return StreamOp::Error("Failed to read value");
+            }
+        },
+        (b"S" | b"N" | b"B", _) => return StreamOp::Error(ValueType::String.error_message()),
+        (b"BOOL", Peek::True | Peek::False) | (b"NULL", Peek::True) => {
+            if let Err(e) = rjiter.known_bool(peek) {
+                conv.store_rjiter_error(e, "reading scalar value for the hook");
+                return StreamOp::Error("Failed to consume boolean value");
+            }
+            match (type_key, peek) {
+                (b"NULL", _) => b"null",
+                (_, Peek::True) => b"true",
+                (_, _) => b"false",
+            }
+        }
+        (b"BOOL", _) => return StreamOp::Error(ValueType::Bool.error_message()),
+        (_, _) => return StreamOp::Error("Expected true for NULL type"),
+    };
+
+    let mut replacement = [0u8; MAX_SCALAR_REPLACEMENT];
+    let mut decoded = [0u8; MAX_ESCAPED_COERCION];
+    let path = conv.hook_path.get(..conv.hook_path_len).unwrap_or(&[]);
+    let emit = on_scalar(path, type_key, value, &mut replacement);
+    let (output, with_quotes) = match emit {
+        Emit::Drop => {
+            conv.pending_field = None;
+            conv.flat_pending = false;
+            return StreamOp::ValueIsConsumed;
+        }
+        Emit::Replace(len) => match replacement.get(..len) {
+            Some(bytes) => (bytes, false),
+            None => return StreamOp::Error("Scalar hook replacement is too long"),
+        },
+        Emit::Keep => match type_key {
+            b"S" => match coerced_value(conv.coerce_to, value, &mut decoded) {
+                Some(coerced) => (coerced, false),
+                None => (value, true),
+            },
+            b"B" => (value, true),
+            _ => (value, false),
+        },
+    };
+
+    let result = conv
+        .write_pending_field()
+        .and_then(|()| conv.write_comma_if_pending());
+    let result = if with_quotes {
+        result
+            .and_then(|()| conv.try_write_any(b"\"", "writing opening quote"))
+            .and_then(|()| conv.try_write_any(output, "writing scalar value"))
+            .and_then(|()| conv.try_write_any(b"\"", "writing closing quote"))
+    } else if type_key == b"N" && emit == Emit::Keep {
+        result.and_then(|()| conv.try_write_number(output))
+    } else {
+        result.and_then(|()| conv.try_write_any(output, "writing scalar value"))
+    };
+    if let Err(e) = result {
+        return StreamOp::Error(e);
+    }
+
+    conv.pending_comma = true;
+    StreamOp::ValueIsConsumed
+}
+
+/// Maximum length of an attribute path for `ScalarHook`
+const MAX_HOOK_PATH: usize = 256;
+
+/// `DynamoDB` supports up to 32 levels of nesting in the original data
+const MAX_NESTING: usize = 32;
+
+/// In the context, each nesting level has two entries: the field name
+/// (`#array` in a list) and the type key `M` or `L`
+const ENTRIES_PER_LEVEL: usize = 2;
+
+/// The slots of the context pool: `#top`, the optional `Item` wrapper, the levels,
+/// and the field name, the type key and the value at the leaf
+const CONTEXT_SLOTS: usize = ENTRIES_PER_LEVEL * MAX_NESTING + 4;
+
+/// Write the path `a.b[].c` of the scalar to `buf` and return the length, or `None`
+/// if the path doesn't fit. `context` is positioned after the type key:
+/// `c M #array L b M a #top`, with field names and type keys alternating.
+fn attribute_path(
+    mut context: ContextIter,
+    item_wrapper_mode: ItemWrapperMode,
+    buf: &mut [u8],
+) -> Option<usize> {
+    // Segments from the innermost, a list element is `[]`
+    let mut segments: [&[u8]; CONTEXT_SLOTS / ENTRIES_PER_LEVEL] =
+        [&[]; CONTEXT_SLOTS / ENTRIES_PER_LEVEL];
+    let mut n_segments = 0;
+    loop {
+        let name = context.next()?;
+        let is_list_element = name == b"#array";
+        *segments.get_mut(n_segments)? = if is_list_element { b"[]" } else { name };
+        n_segments += 1;
+        match (is_list_element, context.next()?) {
+            (true, b"L") | (false, b"M") => {}
+            (false, b"#top") => break,
+            (false, b"Item")
+                if item_wrapper_mode == ItemWrapperMode::AsWrapper
+                    && context.next() == Some(b"#top".as_slice()) =>
+            {
+                break
+            }
+            _ => return None,
+        }
+    }
+
+    let mut len = 0;
+    for (i, segment) in segments.get(..n_segments)?.iter().rev().enumerate() {
+        if i > 0 && *segment != b"[]" {
+            *buf.get_mut(len)? = b'.';
+            len += 1;
+        }
+        buf.get_mut(len..len + segment.len())?
+            .copy_from_slice(segment);
+        len += segment.len();
+    }
+    Some(len)
+}
+
+/// Check if the bytes are exactly one JSON number
+pub(crate) fn is_json_number(bytes: &[u8]) -> bool {
+    if !bytes
+        .first()
+        .is_some_and(|b| *b == b'-' || b.is_ascii_digit())
+    {
+        return false;
+    }
+    let mut jiter = rjiter::jiter::Jiter::new(bytes);
+    jiter.next_number().is_ok() && jiter.finish().is_ok()
+}
+
+/// Find the coercion for the `S` value, `context` is positioned after the type key
+fn find_coercion(
+    type_coercions: &[(&str, CoerceTo)],
+    context: &ContextIter,
+    item_wrapper_mode: ItemWrapperMode,
+) -> Option<CoerceTo> {
+    type_coercions
+        .iter()
+        .find(|(path, _)| path_matches(path.as_bytes(), context.clone(), item_wrapper_mode))
+        .map(|(_, coerce_to)| *coerce_to)
+}
+
+/// Check if the field path `a.b.c` matches the `DynamoDB` context `#top [Item] a M b M c`,
+/// `context` is iterated from the innermost field name to the top
+fn path_matches(path: &[u8], mut context: ContextIter, item_wrapper_mode: ItemWrapperMode) -> bool {
+    for (i, segment) in path.rsplit(|b| *b == b'.').enumerate() {
+        if i > 0 && context.next() != Some(b"M".as_slice()) {
+            return false;
+        }
+        if context.next() != Some(segment) {
+            return false;
+        }
+    }
+    match context.next() {
+        Some(b"#top") => true,
+        Some(b"Item") => {
+            item_wrapper_mode == ItemWrapperMode::AsWrapper
+                && context.next() == Some(b"#top".as_slice())
+        }
+        _ => false,
+    }
+}
+
+/// Copy the value of an unknown type descriptor as `{"$ddb:X": value}`
+fn write_tagged_value<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    conv: &mut DdbConverter<'_, '_, W>,
+    type_key: &[u8],
+) -> StreamOp {
+    if let Err(e) = conv.write_comma_if_pending() {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(b"{\"", "writing tagged type opening") {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(TAGGED_TYPE_PREFIX, "writing tagged type prefix") {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(type_key, "writing tagged type descriptor") {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(b"\":", "writing tagged type colon") {
+        return StreamOp::Error(e);
+    }
+
+    // The value can be of any shape, copy it as is
+    let mut value_buffer = [0u8; 512];
+    let Ok(mut value_context) = U8Pool::new(&mut value_buffer, 32) else {
+        return StreamOp::Error("Failed to create context pool for tagged value");
+    };
+    if let Err(e) = scan_json::idtransform(rjiter, conv.writer, &mut value_context) {
+        conv.last_error = Some(ConversionError::ScanError(e));
+        return StreamOp::Error("Failed to copy tagged value");
+    }
+    if conv.unbuffered {
+        if let Err(e) = conv.writer.flush() {
+            conv.last_error = Some(ConversionError::IOError {
+                kind: e.kind(),
+                context: "flushing after copying tagged value",
+            });
+            return StreamOp::Error("Failed to flush writer");
+        }
+    }
+    if let Err(e) = conv.try_write_any(b"}", "writing tagged type closing brace") {
+        return StreamOp::Error(e);
+    }
+
+    conv.pending_comma = true;
+    StreamOp::ValueIsConsumed
+}
+
 // Type descriptor value handlers for set element atoms (SS, NS)
 
+/// Count the set element and check the limit
+fn count_set_element<W: IoWrite>(conv: &mut DdbConverter<'_, '_, W>) -> Result<(), &'static str> {
+    conv.set_element_count += 1;
+    if let Some(max_elements) = conv.max_set_elements {
+        if conv.set_element_count > max_elements {
+            conv.last_error = Some(ConversionError::SetTooLarge { max_elements });
+            return Err("Too many set elements");
+        }
+    }
+    Ok(())
+}
+
 fn on_set_string_element<R: embedded_io::Read, W: IoWrite>(
     rjiter: &mut RJiter<R>,
     baton: DdbBaton<'_, '_, W>,
+) -> StreamOp {
+    measure_string(rjiter, baton, write_set_string_element)
+}
+
+fn write_set_string_element<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    baton: DdbBaton<'_, '_, W>,
 ) -> StreamOp {
     let mut conv = baton.borrow_mut();
+    if let Err(e) = count_set_element(&mut conv) {
+        return StreamOp::Error(e);
+    }
     write_string_value(
         rjiter,
         &mut conv,
-        true, // with_quotes
+        true,  // with_quotes
         true, // write_comma_if_pending: always for set elements (pending_comma handles first element)
+        false, // is_number
         "peeking SS/BS (string set) element",
         "writing SS/BS (string set) element",
     )
@@ -460,11 +1646,15 @@ fn on_set_number_element<R: embedded_io::Read, W: IoWrite>(
     baton: DdbBaton<'_, '_, W>,
 ) -> StreamOp {
     let mut conv = baton.borrow_mut();
+    if let Err(e) = count_set_element(&mut conv) {
+        return StreamOp::Error(e);
+    }
     write_string_value(
         rjiter,
         &mut conv,
         false, // with_quotes
         true, // write_comma_if_pending: always for set elements (pending_comma handles first element)
+        true, // is_number
         "peeking NS (number set) element",
         "writing NS (number set) element",
     )
@@ -547,11 +1737,23 @@ fn find_action_key<'a, 'workbuf, R: embedded_io::Read, W: IoWrite>(
     // Begin-transitions (based on current phase before processing the key)
     match phase {
         Phase::ExpectingField => {
-            // Check for Item at top with AsWrapper - early return without side effects
+            // In a routed conversion, the table is selected before the attributes
+            if baton.borrow().select_table.is_some() && context.clone().next() == Some(b"#top") {
+                if key == b"TableName" {
+                    return Some(on_table_name);
+                }
+                let mut conv = baton.borrow_mut();
+                if !conv.has_table {
+                    conv.store_parse_error("Record has no TableName before the attributes", None);
+                    return Some(on_error);
+                }
+            }
+            // Check for Item at top with AsWrapper - early return, only count the wrapped record
             if key == b"Item" {
                 let mode = baton.borrow().item_wrapper_mode;
                 if mode == ItemWrapperMode::AsWrapper {
                     if let Some(b"#top") = context.next() {
+                        baton.borrow_mut().wrapped_records += 1;
                         return None;
                     }
                 }
@@ -573,6 +1775,19 @@ fn find_action_key<'a, 'workbuf, R: embedded_io::Read, W: IoWrite>(
             let key_slice: &'workbuf [u8] =
                 unsafe { core::mem::transmute::<&[u8], &'workbuf [u8]>(key) };
             conv.current_field = Some(key_slice);
+            if key == b"S" && !conv.type_coercions.is_empty() {
+                conv.coerce_to =
+                    find_coercion(conv.type_coercions, &context, conv.item_wrapper_mode);
+            }
+            if conv.on_scalar.is_some() && is_scalar_type(key) {
+                let item_wrapper_mode = conv.item_wrapper_mode;
+                let Some(len) = attribute_path(context, item_wrapper_mode, &mut conv.hook_path)
+                else {
+                    conv.store_parse_error("Attribute path is too long for the scalar hook", None);
+                    return Some(on_error);
+                };
+                conv.hook_path_len = len;
+            }
 
             // Transition: ExpectingTypeKey -> if in "M", then ExpectingField; otherwise, ExpectingValue
             // Note: The actual transition happens in on_type_key based on the type
@@ -713,8 +1928,10 @@ fn on_list_end<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<(), &'static st
         return Err("Invalid phase when ending L array (expected ExpectingTypeKey)");
     }
 
-    conv.try_write_any(b"]", "writing L closing bracket")?;
-    conv.pending_comma = true;
+    if !conv.end_flat_container()? {
+        conv.try_write_any(b"]", "writing L closing bracket")?;
+        conv.pending_comma = true;
+    }
 
     // Transition: ExpectingTypeKey -> ExpectingValue (at end of array)
     conv.phase = Phase::ExpectingValue;
@@ -722,9 +1939,15 @@ fn on_list_end<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<(), &'static st
     Ok(())
 }
 
-#[allow(clippy::unnecessary_wraps)]
 fn on_set_end<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<(), &'static str> {
     let mut conv = baton.borrow_mut();
+    if conv.reject_empty_sets && conv.set_element_count == 0 {
+        conv.last_error = Some(ConversionError::EmptySet);
+        return Err("Empty set");
+    }
+    let set_len = conv.set_element_count as u64;
+    conv.set_elements += set_len;
+    conv.max_set_len = conv.max_set_len.max(set_len);
     conv.try_write_any(b"]", "writing SS/NS/BS closing bracket")?;
     conv.pending_comma = true;
 
@@ -737,11 +1960,13 @@ fn on_set_end<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<(), &'static str
 #[allow(clippy::unnecessary_wraps)]
 fn on_map_end<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<(), &'static str> {
     let mut conv = baton.borrow_mut();
-    conv.newline_if_pretty()?;
-    conv.output_depth -= 1;
-    conv.indent_if_pretty()?;
-    conv.try_write_any(b"}", "writing M closing brace")?;
-    conv.pending_comma = true;
+    if !conv.end_flat_container()? {
+        conv.newline_if_pretty()?;
+        conv.output_depth -= 1;
+        conv.indent_if_pretty()?;
+        conv.try_write_any(b"}", "writing M closing brace")?;
+        conv.pending_comma = true;
+    }
 
     // M container value is consumed
     conv.current_type = None;
@@ -782,9 +2007,11 @@ fn on_type_key_end_in_array<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<()
 #[allow(clippy::unnecessary_wraps)]
 fn on_root_object_end<W: IoWrite>(baton: DdbBaton<'_, '_, W>) -> Result<(), &'static str> {
     let mut conv = baton.borrow_mut();
+    conv.write_presence()?;
     conv.newline_if_pretty()?;
     conv.try_write_any(b"}", "writing root object closing brace")?;
     conv.try_write_any(b"\n", "writing final newline")?;
+    conv.finish_record()?;
 
     // Reset state for next JSONL record
     conv.pending_comma = false;
@@ -849,6 +2076,13 @@ fn find_end_action_key<'a, 'workbuf, W: IoWrite>(
             }
         }
         Phase::ExpectingField => {
+            // TableName of a routed conversion is not an attribute
+            if key == b"TableName"
+                && baton.borrow().select_table.is_some()
+                && context.clone().next() == Some(b"#top")
+            {
+                return None;
+            }
             // Check for Item at top with AsWrapper - early return without side effects
             if key == b"Item" {
                 let mode = baton.borrow().item_wrapper_mode;
@@ -919,6 +2153,8 @@ fn find_end_action<'a, 'workbuf, W: IoWrite>(
 /// Convert `DynamoDB` JSON to normal JSON in a streaming, allocation-free manner.
 /// Supports JSONL format (newline-delimited JSON) - processes multiple JSON objects.
 ///
+/// A shortcut for `convert_ddb_to_normal_with_options`.
+///
 /// # Arguments
 /// * `reader` - Input stream implementing `embedded_io::Read`
 /// * `writer` - Output stream implementing `embedded_io::Write`
@@ -946,18 +2182,140 @@ pub fn convert_ddb_to_normal<R: IoRead, W: IoWrite>(
     unbuffered: bool,
     item_wrapper_mode: ItemWrapperMode,
 ) -> Result<(), (ConversionError, usize)> {
+    let mut options = DdbToNormalOptions::new();
+    options.pretty = pretty;
+    options.unbuffered = unbuffered;
+    options.item_wrapper_mode = item_wrapper_mode;
+    convert_ddb_to_normal_with_options(reader, writer, rjiter_buffer, context_buffer, &options)
+}
+
+/// Convert `DynamoDB` JSON to normal JSON in a streaming, allocation-free manner.
+/// Supports JSONL format (newline-delimited JSON) - processes multiple JSON objects.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `writer` - Output stream implementing `embedded_io::Write`
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `DdbToNormalOptions`
+///
+/// # Errors
+/// Returns `ConversionError` if:
+/// - Input JSON is malformed or invalid
+/// - Input contains invalid `DynamoDB` type descriptors
+/// - I/O errors occur during reading or writing
+/// - Buffer sizes are insufficient for the input data
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_ddb_to_normal_with_options<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    convert_ddb_to_normal_with_metrics(
+        reader,
+        writer,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut Metrics::new(),
+    )
+}
+
+/// Convert `DynamoDB` JSON to normal JSON and update the conversion metrics.
+///
+/// The same as `convert_ddb_to_normal_with_options`, but also adds the number of records,
+/// attributes, errors and the input/output bytes to `metrics`.
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`. The error is also counted in `metrics`.
+pub fn convert_ddb_to_normal_with_metrics<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+    metrics: &mut Metrics,
+) -> Result<(), (ConversionError, usize)> {
+    let mut counting_reader = CountingReader { reader, count: 0 };
+    let mut counting_writer = CountingWriter { writer, count: 0 };
+    let result = convert(
+        &mut counting_reader,
+        &mut counting_writer,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        metrics,
+        &RecordHooks {
+            bytes_written: Some(|writer: &CountingWriter<W>| writer.count),
+            ..RecordHooks::none()
+        },
+    );
+    metrics.bytes_in += counting_reader.count;
+    metrics.bytes_out += counting_writer.count;
+    if result.is_err() {
+        metrics.errors += 1;
+    }
+    result.map(|_| ())
+}
+
+/// Convert a prefix of the input, limited by `DdbToNormalOptions::limit`,
+/// and report where the conversion stopped.
+///
+/// The same as `convert_ddb_to_normal_with_options`, but returns the number of
+/// the converted records and the consumed input bytes. For example, a sampling
+/// job converts the first records of a large export, and a later job resumes
+/// from `PrefixReport::consumed`.
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`
+pub fn convert_ddb_to_normal_prefix<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<PrefixReport, (ConversionError, usize)> {
+    let mut counting_writer = CountingWriter { writer, count: 0 };
+    convert(
+        reader,
+        &mut counting_writer,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut Metrics::new(),
+        &RecordHooks {
+            bytes_written: Some(|writer: &CountingWriter<W>| writer.count),
+            ..RecordHooks::none()
+        },
+    )
+}
+
+fn convert<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+    metrics: &mut Metrics,
+    hooks: &RecordHooks<W>,
+) -> Result<PrefixReport, (ConversionError, usize)> {
     let mut rjiter = RJiter::new(reader, rjiter_buffer);
 
-    let converter = DdbConverter::new(writer, pretty, unbuffered, item_wrapper_mode);
+    let mut converter = DdbConverter::new(writer, options);
+    converter.select_table = hooks.select_table;
+    converter.end_record = hooks.end_record;
     let baton = RefCell::new(converter);
+    let on_record = options.on_record.zip(hooks.bytes_written);
 
-    // DynamoDB supports up to 32 levels of nesting in the original data.
     // In DynamoDB JSON format, each nested object/array adds extra levels:
     // - Each Map: {"M": {...}} adds 1 level
     // - Each List: {"L": [...]} adds 1 level
     // - Optional "Item" wrapper adds 1 level
-    // For 32 levels: 1 (Item/#top) + 32 (level_N) + 32 (M) + 1 (value) + 1 (S) + 1 (leaf value) = 68 slots
-    let mut context = U8Pool::new(context_buffer, 68).map_err(|_| {
+    let mut context = U8Pool::new(context_buffer, CONTEXT_SLOTS).map_err(|_| {
         (
             ConversionError::ScanError(scan_json::Error::InternalError {
                 position: 0,
@@ -967,41 +2325,494 @@ pub fn convert_ddb_to_normal<R: IoRead, W: IoWrite>(
         )
     })?;
 
-    if let Err(e) = scan(
-        find_action,
-        find_end_action,
-        &mut rjiter,
-        &baton,
-        &mut context,
-        &Options::new(),
-    ) {
+    // After each record, call the record callback and check the limit
+    let limit = options.limit;
+    let is_limit_reached = Cell::new(limit.is_reached(0, rjiter.current_index()));
+    let after_record = |report: &ScanReport| {
+        let conv = baton.borrow();
+        if let Some((on_record, bytes_written)) = on_record {
+            on_record(
+                conv.record_start,
+                report.position,
+                bytes_written(conv.writer),
+            );
+        }
+        is_limit_reached.set(limit.is_reached(conv.records, report.position));
+        is_limit_reached.get()
+    };
+    let mut scan_options = Options::new();
+    if limit != Limit::NONE || on_record.is_some() {
+        scan_options.stop_when = Some(&after_record);
+    }
+    let mut error_attribute = ([0u8; 64], 0);
+    let result = if is_limit_reached.get() {
+        Ok(())
+    } else {
+        scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            &baton,
+            &mut context,
+            &scan_options,
+        )
+    };
+    if result.is_err() {
+        error_attribute.1 = context_attribute_path(
+            ContextIter::new(&context),
+            options.item_wrapper_mode,
+            &mut error_attribute.0,
+        );
+    }
+    let report = PrefixReport {
+        records: baton.borrow().records,
+        consumed: rjiter.current_index(),
+        is_limit_reached: is_limit_reached.get(),
+    };
+    {
+        let conv = baton.borrow();
+        metrics.records += conv.records;
+        metrics.wrapped_records += conv.wrapped_records;
+        metrics.attributes.add(&conv.attributes);
+        metrics.set_elements += conv.set_elements;
+        metrics.max_set_len = metrics.max_set_len.max(conv.max_set_len);
+        metrics.max_string_len = metrics.max_string_len.max(conv.max_string_len);
+    }
+    if let Err(e) = result {
         // Check if there's a stored detailed error in the baton
         let stored_error = baton.borrow_mut().last_error.take();
         if let Some(err) = stored_error {
             // Extract position from scan_json's error - scan_json provides accurate position
-            let position = match &e {
-                scan_json::Error::ActionError { position, .. } => *position,
-                scan_json::Error::MaxNestingExceeded { position, .. } => *position,
-                scan_json::Error::InternalError { position, .. } => *position,
-                scan_json::Error::UnhandledPeek { position, .. } => *position,
-                scan_json::Error::UnbalancedJson(position) => *position,
-                scan_json::Error::RJiterError(e) => e.index,
-                scan_json::Error::IOError(_) => rjiter.current_index(),
-            };
+            let position = e.byte_position().unwrap_or_else(|| rjiter.current_index());
+            let err = too_large_or(err, position, error_attribute);
             return Err((err, position));
         }
         // Otherwise return the scan error (which includes position)
-        let position = match &e {
-            scan_json::Error::ActionError { position, .. } => *position,
-            scan_json::Error::MaxNestingExceeded { position, .. } => *position,
-            scan_json::Error::InternalError { position, .. } => *position,
-            scan_json::Error::UnhandledPeek { position, .. } => *position,
-            scan_json::Error::UnbalancedJson(position) => *position,
-            scan_json::Error::RJiterError(e) => e.index,
-            scan_json::Error::IOError(_) => rjiter.current_index(),
+        let position = e.byte_position().unwrap_or_else(|| rjiter.current_index());
+        let err = too_large_or(ConversionError::ScanError(e), position, error_attribute);
+        return Err((err, position));
+    }
+
+    Ok(report)
+}
+
+/// Replace the `BufferFull` error by `AttributeTooLarge`: the string,
+/// a name or a value, doesn't fit into the `RJiter` buffer
+fn too_large_or(
+    error: ConversionError,
+    position: usize,
+    attribute: ([u8; 64], usize),
+) -> ConversionError {
+    let start = match &error {
+        // An action reports the position where the value starts
+        ConversionError::RJiterError {
+            kind: rjiter::error::ErrorType::BufferFull,
+            ..
+        } => position,
+        // The parser reports the position where the name starts
+        ConversionError::ScanError(scan_json::Error::RJiterError(rjiter::Error {
+            error_type: rjiter::error::ErrorType::BufferFull,
+            index,
+            ..
+        })) => *index,
+        _ => return error,
+    };
+    ConversionError::AttributeTooLarge {
+        attribute,
+        position: start,
+    }
+}
+
+/// Write the path of the attribute where the conversion stopped to `buf`, truncated
+/// to its length, and return the length. The context from the top is `#top`, `Item`
+/// if it is the wrapper, then the field names (`#array` in a list) alternating with
+/// the type keys.
+fn context_attribute_path(
+    context: ContextIter,
+    item_wrapper_mode: ItemWrapperMode,
+    buf: &mut [u8],
+) -> usize {
+    let mut names: [&[u8]; CONTEXT_SLOTS] = [&[]; CONTEXT_SLOTS];
+    let mut n_names = 0;
+    for name in context {
+        let Some(slot) = names.get_mut(n_names) else {
+            break;
         };
-        return Err((ConversionError::ScanError(e), position));
+        *slot = name;
+        n_names += 1;
+    }
+    let mut names = names
+        .get(..n_names)
+        .unwrap_or(&[])
+        .iter()
+        .rev()
+        .skip(1)
+        .peekable();
+    if item_wrapper_mode == ItemWrapperMode::AsWrapper {
+        names.next_if(|name| **name == b"Item");
     }
 
-    Ok(())
+    let mut len = 0;
+    for (i, name) in names.step_by(ENTRIES_PER_LEVEL).enumerate() {
+        let segment: &[u8] = if *name == b"#array" { b"[]" } else { name };
+        let separator: &[u8] = if i > 0 && segment != b"[]" { b"." } else { b"" };
+        for byte in separator.iter().chain(segment) {
+            let Some(dst) = buf.get_mut(len) else {
+                return len;
+            };
+            *dst = *byte;
+            len += 1;
+        }
+    }
+    len
+}
+
+/// Convert `DynamoDB` JSON records of several tables in one pass, writing
+/// each record to the output of its table.
+///
+/// The table name is the top-level key `TableName`, which comes before the attributes,
+/// as in the `PutItem` requests: `{"TableName":"Orders","Item":{...}}`.
+/// The key itself is not written. `router` selects the output for each record,
+/// for example, a file per table.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `router` - Selects the output by the table name, see `TableRouter`
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `DdbToNormalOptions`
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`, and `ParseError` if a record has
+/// no `TableName` before the attributes, has it twice, or the router has no output for
+/// the table. Records copied by `Tolerance::PassThroughNormal` are not routed, they fail
+/// with `IOError`.
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_ddb_to_normal_routed<R: IoRead, T: TableRouter>(
+    reader: &mut R,
+    router: &mut T,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut writer = RoutingWriter::new(router);
+    convert(
+        reader,
+        &mut writer,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut Metrics::new(),
+        &RecordHooks {
+            select_table: Some(RoutingWriter::select),
+            end_record: None,
+            bytes_written: Some(RoutingWriter::bytes_written),
+        },
+    )
+    .map(|_| ())
+}
+
+/// Convert `DynamoDB` JSON to normal JSON with the given framing of the records.
+///
+/// With `Framing::LengthPrefixed`, each record is collected in `record_buffer`
+/// and written after its length, which includes the final newline.
+/// With `Framing::Newline`, the conversion is the same as `convert_ddb_to_normal_with_options`
+/// and `record_buffer` is not used.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `writer` - Output stream implementing `embedded_io::Write`
+/// * `framing` - How to separate the records, see `Framing`
+/// * `record_buffer` - Buffer for the largest converted record, including the newline
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `DdbToNormalOptions`
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`. A record which doesn't fit
+/// into `record_buffer` fails with an I/O error of the kind `OutOfMemory`.
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_ddb_to_normal_framed<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    framing: Framing,
+    record_buffer: &mut [u8],
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let Framing::LengthPrefixed(prefix) = framing else {
+        return convert_ddb_to_normal_with_options(
+            reader,
+            writer,
+            rjiter_buffer,
+            context_buffer,
+            options,
+        );
+    };
+    let mut framing_writer = FramingWriter::new(writer, prefix, record_buffer);
+    convert(
+        reader,
+        &mut framing_writer,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut Metrics::new(),
+        &RecordHooks {
+            select_table: None,
+            end_record: Some(FramingWriter::end_record),
+            bytes_written: Some(FramingWriter::bytes_written),
+        },
+    )
+    .map(|_| ())
+}
+
+/// Reader that copies everything it reads to a writer
+struct TeeReader<'a, R: IoRead, C: IoWrite> {
+    reader: &'a mut R,
+    copy: &'a mut C,
+    copy_error: Option<embedded_io::ErrorKind>,
+}
+
+impl<R: IoRead, C: IoWrite> embedded_io::ErrorType for TeeReader<'_, R, C> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<R: IoRead, C: IoWrite> IoRead for TeeReader<'_, R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.reader.read(buf).map_err(|e| e.kind())?;
+        let bytes = buf.get(..n).unwrap_or(&[]);
+        if let Err(e) = self.copy.write_all(bytes) {
+            // Remember the error to report it as a copy error, not as a read error
+            self.copy_error = Some(e.kind());
+            return Err(e.kind());
+        }
+        Ok(n)
+    }
+}
+
+/// Convert `DynamoDB` JSON to normal JSON and write a verbatim copy of the input.
+///
+/// The input is read once. Each chunk read from `reader` is written to
+/// `writer_rawcopy` before it is converted, therefore the raw copy is complete
+/// when the conversion succeeds, and contains at least the converted part otherwise.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `writer_normal` - Output stream for the normal JSON
+/// * `writer_rawcopy` - Output stream for the untouched copy of the input
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `DdbToNormalOptions`
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`. A failure to write the raw copy
+/// is reported as `IOError` with the context "writing raw copy".
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_ddb_to_normal_tee<R: IoRead, W: IoWrite, C: IoWrite>(
+    reader: &mut R,
+    writer_normal: &mut W,
+    writer_rawcopy: &mut C,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut tee = TeeReader {
+        reader,
+        copy: writer_rawcopy,
+        copy_error: None,
+    };
+    let result = convert_ddb_to_normal_with_options(
+        &mut tee,
+        writer_normal,
+        rjiter_buffer,
+        context_buffer,
+        options,
+    );
+    match (result, tee.copy_error) {
+        (Err((_, position)), Some(kind)) => Err((
+            ConversionError::IOError {
+                kind,
+                context: "writing raw copy",
+            },
+            position,
+        )),
+        (result, _) => result,
+    }
+}
+
+/// Writer that collects the output in a fixed buffer and gives the full buffer to a callback
+struct SpillWriter<'a, F: FnMut(&[u8]) -> Result<(), embedded_io::ErrorKind>> {
+    buffer: &'a mut [u8],
+    len: usize,
+    on_output_full: F,
+    spill_error: Option<embedded_io::ErrorKind>,
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), embedded_io::ErrorKind>> SpillWriter<'_, F> {
+    fn spill(&mut self) -> Result<(), embedded_io::ErrorKind> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let result = (self.on_output_full)(self.buffer.get(..self.len).unwrap_or(&[]));
+        self.len = 0;
+        // Remember the error to report it as a spill error, not as a write error
+        result.inspect_err(|kind| self.spill_error = Some(*kind))
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), embedded_io::ErrorKind>> embedded_io::ErrorType
+    for SpillWriter<'_, F>
+{
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), embedded_io::ErrorKind>> IoWrite for SpillWriter<'_, F> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.buffer.is_empty() {
+            // No buffer: give the bytes to the callback as is
+            return (self.on_output_full)(buf)
+                .map(|()| buf.len())
+                .inspect_err(|kind| self.spill_error = Some(*kind));
+        }
+        let free = self.buffer.get_mut(self.len..).unwrap_or(&mut []);
+        let n = free.len().min(buf.len());
+        if let (Some(dst), Some(src)) = (free.get_mut(..n), buf.get(..n)) {
+            dst.copy_from_slice(src);
+        }
+        self.len += n;
+        if self.len == self.buffer.len() {
+            self.spill()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.spill()
+    }
+}
+
+/// Convert `DynamoDB` JSON to normal JSON using a fixed-size output buffer.
+///
+/// The output is collected in `output_buffer`. When the buffer is full,
+/// or when the converter flushes the output (see `DdbToNormalOptions::unbuffered`),
+/// `on_output_full` gets the collected bytes and the buffer is reused. The rest
+/// of the output is given to `on_output_full` at the end of the conversion.
+/// The memory use is therefore fixed, and the callback can upload the chunks
+/// (for example, as parts of a multi-part upload) or rotate the output files.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `output_buffer` - Buffer to collect the output. If empty, each write goes to `on_output_full` directly
+/// * `on_output_full` - Callback for the collected output
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `DdbToNormalOptions`
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`. An error from `on_output_full`
+/// is reported as `IOError` with the context "spilling output".
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_ddb_to_normal_spill<R: IoRead, F>(
+    reader: &mut R,
+    output_buffer: &mut [u8],
+    on_output_full: F,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)>
+where
+    F: FnMut(&[u8]) -> Result<(), embedded_io::ErrorKind>,
+{
+    let mut spill_writer = SpillWriter {
+        buffer: output_buffer,
+        len: 0,
+        on_output_full,
+        spill_error: None,
+    };
+    // The metrics give the end position for an error of the last spill
+    let mut metrics = Metrics::new();
+    let result = convert_ddb_to_normal_with_metrics(
+        reader,
+        &mut spill_writer,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut metrics,
+    );
+    let spill_error = |kind| ConversionError::IOError {
+        kind,
+        context: "spilling output",
+    };
+    match (result, spill_writer.spill_error) {
+        (Err((_, position)), Some(kind)) => Err((spill_error(kind), position)),
+        (Err(e), None) => Err(e),
+        (Ok(()), _) => spill_writer.spill().map_err(|kind| {
+            let position = usize::try_from(metrics.bytes_in).unwrap_or(usize::MAX);
+            (spill_error(kind), position)
+        }),
+    }
+}
+
+/// Convert `DynamoDB` JSON to normal JSON, writing to a `core::fmt::Write` sink,
+/// such as `heapless::String` or a `String`, instead of `embedded_io::Write`.
+///
+/// The output is UTF-8, therefore `InvalidUtf8Policy::Copy` fails on invalid attribute names.
+/// To give a text sink to the other converters, use `FmtWriteAdapter`.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `writer` - Output sink implementing `core::fmt::Write`
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `DdbToNormalOptions`
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`. An error of the sink is reported
+/// as `IOError` with the kind `Other` and the context "writing to the text output",
+/// invalid UTF-8 output with the kind `InvalidData`.
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_ddb_to_normal_fmt<R: IoRead, F: core::fmt::Write>(
+    reader: &mut R,
+    writer: &mut F,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut adapter = FmtWriteAdapter::new(writer);
+    // The metrics give the end position for an incomplete code point at the end
+    let mut metrics = Metrics::new();
+    let result = convert_ddb_to_normal_with_metrics(
+        reader,
+        &mut adapter,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut metrics,
+    );
+    let sink_error = |kind| ConversionError::IOError {
+        kind,
+        context: "writing to the text output",
+    };
+    match (result, adapter.error()) {
+        (Err((_, position)), Some(kind)) => Err((sink_error(kind), position)),
+        (Err(e), None) => Err(e),
+        (Ok(()), _) => adapter.finish().map_err(|kind| {
+            let position = usize::try_from(metrics.bytes_in).unwrap_or(usize::MAX);
+            (sink_error(kind), position)
+        }),
+    }
 }
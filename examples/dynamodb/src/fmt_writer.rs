@@ -0,0 +1,125 @@
+//! Adapter from `core::fmt::Write` sinks to `embedded_io::Write`
+
+use embedded_io::Write as IoWrite;
+
+/// `embedded_io::Write` over a `core::fmt::Write` sink, such as `heapless::String`
+/// or a `String`. Use it with the converters when the application has a text sink.
+///
+/// A code point split between two writes is kept until the rest arrives.
+/// Bytes which are not valid UTF-8 fail the write with `ErrorKind::InvalidData`,
+/// an error of the sink fails it with `ErrorKind::Other`.
+pub struct FmtWriteAdapter<'a, F: core::fmt::Write> {
+    sink: &'a mut F,
+    // The beginning of a code point, which is continued in the next write
+    pending: [u8; 4],
+    pending_len: usize,
+    // The error of the last failed write
+    error: Option<embedded_io::ErrorKind>,
+}
+
+impl<'a, F: core::fmt::Write> FmtWriteAdapter<'a, F> {
+    /// Create the adapter for the sink
+    pub fn new(sink: &'a mut F) -> Self {
+        Self {
+            sink,
+            pending: [0; 4],
+            pending_len: 0,
+            error: None,
+        }
+    }
+
+    /// Check that the output doesn't end in the middle of a code point.
+    ///
+    /// # Errors
+    /// `ErrorKind::InvalidData` if a code point is not complete.
+    pub fn finish(&self) -> Result<(), embedded_io::ErrorKind> {
+        if self.pending_len == 0 {
+            Ok(())
+        } else {
+            Err(embedded_io::ErrorKind::InvalidData)
+        }
+    }
+
+    /// The error of the last failed write, if any. Useful when the error is
+    /// reported by the caller with another kind or context.
+    #[must_use]
+    pub fn error(&self) -> Option<embedded_io::ErrorKind> {
+        self.error
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<(), embedded_io::ErrorKind> {
+        self.sink
+            .write_str(s)
+            .map_err(|_| embedded_io::ErrorKind::Other)
+    }
+
+    // Complete the pending code point with the first bytes of `buf`.
+    // Returns the number of the used bytes.
+    fn complete_pending(&mut self, buf: &[u8]) -> Result<usize, embedded_io::ErrorKind> {
+        let mut used = 0;
+        for byte in buf {
+            let Some(slot) = self.pending.get_mut(self.pending_len) else {
+                return Err(embedded_io::ErrorKind::InvalidData);
+            };
+            *slot = *byte;
+            self.pending_len += 1;
+            used += 1;
+            let pending = self.pending;
+            match core::str::from_utf8(pending.get(..self.pending_len).unwrap_or(&[])) {
+                Ok(code_point) => {
+                    self.pending_len = 0;
+                    self.write_str(code_point)?;
+                    break;
+                }
+                Err(e) if e.error_len().is_some() => {
+                    return Err(embedded_io::ErrorKind::InvalidData);
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(used)
+    }
+}
+
+impl<F: core::fmt::Write> embedded_io::ErrorType for FmtWriteAdapter<'_, F> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<F: core::fmt::Write> IoWrite for FmtWriteAdapter<'_, F> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_utf8(buf)
+            .map(|()| buf.len())
+            .inspect_err(|kind| self.error = Some(*kind))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<F: core::fmt::Write> FmtWriteAdapter<'_, F> {
+    fn write_utf8(&mut self, buf: &[u8]) -> Result<(), embedded_io::ErrorKind> {
+        let used = if self.pending_len > 0 {
+            self.complete_pending(buf)?
+        } else {
+            0
+        };
+        let rest = buf.get(used..).unwrap_or(&[]);
+        if self.pending_len > 0 || rest.is_empty() {
+            return Ok(());
+        }
+        match core::str::from_utf8(rest) {
+            Ok(s) => self.write_str(s)?,
+            Err(e) => {
+                if e.error_len().is_some() {
+                    return Err(embedded_io::ErrorKind::InvalidData);
+                }
+                // The end is the beginning of a code point, keep it for the next write
+                let (valid, incomplete) = rest.split_at(e.valid_up_to());
+                self.write_str(core::str::from_utf8(valid).unwrap_or_default())?;
+                self.complete_pending(incomplete)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Framing of the converted records, see `convert_ddb_to_normal_framed`
+
+use crate::ConversionError;
+use embedded_io::{Error as IoError, Write as IoWrite};
+
+/// How the converted records are separated in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// Each record ends with a newline (JSON Lines)
+    Newline,
+    /// Each record ends with a newline and is preceded by its length in bytes,
+    /// including the newline. A reader can read the prefix, then exactly
+    /// the given number of bytes.
+    LengthPrefixed(LengthPrefix),
+}
+
+/// The format of the length in `Framing::LengthPrefixed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthPrefix {
+    /// ASCII digits and a space, for example, `9 {"a":42}\n`
+    AsciiDecimal,
+    /// 4 bytes, little-endian unsigned integer
+    U32Le,
+}
+
+/// A writer which collects a record in a buffer and writes it with
+/// the length prefix when the record ends
+pub(crate) struct FramingWriter<'a, W: IoWrite> {
+    writer: &'a mut W,
+    prefix: LengthPrefix,
+    buffer: &'a mut [u8],
+    len: usize,
+    written: u64, // Bytes of the framed records written to the output
+}
+
+impl<'a, W: IoWrite> FramingWriter<'a, W> {
+    pub(crate) fn new(writer: &'a mut W, prefix: LengthPrefix, buffer: &'a mut [u8]) -> Self {
+        Self {
+            writer,
+            prefix,
+            buffer,
+            len: 0,
+            written: 0,
+        }
+    }
+
+    /// Number of bytes written to the output, with the length prefixes
+    pub(crate) fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Write the collected record with its length prefix
+    pub(crate) fn end_record(&mut self) -> Result<(), ConversionError> {
+        let record = self.buffer.get(..self.len).unwrap_or(&[]);
+        self.len = 0;
+        let io_error = |e: W::Error| ConversionError::IOError {
+            kind: e.kind(),
+            context: "writing framed record",
+        };
+        match self.prefix {
+            LengthPrefix::AsciiDecimal => {
+                // `usize::MAX` has at most 20 digits
+                let mut digits = [0u8; 21];
+                let mut start = digits.len() - 1;
+                if let Some(space) = digits.get_mut(start) {
+                    *space = b' ';
+                }
+                let mut n = record.len();
+                loop {
+                    start -= 1;
+                    if let Some(digit) = digits.get_mut(start) {
+                        #[allow(clippy::cast_possible_truncation)]
+                        let value = (n % 10) as u8;
+                        *digit = b'0' + value;
+                    }
+                    n /= 10;
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let prefix = digits.get(start..).unwrap_or(&[]);
+                self.writer.write_all(prefix).map_err(io_error)?;
+                self.written += prefix.len() as u64;
+            }
+            LengthPrefix::U32Le => {
+                let len = u32::try_from(record.len()).map_err(|_| ConversionError::IOError {
+                    kind: embedded_io::ErrorKind::InvalidData,
+                    context: "writing framed record longer than 4 GiB",
+                })?;
+                self.writer
+                    .write_all(&len.to_le_bytes())
+                    .map_err(io_error)?;
+                self.written += 4;
+            }
+        }
+        self.writer.write_all(record).map_err(io_error)?;
+        self.written += record.len() as u64;
+        Ok(())
+    }
+}
+
+impl<W: IoWrite> embedded_io::ErrorType for FramingWriter<'_, W> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<W: IoWrite> IoWrite for FramingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let end = self.len + buf.len();
+        let Some(dst) = self.buffer.get_mut(self.len..end) else {
+            return Err(embedded_io::ErrorKind::OutOfMemory);
+        };
+        dst.copy_from_slice(buf);
+        self.len = end;
+        Ok(buf.len())
+    }
+
+    // Only complete records are written to the output, flush them
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush().map_err(|e| e.kind())
+    }
+}
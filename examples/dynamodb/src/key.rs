@@ -0,0 +1,264 @@
+//! Conversion of key-only maps, such as `LastEvaluatedKey` and `ExclusiveStartKey`
+//!
+//! The helpers use `RJiter` directly, without the `scan_json` context,
+//! therefore they work with a small buffer and have no setup cost.
+
+use crate::ddb_to_normal::is_json_number;
+use crate::ConversionError;
+use embedded_io::{Error as IoError, Read as IoRead, Write as IoWrite};
+use rjiter::jiter::Peek;
+use rjiter::RJiter;
+
+type KeyResult<T> = Result<T, (ConversionError, usize)>;
+
+pub(crate) fn rjiter_error(
+    error: &rjiter::Error,
+    context: &'static str,
+) -> (ConversionError, usize) {
+    (
+        ConversionError::RJiterError {
+            kind: error.error_type.clone(),
+            context,
+        },
+        error.index,
+    )
+}
+
+pub(crate) fn parse_error(context: &'static str, position: usize) -> (ConversionError, usize) {
+    (
+        ConversionError::ParseError {
+            context,
+            unknown_type: None,
+            invalid_utf8: None,
+        },
+        position,
+    )
+}
+
+pub(crate) fn write_all<W: IoWrite>(
+    writer: &mut W,
+    bytes: &[u8],
+    context: &'static str,
+    position: usize,
+) -> KeyResult<()> {
+    writer.write_all(bytes).map_err(|e| {
+        (
+            ConversionError::IOError {
+                kind: e.kind(),
+                context,
+            },
+            position,
+        )
+    })
+}
+
+/// Key attribute types
+#[derive(Clone, Copy)]
+enum KeyType {
+    S,
+    N,
+    B,
+}
+
+// Read the next attribute name of the key map and write it as `"name":`.
+// Returns `false` at the end of the map.
+fn convert_name<R: IoRead, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    writer: &mut W,
+    is_first: bool,
+) -> KeyResult<bool> {
+    // The name borrows rjiter, therefore the position for errors is taken before
+    let position = rjiter.current_index();
+    let name = if is_first {
+        rjiter.next_object_bytes()
+    } else {
+        rjiter.next_key_bytes()
+    }
+    .map_err(|e| rjiter_error(&e, "reading key map"))?;
+    let Some(name) = name else {
+        return Ok(false);
+    };
+    if !is_first {
+        write_all(writer, b",", "writing comma", position)?;
+    }
+    write_all(
+        writer,
+        b"\"",
+        "writing attribute name opening quote",
+        position,
+    )?;
+    write_all(writer, name, "writing attribute name", position)?;
+    write_all(
+        writer,
+        b"\":",
+        "writing attribute name closing quote and colon",
+        position,
+    )?;
+    Ok(true)
+}
+
+/// Convert a `DynamoDB` key map to normal JSON, for example,
+/// `{"pk":{"S":"user#1"},"sk":{"N":"42"}}` to `{"pk":"user#1","sk":42}`.
+///
+/// The key attributes can have only the types `S`, `N` and `B`.
+/// The output is compact and has no trailing newline.
+///
+/// # Arguments
+/// * `reader` - Input stream with one key map
+/// * `writer` - Output stream
+/// * `rjiter_buffer` - Buffer for rjiter, should fit a number and an attribute name (for example, 64 bytes)
+///
+/// # Errors
+/// Returns `ConversionError` and the position if the input is not a valid key map,
+/// or if I/O errors occur.
+pub fn convert_key_to_normal<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+) -> KeyResult<()> {
+    let mut rjiter = RJiter::new(reader, rjiter_buffer);
+
+    write_all(writer, b"{", "writing key opening brace", 0)?;
+    let mut is_first = true;
+    while convert_name(&mut rjiter, writer, is_first)? {
+        is_first = false;
+
+        let type_key = rjiter
+            .next_object_bytes()
+            .map_err(|e| rjiter_error(&e, "reading key attribute type"))?;
+        let key_type = match type_key {
+            Some(b"S") => KeyType::S,
+            Some(b"N") => KeyType::N,
+            Some(b"B") => KeyType::B,
+            _ => {
+                return Err(parse_error(
+                    "Invalid key attribute: expected type S, N or B",
+                    rjiter.current_index(),
+                ))
+            }
+        };
+
+        let peek = rjiter
+            .peek()
+            .map_err(|e| rjiter_error(&e, "peeking key attribute value"))?;
+        let position = rjiter.current_index();
+        if peek != Peek::String {
+            return Err(parse_error(
+                "Invalid key attribute: expected string value",
+                position,
+            ));
+        }
+        if let KeyType::N = key_type {
+            // The number is written without quotes, therefore it should be a JSON number
+            let number = rjiter
+                .known_bytes()
+                .map_err(|e| rjiter_error(&e, "reading key attribute number"))?;
+            if !is_json_number(number) {
+                return Err(parse_error(
+                    "Invalid key attribute: N value is not a number",
+                    position,
+                ));
+            }
+            write_all(writer, number, "writing key attribute value", position)?;
+        } else {
+            write_all(writer, b"\"", "writing opening quote", position)?;
+            rjiter
+                .write_long_bytes(writer)
+                .map_err(|e| rjiter_error(&e, "writing key attribute value"))?;
+            write_all(
+                writer,
+                b"\"",
+                "writing closing quote",
+                rjiter.current_index(),
+            )?;
+        }
+
+        let extra_key = rjiter
+            .next_key_bytes()
+            .map_err(|e| rjiter_error(&e, "reading key attribute"))?;
+        if extra_key.is_some() {
+            return Err(parse_error(
+                "Invalid key attribute: expected one type descriptor",
+                rjiter.current_index(),
+            ));
+        }
+    }
+    write_all(
+        writer,
+        b"}",
+        "writing key closing brace",
+        rjiter.current_index(),
+    )?;
+
+    rjiter
+        .finish()
+        .map_err(|e| rjiter_error(&e, "checking end of key map"))
+}
+
+/// Convert a normal JSON key map to `DynamoDB` JSON, for example,
+/// `{"pk":"user#1","sk":42}` to `{"pk":{"S":"user#1"},"sk":{"N":"42"}}`.
+///
+/// Strings become `S` and numbers become `N`, other values are an error.
+/// The output is compact and has no trailing newline.
+///
+/// # Arguments
+/// * `reader` - Input stream with one key map
+/// * `writer` - Output stream
+/// * `rjiter_buffer` - Buffer for rjiter, should fit a number and an attribute name (for example, 64 bytes)
+///
+/// # Errors
+/// Returns `ConversionError` and the position if the input is not a valid key map,
+/// or if I/O errors occur.
+pub fn convert_key_to_ddb<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+) -> KeyResult<()> {
+    let mut rjiter = RJiter::new(reader, rjiter_buffer);
+
+    write_all(writer, b"{", "writing key opening brace", 0)?;
+    let mut is_first = true;
+    while convert_name(&mut rjiter, writer, is_first)? {
+        is_first = false;
+
+        let peek = rjiter
+            .peek()
+            .map_err(|e| rjiter_error(&e, "peeking key attribute value"))?;
+        let position = rjiter.current_index();
+        if peek == Peek::String {
+            write_all(writer, b"{\"S\":\"", "writing S type opening", position)?;
+            rjiter
+                .write_long_bytes(writer)
+                .map_err(|e| rjiter_error(&e, "writing key attribute value"))?;
+            write_all(
+                writer,
+                b"\"}",
+                "writing S type closing",
+                rjiter.current_index(),
+            )?;
+        } else if peek.is_num() {
+            write_all(writer, b"{\"N\":\"", "writing N type opening", position)?;
+            // The parser accepts only valid JSON numbers
+            let number = rjiter
+                .next_number_bytes()
+                .map_err(|e| rjiter_error(&e, "reading key attribute number"))?;
+            write_all(writer, number, "writing key attribute value", position)?;
+            write_all(writer, b"\"}", "writing N type closing", position)?;
+        } else {
+            return Err(parse_error(
+                "Invalid key attribute: expected string or number value",
+                position,
+            ));
+        }
+    }
+    write_all(
+        writer,
+        b"}",
+        "writing key closing brace",
+        rjiter.current_index(),
+    )?;
+
+    rjiter
+        .finish()
+        .map_err(|e| rjiter_error(&e, "checking end of key map"))
+}
@@ -10,15 +10,70 @@ extern crate std;
 
 extern crate alloc;
 
+mod auto;
 mod ddb_to_normal;
+mod fmt_writer;
+mod framing;
+mod key;
+mod metrics;
 mod normal_to_ddb;
+mod number;
+mod patch;
+mod plan;
+mod route;
 
-pub use ddb_to_normal::{convert_ddb_to_normal, ItemWrapperMode};
-pub use normal_to_ddb::convert_normal_to_ddb;
+pub use auto::{convert_auto, Direction};
+pub use ddb_to_normal::{
+    convert_ddb_to_normal, convert_ddb_to_normal_fmt, convert_ddb_to_normal_framed,
+    convert_ddb_to_normal_prefix, convert_ddb_to_normal_routed, convert_ddb_to_normal_spill,
+    convert_ddb_to_normal_tee, convert_ddb_to_normal_with_metrics,
+    convert_ddb_to_normal_with_options, CoerceTo, DdbToNormalOptions, Emit, ItemWrapperMode, Limit,
+    NumberOutput, PrefixReport, PresenceCallback, PresenceOutput, RecordCallback, ScalarHook,
+    Tolerance, MAX_FLATTEN_DEPTH, MAX_FLAT_KEY, MAX_PRESENCE_FIELDS, MAX_SCALAR_REPLACEMENT,
+    PRESENCE_FIELD,
+};
+pub use fmt_writer::FmtWriteAdapter;
+pub use framing::{Framing, LengthPrefix};
+pub use key::{convert_key_to_ddb, convert_key_to_normal};
+pub use metrics::{AttributeCounts, Metrics};
+pub use normal_to_ddb::{
+    convert_normal_to_ddb, convert_normal_to_ddb_with_options, NormalToDdbOptions, NullPolicy,
+};
+pub use patch::diff_to_json_patch;
+pub use plan::{plan_ddb_to_normal, InputFormat, Plan, WrapperUsage};
+pub use route::{TableRouter, MAX_TABLE_NAME};
+
+/// Key prefix for type descriptors preserved by `UnknownTypePolicy::PassthroughTagged`.
+///
+/// An unknown descriptor `X` with value `V` becomes `{"$ddb:X": V}` in normal JSON.
+pub const TAGGED_TYPE_PREFIX: &[u8] = b"$ddb:";
+
+/// How to handle type descriptors that the converter doesn't know
+/// (for example, types added by AWS in the future)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownTypePolicy {
+    /// Fail the conversion with a `ParseError`
+    Error,
+    /// Keep the value under a reserved key (see `TAGGED_TYPE_PREFIX`).
+    /// The normal-to-DDB direction restores the original type descriptor from such keys.
+    PassthroughTagged,
+}
+
+/// How to handle attribute names which are not valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidUtf8Policy {
+    /// Copy the name as is, the output is not valid JSON
+    Copy,
+    /// Fail the conversion with a `ParseError` which shows the invalid bytes
+    Error,
+    /// Replace each invalid sequence with U+FFFD (the replacement character)
+    Replace,
+}
 
 /// Detailed error information for conversion errors
 /// Position is returned separately by convert functions
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ConversionError {
     /// `RJiter` error with context
     RJiterError {
@@ -40,11 +95,81 @@ pub enum ConversionError {
         context: &'static str,
         /// Unknown type descriptor bytes (buffer, actual length used)
         unknown_type: Option<([u8; 32], usize)>,
+        /// Invalid UTF-8 bytes of an attribute name, starting from the first
+        /// invalid byte (buffer, actual length used)
+        invalid_utf8: Option<([u8; 16], usize)>,
+    },
+    /// Empty set (SS, NS or BS), which `DynamoDB` doesn't allow
+    EmptySet,
+    /// Set (SS, NS or BS) has more elements than allowed
+    SetTooLarge {
+        /// The maximum number of elements
+        max_elements: usize,
+    },
+    /// An attribute name or value doesn't fit into the `RJiter` buffer, and the converter
+    /// can't stream it: an attribute name, or a value which is read as a whole,
+    /// for the coercion or for `ScalarHook`. The other values are streamed.
+    AttributeTooLarge {
+        /// The path of the attribute, as in `ScalarHook`, for example `user.email` or `tags[]`
+        /// (buffer, actual length used). For a too long attribute name,
+        /// the path of the map with this name, empty at the top of the item.
+        attribute: ([u8; 64], usize),
+        /// The position where the value starts, or for a name, the position
+        /// of the `{` or `,` before it
+        position: usize,
     },
     /// Scan error (from `scan_json` library)
     ScanError(scan_json::Error),
 }
 
+impl ConversionError {
+    /// Whether the error is transient and a new attempt can succeed on the same input:
+    /// the I/O errors `Interrupted` and `TimedOut`, if the reader or the writer
+    /// doesn't lose data on them.
+    ///
+    /// The other errors, such as invalid input, repeat on a retry.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        let is_transient = |kind: &embedded_io::ErrorKind| {
+            matches!(
+                kind,
+                embedded_io::ErrorKind::Interrupted | embedded_io::ErrorKind::TimedOut
+            )
+        };
+        match self {
+            ConversionError::RJiterError {
+                kind: rjiter::error::ErrorType::IoError { kind },
+                ..
+            }
+            | ConversionError::IOError { kind, .. }
+            | ConversionError::ScanError(
+                scan_json::Error::IOError(kind)
+                | scan_json::Error::RJiterError(rjiter::Error {
+                    error_type: rjiter::error::ErrorType::IoError { kind },
+                    ..
+                }),
+            ) => is_transient(kind),
+            ConversionError::RJiterError { .. }
+            | ConversionError::ScanError(_)
+            | ConversionError::ParseError { .. }
+            | ConversionError::EmptySet
+            | ConversionError::SetTooLarge { .. }
+            | ConversionError::AttributeTooLarge { .. } => false,
+        }
+    }
+
+    /// Line and column in the input where the error occurred, if the parser
+    /// has found it, see `scan_json::Error::line_position`.
+    /// The other errors have only the byte position.
+    #[must_use]
+    pub fn line_position(&self) -> Option<&rjiter::jiter::LinePosition> {
+        match self {
+            ConversionError::ScanError(e) => e.line_position(),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "std")]
 impl core::fmt::Display for ConversionError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -58,8 +183,15 @@ impl core::fmt::Display for ConversionError {
             ConversionError::ParseError {
                 context,
                 unknown_type,
+                invalid_utf8,
             } => {
-                if let Some((bytes, len)) = unknown_type {
+                if let Some((bytes, len)) = invalid_utf8 {
+                    write!(f, "Parse error: {context} (invalid UTF-8 bytes:")?;
+                    for byte in bytes.get(..*len).unwrap_or(&[]) {
+                        write!(f, " {byte:02x}")?;
+                    }
+                    write!(f, ")")
+                } else if let Some((bytes, len)) = unknown_type {
                     let type_str =
                         std::string::String::from_utf8_lossy(bytes.get(..*len).unwrap_or(&[]));
                     write!(
@@ -70,6 +202,25 @@ impl core::fmt::Display for ConversionError {
                     write!(f, "Parse error: {context}")
                 }
             }
+            ConversionError::EmptySet => {
+                write!(f, "Validation error: empty sets are not allowed")
+            }
+            ConversionError::SetTooLarge { max_elements } => {
+                write!(
+                    f,
+                    "Validation error: set has more than {max_elements} elements"
+                )
+            }
+            ConversionError::AttributeTooLarge {
+                attribute: (bytes, len),
+                position,
+            } => {
+                let path = std::string::String::from_utf8_lossy(bytes.get(..*len).unwrap_or(&[]));
+                write!(
+                    f,
+                    "Attribute '{path}' at position {position} doesn't fit into the RJiter buffer, use a larger buffer"
+                )
+            }
             ConversionError::ScanError(err) => {
                 write!(f, "{err}")
             }
@@ -1,9 +1,17 @@
 //! `DynamoDB` JSON converter CLI tool
 
 use clap::{Parser, ValueEnum};
-use ddb_convert::{convert_ddb_to_normal, convert_normal_to_ddb, ConversionError};
+use ddb_convert::{
+    convert_auto, convert_ddb_to_normal_framed, convert_ddb_to_normal_prefix,
+    convert_ddb_to_normal_routed, convert_ddb_to_normal_tee, convert_normal_to_ddb_with_options,
+    plan_ddb_to_normal, CoerceTo, ConversionError, DdbToNormalOptions, Direction, Framing,
+    InvalidUtf8Policy, LengthPrefix, Limit, NormalToDdbOptions, NullPolicy, NumberOutput,
+    TableRouter, Tolerance, UnknownTypePolicy,
+};
 use embedded_io_adapters::std::FromStd;
+use std::collections::HashMap;
 use std::io::{self, BufReader, BufWriter};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum ConversionMode {
@@ -11,12 +19,46 @@ enum ConversionMode {
     FromDdb,
     /// Convert normal JSON to `DynamoDB` JSON
     ToDdb,
+    /// Detect the direction from the value of the first attribute and convert
+    Auto,
+}
+
+/// How to handle attribute names which are not valid UTF-8
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InvalidUtf8Names {
+    /// Copy the name as is
+    Copy,
+    /// Fail with an error which shows the invalid bytes
+    Error,
+    /// Replace invalid sequences with U+FFFD
+    Replace,
+}
+
+/// The format of the record length for `--length-prefix`
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LengthPrefixFormat {
+    /// ASCII digits and a space
+    Ascii,
+    /// 4 bytes, little-endian
+    U32le,
+}
+
+/// How to write `null` values for `--null-policy`
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum NullValues {
+    /// Write the NULL type
+    Emit,
+    /// Drop the attributes with `null` values
+    Omit,
+    /// Write an empty string
+    EmptyString,
 }
 
 #[derive(Parser, Debug)]
 #[command(name = "ddb_convert")]
 #[command(version)]
 #[command(about = "Convert between DynamoDB JSON and normal JSON formats", long_about = None)]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
     /// Conversion mode
     #[arg(value_enum)]
@@ -44,49 +86,308 @@ struct Args {
     /// Do unbuffered reads and writes
     #[arg(long = "unbuffered", default_value_t = false)]
     unbuffered: bool,
+
+    /// Preserve unknown type descriptors instead of failing
+    ///
+    /// In from-ddb mode, an unknown descriptor `X` is written as {"$ddb:X": value}.
+    /// In to-ddb mode, such objects are converted back to the original descriptor.
+    #[arg(long = "passthrough-unknown-types", default_value_t = false)]
+    passthrough_unknown_types: bool,
+
+    /// Reject empty sets (SS, NS, BS) like `DynamoDB` does (only applies to from-ddb mode)
+    #[arg(long = "reject-empty-sets", default_value_t = false)]
+    reject_empty_sets: bool,
+
+    /// Maximum number of elements in a set (only applies to from-ddb mode)
+    #[arg(long = "max-set-elements")]
+    max_set_elements: Option<usize>,
+
+    /// Also write a verbatim copy of the input to this file (only applies to from-ddb mode)
+    #[arg(long = "tee")]
+    tee: Option<String>,
+
+    /// Write S values at a path as numbers or booleans (only applies to from-ddb mode)
+    ///
+    /// The format is `PATH=TYPE`, where `PATH` is a dot-separated list of field names
+    /// and `TYPE` is `number` or `bool`, for example, `user.age=number`. Can be repeated.
+    #[arg(long = "coerce", value_name = "PATH=TYPE")]
+    coerce: Vec<String>,
+
+    /// Copy records which are already normal JSON as is (only applies to from-ddb mode)
+    #[arg(long = "pass-through-normal", default_value_t = false)]
+    pass_through_normal: bool,
+
+    /// Report the presence of a top-level attribute in `$present` (only applies to from-ddb mode)
+    ///
+    /// Each record gets the field `$present`, an array of booleans in the order
+    /// of the attributes. Can be repeated.
+    #[arg(long = "presence", value_name = "FIELD")]
+    presence: Vec<String>,
+
+    /// Flatten nested maps and lists into keys like `address.city` and `tags[0]` (only applies to from-ddb mode)
+    ///
+    /// Values nested deeper than `DEPTH` are written as nested JSON.
+    #[arg(long = "flatten", value_name = "DEPTH")]
+    flatten: Option<usize>,
+
+    /// How to handle attribute names which are not valid UTF-8 (only applies to from-ddb mode)
+    #[arg(long = "invalid-utf8-names", value_enum, default_value_t = InvalidUtf8Names::Copy)]
+    invalid_utf8_names: InvalidUtf8Names,
+
+    /// Write integral numbers as integers, for example, `42.0` and `042` as `42` (only applies to from-ddb mode)
+    ///
+    /// The leading zeros and the trailing zeros of the fraction are removed.
+    #[arg(long = "normalize-integers", default_value_t = false)]
+    normalize_integers: bool,
+
+    /// Scan the input and report what would be converted, without writing the output (only applies to from-ddb mode)
+    ///
+    /// The report has the record count, the format (JSON, JSON Lines or a stream), the use of
+    /// the "Item" wrapper, the attribute types and the size of the output.
+    #[arg(long = "dry-run", default_value_t = false)]
+    dry_run: bool,
+
+    /// Write the records of each table to `DIR/TABLE.jsonl` (only applies to from-ddb mode)
+    ///
+    /// Each record should have the table name in the top-level `TableName` key,
+    /// before the attributes, for example, {"TableName":"Orders","Item":{...}}.
+    #[arg(long = "route-by-table", value_name = "DIR")]
+    route_by_table: Option<PathBuf>,
+
+    /// Precede each record with its length in bytes, including the newline (only applies to from-ddb mode)
+    #[arg(long = "length-prefix", value_enum, value_name = "FORMAT")]
+    length_prefix: Option<LengthPrefixFormat>,
+
+    /// Convert only the first N records (only applies to from-ddb mode)
+    #[arg(long = "max-records", value_name = "N")]
+    max_records: Option<u64>,
+
+    /// Don't start a new record after N input bytes (only applies to from-ddb mode)
+    ///
+    /// The record which crosses the limit is converted completely.
+    #[arg(long = "max-bytes", value_name = "N")]
+    max_bytes: Option<usize>,
+
+    /// How to write `null` values (only applies to to-ddb mode)
+    ///
+    /// In the `omit` mode, `null` elements of arrays are still written as NULL.
+    #[arg(long = "null-policy", value_enum, default_value_t = NullValues::Emit)]
+    null_policy: NullValues,
+}
+
+/// Router for `--route-by-table`, creates the output file of a table on its first record
+struct FileRouter {
+    dir: PathBuf,
+    tables: HashMap<Vec<u8>, usize>,
+    outputs: Vec<FromStd<BufWriter<std::fs::File>>>,
+}
+
+impl TableRouter for FileRouter {
+    type Writer = FromStd<BufWriter<std::fs::File>>;
+
+    fn route(&mut self, table_name: &[u8]) -> Option<usize> {
+        if let Some(index) = self.tables.get(table_name) {
+            return Some(*index);
+        }
+        // Table names have only letters, digits, `_`, `-` and `.`, safe for a file name
+        let is_valid = !table_name.is_empty()
+            && table_name
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'));
+        if !is_valid {
+            return None;
+        }
+        let name = std::str::from_utf8(table_name).ok()?;
+        let path = self.dir.join(format!("{name}.jsonl"));
+        let file = std::fs::File::create(&path)
+            .inspect_err(|e| eprintln!("Error creating output file '{}': {e}", path.display()))
+            .ok()?;
+        self.outputs.push(FromStd::new(BufWriter::new(file)));
+        self.tables
+            .insert(table_name.to_vec(), self.outputs.len() - 1);
+        Some(self.outputs.len() - 1)
+    }
+
+    fn output(&mut self, index: usize) -> Option<&mut Self::Writer> {
+        self.outputs.get_mut(index)
+    }
+}
+
+/// Parse a `PATH=TYPE` coercion rule
+fn parse_coercion(rule: &str) -> (&str, CoerceTo) {
+    let coerce_to = match rule.rsplit_once('=') {
+        Some((path, "number")) if !path.is_empty() => Some((path, CoerceTo::Number)),
+        Some((path, "bool")) if !path.is_empty() => Some((path, CoerceTo::Bool)),
+        _ => None,
+    };
+    coerce_to.unwrap_or_else(|| {
+        eprintln!("Invalid coercion rule '{rule}', expected PATH=number or PATH=bool");
+        std::process::exit(1);
+    })
 }
 
 /// Helper to create buffers and run conversion from `DynamoDB` JSON to normal JSON
 fn convert_from_ddb<R: embedded_io::Read, W: embedded_io::Write>(
     input_reader: &mut R,
     output_writer: &mut W,
-    pretty: bool,
-    unbuffered: bool,
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut rjiter_buffer = vec![0u8; 64 * 1024];
+    let mut context_buffer = vec![0u8; 2048];
+    let report = convert_ddb_to_normal_prefix(
+        input_reader,
+        output_writer,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        options,
+    )?;
+    if report.is_limit_reached {
+        eprintln!(
+            "Stopped after {} records, to resume, skip the first {} bytes of the input",
+            report.records, report.consumed
+        );
+    }
+    Ok(())
+}
+
+/// Helper to create buffers and run conversion from `DynamoDB` JSON to normal JSON,
+/// copying the input to `rawcopy_writer`
+fn convert_from_ddb_tee<R: embedded_io::Read, W: embedded_io::Write, C: embedded_io::Write>(
+    input_reader: &mut R,
+    output_writer: &mut W,
+    rawcopy_writer: &mut C,
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut rjiter_buffer = vec![0u8; 64 * 1024];
+    let mut context_buffer = vec![0u8; 2048];
+    let result = convert_ddb_to_normal_tee(
+        input_reader,
+        output_writer,
+        rawcopy_writer,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        options,
+    );
+    if let Err(e) = rawcopy_writer.flush() {
+        eprintln!("Error writing the raw copy: {e:?}");
+        std::process::exit(1);
+    }
+    result
+}
+
+/// Helper to create buffers and run conversion from `DynamoDB` JSON to normal JSON,
+/// writing the records of each table to a file in `dir`
+fn convert_from_ddb_routed<R: embedded_io::Read>(
+    input_reader: &mut R,
+    dir: PathBuf,
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut rjiter_buffer = vec![0u8; 64 * 1024];
+    let mut context_buffer = vec![0u8; 2048];
+    let mut router = FileRouter {
+        dir,
+        tables: HashMap::new(),
+        outputs: Vec::new(),
+    };
+    let result = convert_ddb_to_normal_routed(
+        input_reader,
+        &mut router,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        options,
+    );
+    for output in &mut router.outputs {
+        if let Err(e) = embedded_io::Write::flush(output) {
+            eprintln!("Error writing a table output: {e}");
+            std::process::exit(1);
+        }
+    }
+    result
+}
+
+/// Helper to create buffers and run conversion from `DynamoDB` JSON to normal JSON,
+/// with a length prefix before each record
+fn convert_from_ddb_framed<R: embedded_io::Read, W: embedded_io::Write>(
+    input_reader: &mut R,
+    output_writer: &mut W,
+    prefix: LengthPrefix,
+    options: &DdbToNormalOptions,
 ) -> Result<(), (ConversionError, usize)> {
+    // Larger than the largest DynamoDB item (400 KB) in the normal JSON
+    let mut record_buffer = vec![0u8; 4 * 1024 * 1024];
     let mut rjiter_buffer = vec![0u8; 64 * 1024];
     let mut context_buffer = vec![0u8; 2048];
-    convert_ddb_to_normal(
+    convert_ddb_to_normal_framed(
         input_reader,
         output_writer,
+        Framing::LengthPrefixed(prefix),
+        &mut record_buffer,
         &mut rjiter_buffer,
         &mut context_buffer,
-        pretty,
-        unbuffered,
-        ddb_convert::ItemWrapperMode::AsWrapper,
+        options,
     )
 }
 
+/// Helper to create buffers and run a dry run of the conversion from `DynamoDB` JSON
+fn plan_from_ddb<R: embedded_io::Read>(
+    input_reader: &mut R,
+    options: &DdbToNormalOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut rjiter_buffer = vec![0u8; 64 * 1024];
+    let mut context_buffer = vec![0u8; 2048];
+    let plan = plan_ddb_to_normal(
+        input_reader,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        options,
+    )?;
+    print!("{plan}");
+    Ok(())
+}
+
 /// Helper to create buffers and run conversion from normal JSON to `DynamoDB` JSON
 fn convert_to_ddb<R: embedded_io::Read, W: embedded_io::Write>(
     input_reader: &mut R,
     output_writer: &mut W,
-    pretty: bool,
-    unbuffered: bool,
-    with_item_wrapper: bool,
+    options: NormalToDdbOptions,
 ) -> Result<(), (ConversionError, usize)> {
     let mut rjiter_buffer = vec![0u8; 64 * 1024];
     let mut context_buffer = vec![0u8; 2048];
-    convert_normal_to_ddb(
+    convert_normal_to_ddb_with_options(
         input_reader,
         output_writer,
         &mut rjiter_buffer,
         &mut context_buffer,
-        pretty,
-        unbuffered,
-        with_item_wrapper,
+        &options,
     )
 }
 
+/// Helper to create buffers and run conversion in the detected direction
+fn convert_either<R: embedded_io::Read, W: embedded_io::Write>(
+    input_reader: &mut R,
+    output_writer: &mut W,
+    to_normal: &DdbToNormalOptions,
+    to_ddb: NormalToDdbOptions,
+) -> Result<(), (ConversionError, usize)> {
+    let mut rjiter_buffer = vec![0u8; 64 * 1024];
+    let mut context_buffer = vec![0u8; 2048];
+    let direction = convert_auto(
+        input_reader,
+        output_writer,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        to_normal,
+        &to_ddb,
+    )?;
+    let mode = match direction {
+        Direction::DdbToNormal => "from-ddb",
+        Direction::NormalToDdb => "to-ddb",
+    };
+    eprintln!("Converted in the {mode} mode");
+    Ok(())
+}
+
+#[allow(clippy::too_many_lines)]
 fn main() {
     let args = Args::parse();
 
@@ -103,40 +404,168 @@ fn main() {
         }
     };
 
-    let mut output_writer: Box<dyn embedded_io::Write<Error = std::io::Error>> = {
-        let output_channel: Box<dyn io::Write> = if let Some(output_path) = &args.output {
-            Box::new(create_output_file(output_path))
-        } else {
-            Box::new(io::stdout())
-        };
-        if args.unbuffered {
-            Box::new(FromStd::new(output_channel))
-        } else {
-            Box::new(FromStd::new(BufWriter::new(output_channel)))
+    let is_from_ddb = matches!(args.mode, ConversionMode::FromDdb);
+    if args.dry_run && !is_from_ddb {
+        eprintln!("--dry-run only applies to from-ddb mode");
+        std::process::exit(1);
+    }
+    let from_ddb_flags = [
+        ("--tee", args.tee.is_some()),
+        ("--max-records", args.max_records.is_some()),
+        ("--max-bytes", args.max_bytes.is_some()),
+        ("--flatten", args.flatten.is_some()),
+        ("--presence", !args.presence.is_empty()),
+        ("--coerce", !args.coerce.is_empty()),
+        ("--pass-through-normal", args.pass_through_normal),
+    ];
+    if !is_from_ddb {
+        if let Some((flag, _)) = from_ddb_flags.iter().find(|(_, is_set)| *is_set) {
+            eprintln!("{flag} only applies to from-ddb mode");
+            std::process::exit(1);
         }
+    }
+    if args.route_by_table.is_some()
+        && (!is_from_ddb || args.output.is_some() || args.tee.is_some())
+    {
+        eprintln!("--route-by-table only applies to from-ddb mode, without --output and --tee");
+        std::process::exit(1);
+    }
+    if args.length_prefix.is_some()
+        && (!is_from_ddb || args.tee.is_some() || args.route_by_table.is_some())
+    {
+        eprintln!(
+            "--length-prefix only applies to from-ddb mode, without --tee and --route-by-table"
+        );
+        std::process::exit(1);
+    }
+
+    let unknown_type_policy = if args.passthrough_unknown_types {
+        UnknownTypePolicy::PassthroughTagged
+    } else {
+        UnknownTypePolicy::Error
+    };
+
+    let tolerance = if args.pass_through_normal {
+        Tolerance::PassThroughNormal
+    } else {
+        Tolerance::Strict
+    };
+
+    let type_coercions: Vec<(&str, CoerceTo)> = args
+        .coerce
+        .iter()
+        .map(|rule| parse_coercion(rule))
+        .collect();
+
+    let invalid_utf8_names = match args.invalid_utf8_names {
+        InvalidUtf8Names::Copy => InvalidUtf8Policy::Copy,
+        InvalidUtf8Names::Error => InvalidUtf8Policy::Error,
+        InvalidUtf8Names::Replace => InvalidUtf8Policy::Replace,
+    };
+
+    let number_output = if args.normalize_integers {
+        NumberOutput::NormalizeIntegers
+    } else {
+        NumberOutput::AsIs
+    };
+
+    let presence_fields: Vec<&str> = args.presence.iter().map(String::as_str).collect();
+
+    let mut to_normal_options = DdbToNormalOptions::new();
+    to_normal_options.pretty = args.pretty;
+    to_normal_options.unbuffered = args.unbuffered;
+    to_normal_options.unknown_type_policy = unknown_type_policy;
+    to_normal_options.reject_empty_sets = args.reject_empty_sets;
+    to_normal_options.max_set_elements = args.max_set_elements;
+    to_normal_options.type_coercions = &type_coercions;
+    to_normal_options.tolerance = tolerance;
+    to_normal_options.presence_fields = &presence_fields;
+    to_normal_options.flatten = args.flatten;
+    to_normal_options.invalid_utf8_names = invalid_utf8_names;
+    to_normal_options.number_output = number_output;
+    to_normal_options.limit = Limit {
+        max_records: args.max_records,
+        max_bytes: args.max_bytes,
+    };
+    let mut to_ddb_options = NormalToDdbOptions::new();
+    to_ddb_options.pretty = args.pretty;
+    to_ddb_options.unbuffered = args.unbuffered;
+    to_ddb_options.with_item_wrapper = !args.without_item;
+    to_ddb_options.unknown_type_policy = unknown_type_policy;
+    to_ddb_options.null_policy = match args.null_policy {
+        NullValues::Emit => NullPolicy::EmitNull,
+        NullValues::Omit => NullPolicy::OmitAttribute,
+        NullValues::EmptyString => NullPolicy::EmptyString,
     };
 
     let result = match args.mode {
         ConversionMode::FromDdb => {
-            convert_from_ddb(&mut input_reader, &mut output_writer, args.pretty, args.unbuffered)
+            let options = &to_normal_options;
+            if args.dry_run {
+                plan_from_ddb(&mut input_reader, options)
+            } else if let Some(dir) = &args.route_by_table {
+                convert_from_ddb_routed(&mut input_reader, dir.clone(), options)
+            } else if let Some(format) = args.length_prefix {
+                let prefix = match format {
+                    LengthPrefixFormat::Ascii => LengthPrefix::AsciiDecimal,
+                    LengthPrefixFormat::U32le => LengthPrefix::U32Le,
+                };
+                let mut output_writer = open_output(&args);
+                convert_from_ddb_framed(&mut input_reader, &mut output_writer, prefix, options)
+            } else if let Some(tee_path) = &args.tee {
+                let mut output_writer = open_output(&args);
+                let mut rawcopy_writer = FromStd::new(BufWriter::new(create_output_file(tee_path)));
+                convert_from_ddb_tee(
+                    &mut input_reader,
+                    &mut output_writer,
+                    &mut rawcopy_writer,
+                    options,
+                )
+            } else {
+                let mut output_writer = open_output(&args);
+                convert_from_ddb(&mut input_reader, &mut output_writer, options)
+            }
         }
         ConversionMode::ToDdb => {
-            convert_to_ddb(
+            let mut output_writer = open_output(&args);
+            convert_to_ddb(&mut input_reader, &mut output_writer, to_ddb_options)
+        }
+        ConversionMode::Auto => {
+            let mut output_writer = open_output(&args);
+            convert_either(
                 &mut input_reader,
                 &mut output_writer,
-                args.pretty,
-                args.unbuffered,
-                !args.without_item,
+                &to_normal_options,
+                to_ddb_options,
             )
         }
     };
 
     if let Err((e, position)) = result {
-        eprintln!("Error at position {position}: {e}");
+        match e.line_position() {
+            Some(line_position) => {
+                eprintln!("Error at {line_position} (position {position}): {e}");
+            }
+            None => eprintln!("Error at position {position}: {e}"),
+        }
         std::process::exit(1);
     }
 }
 
+/// The output file or stdout, not created in the dry-run mode
+fn open_output(args: &Args) -> Box<dyn embedded_io::Write<Error = std::io::Error>> {
+    let output_channel: Box<dyn io::Write> = if let Some(output_path) = &args.output {
+        Box::new(create_output_file(output_path))
+    } else {
+        Box::new(io::stdout())
+    };
+    if args.unbuffered {
+        Box::new(FromStd::new(output_channel))
+    } else {
+        Box::new(FromStd::new(BufWriter::new(output_channel)))
+    }
+}
+
 fn open_input_file(path: &str) -> std::fs::File {
     std::fs::File::open(path).unwrap_or_else(|e| {
         eprintln!("Error opening input file '{path}': {e}");
@@ -0,0 +1,230 @@
+//! Conversion metrics: counters updated during conversion
+
+use embedded_io::{Read as IoRead, Write as IoWrite};
+
+/// Number of attribute values per `DynamoDB` type descriptor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AttributeCounts {
+    /// `S` (string)
+    pub s: u64,
+    /// `N` (number)
+    pub n: u64,
+    /// `B` (binary)
+    pub b: u64,
+    /// `BOOL`
+    pub boolean: u64,
+    /// `NULL`
+    pub null: u64,
+    /// `M` (map)
+    pub m: u64,
+    /// `L` (list)
+    pub l: u64,
+    /// `SS` (string set)
+    pub ss: u64,
+    /// `NS` (number set)
+    pub ns: u64,
+    /// `BS` (binary set)
+    pub bs: u64,
+    /// Unknown type descriptors, see `UnknownTypePolicy`
+    pub unknown: u64,
+}
+
+impl AttributeCounts {
+    /// Count a type descriptor
+    pub(crate) fn count(&mut self, type_key: &[u8]) {
+        let counter = match type_key {
+            b"S" => &mut self.s,
+            b"N" => &mut self.n,
+            b"B" => &mut self.b,
+            b"BOOL" => &mut self.boolean,
+            b"NULL" => &mut self.null,
+            b"M" => &mut self.m,
+            b"L" => &mut self.l,
+            b"SS" => &mut self.ss,
+            b"NS" => &mut self.ns,
+            b"BS" => &mut self.bs,
+            _ => &mut self.unknown,
+        };
+        *counter += 1;
+    }
+
+    /// Add the counters of `other`
+    pub(crate) fn add(&mut self, other: &Self) {
+        self.s += other.s;
+        self.n += other.n;
+        self.b += other.b;
+        self.boolean += other.boolean;
+        self.null += other.null;
+        self.m += other.m;
+        self.l += other.l;
+        self.ss += other.ss;
+        self.ns += other.ns;
+        self.bs += other.bs;
+        self.unknown += other.unknown;
+    }
+
+    /// The counters with the names of the type descriptors
+    #[must_use]
+    pub fn by_type(&self) -> [(&'static str, u64); 11] {
+        [
+            ("S", self.s),
+            ("N", self.n),
+            ("B", self.b),
+            ("BOOL", self.boolean),
+            ("NULL", self.null),
+            ("M", self.m),
+            ("L", self.l),
+            ("SS", self.ss),
+            ("NS", self.ns),
+            ("BS", self.bs),
+            ("unknown", self.unknown),
+        ]
+    }
+}
+
+/// Conversion metrics.
+///
+/// The conversion functions with metrics add to the counters,
+/// therefore one `Metrics` can accumulate several conversions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// Number of converted top-level records
+    pub records: u64,
+    /// Number of records in the top-level `Item` wrapper, see `ItemWrapperMode`
+    pub wrapped_records: u64,
+    /// Number of attribute values per type descriptor
+    pub attributes: AttributeCounts,
+    /// Number of failed conversions
+    pub errors: u64,
+    /// Number of bytes read from the input
+    pub bytes_in: u64,
+    /// Number of bytes written to the output
+    pub bytes_out: u64,
+    /// Number of elements in `SS`, `NS` and `BS` sets
+    pub set_elements: u64,
+    /// The most elements in one set
+    pub max_set_len: u64,
+    /// The longest `S` or `B` value or string set element, in input bytes between the quotes.
+    /// With escapes, it is more than the length of the decoded string.
+    pub max_string_len: u64,
+}
+
+impl Metrics {
+    /// Creates zero metrics
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render the metrics in the Prometheus text format to `buf`.
+    ///
+    /// # Errors
+    /// Returns `core::fmt::Error` if the buffer is too small.
+    ///
+    /// # Returns
+    /// The number of bytes written
+    #[cfg(feature = "std")]
+    pub fn render_prometheus(&self, buf: &mut [u8]) -> Result<usize, core::fmt::Error> {
+        use core::fmt::Write as _;
+
+        let mut out = SliceWriter { buf, len: 0 };
+        for (name, help, value) in [
+            ("records", "Converted records", self.records),
+            ("errors", "Failed conversions", self.errors),
+            ("bytes_in", "Bytes read from the input", self.bytes_in),
+            ("bytes_out", "Bytes written to the output", self.bytes_out),
+            (
+                "set_elements",
+                "Elements of SS, NS and BS sets",
+                self.set_elements,
+            ),
+        ] {
+            writeln!(out, "# HELP ddb_convert_{name}_total {help}")?;
+            writeln!(out, "# TYPE ddb_convert_{name}_total counter")?;
+            writeln!(out, "ddb_convert_{name}_total {value}")?;
+        }
+        writeln!(
+            out,
+            "# HELP ddb_convert_attributes_total Attribute values per type descriptor"
+        )?;
+        writeln!(out, "# TYPE ddb_convert_attributes_total counter")?;
+        for (type_name, value) in self.attributes.by_type() {
+            writeln!(
+                out,
+                "ddb_convert_attributes_total{{type=\"{type_name}\"}} {value}"
+            )?;
+        }
+        for (name, help, value) in [
+            ("max_set_len", "Most elements in one set", self.max_set_len),
+            (
+                "max_string_len_bytes",
+                "Longest string value in input bytes",
+                self.max_string_len,
+            ),
+        ] {
+            writeln!(out, "# HELP ddb_convert_{name} {help}")?;
+            writeln!(out, "# TYPE ddb_convert_{name} gauge")?;
+            writeln!(out, "ddb_convert_{name} {value}")?;
+        }
+        Ok(out.len)
+    }
+}
+
+/// `fmt::Write` to a byte slice, fails if the slice is full
+#[cfg(feature = "std")]
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Write for SliceWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        let dst = self.buf.get_mut(self.len..end).ok_or(core::fmt::Error)?;
+        dst.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Reader that counts the read bytes
+pub(crate) struct CountingReader<'a, R: IoRead> {
+    pub(crate) reader: &'a mut R,
+    pub(crate) count: u64,
+}
+
+impl<R: IoRead> embedded_io::ErrorType for CountingReader<'_, R> {
+    type Error = R::Error;
+}
+
+impl<R: IoRead> IoRead for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.reader.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Writer that counts the written bytes
+pub(crate) struct CountingWriter<'a, W: IoWrite> {
+    pub(crate) writer: &'a mut W,
+    pub(crate) count: u64,
+}
+
+impl<W: IoWrite> embedded_io::ErrorType for CountingWriter<'_, W> {
+    type Error = W::Error;
+}
+
+impl<W: IoWrite> IoWrite for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = self.writer.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::ConversionError;
+use crate::{ConversionError, UnknownTypePolicy, TAGGED_TYPE_PREFIX};
 use core::cell::RefCell;
 use embedded_io::{Error as IoError, Read as IoRead, Write as IoWrite};
 use rjiter::jiter::Peek;
@@ -8,6 +8,51 @@ use scan_json::stack::ContextIter;
 use scan_json::{scan, Action, EndAction, Options, StreamOp};
 use u8pool::U8Pool;
 
+/// How to convert JSON `null` values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullPolicy {
+    /// Write the `DynamoDB` NULL type, `{"NULL":true}`
+    EmitNull,
+    /// Drop the attribute, as if it were not in the object.
+    /// Elements of arrays and top-level values are not attributes, they are written as NULL.
+    OmitAttribute,
+    /// Write an empty string, `{"S":""}`, for tables which don't use the NULL type
+    EmptyString,
+}
+
+/// Options for `convert_normal_to_ddb_with_options`
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct NormalToDdbOptions {
+    /// Whether to pretty-print the output
+    pub pretty: bool,
+    /// Whether to flush after every write
+    pub unbuffered: bool,
+    /// Whether to wrap the output in an "Item" key
+    pub with_item_wrapper: bool,
+    /// How to handle objects produced by `UnknownTypePolicy::PassthroughTagged`.
+    /// With `Error`, such objects are converted as usual maps.
+    pub unknown_type_policy: UnknownTypePolicy,
+    /// How to convert `null` values
+    pub null_policy: NullPolicy,
+}
+
+impl NormalToDdbOptions {
+    /// Creates default options: compact output, buffered, with "Item" wrapper,
+    /// no restoring of tagged type descriptors, `null` as the NULL type
+    #[allow(clippy::new_without_default)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            pretty: false,
+            unbuffered: false,
+            with_item_wrapper: true,
+            unknown_type_policy: UnknownTypePolicy::Error,
+            null_policy: NullPolicy::EmitNull,
+        }
+    }
+}
+
 pub struct NormalToDdbConverter<'a, 'workbuf, W: IoWrite> {
     writer: &'a mut W,
     pending_comma: bool,
@@ -16,19 +61,23 @@ pub struct NormalToDdbConverter<'a, 'workbuf, W: IoWrite> {
     current_field: Option<&'workbuf [u8]>,
     pretty: bool,
     depth: usize,
+    unknown_type_policy: UnknownTypePolicy,
+    null_policy: NullPolicy,
     last_error: Option<ConversionError>,
 }
 
 impl<'a, W: IoWrite> NormalToDdbConverter<'a, '_, W> {
-    fn new(writer: &'a mut W, with_item_wrapper: bool, pretty: bool, unbuffered: bool) -> Self {
+    fn new(writer: &'a mut W, options: NormalToDdbOptions) -> Self {
         Self {
             writer,
             pending_comma: false,
-            with_item_wrapper,
-            unbuffered,
+            with_item_wrapper: options.with_item_wrapper,
+            unbuffered: options.unbuffered,
             current_field: None,
-            pretty,
+            pretty: options.pretty,
             depth: 0,
+            unknown_type_policy: options.unknown_type_policy,
+            null_policy: options.null_policy,
             last_error: None,
         }
     }
@@ -166,13 +215,20 @@ fn on_root_array_end<W: IoWrite>(baton: NormalToDdbBaton<'_, '_, W>) -> Result<(
 }
 
 fn on_field_key<R: embedded_io::Read, W: IoWrite>(
-    _rjiter: &mut RJiter<R>,
+    rjiter: &mut RJiter<R>,
     baton: NormalToDdbBaton<'_, '_, W>,
 ) -> StreamOp {
     let mut conv = baton.borrow_mut();
     let Some(field_name) = conv.current_field else {
         return StreamOp::Error("Internal error: current_field not set (impossible)");
     };
+    if conv.null_policy == NullPolicy::OmitAttribute && rjiter.peek().ok() == Some(Peek::Null) {
+        // Drop the attribute: nothing is written, the comma stays pending
+        if rjiter.known_null().is_err() {
+            return StreamOp::Error("Failed to read null value");
+        }
+        return StreamOp::ValueIsConsumed;
+    }
     if let Err(e) = conv.write_comma() {
         return StreamOp::Error(e);
     }
@@ -207,7 +263,7 @@ fn on_string_value_toddb<R: embedded_io::Read, W: IoWrite>(
     if let Err(e) = conv.try_write_any(b"\"S\":\"", "writing S type opening") {
         return StreamOp::Error(e);
     }
-    if let Err(_) = rjiter.write_long_bytes(conv.writer) {
+    if rjiter.write_long_bytes(conv.writer).is_err() {
         return StreamOp::Error("Failed to write string value");
     }
     if conv.unbuffered {
@@ -293,7 +349,11 @@ fn on_null_value_toddb<R: embedded_io::Read, W: IoWrite>(
     if let Err(e) = conv.indent() {
         return StreamOp::Error(e);
     }
-    if let Err(e) = conv.try_write_any(b"\"NULL\":true", "writing NULL type") {
+    let null_value = match conv.null_policy {
+        NullPolicy::EmptyString => b"\"S\":\"\"".as_slice(),
+        NullPolicy::EmitNull | NullPolicy::OmitAttribute => b"\"NULL\":true".as_slice(),
+    };
+    if let Err(e) = conv.try_write_any(null_value, "writing NULL type") {
         return StreamOp::Error(e);
     }
     if let Err(e) = conv.newline() {
@@ -466,10 +526,14 @@ fn on_array_element_object<R: embedded_io::Read, W: IoWrite>(
 }
 
 fn on_nested_object_begin_toddb<R: embedded_io::Read, W: IoWrite>(
-    _rjiter: &mut RJiter<R>,
+    rjiter: &mut RJiter<R>,
     baton: NormalToDdbBaton<'_, '_, W>,
 ) -> StreamOp {
     let mut conv = baton.borrow_mut();
+    if conv.unknown_type_policy == UnknownTypePolicy::PassthroughTagged && is_tagged_object(rjiter)
+    {
+        return on_tagged_object_toddb(rjiter, &mut conv);
+    }
     if let Err(e) = conv.indent() {
         return StreamOp::Error(e);
     }
@@ -484,6 +548,107 @@ fn on_nested_object_begin_toddb<R: embedded_io::Read, W: IoWrite>(
     StreamOp::None
 }
 
+/// Check if the object at the current position starts with a `"$ddb:..."` key
+fn is_tagged_object<R: embedded_io::Read>(rjiter: &mut RJiter<R>) -> bool {
+    let Ok(head) = rjiter.lookahead_while(|b| b == b'{' || b.is_ascii_whitespace()) else {
+        return false;
+    };
+    // `head` is the opening brace with the whitespace around it, the first key follows
+    let head_len = head.len();
+    let Ok(lookahead) = rjiter.lookahead_n(head_len + 1 + TAGGED_TYPE_PREFIX.len()) else {
+        return false;
+    };
+    lookahead.get(head_len) == Some(&b'"')
+        && lookahead.get(head_len + 1..) == Some(TAGGED_TYPE_PREFIX)
+}
+
+/// Restore `{"$ddb:X": value}` as the type descriptor `"X": value`
+fn on_tagged_object_toddb<R: embedded_io::Read, W: IoWrite>(
+    rjiter: &mut RJiter<R>,
+    conv: &mut NormalToDdbConverter<'_, '_, W>,
+) -> StreamOp {
+    let mut type_buf = [0u8; 32];
+    let type_len = match rjiter.next_object_bytes() {
+        Ok(Some(key)) => {
+            let type_key = key.get(TAGGED_TYPE_PREFIX.len()..).unwrap_or_default();
+            if type_key.is_empty() || type_key.len() > type_buf.len() {
+                conv.last_error = Some(ConversionError::ParseError {
+                    context: "Invalid tagged type descriptor",
+                    unknown_type: None,
+                    invalid_utf8: None,
+                });
+                return StreamOp::Error("Invalid tagged type descriptor");
+            }
+            if let Some(dst) = type_buf.get_mut(..type_key.len()) {
+                dst.copy_from_slice(type_key);
+            }
+            type_key.len()
+        }
+        _ => return StreamOp::Error("Failed to read tagged type key"),
+    };
+
+    if let Err(e) = conv.indent() {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(b"\"", "writing tagged type opening quote") {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(
+        type_buf.get(..type_len).unwrap_or_default(),
+        "writing tagged type descriptor",
+    ) {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(b"\":", "writing tagged type closing quote and colon") {
+        return StreamOp::Error(e);
+    }
+
+    // The value can be of any shape, copy it as is
+    let mut value_buffer = [0u8; 512];
+    let Ok(mut value_context) = U8Pool::new(&mut value_buffer, 32) else {
+        return StreamOp::Error("Failed to create context pool for tagged value");
+    };
+    if let Err(e) = scan_json::idtransform(rjiter, conv.writer, &mut value_context) {
+        conv.last_error = Some(ConversionError::ScanError(e));
+        return StreamOp::Error("Failed to copy tagged value");
+    }
+    if conv.unbuffered {
+        if let Err(e) = conv.writer.flush() {
+            conv.last_error = Some(ConversionError::IOError {
+                kind: e.kind(),
+                context: "flushing after copying tagged value",
+            });
+            return StreamOp::Error("Failed to flush writer");
+        }
+    }
+
+    match rjiter.next_key_bytes() {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            conv.last_error = Some(ConversionError::ParseError {
+                context: "Tagged type object must have exactly one key",
+                unknown_type: None,
+                invalid_utf8: None,
+            });
+            return StreamOp::Error("Tagged type object must have exactly one key");
+        }
+        Err(_) => return StreamOp::Error("Failed to read end of tagged type object"),
+    }
+
+    if let Err(e) = conv.newline() {
+        return StreamOp::Error(e);
+    }
+    conv.depth -= 1;
+    if let Err(e) = conv.indent() {
+        return StreamOp::Error(e);
+    }
+    if let Err(e) = conv.try_write_any(b"}", "writing closing brace") {
+        return StreamOp::Error(e);
+    }
+    conv.pending_comma = true;
+    StreamOp::ValueIsConsumed
+}
+
 #[allow(clippy::unnecessary_wraps)]
 fn on_root_object_end<W: IoWrite>(baton: NormalToDdbBaton<'_, '_, W>) -> Result<(), &'static str> {
     let mut conv = baton.borrow_mut();
@@ -710,6 +875,8 @@ fn find_end_action<'a, 'workbuf, W: IoWrite>(
 /// Convert normal JSON to `DynamoDB` JSON in a streaming manner.
 /// Supports JSONL format (newline-delimited JSON) - processes multiple JSON objects.
 ///
+/// A shortcut for `convert_normal_to_ddb_with_options`.
+///
 /// # Arguments
 /// * `reader` - Input stream implementing `embedded_io::Read`
 /// * `writer` - Output stream implementing `embedded_io::Write`
@@ -736,10 +903,45 @@ pub fn convert_normal_to_ddb<R: IoRead, W: IoWrite>(
     pretty: bool,
     unbuffered: bool,
     with_item_wrapper: bool,
+) -> Result<(), (ConversionError, usize)> {
+    let options = NormalToDdbOptions {
+        pretty,
+        unbuffered,
+        with_item_wrapper,
+        ..NormalToDdbOptions::new()
+    };
+    convert_normal_to_ddb_with_options(reader, writer, rjiter_buffer, context_buffer, &options)
+}
+
+/// Convert normal JSON to `DynamoDB` JSON in a streaming manner.
+/// Supports JSONL format (newline-delimited JSON) - processes multiple JSON objects.
+///
+/// # Arguments
+/// * `reader` - Input stream implementing `embedded_io::Read`
+/// * `writer` - Output stream implementing `embedded_io::Write`
+/// * `rjiter_buffer` - Buffer for rjiter to use (recommended: 4096 bytes)
+/// * `context_buffer` - Buffer for `scan_json` context tracking (recommended: 2048 bytes)
+/// * `options` - Conversion options, see `NormalToDdbOptions`
+///
+/// # Errors
+/// Returns `ConversionError` if:
+/// - Input JSON is malformed or invalid
+/// - Input contains data types that cannot be represented in `DynamoDB` format
+/// - I/O errors occur during reading or writing
+/// - Buffer sizes are insufficient for the input data
+///
+/// # Returns
+/// `Ok(())` on success, or `Err((ConversionError, position))` with detailed error information on failure
+pub fn convert_normal_to_ddb_with_options<R: IoRead, W: IoWrite>(
+    reader: &mut R,
+    writer: &mut W,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &NormalToDdbOptions,
 ) -> Result<(), (ConversionError, usize)> {
     let mut rjiter = RJiter::new(reader, rjiter_buffer);
 
-    let converter = NormalToDdbConverter::new(writer, with_item_wrapper, pretty, unbuffered);
+    let converter = NormalToDdbConverter::new(writer, *options);
     let baton = RefCell::new(converter);
 
     // DynamoDB supports up to 32 levels of nesting.
@@ -767,27 +969,11 @@ pub fn convert_normal_to_ddb<R: IoRead, W: IoWrite>(
         let stored_error = baton.borrow_mut().last_error.take();
         if let Some(err) = stored_error {
             // Extract position from scan_json's error - scan_json provides accurate position
-            let position = match &e {
-                scan_json::Error::ActionError { position, .. } => *position,
-                scan_json::Error::MaxNestingExceeded { position, .. } => *position,
-                scan_json::Error::InternalError { position, .. } => *position,
-                scan_json::Error::UnhandledPeek { position, .. } => *position,
-                scan_json::Error::UnbalancedJson(position) => *position,
-                scan_json::Error::RJiterError(e) => e.index,
-                scan_json::Error::IOError(_) => rjiter.current_index(),
-            };
+            let position = e.byte_position().unwrap_or_else(|| rjiter.current_index());
             return Err((err, position));
         }
         // Otherwise return the scan error (which includes position)
-        let position = match &e {
-            scan_json::Error::ActionError { position, .. } => *position,
-            scan_json::Error::MaxNestingExceeded { position, .. } => *position,
-            scan_json::Error::InternalError { position, .. } => *position,
-            scan_json::Error::UnhandledPeek { position, .. } => *position,
-            scan_json::Error::UnbalancedJson(position) => *position,
-            scan_json::Error::RJiterError(e) => e.index,
-            scan_json::Error::IOError(_) => rjiter.current_index(),
-        };
+        let position = e.byte_position().unwrap_or_else(|| rjiter.current_index());
         return Err((ConversionError::ScanError(e), position));
     }
 
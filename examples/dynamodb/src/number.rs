@@ -0,0 +1,130 @@
+//! Streaming normalization of `N` values, see `NumberOutput::NormalizeIntegers`
+
+use embedded_io::{ErrorType, Write as IoWrite};
+
+/// Which part of the number is being read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Part {
+    Integer,
+    Fraction,
+    Verbatim, // The exponent or unexpected bytes are copied as is
+}
+
+/// A writer which normalizes the number written to it, byte by byte:
+/// removes the leading zeros of the integer part and the trailing zeros
+/// of the fraction, and the fraction itself if it is zero.
+/// For example, `007` becomes `7`, `42.0` becomes `42`, `-0.50` becomes `-0.5`.
+///
+/// Call `finish` after the last byte of the number.
+pub(crate) struct NumberNormalizer<'w, W: IoWrite> {
+    writer: &'w mut W,
+    part: Part,
+    negative: bool,
+    started: bool,         // The sign and the first significant digit are written
+    has_fraction: bool,    // The decimal point is written
+    fraction_zeros: usize, // Zeros of the fraction which are not written yet
+}
+
+impl<'w, W: IoWrite> NumberNormalizer<'w, W> {
+    pub(crate) fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            part: Part::Integer,
+            negative: false,
+            started: false,
+            has_fraction: false,
+            fraction_zeros: 0,
+        }
+    }
+
+    fn start(&mut self) -> Result<(), W::Error> {
+        if !self.started {
+            self.started = true;
+            if self.negative {
+                self.writer.write_all(b"-")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `0` if no digits are written, the integer part is zero
+    fn write_zero_if_not_started(&mut self) -> Result<(), W::Error> {
+        if !self.started {
+            self.started = true;
+            self.writer.write_all(b"0")?;
+        }
+        Ok(())
+    }
+
+    fn write_fraction_digit(&mut self, digit: u8) -> Result<(), W::Error> {
+        const ZEROS: [u8; 16] = [b'0'; 16];
+        if !self.started {
+            self.start()?;
+            self.writer.write_all(b"0")?;
+        }
+        if !self.has_fraction {
+            self.has_fraction = true;
+            self.writer.write_all(b".")?;
+        }
+        while self.fraction_zeros > 0 {
+            let n = self.fraction_zeros.min(ZEROS.len());
+            self.writer.write_all(ZEROS.get(..n).unwrap_or(&[]))?;
+            self.fraction_zeros -= n;
+        }
+        self.writer.write_all(&[digit])
+    }
+
+    /// Copy the rest of the number as is
+    fn begin_verbatim(&mut self, byte: u8) -> Result<(), W::Error> {
+        self.write_zero_if_not_started()?;
+        self.part = Part::Verbatim;
+        self.writer.write_all(&[byte])
+    }
+
+    /// Write the pending digits. The number is complete.
+    pub(crate) fn finish(mut self) -> Result<(), W::Error> {
+        self.write_zero_if_not_started()
+    }
+}
+
+impl<W: IoWrite> ErrorType for NumberNormalizer<'_, W> {
+    type Error = W::Error;
+}
+
+impl<W: IoWrite> IoWrite for NumberNormalizer<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let mut rest = buf;
+        while let Some((&byte, tail)) = rest.split_first() {
+            match (self.part, byte) {
+                (Part::Verbatim, _) => {
+                    self.writer.write_all(rest)?;
+                    break;
+                }
+                (Part::Integer, b'0'..=b'9') if self.started => {
+                    // Copy the run of digits at once
+                    let n = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                    let (digits, tail) = rest.split_at(n);
+                    self.writer.write_all(digits)?;
+                    rest = tail;
+                    continue;
+                }
+                (Part::Integer, b'-') if !self.started && !self.negative => self.negative = true,
+                (Part::Integer, b'0') => {} // A leading zero
+                (Part::Fraction, b'0') => self.fraction_zeros += 1,
+                (Part::Integer, b'1'..=b'9') => {
+                    self.start()?;
+                    self.writer.write_all(&[byte])?;
+                }
+                (Part::Integer, b'.') => self.part = Part::Fraction,
+                (Part::Fraction, b'1'..=b'9') => self.write_fraction_digit(byte)?,
+                (_, _) => self.begin_verbatim(byte)?,
+            }
+            rest = tail;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush()
+    }
+}
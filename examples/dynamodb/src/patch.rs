@@ -0,0 +1,353 @@
+//! JSON Patch (RFC 6902) between two exports converted to normal JSON
+//!
+//! Both inputs are streams of records (one object per line, as written by
+//! `convert_ddb_to_normal`), sorted by the key attribute. Only one record
+//! of each input is in memory at a time.
+
+use crate::key::{parse_error, rjiter_error, write_all};
+use crate::ConversionError;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use embedded_io::{Read as IoRead, Write as IoWrite};
+use rjiter::jiter::Peek;
+use rjiter::RJiter;
+
+type PatchResult<T> = Result<T, (ConversionError, usize)>;
+
+// A record: the compact JSON text of the key value and the attributes sorted by name.
+// Names are kept as in the input (with JSON escapes), values are compact JSON text.
+struct Record {
+    key: Vec<u8>,
+    attributes: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+// Adapter to write `RJiter` output into a vector
+struct VecWriter<'a>(&'a mut Vec<u8>);
+
+impl embedded_io::ErrorType for VecWriter<'_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl IoWrite for VecWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn push_name(out: &mut Vec<u8>, name: &[u8]) {
+    out.push(b'"');
+    out.extend_from_slice(name);
+    out.extend_from_slice(b"\":");
+}
+
+// Copy the next value as compact JSON text. Strings are copied with their escapes.
+fn copy_value<R: IoRead>(rjiter: &mut RJiter<R>, out: &mut Vec<u8>) -> PatchResult<()> {
+    let err = |e: rjiter::Error| rjiter_error(&e, "reading attribute value");
+    // For each open container: `true` for an array, `false` for an object
+    let mut stack: Vec<bool> = Vec::new();
+    let mut peek = rjiter.peek().map_err(err)?;
+    loop {
+        match peek {
+            Peek::Object => {
+                out.push(b'{');
+                if let Some(name) = rjiter.next_object_bytes().map_err(err)? {
+                    push_name(out, name);
+                    stack.push(false);
+                    peek = rjiter.peek().map_err(err)?;
+                    continue;
+                }
+                out.push(b'}');
+            }
+            Peek::Array => {
+                out.push(b'[');
+                if let Some(item) = rjiter.known_array().map_err(err)? {
+                    stack.push(true);
+                    peek = item;
+                    continue;
+                }
+                out.push(b']');
+            }
+            Peek::String => {
+                out.push(b'"');
+                rjiter.write_long_bytes(&mut VecWriter(out)).map_err(err)?;
+                out.push(b'"');
+            }
+            Peek::Null => {
+                rjiter.known_null().map_err(err)?;
+                out.extend_from_slice(b"null");
+            }
+            Peek::True | Peek::False => {
+                let value = rjiter.known_bool(peek).map_err(err)?;
+                out.extend_from_slice(if value { b"true" } else { b"false" });
+            }
+            _ => out.extend_from_slice(rjiter.next_number_bytes().map_err(err)?),
+        }
+
+        // The value is complete: close the finished containers, find the next value
+        loop {
+            match stack.last() {
+                None => return Ok(()),
+                Some(true) => {
+                    if let Some(item) = rjiter.array_step().map_err(err)? {
+                        out.push(b',');
+                        peek = item;
+                        break;
+                    }
+                    out.push(b']');
+                }
+                Some(false) => {
+                    if let Some(name) = rjiter.next_key_bytes().map_err(err)? {
+                        out.push(b',');
+                        push_name(out, name);
+                        peek = rjiter.peek().map_err(err)?;
+                        break;
+                    }
+                    out.push(b'}');
+                }
+            }
+            stack.pop();
+        }
+    }
+}
+
+// Read the next record, or `None` at the end of the input
+fn read_record<R: IoRead>(
+    rjiter: &mut RJiter<R>,
+    key_attribute: &[u8],
+) -> PatchResult<Option<Record>> {
+    if rjiter.finish().is_ok() {
+        return Ok(None);
+    }
+    let position = rjiter.current_index();
+    let mut attributes: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    let mut name = rjiter
+        .next_object_bytes()
+        .map_err(|e| rjiter_error(&e, "reading record"))?
+        .map(<[u8]>::to_vec);
+    while let Some(attribute_name) = name {
+        let mut value = Vec::new();
+        copy_value(rjiter, &mut value)?;
+        attributes.push((attribute_name, value));
+        name = rjiter
+            .next_key_bytes()
+            .map_err(|e| rjiter_error(&e, "reading record"))?
+            .map(<[u8]>::to_vec);
+    }
+    attributes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let key = attributes
+        .iter()
+        .find(|(name, _)| name == key_attribute)
+        .map(|(_, value)| value.clone())
+        .ok_or_else(|| parse_error("Record without the key attribute", position))?;
+    Ok(Some(Record { key, attributes }))
+}
+
+// Read the next record and check that the keys increase
+fn next_record<R: IoRead>(
+    rjiter: &mut RJiter<R>,
+    key_attribute: &[u8],
+    previous: Option<&Record>,
+) -> PatchResult<Option<Record>> {
+    let position = rjiter.current_index();
+    let record = read_record(rjiter, key_attribute)?;
+    if let (Some(previous), Some(record)) = (previous, &record) {
+        if record.key <= previous.key {
+            return Err(parse_error(
+                "Records are not sorted by the key attribute",
+                position,
+            ));
+        }
+    }
+    Ok(record)
+}
+
+// Writes the operations as the elements of a JSON array
+struct PatchWriter<'w, W: IoWrite> {
+    writer: &'w mut W,
+    count: usize,
+}
+
+impl<W: IoWrite> PatchWriter<'_, W> {
+    fn write(&mut self, bytes: &[u8]) -> PatchResult<()> {
+        write_all(self.writer, bytes, "writing patch", 0)
+    }
+
+    // Write a JSON Pointer segment. Keys of the string type are written without the quotes.
+    fn write_segment(&mut self, segment: &[u8]) -> PatchResult<()> {
+        let segment = segment
+            .strip_prefix(b"\"")
+            .and_then(|s| s.strip_suffix(b"\""))
+            .unwrap_or(segment);
+        self.write(b"/")?;
+        for piece in segment.split_inclusive(|b| *b == b'~' || *b == b'/') {
+            match piece.split_last() {
+                Some((b'~', rest)) => {
+                    self.write(rest)?;
+                    self.write(b"~0")?;
+                }
+                Some((b'/', rest)) => {
+                    self.write(rest)?;
+                    self.write(b"~1")?;
+                }
+                _ => self.write(piece)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_op(&mut self, op: &[u8], key: &[u8], attribute: Option<&[u8]>) -> PatchResult<()> {
+        self.write(if self.count == 0 { b"\n" } else { b",\n" })?;
+        self.count += 1;
+        self.write(b"{\"op\":\"")?;
+        self.write(op)?;
+        self.write(b"\",\"path\":\"")?;
+        self.write_segment(key)?;
+        if let Some(attribute) = attribute {
+            self.write_segment(attribute)?;
+        }
+        self.write(b"\"")
+    }
+
+    fn remove(&mut self, key: &[u8], attribute: Option<&[u8]>) -> PatchResult<()> {
+        self.begin_op(b"remove", key, attribute)?;
+        self.write(b"}")
+    }
+
+    fn add_record(&mut self, record: &Record) -> PatchResult<()> {
+        self.begin_op(b"add", &record.key, None)?;
+        self.write(b",\"value\":{")?;
+        for (i, (name, value)) in record.attributes.iter().enumerate() {
+            if i > 0 {
+                self.write(b",")?;
+            }
+            self.write(b"\"")?;
+            self.write(name)?;
+            self.write(b"\":")?;
+            self.write(value)?;
+        }
+        self.write(b"}}")
+    }
+
+    fn set_attribute(
+        &mut self,
+        op: &[u8],
+        key: &[u8],
+        attribute: &[u8],
+        value: &[u8],
+    ) -> PatchResult<()> {
+        self.begin_op(op, key, Some(attribute))?;
+        self.write(b",\"value\":")?;
+        self.write(value)?;
+        self.write(b"}")
+    }
+
+    // The operations for a record which is in both inputs
+    fn diff_records(&mut self, old: &Record, new: &Record) -> PatchResult<()> {
+        let mut old_attributes = old.attributes.iter().peekable();
+        let mut new_attributes = new.attributes.iter().peekable();
+        loop {
+            let order = match (old_attributes.peek(), new_attributes.peek()) {
+                (None, None) => return Ok(()),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((old_name, _)), Some((new_name, _))) => old_name.cmp(new_name),
+            };
+            match order {
+                Ordering::Less => {
+                    if let Some((name, _)) = old_attributes.next() {
+                        self.remove(&old.key, Some(name))?;
+                    }
+                }
+                Ordering::Greater => {
+                    if let Some((name, value)) = new_attributes.next() {
+                        self.set_attribute(b"add", &new.key, name, value)?;
+                    }
+                }
+                Ordering::Equal => {
+                    if let (Some((_, old_value)), Some((name, new_value))) =
+                        (old_attributes.next(), new_attributes.next())
+                    {
+                        if old_value != new_value {
+                            self.set_attribute(b"replace", &new.key, name, new_value)?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Write a JSON Patch (RFC 6902) which turns the records of `old` into the records of `new`.
+///
+/// The inputs are normal JSON records, as written by `convert_ddb_to_normal`,
+/// sorted by the value of `key_attribute` in the byte order of its JSON text
+/// (for string keys, the order of the strings). The patch treats the records as
+/// an object with the record keys as the member names: a new record is `add /key`,
+/// a deleted record is `remove /key`, and a changed attribute is
+/// `add`, `remove` or `replace` of `/key/attribute`.
+///
+/// The values are compared as compact JSON text, therefore nested maps should
+/// have the same member order in both inputs. The output is a JSON array,
+/// one operation per line.
+///
+/// # Arguments
+/// * `old` - Input stream with the old records
+/// * `new` - Input stream with the new records
+/// * `writer` - Output stream for the patch
+/// * `key_attribute` - Name of the attribute which identifies a record, for example, `b"pk"`
+/// * `old_buffer` - Buffer for rjiter of `old`, should fit an attribute name and a number
+/// * `new_buffer` - Buffer for rjiter of `new`, the same as `old_buffer`
+///
+/// Returns the number of the operations.
+///
+/// # Errors
+/// Returns `ConversionError` and the position in the failed input if an input
+/// is not valid JSON, a record has no key attribute, the records are not sorted,
+/// or if I/O errors occur.
+pub fn diff_to_json_patch<R1: IoRead, R2: IoRead, W: IoWrite>(
+    old: &mut R1,
+    new: &mut R2,
+    writer: &mut W,
+    key_attribute: &[u8],
+    old_buffer: &mut [u8],
+    new_buffer: &mut [u8],
+) -> PatchResult<usize> {
+    let mut old_rjiter = RJiter::new(old, old_buffer);
+    let mut new_rjiter = RJiter::new(new, new_buffer);
+    let mut patch = PatchWriter { writer, count: 0 };
+
+    patch.write(b"[")?;
+    let mut old_record = next_record(&mut old_rjiter, key_attribute, None)?;
+    let mut new_record = next_record(&mut new_rjiter, key_attribute, None)?;
+    loop {
+        let order = match (&old_record, &new_record) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(old), Some(new)) => old.key.cmp(&new.key),
+        };
+        if let (Ordering::Less, Some(old)) = (order, &old_record) {
+            patch.remove(&old.key, None)?;
+        }
+        if let (Ordering::Greater, Some(new)) = (order, &new_record) {
+            patch.add_record(new)?;
+        }
+        if let (Ordering::Equal, Some(old), Some(new)) = (order, &old_record, &new_record) {
+            patch.diff_records(old, new)?;
+        }
+        if order != Ordering::Greater {
+            old_record = next_record(&mut old_rjiter, key_attribute, old_record.as_ref())?;
+        }
+        if order != Ordering::Less {
+            new_record = next_record(&mut new_rjiter, key_attribute, new_record.as_ref())?;
+        }
+    }
+    patch.write(if patch.count == 0 { b"]\n" } else { b"\n]\n" })?;
+    Ok(patch.count)
+}
@@ -0,0 +1,191 @@
+//! Dry run: scan the input and report what the conversion would do, without writing
+
+use embedded_io::{Read as IoRead, Write as IoWrite};
+
+use crate::ddb_to_normal::convert_ddb_to_normal_with_metrics;
+use crate::{ConversionError, DdbToNormalOptions, Metrics};
+
+/// Layout of the input, detected from the records and the lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// No records
+    Empty,
+    /// One top-level record
+    Json,
+    /// Several top-level records, one per line
+    JsonLines,
+    /// Several top-level records, not one per line, for example, pretty-printed
+    JsonStream,
+}
+
+/// Use of the top-level `Item` wrapper in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapperUsage {
+    /// No record is wrapped, or there are no records
+    None,
+    /// All the records are wrapped in `{"Item": ...}`
+    Item,
+    /// Some records are wrapped, some are not
+    Mixed,
+}
+
+/// The result of a dry run, see `plan_ddb_to_normal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Plan {
+    /// The counters of the dry run. `bytes_out` is the size of the output
+    /// which the conversion with the same options would write.
+    pub metrics: Metrics,
+    /// The number of input lines with a non-whitespace byte
+    text_lines: u64,
+}
+
+impl Plan {
+    /// The number of records
+    #[must_use]
+    pub fn records(&self) -> u64 {
+        self.metrics.records
+    }
+
+    /// JSON or JSON Lines
+    #[must_use]
+    pub fn format(&self) -> InputFormat {
+        match self.metrics.records {
+            0 => InputFormat::Empty,
+            1 => InputFormat::Json,
+            // A JSON string can't contain a raw newline, therefore
+            // each record is on its own line if the counts are the same
+            n if n == self.text_lines => InputFormat::JsonLines,
+            _ => InputFormat::JsonStream,
+        }
+    }
+
+    /// Whether the records are wrapped in `{"Item": ...}`
+    #[must_use]
+    pub fn wrapper(&self) -> WrapperUsage {
+        match self.metrics.wrapped_records {
+            0 => WrapperUsage::None,
+            n if n == self.metrics.records => WrapperUsage::Item,
+            _ => WrapperUsage::Mixed,
+        }
+    }
+
+    /// The type descriptors which appear in the input, with their counts
+    pub fn attribute_types(&self) -> impl Iterator<Item = (&'static str, u64)> {
+        self.metrics
+            .attributes
+            .by_type()
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+    }
+
+    /// The size of the output in bytes
+    #[must_use]
+    pub fn estimated_output_bytes(&self) -> u64 {
+        self.metrics.bytes_out
+    }
+}
+
+/// The report for operators, one property per line
+#[cfg(feature = "std")]
+impl core::fmt::Display for Plan {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "records: {}", self.records())?;
+        writeln!(f, "format: {:?}", self.format())?;
+        writeln!(f, "item wrapper: {:?}", self.wrapper())?;
+        write!(f, "attribute types:")?;
+        for (type_name, count) in self.attribute_types() {
+            write!(f, " {type_name}={count}")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "input bytes: {}", self.metrics.bytes_in)?;
+        writeln!(f, "max set elements: {}", self.metrics.max_set_len)?;
+        writeln!(f, "max string bytes: {}", self.metrics.max_string_len)?;
+        writeln!(
+            f,
+            "estimated output bytes: {}",
+            self.estimated_output_bytes()
+        )
+    }
+}
+
+/// A writer that discards all written data
+struct Sink;
+
+impl embedded_io::ErrorType for Sink {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl IoWrite for Sink {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Reader that counts the lines with a non-whitespace byte
+struct LineCounter<'a, R: IoRead> {
+    reader: &'a mut R,
+    text_lines: u64,
+    is_line_blank: bool,
+}
+
+impl<R: IoRead> embedded_io::ErrorType for LineCounter<'_, R> {
+    type Error = R::Error;
+}
+
+impl<R: IoRead> IoRead for LineCounter<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.reader.read(buf)?;
+        for byte in buf.get(..n).unwrap_or(&[]) {
+            match byte {
+                b'\n' => self.is_line_blank = true,
+                b' ' | b'\t' | b'\r' => {}
+                _ if self.is_line_blank => {
+                    self.text_lines += 1;
+                    self.is_line_blank = false;
+                }
+                _ => {}
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Scan `DynamoDB` JSON and report what `convert_ddb_to_normal_with_options` would do,
+/// without writing the output.
+///
+/// The input is converted to a sink, therefore the report has the exact output size,
+/// and the input errors are found as by the real conversion. The buffers are the same
+/// as for `convert_ddb_to_normal_with_options`. The hooks and callbacks of `options`
+/// are called as in the conversion.
+///
+/// # Errors
+/// The same as `convert_ddb_to_normal_with_options`, except for the output errors.
+pub fn plan_ddb_to_normal<R: IoRead>(
+    reader: &mut R,
+    rjiter_buffer: &mut [u8],
+    context_buffer: &mut [u8],
+    options: &DdbToNormalOptions,
+) -> Result<Plan, (ConversionError, usize)> {
+    let mut metrics = Metrics::new();
+    let mut line_counter = LineCounter {
+        reader,
+        text_lines: 0,
+        is_line_blank: true,
+    };
+    convert_ddb_to_normal_with_metrics(
+        &mut line_counter,
+        &mut Sink,
+        rjiter_buffer,
+        context_buffer,
+        options,
+        &mut metrics,
+    )?;
+    Ok(Plan {
+        metrics,
+        text_lines: line_counter.text_lines,
+    })
+}
@@ -0,0 +1,129 @@
+//! Routing of the converted records to per-table outputs, see `convert_ddb_to_normal_routed`
+
+use crate::ConversionError;
+use embedded_io::{Error as IoError, Write as IoWrite};
+
+/// Maximum length of a table name, as in `DynamoDB`
+pub const MAX_TABLE_NAME: usize = 255;
+
+/// Selects the output for the records of a table
+pub trait TableRouter {
+    /// The output for the records of a table
+    type Writer: IoWrite;
+
+    /// The index of the output for the records of the table, `None` if the table has no output.
+    ///
+    /// Called once for each record, when its table is known.
+    fn route(&mut self, table_name: &[u8]) -> Option<usize>;
+
+    /// The output with the index from `route`.
+    ///
+    /// Called for each write of a record, therefore should be cheap,
+    /// for example, an index into a slice.
+    fn output(&mut self, index: usize) -> Option<&mut Self::Writer>;
+}
+
+/// A writer which writes a record to the output of its table.
+///
+/// The beginning of a record, before its table is known, is kept in a small buffer.
+/// `select` gives the table of the record and then marks the end of the record.
+pub(crate) struct RoutingWriter<'a, T: TableRouter> {
+    router: &'a mut T,
+    output: Option<usize>, // `None` until the table of the record is known
+    pending: [u8; 8],      // The opening brace and the layout before the table is known
+    pending_len: usize,
+    written: u64, // Bytes of the records, summed over the tables
+}
+
+fn parse_error(context: &'static str) -> ConversionError {
+    ConversionError::ParseError {
+        context,
+        unknown_type: None,
+        invalid_utf8: None,
+    }
+}
+
+impl<'a, T: TableRouter> RoutingWriter<'a, T> {
+    pub(crate) fn new(router: &'a mut T) -> Self {
+        Self {
+            router,
+            output: None,
+            pending: [0; 8],
+            pending_len: 0,
+            written: 0,
+        }
+    }
+
+    /// Number of bytes written to the outputs of all tables
+    pub(crate) fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    fn output(&mut self) -> Option<&mut T::Writer> {
+        self.router.output(self.output?)
+    }
+
+    /// Set the table of the current record and write the pending bytes to its output.
+    /// With `None`, end the record, the next record is routed anew.
+    pub(crate) fn select(&mut self, table_name: Option<&[u8]>) -> Result<(), ConversionError> {
+        let Some(table_name) = table_name else {
+            self.pending_len = 0;
+            if self.output.take().is_none() {
+                return Err(parse_error("Record has no TableName"));
+            }
+            return Ok(());
+        };
+        if self.output.is_some() {
+            return Err(parse_error("Record has more than one TableName"));
+        }
+        if table_name.len() > MAX_TABLE_NAME {
+            return Err(parse_error("TableName is too long"));
+        }
+        self.output = self.router.route(table_name);
+
+        let pending = self.pending;
+        let pending = pending.get(..self.pending_len).unwrap_or(&[]);
+        self.pending_len = 0;
+        let Some(output) = self.output() else {
+            return Err(parse_error("No output for the table of the record"));
+        };
+        output
+            .write_all(pending)
+            .map_err(|e| ConversionError::IOError {
+                kind: e.kind(),
+                context: "writing to the table output",
+            })?;
+        self.written += pending.len() as u64;
+        Ok(())
+    }
+}
+
+impl<T: TableRouter> embedded_io::ErrorType for RoutingWriter<'_, T> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<T: TableRouter> IoWrite for RoutingWriter<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if self.output.is_some() {
+            let output = self.output().ok_or(embedded_io::ErrorKind::NotConnected)?;
+            let n = output.write(buf).map_err(|e| e.kind())?;
+            self.written += n as u64;
+            return Ok(n);
+        }
+        // Only the beginning of a record can come before its table
+        let free = self.pending.get_mut(self.pending_len..).unwrap_or(&mut []);
+        let Some(dst) = free.get_mut(..buf.len()) else {
+            return Err(embedded_io::ErrorKind::NotConnected);
+        };
+        dst.copy_from_slice(buf);
+        self.pending_len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        match self.output() {
+            Some(output) => output.flush().map_err(|e| e.kind()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Tests for `convert_auto`
+
+use ddb_convert::{ConversionError, DdbToNormalOptions, Direction, NormalToDdbOptions};
+
+fn convert_auto_with(
+    input: &str,
+    to_normal: &DdbToNormalOptions,
+) -> Result<(Direction, String), (ConversionError, usize)> {
+    let mut reader = input.as_bytes();
+    let mut output = Vec::new();
+    let mut rjiter_buffer = [0u8; 1024];
+    let mut context_buffer = [0u8; 2048];
+    let direction = ddb_convert::convert_auto(
+        &mut reader,
+        &mut embedded_io_adapters::std::FromStd::new(&mut output),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        to_normal,
+        &NormalToDdbOptions::new(),
+    )?;
+    Ok((direction, String::from_utf8(output).unwrap()))
+}
+
+fn convert_auto(input: &str) -> (Direction, String) {
+    convert_auto_with(input, &DdbToNormalOptions::new()).unwrap()
+}
+
+#[test]
+fn test_auto_detects_ddb_json() {
+    let (direction, output) = convert_auto(r#"{"Item":{"name":{"S":"Alice"},"age":{"N":"30"}}}"#);
+    assert_eq!(direction, Direction::DdbToNormal);
+    assert_eq!(output, "{\"name\":\"Alice\",\"age\":30}\n");
+
+    // Records without attributes are skipped
+    let input = r#"{}
+        {"Item":{}}
+        {"Item":{"tags":{"L":[]}}}"#;
+    let (direction, output) = convert_auto(input);
+    assert_eq!(direction, Direction::DdbToNormal);
+    assert_eq!(output, "{}\n{}\n{\"tags\":[]}\n");
+}
+
+#[test]
+fn test_auto_detects_normal_json() {
+    let (direction, output) = convert_auto(r#"{"name":"Alice","age":30}"#);
+    assert_eq!(direction, Direction::NormalToDdb);
+    assert_eq!(
+        output,
+        "{\"Item\":{\"name\":{\"S\":\"Alice\"},\"age\":{\"N\":\"30\"}}}\n"
+    );
+
+    // Nested objects, "Item" with a normal value, a tagged unknown type
+    for input in [
+        r#"{"address":{"city":"Oslo"}}"#,
+        r#"{"Item":{"count":3}}"#,
+        r#"{"address":{}}"#,
+        r#"{"shape":{"$ddb:X":1}}"#,
+    ] {
+        assert_eq!(convert_auto(input).0, Direction::NormalToDdb, "{input}");
+    }
+}
+
+#[test]
+fn test_auto_with_item_as_field() {
+    let input = r#"{"Item":{"S":"a field named Item"}}"#;
+    let mut options = DdbToNormalOptions::new();
+    options.item_wrapper_mode = ddb_convert::ItemWrapperMode::AsField;
+    let (direction, output) = convert_auto_with(input, &options).unwrap();
+    assert_eq!(direction, Direction::DdbToNormal);
+    assert_eq!(output, "{\"Item\":\"a field named Item\"}\n");
+}
+
+#[test]
+fn test_auto_decides_after_long_prefix() {
+    // The bytes read for the decision are given to the converter
+    let long_name = "x".repeat(300);
+    let input = format!(r#"{{"Item":{{"{long_name}":{{"BOOL":true}}}}}}"#);
+    let (direction, output) = convert_auto(&input);
+    assert_eq!(direction, Direction::DdbToNormal);
+    assert_eq!(output, format!("{{\"{long_name}\":true}}\n"));
+
+    // Undecided in the first 512 bytes: converted as DynamoDB JSON
+    let long_name = "x".repeat(600);
+    let input = format!(r#"{{"Item":{{"{long_name}":{{"BOOL":true}}}}}}"#);
+    assert_eq!(convert_auto(&input).0, Direction::DdbToNormal);
+    let input = format!(r#"{{"{long_name}":true}}"#);
+    assert!(convert_auto_with(&input, &DdbToNormalOptions::new()).is_err());
+}
+
+#[test]
+fn test_auto_error_position_in_whole_input() {
+    let input = r#"{"Item":{"name":{"S":"Alice"}}} {"Item":{"name":{"X":"Bob"}}}"#;
+    let (error, position) = convert_auto_with(input, &DdbToNormalOptions::new()).unwrap_err();
+    assert!(
+        matches!(error, ConversionError::ParseError { .. }),
+        "unexpected error: {error:?}"
+    );
+    assert_eq!(position, input.find("\"Bob\"").unwrap());
+}
@@ -0,0 +1,72 @@
+//! Helpers shared by the conversion tests
+#![allow(dead_code)]
+
+use ddb_convert::{ConversionError, DdbToNormalOptions, NormalToDdbOptions};
+
+/// Result of a conversion: the output, or the error with its position
+pub type ConvertResult<T> = Result<T, (ConversionError, usize)>;
+
+/// Call `f` with the rjiter and context buffers of the usual test sizes
+pub fn with_buffers<T>(f: impl FnOnce(&mut [u8], &mut [u8]) -> T) -> T {
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+    f(&mut rjiter_buffer, &mut context_buffer)
+}
+
+/// Convert DDB JSON to normal JSON using the given rjiter buffer
+pub fn try_from_ddb_in(
+    ddb_json: &str,
+    options: &DdbToNormalOptions,
+    rjiter_buffer: &mut [u8],
+) -> ConvertResult<String> {
+    let mut reader = ddb_json.as_bytes();
+    let mut output = Vec::new();
+    let mut context_buffer = [0u8; 2048];
+    ddb_convert::convert_ddb_to_normal_with_options(
+        &mut reader,
+        &mut output,
+        rjiter_buffer,
+        &mut context_buffer,
+        options,
+    )?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+/// Convert DDB JSON to normal JSON
+pub fn try_from_ddb(ddb_json: &str, options: &DdbToNormalOptions) -> ConvertResult<String> {
+    try_from_ddb_in(ddb_json, options, &mut [0u8; 4096])
+}
+
+/// Convert DDB JSON to normal JSON, panic on an error
+pub fn from_ddb(ddb_json: &str, options: &DdbToNormalOptions) -> String {
+    try_from_ddb(ddb_json, options).unwrap()
+}
+
+/// Convert DDB JSON to normal JSON, panic on a success
+pub fn from_ddb_expect_error(
+    ddb_json: &str,
+    options: &DdbToNormalOptions,
+) -> (ConversionError, usize) {
+    try_from_ddb(ddb_json, options).expect_err("Expected conversion to fail but it succeeded")
+}
+
+/// Convert normal JSON to DDB JSON
+pub fn try_to_ddb(normal_json: &str, options: &NormalToDdbOptions) -> ConvertResult<String> {
+    let mut reader = normal_json.as_bytes();
+    let mut output = Vec::new();
+    with_buffers(|rjiter_buffer, context_buffer| {
+        ddb_convert::convert_normal_to_ddb_with_options(
+            &mut reader,
+            &mut output,
+            rjiter_buffer,
+            context_buffer,
+            options,
+        )
+    })?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+/// Convert normal JSON to DDB JSON, panic on an error
+pub fn to_ddb(normal_json: &str, options: &NormalToDdbOptions) -> String {
+    try_to_ddb(normal_json, options).unwrap()
+}
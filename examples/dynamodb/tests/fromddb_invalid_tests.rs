@@ -1,4 +1,6 @@
-/// Tests for invalid DynamoDB JSON format validation
+//! Tests for invalid DynamoDB JSON format validation
+
+mod common;
 
 /// Helper function to test that conversion fails with an error
 fn convert_test_expect_error(ddb_json: &str) -> (ddb_convert::ConversionError, usize) {
@@ -51,7 +53,7 @@ fn test_string_type_with_number_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("S"),
+        error_message.contains("Expected a string"),
         "Error message should explain that S type expects a string value, got: {}",
         error_message
     );
@@ -64,7 +66,7 @@ fn test_string_type_with_boolean_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("S"),
+        error_message.contains("Expected a string"),
         "Error message should explain that S type expects a string value, got: {}",
         error_message
     );
@@ -77,7 +79,7 @@ fn test_string_type_with_null_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("S"),
+        error_message.contains("Expected a string"),
         "Error message should explain that S type expects a string value, got: {}",
         error_message
     );
@@ -90,7 +92,7 @@ fn test_string_type_with_array_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("S"),
+        error_message.contains("Expected a string"),
         "Error message should explain that S type expects a string value, got: {}",
         error_message
     );
@@ -103,7 +105,7 @@ fn test_string_type_with_object_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("S"),
+        error_message.contains("Expected a string"),
         "Error message should explain that S type expects a string value, got: {}",
         error_message
     );
@@ -120,7 +122,7 @@ fn test_binary_type_with_number_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("B"),
+        error_message.contains("Expected a string"),
         "Error message should explain that B type expects a string value, got: {}",
         error_message
     );
@@ -133,7 +135,7 @@ fn test_binary_type_with_boolean_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("B"),
+        error_message.contains("Expected a string"),
         "Error message should explain that B type expects a string value, got: {}",
         error_message
     );
@@ -146,7 +148,7 @@ fn test_binary_type_with_null_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("B"),
+        error_message.contains("Expected a string"),
         "Error message should explain that B type expects a string value, got: {}",
         error_message
     );
@@ -159,7 +161,7 @@ fn test_binary_type_with_array_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("B"),
+        error_message.contains("Expected a string"),
         "Error message should explain that B type expects a string value, got: {}",
         error_message
     );
@@ -172,7 +174,7 @@ fn test_binary_type_with_object_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("B"),
+        error_message.contains("Expected a string"),
         "Error message should explain that B type expects a string value, got: {}",
         error_message
     );
@@ -192,7 +194,7 @@ fn test_number_type_with_number_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("N"),
+        error_message.contains("Expected a string"),
         "Error message should explain that N type expects a string value, got: {}",
         error_message
     );
@@ -205,7 +207,7 @@ fn test_number_type_with_boolean_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("N"),
+        error_message.contains("Expected a string"),
         "Error message should explain that N type expects a string value, got: {}",
         error_message
     );
@@ -223,7 +225,7 @@ fn test_number_type_with_null_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("N"),
+        error_message.contains("Expected a string"),
         "Error message should explain that N type expects a string value, got: {}",
         error_message
     );
@@ -236,7 +238,7 @@ fn test_number_type_with_array_value() {
 
     let error_message = format!("{:?}", error);
     assert!(
-        error_message.contains("Expected string") || error_message.contains("N"),
+        error_message.contains("Expected a string"),
         "Error message should explain that N type expects a string value, got: {}",
         error_message
     );
@@ -749,3 +751,362 @@ fn test_attribute_without_type_descriptor() {
         error_message
     );
 }
+
+// ============================================================================
+// Set validation options: empty sets and element count limits
+// ============================================================================
+
+/// Helper function to convert with set validation options and expect an error
+fn convert_sets_expect_error(
+    ddb_json: &str,
+    reject_empty_sets: bool,
+    max_set_elements: Option<usize>,
+) -> (ddb_convert::ConversionError, usize) {
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.reject_empty_sets = reject_empty_sets;
+    options.max_set_elements = max_set_elements;
+    common::from_ddb_expect_error(ddb_json, &options)
+}
+
+#[test]
+fn test_empty_string_set_rejected() {
+    let ddb_json = r#"{"Item":{"Tags": {"SS": []}}}"#;
+    let (error, position) = convert_sets_expect_error(ddb_json, true, None);
+
+    assert!(matches!(error, ddb_convert::ConversionError::EmptySet));
+    assert_eq!(position, ddb_json.find(']').unwrap() + 1);
+}
+
+#[test]
+fn test_empty_number_and_binary_sets_rejected() {
+    for ddb_json in [
+        r#"{"Item":{"Nums": {"NS": [ ]}}}"#,
+        r#"{"Item":{"Bins": {"BS": []}}}"#,
+    ] {
+        let (error, _position) = convert_sets_expect_error(ddb_json, true, None);
+        assert!(
+            matches!(error, ddb_convert::ConversionError::EmptySet),
+            "Expected EmptySet for {ddb_json}, got: {error:?}"
+        );
+    }
+}
+
+#[test]
+fn test_empty_set_accepted_by_default() {
+    let ddb_json = r#"{"Item":{"Tags": {"SS": []}}}"#;
+    let result = common::try_from_ddb(ddb_json, &ddb_convert::DdbToNormalOptions::new());
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_set_too_large() {
+    let ddb_json = r#"{"Item":{"Nums": {"NS": ["1", "2", "3"]}}}"#;
+    let (error, position) = convert_sets_expect_error(ddb_json, false, Some(2));
+
+    assert!(matches!(
+        error,
+        ddb_convert::ConversionError::SetTooLarge { max_elements: 2 }
+    ));
+    assert_eq!(position, ddb_json.find("\"3\"").unwrap());
+}
+
+#[test]
+fn test_tee_rawcopy_write_error() {
+    let ddb_json = r#"{"Item":{"name": {"S": "Alice"}, "city": {"S": "Paris"}}}"#;
+    let mut reader = ddb_json.as_bytes();
+    let mut output = vec![0u8; 4096];
+    let mut output_slice = output.as_mut_slice();
+    // Too small for the copy of the input
+    let mut rawcopy = [0u8; 10];
+    let mut rawcopy_slice = rawcopy.as_mut_slice();
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+
+    let (error, _position) = ddb_convert::convert_ddb_to_normal_tee(
+        &mut reader,
+        &mut output_slice,
+        &mut rawcopy_slice,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+    )
+    .expect_err("Expected conversion to fail but it succeeded");
+
+    assert!(
+        matches!(
+            error,
+            ddb_convert::ConversionError::IOError {
+                context: "writing raw copy",
+                ..
+            }
+        ),
+        "Expected raw copy IOError, got: {error:?}"
+    );
+}
+
+#[test]
+fn test_spill_callback_error() {
+    let ddb_json = r#"{"Item":{"name": {"S": "Alice"}, "city": {"S": "Paris"}}}"#;
+    let mut reader = ddb_json.as_bytes();
+    let mut output_buffer = [0u8; 8];
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+
+    let (error, _position) = ddb_convert::convert_ddb_to_normal_spill(
+        &mut reader,
+        &mut output_buffer,
+        |_chunk| Err(embedded_io::ErrorKind::WriteZero),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+    )
+    .expect_err("Expected conversion to fail but it succeeded");
+
+    assert!(
+        matches!(
+            error,
+            ddb_convert::ConversionError::IOError {
+                kind: embedded_io::ErrorKind::WriteZero,
+                context: "spilling output",
+            }
+        ),
+        "Expected spill IOError, got: {error:?}"
+    );
+}
+
+#[test]
+fn test_invalid_utf8_attribute_name() {
+    let ddb_json: &[u8] = b"{\"Item\":{\"ok\": {\"S\": \"x\"}, \"a\xff\xfeb\": {\"S\": \"y\"}}}";
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.invalid_utf8_names = ddb_convert::InvalidUtf8Policy::Error;
+    let mut reader = ddb_json;
+    let mut output = vec![0u8; 256];
+    let mut output_slice = output.as_mut_slice();
+    let mut rjiter_buffer = [0u8; 256];
+    let mut context_buffer = [0u8; 2048];
+
+    let (error, position) = ddb_convert::convert_ddb_to_normal_with_options(
+        &mut reader,
+        &mut output_slice,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &options,
+    )
+    .expect_err("Expected conversion to fail but it succeeded");
+
+    let ddb_convert::ConversionError::ParseError {
+        invalid_utf8: Some((bytes, len)),
+        ..
+    } = &error
+    else {
+        panic!("Expected ParseError with invalid bytes, got {error:?}");
+    };
+    assert_eq!(&bytes[..*len], b"\xff\xfeb");
+    assert_eq!(
+        error.to_string(),
+        "Parse error: Attribute name is not valid UTF-8 (invalid UTF-8 bytes: ff fe 62)"
+    );
+    // After the name and the colon
+    assert_eq!(position, 34);
+}
+
+#[test]
+fn test_retryable_errors() {
+    struct FailingReader(embedded_io::ErrorKind);
+    impl embedded_io::ErrorType for FailingReader {
+        type Error = embedded_io::ErrorKind;
+    }
+    impl embedded_io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Err(self.0)
+        }
+    }
+
+    for (kind, is_retryable) in [
+        (embedded_io::ErrorKind::Interrupted, true),
+        (embedded_io::ErrorKind::TimedOut, true),
+        (embedded_io::ErrorKind::BrokenPipe, false),
+    ] {
+        let mut output = vec![0u8; 4096];
+        let mut output_slice = output.as_mut_slice();
+        let mut rjiter_buffer = [0u8; 4096];
+        let mut context_buffer = [0u8; 2048];
+        let (error, _position) = ddb_convert::convert_ddb_to_normal(
+            &mut FailingReader(kind),
+            &mut output_slice,
+            &mut rjiter_buffer,
+            &mut context_buffer,
+            false,
+            false,
+            ddb_convert::ItemWrapperMode::AsWrapper,
+        )
+        .expect_err("Expected conversion to fail but it succeeded");
+        assert_eq!(
+            error.is_retryable(),
+            is_retryable,
+            "{kind:?}, got: {error:?}"
+        );
+    }
+
+    let (error, _position) = convert_test_expect_error(r#"{"Item":{"a": {"S": 1}}}"#);
+    assert!(
+        !error.is_retryable(),
+        "Expected final error, got: {error:?}"
+    );
+    let (error, _position) =
+        convert_sets_expect_error(r#"{"Item":{"Tags": {"SS": []}}}"#, true, None);
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_fmt_write_sink_error() {
+    // A sink with a fixed capacity, like `heapless::String`
+    struct SmallSink(usize);
+    impl core::fmt::Write for SmallSink {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            self.0 = self.0.checked_sub(s.len()).ok_or(core::fmt::Error)?;
+            Ok(())
+        }
+    }
+
+    let ddb_json = r#"{"Item":{"name": {"S": "Alice"}, "city": {"S": "Paris"}}}"#;
+    let mut reader = ddb_json.as_bytes();
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+
+    let (error, _position) = ddb_convert::convert_ddb_to_normal_fmt(
+        &mut reader,
+        &mut SmallSink(10),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+    )
+    .expect_err("Expected conversion to fail but it succeeded");
+
+    assert!(
+        matches!(
+            error,
+            ddb_convert::ConversionError::IOError {
+                kind: embedded_io::ErrorKind::Other,
+                context: "writing to the text output",
+            }
+        ),
+        "Expected IOError, got: {error:?}"
+    );
+}
+
+fn convert_small_buffer_expect_error(
+    ddb_json: &str,
+    options: &ddb_convert::DdbToNormalOptions,
+) -> (ddb_convert::ConversionError, usize) {
+    common::try_from_ddb_in(ddb_json, options, &mut [0u8; 32])
+        .expect_err("Expected conversion to fail but it succeeded")
+}
+
+fn assert_attribute_too_large(
+    error: &ddb_convert::ConversionError,
+    expected_attribute: &str,
+    expected_position: usize,
+) {
+    let ddb_convert::ConversionError::AttributeTooLarge {
+        attribute: (bytes, len),
+        position,
+    } = error
+    else {
+        panic!("Expected AttributeTooLarge, got: {error:?}");
+    };
+    assert_eq!(&bytes[..*len], expected_attribute.as_bytes());
+    assert_eq!(*position, expected_position);
+}
+
+#[test]
+fn test_attribute_too_large() {
+    let long = "x".repeat(100);
+    let options = ddb_convert::DdbToNormalOptions::new();
+
+    // Values and set elements are streamed
+    for ddb_json in [
+        format!(r#"{{"Item":{{"a": {{"S": "{long}"}}, "b": {{"SS": ["{long}"]}}}}}}"#),
+        format!(r#"{{"Item":{{"n": {{"NS": ["1{}"]}}}}}}"#, "0".repeat(100)),
+    ] {
+        let mut reader = ddb_json.as_bytes();
+        let mut output = vec![0u8; 4096];
+        let mut output_slice = output.as_mut_slice();
+        ddb_convert::convert_ddb_to_normal_with_options(
+            &mut reader,
+            &mut output_slice,
+            &mut [0u8; 32],
+            &mut [0u8; 2048],
+            &options,
+        )
+        .unwrap();
+    }
+
+    // Attribute names are not
+    let ddb_json = format!(r#"{{"Item":{{"{long}": {{"S": "x"}}}}}}"#);
+    let (error, _position) = convert_small_buffer_expect_error(&ddb_json, &options);
+    assert_attribute_too_large(&error, "", 8);
+    assert!(!error.is_retryable());
+
+    let ddb_json =
+        format!(r#"{{"Item":{{"user": {{"L": [{{"M": {{"{long}": {{"S": "x"}}}}}}]}}}}}}"#);
+    let (error, _position) = convert_small_buffer_expect_error(&ddb_json, &options);
+    assert_attribute_too_large(&error, "user[]", 30);
+    assert_eq!(
+        error.to_string(),
+        "Attribute 'user[]' at position 30 doesn't fit into the RJiter buffer, use a larger buffer"
+    );
+
+    // A truncated input is not a too large attribute
+    let (error, _position) =
+        convert_small_buffer_expect_error(r#"{"Item":{"a": {"S": "xx"#, &options);
+    assert!(
+        matches!(error, ddb_convert::ConversionError::RJiterError { .. }),
+        "Expected RJiterError, got: {error:?}"
+    );
+}
+
+#[test]
+fn test_attribute_too_large_for_whole_value() {
+    fn keep(
+        _path: &[u8],
+        _type_key: &[u8],
+        _value: &[u8],
+        _replacement: &mut [u8],
+    ) -> ddb_convert::Emit {
+        ddb_convert::Emit::Keep
+    }
+    let long = "x".repeat(100);
+    let ddb_json = format!(r#"{{"Item":{{"user": {{"M": {{"email": {{"S": "{long}"}}}}}}}}}}"#);
+
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.on_scalar = Some(keep);
+    let (error, position) = convert_small_buffer_expect_error(&ddb_json, &options);
+    assert_attribute_too_large(&error, "user.email", 39);
+    assert_eq!(position, 39);
+
+    let coercions = [("user.email", ddb_convert::CoerceTo::Bool)];
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.type_coercions = &coercions;
+    let (error, _position) = convert_small_buffer_expect_error(&ddb_json, &options);
+    assert_attribute_too_large(&error, "user.email", 39);
+
+    // A number is read as a whole for the hook too
+    let ddb_json = format!(r#"{{"Item":{{"n": {{"N": "1{}"}}}}}}"#, "0".repeat(100));
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.on_scalar = Some(keep);
+    let (error, _position) = convert_small_buffer_expect_error(&ddb_json, &options);
+    assert_attribute_too_large(&error, "n", 20);
+}
+
+#[test]
+fn test_syntax_error_has_line_position() {
+    let ddb_json = "{\"Item\":{\n  \"a\": {\"S\": \"x\"},\n  \"b\" {\"S\": \"y\"}\n}}";
+    let (error, position) = convert_test_expect_error(ddb_json);
+    assert_eq!(position, 40, "{error:?}");
+    assert_eq!(
+        error.line_position(),
+        Some(&rjiter::jiter::LinePosition::new(3, 12)),
+        "{error:?}"
+    );
+}
@@ -1,3 +1,5 @@
+mod common;
+
 /// Helper function to convert DDB JSON to normal JSON for testing
 fn convert_test(ddb_json: &str) -> String {
     convert_test_with_pretty(ddb_json, false)
@@ -512,3 +514,970 @@ fn test_jsonl_four_records() {
 "#;
     assert_eq!(result, expected);
 }
+
+/// Helper function to convert DDB JSON with unknown type descriptors preserved
+fn convert_test_passthrough(ddb_json: &str) -> String {
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.unknown_type_policy = ddb_convert::UnknownTypePolicy::PassthroughTagged;
+    common::from_ddb(ddb_json, &options)
+}
+
+#[test]
+fn test_unknown_type_passthrough_scalar() {
+    let ddb_json = r#"{"Item":{"a": {"S": "x"}, "v": {"VEC": "1,2,3"}, "b": {"N": "1"}}}"#;
+    let result = convert_test_passthrough(ddb_json);
+    let expected = r#"{"a":"x","v":{"$ddb:VEC":"1,2,3"},"b":1}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_unknown_type_passthrough_structured() {
+    let ddb_json = r#"{"Item":{"v": {"X": {"k": [1, {"z": null}]}}}}"#;
+    let result = convert_test_passthrough(ddb_json);
+    let expected = r#"{"v":{"$ddb:X":{"k":[1,{"z":null}]}}}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_unknown_type_passthrough_in_list() {
+    let ddb_json = r#"{"Item":{"l": {"L": [{"S": "a"}, {"X": 5}, {"N": "2"}]}}}"#;
+    let result = convert_test_passthrough(ddb_json);
+    let expected = r#"{"l":["a",{"$ddb:X":5},2]}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_sets_within_limits() {
+    let ddb_json = r#"{"Item":{"a": {"SS": ["x", "y"]}, "b": {"NS": ["1", "2"]}}}"#;
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.reject_empty_sets = true;
+    options.max_set_elements = Some(2);
+    let result = common::from_ddb(ddb_json, &options);
+    assert_eq!(result, "{\"a\":[\"x\",\"y\"],\"b\":[1,2]}\n");
+}
+
+#[test]
+fn test_tee_writes_converted_and_raw_copy() {
+    let ddb_json =
+        "{\"Item\":{\"name\": {\"S\": \"Alice\"}, \"tags\": {\"SS\": [\"a\", \"b\"]}}}\n\
+                    {\"Item\":{\"age\": {\"N\": \"30\"}}}\n";
+    let mut reader = ddb_json.as_bytes();
+    let mut output = vec![0u8; 4096];
+    let mut output_slice = output.as_mut_slice();
+    let mut rawcopy = vec![0u8; 4096];
+    let mut rawcopy_slice = rawcopy.as_mut_slice();
+    // Small buffer to have several reads
+    let mut rjiter_buffer = [0u8; 32];
+    let mut context_buffer = [0u8; 2048];
+
+    ddb_convert::convert_ddb_to_normal_tee(
+        &mut reader,
+        &mut output_slice,
+        &mut rawcopy_slice,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+    )
+    .unwrap();
+
+    let bytes_written = 4096 - output_slice.len();
+    let result = std::str::from_utf8(&output[..bytes_written]).unwrap();
+    assert_eq!(
+        result,
+        "{\"name\":\"Alice\",\"tags\":[\"a\",\"b\"]}\n{\"age\":30}\n"
+    );
+
+    let rawcopy_written = 4096 - rawcopy_slice.len();
+    assert_eq!(&rawcopy[..rawcopy_written], ddb_json.as_bytes());
+}
+
+/// Helper function to convert DDB JSON with type coercions
+fn convert_test_coerce(
+    ddb_json: &str,
+    type_coercions: &[(&str, ddb_convert::CoerceTo)],
+    item_wrapper_mode: ddb_convert::ItemWrapperMode,
+) -> String {
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.type_coercions = type_coercions;
+    options.item_wrapper_mode = item_wrapper_mode;
+    common::from_ddb(ddb_json, &options)
+}
+
+#[test]
+fn test_type_coercion_by_path() {
+    use ddb_convert::CoerceTo;
+
+    let ddb_json = r#"{"Item":{"age": {"S": "30"}, "user": {"M": {"age": {"S": "-1.5e3"}, "name": {"S": "42"}}}, "flags": {"M": {"active": {"S": "true"}}}}}"#;
+    let coercions = [
+        ("age", CoerceTo::Number),
+        ("user.age", CoerceTo::Number),
+        ("flags.active", CoerceTo::Bool),
+    ];
+    let result = convert_test_coerce(
+        ddb_json,
+        &coercions,
+        ddb_convert::ItemWrapperMode::AsWrapper,
+    );
+    let expected = r#"{"age":30,"user":{"age":-1.5e3,"name":"42"},"flags":{"active":true}}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_type_coercion_keeps_non_matching_strings() {
+    use ddb_convert::CoerceTo;
+
+    let ddb_json = r#"{"Item":{"age": {"S": "unknown"}, "n": {"S": "1 2"}, "b": {"S": "TRUE"}, "l": {"L": [{"S": "7"}]}}}
+{"Item":{"age": {"S": "3"}}}"#;
+    let coercions = [
+        ("age", CoerceTo::Number),
+        ("n", CoerceTo::Number),
+        ("b", CoerceTo::Bool),
+        ("l", CoerceTo::Number),
+    ];
+    let result = convert_test_coerce(
+        ddb_json,
+        &coercions,
+        ddb_convert::ItemWrapperMode::AsWrapper,
+    );
+    let expected = r#"{"age":"unknown","n":"1 2","b":"TRUE","l":["7"]}
+{"age":3}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_type_coercion_decodes_escapes() {
+    use ddb_convert::CoerceTo;
+
+    let ddb_json = r#"{"Item":{"age": {"S": "\u0034\u0032"}, "ok": {"S": "tru\u0065"}, "s": {"S": "4\n2"}, "q": {"S": "\"1\""}}}"#;
+    let coercions = [
+        ("age", CoerceTo::Number),
+        ("ok", CoerceTo::Bool),
+        ("s", CoerceTo::Number),
+        ("q", CoerceTo::Number),
+    ];
+    let result = convert_test_coerce(
+        ddb_json,
+        &coercions,
+        ddb_convert::ItemWrapperMode::AsWrapper,
+    );
+    let expected = r#"{"age":42,"ok":true,"s":"4\n2","q":"\"1\""}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_type_coercion_item_as_field() {
+    use ddb_convert::CoerceTo;
+
+    let ddb_json = r#"{"Item": {"M": {"count": {"S": "5"}}}, "count": {"S": "6"}}"#;
+    let coercions = [("Item.count", CoerceTo::Number)];
+    let result = convert_test_coerce(ddb_json, &coercions, ddb_convert::ItemWrapperMode::AsField);
+    let expected = r#"{"Item":{"count":5},"count":"6"}
+"#;
+    assert_eq!(result, expected);
+}
+
+/// Helper function to convert DDB JSON with a scalar hook
+fn convert_test_hook(ddb_json: &str, on_scalar: ddb_convert::ScalarHook, pretty: bool) -> String {
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.on_scalar = Some(on_scalar);
+    options.pretty = pretty;
+    common::from_ddb(ddb_json, &options)
+}
+
+fn redact_hook(
+    path: &[u8],
+    _type_key: &[u8],
+    value: &[u8],
+    replacement: &mut [u8],
+) -> ddb_convert::Emit {
+    match path {
+        b"user.email" | b"contacts[].phone" => {
+            replacement[..5].copy_from_slice(b"\"***\"");
+            ddb_convert::Emit::Replace(5)
+        }
+        b"photo" | b"tags[]" if value.len() > 3 => ddb_convert::Emit::Drop,
+        b"active" => {
+            replacement[..4].copy_from_slice(b"null");
+            ddb_convert::Emit::Replace(4)
+        }
+        _ => ddb_convert::Emit::Keep,
+    }
+}
+
+#[test]
+fn test_scalar_hook_replaces_and_drops() {
+    let ddb_json = r#"{"Item":{"photo":{"B":"aGVsbG8="},"user":{"M":{"name":{"S":"Alice"},"email":{"S":"a@example.com"}}},"tags":{"L":[{"S":"long tag"},{"S":"ok"},{"N":"12345"}]},"contacts":{"L":[{"M":{"phone":{"S":"555"}}}]},"active":{"BOOL":true},"gone":{"NULL":true},"age":{"N":"30"},"set":{"SS":["x"]}}}
+{"photo":{"B":"aGVsbG8="}}"#;
+    let result = convert_test_hook(ddb_json, redact_hook, false);
+    let expected = r#"{"user":{"name":"Alice","email":"***"},"tags":["ok"],"contacts":[{"phone":"***"}],"active":null,"gone":null,"age":30,"set":["x"]}
+{}
+"#;
+    assert_eq!(result, expected);
+}
+
+fn path_hook(
+    path: &[u8],
+    type_key: &[u8],
+    value: &[u8],
+    replacement: &mut [u8],
+) -> ddb_convert::Emit {
+    let text = format!(
+        "\"{}:{}:{}\"",
+        std::str::from_utf8(path).unwrap(),
+        std::str::from_utf8(type_key).unwrap(),
+        std::str::from_utf8(value).unwrap()
+    );
+    replacement[..text.len()].copy_from_slice(text.as_bytes());
+    ddb_convert::Emit::Replace(text.len())
+}
+
+#[test]
+fn test_scalar_hook_gets_path_and_value() {
+    let ddb_json = r#"{"Item":{"a":{"M":{"b":{"L":[{"L":[{"N":"1"}]}]}}},"c":{"BOOL":false},"d":{"NULL":true}}}"#;
+    let result = convert_test_hook(ddb_json, path_hook, false);
+    let expected = r#"{"a":{"b":[["a.b[][]:N:1"]]},"c":"c:BOOL:false","d":"d:NULL:null"}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_scalar_hook_drop_in_pretty_output() {
+    let ddb_json = r#"{"Item":{"photo":{"B":"aGVsbG8="},"name":{"S":"Alice"}}}"#;
+    let result = convert_test_hook(ddb_json, redact_hook, true);
+    let expected = "{\n  \"name\":\"Alice\"\n}\n";
+    assert_eq!(result, expected);
+}
+
+/// Helper function to convert DDB JSON with a tolerance
+fn convert_test_tolerance(
+    ddb_json: &str,
+    tolerance: ddb_convert::Tolerance,
+) -> common::ConvertResult<String> {
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.tolerance = tolerance;
+    common::try_from_ddb(ddb_json, &options)
+}
+
+#[test]
+fn test_pass_through_normal_records() {
+    use ddb_convert::Tolerance;
+
+    let input = r#"{"Item":{"name":{"S":"Alice"}}}
+{"name": "Bob", "tags": ["a", "b"]}
+{"user": {"M": {"age": {"N": "30"}}}}
+{"user": {"age": 31, "name": {"S": "looks like ddb"}}}
+{"Item": {"name": "Carol"}}
+{"Item": {"name": {"N": "1"}}}
+{}
+"#;
+    let result = convert_test_tolerance(input, Tolerance::PassThroughNormal).unwrap();
+    let expected = r#"{"name":"Alice"}
+{"name":"Bob","tags":["a","b"]}
+{"user":{"age":30}}
+{"user":{"age":31,"name":{"S":"looks like ddb"}}}
+{"Item":{"name":"Carol"}}
+{"name":1}
+{}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_strict_tolerance_rejects_normal_records() {
+    let input = r#"{"name": "Bob"}"#;
+    let result = convert_test_tolerance(input, ddb_convert::Tolerance::Strict);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_metrics_are_accumulated() {
+    let ddb_json = r#"{"Item":{"name":{"S":"Alice"},"tags":{"SS":["a","b"]}}}
+{"Item":{"age":{"N":"30"},"info":{"M":{"ok":{"BOOL":true}}}}}"#;
+    let mut metrics = ddb_convert::Metrics::new();
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+
+    let mut output = Vec::new();
+    let mut reader = ddb_json.as_bytes();
+    ddb_convert::convert_ddb_to_normal_with_metrics(
+        &mut reader,
+        &mut embedded_io_adapters::std::FromStd::new(&mut output),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+        &mut metrics,
+    )
+    .unwrap();
+
+    assert_eq!(metrics.records, 2);
+    assert_eq!(metrics.errors, 0);
+    assert_eq!(metrics.bytes_in, ddb_json.len() as u64);
+    assert_eq!(metrics.bytes_out, output.len() as u64);
+    let expected = ddb_convert::AttributeCounts {
+        s: 1,
+        n: 1,
+        boolean: 1,
+        m: 1,
+        ss: 1,
+        ..Default::default()
+    };
+    assert_eq!(metrics.attributes, expected);
+    assert_eq!(metrics.set_elements, 2);
+    assert_eq!(metrics.max_set_len, 2);
+    assert_eq!(metrics.max_string_len, 5);
+
+    // A failed conversion is added to the same metrics
+    let mut reader = r#"{"Item":{"x":{"Q":"1"}}}"#.as_bytes();
+    let result = ddb_convert::convert_ddb_to_normal_with_metrics(
+        &mut reader,
+        &mut embedded_io_adapters::std::FromStd::new(&mut Vec::new()),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+        &mut metrics,
+    );
+    assert!(result.is_err());
+    assert_eq!(metrics.records, 3);
+    assert_eq!(metrics.errors, 1);
+    assert_eq!(metrics.attributes.unknown, 1);
+}
+
+#[test]
+fn test_metrics_track_largest_sets_and_strings() {
+    let ddb_json = r#"{"Item":{"a":{"SS":["x","long \"quoted\""]},"b":{"NS":["1","2","3"]}}}
+{"Item":{"c":{"L":[{"S":"12345678"},{"BS":["QUJD"]}]},"d":{"B":"QQ=="},"e":{"N":"123456789012"}}}"#;
+    let mut metrics = ddb_convert::Metrics::new();
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+    let mut reader = ddb_json.as_bytes();
+    ddb_convert::convert_ddb_to_normal_with_metrics(
+        &mut reader,
+        &mut embedded_io_adapters::std::FromStd::new(&mut Vec::new()),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+        &mut metrics,
+    )
+    .unwrap();
+
+    assert_eq!(metrics.set_elements, 6);
+    assert_eq!(metrics.max_set_len, 3);
+    // The escaped quotes are counted as in the input, the numbers are not strings
+    assert_eq!(metrics.max_string_len, 15);
+}
+
+#[test]
+fn test_metrics_render_prometheus() {
+    let mut metrics = ddb_convert::Metrics::default();
+    metrics.records = 2;
+    metrics.bytes_in = 100;
+    let mut buf = [0u8; 2048];
+    let len = metrics.render_prometheus(&mut buf).unwrap();
+    let text = std::str::from_utf8(&buf[..len]).unwrap();
+
+    assert!(text.contains("# TYPE ddb_convert_records_total counter\n"));
+    assert!(text.contains("ddb_convert_records_total 2\n"));
+    assert!(text.contains("ddb_convert_bytes_in_total 100\n"));
+    assert!(text.contains("ddb_convert_attributes_total{type=\"BOOL\"} 0\n"));
+    assert!(text.contains("# TYPE ddb_convert_max_set_len gauge\n"));
+    assert!(text.contains("ddb_convert_max_string_len_bytes 0\n"));
+
+    let mut small = [0u8; 16];
+    assert!(metrics.render_prometheus(&mut small).is_err());
+}
+
+#[test]
+fn test_spill_gives_output_in_chunks() {
+    let ddb_json =
+        "{\"Item\":{\"name\": {\"S\": \"Alice\"}, \"tags\": {\"SS\": [\"a\", \"b\"]}}}\n\
+                    {\"Item\":{\"age\": {\"N\": \"30\"}}}\n";
+    let mut reader = ddb_json.as_bytes();
+    let mut output_buffer = [0u8; 8];
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+
+    ddb_convert::convert_ddb_to_normal_spill(
+        &mut reader,
+        &mut output_buffer,
+        |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        },
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+    )
+    .unwrap();
+
+    let expected = "{\"name\":\"Alice\",\"tags\":[\"a\",\"b\"]}\n{\"age\":30}\n";
+    assert!(chunks
+        .iter()
+        .all(|chunk| !chunk.is_empty() && chunk.len() <= 8));
+    assert!(chunks[..chunks.len() - 1]
+        .iter()
+        .all(|chunk| chunk.len() == 8));
+    assert_eq!(String::from_utf8(chunks.concat()).unwrap(), expected);
+}
+
+#[test]
+fn test_spill_flushes_after_each_record_when_unbuffered() {
+    let ddb_json = "{\"Item\":{\"a\": {\"N\": \"1\"}}}\n{\"Item\":{\"b\": {\"N\": \"2\"}}}\n";
+    let mut reader = ddb_json.as_bytes();
+    let mut output_buffer = [0u8; 1024];
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.unbuffered = true;
+
+    ddb_convert::convert_ddb_to_normal_spill(
+        &mut reader,
+        &mut output_buffer,
+        |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        },
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &options,
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8(chunks.concat()).unwrap(),
+        "{\"a\":1}\n{\"b\":2}\n"
+    );
+    assert!(chunks.len() >= 2);
+}
+
+#[test]
+fn test_presence_appended_field() {
+    let ddb_json = "{\"Item\":{\"email\": {\"S\": \"a@b\"}, \"user\": {\"M\": {\"phone\": {\"S\": \"1\"}}}}}\n\
+                    {\"Item\":{\"phone\": {\"S\": \"2\"}}}\n\
+                    {\"Item\":{}}\n";
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.presence_fields = &["email", "phone"];
+
+    assert_eq!(
+        common::from_ddb(ddb_json, &options),
+        "{\"email\":\"a@b\",\"user\":{\"phone\":\"1\"},\"$present\":[true,false]}\n\
+         {\"phone\":\"2\",\"$present\":[false,true]}\n\
+         {\"$present\":[false,false]}\n"
+    );
+
+    let mut pretty_options = options;
+    pretty_options.pretty = true;
+    let result = common::from_ddb("{\"Item\":{\"email\": {\"S\": \"a@b\"}}}", &pretty_options);
+    let json: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(json["$present"], serde_json::json!([true, false]));
+}
+
+#[test]
+fn test_presence_callback() {
+    use std::sync::Mutex;
+
+    static REPORTS: Mutex<Vec<(u64, u64)>> = Mutex::new(Vec::new());
+    fn on_presence(record: u64, present: u64) {
+        REPORTS.lock().unwrap().push((record, present));
+    }
+
+    let ddb_json = "{\"Item\":{\"a\": {\"N\": \"1\"}, \"c\": {\"N\": \"3\"}}}\n\
+                    {\"Item\":{\"b\": {\"NULL\": true}}}\n";
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.presence_fields = &["a", "b", "c"];
+    options.presence_output = ddb_convert::PresenceOutput::Callback(on_presence);
+
+    assert_eq!(
+        common::from_ddb(ddb_json, &options),
+        "{\"a\":1,\"c\":3}\n{\"b\":null}\n"
+    );
+    assert_eq!(*REPORTS.lock().unwrap(), vec![(1, 0b101), (2, 0b010)]);
+}
+
+#[test]
+fn test_flatten_nested_values() {
+    let ddb_json = r#"{"Item":{"id": {"N": "1"}, "address": {"M": {"city": {"S": "Oslo"}, "geo": {"M": {"lat": {"N": "59.9"}}}}}, "tags": {"L": [{"S": "a"}, {"M": {"x": {"BOOL": true}}}, {"SS": ["s"]}]}, "empty": {"M": {}}, "none": {"L": []}}}
+"#;
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.flatten = Some(ddb_convert::MAX_FLATTEN_DEPTH);
+
+    assert_eq!(
+        common::from_ddb(ddb_json, &options),
+        "{\"id\":1,\"address.city\":\"Oslo\",\"address.geo.lat\":59.9,\"tags[0]\":\"a\",\"tags[1].x\":true,\"tags[2]\":[\"s\"],\"empty\":{},\"none\":[]}\n"
+    );
+}
+
+#[test]
+fn test_flatten_depth_cap() {
+    let ddb_json = r#"{"a": {"M": {"b": {"M": {"c": {"N": "1"}}}, "l": {"L": [{"L": [{"N": "2"}]}, {"M": {"d": {"S": "x"}}}]}}}, "e": {"S": "y"}}
+{"f": {"L": [{"N": "3"}]}}
+"#;
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.flatten = Some(1);
+
+    assert_eq!(
+        common::from_ddb(ddb_json, &options),
+        "{\"a.b\":{\"c\":1},\"a.l\":[[2],{\"d\":\"x\"}],\"e\":\"y\"}\n{\"f[0]\":3}\n"
+    );
+}
+
+#[test]
+fn test_invalid_utf8_attribute_name_replaced() {
+    let ddb_json: &[u8] = b"{\"Item\":{\"a\xffb\": {\"M\": {\"\xc3c\": {\"N\": \"1\"}}}}}\n";
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.invalid_utf8_names = ddb_convert::InvalidUtf8Policy::Replace;
+    let mut reader = ddb_json;
+    let mut output = Vec::new();
+    let mut rjiter_buffer = [0u8; 256];
+    let mut context_buffer = [0u8; 2048];
+
+    ddb_convert::convert_ddb_to_normal_with_options(
+        &mut reader,
+        &mut embedded_io_adapters::std::FromStd::new(&mut output),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &options,
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\"a\u{fffd}b\":{\"\u{fffd}c\":1}}\n"
+    );
+}
+
+#[test]
+fn test_convert_into_fmt_write() {
+    let ddb_json = "{\"Item\":{\"name\": {\"S\": \"Zoë 😀\"}, \"n\": {\"N\": \"1\"}}}\n";
+    let mut reader = ddb_json.as_bytes();
+    let mut rjiter_buffer = [0u8; 4096];
+    let mut context_buffer = [0u8; 2048];
+    let mut output = String::new();
+
+    ddb_convert::convert_ddb_to_normal_fmt(
+        &mut reader,
+        &mut output,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &ddb_convert::DdbToNormalOptions::new(),
+    )
+    .unwrap();
+
+    assert_eq!(output, "{\"name\":\"Zoë 😀\",\"n\":1}\n");
+}
+
+#[test]
+fn test_fmt_write_adapter_joins_split_code_points() {
+    use embedded_io::Write;
+
+    let text = "aé😀z";
+    let mut output = String::new();
+    let mut adapter = ddb_convert::FmtWriteAdapter::new(&mut output);
+    for byte in text.as_bytes() {
+        adapter.write_all(&[*byte]).unwrap();
+    }
+    adapter.finish().unwrap();
+    adapter.write_all(&text.as_bytes()[..2]).unwrap();
+    assert_eq!(adapter.finish(), Err(embedded_io::ErrorKind::InvalidData));
+    assert_eq!(
+        adapter.write_all(b"\xff"),
+        Err(embedded_io::ErrorKind::InvalidData)
+    );
+    assert_eq!(output, "aé😀za");
+}
+
+fn convert_normalize_integers(ddb_json: &str, rjiter_buffer: &mut [u8]) -> String {
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.number_output = ddb_convert::NumberOutput::NormalizeIntegers;
+    common::try_from_ddb_in(ddb_json, &options, rjiter_buffer).unwrap()
+}
+
+#[test]
+fn test_normalize_integers() {
+    let ddb_json = r#"{"a":{"N":"42.0"},"b":{"N":"007"},"c":{"N":"-0.50"},"d":{"N":"-0.0"},"e":{"N":"100"},"f":{"N":"1.0e3"},"g":{"N":"0.000"},"h":{"NS":["10.00","-03","0.25"]},"i":{"L":[{"N":"-12.300"}]}}"#;
+    let result = convert_normalize_integers(ddb_json, &mut [0u8; 4096]);
+    let expected = r#"{"a":42,"b":7,"c":-0.5,"d":0,"e":100,"f":1e3,"g":0,"h":[10,-3,0.25],"i":[-12.3]}
+"#;
+    assert_eq!(result, expected);
+
+    // The default keeps the numbers as is
+    assert_eq!(
+        convert_test(r#"{"a":{"N":"42.0"},"b":{"N":"007"}}"#),
+        "{\"a\":42.0,\"b\":007}\n"
+    );
+}
+
+#[test]
+fn test_normalize_integers_in_chunks() {
+    // The numbers are longer than the buffer, they are normalized in chunks
+    let ddb_json = r#"{"a":{"N":"0000000000000000000000000000001234567890123.000000000000000000000000000000"},"b":{"N":"-000000000000000000000000000000000.000000000000000000000000000000000001000"}}"#;
+    let result = convert_normalize_integers(ddb_json, &mut [0u8; 32]);
+    let expected = r#"{"a":1234567890123,"b":-0.000000000000000000000000000000000001}
+"#;
+    assert_eq!(result, expected);
+}
+
+fn keep_scalar(
+    _path: &[u8],
+    _type_key: &[u8],
+    _value: &[u8],
+    _replacement: &mut [u8],
+) -> ddb_convert::Emit {
+    ddb_convert::Emit::Keep
+}
+
+#[test]
+fn test_normalize_integers_with_hook() {
+    let ddb_json = r#"{"a":{"N":"5.000"},"b":{"S":"5.000"}}"#;
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.number_output = ddb_convert::NumberOutput::NormalizeIntegers;
+    options.on_scalar = Some(keep_scalar);
+    assert_eq!(
+        common::from_ddb(ddb_json, &options),
+        "{\"a\":5,\"b\":\"5.000\"}\n"
+    );
+}
+
+/// Router with an output for the tables "Orders" and "Users"
+#[derive(Default)]
+struct TwoTables {
+    orders: Vec<u8>,
+    users: Vec<u8>,
+    lookups: usize,
+}
+
+impl ddb_convert::TableRouter for TwoTables {
+    type Writer = Vec<u8>;
+
+    fn route(&mut self, table_name: &[u8]) -> Option<usize> {
+        self.lookups += 1;
+        match table_name {
+            b"Orders" => Some(0),
+            b"Users" => Some(1),
+            _ => None,
+        }
+    }
+
+    fn output(&mut self, index: usize) -> Option<&mut Vec<u8>> {
+        match index {
+            0 => Some(&mut self.orders),
+            1 => Some(&mut self.users),
+            _ => None,
+        }
+    }
+}
+
+fn convert_routed(
+    ddb_json: &str,
+    options: &ddb_convert::DdbToNormalOptions,
+) -> (Result<(), (ddb_convert::ConversionError, usize)>, TwoTables) {
+    let mut reader = ddb_json.as_bytes();
+    let mut router = TwoTables::default();
+    let result = common::with_buffers(|rjiter_buffer, context_buffer| {
+        ddb_convert::convert_ddb_to_normal_routed(
+            &mut reader,
+            &mut router,
+            rjiter_buffer,
+            context_buffer,
+            options,
+        )
+    });
+    (result, router)
+}
+
+#[test]
+fn test_routed_records_go_to_table_outputs() {
+    let ddb_json = r#"{"TableName":"Orders","Item":{"id":{"N":"1"},"total":{"N":"9.5"}}}
+{"TableName":"Users","Item":{"name":{"S":"Alice"}}}
+{"TableName":"Orders","Item":{"id":{"N":"2"},"tags":{"SS":["a"]}}}
+"#;
+    let (result, router) = convert_routed(ddb_json, &ddb_convert::DdbToNormalOptions::new());
+    result.unwrap();
+    assert_eq!(
+        String::from_utf8(router.orders).unwrap(),
+        "{\"id\":1,\"total\":9.5}\n{\"id\":2,\"tags\":[\"a\"]}\n"
+    );
+    assert_eq!(
+        String::from_utf8(router.users).unwrap(),
+        "{\"name\":\"Alice\"}\n"
+    );
+    // The table is looked up once for each record
+    assert_eq!(router.lookups, 3);
+}
+
+#[test]
+fn test_routed_pretty_without_item_wrapper() {
+    let ddb_json = r#"{"TableName":"Users","name":{"S":"Bob"},"age":{"N":"30"}}"#;
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.pretty = true;
+    options.item_wrapper_mode = ddb_convert::ItemWrapperMode::AsField;
+    let (result, router) = convert_routed(ddb_json, &options);
+    result.unwrap();
+    assert_eq!(
+        String::from_utf8(router.users).unwrap(),
+        "{\n  \"name\":\"Bob\",\n  \"age\":30\n}\n"
+    );
+    assert!(router.orders.is_empty());
+}
+
+fn assert_routing_error(ddb_json: &str, expected_context: &str) {
+    let (result, _) = convert_routed(ddb_json, &ddb_convert::DdbToNormalOptions::new());
+    match result {
+        Err((ddb_convert::ConversionError::ParseError { context, .. }, _)) => {
+            assert_eq!(context, expected_context);
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_routed_errors() {
+    assert_routing_error(
+        r#"{"Item":{"id":{"N":"1"}},"TableName":"Orders"}"#,
+        "Record has no TableName before the attributes",
+    );
+    assert_routing_error(
+        r#"{"TableName":"Invoices","Item":{"id":{"N":"1"}}}"#,
+        "No output for the table of the record",
+    );
+    assert_routing_error(
+        r#"{"TableName":"Orders","Item":{"id":{"N":"1"}},"TableName":"Users"}"#,
+        "Record has more than one TableName",
+    );
+    assert_routing_error(r#"{}"#, "Record has no TableName");
+}
+
+fn convert_framed(
+    ddb_json: &str,
+    framing: ddb_convert::Framing,
+    record_buffer: &mut [u8],
+) -> Result<Vec<u8>, (ddb_convert::ConversionError, usize)> {
+    let mut reader = ddb_json.as_bytes();
+    let mut output = Vec::new();
+    common::with_buffers(|rjiter_buffer, context_buffer| {
+        ddb_convert::convert_ddb_to_normal_framed(
+            &mut reader,
+            &mut output,
+            framing,
+            record_buffer,
+            rjiter_buffer,
+            context_buffer,
+            &ddb_convert::DdbToNormalOptions::new(),
+        )
+    })?;
+    Ok(output)
+}
+
+#[test]
+fn test_framed_length_prefix() {
+    use ddb_convert::{Framing, LengthPrefix};
+
+    let ddb_json = "{\"Item\":{\"a\":{\"N\":\"42\"}}}\n{\"Item\":{\"name\":{\"S\":\"Alice\"},\"tags\":{\"SS\":[\"x\"]}}}\n";
+    let mut record_buffer = [0u8; 64];
+
+    let output = convert_framed(
+        ddb_json,
+        Framing::LengthPrefixed(LengthPrefix::AsciiDecimal),
+        &mut record_buffer,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "9 {\"a\":42}\n30 {\"name\":\"Alice\",\"tags\":[\"x\"]}\n"
+    );
+
+    let output = convert_framed(
+        ddb_json,
+        Framing::LengthPrefixed(LengthPrefix::U32Le),
+        &mut record_buffer,
+    )
+    .unwrap();
+    let mut expected = vec![9, 0, 0, 0];
+    expected.extend_from_slice(b"{\"a\":42}\n");
+    expected.extend_from_slice(&[30, 0, 0, 0]);
+    expected.extend_from_slice(b"{\"name\":\"Alice\",\"tags\":[\"x\"]}\n");
+    assert_eq!(output, expected);
+
+    let output = convert_framed(ddb_json, Framing::Newline, &mut []).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\"a\":42}\n{\"name\":\"Alice\",\"tags\":[\"x\"]}\n"
+    );
+}
+
+#[test]
+fn test_framed_record_too_large_for_buffer() {
+    let ddb_json = "{\"Item\":{\"name\":{\"S\":\"a rather long value\"}}}";
+    let mut record_buffer = [0u8; 16];
+    let result = convert_framed(
+        ddb_json,
+        ddb_convert::Framing::LengthPrefixed(ddb_convert::LengthPrefix::AsciiDecimal),
+        &mut record_buffer,
+    );
+    // The value is written with rjiter, which reports the error of the writer
+    match result {
+        Err((
+            ddb_convert::ConversionError::RJiterError {
+                kind: rjiter::error::ErrorType::IoError { kind },
+                ..
+            },
+            _,
+        )) => {
+            assert_eq!(kind, embedded_io::ErrorKind::OutOfMemory);
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+fn convert_prefix(
+    ddb_json: &str,
+    limit: ddb_convert::Limit,
+) -> (String, ddb_convert::PrefixReport) {
+    let mut reader = ddb_json.as_bytes();
+    let mut output = Vec::new();
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.limit = limit;
+    let report = common::with_buffers(|rjiter_buffer, context_buffer| {
+        ddb_convert::convert_ddb_to_normal_prefix(
+            &mut reader,
+            &mut output,
+            rjiter_buffer,
+            context_buffer,
+            &options,
+        )
+    })
+    .unwrap();
+    (String::from_utf8(output).unwrap(), report)
+}
+
+#[test]
+fn test_prefix_max_records() {
+    let ddb_json = "{\"Item\":{\"a\":{\"N\":\"1\"}}}\n{\"Item\":{\"a\":{\"N\":\"2\"}}}\n{\"Item\":{\"a\":{\"N\":\"3\"}}}\n";
+    let limit = |max_records| ddb_convert::Limit {
+        max_records: Some(max_records),
+        max_bytes: None,
+    };
+
+    let (output, report) = convert_prefix(ddb_json, limit(2));
+    assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n");
+    assert_eq!(report.records, 2);
+    assert_eq!(report.consumed, 49);
+    assert!(report.is_limit_reached);
+
+    // Resume from the consumed offset
+    let (output, report) = convert_prefix(&ddb_json[49..], limit(2));
+    assert_eq!(output, "{\"a\":3}\n");
+    assert_eq!(report.records, 1);
+    assert!(!report.is_limit_reached);
+
+    let (output, report) = convert_prefix(ddb_json, limit(0));
+    assert_eq!(output, "");
+    assert_eq!((report.records, report.consumed), (0, 0));
+    assert!(report.is_limit_reached);
+}
+
+#[test]
+fn test_prefix_max_bytes_stops_at_record_boundary() {
+    let ddb_json = "{\"Item\":{\"a\":{\"N\":\"1\"}}}\n{\"Item\":{\"a\":{\"N\":\"2\"}}}\n{\"Item\":{\"a\":{\"N\":\"3\"}}}\n";
+    let limit = |max_bytes| ddb_convert::Limit {
+        max_records: None,
+        max_bytes: Some(max_bytes),
+    };
+
+    // The second record crosses the limit, it is converted completely
+    let (output, report) = convert_prefix(ddb_json, limit(30));
+    assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n");
+    assert_eq!((report.records, report.consumed), (2, 49));
+    assert!(report.is_limit_reached);
+
+    // The limit at the end of a record
+    let (output, report) = convert_prefix(ddb_json, limit(24));
+    assert_eq!(output, "{\"a\":1}\n");
+    assert_eq!((report.records, report.consumed), (1, 24));
+
+    let (output, report) = convert_prefix(ddb_json, limit(1000));
+    assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n");
+    assert_eq!(report.records, 3);
+    assert_eq!(report.consumed, ddb_json.len());
+    assert!(!report.is_limit_reached);
+}
+
+#[test]
+fn test_no_limit_converts_everything() {
+    let ddb_json = "{\"Item\":{\"a\":{\"N\":\"1\"}}}\n{\"Item\":{\"a\":{\"N\":\"2\"}}}";
+    let (output, report) = convert_prefix(ddb_json, ddb_convert::Limit::NONE);
+    assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n");
+    assert_eq!(report.records, 2);
+    assert!(!report.is_limit_reached);
+}
+
+#[test]
+fn test_on_record_reports_boundaries() {
+    use std::sync::Mutex;
+    static RECORDS: Mutex<Vec<(usize, usize, u64)>> = Mutex::new(Vec::new());
+    fn on_record(start: usize, end: usize, bytes_out: u64) {
+        RECORDS.lock().unwrap().push((start, end, bytes_out));
+    }
+
+    let ddb_json =
+        "{\"Item\":{\"a\":{\"N\":\"1\"}}}\n  {\"Item\":{\"a\":{\"S\":\"xy\"}}}\n\n{\"Item\":{}}\n";
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.on_record = Some(on_record);
+    let output = common::from_ddb(ddb_json, &options);
+    assert_eq!(output, "{\"a\":1}\n{\"a\":\"xy\"}\n{}\n");
+
+    let records = RECORDS.lock().unwrap().clone();
+    assert_eq!(records, vec![(0, 24, 8), (27, 52, 19), (54, 65, 22)]);
+    for (start, end, _) in &records {
+        assert!(ddb_json[*start..*end].starts_with('{'));
+        assert!(ddb_json[*start..*end].ends_with('}'));
+    }
+}
+
+#[test]
+fn test_on_record_in_routed_and_framed_conversion() {
+    use std::sync::Mutex;
+    static RECORDS: Mutex<Vec<(usize, usize, u64)>> = Mutex::new(Vec::new());
+    fn on_record(start: usize, end: usize, bytes_out: u64) {
+        RECORDS.lock().unwrap().push((start, end, bytes_out));
+    }
+    let mut options = ddb_convert::DdbToNormalOptions::new();
+    options.on_record = Some(on_record);
+
+    let ddb_json = r#"{"TableName":"Orders","Item":{"id":{"N":"1"}}}
+{"TableName":"Users","Item":{"name":{"S":"Al"}}}
+"#;
+    let (result, router) = convert_routed(ddb_json, &options);
+    result.unwrap();
+    assert_eq!(router.orders, b"{\"id\":1}\n");
+    assert_eq!(router.users, b"{\"name\":\"Al\"}\n");
+    let records = std::mem::take(&mut *RECORDS.lock().unwrap());
+    assert_eq!(records, vec![(0, 46, 9), (47, 95, 23)]);
+
+    let ddb_json = "{\"Item\":{\"a\":{\"N\":\"42\"}}}\n{\"Item\":{}}\n";
+    let mut reader = ddb_json.as_bytes();
+    let mut output = Vec::new();
+    let mut record_buffer = [0u8; 64];
+    common::with_buffers(|rjiter_buffer, context_buffer| {
+        ddb_convert::convert_ddb_to_normal_framed(
+            &mut reader,
+            &mut output,
+            ddb_convert::Framing::LengthPrefixed(ddb_convert::LengthPrefix::AsciiDecimal),
+            &mut record_buffer,
+            rjiter_buffer,
+            context_buffer,
+            &options,
+        )
+    })
+    .unwrap();
+    assert_eq!(output, b"9 {\"a\":42}\n3 {}\n");
+    let records = std::mem::take(&mut *RECORDS.lock().unwrap());
+    assert_eq!(records, vec![(0, 25, 11), (26, 37, 16)]);
+}
@@ -0,0 +1,108 @@
+//! Tests for the key map helpers `convert_key_to_normal` and `convert_key_to_ddb`
+
+fn key_to_normal(ddb_json: &str) -> Result<String, (ddb_convert::ConversionError, usize)> {
+    let mut reader = ddb_json.as_bytes();
+    let mut output = vec![0u8; 256];
+    let mut output_slice = output.as_mut_slice();
+    let mut rjiter_buffer = [0u8; 16];
+
+    ddb_convert::convert_key_to_normal(&mut reader, &mut output_slice, &mut rjiter_buffer)?;
+
+    let bytes_written = 256 - output_slice.len();
+    Ok(std::str::from_utf8(&output[..bytes_written])
+        .unwrap()
+        .to_string())
+}
+
+fn key_to_ddb(normal_json: &str) -> Result<String, (ddb_convert::ConversionError, usize)> {
+    let mut reader = normal_json.as_bytes();
+    let mut output = vec![0u8; 256];
+    let mut output_slice = output.as_mut_slice();
+    let mut rjiter_buffer = [0u8; 16];
+
+    ddb_convert::convert_key_to_ddb(&mut reader, &mut output_slice, &mut rjiter_buffer)?;
+
+    let bytes_written = 256 - output_slice.len();
+    Ok(std::str::from_utf8(&output[..bytes_written])
+        .unwrap()
+        .to_string())
+}
+
+#[test]
+fn test_key_to_normal() {
+    let ddb_json =
+        r#"{ "pk": {"S": "user#a-long-partition-key"}, "sk": {"N": "42"}, "b": {"B": "AQI="} }"#;
+    assert_eq!(
+        key_to_normal(ddb_json).unwrap(),
+        r#"{"pk":"user#a-long-partition-key","sk":42,"b":"AQI="}"#
+    );
+    assert_eq!(key_to_normal("{}").unwrap(), "{}");
+}
+
+#[test]
+fn test_key_to_ddb() {
+    let normal_json = r#"{ "pk": "user#a-long-partition-key", "sk": -4.2e1 }"#;
+    assert_eq!(
+        key_to_ddb(normal_json).unwrap(),
+        r#"{"pk":{"S":"user#a-long-partition-key"},"sk":{"N":"-4.2e1"}}"#
+    );
+    assert_eq!(key_to_ddb("{}").unwrap(), "{}");
+}
+
+#[test]
+fn test_key_roundtrip() {
+    let ddb_json = r#"{"pk":{"S":"user#1"},"sk":{"N":"7"}}"#;
+    let normal_json = key_to_normal(ddb_json).unwrap();
+    assert_eq!(key_to_ddb(&normal_json).unwrap(), ddb_json);
+}
+
+#[test]
+fn test_key_to_normal_invalid() {
+    let (err, position) = key_to_normal(r#"{"pk":{"BOOL":true}}"#).unwrap_err();
+    assert!(matches!(
+        err,
+        ddb_convert::ConversionError::ParseError { .. }
+    ));
+    assert_eq!(position, 14);
+
+    let (err, _) = key_to_normal(r#"{"pk":{"S":"a","N":"1"}}"#).unwrap_err();
+    assert!(matches!(
+        err,
+        ddb_convert::ConversionError::ParseError { .. }
+    ));
+
+    let (err, _) = key_to_normal(r#"{"pk":{"S":"a"}} {}"#).unwrap_err();
+    assert!(matches!(
+        err,
+        ddb_convert::ConversionError::RJiterError { .. }
+    ));
+
+    for number in ["abc", "", "1 2", "01", "1e", "-", "1}"] {
+        let ddb_json = format!(r#"{{"pk":{{"N":"{number}"}}}}"#);
+        let (err, position) = key_to_normal(&ddb_json).unwrap_err();
+        assert!(
+            matches!(err, ddb_convert::ConversionError::ParseError { .. }),
+            "{number}: {err:?}"
+        );
+        assert_eq!(position, 11, "{number}");
+    }
+}
+
+#[test]
+fn test_key_to_ddb_invalid() {
+    let (err, position) = key_to_ddb(r#"{"pk":true}"#).unwrap_err();
+    assert!(matches!(
+        err,
+        ddb_convert::ConversionError::ParseError { .. }
+    ));
+    assert_eq!(position, 6);
+
+    for number in ["-", "01", "1.", "1e"] {
+        let normal_json = format!(r#"{{"pk":{number}}}"#);
+        let (err, _) = key_to_ddb(&normal_json).unwrap_err();
+        assert!(
+            matches!(err, ddb_convert::ConversionError::RJiterError { .. }),
+            "{number}: {err:?}"
+        );
+    }
+}
@@ -1,5 +1,5 @@
-/// Tests for deeply nested JSON structures
-/// DynamoDB supports up to 32 levels of nesting
+//! Tests for deeply nested JSON structures
+//! DynamoDB supports up to 32 levels of nesting
 
 // Helper function to generate nested object JSON with specified depth
 // Creates structure like: {"level_0": {"level_1": {"level_2": ... {"value": "leaf"}}}}
@@ -14,9 +14,9 @@ fn generate_nested_object_normal_json(depth: usize) -> String {
     }
     json.push_str(r#""value":"leaf""#);
     for _ in 0..depth {
-        json.push_str("}");
+        json.push('}');
     }
-    json.push_str("}");
+    json.push('}');
     json
 }
 
@@ -69,7 +69,7 @@ fn generate_nested_object_ddb_json(depth: usize, with_item_wrapper: bool) -> Str
         json.push_str(r#"{"Item":"#);
     }
 
-    json.push_str("{");
+    json.push('{');
     for i in 0..depth {
         json.push_str(&format!(r#""level_{}":{{"M":{{"#, i));
     }
@@ -77,12 +77,12 @@ fn generate_nested_object_ddb_json(depth: usize, with_item_wrapper: bool) -> Str
     for _ in 0..depth {
         json.push_str("}}");
     }
-    json.push_str("}");
+    json.push('}');
 
     if with_item_wrapper {
-        json.push_str("}");
+        json.push('}');
     }
-    json.push_str("\n");
+    json.push('\n');
     json
 }
 
@@ -98,7 +98,7 @@ fn generate_nested_array_normal_json(depth: usize) -> String {
     for _ in 0..depth {
         json.push(']');
     }
-    json.push_str("}");
+    json.push('}');
     json
 }
 
@@ -127,9 +127,9 @@ fn generate_nested_array_ddb_json(depth: usize, with_item_wrapper: bool) -> Stri
     json.push_str("]}}");
 
     if with_item_wrapper {
-        json.push_str("}");
+        json.push('}');
     }
-    json.push_str("\n");
+    json.push('\n');
     json
 }
 
@@ -0,0 +1,92 @@
+//! Tests for `diff_to_json_patch`
+
+fn diff(old: &str, new: &str) -> Result<(String, usize), (ddb_convert::ConversionError, usize)> {
+    let mut old_reader = old.as_bytes();
+    let mut new_reader = new.as_bytes();
+    let mut output = vec![0u8; 1024];
+    let mut output_slice = output.as_mut_slice();
+    let mut old_buffer = [0u8; 32];
+    let mut new_buffer = [0u8; 32];
+
+    let count = ddb_convert::diff_to_json_patch(
+        &mut old_reader,
+        &mut new_reader,
+        &mut output_slice,
+        b"pk",
+        &mut old_buffer,
+        &mut new_buffer,
+    )?;
+
+    let bytes_written = 1024 - output_slice.len();
+    let patch = std::str::from_utf8(&output[..bytes_written])
+        .unwrap()
+        .to_string();
+    Ok((patch, count))
+}
+
+#[test]
+fn test_patch_between_exports() {
+    let old = r#"{"pk":"a","name":"Ann","age":30}
+{"pk":"b","name":"Bob","tags":["x", "y"]}
+{"pk":"c/d~e","name":"Cid"}
+"#;
+    let new = r#"{"age":31,"pk":"a","name":"Ann","city":"Oslo"}
+{"pk":"b","tags":["x","y"],"name":"Bob"}
+{"pk":"z","nested":{"a":[1,{"b":null}],"c":true}}
+"#;
+    let (patch, count) = diff(old, new).unwrap();
+    assert_eq!(
+        patch,
+        r#"[
+{"op":"replace","path":"/a/age","value":31},
+{"op":"add","path":"/a/city","value":"Oslo"},
+{"op":"remove","path":"/c~1d~0e"},
+{"op":"add","path":"/z","value":{"nested":{"a":[1,{"b":null}],"c":true},"pk":"z"}}
+]
+"#
+    );
+    assert_eq!(count, 4);
+
+    let patch: serde_json::Value = serde_json::from_str(&patch).unwrap();
+    assert_eq!(patch.as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_patch_no_changes() {
+    let records = "{\"pk\":1,\"v\":\"x\"}\n{\"pk\":2,\"v\":\"y\"}\n";
+    assert_eq!(diff(records, records).unwrap(), ("[]\n".to_string(), 0));
+    assert_eq!(diff("", "").unwrap(), ("[]\n".to_string(), 0));
+}
+
+#[test]
+fn test_patch_remove_attribute_and_record() {
+    let old = r#"{"pk":"a","v":1,"w":2} {"pk":"b"}"#;
+    let new = r#"{"pk":"a","v":1}"#;
+    let (patch, _) = diff(old, new).unwrap();
+    assert_eq!(
+        patch,
+        "[\n{\"op\":\"remove\",\"path\":\"/a/w\"},\n{\"op\":\"remove\",\"path\":\"/b\"}\n]\n"
+    );
+}
+
+#[test]
+fn test_patch_errors() {
+    let (error, position) = diff(r#"{"pk":"b"} {"pk":"a"}"#, "").unwrap_err();
+    assert!(matches!(
+        error,
+        ddb_convert::ConversionError::ParseError {
+            context: "Records are not sorted by the key attribute",
+            ..
+        }
+    ));
+    assert_eq!(position, 10);
+
+    let (error, _) = diff("", r#"{"id":"a"}"#).unwrap_err();
+    assert!(matches!(
+        error,
+        ddb_convert::ConversionError::ParseError {
+            context: "Record without the key attribute",
+            ..
+        }
+    ));
+}
@@ -0,0 +1,118 @@
+//! Tests for `plan_ddb_to_normal`
+
+use ddb_convert::{DdbToNormalOptions, InputFormat, Plan, WrapperUsage};
+
+fn plan(input: &str, options: &DdbToNormalOptions) -> Plan {
+    let mut reader = input.as_bytes();
+    let mut rjiter_buffer = [0u8; 1024];
+    let mut context_buffer = [0u8; 2048];
+    ddb_convert::plan_ddb_to_normal(
+        &mut reader,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        options,
+    )
+    .unwrap()
+}
+
+fn convert(input: &str, options: &DdbToNormalOptions) -> Vec<u8> {
+    let mut reader = input.as_bytes();
+    let mut output = Vec::new();
+    let mut rjiter_buffer = [0u8; 1024];
+    let mut context_buffer = [0u8; 2048];
+    ddb_convert::convert_ddb_to_normal_with_options(
+        &mut reader,
+        &mut embedded_io_adapters::std::FromStd::new(&mut output),
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        options,
+    )
+    .unwrap();
+    output
+}
+
+#[test]
+fn test_plan_of_json_lines_with_item_wrapper() {
+    let input = r#"{"Item":{"name":{"S":"Ann"},"age":{"N":"30"}}}
+{"Item":{"name":{"S":"Bob"},"tags":{"SS":["a","b"]},"address":{"M":{"city":{"S":"Oslo"}}}}}
+"#;
+    let options = DdbToNormalOptions::new();
+    let plan = plan(input, &options);
+
+    assert_eq!(plan.records(), 2);
+    assert_eq!(plan.format(), InputFormat::JsonLines);
+    assert_eq!(plan.wrapper(), WrapperUsage::Item);
+    assert_eq!(
+        plan.attribute_types().collect::<Vec<_>>(),
+        [("S", 3), ("N", 1), ("M", 1), ("SS", 1)]
+    );
+    assert_eq!(plan.metrics.bytes_in, input.len() as u64);
+    assert_eq!(
+        plan.estimated_output_bytes(),
+        convert(input, &options).len() as u64
+    );
+
+    let report = plan.to_string();
+    assert!(report.contains("records: 2\n"));
+    assert!(report.contains("format: JsonLines\n"));
+    assert!(report.contains("item wrapper: Item\n"));
+    assert!(report.contains("attribute types: S=3 N=1 M=1 SS=1\n"));
+    assert!(report.contains("max set elements: 2\n"));
+    assert!(report.contains("max string bytes: 4\n"));
+}
+
+#[test]
+fn test_plan_detects_format_and_wrapper() {
+    let options = DdbToNormalOptions::new();
+
+    let plan_one = plan(r#"{"name":{"S":"Ann"}}"#, &options);
+    assert_eq!(plan_one.format(), InputFormat::Json);
+    assert_eq!(plan_one.wrapper(), WrapperUsage::None);
+
+    let mixed = "{\"Item\":{\"a\":{\"N\":\"1\"}}}\n{\"b\":{\"BOOL\":true}}\n";
+    let plan_mixed = plan(mixed, &options);
+    assert_eq!(plan_mixed.wrapper(), WrapperUsage::Mixed);
+
+    // The format is from the layout of the records, not from their number
+    let pretty = "{\n  \"a\": {\"N\": \"1\"}\n}\n{\n  \"b\": {\"N\": \"2\"}\n}\n";
+    assert_eq!(plan(pretty, &options).format(), InputFormat::JsonStream);
+    let one_line = r#"{"a":{"N":"1"}} {"b":{"N":"2"}}"#;
+    assert_eq!(plan(one_line, &options).format(), InputFormat::JsonStream);
+    let with_blank_lines = "\n{\"a\":{\"N\":\"1\"}}\n\n  \n{\"b\":{\"N\":\"2\"}}";
+    assert_eq!(
+        plan(with_blank_lines, &options).format(),
+        InputFormat::JsonLines
+    );
+
+    let plan_empty = plan("  \n", &options);
+    assert_eq!(plan_empty.format(), InputFormat::Empty);
+    assert_eq!(plan_empty.wrapper(), WrapperUsage::None);
+    assert_eq!(plan_empty.attribute_types().count(), 0);
+
+    // The output size depends on the options
+    let mut pretty = DdbToNormalOptions::new();
+    pretty.pretty = true;
+    let plan_pretty = plan(mixed, &pretty);
+    assert_eq!(
+        plan_pretty.estimated_output_bytes(),
+        convert(mixed, &pretty).len() as u64
+    );
+    assert!(plan_pretty.estimated_output_bytes() > plan_mixed.estimated_output_bytes());
+}
+
+#[test]
+fn test_plan_reports_input_errors() {
+    let mut reader = r#"{"name":{"X":"Ann"}}"#.as_bytes();
+    let mut rjiter_buffer = [0u8; 1024];
+    let mut context_buffer = [0u8; 2048];
+    let result = ddb_convert::plan_ddb_to_normal(
+        &mut reader,
+        &mut rjiter_buffer,
+        &mut context_buffer,
+        &DdbToNormalOptions::new(),
+    );
+    assert!(matches!(
+        result,
+        Err((ddb_convert::ConversionError::ParseError { .. }, _))
+    ));
+}
@@ -1,3 +1,5 @@
+mod common;
+
 /// Helper function to convert normal JSON to DDB JSON for testing
 fn convert_to_ddb_test(normal_json: &str, with_item_wrapper: bool) -> String {
     convert_to_ddb_test_with_pretty(normal_json, with_item_wrapper, false)
@@ -544,3 +546,98 @@ fn test_error_incomplete_json_to_ddb() {
         err_msg
     );
 }
+
+/// Helper function to convert normal JSON to DDB JSON, restoring tagged type descriptors
+fn convert_to_ddb_test_passthrough(normal_json: &str) -> String {
+    let mut options = ddb_convert::NormalToDdbOptions::new();
+    options.with_item_wrapper = false;
+    options.unknown_type_policy = ddb_convert::UnknownTypePolicy::PassthroughTagged;
+    common::to_ddb(normal_json, &options)
+}
+
+#[test]
+fn test_to_ddb_tagged_type_restored() {
+    let normal_json = r#"{"a": "x", "v": {"$ddb:VEC": "1,2,3"}, "b": 1}"#;
+    let result = convert_to_ddb_test_passthrough(normal_json);
+    let expected = r#"{"a":{"S":"x"},"v":{"VEC":"1,2,3"},"b":{"N":"1"}}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_to_ddb_tagged_type_in_array() {
+    let normal_json = r#"{"l": [{"$ddb:X": {"k": [1]}}, "s"]}"#;
+    let result = convert_to_ddb_test_passthrough(normal_json);
+    let expected = r#"{"l":{"L":[{"X":{"k":[1]}},{"S":"s"}]}}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_to_ddb_tagged_type_ignored_by_default() {
+    let normal_json = r#"{"v": {"$ddb:X": 1}}"#;
+    let result = convert_to_ddb_test(normal_json, false);
+    let expected = r#"{"v":{"M":{"$ddb:X":{"N":"1"}}}}
+"#;
+    assert_eq!(result, expected);
+}
+
+/// Helper function to convert normal JSON to DDB JSON with the given null policy
+fn convert_to_ddb_test_null_policy(
+    normal_json: &str,
+    null_policy: ddb_convert::NullPolicy,
+) -> String {
+    let mut options = ddb_convert::NormalToDdbOptions::new();
+    options.with_item_wrapper = false;
+    options.null_policy = null_policy;
+    common::to_ddb(normal_json, &options)
+}
+
+#[test]
+fn test_to_ddb_null_policy_emit_null() {
+    let normal_json = r#"{"a": null, "l": [null]}"#;
+    let result = convert_to_ddb_test_null_policy(normal_json, ddb_convert::NullPolicy::EmitNull);
+    let expected = r#"{"a":{"NULL":true},"l":{"L":[{"NULL":true}]}}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_to_ddb_null_policy_empty_string() {
+    let normal_json = r#"{"a": null, "m": {"b": null}, "l": [null, 1]}"#;
+    let result = convert_to_ddb_test_null_policy(normal_json, ddb_convert::NullPolicy::EmptyString);
+    let expected = r#"{"a":{"S":""},"m":{"M":{"b":{"S":""}}},"l":{"L":[{"S":""},{"N":"1"}]}}
+"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_to_ddb_null_policy_omit_attribute() {
+    let omit = ddb_convert::NullPolicy::OmitAttribute;
+    let cases = [
+        (r#"{"a": null, "b": 1}"#, r#"{"b":{"N":"1"}}"#),
+        (
+            r#"{"a": 1, "b": null, "c": 2}"#,
+            r#"{"a":{"N":"1"},"c":{"N":"2"}}"#,
+        ),
+        (r#"{"a": 1, "b": null}"#, r#"{"a":{"N":"1"}}"#),
+        (r#"{"a": null}"#, r#"{}"#),
+        (
+            r#"{"m": {"x": null, "y": null}, "z": "s"}"#,
+            r#"{"m":{"M":{}},"z":{"S":"s"}}"#,
+        ),
+        // Elements of arrays are not attributes, they are kept
+        (
+            r#"{"l": [null, 1]}"#,
+            r#"{"l":{"L":[{"NULL":true},{"N":"1"}]}}"#,
+        ),
+        (
+            r#"{"l": [{"a": null, "b": true}]}"#,
+            r#"{"l":{"L":[{"M":{"b":{"BOOL":true}}}]}}"#,
+        ),
+    ];
+    for (normal_json, expected) in cases {
+        let result = convert_to_ddb_test_null_policy(normal_json, omit);
+        assert_eq!(result, format!("{expected}\n"), "{normal_json}");
+    }
+}
@@ -3,9 +3,89 @@ use embedded_io::{Error as _, Read};
 
 use crate::error::{Error, ErrorType, Result as RJiterResult};
 use crate::jiter::LinePosition;
+use crate::limits::Limit;
+#[cfg(feature = "trace")]
+use crate::trace::{Event, TraceHook};
+
+/// How whitespace between tokens and line breaks are handled, see `RJiter::with_whitespace_policy`.
+///
+/// The default is the lenient behaviour: any ASCII whitespace before a value is skipped,
+/// and only `\n` is a line break.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WhitespacePolicy {
+    /// Skip only the JSON whitespace: space, tab, `\n` and `\r`.
+    /// Other ASCII whitespace, such as form feed, is a parsing error.
+    pub strict: bool,
+    /// A `\r` is also a line break, for error positions and for
+    /// `RJiter::recover_to_next_top_level`. A `\n` after `\r` doesn't start another line.
+    pub cr_line_breaks: bool,
+}
+
+impl WhitespacePolicy {
+    /// Whether the byte is whitespace between tokens
+    #[must_use]
+    pub fn is_whitespace(&self, byte: u8) -> bool {
+        if self.strict {
+            matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+        } else {
+            byte.is_ascii_whitespace()
+        }
+    }
+
+    /// Whether the byte starts a new line. `after_cr` tells if the previous byte is `\r`.
+    #[must_use]
+    pub fn is_line_break(&self, byte: u8, after_cr: bool) -> bool {
+        if self.cr_line_breaks {
+            byte == b'\r' || (byte == b'\n' && !after_cr)
+        } else {
+            byte == b'\n'
+        }
+    }
+
+    // Line-column position of `find` in `data`, as `LinePosition::find`, but with the policy's line breaks
+    fn find_position(self, data: &[u8], find: usize, mut after_cr: bool) -> LinePosition {
+        if !self.cr_line_breaks {
+            return LinePosition::find(data, find);
+        }
+        let mut line = 1;
+        let mut last_line_start = 0;
+        for (index, byte) in data.iter().enumerate() {
+            if self.is_line_break(*byte, after_cr) {
+                line += 1;
+                last_line_start = index + 1;
+            } else if *byte == b'\n' {
+                // The second byte of `\r\n`: the line is already counted
+                last_line_start = index + 1;
+            }
+            after_cr = *byte == b'\r';
+            if index == find {
+                return LinePosition::new(line, index + 1 - last_line_start);
+            }
+        }
+        LinePosition::new(line, data.len().saturating_sub(last_line_start))
+    }
+}
+
+// The limit of the input bytes, see `Limits::max_total_bytes`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TotalLimit {
+    pub(crate) max: usize,
+    // Whether the input has more bytes than `max`. Then a read is `LimitExceeded`.
+    is_exceeded: bool,
+}
+
+impl TotalLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            is_exceeded: false,
+        }
+    }
+}
 
 /// A buffer for reading JSON data.
 /// Is a private struct, the "pub" is only for testing.
+#[non_exhaustive]
 pub struct Buffer<'buf, R: Read> {
     reader: &'buf mut R,
     /// The working buffer for reading JSON data.
@@ -15,7 +95,28 @@ pub struct Buffer<'buf, R: Read> {
     /// Number of bytes that have been shifted out of the buffer.
     pub n_shifted_out: usize,
     /// Line position correction due to shifting operations.
+    /// It is the position after the shifted-out bytes and the counted prefix.
     pub pos_shifted: LinePosition,
+    /// Number of bytes at the beginning of the buffer which are already counted in `pos_shifted`.
+    /// Such a prefix appears when the buffer is shifted not to the position 0.
+    pub n_pos_counted: usize,
+    /// Whether the last read returned 0 bytes while there was free space in the buffer.
+    /// A stream can get more data later, therefore it is only a hint of the end of input.
+    pub is_eof_seen: bool,
+    /// Which bytes are whitespace and line breaks.
+    pub whitespace: WhitespacePolicy,
+    /// Whether the last byte counted in `pos_shifted` is `\r`.
+    pub is_after_cr: bool,
+    /// Minimal number of bytes for `read_more` to collect from several reads, 0 to read once.
+    pub min_fill: usize,
+    /// Whether the bytes come from `append` instead of the reader, see `RJiter::with_feed_mode`.
+    /// Then a read is `NeedMoreData`, or 0 bytes after `is_eof_seen` is set.
+    pub is_feed_mode: bool,
+    // The maximum number of bytes to take from the reader or from `append`
+    pub(crate) total_limit: Option<TotalLimit>,
+    /// The hook for the reads and shifts, and for the events of `RJiter`.
+    #[cfg(feature = "trace")]
+    pub(crate) trace_hook: Option<TraceHook>,
 }
 
 impl<'buf, R: Read> Buffer<'buf, R> {
@@ -28,31 +129,179 @@ impl<'buf, R: Read> Buffer<'buf, R> {
             n_bytes: 0,
             n_shifted_out: 0,
             pos_shifted: LinePosition::new(0, 0),
+            n_pos_counted: 0,
+            is_eof_seen: false,
+            whitespace: WhitespacePolicy::default(),
+            is_after_cr: false,
+            min_fill: 0,
+            is_feed_mode: false,
+            total_limit: None,
+            #[cfg(feature = "trace")]
+            trace_hook: None,
+        }
+    }
+
+    /// Start over with a new reader: drop the buffered bytes and reset the
+    /// index and the position. The whitespace policy, `min_fill`, the feed mode, the limit
+    /// and the hook are kept.
+    pub fn reset(&mut self, reader: &'buf mut R) {
+        self.reader = reader;
+        self.n_bytes = 0;
+        self.n_shifted_out = 0;
+        self.pos_shifted = LinePosition::new(0, 0);
+        self.n_pos_counted = 0;
+        self.is_eof_seen = false;
+        self.is_after_cr = false;
+        if let Some(limit) = &mut self.total_limit {
+            limit.is_exceeded = false;
         }
     }
 
     /// Read from the underlying reader into the buffer.
     ///
+    /// If `min_fill` is set and the reader returns less, read again until
+    /// `min_fill` bytes are collected, the buffer is full, or the reader returns
+    /// 0 bytes or an error. On an error, the bytes collected before it stay in the buffer.
+    ///
     /// Returns the number of bytes read.
     ///
     /// # Errors
     ///
-    /// From the underlying reader.
+    /// From the underlying reader, also after some bytes are collected.
     pub fn read_more(&mut self) -> RJiterResult<usize> {
+        let n_bytes_before = self.n_bytes;
+        let fill_to = n_bytes_before
+            .saturating_add(self.min_fill)
+            .min(self.buf.len());
+        let mut n_new_bytes = self.read_once()?;
+        while n_new_bytes > 0 && self.n_bytes < fill_to {
+            n_new_bytes = self.read_once()?;
+        }
+        Ok(self.n_bytes - n_bytes_before)
+    }
+
+    /// Call the trace hook, if any
+    #[cfg(feature = "trace")]
+    pub fn trace(&self, event: Event) {
+        if let Some(hook) = self.trace_hook {
+            hook(event);
+        }
+    }
+
+    fn read_once(&mut self) -> RJiterResult<usize> {
+        if self.total_limit.is_some_and(|limit| limit.is_exceeded) {
+            return Err(self.over_limit_error());
+        }
+        if self.is_feed_mode {
+            // A full buffer can't get more bytes, as with a reader
+            if self.is_eof_seen || self.n_bytes >= self.buf.len() {
+                return Ok(0);
+            }
+            let index = self.n_shifted_out + self.n_bytes;
+            return Err(Error::new(ErrorType::NeedMoreData, index, |index| {
+                self.position_of(index)
+            }));
+        }
+        #[cfg(feature = "trace")]
+        self.trace(Event::ReadStart {
+            capacity: self.buf.len() - self.n_bytes,
+        });
+        // With the limit, read at most one byte after it, to know if the input is longer
+        let read_end = match self.total_limit {
+            Some(TotalLimit { max, .. }) => {
+                let room = max.saturating_sub(self.n_shifted_out + self.n_bytes);
+                min(
+                    self.n_bytes.saturating_add(room).saturating_add(1),
+                    self.buf.len(),
+                )
+            }
+            None => self.buf.len(),
+        };
         // The only place where `n_bytes` is increased is this `read_more` function.
         // As long as `read` works correctly, `n_bytes` is less or equal to the buffer size.
         #[allow(clippy::indexing_slicing)]
-        let n_new_bytes = self
+        let read_result = self
             .reader
-            .read(&mut self.buf[self.n_bytes..])
-            .map_err(|e| Error {
-                error_type: ErrorType::IoError { kind: e.kind() },
-                index: self.n_bytes,
-            })?;
+            .read(&mut self.buf[self.n_bytes..read_end])
+            .map_err(|e| e.kind());
+        #[cfg(feature = "trace")]
+        self.trace(Event::ReadEnd {
+            n_bytes: read_result.unwrap_or(0),
+            error: read_result.err(),
+        });
+        let n_new_bytes = read_result.map_err(|kind| {
+            let index = self.n_shifted_out + self.n_bytes;
+            Error::new(ErrorType::IoError { kind }, index, |index| {
+                self.position_of(index)
+            })
+        })?;
+        if self.n_bytes < self.buf.len() {
+            self.is_eof_seen = n_new_bytes == 0;
+        }
         self.n_bytes += n_new_bytes;
+        if let Some(limit) = &mut self.total_limit {
+            if self.n_shifted_out + self.n_bytes > limit.max {
+                // Drop the bytes after the limit, they are never parsed
+                self.n_bytes = limit.max.saturating_sub(self.n_shifted_out);
+                limit.is_exceeded = true;
+                return Err(self.over_limit_error());
+            }
+        }
         Ok(n_new_bytes)
     }
 
+    fn over_limit_error(&self) -> Error {
+        let max = self.total_limit.map_or(0, |limit| limit.max);
+        Error::new(
+            ErrorType::LimitExceeded {
+                limit: Limit::TotalBytes,
+                max,
+            },
+            max,
+            |index| self.position_of(index),
+        )
+    }
+
+    /// Copy the bytes after the buffered ones, as many as fit into the buffer
+    /// and into `Limits::max_total_bytes`.
+    ///
+    /// Returns the number of copied bytes.
+    pub fn append(&mut self, data: &[u8]) -> usize {
+        let mut data = data;
+        if let Some(limit) = &mut self.total_limit {
+            let room = limit.max.saturating_sub(self.n_shifted_out + self.n_bytes);
+            if data.len() > room {
+                limit.is_exceeded = true;
+                data = data.get(..room).unwrap_or_default();
+            }
+        }
+        let free = self.buf.get_mut(self.n_bytes..).unwrap_or_default();
+        let n_new_bytes = min(free.len(), data.len());
+        if let (Some(dst), Some(src)) = (free.get_mut(..n_new_bytes), data.get(..n_new_bytes)) {
+            dst.copy_from_slice(src);
+        }
+        self.n_bytes += n_new_bytes;
+        n_new_bytes
+    }
+
+    /// Get the line-column position of an absolute index in the input stream.
+    /// The index should point into the current buffer content, otherwise
+    /// the position is clamped to the buffer boundaries.
+    #[must_use]
+    pub fn position_of(&self, index: usize) -> LinePosition {
+        let n_counted = min(self.n_pos_counted, self.n_bytes);
+        let index = index.saturating_sub(self.n_shifted_out + n_counted);
+        let data = self.buf.get(n_counted..self.n_bytes).unwrap_or_default();
+        let pos = self.whitespace.find_position(data, index, self.is_after_cr);
+        // The shifted-out part of the line counts only for the first line in the buffer
+        let column = if pos.line == 1 {
+            pos.column + self.pos_shifted.column
+        } else {
+            pos.column
+        };
+        LinePosition::new(pos.line + self.pos_shifted.line, column)
+    }
+
     /// Shift the buffer to the left, and update the index and line-column position.
     ///
     /// # Arguments
@@ -62,15 +311,23 @@ impl<'buf, R: Read> Buffer<'buf, R> {
     pub fn shift_buffer(&mut self, to_pos: usize, from_pos: usize) {
         let safe_from_pos = min(from_pos, self.n_bytes);
         if to_pos < safe_from_pos {
-            // `to_pos>=0` (`usize`), `to_pos < safe_from_pos` (if-branch), `safe_from_pos`<=`n_bytes <= buf.len()` (contract)
+            // The bytes before `to_pos` stay in the buffer, but they precede the shifted-out
+            // bytes in the stream. Therefore count them now, and remember them as counted.
+            let count_from = min(self.n_pos_counted, safe_from_pos);
+            self.n_pos_counted = to_pos;
+            // `count_from <= safe_from_pos` (min), `safe_from_pos`<=`n_bytes <= buf.len()` (contract)
             #[allow(clippy::indexing_slicing)]
-            for ch in &self.buf[to_pos..safe_from_pos] {
-                if *ch == b'\n' {
+            for ch in &self.buf[count_from..safe_from_pos] {
+                if self.whitespace.is_line_break(*ch, self.is_after_cr) {
                     self.pos_shifted.line += 1;
                     self.pos_shifted.column = 0;
+                } else if *ch == b'\n' && self.whitespace.cr_line_breaks {
+                    // The second byte of `\r\n`: the line is already counted
+                    self.pos_shifted.column = 0;
                 } else {
                     self.pos_shifted.column += 1;
                 }
+                self.is_after_cr = *ch == b'\r';
             }
         }
 
@@ -79,12 +336,18 @@ impl<'buf, R: Read> Buffer<'buf, R> {
                 self.buf.copy_within(from_pos..self.n_bytes, to_pos);
             }
             let n_shifted_out = safe_from_pos - to_pos;
+            #[cfg(feature = "trace")]
+            self.trace(Event::Shift {
+                n_shifted_out,
+                n_moved: self.n_bytes - safe_from_pos,
+            });
             self.n_bytes -= n_shifted_out;
             self.n_shifted_out += n_shifted_out;
         }
     }
 
-    /// Skip over any ASCII whitespace characters starting at the given position.
+    /// Skip over whitespace characters starting at the given position.
+    /// Which bytes are whitespace is defined by `whitespace`.
     /// Read-shift-read-shift-read-shift... until non-whitespace is found or EOF is reached.
     ///
     /// # Arguments
@@ -95,17 +358,66 @@ impl<'buf, R: Read> Buffer<'buf, R> {
     ///
     /// From the underlying reader.
     pub fn skip_spaces(&mut self, pos: usize) -> RJiterResult<()> {
+        let whitespace = self.whitespace;
+        self.skip_while(|b| whitespace.is_whitespace(b), pos)
+    }
+
+    /// Skip a `//` or a `/* */` comment at the given position, for `RJiter::with_tolerant_mode`.
+    /// The line break after a line comment is not skipped. An unterminated block comment
+    /// is skipped up to the end of input.
+    /// Returns `true` if a comment is found and skipped.
+    ///
+    /// The comment markers are dropped last. If reading fails in the middle,
+    /// for example, with `NeedMoreData` in feed mode, the rest is still a comment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorType::BufferFull` if the beginning of the comment doesn't fit into the buffer.
+    /// Also returns errors from the underlying reader.
+    pub(crate) fn skip_comment(&mut self, pos: usize) -> RJiterResult<bool> {
+        let (start_pos, end_pos) = self.collect_count(2, pos, false)?;
+        let is_block = match self.buf.get(start_pos..end_pos) {
+            Some(b"//") => false,
+            Some(b"/*") => true,
+            _ => return Ok(false),
+        };
+        let content_pos = end_pos;
+        if is_block {
+            loop {
+                self.skip_while(|b| b != b'*', content_pos)?;
+                let (start_pos, end_pos) = self.collect_count(2, content_pos, false)?;
+                match self.buf.get(start_pos..end_pos) {
+                    Some(b"*/") => {
+                        self.shift_buffer(content_pos, end_pos);
+                        break;
+                    }
+                    // The end of input inside the comment, the parser reports it
+                    Some([]) => break,
+                    // A star inside the comment
+                    _ => self.shift_buffer(content_pos, content_pos + 1),
+                }
+            }
+        } else {
+            self.skip_while(|b| !matches!(b, b'\n' | b'\r'), content_pos)?;
+        }
+        self.shift_buffer(pos, content_pos);
+        Ok(true)
+    }
+
+    // Skip the bytes at the given position while the predicate is true,
+    // shifting the buffer if it is full of such bytes
+    fn skip_while(&mut self, predicate: impl Fn(u8) -> bool, pos: usize) -> RJiterResult<()> {
         loop {
-            match self.collect_while(|b| b.is_ascii_whitespace(), pos, false) {
-                Ok((_start_pos, end_of_whitespace)) => {
-                    // Found non-whitespace or EOF
-                    if end_of_whitespace > pos {
-                        self.shift_buffer(pos, end_of_whitespace);
+            match self.collect_while(&predicate, pos, false) {
+                Ok((_start_pos, end_pos)) => {
+                    // Found a rejected byte or EOF
+                    if end_pos > pos {
+                        self.shift_buffer(pos, end_pos);
                     }
                     break;
                 }
                 Err(e) if e.error_type == ErrorType::BufferFull => {
-                    // Buffer is full of whitespace, shift and continue
+                    // Buffer is full of accepted bytes, shift and continue
                     self.shift_buffer(pos, self.n_bytes);
                 }
                 Err(e) => return Err(e),
@@ -114,6 +426,30 @@ impl<'buf, R: Read> Buffer<'buf, R> {
         Ok(())
     }
 
+    /// Skip the bytes `prefix` at the given position if the buffer starts with them there.
+    /// Returns `true` if the prefix is found and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - The position in the buffer to check the prefix at
+    /// * `prefix` - The bytes to skip
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorType::BufferFull` if the prefix doesn't fit into the buffer.
+    /// Also returns errors from the underlying reader.
+    pub fn skip_prefix(&mut self, pos: usize, prefix: &[u8]) -> RJiterResult<bool> {
+        if prefix.is_empty() {
+            return Ok(false);
+        }
+        let (start_pos, end_pos) = self.collect_count(prefix.len(), pos, false)?;
+        if self.buf.get(start_pos..end_pos) != Some(prefix) {
+            return Ok(false);
+        }
+        self.shift_buffer(start_pos, end_pos);
+        Ok(true)
+    }
+
     /// Collect bytes while a predicate is true, starting at the given position.
     /// Returns a tuple of (`start_position`, `end_position`) where `end_position` is the offset
     /// of the first rejected byte, or EOF.
@@ -161,10 +497,11 @@ impl<'buf, R: Read> Buffer<'buf, R> {
                 // Buffer is full, need to shift to make space
                 if !allow_shift || shifted || start_pos == 0 {
                     // Shifting not allowed, already shifted, or start_pos=0 (nothing to discard) - error!
-                    return Err(Error {
-                        error_type: ErrorType::BufferFull,
-                        index: self.n_shifted_out,
-                    });
+                    return Err(Error::new(
+                        ErrorType::BufferFull,
+                        self.n_shifted_out,
+                        |index| self.position_of(index),
+                    ));
                 }
                 // Shift once to make space, discarding everything before start_pos
                 // After shift, everything moves left by start_pos positions
@@ -221,20 +558,22 @@ impl<'buf, R: Read> Buffer<'buf, R> {
                 // Buffer is full, need to shift to make space
                 if !allow_shift || shifted || current_start == 0 {
                     // Shifting not allowed, already shifted, or start_pos=0 (nothing to discard) - error!
-                    return Err(Error {
-                        error_type: ErrorType::BufferFull,
-                        index: self.n_shifted_out,
-                    });
+                    return Err(Error::new(
+                        ErrorType::BufferFull,
+                        self.n_shifted_out,
+                        |index| self.position_of(index),
+                    ));
                 }
 
                 // Check if even after shifting, the buffer would be too small
                 let available_after_shift = self.buf.len();
                 if count > available_after_shift {
                     // Even after shifting, buffer is too small for the requested count
-                    return Err(Error {
-                        error_type: ErrorType::BufferFull,
-                        index: self.n_shifted_out,
-                    });
+                    return Err(Error::new(
+                        ErrorType::BufferFull,
+                        self.n_shifted_out,
+                        |index| self.position_of(index),
+                    ));
                 }
 
                 // Shift once to make space, discarding everything before current_start
@@ -314,8 +653,13 @@ impl<R: Read> core::fmt::Debug for Buffer<'_, R> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
-            "Buffer {{ n_bytes: {:?}, buf: {:?}, n_shifted_out: {:?}, pos_shifted: {:?} }}",
-            self.n_bytes, self.buf, self.n_shifted_out, self.pos_shifted
+            "Buffer {{ n_bytes: {:?}, buf: {:?}, n_shifted_out: {:?}, pos_shifted: {:?}, n_pos_counted: {:?}, is_eof_seen: {:?} }}",
+            self.n_bytes,
+            self.buf,
+            self.n_shifted_out,
+            self.pos_shifted,
+            self.n_pos_counted,
+            self.is_eof_seen
         )
     }
 }
@@ -0,0 +1,174 @@
+use embedded_io::Read;
+
+use crate::buffer::WhitespacePolicy;
+use crate::limits::Limits;
+use crate::rjiter::RJiter;
+#[cfg(feature = "trace")]
+use crate::trace::TraceHook;
+
+/// Builder for a configured `RJiter`, see `RJiter::builder`.
+///
+/// The builder keeps the reader, the buffer and the options, and `build` creates
+/// the parser. Each option is the same as the `with_*` function of `RJiter`.
+/// The options which are not set keep the defaults of `RJiter::new`.
+///
+/// ```
+/// use rjiter::{Limits, RJiter, WhitespacePolicy};
+///
+/// let mut reader = "data: [1, 2]".as_bytes();
+/// let mut buffer = [0u8; 16];
+/// let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+///     .limits(Limits {
+///         max_total_bytes: Some(1024),
+///         ..Limits::default()
+///     })
+///     .whitespace_policy(WhitespacePolicy {
+///         strict: true,
+///         ..WhitespacePolicy::default()
+///     })
+///     .skip_tokens(&[b"data:"])
+///     .build();
+///
+/// assert_eq!(rjiter.skip_tokens().unwrap(), 1);
+/// assert!(rjiter.next_array().unwrap().is_some());
+/// ```
+pub struct RJiterBuilder<'rj, R: Read> {
+    reader: &'rj mut R,
+    buf: &'rj mut [u8],
+    max_token_len: Option<usize>,
+    limits: Limits,
+    whitespace_policy: WhitespacePolicy,
+    min_fill: usize,
+    feed_mode: bool,
+    allow_inf_nan: bool,
+    tolerant_mode: bool,
+    skip_tokens: &'rj [&'rj [u8]],
+    #[cfg(feature = "trace")]
+    trace_hook: Option<TraceHook>,
+}
+
+impl<'rj, R: Read> RJiterBuilder<'rj, R> {
+    pub(crate) fn new(reader: &'rj mut R, buf: &'rj mut [u8]) -> Self {
+        RJiterBuilder {
+            reader,
+            buf,
+            max_token_len: None,
+            limits: Limits::default(),
+            whitespace_policy: WhitespacePolicy::default(),
+            min_fill: 0,
+            feed_mode: false,
+            allow_inf_nan: false,
+            tolerant_mode: false,
+            skip_tokens: &[],
+            #[cfg(feature = "trace")]
+            trace_hook: None,
+        }
+    }
+
+    /// See `RJiter::with_max_token_len`
+    #[must_use]
+    pub fn max_token_len(mut self, max_len: usize) -> Self {
+        self.max_token_len = Some(max_len);
+        self
+    }
+
+    /// See `RJiter::with_limits`
+    #[must_use]
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// See `RJiter::with_whitespace_policy`
+    #[must_use]
+    pub fn whitespace_policy(mut self, policy: WhitespacePolicy) -> Self {
+        self.whitespace_policy = policy;
+        self
+    }
+
+    /// See `RJiter::with_read_coalescing`
+    #[must_use]
+    pub fn read_coalescing(mut self, min_fill: usize) -> Self {
+        self.min_fill = min_fill;
+        self
+    }
+
+    /// See `RJiter::with_feed_mode`
+    #[must_use]
+    pub fn feed_mode(mut self) -> Self {
+        self.feed_mode = true;
+        self
+    }
+
+    /// See `RJiter::with_allow_inf_nan`
+    #[must_use]
+    pub fn allow_inf_nan(mut self) -> Self {
+        self.allow_inf_nan = true;
+        self
+    }
+
+    /// See `RJiter::with_tolerant_mode`
+    #[must_use]
+    pub fn tolerant_mode(mut self) -> Self {
+        self.tolerant_mode = true;
+        self
+    }
+
+    /// See `RJiter::with_skip_tokens`
+    #[must_use]
+    pub fn skip_tokens(mut self, tokens: &'rj [&'rj [u8]]) -> Self {
+        self.skip_tokens = tokens;
+        self
+    }
+
+    /// See `RJiter::with_trace_hook`
+    #[cfg(feature = "trace")]
+    #[must_use]
+    pub fn trace_hook(mut self, hook: TraceHook) -> Self {
+        self.trace_hook = Some(hook);
+        self
+    }
+
+    /// Create the parser with the options
+    #[must_use]
+    pub fn build(self) -> RJiter<'rj, R> {
+        let mut rjiter = RJiter::new(self.reader, self.buf)
+            .with_limits(self.limits)
+            .with_whitespace_policy(self.whitespace_policy)
+            .with_read_coalescing(self.min_fill)
+            .with_skip_tokens(self.skip_tokens);
+        if let Some(max_len) = self.max_token_len {
+            rjiter = rjiter.with_max_token_len(max_len);
+        }
+        if self.feed_mode {
+            rjiter = rjiter.with_feed_mode();
+        }
+        if self.allow_inf_nan {
+            rjiter = rjiter.with_allow_inf_nan();
+        }
+        if self.tolerant_mode {
+            rjiter = rjiter.with_tolerant_mode();
+        }
+        #[cfg(feature = "trace")]
+        if let Some(hook) = self.trace_hook {
+            rjiter = rjiter.with_trace_hook(hook);
+        }
+        rjiter
+    }
+}
+
+impl<R: Read> core::fmt::Debug for RJiterBuilder<'_, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RJiterBuilder")
+            .field("buffer_len", &self.buf.len())
+            .field("max_token_len", &self.max_token_len)
+            .field("limits", &self.limits)
+            .field("whitespace_policy", &self.whitespace_policy)
+            .field("min_fill", &self.min_fill)
+            .field("feed_mode", &self.feed_mode)
+            .field("allow_inf_nan", &self.allow_inf_nan)
+            .field("tolerant_mode", &self.tolerant_mode)
+            .field("skip_tokens", &self.skip_tokens)
+            .finish_non_exhaustive()
+    }
+}
@@ -2,6 +2,7 @@
 extern crate alloc;
 
 use crate::jiter::{JiterError, JiterErrorType, JsonErrorType, JsonType, LinePosition};
+use crate::limits::Limit;
 
 #[cfg(feature = "std")]
 use alloc::{format, string::String};
@@ -12,6 +13,7 @@ pub type Result<T> = core::result::Result<T, Error>;
 /// Like `Jiter::JiterErrorType`, but also with `IoError`
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[allow(clippy::module_name_repetitions)]
+#[non_exhaustive]
 pub enum ErrorType {
     /// JSON parsing error from the underlying jiter.
     JsonError(JsonErrorType),
@@ -29,6 +31,92 @@ pub enum ErrorType {
     },
     /// Buffer is full and cannot accept more data.
     BufferFull,
+    /// A string, a key or a number is longer than the limit, see `RJiter::with_max_token_len`.
+    TokenTooLong {
+        /// The maximum token length in bytes.
+        max_len: usize,
+    },
+    /// The decoded value doesn't fit into the caller's output slice,
+    /// see `RJiter::read_long_str_into`, `RJiter::known_str_into` and `RJiter::next_key_decoded`.
+    OutputFull {
+        /// The bytes written to the output slice before it was full.
+        written: usize,
+    },
+    /// In feed mode, the fed bytes end inside the token. Feed more and call again,
+    /// see `RJiter::with_feed_mode`.
+    NeedMoreData,
+    /// A string, a number or the input is longer than the limit, see `RJiter::with_limits`.
+    LimitExceeded {
+        /// Which limit is exceeded.
+        limit: Limit,
+        /// The maximum in bytes.
+        max: usize,
+    },
+}
+
+/// The broad kind of an error, to decide whether to retry or to abort,
+/// see `ErrorType::category`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+pub enum ErrorCategory {
+    /// The reader or the writer failed.
+    Io,
+    /// The input is not valid JSON, or the value is not of the expected type.
+    Syntax,
+    /// A buffer or a limit is too small for the input.
+    Capacity,
+    /// The input ends inside a value, or, in feed mode, the fed bytes end inside a token.
+    Eof,
+}
+
+impl ErrorType {
+    /// The category of the error.
+    ///
+    /// The categories are stable: a new error type is added to one of them.
+    #[must_use]
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ErrorType::IoError { .. } => ErrorCategory::Io,
+            ErrorType::JsonError(
+                JsonErrorType::EofWhileParsingList
+                | JsonErrorType::EofWhileParsingObject
+                | JsonErrorType::EofWhileParsingString
+                | JsonErrorType::EofWhileParsingValue,
+            )
+            | ErrorType::NeedMoreData => ErrorCategory::Eof,
+            ErrorType::JsonError(_) | ErrorType::WrongType { .. } => ErrorCategory::Syntax,
+            ErrorType::BufferFull
+            | ErrorType::TokenTooLong { .. }
+            | ErrorType::OutputFull { .. }
+            | ErrorType::LimitExceeded { .. } => ErrorCategory::Capacity,
+        }
+    }
+
+    /// Whether the same call can succeed later without changing the input or the settings:
+    /// `NeedMoreData` after feeding more bytes, and the I/O errors
+    /// `Interrupted` and `TimedOut`, if the reader or the writer doesn't lose data on them.
+    ///
+    /// Other errors repeat on the same input, the caller should abort or
+    /// skip the value, for example, with `RJiter::recover_to_next_top_level`.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorType::NeedMoreData
+                | ErrorType::IoError {
+                    kind: embedded_io::ErrorKind::Interrupted | embedded_io::ErrorKind::TimedOut
+                }
+        )
+    }
+
+    pub(crate) fn from_jiter_error_type(error_type: JiterErrorType) -> ErrorType {
+        match error_type {
+            JiterErrorType::JsonError(json_error_type) => ErrorType::JsonError(json_error_type),
+            JiterErrorType::WrongType { expected, actual } => {
+                ErrorType::WrongType { expected, actual }
+            }
+        }
+    }
 }
 
 #[cfg(any(feature = "std", feature = "display"))]
@@ -41,17 +129,36 @@ impl core::fmt::Display for ErrorType {
             }
             ErrorType::IoError { kind } => write!(f, "I/O operation failed: {kind}"),
             ErrorType::BufferFull => write!(f, "buffer is full"),
+            ErrorType::TokenTooLong { max_len } => {
+                write!(f, "token is longer than {max_len} bytes")
+            }
+            ErrorType::OutputFull { written } => {
+                write!(f, "output is full after {written} bytes")
+            }
+            ErrorType::NeedMoreData => write!(f, "more input is needed"),
+            ErrorType::LimitExceeded { limit, max } => {
+                let what = match limit {
+                    Limit::StringBytes => "string",
+                    Limit::NumberBytes => "number",
+                    Limit::TotalBytes => "input",
+                };
+                write!(f, "{what} is longer than the limit of {max} bytes")
+            }
         }
     }
 }
 
 /// An error from the `RJiter` iterator.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct Error {
     /// The type of error that occurred.
     pub error_type: ErrorType,
     /// The byte index in the input where the error occurred.
     pub index: usize,
+    // Captured when the error is created, before the buffer is shifted.
+    // `None` for the retryable errors, see `position`.
+    pub(crate) position: Option<LinePosition>,
 }
 
 #[cfg(any(feature = "std", feature = "display"))]
@@ -62,29 +169,73 @@ impl core::fmt::Display for Error {
 }
 
 impl Error {
-    pub(crate) fn from_jiter_error(index: usize, jiter_error: JiterError) -> Error {
+    // The retryable errors are on the hot paths of the feed mode and of
+    // non-blocking readers, and the line-column position costs a pass over
+    // the buffer. Therefore only the other errors locate themselves.
+    pub(crate) fn new(
+        error_type: ErrorType,
+        index: usize,
+        locate: impl FnOnce(usize) -> LinePosition,
+    ) -> Error {
+        let position = (!error_type.is_retryable()).then(|| locate(index));
         Error {
-            error_type: match jiter_error.error_type {
-                JiterErrorType::JsonError(json_error_type) => ErrorType::JsonError(json_error_type),
-                JiterErrorType::WrongType { expected, actual } => {
-                    ErrorType::WrongType { expected, actual }
-                }
-            },
-            index: jiter_error.index + index,
+            error_type,
+            index,
+            position,
         }
     }
 
-    pub(crate) fn from_json_error(index: usize, json_error_type: JsonErrorType) -> Error {
-        Error {
-            error_type: ErrorType::JsonError(json_error_type),
-            index,
-        }
+    pub(crate) fn from_jiter_error(
+        index: usize,
+        jiter_error: JiterError,
+        locate: impl FnOnce(usize) -> LinePosition,
+    ) -> Error {
+        Error::new(
+            ErrorType::from_jiter_error_type(jiter_error.error_type),
+            jiter_error.index + index,
+            locate,
+        )
+    }
+
+    pub(crate) fn from_json_error(
+        index: usize,
+        json_error_type: JsonErrorType,
+        locate: impl FnOnce(usize) -> LinePosition,
+    ) -> Error {
+        Error::new(ErrorType::JsonError(json_error_type), index, locate)
+    }
+
+    /// The category of the error, see `ErrorType::category`.
+    #[must_use]
+    pub fn category(&self) -> ErrorCategory {
+        self.error_type.category()
+    }
+
+    /// Whether the call can succeed later, see `ErrorType::is_retryable`.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        self.error_type.is_retryable()
+    }
+
+    /// Get the line and column where the error occurred.
+    ///
+    /// The position is captured when the error is created, before the buffer is shifted.
+    /// It is `None` for the retryable errors (see `is_retryable`): nothing is consumed
+    /// on them, and `get_position` finds the position in `rjiter`.
+    #[must_use]
+    pub fn position(&self) -> Option<&LinePosition> {
+        self.position.as_ref()
     }
 
     /// Get the position of the error in the stream.
+    ///
+    /// The captured position, see `position`, or, for a retryable error,
+    /// the position of the index in `rjiter`.
     #[must_use]
     pub fn get_position<R: embedded_io::Read>(&self, rjiter: &crate::RJiter<R>) -> LinePosition {
-        rjiter.error_position(self.index)
+        self.position
+            .clone()
+            .unwrap_or_else(|| rjiter.error_position(self.index))
     }
 
     /// Write a description of the error with position information to the provided formatter.
@@ -98,8 +249,7 @@ impl Error {
         rjiter: &crate::RJiter<R>,
         f: &mut core::fmt::Formatter<'_>,
     ) -> core::fmt::Result {
-        let position = self.get_position(rjiter);
-        write!(f, "{} at {}", self.error_type, position)
+        write!(f, "{} at {}", self.error_type, self.get_position(rjiter))
     }
 
     /// Get the description of the error with position information as a String.
@@ -107,8 +257,7 @@ impl Error {
     #[cfg(feature = "std")]
     #[must_use]
     pub fn description<R: embedded_io::Read>(&self, rjiter: &crate::RJiter<R>) -> String {
-        let position = self.get_position(rjiter);
-        format!("{} at {}", self.error_type, position)
+        format!("{} at {}", self.error_type, self.get_position(rjiter))
     }
 }
 
@@ -3,13 +3,35 @@
 
 /// Buffer management for streaming JSON parsing.
 pub mod buffer;
+/// Builder for `RJiter` with options.
+pub mod builder;
 /// Error types and handling for `RJiter`.
 pub mod error;
+mod lifetime;
+/// Resource limits for untrusted input.
+pub mod limits;
 /// Streaming JSON parser implementation.
 pub mod rjiter;
+/// Segments of long strings which can be decoded separately.
+pub mod segmenter;
+/// Reader for the decoded bytes of a json string.
+pub mod string_reader;
+/// Readers which split the input into chunks, to test the parsing at buffer boundaries.
+#[cfg(feature = "testing")]
+pub mod testing;
+/// Instrumentation points for profiling.
+#[cfg(feature = "trace")]
+pub mod trace;
 
+pub use buffer::WhitespacePolicy;
+pub use builder::RJiterBuilder;
 pub use error::Error;
 pub use error::Result;
-pub use rjiter::RJiter;
+pub use limits::{Limit, Limits};
+pub use rjiter::{NumberKind, PeekedValue, RJiter, TokenMatch};
+pub use segmenter::Segmenter;
+pub use string_reader::StringReader;
+#[cfg(feature = "trace")]
+pub use trace::{Event, TraceHook};
 
 pub use jiter;
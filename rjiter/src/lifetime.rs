@@ -0,0 +1,110 @@
+//! The lifetime extensions of `RJiter`, the only unsafe code of the crate.
+//!
+//! `RJiter` owns the buffer as `&'rj mut [u8]` inside `Buffer` and parses it
+//! with `Jiter<'rj>`, which needs a shared view of the same bytes. The borrow
+//! checker can't express that the view is valid only until the buffer changes,
+//! therefore the lifetimes are extended here, under the following rules:
+//!
+//! - `parse_window` gives `Jiter` the view of the buffer. After each change
+//!   of the buffer (read, shift, feed), `RJiter` creates a new `Jiter`
+//!   and never uses the old one.
+//! - `detach` extends the lifetime of a parsed value, which borrows the buffer
+//!   or the tape of `Jiter`, to `'rj`. It is needed to return the value out of
+//!   the retry loop in `loop_until_success`, which the borrow checker rejects.
+//!   The public functions shorten the lifetime back to the borrow of `RJiter`,
+//!   therefore the caller can't change the buffer or the parser while
+//!   the value is alive.
+//!
+//! The tests in `tests/soundness_test.rs` exercise these paths and are meant
+//! to be run under Miri, see the `miri` target in the Makefile.
+
+use crate::jiter::JiterResult;
+
+/// The only place of the crate where a lifetime is extended.
+///
+/// # Safety
+///
+/// The data behind `value` should not be changed or freed while the result
+/// is used, see the rules in the module documentation.
+#[allow(unsafe_code)]
+unsafe fn extend<'rj, T: ?Sized>(value: &T) -> &'rj T {
+    // SAFETY: the caller guarantees that the data outlives the use of the result,
+    // see the rules in the module documentation
+    unsafe { &*core::ptr::from_ref(value) }
+}
+
+/// A view of the buffer for `Jiter`.
+///
+/// # Safety
+///
+/// The view should not be used after a change of the buffer.
+#[allow(unsafe_code)]
+pub(crate) unsafe fn parse_window<'rj>(bytes: &[u8]) -> &'rj [u8] {
+    // SAFETY: forwarded to the caller
+    unsafe { extend(bytes) }
+}
+
+/// A parsed value which borrows the buffer or the tape of `Jiter`.
+/// Only the borrowing types are implemented, therefore `detach`
+/// can't change the type of a value, only its lifetime.
+pub(crate) trait Detach<'rj> {
+    type Detached;
+
+    /// # Safety
+    ///
+    /// See `detach`
+    #[allow(unsafe_code)]
+    unsafe fn detach(self) -> Self::Detached;
+}
+
+impl<'rj> Detach<'rj> for &[u8] {
+    type Detached = &'rj [u8];
+
+    #[allow(unsafe_code)]
+    unsafe fn detach(self) -> Self::Detached {
+        // SAFETY: forwarded to the caller
+        unsafe { extend(self) }
+    }
+}
+
+impl<'rj> Detach<'rj> for &str {
+    type Detached = &'rj str;
+
+    #[allow(unsafe_code)]
+    unsafe fn detach(self) -> Self::Detached {
+        // SAFETY: forwarded to the caller
+        unsafe { extend(self) }
+    }
+}
+
+impl<'rj, T: Detach<'rj>> Detach<'rj> for Option<T> {
+    type Detached = Option<T::Detached>;
+
+    #[allow(unsafe_code)]
+    unsafe fn detach(self) -> Self::Detached {
+        // SAFETY: forwarded to the caller
+        self.map(|value| unsafe { value.detach() })
+    }
+}
+
+impl<'rj, T: Detach<'rj>> Detach<'rj> for JiterResult<T> {
+    type Detached = JiterResult<T::Detached>;
+
+    #[allow(unsafe_code)]
+    unsafe fn detach(self) -> Self::Detached {
+        // SAFETY: forwarded to the caller
+        self.map(|value| unsafe { value.detach() })
+    }
+}
+
+/// Extend the lifetime of a parsed value to `'rj`.
+///
+/// # Safety
+///
+/// The value should be parsed by the current `Jiter` of `RJiter`. It should not
+/// be used after a change of the buffer or after the next call of the parser.
+#[allow(unsafe_code)]
+pub(crate) unsafe fn detach<'rj, T: Detach<'rj>>(value: T) -> T::Detached {
+    // SAFETY: forwarded to the caller
+    unsafe { value.detach() }
+}
@@ -0,0 +1,43 @@
+/// Resource limits for untrusted input, see `RJiter::with_limits`.
+///
+/// The streaming functions, such as `write_long_bytes`, process strings of any
+/// length with a small buffer, therefore the buffer size doesn't limit the input.
+/// With the limits, a too long string, number or document is the error
+/// `LimitExceeded` as soon as the limit is crossed. `None` is no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum length of a string or a key in bytes,
+    /// as the number of input bytes between the quotes.
+    pub max_string_bytes: Option<usize>,
+    /// The maximum length of a number in bytes, checked by the functions which parse,
+    /// return or stream a number: `next_int`, `next_float`, `next_number`,
+    /// `next_number_bytes`, the `known_*` variants, `write_long_number` and `write_long_value`.
+    pub max_number_bytes: Option<usize>,
+    /// The maximum number of bytes read from the reader or given to `feed`.
+    /// The bytes after the limit are not parsed.
+    pub max_total_bytes: Option<usize>,
+}
+
+/// Which limit is exceeded, see `ErrorType::LimitExceeded`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// `Limits::max_string_bytes`
+    StringBytes,
+    /// `Limits::max_number_bytes`
+    NumberBytes,
+    /// `Limits::max_total_bytes`
+    TotalBytes,
+}
+
+impl Limits {
+    /// The maximum for the limit
+    #[must_use]
+    pub fn max(&self, limit: Limit) -> Option<usize> {
+        match limit {
+            Limit::StringBytes => self.max_string_bytes,
+            Limit::NumberBytes => self.max_number_bytes,
+            Limit::TotalBytes => self.max_total_bytes,
+        }
+    }
+}
@@ -2,23 +2,115 @@ use embedded_io::{Error as _, Read, Write};
 
 use crate::buffer::Buffer;
 use crate::buffer::ChangeFlag;
+use crate::buffer::TotalLimit;
+use crate::buffer::WhitespacePolicy;
+use crate::builder::RJiterBuilder;
 use crate::error::{can_retry_if_partial, Error as RJiterError, ErrorType, Result as RJiterResult};
 use crate::jiter::{
-    Jiter, JiterResult, JsonErrorType, JsonValue, LinePosition, NumberAny, NumberInt, Peek,
+    Jiter, JiterError, JiterErrorType, JiterResult, JsonErrorType, JsonType, JsonValue,
+    LinePosition, NumberAny, NumberInt, Peek,
 };
+use crate::lifetime::{detach, parse_window};
+use crate::limits::{Limit, Limits};
+use crate::segmenter::Segmenter;
+use crate::string_reader::StringReader;
+#[cfg(feature = "trace")]
+use crate::trace::{Event, TraceHook};
+
+// Result of the writers for long strings: on error, the error type and the index
+type LongWriteResult = core::result::Result<(), (ErrorType, usize)>;
+
+// The maximum nesting of containers in `write_long_value`, the same as in `Jiter`
+const WRITE_VALUE_MAX_DEPTH: usize = 200;
+
+// How `next_long_key_after` writes the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyOutput {
+    // The key bytes as such
+    Bytes,
+    // The decoded key
+    Decoded,
+}
+
+/// Kind of the next number, see `RJiter::peek_number_kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NumberKind {
+    /// An integer for which `next_int` returns `NumberInt::Int`: up to 18 digits
+    Int,
+    /// A longer integer, `next_int` returns `NumberInt::BigInt`
+    BigInt,
+    /// A number with a fraction or an exponent, or `NaN` and `Infinity`
+    Float,
+}
+
+impl NumberKind {
+    fn of_token(token: &[u8]) -> Self {
+        let digits = token.strip_prefix(b"-").unwrap_or(token);
+        let is_float = !digits.first().is_some_and(u8::is_ascii_digit)
+            || digits.iter().any(|b| matches!(b, b'.' | b'e' | b'E'));
+        if is_float {
+            return NumberKind::Float;
+        }
+        // The same limit as in `Jiter`: up to 18 digits always fit into `i64`
+        if digits.len() <= 18 {
+            NumberKind::Int
+        } else {
+            NumberKind::BigInt
+        }
+    }
+}
+
+/// The next value, see `RJiter::peek_value`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PeekedValue {
+    /// The type of the value, as `peek` returns it
+    pub peek: Peek,
+    /// The length of the value in bytes, `None` if the value doesn't fit into the buffer
+    pub len: Option<usize>,
+}
+
+/// How `RJiter::known_skip_token_with` compares the token with the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenMatch {
+    /// The input bytes are the token
+    #[default]
+    Exact,
+    /// The input bytes are the token, ignoring the ASCII case, for example, `Data:` for `data:`
+    CaseInsensitive,
+    /// The input starts with the token. Only the token is skipped, the bytes after it
+    /// can be the JSON value, for example, `{"a":1}` in `)]}'{"a":1}` or `data:{"a":1}`.
+    /// A variable suffix is left to the caller, see `lookahead_while` and `skip_n_bytes`
+    Prefix,
+}
 
 /// Streaming JSON parser, a wrapper around `Jiter`.
 pub struct RJiter<'rj, R: Read> {
     jiter: Jiter<'rj>,
     buffer: Buffer<'rj, R>,
+    max_token_len: Option<usize>,
+    limits: Limits,
+    // The tokens for `skip_tokens`
+    skip_tokens: &'rj [&'rj [u8]],
+    // Passed to each new `Jiter`
+    allow_inf_nan: bool,
+    // Skip comments and trailing commas, see `with_tolerant_mode`
+    tolerant: bool,
+    // For `next_str_partial` in the middle of a string: the index of the opening quote
+    // and the end of the chunk returned by the previous call
+    partial_str: Option<(usize, usize)>,
+    // The index where `recover_to_next_top_level` stopped the last time
+    recovered_at: Option<usize>,
 }
 
 impl<R: Read> core::fmt::Debug for RJiter<'_, R> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
-            "RJiter {{ jiter: {:?}, buffer: {:?} }}",
-            self.jiter, self.buffer
+            "RJiter {{ jiter: {:?}, buffer: {:?}, max_token_len: {:?}, limits: {:?} }}",
+            self.jiter, self.buffer, self.max_token_len, self.limits
         )
     }
 }
@@ -30,27 +122,217 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// - `reader`: The json stream
     /// - `buf`: The working buffer
     pub fn new(reader: &'rj mut R, buf: &'rj mut [u8]) -> Self {
-        #[allow(unsafe_code)]
-        let buf_alias = unsafe {
-            #[allow(mutable_transmutes)]
-            #[allow(clippy::transmute_ptr_to_ptr)]
-            core::mem::transmute::<&[u8], &'rj mut [u8]>(buf)
+        let mut rjiter = RJiter {
+            jiter: Jiter::new(&[]),
+            buffer: Buffer::new(reader, buf),
+            max_token_len: None,
+            limits: Limits::default(),
+            skip_tokens: &[],
+            allow_inf_nan: false,
+            tolerant: false,
+            partial_str: None,
+            recovered_at: None,
         };
-        let buffer = Buffer::new(reader, buf_alias);
-        // `0 <= buffer.n_bytes <= buf.len()` by the `Buffer` contract
-        #[allow(clippy::indexing_slicing)]
-        let jiter = Jiter::new(&buf[..buffer.n_bytes]);
+        rjiter.create_new_jiter();
+        rjiter
+    }
+
+    /// Constructs a builder for an `RJiter` with options, see `RJiterBuilder`.
+    ///
+    /// # Arguments
+    /// - `reader`: The json stream
+    /// - `buf`: The working buffer
+    pub fn builder(reader: &'rj mut R, buf: &'rj mut [u8]) -> RJiterBuilder<'rj, R> {
+        RJiterBuilder::new(reader, buf)
+    }
+
+    /// Limit the length of strings, keys and numbers, independent of the buffer size.
+    ///
+    /// The limit is checked for the streaming functions for long strings
+    /// (`write_long_*`, `string_reader`, `next_*long_key`) and for the functions
+    /// which return strings, keys or numbers. The length is the number of input bytes,
+    /// between the quotes for strings and keys, therefore escapes are counted as written.
+    /// The limit is also checked while the buffer is filled with a token, so that
+    /// a too long token is not read to the end. A longer token is the error
+    /// `TokenTooLong` at the index where it is detected.
+    #[must_use]
+    pub fn with_max_token_len(mut self, max_len: usize) -> Self {
+        self.max_token_len = Some(max_len);
+        self
+    }
+
+    /// Set the limits for untrusted input, see `Limits`.
+    ///
+    /// Unlike the buffer size, the limits also apply to the streaming functions,
+    /// therefore a too long string in `write_long_bytes` or a too long document
+    /// is the error `LimitExceeded` instead of being processed to the end.
+    /// The string and number limits are checked in addition to `with_max_token_len`.
+    #[must_use]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self.buffer.total_limit = limits.max_total_bytes.map(TotalLimit::new);
+        self
+    }
+
+    /// Set how whitespace and line breaks are handled, see `WhitespacePolicy`.
+    ///
+    /// For example, for streams with lone `\r` as line separators, use
+    /// `cr_line_breaks` to get correct error positions and record recovery.
+    /// To reject input with non-JSON whitespace such as form feed, use `strict`.
+    #[must_use]
+    pub fn with_whitespace_policy(mut self, policy: WhitespacePolicy) -> Self {
+        self.buffer.whitespace = policy;
+        self
+    }
+
+    /// Collect at least `min_fill` bytes from several reads before parsing, if possible.
+    ///
+    /// Useful for readers which return a few bytes at a time, such as UART or SSE sources:
+    /// each read of an incomplete token makes the parser restart the token.
+    /// The reads stop earlier at the end of input, on an error or if the buffer is full.
+    /// An error is returned, the bytes collected before it are kept for the next call.
+    /// As the reads are blocking, use it only if the reader doesn't wait long for new data.
+    #[must_use]
+    pub fn with_read_coalescing(mut self, min_fill: usize) -> Self {
+        self.buffer.min_fill = min_fill;
+        self
+    }
+
+    /// Parse the bytes given to `feed` instead of reading them from the reader.
+    ///
+    /// For event-driven network stacks, where data arrives in callbacks and there is
+    /// no blocking reader. The reader is not used, for example, pass an empty slice.
+    /// If the fed bytes end inside a token, a parsing function returns the error
+    /// `NeedMoreData` and doesn't consume anything. Feed more and call the function again.
+    /// After the last chunk, call `set_eof_known(true)`: then the end of the fed
+    /// bytes is the end of the input, for example, for a number at the end.
+    ///
+    /// The functions which stream a long value in parts (`write_long_*`,
+    /// `next_str_partial`, `string_reader` and so on) can consume a part of the value
+    /// before they return `NeedMoreData`. Feed the whole value before calling them.
+    #[must_use]
+    pub fn with_feed_mode(mut self) -> Self {
+        self.buffer.is_feed_mode = true;
+        self
+    }
+
+    /// Parse `NaN`, `Infinity` and `-Infinity` as numbers, see `Jiter::with_allow_inf_nan`.
+    ///
+    /// Such values are common in JSON produced by Python. The setting is kept
+    /// when the buffer is refilled and after `reset`.
+    #[must_use]
+    pub fn with_allow_inf_nan(mut self) -> Self {
+        self.allow_inf_nan = true;
+        self.create_new_jiter();
+        self
+    }
+
+    /// Skip `//` and `/* */` comments and allow a trailing comma in arrays and objects,
+    /// as in JSONC and in configuration files.
+    ///
+    /// A comment is skipped as whitespace where the parser finds it between
+    /// the tokens, therefore a comment can cross the buffer boundary, and the line-column
+    /// positions of the errors don't change. A trailing comma is skipped only after a value,
+    /// `[,]` is an error. The setting is kept after `reset`.
+    #[must_use]
+    pub fn with_tolerant_mode(mut self) -> Self {
+        self.tolerant = true;
+        self
+    }
+
+    /// Set the tokens which `skip_tokens` skips between values.
+    ///
+    /// For example, the server-sent events prefix `data:` and the end marker `[DONE]`.
+    #[must_use]
+    pub fn with_skip_tokens(mut self, tokens: &'rj [&'rj [u8]]) -> Self {
+        self.skip_tokens = tokens;
+        self
+    }
+
+    /// Call `hook` at the instrumentation points: reads, buffer shifts,
+    /// re-creation of the parser and segments of long strings, see `Event`.
+    ///
+    /// The hook is a plain function, therefore it works without `std`
+    /// and without allocation. To collect the data, use a `static`,
+    /// for example, with atomic counters.
+    #[cfg(feature = "trace")]
+    #[must_use]
+    pub fn with_trace_hook(mut self, hook: TraceHook) -> Self {
+        self.buffer.trace_hook = Some(hook);
+        self
+    }
+
+    // Check the length of a token, `limit` tells if it is a string or a number.
+    // The error is at `index`.
+    fn check_token_len(&self, len: usize, limit: Limit, index: usize) -> RJiterResult<()> {
+        if let Some(max_len) = self.max_token_len.filter(|max_len| len > *max_len) {
+            return Err(self.locate_error(ErrorType::TokenTooLong { max_len }, index));
+        }
+        match self.limits.max(limit) {
+            Some(max) if len > max => {
+                Err(self.locate_error(ErrorType::LimitExceeded { limit, max }, index))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Parse a string, a key or a number with `f` and check the number of its input bytes,
+    // also while the buffer is filled with an incomplete token
+    fn next_token_with<T, F>(
+        &mut self,
+        mut f: F,
+        skip_spaces_token: Option<u8>,
+        should_eager_consume: bool,
+        limit: Limit,
+    ) -> RJiterResult<T>
+    where
+        F: FnMut(&mut Jiter<'rj>) -> JiterResult<T>,
+        T: core::fmt::Debug,
+    {
+        let (token, start, end) = self.loop_until_success_limited(
+            |j: &mut Jiter<'rj>| {
+                let start = j.current_index();
+                let token = f(j)?;
+                Ok((token, start, j.current_index()))
+            },
+            skip_spaces_token,
+            should_eager_consume,
+            Some(limit),
+        )?;
+        let bytes = self.buffer.buf.get(start..end).unwrap_or_default();
+        if let Some(len) = token_input_len(bytes, limit) {
+            self.check_token_len(len, limit, self.current_index())?;
+        }
+        Ok(token)
+    }
 
-        RJiter { jiter, buffer }
+    // Check the length of a long string token, which starts with the quote at `start_index`
+    // and is read until `end_index` (exclusive, without the closing quote)
+    fn check_long_token_len(&self, start_index: usize, end_index: usize) -> RJiterResult<()> {
+        self.check_token_len(
+            end_index.saturating_sub(start_index + 1),
+            Limit::StringBytes,
+            self.current_index(),
+        )
     }
 
     fn create_new_jiter(&mut self) {
         // `0 <= buffer.n_bytes <= buf.len()` by the `Buffer` contract
         #[allow(clippy::indexing_slicing)]
-        let jiter_buffer_2 = &self.buffer.buf[..self.buffer.n_bytes];
+        let jiter_buffer = &self.buffer.buf[..self.buffer.n_bytes];
+        // SAFETY: the old parser is replaced, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let jiter_buffer = unsafe { core::mem::transmute::<&[u8], &'rj [u8]>(jiter_buffer_2) };
-        self.jiter = Jiter::new(jiter_buffer);
+        let jiter_buffer = unsafe { parse_window(jiter_buffer) };
+        let jiter = Jiter::new(jiter_buffer);
+        self.jiter = if self.allow_inf_nan {
+            jiter.with_allow_inf_nan()
+        } else {
+            jiter
+        };
+        #[cfg(feature = "trace")]
+        self.buffer.trace(Event::NewJiter {
+            n_bytes: self.buffer.n_bytes,
+        });
     }
 
     //  ------------------------------------------------------------
@@ -61,14 +343,24 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn peek(&mut self) -> RJiterResult<Peek> {
-        self.loop_until_success(jiter::Jiter::peek, None, false)
+        let tolerant = self.tolerant;
+        let f = |j: &mut Jiter<'rj>| {
+            let peek = j.peek()?;
+            comment_as_error(Some(peek), j, tolerant).map(|_| peek)
+        };
+        self.loop_until_success(f, None, false)
     }
 
     /// See `Jiter::known_array`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_array(&mut self) -> RJiterResult<Option<Peek>> {
-        self.loop_until_success(jiter::Jiter::known_array, Some(b'['), false)
+        let tolerant = self.tolerant;
+        let f = |j: &mut Jiter<'rj>| {
+            let peek = j.known_array()?;
+            comment_as_error(peek, j, tolerant)
+        };
+        self.loop_until_success(f, Some(b'['), false)
     }
 
     /// See `Jiter::known_bool`
@@ -82,25 +374,24 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_bytes(&mut self) -> RJiterResult<&[u8]> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&[u8]>, JiterResult<&'rj [u8]>>(j.known_bytes())
-        };
-        self.loop_until_success(f, None, false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.known_bytes()) };
+        self.next_token_with(f, None, false, Limit::StringBytes)
     }
 
     /// See `Jiter::known_float`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_float(&mut self, peek: Peek) -> RJiterResult<f64> {
-        self.loop_until_success(|j| j.known_float(peek), None, true)
+        self.next_token_with(|j| j.known_float(peek), None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::known_int`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_int(&mut self, peek: Peek) -> RJiterResult<NumberInt> {
-        self.loop_until_success(|j| j.known_int(peek), None, true)
+        self.next_token_with(|j| j.known_int(peek), None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::known_null`
@@ -114,20 +405,17 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_number(&mut self, peek: Peek) -> RJiterResult<NumberAny> {
-        self.loop_until_success(|j| j.known_number(peek), None, true)
+        self.next_token_with(|j| j.known_number(peek), None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::known_object`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_object(&mut self) -> RJiterResult<Option<&str>> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<Option<&str>>, JiterResult<Option<&'rj str>>>(
-                j.known_object(),
-            )
-        };
-        self.loop_until_success(f, Some(b'{'), false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.known_object()) };
+        self.next_token_with(f, Some(b'{'), false, Limit::StringBytes)
     }
 
     /// See `Jiter::known_skip`
@@ -141,11 +429,10 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn known_str(&mut self) -> RJiterResult<&str> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&str>, JiterResult<&'rj str>>(j.known_str())
-        };
-        self.loop_until_success(f, None, false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.known_str()) };
+        self.next_token_with(f, None, false, Limit::StringBytes)
     }
 
     /// See `Jiter::known_value`
@@ -166,14 +453,24 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_array(&mut self) -> RJiterResult<Option<Peek>> {
-        self.loop_until_success(jiter::Jiter::next_array, Some(b'['), false)
+        let tolerant = self.tolerant;
+        let f = |j: &mut Jiter<'rj>| {
+            let peek = j.next_array()?;
+            comment_as_error(peek, j, tolerant)
+        };
+        self.loop_until_success(f, Some(b'['), false)
     }
 
     /// See `Jiter::array_step`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn array_step(&mut self) -> RJiterResult<Option<Peek>> {
-        self.loop_until_success(jiter::Jiter::array_step, Some(b','), false)
+        let tolerant = self.tolerant;
+        let f = |j: &mut Jiter<'rj>| {
+            let peek = j.array_step()?;
+            comment_as_error(peek, j, tolerant)
+        };
+        self.loop_until_success(f, Some(b','), false)
     }
 
     /// See `Jiter::next_bool`
@@ -187,51 +484,44 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_bytes(&mut self) -> RJiterResult<&[u8]> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&[u8]>, JiterResult<&'rj [u8]>>(j.next_bytes())
-        };
-        self.loop_until_success(f, None, false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_bytes()) };
+        self.next_token_with(f, None, false, Limit::StringBytes)
     }
 
     /// See `Jiter::next_float`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_float(&mut self) -> RJiterResult<f64> {
-        self.loop_until_success(jiter::Jiter::next_float, None, true)
+        self.next_token_with(jiter::Jiter::next_float, None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::next_int`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_int(&mut self) -> RJiterResult<NumberInt> {
-        self.loop_until_success(jiter::Jiter::next_int, None, true)
+        self.next_token_with(jiter::Jiter::next_int, None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::next_key`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_key(&mut self) -> RJiterResult<Option<&str>> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<Option<&str>>, JiterResult<Option<&'rj str>>>(
-                j.next_key(),
-            )
-        };
-        self.loop_until_success(f, Some(b','), false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_key()) };
+        self.next_token_with(f, Some(b','), false, Limit::StringBytes)
     }
 
     /// See `Jiter::next_key_bytes`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_key_bytes(&mut self) -> RJiterResult<Option<&[u8]>> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<Option<&[u8]>>, JiterResult<Option<&'rj [u8]>>>(
-                j.next_key_bytes(),
-            )
-        };
-        self.loop_until_success(f, Some(b','), false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_key_bytes()) };
+        self.next_token_with(f, Some(b','), false, Limit::StringBytes)
     }
 
     /// See `Jiter::next_null`
@@ -245,46 +535,37 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_number(&mut self) -> RJiterResult<NumberAny> {
-        self.loop_until_success(jiter::Jiter::next_number, None, true)
+        self.next_token_with(jiter::Jiter::next_number, None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::next_number_bytes`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_number_bytes(&mut self) -> RJiterResult<&[u8]> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&[u8]>, JiterResult<&'rj [u8]>>(
-                j.next_number_bytes(),
-            )
-        };
-        self.loop_until_success(f, None, true)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_number_bytes()) };
+        self.next_token_with(f, None, true, Limit::NumberBytes)
     }
 
     /// See `Jiter::next_object`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_object(&mut self) -> RJiterResult<Option<&str>> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<Option<&str>>, JiterResult<Option<&'rj str>>>(
-                j.next_object(),
-            )
-        };
-        self.loop_until_success(f, Some(b'{'), false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_object()) };
+        self.next_token_with(f, Some(b'{'), false, Limit::StringBytes)
     }
 
     /// See `Jiter::next_object_bytes`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_object_bytes(&mut self) -> RJiterResult<Option<&[u8]>> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<Option<&[u8]>>, JiterResult<Option<&'rj [u8]>>>(
-                j.next_object_bytes(),
-            )
-        };
-        self.loop_until_success(f, Some(b'{'), false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_object_bytes()) };
+        self.next_token_with(f, Some(b'{'), false, Limit::StringBytes)
     }
 
     /// See `Jiter::next_skip`
@@ -298,11 +579,10 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn next_str(&mut self) -> RJiterResult<&str> {
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let f = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&str>, JiterResult<&'rj str>>(j.next_str())
-        };
-        self.loop_until_success(f, None, false)
+        let f = |j: &mut Jiter<'rj>| unsafe { detach(j.next_str()) };
+        self.next_token_with(f, None, false, Limit::StringBytes)
     }
 
     /// See `Jiter::next_value`
@@ -324,10 +604,67 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     //
 
     fn loop_until_success<T, F>(
+        &mut self,
+        f: F,
+        skip_spaces_token: Option<u8>,
+        should_eager_consume: bool,
+    ) -> RJiterResult<T>
+    where
+        F: FnMut(&mut Jiter<'rj>) -> JiterResult<T>,
+        T: core::fmt::Debug,
+    {
+        self.loop_until_success_limited(f, skip_spaces_token, should_eager_consume, None)
+    }
+
+    // `token_limit`: the parsed value is a string, a key or a number,
+    // its length is checked before each read, see `with_max_token_len`
+    fn loop_until_success_limited<T, F>(
+        &mut self,
+        f: F,
+        skip_spaces_token: Option<u8>,
+        should_eager_consume: bool,
+        token_limit: Option<Limit>,
+    ) -> RJiterResult<T>
+    where
+        F: FnMut(&mut Jiter<'rj>) -> JiterResult<T>,
+        T: core::fmt::Debug,
+    {
+        let start_index = self.current_index();
+        let result =
+            self.loop_until_success_once(f, skip_spaces_token, should_eager_consume, token_limit);
+        if let Err(e) = &result {
+            if e.error_type == ErrorType::NeedMoreData {
+                self.rewind_to(start_index)?;
+            }
+        }
+        result
+    }
+
+    // Put the parser back to the index before a failed call, to retry it after `feed`.
+    // The retry handler has moved the bytes at the index to the buffer position 0, see
+    // `skip_spaces_feeding`. After that, only whitespace, comments and trailing commas
+    // are dropped, therefore the parser restarts at the position 0.
+    // If the bytes before the index are still in the buffer, the call can't be retried.
+    fn rewind_to(&mut self, index: usize) -> RJiterResult<()> {
+        if index > self.buffer.n_shifted_out {
+            return Err(RJiterError::new(
+                ErrorType::JsonError(JsonErrorType::InternalError(
+                    "can't rewind the parser for a retry".into(),
+                )),
+                index,
+                |index| self.error_position(index),
+            ));
+        }
+        self.create_new_jiter();
+        Ok(())
+    }
+
+    fn loop_until_success_once<T, F>(
         &mut self,
         mut f: F,
         skip_spaces_token: Option<u8>,
         should_eager_consume: bool,
+        token_limit: Option<Limit>,
     ) -> RJiterResult<T>
     where
         F: FnMut(&mut Jiter<'rj>) -> JiterResult<T>,
@@ -336,11 +673,12 @@ impl<'rj, R: Read> RJiter<'rj, R> {
         // Error-result makes `false`,
         // Ok-result makes `true`, except if the grandcaller hints (`should_eager_consume`) that
         // end of the buffer can be a false positive (e.g. when parsing a number).
+        // If the end of the input is known, the end of the buffer is the real end.
         fn downgrade_ok_if_eof<T>(
             result: &JiterResult<T>,
             should_eager_consume: bool,
             jiter: &Jiter,
-            n_bytes: usize,
+            buffer: &Buffer<impl Read>,
         ) -> bool {
             if !result.is_ok() {
                 return false;
@@ -348,7 +686,7 @@ impl<'rj, R: Read> RJiter<'rj, R> {
             if !should_eager_consume {
                 return true;
             }
-            if jiter.current_index() < n_bytes {
+            if jiter.current_index() < buffer.n_bytes || buffer.is_eof_seen {
                 return true;
             }
             false
@@ -356,12 +694,7 @@ impl<'rj, R: Read> RJiter<'rj, R> {
         let jiter_pos = self.jiter.current_index();
 
         let result = f(&mut self.jiter);
-        let is_ok = downgrade_ok_if_eof(
-            &result,
-            should_eager_consume,
-            &self.jiter,
-            self.buffer.n_bytes,
-        );
+        let is_ok = downgrade_ok_if_eof(&result, should_eager_consume, &self.jiter, &self.buffer);
         if is_ok {
             // `result` is always `Ok`
             if let Ok(value) = result {
@@ -376,20 +709,21 @@ impl<'rj, R: Read> RJiter<'rj, R> {
 
             if let Err(e) = &result {
                 if !can_retry_if_partial(e) {
+                    // In the tolerant mode, drop the comment or the trailing comma and repeat
+                    if self.drop_tolerated(e)? {
+                        continue;
+                    }
                     return Err(RJiterError::from_jiter_error(
                         self.current_index(),
                         e.clone(),
+                        |index| self.error_position(index),
                     ));
                 }
             }
 
             if result.is_ok() {
-                let really_ok = downgrade_ok_if_eof(
-                    &result,
-                    should_eager_consume,
-                    &self.jiter,
-                    self.buffer.n_bytes,
-                );
+                let really_ok =
+                    downgrade_ok_if_eof(&result, should_eager_consume, &self.jiter, &self.buffer);
                 if really_ok {
                     // `result` is always `Ok`
                     if let Ok(value) = result {
@@ -398,13 +732,39 @@ impl<'rj, R: Read> RJiter<'rj, R> {
                 }
             }
 
+            // Don't read more of a too long token. The token starts at the buffer
+            // position 0, see `skip_spaces_feeding`.
+            if let Some(limit) = token_limit {
+                let bytes = self
+                    .buffer
+                    .buf
+                    .get(..self.buffer.n_bytes)
+                    .unwrap_or_default();
+                if let Some(len) = token_input_len(bytes, limit) {
+                    self.check_token_len(
+                        len,
+                        limit,
+                        self.buffer.n_shifted_out + self.buffer.n_bytes,
+                    )?;
+                }
+            }
+
             let n_read = self.buffer.read_more();
             match n_read {
                 Err(e) => return Err(e),
                 Ok(0) => {
+                    // The token starts at the buffer position 0 and fills the whole buffer
+                    if result.is_err() && self.buffer.n_bytes == self.buffer.buf.len() {
+                        return Err(
+                            self.locate_error(ErrorType::BufferFull, self.buffer.n_shifted_out)
+                        );
+                    }
                     // EOF is reached in the error state
-                    return result
-                        .map_err(|e| RJiterError::from_jiter_error(self.current_index(), e));
+                    return result.map_err(|e| {
+                        RJiterError::from_jiter_error(self.current_index(), e, |index| {
+                            self.error_position(index)
+                        })
+                    });
                 }
                 Ok(_) => {
                     self.create_new_jiter();
@@ -427,7 +787,7 @@ impl<'rj, R: Read> RJiter<'rj, R> {
         if jiter_pos > to_pos {
             self.buffer.shift_buffer(to_pos, jiter_pos);
         }
-        self.buffer.skip_spaces(to_pos)?;
+        self.skip_spaces_at(to_pos)?;
         if let Some(transparent_token) = transparent_token {
             if to_pos >= self.buffer.n_bytes {
                 self.buffer.read_more()?;
@@ -435,28 +795,85 @@ impl<'rj, R: Read> RJiter<'rj, R> {
             // `0 <= to_pos` (usize), `to_pos < buffer.n_bytes` (if check), `n_bytes <= buf.len()` by the `Buffer` contract
             #[allow(clippy::indexing_slicing)]
             if to_pos < self.buffer.n_bytes && self.buffer.buf[to_pos] == transparent_token {
-                self.buffer.skip_spaces(to_pos + 1)?;
+                self.skip_spaces_at(to_pos + 1)?;
             }
         }
 
-        if change_flag.is_changed(&self.buffer) {
+        // Also restart the parser if a failed call has advanced it
+        if change_flag.is_changed(&self.buffer) || self.jiter.current_index() != to_pos {
             self.create_new_jiter();
         }
         Ok(())
     }
 
+    // Skip whitespace at the buffer position `pos`, and in the tolerant mode also comments
+    fn skip_spaces_at(&mut self, pos: usize) -> RJiterResult<()> {
+        self.buffer.skip_spaces(pos)?;
+        while self.tolerant && self.buffer.skip_comment(pos)? {
+            self.buffer.skip_spaces(pos)?;
+        }
+        Ok(())
+    }
+
+    // In the tolerant mode, drop the comment or the trailing comma at the error of `Jiter`.
+    // The parser should have started at the buffer position 0.
+    // Returns `true` if something is dropped, then the call can be repeated.
+    fn drop_tolerated(&mut self, e: &JiterError) -> RJiterResult<bool> {
+        if !self.tolerant {
+            return Ok(false);
+        }
+        let pos = e.index;
+        let is_trailing_comma =
+            e.error_type == JiterErrorType::JsonError(JsonErrorType::TrailingComma);
+        let is_dropped = if is_trailing_comma {
+            // `Jiter` reports a trailing comma only after a value, at the closing bracket.
+            // Only whitespace is between the comma and the bracket, the comments are
+            // already dropped.
+            let whitespace = self.buffer.whitespace;
+            let comma_pos = self
+                .buffer
+                .buf
+                .get(..pos)
+                .and_then(|before| before.iter().rposition(|b| !whitespace.is_whitespace(*b)))
+                .filter(|comma_pos| self.buffer.buf.get(*comma_pos) == Some(&b','));
+            if let Some(comma_pos) = comma_pos {
+                self.buffer.shift_buffer(comma_pos, comma_pos + 1);
+            }
+            comma_pos.is_some()
+        } else {
+            self.buffer.skip_comment(pos)?
+        };
+        if is_dropped {
+            self.create_new_jiter();
+        }
+        Ok(is_dropped)
+    }
+
     /// See `Jiter::finish`
     /// # Errors
     /// `IoError` or `JiterError`
     pub fn finish(&mut self) -> RJiterResult<()> {
+        if self.tolerant {
+            self.buffer.shift_buffer(0, self.jiter.current_index());
+            let result = self.skip_spaces_at(0);
+            self.create_new_jiter();
+            result?;
+        }
         loop {
             let finish_in_this_buf = self.jiter.finish();
             // Error here is actually not an error, but a marker that something is found
             // and therefore the jiter is not at the end of the json
             if let Err(e) = finish_in_this_buf {
-                return Err(RJiterError::from_jiter_error(self.current_index(), e));
+                return Err(RJiterError::from_jiter_error(
+                    self.current_index(),
+                    e,
+                    |index| self.error_position(index),
+                ));
+            }
+            // The current buffer was all only spaces. Read more, if the end is not known yet.
+            if self.buffer.is_eof_seen {
+                return Ok(());
             }
-            // The current buffer was all only spaces. Read more.
             if self.jiter.current_index() < self.buffer.buf.len() {
                 let n_new_bytes = self.buffer.read_more()?;
                 // The end of the json is reached
@@ -471,58 +888,292 @@ impl<'rj, R: Read> RJiter<'rj, R> {
 
     //  ------------------------------------------------------------
 
+    /// Whether the buffer holds unparsed bytes other than whitespace.
+    /// Doesn't read from the input.
+    #[must_use]
+    pub fn has_buffered_data(&self) -> bool {
+        self.buffer
+            .buf
+            .get(self.jiter.current_index()..self.buffer.n_bytes)
+            .is_some_and(|rest| {
+                rest.iter()
+                    .any(|b| !self.buffer.whitespace.is_whitespace(*b))
+            })
+    }
+
+    /// Whether the end of the input is known: the last read from the input returned 0 bytes.
+    ///
+    /// It is a hint only: a network stream can get more data later.
+    /// Together with `has_buffered_data`, a framing loop can decide whether to parse
+    /// again or wait for more input.
+    ///
+    /// While the end is known, a number or whitespace at the end of the buffer is
+    /// complete, and rjiter doesn't read again to check it.
+    #[must_use]
+    pub fn eof_known(&self) -> bool {
+        self.buffer.is_eof_seen
+    }
+
+    /// Set or clear the `eof_known` hint.
+    ///
+    /// Clear it if a stream can have more data after a 0-byte read,
+    /// then a number at the end of the buffer is checked by reading again.
+    /// The next read sets the hint anew.
+    pub fn set_eof_known(&mut self, eof_known: bool) {
+        self.buffer.is_eof_seen = eof_known;
+    }
+
+    /// Give the next bytes of the input in feed mode, see `with_feed_mode`.
+    ///
+    /// The consumed bytes are dropped from the buffer, then as many bytes of `data`
+    /// are copied as fit. Returns the number of copied bytes. If it is less than
+    /// `data.len()`, parse further and feed the rest later. A token which is longer
+    /// than the buffer can't be parsed, as with a reader.
+    pub fn feed(&mut self, data: &[u8]) -> usize {
+        // `next_str_partial` keeps positions in the buffer and re-creates the parser itself
+        if self.partial_str.is_some() {
+            return self.buffer.append(data);
+        }
+        self.buffer.shift_buffer(0, self.jiter.current_index());
+        let n_new_bytes = self.buffer.append(data);
+        self.create_new_jiter();
+        n_new_bytes
+    }
+
+    /// Start over with a new reader, reusing the buffer.
+    ///
+    /// The buffered bytes, the index and the position are dropped, as if `RJiter`
+    /// were created anew, but the settings (`with_*` functions) are kept.
+    /// A long-running server can parse each connection or record with the same `RJiter`.
+    pub fn reset(&mut self, new_reader: &'rj mut R) {
+        self.buffer.reset(new_reader);
+        self.partial_str = None;
+        self.recovered_at = None;
+        self.create_new_jiter();
+    }
+
+    /// Skip the rest of a corrupt value after an error and position rjiter
+    /// at the next top-level value.
+    ///
+    /// After an error, rjiter is at an unspecified position inside the value, and
+    /// parsing can't continue. This function drops the input until a plausible start
+    /// of the next top-level value: `{`, `[`, a string, a number, `true`, `false` or `null`
+    /// after the end of a value, and not after `,` or `:`, which separate the items
+    /// of an object or an array. The search skips strings, also with escaped quotes,
+    /// and counts brackets: a value is a candidate only at the outermost nesting seen
+    /// after the error. A raw line break can't be inside a valid string, therefore
+    /// it ends a corrupt string.
+    ///
+    /// The values can share a line or be pretty-printed in any way. If the error
+    /// is at `{` or `[`, for example, after a truncated record of JSON Lines,
+    /// the search stops at this value, but not twice in a row.
+    ///
+    /// Returns `true` if a value is found, or `false` at the end of the input.
+    ///
+    /// # Errors
+    /// `IoError`
+    pub fn recover_to_next_top_level(&mut self) -> RJiterResult<bool> {
+        fn is_value_start(byte: u8) -> bool {
+            matches!(
+                byte,
+                b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n'
+            )
+        }
+        // A value after these bytes is inside an object or an array
+        fn is_separator(byte: u8) -> bool {
+            matches!(byte, b',' | b':' | b'{' | b'[')
+        }
+
+        let mut pos = self.jiter.current_index();
+        let mut in_string = self.partial_str.take().is_some();
+        let mut after_escape = false;
+        // The nesting relative to the error position and its minimum
+        let mut depth: isize = 0;
+        let mut min_depth: isize = 0;
+        // The last byte outside strings, `"` after a string
+        let mut last = b',';
+        let mut after_space = false;
+        let mut after_cr = false;
+        // An object or an array at the error position is a candidate,
+        // but not again after the previous recovery, as the error would repeat
+        let mut at_error = Some(self.current_index()) != self.recovered_at;
+        loop {
+            // Search in the buffer
+            #[allow(clippy::indexing_slicing)]
+            let rest = &self.buffer.buf[pos..self.buffer.n_bytes];
+            for (i, byte) in rest.iter().copied().enumerate() {
+                let is_line_break = self.buffer.whitespace.is_line_break(byte, after_cr);
+                after_cr = byte == b'\r';
+                if in_string {
+                    if is_line_break || (byte == b'"' && !after_escape) {
+                        in_string = false;
+                        last = b'"';
+                        after_space = is_line_break;
+                    }
+                    after_escape = !after_escape && byte == b'\\';
+                    continue;
+                }
+                let is_after_value =
+                    !is_separator(last) && (after_space || matches!(last, b'}' | b']' | b'"'));
+                if is_value_start(byte)
+                    && ((at_error && matches!(byte, b'{' | b'['))
+                        || (depth == min_depth && is_after_value))
+                {
+                    self.buffer.shift_buffer(0, pos + i);
+                    self.create_new_jiter();
+                    self.recovered_at = Some(self.current_index());
+                    return Ok(true);
+                }
+                at_error = false;
+                if byte.is_ascii_whitespace() {
+                    after_space = true;
+                    continue;
+                }
+                after_space = false;
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        min_depth = min_depth.min(depth);
+                    }
+                    _ => {}
+                }
+                last = byte;
+            }
+
+            // Drop the buffer and read more
+            self.buffer.shift_buffer(0, self.buffer.n_bytes);
+            pos = 0;
+            if self.buffer.read_more()? == 0 {
+                self.create_new_jiter();
+                return Ok(false);
+            }
+        }
+    }
+
     /// Get the current index of the parser.
     #[must_use]
     pub fn current_index(&self) -> usize {
         self.jiter.current_index() + self.buffer.n_shifted_out
     }
 
+    fn locate_error(&self, error_type: ErrorType, index: usize) -> RJiterError {
+        RJiterError::new(error_type, index, |index| self.error_position(index))
+    }
+
     /// Get the current `LinePosition` of the parser.
+    ///
+    /// The result is correct only while `index` points into the current buffer.
+    /// Errors capture their position at creation time, see `Error::position`.
     #[must_use]
     pub fn error_position(&self, index: usize) -> LinePosition {
-        let index = index - self.buffer.n_shifted_out;
-        let pos = self.jiter.error_position(index);
-        LinePosition::new(
-            pos.line + self.buffer.pos_shifted.line,
-            pos.column + self.buffer.pos_shifted.column,
-        )
+        self.buffer.position_of(index)
+    }
+
+    /// Get the `LinePosition` of `index` if the bytes before it are still known,
+    /// that is, `index` points into the current buffer. Otherwise `None`, while
+    /// `error_position` would clamp the index to the buffer and give a wrong position.
+    #[must_use]
+    pub fn buffered_position(&self, index: usize) -> Option<LinePosition> {
+        let n_counted = self.buffer.n_pos_counted.min(self.buffer.n_bytes);
+        let first = self.buffer.n_shifted_out + n_counted;
+        let last = self.buffer.n_shifted_out + self.buffer.n_bytes;
+        (first..=last)
+            .contains(&index)
+            .then(|| self.error_position(index))
+    }
+
+    /// Get the `LinePosition` of the parser, the position of `current_index`.
+    ///
+    /// The bytes shifted out of the buffer are counted, therefore the result
+    /// is correct at any place of the input, for example, for logs about
+    /// a multi-megabyte JSON Lines stream.
+    #[must_use]
+    pub fn current_position(&self) -> LinePosition {
+        self.error_position(self.current_index())
     }
 
     //  ------------------------------------------------------------
     // Pass-through long strings and bytes
 
+    // The segmenter for the long string at the beginning of the buffer
+    fn segmenter(&self) -> Segmenter<'_> {
+        Segmenter::new(
+            self.buffer
+                .buf
+                .get(..self.buffer.n_bytes)
+                .unwrap_or_default(),
+        )
+    }
+
+    // Jiter locates invalid UTF-8 in the decoded string, which differs from the input
+    // after escapes. Locate the first invalid sequence of the string in the input instead.
+    // The string is in the buffer: it is completed, only then jiter validates it.
+    fn invalid_utf8_in_string(&self) -> RJiterError {
+        let content_start = self.jiter.current_index() + 1;
+        let content = self
+            .buffer
+            .buf
+            .get(content_start..self.buffer.n_bytes)
+            .unwrap_or_default();
+        let valid_up_to = match core::str::from_utf8(content) {
+            Ok(string) => string.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        self.locate_error(
+            ErrorType::JsonError(JsonErrorType::InvalidUnicodeCodePoint),
+            self.buffer.n_shifted_out + content_start + valid_up_to,
+        )
+    }
+
     //
     // Contract for `write_segment`:
     // - arg 1: `self.buffer.buf`,
     // - arg 2: `1 < segment_end_pos <= self.buffer.n_bytes - 1 <= self.buffer.buf.len() - 1`,
     //          or `1 < segment_end_pos <= self.buffer.n_bytes <= 7`,
     //
+    // The writers can't access the buffer to locate an error, therefore they
+    // return the error type and the index, and `handle_long` adds the position.
+    //
     fn handle_long<F, T, W: Write>(
         &mut self,
         parser: F,
         writer: &mut W,
-        write_completed: impl Fn(T, usize, &mut W) -> RJiterResult<()>,
-        write_segment: impl Fn(&mut [u8], usize, usize, &mut W) -> RJiterResult<()>,
+        write_completed: impl Fn(T, usize, &mut W) -> LongWriteResult,
+        write_segment: impl Fn(&mut [u8], usize, usize, &mut W) -> LongWriteResult,
     ) -> RJiterResult<()>
     where
         F: Fn(&mut Jiter<'rj>) -> JiterResult<T>,
         T: core::fmt::Debug,
     {
+        let start_index = self.current_index();
         loop {
             // Handle simple cases:
             // - The string is completed
             // - The error is not recoverable
             let result = parser(&mut self.jiter);
             if let Ok(value) = result {
-                write_completed(value, self.current_index(), writer)?;
-                return Ok(());
+                self.check_long_token_len(start_index, self.current_index() - 1)?;
+                return write_completed(value, self.current_index(), writer)
+                    .map_err(|(error_type, index)| self.locate_error(error_type, index));
             }
             // We need `err` in the scope later, therefore we don't use `match` for `result`
             // The Ok-arm is handled above
             #[allow(clippy::unwrap_used)]
             let err = result.unwrap_err();
+            if matches!(
+                err.error_type,
+                JiterErrorType::JsonError(JsonErrorType::InvalidUnicodeCodePoint)
+            ) {
+                return Err(self.invalid_utf8_in_string());
+            }
             if !can_retry_if_partial(&err) {
-                return Err(RJiterError::from_jiter_error(self.current_index(), err));
+                return Err(RJiterError::from_jiter_error(
+                    self.current_index(),
+                    err,
+                    |index| self.error_position(index),
+                ));
             }
 
             // Move the string to the beginning of the buffer to avoid corner cases.
@@ -534,73 +1185,39 @@ impl<'rj, R: Read> RJiter<'rj, R> {
 
             // Current state: the string is not completed
             // Find out a segment to write
-
-            #[allow(clippy::indexing_slicing)]
-            let bs_pos = self.buffer.buf[..self.buffer.n_bytes]
-                .iter()
-                .position(|&b| b == b'\\');
-            let segment_end_pos = match bs_pos {
-                // No backslash: the segment is the whole buffer
-                // `-1`: To write a segment, the writer needs an extra byte to put the quote character
-                None => {
-                    if self.buffer.n_bytes == 0 {
-                        0
-                    } else {
-                        self.buffer.n_bytes - 1
-                    }
-                }
-                // Backslash is somewhere in the buffer
-                // The segment is the part of the buffer before the backslash
-                Some(bs_pos) if bs_pos > 1 => bs_pos,
-                // Backslash is the first byte of the buffer
-                // The segment is the escape sequence
-                Some(bs_pos) => {
-                    let buf_len = self.buffer.n_bytes;
-                    // [QUOTE, SLASH, CHAR, ....]
-                    if buf_len < 3 {
-                        bs_pos
-                    } else {
-                        // `buf_len >= 3` in this branch
-                        #[allow(clippy::indexing_slicing)]
-                        let after_bs = self.buffer.buf[2];
-                        if after_bs != b'u' && after_bs != b'U' {
-                            bs_pos + 2
-                        } else {
-                            // [QUOTE, SLASH, u, HEXDEC, HEXDEC, HEXDEC, HEXDEC, ....]
-                            if buf_len < 7 {
-                                bs_pos
-                            } else {
-                                bs_pos + 6
-                            }
-                        }
-                    }
-                }
-            };
-
-            // Correct the segment end position to not break a unicode code point
-            let segment_end_pos = (0..=segment_end_pos)
-                .rev()
-                .find(
-                    #[allow(clippy::indexing_slicing)]
-                    |&pos| is_utf8_leading_byte(self.buffer.buf[pos]),
-                )
-                .unwrap_or(0);
+            let segment_end_pos = self.segmenter().segment_end(self.buffer.n_bytes);
 
             // Write the segment
             if segment_end_pos > 1 {
+                self.check_long_token_len(
+                    start_index,
+                    self.buffer.n_shifted_out + segment_end_pos,
+                )?;
+                #[cfg(feature = "trace")]
+                self.buffer.trace(Event::LongSegment {
+                    index: self.current_index() + 1,
+                    len: segment_end_pos - 1,
+                });
                 write_segment(
                     self.buffer.buf,
                     segment_end_pos,
                     self.current_index(),
                     writer,
-                )?;
+                )
+                .map_err(|(error_type, index)| self.locate_error(error_type, index))?;
                 self.buffer.shift_buffer(1, segment_end_pos);
             }
 
             // Read more and repeat
             let n_new_bytes = self.buffer.read_more()?;
             match n_new_bytes {
-                0 => return Err(RJiterError::from_jiter_error(self.current_index(), err)),
+                0 => {
+                    return Err(RJiterError::from_jiter_error(
+                        self.current_index(),
+                        err,
+                        |index| self.error_position(index),
+                    ))
+                }
                 1.. => self.create_new_jiter(),
             }
         }
@@ -620,31 +1237,26 @@ impl<'rj, R: Read> RJiter<'rj, R> {
             bytes: &[u8],
             index: usize,
             writer: &mut W,
-        ) -> RJiterResult<()> {
-            writer.write_all(bytes).map_err(|e| RJiterError {
-                error_type: ErrorType::IoError { kind: e.kind() },
-                index,
-            })
+        ) -> LongWriteResult {
+            writer
+                .write_all(bytes)
+                .map_err(|e| (ErrorType::IoError { kind: e.kind() }, index))
         }
         fn write_segment<W: Write>(
             bytes: &mut [u8],
             end_pos: usize,
             index: usize,
             writer: &mut W,
-        ) -> RJiterResult<()> {
+        ) -> LongWriteResult {
             // See the `write_long` contract. May panic for a small buffer (less than 7 bytes)
             #[allow(clippy::indexing_slicing)]
             writer
                 .write_all(&bytes[1..end_pos])
-                .map_err(|e| RJiterError {
-                    error_type: ErrorType::IoError { kind: e.kind() },
-                    index,
-                })
+                .map_err(|e| (ErrorType::IoError { kind: e.kind() }, index))
         }
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
         #[allow(unsafe_code)]
-        let parser = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&[u8]>, JiterResult<&'rj [u8]>>(j.known_bytes())
-        };
+        let parser = |j: &mut Jiter<'rj>| unsafe { detach(j.known_bytes()) };
         self.handle_long(parser, writer, write_completed, write_segment)
     }
 
@@ -655,60 +1267,801 @@ impl<'rj, R: Read> RJiter<'rj, R> {
     /// Bounding quotes are not included in the output.
     ///
     /// # Errors
-    /// `IoError` or `JiterError`
+    /// `IoError` or `JiterError`. For invalid UTF-8, the index of the error is
+    /// the absolute position of the first byte of the invalid sequence.
     pub fn write_long_str<W: Write>(&mut self, writer: &mut W) -> RJiterResult<()> {
         fn write_completed<W: Write>(
             string: &str,
             index: usize,
             writer: &mut W,
-        ) -> RJiterResult<()> {
+        ) -> LongWriteResult {
             writer
                 .write_all(string.as_bytes())
-                .map_err(|e| RJiterError {
-                    error_type: ErrorType::IoError { kind: e.kind() },
-                    index,
-                })
+                .map_err(|e| (ErrorType::IoError { kind: e.kind() }, index))
         }
         fn write_segment<W: Write>(
             bytes: &mut [u8],
             end_pos: usize,
             index: usize,
             writer: &mut W,
-        ) -> RJiterResult<()> {
-            // From the `write_long` contract for a big buffer: `1 < end_pos <= self.buffer.n_bytes - 1`
-            // May panic for a small buffer (less than 7 bytes)
-            #[allow(clippy::indexing_slicing)]
-            let orig_char = bytes[end_pos];
-            #[allow(clippy::indexing_slicing)]
-            {
-                bytes[end_pos] = b'"';
+        ) -> LongWriteResult {
+            decode_long_segment(bytes, end_pos, index, |string| {
+                writer
+                    .write_all(string.as_bytes())
+                    .map_err(|e| (ErrorType::IoError { kind: e.kind() }, index))
+            })
+        }
+        // SAFETY: the result borrows `self`, see `crate::lifetime`
+        #[allow(unsafe_code)]
+        let parser = |j: &mut Jiter<'rj>| unsafe { detach(j.known_str()) };
+        self.handle_long(parser, writer, write_completed, write_segment)
+    }
+
+    /// Like `write_long_str`, and also calls `on_chunk` after each chunk is written.
+    ///
+    /// A chunk is a complete piece of the decoded string: it never splits
+    /// a UTF-8 code point, even if the code point crosses the buffer boundary
+    /// in the input or is given as an escaped surrogate pair. Therefore
+    /// a consumer can display the chunks as soon as they arrive.
+    ///
+    /// The callback arguments are the byte offset of the chunk in the decoded string
+    /// and the chunk itself. The chunk boundaries are the offsets plus the total length.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character.
+    /// Bounding quotes are not included in the output.
+    ///
+    /// Returns the length of the decoded string in bytes.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`
+    pub fn write_long_str_chunks<W: Write, F: FnMut(usize, &str)>(
+        &mut self,
+        writer: &mut W,
+        on_chunk: F,
+    ) -> RJiterResult<usize> {
+        let mut chunk_writer = ChunkWriter {
+            writer,
+            on_chunk,
+            offset: 0,
+        };
+        self.write_long_str(&mut chunk_writer)?;
+        Ok(chunk_writer.offset)
+    }
+
+    /// Like `write_long_str`, but calls `on_chunk` with each decoded chunk instead of
+    /// writing to a `Write`, for example, to hash or forward the string without an adapter.
+    ///
+    /// As with `write_long_str_chunks`, a chunk never splits a UTF-8 code point.
+    /// If `on_chunk` returns an error, the parsing stops and the error is returned
+    /// as the inner result. Then rjiter is at an unspecified position inside the string.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character.
+    /// Bounding quotes are not included in the chunks.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError` as the outer result
+    pub fn for_each_str_chunk<E, F: FnMut(&[u8]) -> Result<(), E>>(
+        &mut self,
+        on_chunk: F,
+    ) -> RJiterResult<Result<(), E>> {
+        let mut callback_writer = CallbackWriter {
+            on_chunk,
+            error: None,
+        };
+        match self.write_long_str(&mut callback_writer) {
+            Ok(()) => Ok(Ok(())),
+            Err(e) => match callback_writer.error {
+                Some(callback_error) => Ok(Err(callback_error)),
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Like `write_long_str`, but writes the decoded string to several writers in one pass,
+    /// for example, to a hasher and to the output, without buffering the value.
+    ///
+    /// Each chunk is written to all writers, in the order of the slice,
+    /// before the next chunk is decoded. The writers should have the same error type.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character.
+    /// Bounding quotes are not included in the output.
+    ///
+    /// # Errors
+    /// `IoError` (from the first failed writer) or `JiterError`
+    pub fn write_long_str_fanout<E: embedded_io::Error>(
+        &mut self,
+        writers: &mut [&mut dyn Write<Error = E>],
+    ) -> RJiterResult<()> {
+        let mut fanout_writer = FanoutWriter { writers };
+        self.write_long_str(&mut fanout_writer)
+    }
+
+    /// Write-read-write-read-... until the end of the json number.
+    /// The number is written as it is in the input, therefore it can be longer
+    /// than the buffer, for example, a high-precision decimal.
+    ///
+    /// Rjiter should be positioned at the number or at whitespace before it.
+    /// After the call, rjiter is positioned after the number. In the feed mode,
+    /// a part of the number can be written before `NeedMoreData`.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`: `WrongType` if the next value is not a number,
+    /// `InvalidNumber` or `EofWhileParsingValue` for an invalid or incomplete number.
+    /// After an error, a part of the number can be already written.
+    pub fn write_long_number<W: Write>(&mut self, writer: &mut W) -> RJiterResult<()> {
+        let peek = self.peek()?;
+        if !peek.is_num() {
+            return Err(self.not_a_number(peek));
+        }
+        if self.allow_inf_nan && self.is_inf_nan(peek)? {
+            // The literals are short, therefore they fit into the buffer
+            let written = writer
+                .write_all(self.next_number_bytes()?)
+                .map_err(|e| e.kind());
+            return written.map_err(|kind| {
+                self.locate_error(ErrorType::IoError { kind }, self.current_index())
+            });
+        }
+        // Move the number to the beginning of the buffer
+        self.buffer.shift_buffer(0, self.jiter.current_index());
+        let start_index = self.buffer.n_shifted_out;
+        let mut skeleton = NumberSkeleton::new();
+        // The jiter is re-created after the loop, also on error
+        let result = loop {
+            let bytes = self
+                .buffer
+                .buf
+                .get(..self.buffer.n_bytes)
+                .unwrap_or_default();
+            let len = bytes
+                .iter()
+                .take_while(|&&byte| is_number_byte(byte))
+                .count();
+            let index = self.buffer.n_shifted_out + len;
+            for (i, &byte) in bytes.get(..len).unwrap_or_default().iter().enumerate() {
+                skeleton.push(byte, self.buffer.n_shifted_out + i);
             }
-            #[allow(clippy::indexing_slicing)]
-            let sub_jiter_buf = &bytes[..=end_pos];
+            // The number ends before a delimiter
+            let delimiter = bytes.get(len).copied();
+            if let Err(e) = self.check_token_len(index - start_index, Limit::NumberBytes, index) {
+                break Err(e);
+            }
+            if let Err(e) = writer.write_all(bytes.get(..len).unwrap_or_default()) {
+                break Err(self.locate_error(ErrorType::IoError { kind: e.kind() }, index));
+            }
+            self.buffer.shift_buffer(0, len);
+            let parsed = match delimiter {
+                Some(_) => skeleton.parse(delimiter, index),
+                None => match self.buffer.read_more() {
+                    Ok(0) => skeleton.parse(None, index),
+                    Ok(_) => continue,
+                    Err(e) => break Err(e),
+                },
+            };
+            break parsed.map_err(|(error_type, index)| self.locate_error(error_type, index));
+        };
+        self.create_new_jiter();
+        result
+    }
+
+    /// Decode the current json string into `buf` and return the length of the string in bytes.
+    ///
+    /// In contrast to `known_str`, the result doesn't borrow rjiter, therefore it can be
+    /// kept in the caller's structures while the parsing continues. The string doesn't need
+    /// to fit into the rjiter buffer.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character,
+    /// for example, after `peek` returned `Peek::String`.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`, or `OutputFull` if the decoded string is longer than `buf`.
+    /// After an error, rjiter is at an unspecified position inside the string.
+    pub fn known_str_into(&mut self, buf: &mut [u8]) -> RJiterResult<usize> {
+        let ((), len) = self.write_into(buf, |rjiter, writer| rjiter.write_long_str(writer))?;
+        Ok(len)
+    }
+
+    /// Like `write_long_str`, but writes the decoded string to the slice `out`
+    /// and returns the number of bytes written, for targets without a `Write` implementation.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character.
+    /// Bounding quotes are not included in the output.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`, or `OutputFull` if the decoded string is longer than `out`.
+    /// Then `out[..written]` is the beginning of the string, it doesn't end in the middle
+    /// of a code point. After an error, rjiter is at an unspecified position inside the string.
+    pub fn read_long_str_into(&mut self, out: &mut [u8]) -> RJiterResult<usize> {
+        self.known_str_into(out)
+    }
+
+    /// Consume the current json string without producing output. The string
+    /// doesn't need to fit into the rjiter buffer, in contrast to `next_skip`.
+    ///
+    /// The escapes are not decoded, therefore skipping is cheaper than
+    /// `write_long_str` to a discarding writer.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character.
+    /// After the call, rjiter is positioned after the string.
+    ///
+    /// # Errors
+    /// `JiterError` or `IoError` from the reader
+    pub fn skip_long_str(&mut self) -> RJiterResult<()> {
+        self.write_long_bytes(&mut SkipWriter)
+    }
+
+    /// Find the byte ranges of the top-level values up to the end of the input,
+    /// without materializing them. For example, to split a JSON Lines file
+    /// into ranges for parallel processing in one cheap pass.
+    ///
+    /// For each value, `on_value` gets the absolute index of its first byte and
+    /// the index after its last byte. The values are validated and can be longer
+    /// than the buffer, see `write_long_value`.
+    ///
+    /// Returns the number of the values.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`. The values before the error are already reported.
+    pub fn index_top_level_values<F: FnMut(usize, usize)>(
+        &mut self,
+        mut on_value: F,
+    ) -> RJiterResult<usize> {
+        let mut n_values = 0;
+        while self.peek_raw_byte(true)?.is_some() {
+            let start = self.current_index();
+            self.write_long_value(&mut SkipWriter)?;
+            on_value(start, self.current_index());
+            n_values += 1;
+        }
+        Ok(n_values)
+    }
+
+    /// Copy the next json value to `writer` as it is in the input: an object,
+    /// an array, a string, a number or a literal. The value can be longer than the buffer,
+    /// for example, to forward a subtree from a `scan_json` action.
+    ///
+    /// The strings, the keys and the numbers are copied as such, without decoding
+    /// the escapes, and can be longer than the buffer. The whitespace inside the value
+    /// is copied too, the whitespace before and after the value is not. In the tolerant
+    /// mode, the comments and the trailing commas are dropped, and so is the whitespace.
+    /// The value is validated while copying, the nesting limit is the same as in `Jiter`.
+    ///
+    /// After the call, rjiter is positioned after the value.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`. After an error, a part of the value can be already written.
+    pub fn write_long_value<W: Write>(&mut self, writer: &mut W) -> RJiterResult<()> {
+        // Bit `n` is set if the container at the depth `n` is an object
+        let mut object_bits = [0u8; WRITE_VALUE_MAX_DEPTH / 8];
+        let mut depth = 0;
+        let mut peek = self.peek()?;
+        'value: loop {
+            // Write a scalar, or open a container and continue with its first value
+            let mut is_empty = false;
+            match peek {
+                Peek::Array | Peek::Object => {
+                    let Some(bits) = object_bits.get_mut(depth / 8) else {
+                        return Err(self.error_here(JsonErrorType::RecursionLimitExceeded));
+                    };
+                    let bit = 1 << (depth % 8);
+                    depth += 1;
+                    let (opening, closing): (&[u8], u8) = if peek == Peek::Array {
+                        *bits &= !bit;
+                        (b"[", b']')
+                    } else {
+                        *bits |= bit;
+                        (b"{", b'}')
+                    };
+                    self.skip_n_bytes(1)?;
+                    self.write_raw(writer, opening)?;
+                    self.write_spaces(writer)?;
+                    peek = self.peek()?;
+                    if peek != Peek::new(closing) {
+                        if closing == b'}' {
+                            self.write_long_key_json(writer)?;
+                            peek = self.peek()?;
+                        }
+                        continue 'value;
+                    }
+                    is_empty = true;
+                }
+                Peek::String => {
+                    self.write_raw(writer, b"\"")?;
+                    self.write_long_bytes(writer)?;
+                    self.write_raw(writer, b"\"")?;
+                }
+                Peek::Null => {
+                    self.known_null()?;
+                    self.write_raw(writer, b"null")?;
+                }
+                Peek::True | Peek::False => {
+                    let literal: &[u8] = if self.known_bool(peek)? {
+                        b"true"
+                    } else {
+                        b"false"
+                    };
+                    self.write_raw(writer, literal)?;
+                }
+                _ if peek.is_num() => self.write_long_number(writer)?,
+                _ => return Err(self.error_here(JsonErrorType::ExpectedSomeValue)),
+            }
+
+            // Continue with the next value of the container,
+            // or close the container and repeat for the parent container
+            while depth > 0 {
+                let in_object = object_bits
+                    .get((depth - 1) / 8)
+                    .is_some_and(|bits| bits & (1 << ((depth - 1) % 8)) != 0);
+                let (closing, error_type, eof_type) = if in_object {
+                    (
+                        b'}',
+                        JsonErrorType::ExpectedObjectCommaOrEnd,
+                        JsonErrorType::EofWhileParsingObject,
+                    )
+                } else {
+                    (
+                        b']',
+                        JsonErrorType::ExpectedListCommaOrEnd,
+                        JsonErrorType::EofWhileParsingList,
+                    )
+                };
+                if !is_empty {
+                    self.write_spaces(writer)?;
+                    let next = match self.peek() {
+                        Err(err)
+                            if err.error_type
+                                == ErrorType::JsonError(JsonErrorType::EofWhileParsingValue) =>
+                        {
+                            return Err(self.error_here(eof_type));
+                        }
+                        result => result?,
+                    };
+                    if next == Peek::new(b',') {
+                        self.skip_n_bytes(1)?;
+                        // In the tolerant mode, the whitespace is dropped, and
+                        // the comma is written only if a value follows
+                        if !self.tolerant {
+                            self.write_raw(writer, b",")?;
+                            self.write_spaces(writer)?;
+                        }
+                        peek = self.peek()?;
+                        if peek != Peek::new(closing) {
+                            if self.tolerant {
+                                self.write_raw(writer, b",")?;
+                            }
+                            if in_object {
+                                self.write_long_key_json(writer)?;
+                                peek = self.peek()?;
+                            }
+                            continue 'value;
+                        }
+                        if !self.tolerant {
+                            return Err(self.error_here(JsonErrorType::TrailingComma));
+                        }
+                    } else if next != Peek::new(closing) {
+                        return Err(self.error_here(error_type));
+                    }
+                }
+                self.skip_n_bytes(1)?;
+                self.write_raw(writer, &[closing])?;
+                depth -= 1;
+                is_empty = false;
+            }
+            return Ok(());
+        }
+    }
+
+    /// Get a reader for the decoded bytes of the current json string.
+    ///
+    /// Rjiter should be positioned at the beginning of the json string, on a quote character.
+    /// The reader returns the string without the bounding quotes, then end of input.
+    /// After the end, rjiter is positioned after the string.
+    ///
+    /// As with `write_long_str_chunks`, a non-empty read never splits
+    /// a UTF-8 code point if the caller's buffer has at least 4 bytes.
+    pub fn string_reader(&mut self) -> StringReader<'_, 'rj, R> {
+        StringReader::new(self)
+    }
+
+    /// Pull the next chunk of the current json string, without copying it.
+    ///
+    /// On the first call, rjiter should be positioned before a json string.
+    /// Then call the function again until it returns the last chunk (`true` as the second value),
+    /// without other calls to rjiter in between. After the last chunk, rjiter is positioned
+    /// after the string.
+    ///
+    /// In contrast to `write_long_str`, the caller can do other work between the calls.
+    /// A chunk is decoded and never splits a UTF-8 code point. Only the last chunk can be empty.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`
+    pub fn next_str_partial(&mut self) -> RJiterResult<(&str, bool)> {
+        let start_index = if let Some((start_index, segment_end_pos)) = self.partial_str.take() {
+            // Consume the chunk of the previous call, keep the quote at the beginning
+            self.buffer.shift_buffer(1, segment_end_pos);
+            self.create_new_jiter();
+            start_index
+        } else {
+            if self.peek()? != Peek::String {
+                // Not a string: let `next_str` report the error
+                return self.next_str().map(|string| (string, true));
+            }
+            // Move the string to the beginning of the buffer to avoid corner cases.
+            if self.jiter.current_index() > 0 {
+                self.buffer.shift_buffer(0, self.jiter.current_index());
+                self.create_new_jiter();
+            }
+            self.current_index()
+        };
+
+        loop {
+            // SAFETY: the result borrows `self`, see `crate::lifetime`
             #[allow(unsafe_code)]
-            let sub_jiter_buf = unsafe { core::mem::transmute::<&[u8], &[u8]>(sub_jiter_buf) };
-            let mut sub_jiter = Jiter::new(sub_jiter_buf);
-            let sub_result = sub_jiter.known_str();
-            #[allow(clippy::indexing_slicing)]
-            {
-                bytes[end_pos] = orig_char;
+            let result = unsafe { detach(self.jiter.known_str()) };
+            let err = match result {
+                Ok(string) => {
+                    self.check_long_token_len(start_index, self.current_index() - 1)?;
+                    return Ok((string, true));
+                }
+                Err(err) => err,
+            };
+            if !can_retry_if_partial(&err) {
+                return Err(RJiterError::from_jiter_error(
+                    self.current_index(),
+                    err,
+                    |index| self.error_position(index),
+                ));
             }
 
-            match sub_result {
-                Ok(string) => writer
-                    .write_all(string.as_bytes())
-                    .map_err(|e| RJiterError {
-                        error_type: ErrorType::IoError { kind: e.kind() },
-                        index,
-                    }),
-                Err(e) => Err(RJiterError::from_jiter_error(index, e)),
+            // Fill the buffer to return a bigger chunk
+            if self.buffer.n_bytes < self.buffer.buf.len() {
+                if self.buffer.read_more()? == 0 {
+                    return Err(RJiterError::from_jiter_error(
+                        self.current_index(),
+                        err,
+                        |index| self.error_position(index),
+                    ));
+                }
+                self.create_new_jiter();
+                continue;
             }
+
+            let segment_end_pos = self.segmenter().segment_end(self.buffer.n_bytes);
+            if segment_end_pos <= 1 {
+                return Err(self.locate_error(ErrorType::BufferFull, self.current_index()));
+            }
+            self.check_long_token_len(start_index, self.buffer.n_shifted_out + segment_end_pos)?;
+            // A segment is either plain text, which is returned from the buffer as is,
+            // or one escape. The decoded escape (at most 4 bytes) is shorter
+            // than the escape sequence, and is put over it in the buffer.
+            let is_escape = self.segmenter().is_escape();
+            let mut decoded = [0u8; 4];
+            let mut len = 0;
+            let index = self.current_index();
+            #[cfg(feature = "trace")]
+            self.buffer.trace(Event::LongSegment {
+                index: index + 1,
+                len: segment_end_pos - 1,
+            });
+            decode_long_segment(self.buffer.buf, segment_end_pos, index, |string| {
+                len = string.len();
+                if is_escape {
+                    if let Some(decoded) = decoded.get_mut(..len) {
+                        decoded.copy_from_slice(string.as_bytes());
+                    }
+                }
+                Ok(())
+            })
+            .map_err(|(error_type, index)| self.locate_error(error_type, index))?;
+            if is_escape {
+                if let (Some(dst), Some(src)) =
+                    (self.buffer.buf.get_mut(1..=len), decoded.get(..len))
+                {
+                    dst.copy_from_slice(src);
+                }
+            }
+            self.partial_str = Some((start_index, segment_end_pos));
+            let chunk = self.buffer.buf.get(1..=len).unwrap_or_default();
+            // The segment is decoded above, the check should not fail
+            let Ok(chunk) = core::str::from_utf8(chunk) else {
+                return Err(self.locate_error(
+                    ErrorType::JsonError(JsonErrorType::InvalidUnicodeCodePoint),
+                    index + 1,
+                ));
+            };
+            return Ok((chunk, false));
         }
-        #[allow(unsafe_code)]
-        let parser = |j: &mut Jiter<'rj>| unsafe {
-            core::mem::transmute::<JiterResult<&str>, JiterResult<&'rj str>>(j.known_str())
+    }
+
+    /// Like `next_object_bytes`, but the key can be longer than the buffer.
+    /// The key bytes are written to `writer` as such, without the bounding quotes.
+    ///
+    /// Returns `true` if a key is found, or `false` if the object is empty.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`
+    pub fn next_object_long_key<W: Write>(&mut self, writer: &mut W) -> RJiterResult<bool> {
+        self.next_long_key_after(b'{', writer, KeyOutput::Bytes)
+    }
+
+    /// Like `next_key_bytes`, but the key can be longer than the buffer.
+    /// The key bytes are written to `writer` as such, without the bounding quotes.
+    ///
+    /// Returns `true` if a key is found, or `false` at the end of the object.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`
+    pub fn next_long_key<W: Write>(&mut self, writer: &mut W) -> RJiterResult<bool> {
+        self.next_long_key_after(b',', writer, KeyOutput::Bytes)
+    }
+
+    /// Like `next_object_bytes`, but the escapes in the key are decoded into `buf`.
+    ///
+    /// The result is in `buf`, therefore it doesn't borrow rjiter, and a key
+    /// like `"caf\u00e9"` can be compared with the plain text `café`.
+    /// The key can be longer than the rjiter buffer.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`, or `OutputFull` if the decoded key is longer than `buf`.
+    pub fn next_object_decoded<'b>(&mut self, buf: &'b mut [u8]) -> RJiterResult<Option<&'b [u8]>> {
+        self.next_key_decoded_after(b'{', buf)
+    }
+
+    /// Like `next_key_bytes`, but the escapes in the key are decoded into `buf`,
+    /// see `next_object_decoded`.
+    ///
+    /// # Errors
+    /// `IoError` or `JiterError`, or `OutputFull` if the decoded key is longer than `buf`.
+    pub fn next_key_decoded<'b>(&mut self, buf: &'b mut [u8]) -> RJiterResult<Option<&'b [u8]>> {
+        self.next_key_decoded_after(b',', buf)
+    }
+
+    fn next_key_decoded_after<'b>(
+        &mut self,
+        opening: u8,
+        buf: &'b mut [u8],
+    ) -> RJiterResult<Option<&'b [u8]>> {
+        let (is_key, len) = self.write_into(buf, |rjiter, writer| {
+            rjiter.next_long_key_after(opening, writer, KeyOutput::Decoded)
+        })?;
+        let buf: &'b [u8] = buf;
+        Ok(if is_key { buf.get(..len) } else { None })
+    }
+
+    // Call `write` with a writer to `buf`, return its result and the number of written bytes.
+    // If `buf` overflows, the error is `OutputFull`.
+    fn write_into<T>(
+        &mut self,
+        buf: &mut [u8],
+        write: impl FnOnce(&mut Self, &mut SliceWriter<'_>) -> RJiterResult<T>,
+    ) -> RJiterResult<(T, usize)> {
+        let mut slice_writer = SliceWriter {
+            buf,
+            len: 0,
+            is_full: false,
         };
-        self.handle_long(parser, writer, write_completed, write_segment)
+        match write(self, &mut slice_writer) {
+            Ok(result) => Ok((result, slice_writer.len)),
+            Err(e) if slice_writer.is_full => Err(RJiterError {
+                error_type: ErrorType::OutputFull {
+                    written: slice_writer.len,
+                },
+                ..e
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Consume `opening` (`{` or `,`), then a key and a colon, or the end of the object.
+    // The key is written as such, decoded or as json.
+    fn next_long_key_after<W: Write>(
+        &mut self,
+        opening: u8,
+        writer: &mut W,
+        output: KeyOutput,
+    ) -> RJiterResult<bool> {
+        let (expected, error_type) = if opening == b'{' {
+            (Peek::Object, JsonErrorType::ExpectedSomeValue)
+        } else {
+            (Peek::new(b','), JsonErrorType::ExpectedObjectCommaOrEnd)
+        };
+        let peek = self.peek()?;
+        if opening == b',' && peek == Peek::new(b'}') {
+            self.skip_n_bytes(1)?;
+            return Ok(false);
+        }
+        if peek != expected {
+            return Err(self.error_here(error_type));
+        }
+        self.skip_n_bytes(1)?;
+
+        let peek = self.peek()?;
+        // In the tolerant mode, a comma can be before the end of the object
+        if (opening == b'{' || self.tolerant) && peek == Peek::new(b'}') {
+            self.skip_n_bytes(1)?;
+            return Ok(false);
+        }
+        if peek != Peek::String {
+            return Err(self.error_here(JsonErrorType::KeyMustBeAString));
+        }
+        match output {
+            KeyOutput::Bytes => self.write_long_bytes(writer)?,
+            KeyOutput::Decoded => self.write_long_str(writer)?,
+        }
+
+        if self.peek()? != Peek::new(b':') {
+            return Err(self.error_here(JsonErrorType::ExpectedColon));
+        }
+        self.skip_n_bytes(1)?;
+        Ok(true)
+    }
+
+    // Copy a key, the colon and the whitespace around it for `write_long_value`
+    fn write_long_key_json<W: Write>(&mut self, writer: &mut W) -> RJiterResult<()> {
+        if self.peek()? != Peek::String {
+            return Err(self.error_here(JsonErrorType::KeyMustBeAString));
+        }
+        self.write_raw(writer, b"\"")?;
+        self.write_long_bytes(writer)?;
+        self.write_raw(writer, b"\"")?;
+        self.write_spaces(writer)?;
+        if self.peek()? != Peek::new(b':') {
+            return Err(self.error_here(JsonErrorType::ExpectedColon));
+        }
+        self.skip_n_bytes(1)?;
+        self.write_raw(writer, b":")?;
+        self.write_spaces(writer)
+    }
+
+    // Copy the whitespace before the next token for `write_long_value`.
+    // In the tolerant mode, the whitespace is skipped by `peek` together with the comments.
+    fn write_spaces<W: Write>(&mut self, writer: &mut W) -> RJiterResult<()> {
+        if self.tolerant {
+            return Ok(());
+        }
+        let whitespace = self.buffer.whitespace;
+        loop {
+            let pos = self.jiter.current_index();
+            let rest = self
+                .buffer
+                .buf
+                .get(pos..self.buffer.n_bytes)
+                .unwrap_or_default();
+            let n_spaces = rest
+                .iter()
+                .take_while(|byte| whitespace.is_whitespace(**byte))
+                .count();
+            if n_spaces > 0 {
+                #[allow(clippy::indexing_slicing)]
+                self.write_raw(writer, &rest[..n_spaces])?;
+            } else if !rest.is_empty() {
+                return Ok(());
+            }
+            // Drop the written whitespace, read more at the end of the buffer
+            self.buffer.shift_buffer(0, pos + n_spaces);
+            let n_read = if self.buffer.n_bytes == 0 {
+                self.buffer.read_more()
+            } else {
+                Ok(1)
+            };
+            self.create_new_jiter();
+            if n_read? == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Write the json syntax around the values for `write_long_value`
+    fn write_raw<W: Write>(&self, writer: &mut W, bytes: &[u8]) -> RJiterResult<()> {
+        writer.write_all(bytes).map_err(|e| {
+            self.locate_error(ErrorType::IoError { kind: e.kind() }, self.current_index())
+        })
+    }
+
+    fn error_here(&self, error_type: JsonErrorType) -> RJiterError {
+        let index = self.current_index();
+        RJiterError::from_json_error(index, error_type, |index| self.error_position(index))
+    }
+
+    // Read the next decoded piece of a long string into `out`.
+    // If a code point or an escape doesn't fit into `out`, the tail of its
+    // decoded bytes is put into `overflow`.
+    // Returns the number of bytes in `out`, in `overflow` and whether the string is completed.
+    // `start_index` is the index of the opening quote, to check the token length.
+    pub(crate) fn read_long_str_chunk(
+        &mut self,
+        out: &mut [u8],
+        overflow: &mut [u8; 4],
+        start_index: usize,
+    ) -> RJiterResult<(usize, usize, bool)> {
+        // Move the string to the beginning of the buffer to avoid corner cases.
+        if self.jiter.current_index() > 0 {
+            self.buffer.shift_buffer(0, self.jiter.current_index());
+            self.create_new_jiter();
+        }
+
+        loop {
+            // SAFETY: the result borrows `self`, see `crate::lifetime`
+            #[allow(unsafe_code)]
+            let result = unsafe { detach(self.jiter.known_str()) };
+            // If the string is completed in the buffer and doesn't fit into `out`,
+            // rewind and copy the part before the closing quote
+            let mut max_end = self.buffer.n_bytes;
+            let mut partial_err = None;
+            match result {
+                Ok(string) => {
+                    self.check_long_token_len(start_index, self.current_index() - 1)?;
+                    if let Some(out) = out.get_mut(..string.len()) {
+                        out.copy_from_slice(string.as_bytes());
+                        return Ok((string.len(), 0, true));
+                    }
+                    max_end = self.jiter.current_index() - 1;
+                    self.create_new_jiter();
+                }
+                Err(err) => {
+                    if !can_retry_if_partial(&err) {
+                        return Err(RJiterError::from_jiter_error(
+                            self.current_index(),
+                            err,
+                            |index| self.error_position(index),
+                        ));
+                    }
+                    partial_err = Some(err);
+                }
+            }
+
+            // A plain segment: decoded bytes are the same as the raw bytes,
+            // make it fit into `out`, but take at least one code point
+            let segmenter = self.segmenter();
+            let segment_end_pos = segmenter.fit_end(segmenter.segment_end(max_end), out.len());
+
+            if segment_end_pos > 1 {
+                self.check_long_token_len(
+                    start_index,
+                    self.buffer.n_shifted_out + segment_end_pos,
+                )?;
+                let index = self.current_index();
+                #[cfg(feature = "trace")]
+                self.buffer.trace(Event::LongSegment {
+                    index: index + 1,
+                    len: segment_end_pos - 1,
+                });
+                let mut n_written = (0, 0);
+                decode_long_segment(self.buffer.buf, segment_end_pos, index, |string| {
+                    let bytes = string.as_bytes();
+                    let n_out = bytes.len().min(out.len());
+                    let (to_out, to_overflow) = bytes.split_at(n_out);
+                    if let (Some(out), Some(overflow)) =
+                        (out.get_mut(..n_out), overflow.get_mut(..to_overflow.len()))
+                    {
+                        out.copy_from_slice(to_out);
+                        overflow.copy_from_slice(to_overflow);
+                        n_written = (n_out, to_overflow.len());
+                    }
+                    Ok(())
+                })
+                .map_err(|(error_type, index)| self.locate_error(error_type, index))?;
+                self.buffer.shift_buffer(1, segment_end_pos);
+                self.create_new_jiter();
+                return Ok((n_written.0, n_written.1, false));
+            }
+
+            // Read more and repeat
+            let n_new_bytes = self.buffer.read_more()?;
+            match (n_new_bytes, partial_err) {
+                (1.., _) => self.create_new_jiter(),
+                (0, Some(err)) => {
+                    return Err(RJiterError::from_jiter_error(
+                        self.current_index(),
+                        err,
+                        |index| self.error_position(index),
+                    ))
+                }
+                (0, None) => {
+                    return Err(self.locate_error(ErrorType::BufferFull, self.current_index()))
+                }
+            }
+        }
     }
 
     //  ------------------------------------------------------------
@@ -805,6 +2158,136 @@ impl<'rj, R: Read> RJiter<'rj, R> {
         Ok(slice)
     }
 
+    /// Peek the next unconsumed byte as is, without JSON interpretation.
+    /// If `skip_whitespace` is `true`, whitespace is consumed first.
+    ///
+    /// Useful for debugging, and for custom framing code which inspects delimiters
+    /// that `RJiter` doesn't understand, such as the record separator of JSON text sequences.
+    /// Returns `None` at the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from the underlying reader.
+    pub fn peek_raw_byte(&mut self, skip_whitespace: bool) -> RJiterResult<Option<u8>> {
+        if skip_whitespace {
+            // Discard the already parsed bytes, then the new jiter starts at position 0
+            self.buffer.shift_buffer(0, self.jiter.current_index());
+            let result = self.skip_spaces_at(0);
+            self.create_new_jiter();
+            result?;
+        }
+        Ok(self.lookahead_n(1)?.first().copied())
+    }
+
+    /// Find out the kind of the next number without parsing and consuming it.
+    ///
+    /// Useful to choose how to read and write the number, for example, as `i64`
+    /// or as text, before calling `next_int`, `next_float` or `next_number_bytes`.
+    /// The number is looked ahead, therefore it should fit into the buffer.
+    /// The number is not validated, the next `next_*` call reports errors in it.
+    ///
+    /// # Errors
+    ///
+    /// `WrongType` if the next value is not a number, `BufferFull` if the number
+    /// doesn't fit into the buffer. Also returns errors from the underlying reader.
+    pub fn peek_number_kind(&mut self) -> RJiterResult<NumberKind> {
+        let peek = self.peek()?;
+        if !peek.is_num() {
+            return Err(self.not_a_number(peek));
+        }
+        let token =
+            self.lookahead_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.'))?;
+        Ok(NumberKind::of_token(token))
+    }
+
+    /// Find out the type and the length of the next value without consuming it.
+    ///
+    /// Useful to decide whether to copy or to skip a value before consuming it.
+    /// The whole value is looked ahead, therefore the length is known only if
+    /// the value fits into the buffer, otherwise `len` is `None`. The value is
+    /// validated: the next `next_*` call succeeds if `len` is known.
+    /// Whitespace before the value is consumed.
+    ///
+    /// # Errors
+    ///
+    /// `JsonError` if the value is invalid. Also returns errors from the underlying reader.
+    pub fn peek_value(&mut self) -> RJiterResult<PeekedValue> {
+        let peek = self.peek()?;
+        loop {
+            let start = self.jiter.current_index();
+            // `start <= n_bytes` (the parser is in the buffer), `n_bytes <= buf.len()` (contract)
+            #[allow(clippy::indexing_slicing)]
+            let window = &self.buffer.buf[start..self.buffer.n_bytes];
+            let mut jiter = Jiter::new(window);
+            if self.allow_inf_nan {
+                jiter = jiter.with_allow_inf_nan();
+            }
+            let result = jiter.next_skip().map(|()| jiter.current_index());
+            let err = match result {
+                // A number at the end of the buffer can continue after reading more
+                Ok(len) if len < window.len() || !peek.is_num() || self.buffer.is_eof_seen => {
+                    return Ok(PeekedValue {
+                        peek,
+                        len: Some(len),
+                    });
+                }
+                Ok(_) => None,
+                Err(err) if can_retry_if_partial(&err) => Some(err),
+                Err(err) => {
+                    return Err(RJiterError::from_jiter_error(
+                        self.current_index(),
+                        err,
+                        |index| self.error_position(index),
+                    ));
+                }
+            };
+
+            // Move the value to the beginning of the buffer and read more
+            if start > 0 {
+                self.buffer.shift_buffer(0, start);
+                self.create_new_jiter();
+            }
+            if self.buffer.n_bytes >= self.buffer.buf.len() {
+                return Ok(PeekedValue { peek, len: None });
+            }
+            if self.buffer.read_more()? == 0 {
+                if let Some(err) = err {
+                    return Err(RJiterError::from_jiter_error(
+                        self.current_index(),
+                        err,
+                        |index| self.error_position(index),
+                    ));
+                }
+            }
+            self.create_new_jiter();
+        }
+    }
+
+    // Whether the number at `peek` is `NaN`, `Infinity` or `-Infinity`
+    fn is_inf_nan(&mut self, peek: Peek) -> RJiterResult<bool> {
+        match peek {
+            Peek::NaN | Peek::Infinity => Ok(true),
+            Peek::Minus => Ok(self.lookahead_n(2)? == b"-I"),
+            _ => Ok(false),
+        }
+    }
+
+    // The error for `peek` when a number is expected
+    fn not_a_number(&self, peek: Peek) -> RJiterError {
+        let actual = match peek {
+            Peek::Null => JsonType::Null,
+            Peek::True | Peek::False => JsonType::Bool,
+            Peek::String => JsonType::String,
+            Peek::Array => JsonType::Array,
+            _ => JsonType::Object,
+        };
+        let expected = JsonType::Int;
+        self.locate_error(
+            ErrorType::WrongType { expected, actual },
+            self.current_index(),
+        )
+    }
+
     /// Skip exactly `count` bytes, consuming them from the buffer.
     /// Returns the number of bytes actually skipped (may be less than `count` if EOF is reached).
     ///
@@ -834,39 +2317,388 @@ impl<'rj, R: Read> RJiter<'rj, R> {
         Ok(bytes_skipped)
     }
 
+    /// Skip a UTF-8 byte order mark and the bytes `prologue` before the first value.
+    /// Each of them is skipped only if present. Whitespace between them is also skipped.
+    /// Use an empty `prologue` to skip only the byte order mark.
+    ///
+    /// Some feeds prepend a prologue to the JSON, for example, the anti-XSSI prefix `)]}'`.
+    /// Call this function before parsing the first value.
+    ///
+    /// Returns the number of skipped bytes of the byte order mark and the prologue,
+    /// not counting whitespace.
+    ///
+    /// # Errors
+    ///
+    /// `IoError`, or `BufferFull` if the prologue doesn't fit into the buffer.
+    pub fn skip_prologue(&mut self, prologue: &[u8]) -> RJiterResult<usize> {
+        const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+        // Discard the already parsed bytes, then the new jiter starts at position 0
+        self.buffer.shift_buffer(0, self.jiter.current_index());
+        self.create_new_jiter();
+
+        let mut n_skipped = 0;
+        if self.buffer.skip_prefix(0, BOM)? {
+            n_skipped += BOM.len();
+        }
+        if !prologue.is_empty() {
+            self.buffer.skip_spaces(0)?;
+            if self.buffer.skip_prefix(0, prologue)? {
+                n_skipped += prologue.len();
+            }
+        }
+        self.create_new_jiter();
+        Ok(n_skipped)
+    }
+
     //  ------------------------------------------------------------
     // Skip token
     //
 
+    /// Skip whitespace and the tokens of `with_skip_tokens`, as long as one of them is found.
+    /// Call this function between the values, for example, before each top-level value
+    /// of a server-sent events stream.
+    ///
+    /// Returns the number of skipped tokens.
+    ///
+    /// # Errors
+    ///
+    /// `IoError`, or `BufferFull` if a token doesn't fit into the buffer.
+    pub fn skip_tokens(&mut self) -> RJiterResult<usize> {
+        // Discard the already parsed bytes, then the new jiter starts at position 0
+        self.buffer.shift_buffer(0, self.jiter.current_index());
+        let mut n_skipped = 0;
+        let result = 'skip: loop {
+            if let Err(e) = self.skip_spaces_at(0) {
+                break Err(e);
+            }
+            for token in self.skip_tokens {
+                match self.buffer.skip_prefix(0, token) {
+                    Ok(true) => {
+                        n_skipped += 1;
+                        continue 'skip;
+                    }
+                    Ok(false) => {}
+                    Err(e) => break 'skip Err(e),
+                }
+            }
+            break Ok(n_skipped);
+        };
+        // Also after an error, as the buffer may be shifted
+        self.create_new_jiter();
+        result
+    }
+
     /// Skip the token if found, otherwise return an error.
     /// `RJiter` should be positioned at the beginning of the potential token using `peek()` or `finish()`
     ///
     /// # Errors
     /// `IoError` or `RJiterError(ExpectedSomeIdent)`
     pub fn known_skip_token(&mut self, token: &[u8]) -> RJiterResult<()> {
+        self.known_skip_token_with(token, TokenMatch::Exact)
+    }
+
+    /// Skip the token if found, comparing as set by `mode`, otherwise return an error.
+    /// `RJiter` should be positioned at the beginning of the potential token using `peek()` or `finish()`
+    ///
+    /// With any mode, only the bytes of the token are skipped.
+    ///
+    /// # Errors
+    /// `IoError` or `RJiterError(ExpectedSomeIdent)`
+    pub fn known_skip_token_with(&mut self, token: &[u8], mode: TokenMatch) -> RJiterResult<()> {
         // Lookahead the expected number of bytes
         let lookahead = self.lookahead_n(token.len())?;
 
         // Check if the lookahead matches the token
-        let found = lookahead == token;
+        let found = match mode {
+            TokenMatch::Exact | TokenMatch::Prefix => lookahead == token,
+            TokenMatch::CaseInsensitive => lookahead.eq_ignore_ascii_case(token),
+        };
 
         // If found, skip the bytes to consume them
         if found {
             self.skip_n_bytes(token.len())?;
             Ok(())
         } else {
+            let index = self.current_index();
             Err(RJiterError::from_json_error(
-                self.current_index(),
+                index,
                 JsonErrorType::ExpectedSomeIdent,
+                |index| self.error_position(index),
             ))
         }
     }
 }
 
-fn is_utf8_leading_byte(b: u8) -> bool {
-    // Linters suggests to use `!(0b1000_0000..0b1100_0000).contains(&b)`,
-    // but for me the suggestion looks much less readable
-    #[allow(clippy::manual_range_contains)]
-    let flag = (b < 0b1000_0000) || (b >= 0b1100_0000);
-    flag
+// In the tolerant mode, a peeked slash can start a comment. Then make an error
+// at the slash, and `loop_until_success` drops the comment and repeats the call.
+fn comment_as_error(
+    peek: Option<Peek>,
+    jiter: &Jiter,
+    tolerant: bool,
+) -> JiterResult<Option<Peek>> {
+    if tolerant && peek == Some(Peek::new(b'/')) {
+        return Err(JiterError {
+            error_type: JiterErrorType::JsonError(JsonErrorType::ExpectedSomeValue),
+            index: jiter.current_index(),
+        });
+    }
+    Ok(peek)
+}
+
+// Decode the segment `bytes[1..end_pos]` of a long string and pass the result to `f`.
+// Temporarily puts a quote at `end_pos` to parse the segment as a complete string.
+// `index` is the absolute position of `bytes[0]`.
+//
+// With escapes, the parser locates invalid UTF-8 in the decoded text. The escapes
+// are ASCII, therefore the error is located again in the raw bytes, and the index
+// is the absolute position of the first byte of the invalid sequence.
+fn decode_long_segment(
+    bytes: &mut [u8],
+    end_pos: usize,
+    index: usize,
+    f: impl FnOnce(&str) -> LongWriteResult,
+) -> LongWriteResult {
+    // From the `handle_long` contract for a big buffer: `1 < end_pos <= self.buffer.n_bytes - 1`
+    // May panic for a small buffer (less than 7 bytes)
+    #[allow(clippy::indexing_slicing)]
+    let orig_char = bytes[end_pos];
+    #[allow(clippy::indexing_slicing)]
+    {
+        bytes[end_pos] = b'"';
+    }
+    #[allow(clippy::indexing_slicing)]
+    let mut sub_jiter = Jiter::new(&bytes[..=end_pos]);
+    let result = match sub_jiter.known_str() {
+        Ok(string) => f(string),
+        Err(e) => {
+            let raw_error = (e.error_type
+                == JiterErrorType::JsonError(JsonErrorType::InvalidUnicodeCodePoint))
+            .then(|| core::str::from_utf8(bytes.get(1..end_pos).unwrap_or_default()).err())
+            .flatten();
+            let error_index = match raw_error {
+                Some(utf8_error) => index + 1 + utf8_error.valid_up_to(),
+                None => e.index + index,
+            };
+            Err((ErrorType::from_jiter_error_type(e.error_type), error_index))
+        }
+    };
+    #[allow(clippy::indexing_slicing)]
+    {
+        bytes[end_pos] = orig_char;
+    }
+    result
+}
+
+fn is_number_byte(byte: u8) -> bool {
+    matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+}
+
+// The number of input bytes of the string, the key or the number in `bytes`,
+// between the quotes for strings and keys, the number characters for numbers.
+// `bytes` can also have the delimiters around the token, such as `,` and `:` of a key.
+// `None` if there is no token, for example, `}` of an object without more keys.
+// For an incomplete token, it is the number of the bytes read so far.
+fn token_input_len(bytes: &[u8], limit: Limit) -> Option<usize> {
+    if limit == Limit::NumberBytes {
+        let len = bytes
+            .iter()
+            .filter(|b| is_number_byte(**b) || b.is_ascii_alphabetic())
+            .count();
+        return (len > 0).then_some(len);
+    }
+    let first = bytes.iter().position(|b| *b == b'"')?;
+    match bytes.iter().rposition(|b| *b == b'"') {
+        Some(last) if last > first => Some(last - first - 1),
+        _ => Some(bytes.len() - first - 1),
+    }
+}
+
+// Capacity of `NumberSkeleton`, a valid skeleton is at most 10 bytes: `-12.12e+12`
+const MAX_NUMBER_SKELETON: usize = 16;
+
+// A long number for the jiter parser in `write_long_number`, with the runs of digits
+// shortened to two digits, which are enough to accept or reject a run. The bytes after
+// the capacity are dropped, as the parser rejects such a number before them.
+struct NumberSkeleton {
+    bytes: [u8; MAX_NUMBER_SKELETON],
+    indexes: [usize; MAX_NUMBER_SKELETON],
+    len: usize,
+    n_run_digits: usize,
+}
+
+impl NumberSkeleton {
+    fn new() -> Self {
+        NumberSkeleton {
+            bytes: [0; MAX_NUMBER_SKELETON],
+            indexes: [0; MAX_NUMBER_SKELETON],
+            len: 0,
+            n_run_digits: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8, index: usize) {
+        if byte.is_ascii_digit() {
+            self.n_run_digits += 1;
+            if self.n_run_digits > 2 {
+                return;
+            }
+        } else {
+            self.n_run_digits = 0;
+        }
+        if let (Some(dst), Some(dst_index)) =
+            (self.bytes.get_mut(self.len), self.indexes.get_mut(self.len))
+        {
+            *dst = byte;
+            *dst_index = index;
+            self.len += 1;
+        }
+    }
+
+    // Parse the number and the delimiter after it, `end_index` is the position after the number
+    fn parse(mut self, delimiter: Option<u8>, end_index: usize) -> Result<(), (ErrorType, usize)> {
+        let n_number = self.len;
+        if let Some(delimiter) = delimiter {
+            self.push(delimiter, end_index);
+        }
+        let index_of = |i: usize| self.indexes.get(..self.len).and_then(|v| v.get(i)).copied();
+        let mut jiter = Jiter::new(self.bytes.get(..self.len).unwrap_or_default());
+        match jiter.next_number() {
+            Err(e) => Err((
+                ErrorType::from_jiter_error_type(e.error_type),
+                index_of(e.index).unwrap_or(end_index),
+            )),
+            // The parser stops before an invalid byte, such as `-` in `1-`
+            Ok(_) if jiter.current_index() < n_number => Err((
+                ErrorType::JsonError(JsonErrorType::InvalidNumber),
+                index_of(jiter.current_index()).unwrap_or(end_index),
+            )),
+            Ok(_) => Ok(()),
+        }
+    }
+}
+
+// Writer for `write_long_str_chunks`. `write_long_str` passes each decoded
+// chunk to one `write_all` call, and `write` consumes the whole input,
+// therefore one `write` call gets exactly one complete chunk.
+struct ChunkWriter<'w, W: Write, F: FnMut(usize, &str)> {
+    writer: &'w mut W,
+    on_chunk: F,
+    offset: usize,
+}
+
+impl<W: Write, F: FnMut(usize, &str)> embedded_io::ErrorType for ChunkWriter<'_, W, F> {
+    type Error = W::Error;
+}
+
+impl<W: Write, F: FnMut(usize, &str)> Write for ChunkWriter<'_, W, F> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.writer.write_all(buf)?;
+        // The buffer is a decoded `str`, the conversion doesn't fail.
+        // The offset counts only the chunks given to the callback.
+        if let Ok(chunk) = core::str::from_utf8(buf) {
+            (self.on_chunk)(self.offset, chunk);
+            self.offset += buf.len();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush()
+    }
+}
+
+// Writer for `for_each_str_chunk`. As in `ChunkWriter`, one `write` call gets
+// one complete chunk. The error of the callback is kept for the caller.
+struct CallbackWriter<E, F: FnMut(&[u8]) -> Result<(), E>> {
+    on_chunk: F,
+    error: Option<E>,
+}
+
+impl<E, F: FnMut(&[u8]) -> Result<(), E>> embedded_io::ErrorType for CallbackWriter<E, F> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl<E, F: FnMut(&[u8]) -> Result<(), E>> Write for CallbackWriter<E, F> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if let Err(e) = (self.on_chunk)(buf) {
+            self.error = Some(e);
+            return Err(embedded_io::ErrorKind::Other);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Writer for `write_into`, fails if the slice is full
+struct SliceWriter<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+    is_full: bool,
+}
+
+impl embedded_io::ErrorType for SliceWriter<'_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl Write for SliceWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let end = self.len + buf.len();
+        let Some(dst) = self.buf.get_mut(self.len..end) else {
+            self.is_full = true;
+            return Err(embedded_io::ErrorKind::OutOfMemory);
+        };
+        dst.copy_from_slice(buf);
+        self.len = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Writer for `skip_long_str`, drops the bytes
+struct SkipWriter;
+
+impl embedded_io::ErrorType for SkipWriter {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl Write for SkipWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Writer for `write_long_str_fanout`, repeats each write to all the writers
+struct FanoutWriter<'f, 'w, E: embedded_io::Error> {
+    writers: &'f mut [&'w mut dyn Write<Error = E>],
+}
+
+impl<E: embedded_io::Error> embedded_io::ErrorType for FanoutWriter<'_, '_, E> {
+    type Error = E;
+}
+
+impl<E: embedded_io::Error> Write for FanoutWriter<'_, '_, E> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for writer in self.writers.iter_mut() {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        for writer in self.writers.iter_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
 }
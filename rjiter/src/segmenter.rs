@@ -0,0 +1,140 @@
+/// Splits a long json string into segments which can be decoded separately.
+///
+/// The input is the part of the string in the buffer. It starts with one byte
+/// before the content, the opening quote or the tail of the previous segment,
+/// and the segment is `bytes[1..end]`. A segment is either plain text or exactly
+/// one escape sequence. The boundaries:
+///
+/// - A plain segment stops before a backslash.
+/// - An escape is not split. A high surrogate `\uD8xx`-`\uDBxx` is kept
+///   together with the following low surrogate.
+/// - A UTF-8 code point is not split.
+/// - The last byte of the input is not in a plain segment: the caller
+///   needs a byte after the segment to put the closing quote there.
+///
+/// ```
+/// use rjiter::Segmenter;
+///
+/// let segmenter = Segmenter::new(br#""hello\nworld"#);
+/// assert_eq!(segmenter.segment_end(usize::MAX), 6); // "hello"
+///
+/// let segmenter = Segmenter::new(br#""\nworld"#);
+/// assert!(segmenter.is_escape());
+/// assert_eq!(segmenter.segment_end(usize::MAX), 3); // "\n"
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Segmenter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Segmenter<'a> {
+    /// Create a segmenter for the bytes of a string in the buffer, see `Segmenter`.
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Segmenter { bytes }
+    }
+
+    /// Whether the next segment is an escape sequence.
+    #[must_use]
+    pub fn is_escape(&self) -> bool {
+        self.bytes.get(1) == Some(&b'\\')
+    }
+
+    /// Find the end of the next segment, the segment is `bytes[1..end]`.
+    ///
+    /// The result is not more than `max_end` and doesn't break a unicode code point.
+    /// A result `<= 1` means that the input doesn't have a complete segment:
+    /// the caller should read more bytes.
+    ///
+    /// The input is not validated. If the text is not valid UTF-8, the end is
+    /// still a position in the input, and the decoding reports the error.
+    #[must_use]
+    pub fn segment_end(&self, max_end: usize) -> usize {
+        let bytes = self.bytes;
+        let bs_pos = bytes.iter().position(|&b| b == b'\\');
+        let segment_end_pos = match bs_pos {
+            // No backslash: the segment is the whole input
+            // `-1`: To write a segment, the writer needs an extra byte to put the quote character
+            None => bytes.len().saturating_sub(1),
+            // Backslash is somewhere in the input
+            // The segment is the part of the input before the backslash
+            Some(bs_pos) if bs_pos > 1 => bs_pos,
+            // Backslash is the first byte of the content
+            // The segment is the escape sequence
+            Some(bs_pos) => Self::escape_end(bytes, bs_pos),
+        };
+
+        let segment_end_pos = segment_end_pos.min(max_end);
+        // An escape sequence is ASCII, the end of it is a boundary
+        if self.is_escape() {
+            return segment_end_pos;
+        }
+
+        // Correct the segment end position to not break a unicode code point.
+        // The end of the input is a boundary.
+        // A code point has at most 3 continuation bytes. If there are more,
+        // the text is not valid UTF-8: keep the end, the decoding reports the error.
+        (segment_end_pos.saturating_sub(3)..=segment_end_pos)
+            .rev()
+            .find(|&pos| bytes.get(pos).is_none_or(|&b| is_utf8_leading_byte(b)))
+            .unwrap_or(segment_end_pos)
+    }
+
+    /// Shorten a plain segment `bytes[1..end]` to at most `max_len` bytes.
+    ///
+    /// The result doesn't break a unicode code point, therefore it can be
+    /// shorter than `max_len`. But the segment keeps at least one code point,
+    /// even if the code point is longer than `max_len`. An escape segment is
+    /// not shortened, the decoded escape is shorter than the escape sequence.
+    #[must_use]
+    pub fn fit_end(&self, end: usize, max_len: usize) -> usize {
+        if end <= max_len + 1 || self.is_escape() {
+            return end;
+        }
+        (2..=max_len + 1)
+            .rev()
+            .chain(max_len + 2..end)
+            .find(|&pos| {
+                self.bytes
+                    .get(pos)
+                    .is_some_and(|&b| is_utf8_leading_byte(b))
+            })
+            .unwrap_or(end)
+    }
+
+    // The end of the escape sequence which starts at `bs_pos`,
+    // or `bs_pos` if the sequence is not complete in the input
+    fn escape_end(bytes: &[u8], bs_pos: usize) -> usize {
+        // [QUOTE, SLASH, CHAR, ....]
+        let Some(&after_bs) = bytes.get(2) else {
+            return bs_pos;
+        };
+        if after_bs != b'u' && after_bs != b'U' {
+            return bs_pos + 2;
+        }
+        // [QUOTE, SLASH, u, HEXDEC, HEXDEC, HEXDEC, HEXDEC, ....]
+        if bytes.len() < 7 {
+            return bs_pos;
+        }
+        // A high surrogate is followed by a low surrogate,
+        // and the pair should be decoded together:
+        // [QUOTE, SLASH, u, D, 8-B, HEXDEC, HEXDEC, SLASH, u, ....]
+        let is_high_surrogate = matches!(bytes.get(3), Some(b'd' | b'D'))
+            && matches!(bytes.get(4), Some(b'8' | b'9' | b'a' | b'A' | b'b' | b'B'));
+        if !is_high_surrogate {
+            bs_pos + 6
+        } else if bytes.len() < 13 {
+            bs_pos
+        } else {
+            bs_pos + 12
+        }
+    }
+}
+
+fn is_utf8_leading_byte(b: u8) -> bool {
+    // Linters suggests to use `!(0b1000_0000..0b1100_0000).contains(&b)`,
+    // but for me the suggestion looks much less readable
+    #[allow(clippy::manual_range_contains)]
+    let flag = (b < 0b1000_0000) || (b >= 0b1100_0000);
+    flag
+}
@@ -0,0 +1,108 @@
+use embedded_io::{ErrorKind, ErrorType, Read};
+
+use crate::error::{Error as RJiterError, ErrorType as RJiterErrorType};
+use crate::RJiter;
+
+/// Reader for the decoded bytes of a json string, see `RJiter::string_reader`.
+///
+/// The reader pulls the input from `RJiter` on demand, therefore
+/// the string can be longer than the `RJiter` buffer.
+///
+/// The error of `read` is an `ErrorKind`: the kind of an I/O error,
+/// or `InvalidData` otherwise. The detailed error is kept, see `error()`.
+pub struct StringReader<'r, 'rj, R: Read> {
+    rjiter: &'r mut RJiter<'rj, R>,
+    // Tail of a code point or an escape which didn't fit into the caller's buffer
+    overflow: [u8; 4],
+    overflow_pos: usize,
+    overflow_len: usize,
+    is_finished: bool,
+    error: Option<RJiterError>,
+    // Index of the opening quote, to check the token length
+    start_index: usize,
+}
+
+impl<'r, 'rj, R: Read> StringReader<'r, 'rj, R> {
+    pub(crate) fn new(rjiter: &'r mut RJiter<'rj, R>) -> Self {
+        let start_index = rjiter.current_index();
+        StringReader {
+            rjiter,
+            overflow: [0; 4],
+            overflow_pos: 0,
+            overflow_len: 0,
+            is_finished: false,
+            error: None,
+            start_index,
+        }
+    }
+
+    /// Whether the closing quote of the string is consumed.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.is_finished && self.overflow_pos == self.overflow_len
+    }
+
+    /// The `RJiter` error of the last failed `read`.
+    #[must_use]
+    pub fn error(&self) -> Option<&RJiterError> {
+        self.error.as_ref()
+    }
+}
+
+impl<R: Read> core::fmt::Debug for StringReader<'_, '_, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "StringReader {{ rjiter: {:?}, is_finished: {} }}",
+            self.rjiter, self.is_finished
+        )
+    }
+}
+
+impl<R: Read> ErrorType for StringReader<'_, '_, R> {
+    type Error = ErrorKind;
+}
+
+impl<R: Read> Read for StringReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.overflow_pos < self.overflow_len {
+            let pending = self
+                .overflow
+                .get(self.overflow_pos..self.overflow_len)
+                .unwrap_or(&[]);
+            let n = pending.len().min(buf.len());
+            if let (Some(dst), Some(src)) = (buf.get_mut(..n), pending.get(..n)) {
+                dst.copy_from_slice(src);
+            }
+            self.overflow_pos += n;
+            return Ok(n);
+        }
+        if self.is_finished {
+            return Ok(0);
+        }
+
+        // A chunk can be empty: the string is completed or an escape is decoded to nothing
+        loop {
+            let (n_buf, n_overflow, is_finished) = self
+                .rjiter
+                .read_long_str_chunk(buf, &mut self.overflow, self.start_index)
+                .map_err(|e| {
+                    let kind = match e.error_type {
+                        RJiterErrorType::IoError { kind } => kind,
+                        _ => ErrorKind::InvalidData,
+                    };
+                    self.error = Some(e);
+                    kind
+                })?;
+            self.overflow_pos = 0;
+            self.overflow_len = n_overflow;
+            self.is_finished = is_finished;
+            if n_buf > 0 || is_finished {
+                return Ok(n_buf);
+            }
+        }
+    }
+}
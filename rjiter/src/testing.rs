@@ -0,0 +1,159 @@
+use embedded_io::{ErrorKind, ErrorType, Read};
+
+/// Reader which returns one byte per `read`, the worst case for the buffer logic.
+pub struct OneByteReader<I>
+where
+    I: Iterator<Item = u8>,
+{
+    iter: I,
+}
+
+impl<I> OneByteReader<I>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Creates a reader for the bytes of `iter`.
+    pub fn new(iter: I) -> Self {
+        OneByteReader { iter }
+    }
+}
+
+impl<I> ErrorType for OneByteReader<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = ErrorKind;
+}
+
+impl<I> Read for OneByteReader<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let Some(first) = buf.first_mut() else {
+            return Ok(0);
+        };
+        if let Some(next_byte) = self.iter.next() {
+            *first = next_byte;
+            Ok(1)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// How `ChunkReader` splits the data into the results of `read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    /// Chunks of the given size. The last chunk can be shorter.
+    Fixed(usize),
+    /// Chunks of a pseudo-random size from 1 to `max_len`.
+    /// The same seed gives the same chunks.
+    Random {
+        /// Seed of the pseudo-random generator
+        seed: u64,
+        /// Maximal size of a chunk
+        max_len: usize,
+    },
+    /// Chunks end at the delimiter byte. The delimiter only marks
+    /// the chunk boundaries in the test data and is not returned.
+    AtDelimiter(u8),
+}
+
+/// Reader which returns the data in chunks, to test the parsing at buffer boundaries.
+///
+/// A chunk is also limited by the buffer of `read`. Then the rest of the chunk
+/// is returned by the next `read`.
+pub struct ChunkReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    chunking: Chunking,
+    // State of the pseudo-random generator for `Chunking::Random`
+    rng_state: u64,
+    // Bytes left in the current chunk
+    chunk_left: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    /// Creates a reader with chunks ending at the `interrupt` byte,
+    /// see `Chunking::AtDelimiter`.
+    #[must_use]
+    pub fn new(data: &'a [u8], interrupt: u8) -> Self {
+        Self::with_chunking(data, Chunking::AtDelimiter(interrupt))
+    }
+
+    /// Creates a reader with the given chunking strategy.
+    #[must_use]
+    pub fn with_chunking(data: &'a [u8], chunking: Chunking) -> Self {
+        let rng_state = match chunking {
+            // Xorshift doesn't work with the zero state
+            Chunking::Random { seed, .. } => seed | 1,
+            _ => 0,
+        };
+        ChunkReader {
+            data,
+            position: 0,
+            chunking,
+            rng_state,
+            chunk_left: 0,
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        x
+    }
+
+    fn next_chunk_len(&mut self, remaining: &[u8]) -> usize {
+        match self.chunking {
+            Chunking::Fixed(len) => len.max(1),
+            Chunking::Random { max_len, .. } => {
+                let max_len = u64::try_from(max_len.max(1)).unwrap_or(u64::MAX);
+                usize::try_from(self.next_random() % max_len + 1).unwrap_or(1)
+            }
+            Chunking::AtDelimiter(delimiter) => remaining
+                .iter()
+                .position(|&b| b == delimiter)
+                .unwrap_or(remaining.len()),
+        }
+    }
+}
+
+impl ErrorType for ChunkReader<'_> {
+    type Error = ErrorKind;
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining_data = self.data.get(self.position..).unwrap_or(&[]);
+        if self.chunk_left == 0 {
+            self.chunk_left = self.next_chunk_len(remaining_data);
+        }
+
+        let bytes_to_write = self.chunk_left.min(buf.len()).min(remaining_data.len());
+        if let (Some(dst), Some(src)) = (
+            buf.get_mut(..bytes_to_write),
+            remaining_data.get(..bytes_to_write),
+        ) {
+            dst.copy_from_slice(src);
+        }
+        self.position += bytes_to_write;
+        self.chunk_left -= bytes_to_write;
+
+        // Skip the delimiter at the end of the chunk
+        if let Chunking::AtDelimiter(delimiter) = self.chunking {
+            if self.chunk_left == 0 && self.data.get(self.position) == Some(&delimiter) {
+                self.position += 1;
+            }
+        }
+
+        Ok(bytes_to_write)
+    }
+}
@@ -0,0 +1,44 @@
+/// An instrumentation point of `RJiter`, passed to the hook of `RJiter::with_trace_hook`.
+///
+/// The events mark the work which is not parsing: reading, copying inside
+/// the buffer and restarting the parser. A hook which reads a cycle counter
+/// or a timer can attribute the time between the events: from `ReadStart`
+/// to `ReadEnd` is IO, the rest is mostly parsing and copying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// Before a read from the reader
+    ReadStart {
+        /// The free space in the buffer
+        capacity: usize,
+    },
+    /// After a read from the reader, also after a failed one
+    ReadEnd {
+        /// The number of bytes read, 0 at the end of input or on error
+        n_bytes: usize,
+        /// The error of the reader, `None` on success
+        error: Option<embedded_io::ErrorKind>,
+    },
+    /// The buffer is shifted to the left
+    Shift {
+        /// The number of bytes dropped from the buffer
+        n_shifted_out: usize,
+        /// The number of bytes moved inside the buffer
+        n_moved: usize,
+    },
+    /// The parser is created again for the new buffer content
+    NewJiter {
+        /// The number of bytes in the buffer
+        n_bytes: usize,
+    },
+    /// A segment of a long string is written or returned
+    LongSegment {
+        /// The index of the segment in the input stream
+        index: usize,
+        /// The number of raw bytes in the segment
+        len: usize,
+    },
+}
+
+/// The hook for the events, see `RJiter::with_trace_hook`
+pub type TraceHook = fn(Event);
@@ -1,8 +1,6 @@
 use rjiter::buffer::Buffer;
 use rjiter::jiter::LinePosition;
-
-mod one_byte_reader;
-use one_byte_reader::OneByteReader;
+use rjiter::testing::OneByteReader;
 
 #[test]
 fn test_read_until_full() {
@@ -18,6 +16,98 @@ fn test_read_until_full() {
     assert_eq!(n_bytes, 0);
 }
 
+#[test]
+fn test_eof_seen_only_with_free_space() {
+    let input = "abcdef";
+    let mut reader = input.as_bytes();
+    let mut buf = [0u8; 4];
+    let mut buffer = Buffer::new(&mut reader, &mut buf);
+
+    buffer.read_more().unwrap();
+    // The buffer is full, reading 0 bytes is not the end of input
+    assert_eq!(buffer.read_more().unwrap(), 0);
+    assert!(!buffer.is_eof_seen);
+
+    buffer.shift_buffer(0, 4);
+    assert_eq!(buffer.read_more().unwrap(), 2);
+    assert!(!buffer.is_eof_seen);
+    assert_eq!(buffer.read_more().unwrap(), 0);
+    assert!(buffer.is_eof_seen);
+}
+
+#[test]
+fn test_read_coalescing() {
+    let input = "0123456789abc";
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buf = [0u8; 12];
+    let mut buffer = Buffer::new(&mut reader, &mut buf);
+    buffer.min_fill = 5;
+
+    assert_eq!(buffer.read_more().unwrap(), 5);
+    assert_eq!(buffer.read_more().unwrap(), 5);
+    // Limited by the buffer size
+    assert_eq!(buffer.read_more().unwrap(), 2);
+    assert_eq!(&buffer.buf[..buffer.n_bytes], b"0123456789ab");
+
+    // Limited by the end of input
+    buffer.shift_buffer(0, 12);
+    assert_eq!(buffer.read_more().unwrap(), 1);
+    assert!(buffer.is_eof_seen);
+}
+
+/// Returns the chunks one by one, and a timeout instead of an empty chunk
+struct TimeoutReader<'a> {
+    chunks: &'a [&'a [u8]],
+}
+
+impl embedded_io::ErrorType for TimeoutReader<'_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl embedded_io::Read for TimeoutReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let Some((chunk, rest)) = self.chunks.split_first() else {
+            return Ok(0);
+        };
+        self.chunks = rest;
+        if chunk.is_empty() {
+            return Err(embedded_io::ErrorKind::TimedOut);
+        }
+        buf[..chunk.len()].copy_from_slice(chunk);
+        Ok(chunk.len())
+    }
+}
+
+#[test]
+fn test_read_coalescing_stops_on_error() {
+    let mut reader = TimeoutReader {
+        chunks: &[b"ab", b"c", b"", b"", b"de"],
+    };
+    let mut buf = [0u8; 16];
+    let mut buffer = Buffer::new(&mut reader, &mut buf);
+    buffer.min_fill = 8;
+
+    // The error after the data is reported, the data stays in the buffer
+    let err = buffer.read_more().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::IoError {
+            kind: embedded_io::ErrorKind::TimedOut
+        }
+    );
+    assert_eq!(&buffer.buf[..buffer.n_bytes], b"abc");
+    // The first read fails
+    let err = buffer.read_more().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::IoError {
+            kind: embedded_io::ErrorKind::TimedOut
+        }
+    );
+    assert_eq!(buffer.read_more().unwrap(), 2);
+    assert_eq!(&buffer.buf[..buffer.n_bytes], b"abcde");
+}
+
 #[test]
 fn test_basic_skip_spaces() {
     let spaces = " ".repeat(4);
@@ -324,6 +414,27 @@ fn test_shift_position_multiple_reads() {
     assert_eq!(buffer.pos_shifted, LinePosition::new(3, 3));
 }
 
+#[test]
+fn test_shift_position_with_kept_prefix() {
+    // Like skipping spaces after a comma: the comma stays, the newlines are removed
+    let input = ",\n\n   3 4";
+    let mut reader = input.as_bytes();
+    let mut buf = [0u8; 32];
+    let mut buffer = Buffer::new(&mut reader, &mut buf);
+    buffer.read_more().unwrap();
+
+    buffer.shift_buffer(1, input.find('3').unwrap());
+    assert_eq!(buffer.pos_shifted, LinePosition::new(2, 3));
+    assert_eq!(buffer.n_pos_counted, 1);
+
+    buffer.shift_buffer(0, 2);
+    assert_eq!(buffer.pos_shifted, LinePosition::new(2, 4));
+    assert_eq!(buffer.n_pos_counted, 0);
+
+    let pos_4 = input.find('4').unwrap();
+    assert_eq!(buffer.position_of(pos_4), LinePosition::new(3, 6));
+}
+
 #[test]
 fn test_collect_while_basic() {
     let input = "abc123def";
@@ -952,3 +1063,18 @@ fn test_skip_n_very_small_buffer_many_bytes() {
     assert_eq!(buffer.n_shifted_out, 27); // 27 bytes shifted
     assert_eq!(&buffer.buf[..buffer.n_bytes], b"123"); // Bytes 27-29 in buffer
 }
+
+#[test]
+fn test_skip_prefix() {
+    let input = "xx)]}'abc";
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buf = [0u8; 16];
+    let mut buffer = Buffer::new(&mut reader, &mut buf);
+
+    assert!(!buffer.skip_prefix(2, b"abc").unwrap());
+    assert!(buffer.skip_prefix(2, b")]}'").unwrap());
+
+    buffer.collect_count(3, 2, false).unwrap();
+    assert_eq!(&buffer.buf[..buffer.n_bytes], b"xxabc");
+    assert_eq!(buffer.n_shifted_out, 4);
+}
@@ -0,0 +1,102 @@
+use rjiter::error::ErrorType;
+use rjiter::jiter::{NumberInt, Peek};
+use rjiter::testing::OneByteReader;
+use rjiter::{Limit, Limits, RJiter, WhitespacePolicy};
+
+#[test]
+fn builder_sets_options() {
+    let mut reader = "[\"abcdef\"]".as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+        .limits(Limits {
+            max_string_bytes: Some(4),
+            ..Limits::default()
+        })
+        .build();
+
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::LimitExceeded {
+            limit: Limit::StringBytes,
+            max: 4
+        }
+    );
+
+    let mut reader = " \x0c 1".as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+        .whitespace_policy(WhitespacePolicy {
+            strict: true,
+            ..WhitespacePolicy::default()
+        })
+        .build();
+
+    assert!(rjiter.next_int().is_err());
+
+    let mut reader = "[1, /* two */ 2,]".as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+        .tolerant_mode()
+        .max_token_len(1)
+        .build();
+
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::new(b'2')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+    assert_eq!(rjiter.array_step().unwrap(), None);
+}
+
+#[test]
+fn builder_without_options_is_new() {
+    let input = r#"{"a": [1, "b"]}"#;
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::builder(&mut reader, &mut buffer).build();
+
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "b");
+    assert_eq!(rjiter.array_step().unwrap(), None);
+    assert_eq!(rjiter.next_key().unwrap(), None);
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn skip_tokens_of_sse_stream() {
+    let input = "data: {\"n\": 1}\n\ndata: {\"n\": 2}\n\ndata: [DONE]\n\n";
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 8];
+    let tokens: &[&[u8]] = &[b"data:", b"[DONE]"];
+    let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+        .skip_tokens(tokens)
+        .build();
+
+    let mut values = Vec::new();
+    loop {
+        rjiter.skip_tokens().unwrap();
+        if rjiter.finish().is_ok() {
+            break;
+        }
+        assert_eq!(rjiter.next_object().unwrap(), Some("n"));
+        values.push(rjiter.next_int().unwrap());
+        assert_eq!(rjiter.next_key().unwrap(), None);
+    }
+    assert_eq!(values, [NumberInt::Int(1), NumberInt::Int(2)]);
+}
+
+#[test]
+fn skip_tokens_counts_the_tokens() {
+    let mut reader = "  data: data:[1]".as_bytes();
+    let mut buffer = [0u8; 16];
+    let tokens: &[&[u8]] = &[b"data:"];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_skip_tokens(tokens);
+
+    assert_eq!(rjiter.skip_tokens().unwrap(), 2);
+    assert_eq!(rjiter.skip_tokens().unwrap(), 0);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+}
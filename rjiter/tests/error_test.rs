@@ -74,3 +74,113 @@ fn display_of_error() {
         _ => panic!("Expected JiterError"),
     }
 }
+
+#[test]
+fn position_is_kept_after_buffer_shift() {
+    let leading_text = "\n \n  \n   \n    \n      \n   ";
+    let trailing_text = "\n".repeat(40);
+    let input = format!(r#"{leading_text}"hello"{trailing_text}"world""#);
+    let mut buffer = [0u8; 10];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let err = rjiter.next_bool().unwrap_err();
+    assert_eq!(err.position(), Some(&LinePosition::new(7, 4)));
+
+    // Move the parser forward, so that the error location is shifted out
+    assert_eq!(rjiter.next_str().unwrap(), "hello");
+    assert_eq!(rjiter.next_str().unwrap(), "world");
+
+    assert_eq!(err.position(), Some(&LinePosition::new(7, 4)));
+    assert_eq!(err.get_position(&rjiter), LinePosition::new(7, 4));
+}
+
+#[test]
+fn error_categories_and_retry() {
+    use rjiter::error::{ErrorCategory, ErrorType};
+
+    let category = |input: &str| {
+        let mut reader = input.as_bytes();
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(4);
+        let err = rjiter
+            .next_array()
+            .and_then(|_| rjiter.next_str())
+            .unwrap_err();
+        (err.category(), err.is_retryable())
+    };
+    assert_eq!(category(r#"["abc"#), (ErrorCategory::Eof, false));
+    assert_eq!(category("[true]"), (ErrorCategory::Syntax, false));
+    assert_eq!(category("[tru]"), (ErrorCategory::Syntax, false));
+    assert_eq!(category(r#"["hello"]"#), (ErrorCategory::Capacity, false));
+
+    let mut buffer = [0u8; 16];
+    let mut no_reader: &[u8] = &[];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer).with_feed_mode();
+    rjiter.feed(b"[tr");
+    let err = rjiter.next_value().unwrap_err();
+    assert_eq!(err.error_type, ErrorType::NeedMoreData);
+    assert_eq!(err.category(), ErrorCategory::Eof);
+    assert!(err.is_retryable());
+    // A retryable error is located on demand
+    assert_eq!(err.position(), None);
+    assert_eq!(err.get_position(&rjiter), rjiter.error_position(err.index));
+
+    let io = |kind| ErrorType::IoError { kind };
+    assert_eq!(
+        io(embedded_io::ErrorKind::Interrupted).category(),
+        ErrorCategory::Io
+    );
+    assert!(io(embedded_io::ErrorKind::Interrupted).is_retryable());
+    assert!(io(embedded_io::ErrorKind::TimedOut).is_retryable());
+    assert!(!io(embedded_io::ErrorKind::BrokenPipe).is_retryable());
+    assert_eq!(
+        ErrorType::OutputFull { written: 3 }.category(),
+        ErrorCategory::Capacity
+    );
+}
+
+// Fails the first read with `kind`, then reads `data`
+struct FailOnceReader<'a> {
+    kind: Option<embedded_io::ErrorKind>,
+    data: &'a [u8],
+}
+
+impl embedded_io::ErrorType for FailOnceReader<'_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl embedded_io::Read for FailOnceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if let Some(kind) = self.kind.take() {
+            return Err(kind);
+        }
+        embedded_io::Read::read(&mut self.data, buf).map_err(|_| embedded_io::ErrorKind::Other)
+    }
+}
+
+#[test]
+fn retry_after_transient_read_errors() {
+    use rjiter::error::{ErrorCategory, ErrorType};
+
+    for (kind, is_retryable) in [
+        (embedded_io::ErrorKind::Interrupted, true),
+        (embedded_io::ErrorKind::TimedOut, true),
+        (embedded_io::ErrorKind::BrokenPipe, false),
+    ] {
+        let mut reader = FailOnceReader {
+            kind: Some(kind),
+            data: br#"{"a": 1}"#,
+        };
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let err = rjiter.next_object().unwrap_err();
+        assert_eq!(err.error_type, ErrorType::IoError { kind });
+        assert_eq!(err.category(), ErrorCategory::Io);
+        assert_eq!(err.is_retryable(), is_retryable, "{kind:?}");
+
+        // Nothing is lost, the same call succeeds
+        assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+        assert_eq!(rjiter.next_int().unwrap(), rjiter::jiter::NumberInt::Int(1));
+    }
+}
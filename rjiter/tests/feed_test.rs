@@ -0,0 +1,128 @@
+use rjiter::error::ErrorType;
+use rjiter::jiter::{NumberInt, Peek};
+use rjiter::RJiter;
+
+// Call the parsing function, feed the next byte while it needs more data
+macro_rules! parse_fed {
+    ($rjiter:ident, $input:ident, $call:expr) => {
+        loop {
+            match $call {
+                Err(e) if e.error_type == ErrorType::NeedMoreData => {
+                    let (head, rest) = $input.split_at(1.min($input.len()));
+                    if head.is_empty() {
+                        $rjiter.set_eof_known(true);
+                    }
+                    assert_eq!($rjiter.feed(head), head.len());
+                    $input = rest;
+                }
+                result => break result,
+            }
+        }
+    };
+}
+
+#[test]
+fn feed_one_byte_at_a_time() {
+    let mut input: &[u8] = br#"{"name": "Ann\n", "n": 123, "tags": [true, null, 4.5]} "#;
+    let input_len = input.len();
+    let mut no_reader: &[u8] = &[];
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer).with_feed_mode();
+
+    let key = parse_fed!(rjiter, input, rjiter.next_object()).unwrap();
+    assert_eq!(key, Some("name"));
+    let name = parse_fed!(rjiter, input, rjiter.next_str()).unwrap();
+    assert_eq!(name, "Ann\n");
+    let key = parse_fed!(rjiter, input, rjiter.next_key()).unwrap();
+    assert_eq!(key, Some("n"));
+    let n = parse_fed!(rjiter, input, rjiter.next_int()).unwrap();
+    assert_eq!(n, NumberInt::Int(123));
+    let key = parse_fed!(rjiter, input, rjiter.next_key()).unwrap();
+    assert_eq!(key, Some("tags"));
+
+    let peek = parse_fed!(rjiter, input, rjiter.next_array()).unwrap();
+    assert_eq!(peek, Some(Peek::True));
+    assert!(parse_fed!(rjiter, input, rjiter.next_bool()).unwrap());
+    let peek = parse_fed!(rjiter, input, rjiter.array_step()).unwrap();
+    assert_eq!(peek, Some(Peek::Null));
+    parse_fed!(rjiter, input, rjiter.next_null()).unwrap();
+    parse_fed!(rjiter, input, rjiter.array_step()).unwrap();
+    let x = parse_fed!(rjiter, input, rjiter.next_float()).unwrap();
+    assert!((x - 4.5).abs() < f64::EPSILON);
+    assert_eq!(
+        parse_fed!(rjiter, input, rjiter.array_step()).unwrap(),
+        None
+    );
+    assert_eq!(parse_fed!(rjiter, input, rjiter.next_key()).unwrap(), None);
+    parse_fed!(rjiter, input, rjiter.finish()).unwrap();
+    assert_eq!(rjiter.current_index(), input_len);
+}
+
+#[test]
+fn number_at_end_waits_for_end_of_input() {
+    let mut no_reader: &[u8] = &[];
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer).with_feed_mode();
+
+    assert_eq!(rjiter.feed(b" 12"), 3);
+    let err = rjiter.next_int().unwrap_err();
+    assert_eq!(err.error_type, ErrorType::NeedMoreData);
+    assert_eq!(rjiter.feed(b"3"), 1);
+    let err = rjiter.next_int().unwrap_err();
+    assert_eq!(err.error_type, ErrorType::NeedMoreData);
+
+    rjiter.set_eof_known(true);
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(123));
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn feed_takes_what_fits_into_buffer() {
+    let mut no_reader: &[u8] = &[];
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer).with_feed_mode();
+
+    let input = br#"["ab", "cd", "ef"]"#;
+    let n = rjiter.feed(input);
+    assert_eq!(n, 8);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "ab");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+
+    // The consumed bytes are dropped to make space
+    let n = n + rjiter.feed(&input[n..]);
+    assert_eq!(n, 15);
+    assert_eq!(rjiter.next_str().unwrap(), "cd");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(err.error_type, ErrorType::NeedMoreData);
+
+    assert_eq!(rjiter.feed(&input[n..]), input.len() - n);
+    assert_eq!(rjiter.next_str().unwrap(), "ef");
+    assert_eq!(rjiter.array_step().unwrap(), None);
+}
+
+#[test]
+fn feed_comments_in_tolerant_mode() {
+    let mut input: &[u8] = b"[1, /* c */ 2, // end\n] /";
+    let mut no_reader: &[u8] = &[];
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer)
+        .with_feed_mode()
+        .with_tolerant_mode();
+
+    let peek = parse_fed!(rjiter, input, rjiter.next_array()).unwrap();
+    assert_eq!(peek, Some(Peek::new(b'1')));
+    let n = parse_fed!(rjiter, input, rjiter.next_int()).unwrap();
+    assert_eq!(n, NumberInt::Int(1));
+    let peek = parse_fed!(rjiter, input, rjiter.array_step()).unwrap();
+    assert_eq!(peek, Some(Peek::new(b'2')));
+    let n = parse_fed!(rjiter, input, rjiter.next_int()).unwrap();
+    assert_eq!(n, NumberInt::Int(2));
+    assert_eq!(
+        parse_fed!(rjiter, input, rjiter.array_step()).unwrap(),
+        None
+    );
+    // The slash at the end is not a comment
+    assert!(parse_fed!(rjiter, input, rjiter.finish()).is_err());
+}
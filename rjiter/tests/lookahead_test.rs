@@ -1,10 +1,8 @@
-use rjiter::jiter::Peek;
-use rjiter::RJiter;
+#![allow(clippy::bool_assert_comparison)]
 
-mod one_byte_reader;
-use crate::one_byte_reader::OneByteReader;
-mod chunk_reader;
-use crate::chunk_reader::ChunkReader;
+use rjiter::jiter::Peek;
+use rjiter::testing::{ChunkReader, OneByteReader};
+use rjiter::{RJiter, TokenMatch};
 
 //
 // known_skip_token tests
@@ -43,6 +41,70 @@ fn known_skip_token() {
     }
 }
 
+#[test]
+fn known_skip_token_case_insensitive() {
+    let input = "Data: DATA:data: [1]";
+    for buffer_len in 6..input.len() {
+        let mut buffer = vec![0u8; buffer_len];
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+        let _ = rjiter.peek();
+        assert!(rjiter.known_skip_token(b"data:").is_err());
+        for _ in 0..3 {
+            let _ = rjiter.peek();
+            rjiter
+                .known_skip_token_with(b"data:", TokenMatch::CaseInsensitive)
+                .unwrap();
+        }
+        assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    }
+}
+
+#[test]
+fn known_skip_token_prefix() {
+    // The value right after the prefix is not skipped
+    for input in [r#")]}'{"a": 1}"#, r#"data:{"a": 1}"#] {
+        let token: &[u8] = if input.starts_with("data") {
+            b"data:"
+        } else {
+            b")]}'"
+        };
+        for buffer_len in 6..input.len() {
+            let mut buffer = vec![0u8; buffer_len];
+            let mut reader = ChunkReader::new(input.as_bytes(), 3);
+            let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+            rjiter
+                .known_skip_token_with(token, TokenMatch::Prefix)
+                .unwrap();
+            assert_eq!(rjiter.current_index(), token.len());
+            assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+        }
+    }
+
+    // A variable suffix is skipped by the caller
+    let input = ")]}'-generated-at-12345 {\"a\": 1}";
+    let mut buffer = [0u8; 32];
+    let mut reader = ChunkReader::new(input.as_bytes(), 3);
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    rjiter
+        .known_skip_token_with(b")]}'", TokenMatch::Prefix)
+        .unwrap();
+    let suffix_len = rjiter.lookahead_while(|b| b != b' ').unwrap().len();
+    rjiter.skip_n_bytes(suffix_len).unwrap();
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+
+    // No match is an error and doesn't consume the input
+    let mut buffer = [0u8; 16];
+    let mut reader = "data: 1".as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert!(rjiter
+        .known_skip_token_with(b"date", TokenMatch::Prefix)
+        .is_err());
+    assert_eq!(rjiter.current_index(), 0);
+}
+
 //
 // lookahead_while tests
 //
@@ -575,3 +637,192 @@ fn test_skip_n_bytes_small_buffer() {
     assert!(peek_result.is_ok());
     assert_eq!(peek_result.unwrap(), Peek::new(b'u'));
 }
+
+//
+// peek_number_kind tests
+//
+
+#[test]
+fn peek_number_kind() {
+    use rjiter::jiter::NumberInt;
+    use rjiter::NumberKind;
+
+    let input = r#"[ 42, -7, 999999999999999999, -999999999999999999, 1000000000000000000,
+        1.5, -2e10, 3E-2, 0, 12345678901234567890123]"#;
+    // The numbers are split between the reads
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let expected = [
+        NumberKind::Int,
+        NumberKind::Int,
+        NumberKind::Int,
+        NumberKind::Int,
+        NumberKind::BigInt,
+        NumberKind::Float,
+        NumberKind::Float,
+        NumberKind::Float,
+        NumberKind::Int,
+        NumberKind::BigInt,
+    ];
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'4')));
+    for (i, kind) in expected.iter().enumerate() {
+        if i > 0 {
+            rjiter.array_step().unwrap();
+        }
+        assert_eq!(rjiter.peek_number_kind().unwrap(), *kind, "number {i}");
+        // The number is not consumed
+        match kind {
+            NumberKind::Int => assert!(matches!(rjiter.next_int().unwrap(), NumberInt::Int(_))),
+            NumberKind::BigInt => {
+                assert!(matches!(rjiter.next_int().unwrap(), NumberInt::BigInt(_)))
+            }
+            NumberKind::Float => assert!(rjiter.next_float().is_ok()),
+            _ => unreachable!(),
+        }
+    }
+    assert_eq!(rjiter.array_step().unwrap(), None);
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn peek_number_kind_at_eof_and_errors() {
+    use rjiter::error::ErrorType;
+    use rjiter::jiter::JsonType;
+    use rjiter::NumberKind;
+
+    let input = "123";
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert_eq!(rjiter.peek_number_kind().unwrap(), NumberKind::Int);
+
+    let input = r#"  "123""#;
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.peek_number_kind().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::WrongType {
+            expected: JsonType::Int,
+            actual: JsonType::String
+        }
+    );
+    assert_eq!(err.index, 2);
+
+    let input = "1234567890123456789012345";
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.peek_number_kind().unwrap_err();
+    assert_eq!(err.error_type, ErrorType::BufferFull);
+}
+
+//
+// peek_raw_byte tests
+//
+
+#[test]
+fn peek_raw_byte_for_custom_framing() {
+    // JSON text sequences (RFC 7464): each value starts with the record separator
+    let input = "\x1e{\"a\": 1}\n          \x1e[true]   \n";
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let mut values = Vec::new();
+    while let Some(byte) = rjiter.peek_raw_byte(true).unwrap() {
+        assert_eq!(byte, 0x1e);
+        rjiter.skip_n_bytes(1).unwrap();
+        values.push(rjiter.next_value().unwrap());
+    }
+    assert_eq!(values.len(), 2);
+    assert_eq!(rjiter.peek_raw_byte(false).unwrap(), None);
+}
+
+#[test]
+fn peek_raw_byte_keeps_whitespace() {
+    let input = r#"[1,  "x"]"#;
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.peek_raw_byte(false).unwrap(), Some(b'['));
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    rjiter.next_int().unwrap();
+    assert_eq!(rjiter.peek_raw_byte(false).unwrap(), Some(b','));
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "x");
+    assert_eq!(rjiter.peek_raw_byte(false).unwrap(), Some(b']'));
+    assert_eq!(rjiter.current_index(), 8);
+}
+
+//
+// peek_value tests
+//
+
+#[test]
+fn peek_value() {
+    let input = r#"  {"a": [1, "x"]} 123 "s\"t" [] true"#;
+    let expected = [
+        (Peek::Object, r#"{"a": [1, "x"]}"#),
+        (Peek::new(b'1'), "123"),
+        (Peek::String, r#""s\"t""#),
+        (Peek::Array, "[]"),
+        (Peek::True, "true"),
+    ];
+    for buffer_len in 16..input.len() {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+        for (peek, value) in expected {
+            let peeked = rjiter.peek_value().unwrap();
+            assert_eq!(peeked.peek, peek, "buffer {buffer_len}");
+            assert_eq!(peeked.len, Some(value.len()), "buffer {buffer_len}");
+            // The value is not consumed
+            let start = rjiter.current_index();
+            rjiter.next_skip().unwrap();
+            assert_eq!(rjiter.current_index() - start, value.len());
+        }
+        rjiter.finish().unwrap();
+    }
+}
+
+#[test]
+fn peek_value_too_long_or_invalid() {
+    use rjiter::error::ErrorType;
+    use rjiter::jiter::JsonErrorType;
+
+    let input = r#"["a long string value", 1]"#;
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let peeked = rjiter.peek_value().unwrap();
+    assert_eq!(peeked.peek, Peek::Array);
+    assert_eq!(peeked.len, None);
+    // The value can still be parsed
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let mut writer = Vec::new();
+    rjiter.write_long_bytes(&mut writer).unwrap();
+    assert_eq!(writer, b"a long string value");
+
+    let mut reader = "[1, 2".as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.peek_value().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::JsonError(JsonErrorType::EofWhileParsingList)
+    );
+
+    let mut reader = " [1, x]".as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.peek_value().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::JsonError(JsonErrorType::ExpectedSomeValue)
+    );
+    assert_eq!(err.index, 5);
+}
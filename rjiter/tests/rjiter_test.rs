@@ -1,12 +1,11 @@
+#![allow(clippy::bool_assert_comparison, clippy::approx_constant)]
+
 use std::sync::Arc;
 
-use rjiter::jiter::{JsonValue, LazyIndexMap, NumberInt, Peek};
-use rjiter::RJiter;
+use rjiter::jiter::{JsonValue, LazyIndexMap, LinePosition, NumberInt, Peek};
+use rjiter::testing::{ChunkReader, Chunking, OneByteReader};
 use rjiter::Result as RJiterResult;
-mod one_byte_reader;
-use crate::one_byte_reader::OneByteReader;
-mod chunk_reader;
-use crate::chunk_reader::ChunkReader;
+use rjiter::{RJiter, WhitespacePolicy};
 
 #[test]
 fn sanity_check() {
@@ -607,426 +606,2242 @@ fn regression_long_writer_search_escape_in_nbytes_2() {
     assert_eq!(writer, "123456".as_bytes());
 }
 
-// ----------------------------------------------
-// Auto-generated from a template
-
 #[test]
-fn peek() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+fn write_long_str_with_escaped_surrogate_pair() {
+    let input = r#""AAAAAAAAAAA\uD83D\uDE00BBBBBBBBBBBBBBBBBBB""#;
+    let mut buffer = [0u8; 16];
+    let mut reader = input.as_bytes();
+    let mut writer = Vec::new();
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.peek();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Peek::String);
+    let wb = rjiter.write_long_str(&mut writer);
+    wb.unwrap();
+
+    assert_eq!(writer, "AAAAAAAAAAA\u{1F600}BBBBBBBBBBBBBBBBBBB".as_bytes());
 }
 
 #[test]
-fn next_null() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}null"#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn write_long_str_escape_after_multibyte_chars() {
+    // After a segment, the buffer has stale bytes of the previous chars
+    let expected = "😀😀😀\n😀\t├├\"x";
+    let input = r#""😀😀😀\n😀\t├├\"x""#;
 
-    let result = rjiter.next_null();
-    assert!(result.is_ok());
+    for buffer_size in [8, 13, 16] {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_size];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut writer = Vec::new();
+        rjiter.write_long_str(&mut writer).unwrap();
+        assert_eq!(writer, expected.as_bytes(), "buffer size {buffer_size}");
+    }
 }
 
 #[test]
-fn known_null() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}null"#);
+fn write_long_str_chunks_keeps_code_points() {
+    let expected = "aä├😀b\u{1F600}\nz├├├├├├├├├├😀😀😀😀";
+    let input = r#""aä├😀b\uD83D\uDE00\nz├├├├├├├├├├😀😀😀😀""#;
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
+    let mut writer = Vec::new();
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let peek = rjiter.peek().unwrap();
-    assert_eq!(peek, Peek::Null);
-    let result = rjiter.known_null();
-    assert!(result.is_ok());
+    let mut chunks: Vec<(usize, String)> = Vec::new();
+    let len = rjiter
+        .write_long_str_chunks(&mut writer, |offset, chunk| {
+            chunks.push((offset, chunk.to_string()));
+        })
+        .unwrap();
+
+    assert_eq!(writer, expected.as_bytes());
+    assert_eq!(len, expected.len());
+    assert!(chunks.len() > 1);
+    let mut next_offset = 0;
+    for (offset, chunk) in &chunks {
+        assert_eq!(*offset, next_offset);
+        assert!(expected.is_char_boundary(*offset));
+        next_offset += chunk.len();
+    }
+    assert_eq!(next_offset, expected.len());
+    let joined: String = chunks.into_iter().map(|(_, chunk)| chunk).collect();
+    assert_eq!(joined, expected);
 }
 
 #[test]
-fn next_bool() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}true"#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn known_str_into_copies_decoded_strings() {
+    struct Record {
+        name: [u8; 32],
+        name_len: usize,
+    }
 
-    let result = rjiter.next_bool();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), true);
+    let input = r#"{"name": "café \"😀\" long name", "short": "ok"}"#;
+    for chunk_len in 1..5 {
+        let mut reader = ChunkReader::with_chunking(input.as_bytes(), Chunking::Fixed(chunk_len));
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+        assert_eq!(rjiter.next_object().unwrap(), Some("name"));
+        assert_eq!(rjiter.peek().unwrap(), Peek::String);
+        let mut record = Record {
+            name: [0; 32],
+            name_len: 0,
+        };
+        record.name_len = rjiter.known_str_into(&mut record.name).unwrap();
+
+        // The record is independent of rjiter
+        assert_eq!(rjiter.next_key().unwrap(), Some("short"));
+        let mut short = [0u8; 2];
+        assert_eq!(rjiter.peek().unwrap(), Peek::String);
+        assert_eq!(rjiter.known_str_into(&mut short).unwrap(), 2);
+        assert_eq!(&short, b"ok");
+        assert_eq!(rjiter.next_key().unwrap(), None);
+        rjiter.finish().unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&record.name[..record.name_len]).unwrap(),
+            "café \"😀\" long name",
+            "chunk {chunk_len}"
+        );
+    }
 }
 
 #[test]
-fn known_bool() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}false"#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+fn known_str_into_small_buffer() {
+    let input = r#"  "0123456789""#;
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 32];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let peek = rjiter.peek().unwrap();
-    assert_eq!(peek, Peek::False);
-    let result = rjiter.known_bool(peek);
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), false);
+    assert_eq!(rjiter.peek().unwrap(), Peek::String);
+    let mut small = [0u8; 8];
+    let err = rjiter.known_str_into(&mut small).unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::OutputFull { written: 0 }
+    );
+    // Detected after the string is parsed in the rjiter buffer
+    assert_eq!(err.index, 14);
 }
 
 #[test]
-fn next_number() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}123.45"#);
+fn read_long_str_into_fixed_buffer() {
+    let input = r#"["caf\u00e9 \"\ud83d\ude00\" long, longer, longest", "next"]"#;
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_number();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), jiter::NumberAny::Float(123.45));
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let mut out = [0u8; 64];
+    let len = rjiter.read_long_str_into(&mut out).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out[..len]).unwrap(),
+        "café \"😀\" long, longer, longest"
+    );
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "next");
+
+    // Too small: the written part is a prefix of the string
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let mut out = [0u8; 20];
+    let err = rjiter.read_long_str_into(&mut out).unwrap_err();
+    let rjiter::error::ErrorType::OutputFull { written } = err.error_type else {
+        panic!("unexpected error {err:?}");
+    };
+    assert!(written > 0 && written <= 20);
+    assert!("café \"😀\" long, longer, longest"
+        .as_bytes()
+        .starts_with(&out[..written]));
 }
 
 #[test]
-fn known_number() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}123.45"#);
+fn skip_long_str_with_escapes() {
+    let long = "\\\"\\u00e9 abc\\n".repeat(20);
+    let input = format!(r#"["{long}", "next", "x\"]"#);
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let peek = rjiter.peek().unwrap();
-    assert!(peek.is_num());
-    let result = rjiter.known_number(peek);
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), jiter::NumberAny::Float(123.45));
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    rjiter.skip_long_str().unwrap();
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "next");
+
+    // Unterminated string
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    let err = rjiter.skip_long_str().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::JsonError(rjiter::jiter::JsonErrorType::EofWhileParsingString)
+    );
 }
 
 #[test]
-fn next_int() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}42"#);
+fn write_long_number_longer_than_buffer() {
+    let digits = "1234567890".repeat(5);
+    let long = format!("-{digits}.{digits}e+12");
+    let input = format!("[{long}, 0, 7.5E-3]  123456789012345678901234567890");
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_int();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), jiter::NumberInt::Int(42));
+    let mut numbers = Vec::new();
+    let mut peek = rjiter.next_array().unwrap();
+    while peek.is_some() {
+        let mut writer = Vec::new();
+        rjiter.write_long_number(&mut writer).unwrap();
+        numbers.push(String::from_utf8(writer).unwrap());
+        peek = rjiter.array_step().unwrap();
+    }
+    assert_eq!(numbers, vec![long.as_str(), "0", "7.5E-3"]);
+
+    // The runs of digits are checked by their first digits
+    for number in [
+        "0.000000000000001",
+        "-0e-000000000000",
+        "100000000000000E+9",
+    ] {
+        let mut reader = OneByteReader::new(number.bytes());
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut writer = Vec::new();
+        rjiter.write_long_number(&mut writer).unwrap();
+        assert_eq!(writer, number.as_bytes());
+    }
+
+    // At the end of the input
+    let mut writer = Vec::new();
+    rjiter.write_long_number(&mut writer).unwrap();
+    assert_eq!(writer, b"123456789012345678901234567890");
+    rjiter.finish().unwrap();
+
+    // The token length limit
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(20);
+    rjiter.next_array().unwrap();
+    let err = rjiter.write_long_number(&mut Vec::new()).unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::TokenTooLong { max_len: 20 }
+    );
 }
 
 #[test]
-fn known_int() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}42"#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn write_long_number_invalid() {
+    use rjiter::error::ErrorType;
+    use rjiter::jiter::{JsonErrorType, JsonType};
+
+    for (input, expected, index) in [
+        ("01", JsonErrorType::InvalidNumber, 1),
+        ("[1.]", JsonErrorType::InvalidNumber, 3),
+        ("1.e5", JsonErrorType::InvalidNumber, 2),
+        ("1e+-5", JsonErrorType::InvalidNumber, 3),
+        (" 1234567890123456789-", JsonErrorType::InvalidNumber, 20),
+        ("-", JsonErrorType::EofWhileParsingValue, 1),
+        ("1.2.3.4.5.6.7.8.9.0", JsonErrorType::InvalidNumber, 3),
+        (
+            "[-12345678901234.5678901234e+]",
+            JsonErrorType::InvalidNumber,
+            29,
+        ),
+    ] {
+        let mut reader = input.as_bytes();
+        let mut buffer = [0u8; 8];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        if input.starts_with('[') {
+            rjiter.next_array().unwrap();
+        }
+        let err = rjiter.write_long_number(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.error_type, ErrorType::JsonError(expected), "{input}");
+        assert_eq!(err.index, index, "{input}");
+    }
 
-    let peek = rjiter.peek().unwrap();
-    assert!(peek.is_num());
-    let result = rjiter.known_int(peek);
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), jiter::NumberInt::Int(42));
+    let mut reader = r#""1""#.as_bytes();
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.write_long_number(&mut Vec::new()).unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::WrongType {
+            expected: JsonType::Int,
+            actual: JsonType::String
+        }
+    );
 }
 
 #[test]
-fn next_float() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}3.14"#);
+fn write_long_value_copies_subtree() {
+    let long = "very long \\\"text\\\" \\u00e9 ".repeat(5);
+    let value = format!(
+        r#"{{"{long}": [1, -2.5e3, true, false, null, "{long}"], "e": {{}}, "a": [[], [{{}}]], "n": {{"m": "x"}}}}"#
+    );
+    let value = value.replace(", ", " ,\n ");
+    let input = format!("[ {value}, \"next\", 7 ]");
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_float();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), 3.14);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::Object));
+    let mut writer = Vec::new();
+    rjiter.write_long_value(&mut writer).unwrap();
+    assert_eq!(String::from_utf8(writer).unwrap(), value);
+
+    // Scalars, the parser continues after the value
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    let mut writer = Vec::new();
+    rjiter.write_long_value(&mut writer).unwrap();
+    assert_eq!(writer, b"\"next\"");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::new(b'7')));
+    let mut writer = Vec::new();
+    rjiter.write_long_value(&mut writer).unwrap();
+    assert_eq!(writer, b"7");
+    assert_eq!(rjiter.array_step().unwrap(), None);
+    rjiter.finish().unwrap();
 }
 
 #[test]
-fn known_float() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}3.14"#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn write_long_value_keeps_pretty_printing() {
+    let value = "{\n  \"a\" : [\n    1,\n    \"two\",\n    {}\n  ],\n\t\"b\":\r\n{ \"c\": [ ] }\n}";
+    let input = format!("  {value}  \n");
+    for buffer_len in [8, 16, 64] {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let peek = rjiter.peek().unwrap();
-    assert!(peek.is_num());
-    let result = rjiter.known_float(peek);
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), 3.14);
+        let mut writer = Vec::new();
+        rjiter.write_long_value(&mut writer).unwrap();
+        assert_eq!(
+            String::from_utf8(writer).unwrap(),
+            value,
+            "buffer {buffer_len}"
+        );
+        rjiter.finish().unwrap();
+    }
 }
 
 #[test]
-fn next_number_bytes() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}123.45"#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn write_long_value_invalid() {
+    use rjiter::error::ErrorType;
+    use rjiter::jiter::JsonErrorType;
+
+    for (input, expected) in [
+        (r#"[1, 2"#, JsonErrorType::EofWhileParsingList),
+        (
+            r#"{"a": 1 "b": 2}"#,
+            JsonErrorType::ExpectedObjectCommaOrEnd,
+        ),
+        (r#"{"a" 1}"#, JsonErrorType::ExpectedColon),
+        (r#"[1, }"#, JsonErrorType::ExpectedSomeValue),
+        (r#"[1, ]"#, JsonErrorType::TrailingComma),
+        (r#"{"a": 1, }"#, JsonErrorType::TrailingComma),
+    ] {
+        let mut reader = input.as_bytes();
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut writer = Vec::new();
+        let err = rjiter.write_long_value(&mut writer).unwrap_err();
+        assert_eq!(err.error_type, ErrorType::JsonError(expected), "{input}");
+    }
 
-    let result = rjiter.next_number_bytes();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), b"123.45");
+    let input = "[".repeat(201);
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.write_long_value(&mut Vec::new()).unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::JsonError(JsonErrorType::RecursionLimitExceeded)
+    );
 }
 
 #[test]
-fn next_str() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
+fn for_each_str_chunk_hashes_and_stops() {
+    let input = r#"["aä├😀b\uD83D\uDE00\nz├├├├├├├├├├😀😀😀😀", "next"]"#;
+    let expected = "aä├😀b\u{1F600}\nz├├├├├├├├├├😀😀😀😀";
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_str();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), "hello");
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let mut checksum = 0u32;
+    let mut n_chunks = 0;
+    let mut len = 0;
+    let result = rjiter.for_each_str_chunk(|chunk| {
+        assert!(std::str::from_utf8(chunk).is_ok());
+        for b in chunk {
+            checksum = checksum.wrapping_mul(31).wrapping_add(u32::from(*b));
+        }
+        n_chunks += 1;
+        len += chunk.len();
+        Ok::<(), ()>(())
+    });
+    assert_eq!(result, Ok(Ok(())));
+    let expected_checksum = expected.bytes().fold(0u32, |acc, b| {
+        acc.wrapping_mul(31).wrapping_add(u32::from(b))
+    });
+    assert_eq!(checksum, expected_checksum);
+    assert_eq!(len, expected.len());
+    assert!(n_chunks > 1);
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+
+    // The closure stops the parsing
+    let mut n_calls = 0;
+    let result = rjiter.for_each_str_chunk(|_chunk| {
+        n_calls += 1;
+        Err("stop")
+    });
+    assert_eq!(result, Ok(Err("stop")));
+    assert_eq!(n_calls, 1);
 }
 
 #[test]
-fn known_str() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
+fn write_long_str_fanout_to_hasher_and_output() {
+    // A toy checksum instead of a real hasher
+    struct Checksum(u32);
+    impl embedded_io::ErrorType for Checksum {
+        type Error = core::convert::Infallible;
+    }
+    impl embedded_io::Write for Checksum {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            for b in buf {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(u32::from(*b));
+            }
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    let expected = "aä├😀b\u{1F600}\nz├├├├├├├├├├😀😀😀😀";
+    let input = r#"["aä├😀b\uD83D\uDE00\nz├├├├├├├├├├😀😀😀😀", 42]"#;
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
 
-    let _ = rjiter.finish();
-    let result = rjiter.known_str();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), "hello");
+    let mut output = Vec::new();
+    let mut checksum = Checksum(0);
+    rjiter
+        .write_long_str_fanout(&mut [&mut output, &mut checksum])
+        .unwrap();
+
+    let mut expected_checksum = Checksum(0);
+    embedded_io::Write::write_all(&mut expected_checksum, expected.as_bytes()).unwrap();
+    assert_eq!(output, expected.as_bytes());
+    assert_eq!(checksum.0, expected_checksum.0);
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::new(b'4')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(42));
 }
 
 #[test]
-fn next_bytes() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn string_reader_with_small_reads() {
+    use embedded_io::Read;
 
-    let result = rjiter.next_bytes();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), b"hello");
+    let expected = "aä├😀b\u{1F600}\n\"z├├├├├├├├├├😀😀😀😀 the end";
+    let input = r#"["aä├😀b\uD83D\uDE00\n\"z├├├├├├├├├├😀😀😀😀 the end", 42]"#;
+
+    for read_size in [1, 2, 3, 5, 64] {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+
+        let mut decoded = Vec::new();
+        let mut string_reader = rjiter.string_reader();
+        let mut chunk = vec![0u8; read_size];
+        loop {
+            let n = string_reader.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            if read_size >= 4 {
+                assert!(std::str::from_utf8(&chunk[..n]).is_ok());
+            }
+            decoded.extend_from_slice(&chunk[..n]);
+        }
+        assert!(string_reader.is_finished());
+        assert_eq!(decoded, expected.as_bytes(), "read size {read_size}");
+
+        // The rjiter is positioned after the string
+        let peek = rjiter.array_step().unwrap().unwrap();
+        assert_eq!(rjiter.known_int(peek).unwrap(), NumberInt::Int(42));
+    }
 }
 
 #[test]
-fn known_bytes() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
+fn string_reader_error() {
+    use embedded_io::Read;
+
+    let input = r#""0123456789\x0123456789""#;
     let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
+    let mut buffer = [0u8; 16];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let _ = rjiter.finish();
-    let result = rjiter.known_bytes();
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), b"hello");
+    let mut string_reader = rjiter.string_reader();
+    let mut decoded = Vec::new();
+    let mut chunk = [0u8; 4];
+    let err = loop {
+        match string_reader.read(&mut chunk) {
+            Ok(n) => decoded.extend_from_slice(&chunk[..n]),
+            Err(err) => break err,
+        }
+    };
+
+    assert_eq!(err, embedded_io::ErrorKind::InvalidData);
+    assert_eq!(decoded, b"0123456789");
+    let rjiter_error = string_reader.error().unwrap();
+    assert_eq!(rjiter_error.index, 12);
 }
 
 #[test]
-fn next_value() {
-    let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
-    let mut reader = OneByteReader::new(input.bytes());
-    let mut buffer = [0u8; 10];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+fn next_str_partial_pulls_chunks() {
+    let expected = "aä├😀b\u{1F600}\n\"z├├├├├├├├├├😀😀😀😀 the end";
+    let input = r#"["aä├😀b\uD83D\uDE00\n\"z├├├├├├├├├├😀😀😀😀 the end", 42]"#;
 
-    let result = rjiter.next_value();
-    assert!(result.is_ok());
+    for buffer_size in [16, 64] {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_size];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+
+        let mut decoded = String::new();
+        let mut n_chunks = 0;
+        loop {
+            let (chunk, is_final) = rjiter.next_str_partial().unwrap();
+            decoded.push_str(chunk);
+            n_chunks += 1;
+            if is_final {
+                break;
+            }
+        }
+        assert_eq!(decoded, expected, "buffer size {buffer_size}");
+        assert!(n_chunks > 1);
+
+        // The rjiter is positioned after the string
+        let peek = rjiter.array_step().unwrap().unwrap();
+        assert_eq!(rjiter.known_int(peek).unwrap(), NumberInt::Int(42));
+    }
+}
+
+#[test]
+fn next_str_partial_invalid_utf8() {
+    let mut input = b"\"0123456789abcdefghij".to_vec();
+    input.extend_from_slice(b"\xff\xfe0123456789\"");
+    let mut reader = OneByteReader::new(input.into_iter());
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let mut decoded = String::new();
+    let err = loop {
+        match rjiter.next_str_partial() {
+            Ok((chunk, is_final)) => {
+                assert!(!is_final);
+                decoded.push_str(chunk);
+            }
+            Err(err) => break err,
+        }
+    };
+    // No empty chunks instead of the error
+    assert!("0123456789abcdefghij".starts_with(&decoded), "{decoded}");
     assert_eq!(
-        result.unwrap(),
-        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
+        err.error_type,
+        rjiter::error::ErrorType::JsonError(rjiter::jiter::JsonErrorType::InvalidUnicodeCodePoint)
     );
 }
 
 #[test]
-fn known_value() {
+fn write_long_str_invalid_utf8_position() {
+    use rjiter::error::ErrorType;
+    use rjiter::jiter::JsonErrorType;
+
+    // The position is the absolute position of the invalid sequence,
+    // even if it is split by chunks or is after segments and escapes
+    let mut inputs: Vec<Vec<u8>> = Vec::new();
+    for prefix in [
+        "",
+        "abcdefghijklmn",
+        "ab\\n├",
+        "abcdefghijklm\\t\\u00e9",
+        "😀😀😀\\n",
+    ] {
+        for bad in [
+            &[0xff][..],
+            &[0xe2, 0x94],
+            &[0xf0, 0x9f, 0x98, 0x80, 0x80],
+            &[0x80; 20],
+        ] {
+            let mut input = b"\"".to_vec();
+            input.extend_from_slice(prefix.as_bytes());
+            input.extend_from_slice(bad);
+            input.extend_from_slice(b"zzzzzzzzzzzzzzzzzzzz\"");
+            inputs.push(input);
+        }
+    }
+
+    for input in &inputs {
+        let expected = std::str::from_utf8(input).unwrap_err().valid_up_to();
+        for buffer_size in [8, 16, 128] {
+            for chunking in [
+                Chunking::Fixed(1),
+                Chunking::Fixed(5),
+                Chunking::Random {
+                    seed: 7,
+                    max_len: 9,
+                },
+            ] {
+                let mut reader = ChunkReader::with_chunking(input, chunking);
+                let mut buffer = vec![0u8; buffer_size];
+                let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+                let mut writer = Vec::new();
+                let err = rjiter.write_long_str(&mut writer).unwrap_err();
+                let context = format!("{input:x?}, buffer size {buffer_size}, {chunking:?}");
+                assert_eq!(
+                    err.error_type,
+                    ErrorType::JsonError(JsonErrorType::InvalidUnicodeCodePoint),
+                    "{context}"
+                );
+                assert_eq!(err.index, expected, "{context}");
+                assert_eq!(
+                    err.position(),
+                    Some(&LinePosition::new(1, expected + 1)),
+                    "{context}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn next_str_partial_short_string_and_errors() {
+    let input = r#"  "short" 42 "0123456789abcdefghij\x0123456789""#;
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.next_str_partial().unwrap(), ("short", true));
+
+    let err = rjiter.next_str_partial().unwrap_err();
+    assert!(matches!(
+        err.error_type,
+        rjiter::error::ErrorType::WrongType { .. }
+    ));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(42));
+
+    let mut decoded = String::new();
+    let err = loop {
+        match rjiter.next_str_partial() {
+            Ok((chunk, _)) => decoded.push_str(chunk),
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(decoded, "0123456789abcd");
+    assert_eq!(err.index, 35);
+}
+
+// Read JSON Lines, drop corrupt records
+fn read_records_with_recovery(input: &str, buffer_size: usize) -> (Vec<JsonValue<'static>>, usize) {
+    read_records_with_policy(input, buffer_size, WhitespacePolicy::default())
+}
+
+fn read_records_with_policy(
+    input: &str,
+    buffer_size: usize,
+    policy: WhitespacePolicy,
+) -> (Vec<JsonValue<'static>>, usize) {
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = vec![0u8; buffer_size];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_whitespace_policy(policy);
+
+    let mut values = Vec::new();
+    let mut n_errors = 0;
+    loop {
+        if rjiter.finish().is_ok() {
+            break;
+        }
+        match rjiter.next_value_owned() {
+            Ok(value) => values.push(value),
+            Err(_) => {
+                n_errors += 1;
+                if !rjiter.recover_to_next_top_level().unwrap() {
+                    break;
+                }
+            }
+        }
+    }
+    (values, n_errors)
+}
+
+#[test]
+fn recover_to_next_top_level_in_json_lines() {
+    let input = "{\"a\": 1}\n{\"b\": tru, \"s\": \"{[\"}\n[2]\n{\"c\": \"x\ny\"}\n\"last\"\n";
+
+    for buffer_size in [16, 64] {
+        let (values, n_errors) = read_records_with_recovery(input, buffer_size);
+        let values: Vec<String> = values.iter().map(|v| format!("{v:?}")).collect();
+        assert_eq!(n_errors, 2, "buffer size {buffer_size}");
+        assert_eq!(values.len(), 3, "buffer size {buffer_size}: {values:?}");
+        assert!(values[0].contains("\"a\""));
+        assert!(values[1].contains("Int(2)"));
+        assert!(values[2].contains("\"last\""));
+    }
+}
+
+#[test]
+fn recover_to_next_top_level_in_pretty_printed_values() {
+    let input = "{\n  \"a\": [\n    1,\n    oops\n  ]\n}\n{\n  \"b\": 2\n}\n";
+    let (values, n_errors) = read_records_with_recovery(input, 16);
+    assert_eq!(n_errors, 1);
+    assert_eq!(values.len(), 1);
+    assert!(format!("{:?}", values[0]).contains("\"b\""));
+}
+
+#[test]
+fn recover_to_next_top_level_in_unindented_values() {
+    let input = "{\n\"a\": [\n1,\noops,\n{\"x\": 1}\n]\n}\n{\n\"b\": 2\n}\n[\n3\n]\n";
+
+    for buffer_size in [16, 64] {
+        let (values, n_errors) = read_records_with_recovery(input, buffer_size);
+        let values: Vec<String> = values.iter().map(|v| format!("{v:?}")).collect();
+        assert_eq!(n_errors, 1, "buffer size {buffer_size}");
+        assert_eq!(values.len(), 2, "buffer size {buffer_size}: {values:?}");
+        assert!(values[0].contains("\"b\""));
+        assert!(values[1].contains("Int(3)"));
+    }
+}
+
+#[test]
+fn recover_to_next_top_level_on_one_line() {
+    let input = "{\"a\": 1} {\"b\": tru, \"s\": \"}{\"} [2] \"last\" \
+                 {\"c\": [1, {\"d\": oops}], \"e\": \"x\\\"] {\"} 3";
+
+    for buffer_size in [8, 64] {
+        let (values, n_errors) = read_records_with_recovery(input, buffer_size);
+        let values: Vec<String> = values.iter().map(|v| format!("{v:?}")).collect();
+        assert_eq!(n_errors, 2, "buffer size {buffer_size}");
+        assert_eq!(values.len(), 4, "buffer size {buffer_size}: {values:?}");
+        assert!(values[0].contains("\"a\""));
+        assert!(values[1].contains("Int(2)"));
+        assert!(values[2].contains("\"last\""));
+        assert!(values[3].contains("Int(3)"));
+    }
+}
+
+#[test]
+fn recover_to_next_top_level_after_truncated_record() {
+    // The error is at the start of the next record, which is kept
+    let input = "{\"a\": [1, 2\n{\"c\": 3}\n[4, 5\n[6]\n";
+    let (values, n_errors) = read_records_with_recovery(input, 64);
+    let values: Vec<String> = values.iter().map(|v| format!("{v:?}")).collect();
+    assert_eq!(n_errors, 2);
+    assert_eq!(values.len(), 2, "{values:?}");
+    assert!(values[0].contains("\"c\""));
+    assert!(values[1].contains("Int(6)"));
+
+    // A record which is too large for the buffer is skipped
+    let input = "[1, 2, 3, 4, 5, 6]\n[7]\n";
+    let (values, n_errors) = read_records_with_recovery(input, 8);
+    assert_eq!(n_errors, 1);
+    assert_eq!(values.len(), 1);
+    assert!(format!("{:?}", values[0]).contains("Int(7)"));
+}
+
+#[test]
+fn recover_to_next_top_level_ends_corrupt_string_at_line_break() {
+    // A raw newline can't be in a string, the line after it starts the next value
+    let input = "{\"c\": \"x\n{\"d\": 1}\"}\n[3]\n";
+    let (values, n_errors) = read_records_with_recovery(input, 16);
+    let values: Vec<String> = values.iter().map(|v| format!("{v:?}")).collect();
+    assert_eq!(n_errors, 2);
+    assert_eq!(values.len(), 2, "{values:?}");
+    assert!(values[0].contains("\"d\""));
+    assert!(values[1].contains("Int(3)"));
+}
+
+#[test]
+fn recover_to_next_top_level_at_end() {
+    let input = "{\"a\": oops\n  \n";
+    let (values, n_errors) = read_records_with_recovery(input, 16);
+    assert_eq!(n_errors, 1);
+    assert!(values.is_empty());
+}
+
+#[test]
+fn recover_to_next_top_level_after_lone_cr() {
+    let input = "{\"a\": 1}\r{\"b\": tru}\r[2]\r\n\"last\"\r";
+    let policy = WhitespacePolicy {
+        cr_line_breaks: true,
+        ..WhitespacePolicy::default()
+    };
+
+    for buffer_size in [8, 64] {
+        let (values, n_errors) = read_records_with_policy(input, buffer_size, policy);
+        let values: Vec<String> = values.iter().map(|v| format!("{v:?}")).collect();
+        assert_eq!(n_errors, 1, "buffer size {buffer_size}");
+        assert_eq!(values.len(), 3, "buffer size {buffer_size}: {values:?}");
+        assert!(values[1].contains("Int(2)"));
+        assert!(values[2].contains("\"last\""));
+    }
+
+    // A lone `\r` ends a corrupt string only if it is a line break
+    let input = "{\"a\": 1}\r{\"b\": \"x\r[2]\r\n\"last\"\r";
+    let (values, n_errors) = read_records_with_policy(input, 64, policy);
+    assert_eq!(n_errors, 1);
+    assert_eq!(values.len(), 3);
+
+    // By default, `[2]` is inside the string and is lost
+    let (values, n_errors) = read_records_with_recovery(input, 64);
+    assert_eq!(n_errors, 1);
+    assert_eq!(values.len(), 2);
+}
+
+#[test]
+fn whitespace_policy_strict_rejects_form_feed() {
+    let input = b" \x0C 1 \x0C\r\n 2";
+    let strict = WhitespacePolicy {
+        strict: true,
+        ..WhitespacePolicy::default()
+    };
+
+    for chunk_len in 1..5 {
+        let mut reader = ChunkReader::with_chunking(input, Chunking::Fixed(chunk_len));
+        let mut buffer = [0u8; 8];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        assert_eq!(
+            rjiter.next_int().unwrap(),
+            NumberInt::Int(1),
+            "chunk {chunk_len}"
+        );
+        assert_eq!(
+            rjiter.next_int().unwrap(),
+            NumberInt::Int(2),
+            "chunk {chunk_len}"
+        );
+        rjiter.finish().unwrap();
+
+        let mut reader = ChunkReader::with_chunking(input, Chunking::Fixed(chunk_len));
+        let mut buffer = [0u8; 8];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_whitespace_policy(strict);
+        assert!(rjiter.next_int().is_err(), "chunk {chunk_len}");
+    }
+
+    // The JSON whitespace is still skipped in the strict mode
+    let input = b" \t\r\n 1 \r\r\n\t 2 ";
+    for chunk_len in 1..5 {
+        let mut reader = ChunkReader::with_chunking(input, Chunking::Fixed(chunk_len));
+        let mut buffer = [0u8; 8];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_whitespace_policy(strict);
+        assert_eq!(
+            rjiter.next_int().unwrap(),
+            NumberInt::Int(1),
+            "chunk {chunk_len}"
+        );
+        assert_eq!(
+            rjiter.next_int().unwrap(),
+            NumberInt::Int(2),
+            "chunk {chunk_len}"
+        );
+        rjiter.finish().unwrap();
+        assert!(!rjiter.has_buffered_data());
+    }
+}
+
+#[test]
+fn whitespace_policy_cr_line_breaks_in_positions() {
+    let input = b"1\r2\r\n\r  tru";
+    let cr_line_breaks = WhitespacePolicy {
+        cr_line_breaks: true,
+        ..WhitespacePolicy::default()
+    };
+
+    for chunk_len in 1..5 {
+        for (policy, expected) in [
+            (cr_line_breaks, LinePosition::new(4, 5)),
+            (WhitespacePolicy::default(), LinePosition::new(2, 6)),
+        ] {
+            let mut reader = ChunkReader::with_chunking(input, Chunking::Fixed(chunk_len));
+            let mut buffer = [0u8; 4];
+            let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_whitespace_policy(policy);
+            assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+            assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+            let err = rjiter.next_bool().unwrap_err();
+            assert_eq!(
+                err.position(),
+                Some(&expected),
+                "chunk {chunk_len}, {policy:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn read_coalescing_with_one_byte_reader() {
+    let input = r#"{"name": "a long enough string value", "n": [1, 22.5, -333], "ok": true}
+{"x": null}"#;
+
+    for min_fill in [0, 1, 7, 16, 100] {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = [0u8; 96];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_read_coalescing(min_fill);
+
+        let value = rjiter.next_value().unwrap();
+        let expected = JsonValue::parse(input.lines().next().unwrap().as_bytes(), false);
+        assert_eq!(value, expected.unwrap(), "min_fill {min_fill}");
+        assert_eq!(rjiter.next_object().unwrap(), Some("x"));
+        rjiter.next_null().unwrap();
+        assert_eq!(rjiter.next_key().unwrap(), None);
+        rjiter.finish().unwrap();
+    }
+}
+
+#[test]
+fn buffered_data_and_eof_hint() {
+    let input = "{\"a\": 1}\n{\"b\": 2}\n";
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    // Nothing is read yet
+    assert!(!rjiter.has_buffered_data());
+    assert!(!rjiter.eof_known());
+
+    rjiter.next_value().unwrap();
+    assert!(rjiter.has_buffered_data());
+    assert!(!rjiter.eof_known());
+
+    // Only whitespace is left in the buffer
+    rjiter.next_value().unwrap();
+    assert!(!rjiter.has_buffered_data());
+
+    rjiter.finish().unwrap();
+    assert!(!rjiter.has_buffered_data());
+    assert!(rjiter.eof_known());
+}
+
+#[test]
+fn eof_known_skips_read_at_end() {
+    struct CountingReader<'a> {
+        data: &'a [u8],
+        n_reads: usize,
+    }
+    impl embedded_io::ErrorType for CountingReader<'_> {
+        type Error = embedded_io::ErrorKind;
+    }
+    impl embedded_io::Read for CountingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.n_reads += 1;
+            let n = buf.len().min(self.data.len());
+            let (head, tail) = self.data.split_at(n);
+            buf[..n].copy_from_slice(head);
+            self.data = tail;
+            Ok(n)
+        }
+    }
+
+    let count_reads = |clear_eof_known: bool| {
+        let mut reader = CountingReader {
+            data: b"1 2 3",
+            n_reads: 0,
+        };
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+        assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+        // The number is at the end of the buffer, the read returns 0 bytes
+        assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(3));
+        assert!(rjiter.eof_known());
+        if clear_eof_known {
+            rjiter.set_eof_known(false);
+        }
+        rjiter.finish().unwrap();
+        assert!(rjiter.eof_known());
+        reader.n_reads
+    };
+
+    assert_eq!(count_reads(false), 2);
+    assert_eq!(count_reads(true), 3);
+}
+
+// ----------------------------------------------
+// Auto-generated from a template
+
+#[test]
+fn peek() {
     let lot_of_spaces = " ".repeat(32);
     let input = format!(r#"{lot_of_spaces}"hello""#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let peek = rjiter.peek().unwrap();
-    assert_eq!(peek, Peek::String);
-    let result = rjiter.known_value(peek);
+    let result = rjiter.peek();
     assert!(result.is_ok());
-    assert_eq!(
-        result.unwrap(),
-        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
-    );
+    assert_eq!(result.unwrap(), Peek::String);
 }
 
 #[test]
-fn next_skip() {
+fn next_null() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello"  42"#);
+    let input = format!(r#"{lot_of_spaces}null"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_skip();
-    assert!(result.is_ok());
-
-    // To check that skipped, read the next value
-    let result = rjiter.next_number();
+    let result = rjiter.next_null();
     assert!(result.is_ok());
-    assert_eq!(
-        result.unwrap(),
-        jiter::NumberAny::Int(jiter::NumberInt::Int(42))
-    );
 }
 
 #[test]
-fn known_skip() {
+fn known_null() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello"  42"#);
+    let input = format!(r#"{lot_of_spaces}null"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
     let peek = rjiter.peek().unwrap();
-    assert_eq!(peek, Peek::String);
-    let result = rjiter.known_skip(peek);
-    assert!(result.is_ok());
-
-    // To check that skipped, read the next value
-    let result = rjiter.next_number();
+    assert_eq!(peek, Peek::Null);
+    let result = rjiter.known_null();
     assert!(result.is_ok());
-    assert_eq!(
-        result.unwrap(),
-        jiter::NumberAny::Int(jiter::NumberInt::Int(42))
-    );
 }
 
 #[test]
-fn next_value_owned() {
+fn next_bool() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let input = format!(r#"{lot_of_spaces}true"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_value_owned();
+    let result = rjiter.next_bool();
     assert!(result.is_ok());
-    assert_eq!(
-        result.unwrap(),
-        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
-    );
+    assert_eq!(result.unwrap(), true);
 }
 
 #[test]
-fn known_value_owned() {
+fn known_bool() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let input = format!(r#"{lot_of_spaces}false"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
     let peek = rjiter.peek().unwrap();
-    assert_eq!(peek, Peek::String);
-    let result = rjiter.known_value_owned(peek);
+    assert_eq!(peek, Peek::False);
+    let result = rjiter.known_bool(peek);
     assert!(result.is_ok());
-    assert_eq!(
-        result.unwrap(),
-        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
-    );
+    assert_eq!(result.unwrap(), false);
 }
 
 #[test]
-fn next_array() {
+fn next_number() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}[{lot_of_spaces}false{lot_of_spaces}, 2, 3]"#);
+    let input = format!(r#"{lot_of_spaces}123.45"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_array();
+    let result = rjiter.next_number();
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some(Peek::False));
+    assert_eq!(result.unwrap(), jiter::NumberAny::Float(123.45));
 }
 
 #[test]
-fn known_array() {
+fn known_number() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}[{lot_of_spaces}"hello"{lot_of_spaces}, 2, 3]"#);
+    let input = format!(r#"{lot_of_spaces}123.45"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let _ = rjiter.finish();
-    let result = rjiter.known_array();
+    let peek = rjiter.peek().unwrap();
+    assert!(peek.is_num());
+    let result = rjiter.known_number(peek);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some(Peek::String));
+    assert_eq!(result.unwrap(), jiter::NumberAny::Float(123.45));
 }
 
 #[test]
-fn array_step() {
+fn next_int() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces},{lot_of_spaces} true{lot_of_spaces}, 3]"#);
+    let input = format!(r#"{lot_of_spaces}42"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.array_step();
+    let result = rjiter.next_int();
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some(Peek::True));
+    assert_eq!(result.unwrap(), jiter::NumberInt::Int(42));
 }
 
 #[test]
-fn next_object() {
+fn known_int() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}{{{lot_of_spaces}"key": "value"}}"#);
+    let input = format!(r#"{lot_of_spaces}42"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_object();
+    let peek = rjiter.peek().unwrap();
+    assert!(peek.is_num());
+    let result = rjiter.known_int(peek);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some("key"));
+    assert_eq!(result.unwrap(), jiter::NumberInt::Int(42));
 }
 
 #[test]
-fn known_object() {
+fn next_float() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}{{{lot_of_spaces}"key": "value"}}"#);
+    let input = format!(r#"{lot_of_spaces}3.14"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let _ = rjiter.finish();
-    let result = rjiter.known_object();
+    let result = rjiter.next_float();
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some("key"));
+    assert_eq!(result.unwrap(), 3.14);
 }
 
 #[test]
-fn next_object_bytes() {
+fn known_float() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces}{{{lot_of_spaces}"key": "value"}}"#);
+    let input = format!(r#"{lot_of_spaces}3.14"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_object_bytes();
+    let peek = rjiter.peek().unwrap();
+    assert!(peek.is_num());
+    let result = rjiter.known_float(peek);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some(&b"key"[..]));
+    assert_eq!(result.unwrap(), 3.14);
 }
 
 #[test]
-fn next_key_bytes() {
+fn next_number_bytes() {
     let lot_of_spaces = " ".repeat(32);
-    let input = format!(r#"{lot_of_spaces},{lot_of_spaces}"key": "value"}}"#);
+    let input = format!(r#"{lot_of_spaces}123.45"#);
     let mut reader = OneByteReader::new(input.bytes());
     let mut buffer = [0u8; 10];
     let mut rjiter = RJiter::new(&mut reader, &mut buffer);
 
-    let result = rjiter.next_key_bytes();
+    let result = rjiter.next_number_bytes();
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), Some(&b"key"[..]));
+    assert_eq!(result.unwrap(), b"123.45");
+}
+
+#[test]
+fn next_str() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_str();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), "hello");
+}
+
+#[test]
+fn known_str() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let _ = rjiter.finish();
+    let result = rjiter.known_str();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), "hello");
+}
+
+#[test]
+fn next_bytes() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_bytes();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), b"hello");
+}
+
+#[test]
+fn known_bytes() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let _ = rjiter.finish();
+    let result = rjiter.known_bytes();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), b"hello");
+}
+
+#[test]
+fn next_value() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_value();
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
+    );
+}
+
+#[test]
+fn known_value() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let peek = rjiter.peek().unwrap();
+    assert_eq!(peek, Peek::String);
+    let result = rjiter.known_value(peek);
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
+    );
+}
+
+#[test]
+fn next_skip() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello"  42"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_skip();
+    assert!(result.is_ok());
+
+    // To check that skipped, read the next value
+    let result = rjiter.next_number();
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        jiter::NumberAny::Int(jiter::NumberInt::Int(42))
+    );
+}
+
+#[test]
+fn known_skip() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello"  42"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let peek = rjiter.peek().unwrap();
+    assert_eq!(peek, Peek::String);
+    let result = rjiter.known_skip(peek);
+    assert!(result.is_ok());
+
+    // To check that skipped, read the next value
+    let result = rjiter.next_number();
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        jiter::NumberAny::Int(jiter::NumberInt::Int(42))
+    );
+}
+
+#[test]
+fn next_value_owned() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_value_owned();
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
+    );
+}
+
+#[test]
+fn known_value_owned() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}"hello""#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let peek = rjiter.peek().unwrap();
+    assert_eq!(peek, Peek::String);
+    let result = rjiter.known_value_owned(peek);
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        JsonValue::Str(std::borrow::Cow::Borrowed("hello"))
+    );
+}
+
+#[test]
+fn next_array() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}[{lot_of_spaces}false{lot_of_spaces}, 2, 3]"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_array();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some(Peek::False));
+}
+
+#[test]
+fn known_array() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}[{lot_of_spaces}"hello"{lot_of_spaces}, 2, 3]"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let _ = rjiter.finish();
+    let result = rjiter.known_array();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some(Peek::String));
+}
+
+#[test]
+fn array_step() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces},{lot_of_spaces} true{lot_of_spaces}, 3]"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.array_step();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some(Peek::True));
+}
+
+#[test]
+fn next_object() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}{{{lot_of_spaces}"key": "value"}}"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_object();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some("key"));
+}
+
+#[test]
+fn known_object() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}{{{lot_of_spaces}"key": "value"}}"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let _ = rjiter.finish();
+    let result = rjiter.known_object();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some("key"));
+}
+
+#[test]
+fn next_object_bytes() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces}{{{lot_of_spaces}"key": "value"}}"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_object_bytes();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some(&b"key"[..]));
+}
+
+#[test]
+fn next_key_bytes() {
+    let lot_of_spaces = " ".repeat(32);
+    let input = format!(r#"{lot_of_spaces},{lot_of_spaces}"key": "value"}}"#);
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let result = rjiter.next_key_bytes();
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), Some(&b"key"[..]));
+}
+
+#[test]
+fn skip_prologue_bom_and_anti_xssi_prefix() {
+    let input = "\u{FEFF})]}'\n{\"a\": 1}";
+    let mut buffer = [0u8; 8];
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.skip_prologue(b")]}'").unwrap(), 7);
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    // The index counts the skipped bytes
+    assert_eq!(rjiter.current_index(), 15);
+}
+
+#[test]
+fn skip_prologue_is_optional() {
+    let input = r#"  {"a": 1}"#;
+    let mut buffer = [0u8; 16];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.skip_prologue(b")]}'").unwrap(), 0);
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+}
+
+#[test]
+fn next_long_key_longer_than_buffer() {
+    let input = r#"{ "a_very_long_key_name" : 1 , "b\n" : 2 }  {}"#;
+    let mut buffer = [0u8; 10];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let mut key = Vec::new();
+    assert!(rjiter.next_object_long_key(&mut key).unwrap());
+    assert_eq!(key, b"a_very_long_key_name");
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+
+    key.clear();
+    assert!(rjiter.next_long_key(&mut key).unwrap());
+    assert_eq!(key, br"b\n");
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+
+    assert!(!rjiter.next_long_key(&mut key).unwrap());
+    assert!(!rjiter.next_object_long_key(&mut key).unwrap());
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn next_long_key_errors() {
+    let mut buffer = [0u8; 16];
+    let mut key = Vec::new();
+
+    let mut reader = r#"{1: 2}"#.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.next_object_long_key(&mut key).unwrap_err();
+    assert_eq!(err.index, 1);
+
+    let mut reader = r#"{"a" 2}"#.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let err = rjiter.next_object_long_key(&mut key).unwrap_err();
+    assert_eq!(err.index, 5);
+}
+
+#[test]
+fn next_key_decoded_decodes_escapes() {
+    let input = r#"{ "caf\u00e9" : 1 , "b\n\"" : 2, "a_very_long_key_name": 3 }  {}"#;
+    let mut buffer = [0u8; 10];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut key_buffer = [0u8; 32];
+
+    let key = rjiter.next_object_decoded(&mut key_buffer).unwrap();
+    assert_eq!(key, Some("café".as_bytes()));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+
+    let key = rjiter.next_key_decoded(&mut key_buffer).unwrap();
+    assert_eq!(key, Some(&b"b\n\""[..]));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+
+    // Longer than the rjiter buffer
+    let key = rjiter.next_key_decoded(&mut key_buffer).unwrap();
+    assert_eq!(key, Some(&b"a_very_long_key_name"[..]));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(3));
+
+    assert_eq!(rjiter.next_key_decoded(&mut key_buffer).unwrap(), None);
+    assert_eq!(rjiter.next_object_decoded(&mut key_buffer).unwrap(), None);
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn next_key_decoded_longer_than_scratch() {
+    use rjiter::error::ErrorType;
+    let input = r#"{"abcdef": 1}"#;
+    let mut buffer = [0u8; 16];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut key_buffer = [0u8; 4];
+    let err = rjiter.next_object_decoded(&mut key_buffer).unwrap_err();
+    assert_eq!(err.error_type, ErrorType::OutputFull { written: 0 });
+}
+
+#[test]
+fn max_token_len_for_strings_and_keys() {
+    use rjiter::error::ErrorType;
+    let too_long = ErrorType::TokenTooLong { max_len: 5 };
+
+    let input = r#"{"abcde": "12345", "abcdef": 1}"#;
+    let mut buffer = [0u8; 64];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    assert_eq!(rjiter.next_object().unwrap(), Some("abcde"));
+    assert_eq!(rjiter.next_str().unwrap(), "12345");
+    let err = rjiter.next_key().unwrap_err();
+    assert_eq!(err.error_type, too_long);
+
+    let input = r#"[123456]"#;
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    let err = rjiter.next_number_bytes().unwrap_err();
+    assert_eq!(err.error_type, too_long);
+}
+
+#[test]
+fn max_token_len_while_filling_buffer() {
+    use rjiter::error::ErrorType;
+    let too_long = ErrorType::TokenTooLong { max_len: 5 };
+
+    // Reads of a few bytes, the buffer is not filled with the whole token
+    struct ChunkReader<'a> {
+        data: &'a [u8],
+    }
+    impl embedded_io::ErrorType for ChunkReader<'_> {
+        type Error = embedded_io::ErrorKind;
+    }
+    impl embedded_io::Read for ChunkReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let n = buf.len().min(self.data.len()).min(4);
+            let (head, tail) = self.data.split_at(n);
+            buf[..n].copy_from_slice(head);
+            self.data = tail;
+            Ok(n)
+        }
+    }
+
+    let input = br#"["a long string"]"#;
+    let mut reader = ChunkReader { data: input };
+    let mut buffer = [0u8; 64];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(err.error_type, too_long);
+    assert_eq!(err.index, 8);
+    assert_eq!(reader.data.len(), input.len() - 8);
+
+    let mut reader = ChunkReader {
+        data: br#"{"key": 1, "a long key": 1}"#,
+    };
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    assert_eq!(rjiter.next_object().unwrap(), Some("key"));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    let err = rjiter.next_key().unwrap_err();
+    assert_eq!(err.error_type, too_long);
+    assert_eq!(err.index, 20);
+
+    let mut reader = ChunkReader {
+        data: br#"{"a long key": 1}"#,
+    };
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    let err = rjiter.next_object_bytes().unwrap_err();
+    assert_eq!(err.error_type, too_long);
+    assert_eq!(err.index, 8);
+
+    let mut reader = ChunkReader {
+        data: b"[1234567890]",
+    };
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    let peek = rjiter.next_array().unwrap().unwrap();
+    let err = rjiter.known_int(peek).unwrap_err();
+    assert_eq!(err.error_type, too_long);
+    assert_eq!(err.index, 8);
+
+    // The escapes are counted as written
+    let input = br#""\n\n\n""#;
+    let mut reader = input.as_slice();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(5);
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(err.error_type, too_long);
+}
+
+#[test]
+fn max_token_len_ignores_structural_bytes() {
+    let mut buffer = [0u8; 16];
+
+    // Empty containers
+    let mut reader = b"{ } [ ]".as_slice();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(1);
+    assert_eq!(rjiter.next_object().unwrap(), None);
+    assert_eq!(rjiter.next_array().unwrap(), None);
+    rjiter.finish().unwrap();
+
+    // The closing `}` and `]` after the last item
+    let mut reader = br#"{"a": 1} [2 ]"#.as_slice();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(1);
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert_eq!(rjiter.next_key().unwrap(), None);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'2')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+    assert_eq!(rjiter.array_step().unwrap(), None);
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn max_token_len_for_long_strings() {
+    use rjiter::error::ErrorType;
+    let too_long = ErrorType::TokenTooLong { max_len: 20 };
+    let input = r#""a string longer than the buffer and the limit""#;
+
+    // Streaming through a small buffer
+    let mut buffer = [0u8; 10];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(20);
+    let mut writer = Vec::new();
+    let err = rjiter.write_long_str(&mut writer).unwrap_err();
+    assert_eq!(err.error_type, too_long);
+    assert!(writer.len() <= 20);
+
+    // Completed in a large buffer
+    let mut buffer = [0u8; 100];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(20);
+    rjiter.peek().unwrap();
+    let err = rjiter.write_long_bytes(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.error_type, too_long);
+
+    // String reader
+    let mut buffer = [0u8; 10];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(20);
+    let mut string_reader = rjiter.string_reader();
+    let mut out = [0u8; 4];
+    let err = loop {
+        match embedded_io::Read::read(&mut string_reader, &mut out) {
+            Ok(0) => panic!("the limit is not checked"),
+            Ok(_) => (),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(err, embedded_io::ErrorKind::InvalidData);
+    assert_eq!(string_reader.error().unwrap().error_type, too_long);
+
+    // Within the limit
+    let mut buffer = [0u8; 10];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_max_token_len(100);
+    let mut writer = Vec::new();
+    rjiter.write_long_str(&mut writer).unwrap();
+    assert_eq!(writer, &input.as_bytes()[1..input.len() - 1]);
+}
+
+#[test]
+fn limits_for_strings_and_numbers() {
+    use rjiter::error::{ErrorCategory, ErrorType};
+    use rjiter::{Limit, Limits};
+    let limits = Limits {
+        max_string_bytes: Some(5),
+        max_number_bytes: Some(3),
+        ..Limits::default()
+    };
+    let string_limit = ErrorType::LimitExceeded {
+        limit: Limit::StringBytes,
+        max: 5,
+    };
+
+    let input = r#"{"abcde": "12345", "k": 123, "abcdef": 1}"#;
+    let mut buffer = [0u8; 64];
+    let mut reader = input.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    assert_eq!(rjiter.next_object().unwrap(), Some("abcde"));
+    assert_eq!(rjiter.next_str().unwrap(), "12345");
+    assert_eq!(rjiter.next_key().unwrap(), Some("k"));
+    assert_eq!(rjiter.next_number_bytes().unwrap(), b"123");
+    let err = rjiter.next_key().unwrap_err();
+    assert_eq!(err.error_type, string_limit);
+    assert_eq!(err.category(), ErrorCategory::Capacity);
+    assert!(!err.is_retryable());
+
+    let mut reader = "[1234]".as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    rjiter.next_array().unwrap();
+    let err = rjiter.next_number_bytes().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::LimitExceeded {
+            limit: Limit::NumberBytes,
+            max: 3
+        }
+    );
+
+    // The parsing functions check the number too, the whitespace is not counted
+    let mut reader = "[   123, 1234, 12.5, -1234]".as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    rjiter.next_array().unwrap();
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(123));
+    rjiter.array_step().unwrap();
+    let err = rjiter.next_int().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::LimitExceeded {
+            limit: Limit::NumberBytes,
+            max: 3
+        }
+    );
+    rjiter.array_step().unwrap();
+    assert!(rjiter.next_float().is_err());
+    rjiter.array_step().unwrap();
+    assert!(rjiter.next_number().is_err());
+
+    // A long number through a small buffer
+    let mut buffer = [0u8; 4];
+    let mut reader = "12345678".as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    let err = rjiter.write_long_number(&mut Vec::new()).unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::LimitExceeded {
+            limit: Limit::NumberBytes,
+            max: 3
+        }
+    );
+
+    // The token limit is checked first
+    let mut buffer = [0u8; 64];
+    let mut reader = r#""abcdefgh""#.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer)
+        .with_limits(limits)
+        .with_max_token_len(6);
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(err.error_type, ErrorType::TokenTooLong { max_len: 6 });
+}
+
+#[test]
+fn limits_stop_an_endless_string() {
+    use rjiter::error::ErrorType;
+    use rjiter::{Limit, Limits};
+    let limits = Limits {
+        max_string_bytes: Some(1000),
+        ..Limits::default()
+    };
+
+    let mut reader = OneByteReader::new(std::iter::once(b'"').chain(std::iter::repeat(b'x')));
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    let mut writer = Vec::new();
+    let err = rjiter.write_long_bytes(&mut writer).unwrap_err();
+    assert_eq!(
+        err.error_type,
+        ErrorType::LimitExceeded {
+            limit: Limit::StringBytes,
+            max: 1000
+        }
+    );
+    assert!(writer.len() <= 1000);
+}
+
+#[test]
+fn limits_for_total_bytes() {
+    use rjiter::error::ErrorType;
+    use rjiter::{Limit, Limits};
+    let limits = Limits {
+        max_total_bytes: Some(12),
+        ..Limits::default()
+    };
+    let total_limit = ErrorType::LimitExceeded {
+        limit: Limit::TotalBytes,
+        max: 12,
+    };
+
+    // Within the limit
+    let mut reader = OneByteReader::new(r#"[1, "abc"] "#.bytes());
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "abc");
+    assert_eq!(rjiter.array_step().unwrap(), None);
+    rjiter.finish().unwrap();
+
+    // The bytes after the limit are not parsed
+    let mut reader = OneByteReader::new(r#"[1, "abc", 23, 4]"#.bytes());
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "abc");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::new(b'2')));
+    let err = rjiter.next_int().unwrap_err();
+    assert_eq!(err.error_type, total_limit);
+    assert_eq!(err.index, 12);
+    // The error repeats
+    let err = rjiter.next_int().unwrap_err();
+    assert_eq!(err.error_type, total_limit);
+
+    // An endless whitespace
+    let mut reader = OneByteReader::new(std::iter::repeat(b' '));
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_limits(limits);
+    let err = rjiter.peek().unwrap_err();
+    assert_eq!(err.error_type, total_limit);
+
+    // Feed mode: the bytes after the limit are not taken
+    let mut no_reader: &[u8] = &[];
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer)
+        .with_feed_mode()
+        .with_limits(limits);
+    assert_eq!(rjiter.feed(br#"["abc", "defgh"]"#), 12);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "abc");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(err.error_type, total_limit);
+}
+
+#[test]
+fn reset_starts_over_with_new_reader() {
+    let mut first = "{\"a\": [1, 2,\n \"unfinished".as_bytes();
+    let mut second = "\n  \"hello\" 42".as_bytes();
+    let mut third = "\"toolong\"".as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut first, &mut buffer).with_max_token_len(5);
+
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert!(rjiter.next_str().is_err());
+
+    rjiter.reset(&mut second);
+    assert_eq!(rjiter.current_index(), 0);
+    assert!(!rjiter.eof_known());
+    assert_eq!(rjiter.next_str().unwrap(), "hello");
+    assert_eq!(rjiter.current_index(), 10);
+    assert_eq!(rjiter.error_position(10), LinePosition::new(2, 10));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(42));
+    rjiter.finish().unwrap();
+
+    // The settings are kept
+    rjiter.reset(&mut third);
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::TokenTooLong { max_len: 5 }
+    );
+}
+
+#[test]
+fn retry_after_peek_restarts_token() {
+    // The first read ends inside the key. The failed `next_object` advances
+    // the parser, the retry should start at the object again
+    for chunk_len in 2..5 {
+        let mut reader = ChunkReader::with_chunking(br#"{"name": 1}"#, Chunking::Fixed(chunk_len));
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        assert_eq!(rjiter.peek().unwrap(), Peek::Object);
+        assert_eq!(
+            rjiter.next_object().unwrap(),
+            Some("name"),
+            "chunk {chunk_len}"
+        );
+        assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    }
+}
+
+#[test]
+fn allow_inf_nan_across_refills() {
+    let input = "[NaN, Infinity, -Infinity, 1.5, -Infinity]  NaN";
+    for buffer_len in 10..input.len() {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_allow_inf_nan();
+
+        assert_eq!(rjiter.next_array().unwrap(), Some(Peek::NaN));
+        assert!(rjiter.next_float().unwrap().is_nan(), "buffer {buffer_len}");
+        assert_eq!(rjiter.array_step().unwrap(), Some(Peek::Infinity));
+        assert_eq!(rjiter.next_float().unwrap(), f64::INFINITY);
+        assert_eq!(rjiter.array_step().unwrap(), Some(Peek::Minus));
+        assert_eq!(rjiter.next_number_bytes().unwrap(), b"-Infinity");
+        assert_eq!(rjiter.array_step().unwrap(), Some(Peek::new(b'1')));
+        rjiter.next_skip().unwrap();
+        assert_eq!(rjiter.array_step().unwrap(), Some(Peek::Minus));
+        assert_eq!(
+            rjiter.next_value().unwrap(),
+            JsonValue::Float(f64::NEG_INFINITY)
+        );
+        assert_eq!(rjiter.array_step().unwrap(), None);
+        assert!(rjiter.next_float().unwrap().is_nan());
+        rjiter.finish().unwrap();
+    }
+
+    // Without the option, the literals are errors
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::NaN));
+    assert!(rjiter.next_float().is_err());
+}
+
+#[test]
+fn allow_inf_nan_from_builder_is_kept_after_reset() {
+    let mut reader = "Infinity".as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+        .allow_inf_nan()
+        .build();
+    assert_eq!(rjiter.next_float().unwrap(), f64::INFINITY);
+
+    let mut new_reader = "-Infinity".as_bytes();
+    rjiter.reset(&mut new_reader);
+    assert_eq!(rjiter.next_float().unwrap(), f64::NEG_INFINITY);
+}
+
+#[test]
+fn allow_inf_nan_write_long_value() {
+    let input = "[NaN,-Infinity,{\"a\":Infinity},-1.5]";
+    for buffer_len in 10..input.len() {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_allow_inf_nan();
+
+        let mut writer = Vec::new();
+        rjiter.write_long_value(&mut writer).unwrap();
+        assert_eq!(writer, input.as_bytes(), "buffer {buffer_len}");
+    }
+}
+
+const TOLERANT_INPUT: &str = r#"// config
+{
+  "name": "a//b /* c */", /* block
+  comment */
+  "list": [1, 2, /* x */ 3,],
+  "nested": {"k": true, // trailing
+  },
+}
+// end"#;
+
+fn check_tolerant_calls<R: embedded_io::Read>(rjiter: &mut RJiter<'_, R>, context: &str) {
+    assert_eq!(rjiter.next_object().unwrap(), Some("name"), "{context}");
+    assert_eq!(rjiter.next_str().unwrap(), "a//b /* c */", "{context}");
+    assert_eq!(rjiter.next_key().unwrap(), Some("list"), "{context}");
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert!(rjiter.array_step().unwrap().is_some());
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(2));
+    assert_eq!(
+        rjiter.array_step().unwrap(),
+        Some(Peek::new(b'3')),
+        "{context}"
+    );
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(3));
+    assert_eq!(rjiter.array_step().unwrap(), None, "{context}");
+    assert_eq!(rjiter.next_key().unwrap(), Some("nested"), "{context}");
+    assert_eq!(rjiter.next_object().unwrap(), Some("k"), "{context}");
+    assert!(rjiter.next_bool().unwrap());
+    assert_eq!(rjiter.next_key().unwrap(), None, "{context}");
+    assert_eq!(rjiter.next_key().unwrap(), None, "{context}");
+    rjiter.finish().unwrap();
+}
+
+fn check_tolerant_value<R: embedded_io::Read>(rjiter: &mut RJiter<'_, R>, context: &str) {
+    let mut writer = Vec::new();
+    rjiter.write_long_value(&mut writer).unwrap();
+    rjiter.finish().unwrap();
+    assert_eq!(
+        String::from_utf8(writer).unwrap(),
+        r#"{"name":"a//b /* c */","list":[1,2,3],"nested":{"k":true}}"#,
+        "{context}"
+    );
+}
+
+#[test]
+fn tolerant_mode_across_refills() {
+    let input = TOLERANT_INPUT;
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; input.len() + 1];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+    check_tolerant_calls(&mut rjiter, "slice");
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; input.len() + 1];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+    check_tolerant_value(&mut rjiter, "slice");
+
+    for buffer_len in 20..input.len() {
+        let context = format!("buffer {buffer_len}");
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        check_tolerant_calls(&mut rjiter, &context);
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        check_tolerant_value(&mut rjiter, &context);
+    }
+    for seed in 1..20 {
+        let context = format!("seed {seed}");
+        let mut reader =
+            ChunkReader::with_chunking(input.as_bytes(), Chunking::Random { seed, max_len: 7 });
+        let mut buffer = [0u8; 20];
+        let mut rjiter = RJiter::builder(&mut reader, &mut buffer)
+            .tolerant_mode()
+            .build();
+        check_tolerant_calls(&mut rjiter, &context);
+        let mut reader =
+            ChunkReader::with_chunking(input.as_bytes(), Chunking::Random { seed, max_len: 7 });
+        let mut buffer = [0u8; 20];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        check_tolerant_value(&mut rjiter, &context);
+    }
+
+    // Without the mode, the comment is an error
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert!(rjiter.next_object().is_err());
+}
+
+fn check_tolerant_error<R: embedded_io::Read>(rjiter: &mut RJiter<'_, R>, context: &str) {
+    let err = match rjiter.next_array() {
+        Ok(_) => {
+            assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1), "{context}");
+            assert_eq!(rjiter.array_step().unwrap(), Some(Peek::new(b'x')));
+            rjiter.next_int().unwrap_err()
+        }
+        Err(e) => e,
+    };
+    assert_eq!(err.index, 15, "{context}");
+    assert_eq!(err.position(), Some(&LinePosition::new(2, 7)), "{context}");
+}
+
+fn check_tolerant_invalid<R: embedded_io::Read>(
+    rjiter: &mut RJiter<'_, R>,
+    with_value: bool,
+    context: &str,
+) {
+    let result = if with_value {
+        rjiter.write_long_value(&mut Vec::new())
+    } else {
+        rjiter.next_skip()
+    };
+    assert!(result.and_then(|()| rjiter.finish()).is_err(), "{context}");
+}
+
+#[test]
+fn tolerant_mode_keeps_positions_and_errors() {
+    let input = "[1, /* a\n b */ x]";
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; input.len() + 1];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+    check_tolerant_error(&mut rjiter, "slice");
+    for chunk_len in 1..5 {
+        let mut reader = ChunkReader::with_chunking(input.as_bytes(), Chunking::Fixed(chunk_len));
+        let mut buffer = [0u8; 8];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        check_tolerant_error(&mut rjiter, &format!("chunk {chunk_len}"));
+    }
+
+    // A slash which doesn't start a comment, and a comma without a value
+    for input in [
+        "[1 /]",
+        "[1, /",
+        "[,]",
+        "{,}",
+        "[1,,]",
+        r#"{"a":1,,}"#,
+        "1 /",
+        "[1]/",
+        "[/* x",
+    ] {
+        for with_value in [false, true] {
+            let context = format!("{input}, slice, value {with_value}");
+            let mut reader = input.as_bytes();
+            let mut buffer = [0u8; 16];
+            let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+            check_tolerant_invalid(&mut rjiter, with_value, &context);
+
+            let context = format!("{input}, one byte, value {with_value}");
+            let mut reader = OneByteReader::new(input.bytes());
+            let mut buffer = [0u8; 8];
+            let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+            check_tolerant_invalid(&mut rjiter, with_value, &context);
+        }
+    }
+    for input in ["[,]", "{,}"] {
+        let mut reader = input.as_bytes();
+        let mut buffer = [0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        let is_empty = if input == "[,]" {
+            rjiter.next_array().map(|peek| peek.is_none())
+        } else {
+            rjiter.next_object().map(|key| key.is_none())
+        };
+        assert!(!matches!(is_empty, Ok(true)), "{input}");
+    }
+
+    // A trailing comma after a value, the same for any reader
+    for input in [r#"{"a":1,}"#, r#"{"a":1, /* c */ }"#] {
+        let mut reader = input.as_bytes();
+        let mut buffer = [0u8; 32];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        let mut writer = Vec::new();
+        rjiter.write_long_value(&mut writer).unwrap();
+        rjiter.finish().unwrap();
+        assert_eq!(writer, br#"{"a":1}"#, "{input}");
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = [0u8; 8];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_tolerant_mode();
+        let mut writer = Vec::new();
+        rjiter.write_long_value(&mut writer).unwrap();
+        rjiter.finish().unwrap();
+        assert_eq!(writer, br#"{"a":1}"#, "{input}");
+    }
+}
+
+#[test]
+fn index_top_level_values_over_small_buffer() {
+    let values = [
+        r#"{"id": 1, "text": "a string longer than the buffer"}"#,
+        r#"[1, [2, {"x": null}]]"#,
+        r#""top-level string""#,
+        "true",
+        "-12.5e3",
+    ];
+    let input = format!(
+        "{}\n{}\n\n  {} {}\n{}",
+        values[0], values[1], values[2], values[3], values[4]
+    );
+    for buffer_len in 8..16 {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+        let mut ranges = Vec::new();
+        let n_values = rjiter
+            .index_top_level_values(|start, end| ranges.push((start, end)))
+            .unwrap();
+        assert_eq!(n_values, values.len(), "buffer {buffer_len}");
+        let found: Vec<&str> = ranges
+            .iter()
+            .map(|&(start, end)| &input[start..end])
+            .collect();
+        assert_eq!(found, values, "buffer {buffer_len}");
+    }
+}
+
+#[test]
+fn index_top_level_values_reports_values_before_error() {
+    let input = "{\"a\": 1}\n[1, 2\n{\"b\": 2}";
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    let mut ranges = Vec::new();
+    let err = rjiter
+        .index_top_level_values(|start, end| ranges.push((start, end)))
+        .unwrap_err();
+    assert_eq!(ranges, [(0, 8)]);
+    assert_eq!(err.index, 15);
+}
+
+#[test]
+fn buffered_position_of_shifted_out_index() {
+    let input = "[1,\n  22,\n\n 333]";
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    rjiter.next_array().unwrap();
+    let index_of_1 = rjiter.current_index();
+    assert_eq!(
+        rjiter.buffered_position(index_of_1),
+        Some(LinePosition::new(1, 2))
+    );
+    rjiter.next_int().unwrap();
+    rjiter.array_step().unwrap();
+    rjiter.next_int().unwrap();
+    rjiter.array_step().unwrap();
+
+    // `1` is shifted out, `error_position` would be wrong
+    assert_eq!(rjiter.buffered_position(index_of_1), None);
+    let index = rjiter.current_index();
+    assert_eq!(
+        rjiter.buffered_position(index),
+        Some(rjiter.current_position())
+    );
+}
+
+#[test]
+fn current_position_after_shifts() {
+    let input = "[1,\n  22,\n\n 333]\n\"end\"";
+    for buffer_len in 5..input.len() {
+        let mut reader = OneByteReader::new(input.bytes());
+        let mut buffer = vec![0u8; buffer_len];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let context = format!("buffer {buffer_len}");
+
+        assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+        rjiter.next_int().unwrap();
+        assert_eq!(
+            rjiter.current_position(),
+            LinePosition::new(1, 3),
+            "{context}"
+        );
+        rjiter.array_step().unwrap();
+        rjiter.next_int().unwrap();
+        assert_eq!(
+            rjiter.current_position(),
+            LinePosition::new(2, 5),
+            "{context}"
+        );
+        rjiter.array_step().unwrap();
+        rjiter.next_int().unwrap();
+        assert_eq!(
+            rjiter.current_position(),
+            LinePosition::new(4, 5),
+            "{context}"
+        );
+        assert_eq!(rjiter.array_step().unwrap(), None);
+        assert_eq!(rjiter.next_str().unwrap(), "end");
+        // At the end of the input, the position is the one of the last byte
+        assert_eq!(
+            rjiter.current_position(),
+            LinePosition::new(5, 5),
+            "{context}"
+        );
+    }
+}
+
+#[test]
+fn token_longer_than_buffer_is_buffer_full() {
+    let input = format!(r#"["{}"]"#, "a".repeat(20));
+    let mut reader = input.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(err.error_type, rjiter::error::ErrorType::BufferFull);
+    assert_eq!(err.index, 1);
+
+    // A truncated input is still the end of input
+    let mut reader = r#"["aaa"#.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let err = rjiter.next_str().unwrap_err();
+    assert_eq!(
+        err.error_type,
+        rjiter::error::ErrorType::JsonError(rjiter::jiter::JsonErrorType::EofWhileParsingString)
+    );
 }
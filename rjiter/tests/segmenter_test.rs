@@ -0,0 +1,103 @@
+use rjiter::Segmenter;
+
+fn segment(bytes: &[u8]) -> &[u8] {
+    let end = Segmenter::new(bytes).segment_end(usize::MAX);
+    bytes.get(1..end).unwrap_or_default()
+}
+
+#[test]
+fn plain_segment_keeps_the_last_byte() {
+    assert_eq!(segment(b"\"hello"), b"hell");
+    assert_eq!(segment(b"\"hello\""), b"hello");
+}
+
+#[test]
+fn plain_segment_stops_before_backslash() {
+    assert_eq!(segment(br#""hello\nworld"#), b"hello");
+    assert_eq!(segment(br#"xab\u0041"#), b"ab");
+}
+
+#[test]
+fn short_input_has_no_segment() {
+    assert_eq!(Segmenter::new(b"").segment_end(usize::MAX), 0);
+    assert_eq!(Segmenter::new(b"\"").segment_end(usize::MAX), 0);
+    assert_eq!(Segmenter::new(b"\"a").segment_end(usize::MAX), 1);
+}
+
+#[test]
+fn escape_is_one_segment() {
+    let segmenter = Segmenter::new(br#""\nworld"#);
+    assert!(segmenter.is_escape());
+    assert_eq!(segment(br#""\nworld"#), br"\n");
+    assert_eq!(segment(br#""\\\\"#), br"\\");
+    assert_eq!(segment(br#""\u00e9abc"#), br"\u00e9");
+    assert_eq!(segment(br#""\U00E9"#), br"\U00E9");
+}
+
+#[test]
+fn incomplete_escape_has_no_segment() {
+    for input in [&br#""\"#[..], br#""\u"#, br#""\u00e"#] {
+        let end = Segmenter::new(input).segment_end(usize::MAX);
+        assert!(end <= 1, "{input:?}: {end}");
+    }
+}
+
+#[test]
+fn surrogate_pair_is_one_segment() {
+    assert_eq!(segment(br#""\ud83d\ude00abc"#), br"\ud83d\ude00");
+    assert_eq!(segment(br#""\uDBFF\uDFFF"#), br"\uDBFF\uDFFF");
+    // A high surrogate without the low surrogate in the input
+    let end = Segmenter::new(br#""\ud83d\ude0"#).segment_end(usize::MAX);
+    assert!(end <= 1);
+    // Not a high surrogate
+    assert_eq!(segment(br#""\udc00A"#), br"\udc00");
+}
+
+#[test]
+fn segment_does_not_split_code_point() {
+    let input = "\"a\u{e9}\u{4f60}\u{1f600}b".as_bytes();
+    for max_end in 2..input.len() {
+        let end = Segmenter::new(input).segment_end(max_end);
+        assert!(end <= max_end, "{max_end}: {end}");
+        let segment = input.get(1..end).unwrap();
+        assert!(core::str::from_utf8(segment).is_ok(), "{max_end}: {end}");
+    }
+    // The last byte is kept for the closing quote
+    assert_eq!(segment(input), "a\u{e9}\u{4f60}\u{1f600}".as_bytes());
+}
+
+#[test]
+fn invalid_utf8_keeps_the_end() {
+    // More continuation bytes than a code point can have
+    let input = b"\"a\x80\x80\x80\x80\x80b";
+    assert_eq!(Segmenter::new(input).segment_end(6), 6);
+}
+
+#[test]
+fn fit_end_shortens_plain_segment() {
+    let input = "\"ab\u{e9}cd\"".as_bytes();
+    let segmenter = Segmenter::new(input);
+    let end = segmenter.segment_end(usize::MAX);
+    assert_eq!(end, 7);
+    assert_eq!(segmenter.fit_end(end, 10), 7);
+    assert_eq!(segmenter.fit_end(end, 2), 3);
+    // Don't split the two-byte code point
+    assert_eq!(segmenter.fit_end(end, 3), 3);
+    assert_eq!(segmenter.fit_end(end, 4), 5);
+}
+
+#[test]
+fn fit_end_keeps_at_least_one_code_point() {
+    let input = "\"\u{1f600}\u{1f600}\"".as_bytes();
+    let segmenter = Segmenter::new(input);
+    let end = segmenter.segment_end(usize::MAX);
+    assert_eq!(segmenter.fit_end(end, 1), 5);
+    assert_eq!(segmenter.fit_end(end, 0), 5);
+}
+
+#[test]
+fn fit_end_keeps_escape() {
+    let segmenter = Segmenter::new(br#""\ud83d\ude00abc"#);
+    let end = segmenter.segment_end(usize::MAX);
+    assert_eq!(segmenter.fit_end(end, 1), end);
+}
@@ -0,0 +1,66 @@
+// The paths where `RJiter` extends lifetimes: parsing across buffer shifts
+// and refills, and returning borrowed values out of the retry loop.
+// Small inputs, to run under Miri: `cargo +nightly miri test --test soundness_test`
+
+use rjiter::jiter::Peek;
+use rjiter::testing::OneByteReader;
+use rjiter::RJiter;
+
+#[test]
+fn borrowed_values_across_refills() {
+    let input = r#"{"k\n1": "vé1", "key2": [12.5, "bytes\t"]}"#;
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.next_object().unwrap(), Some("k\n1"));
+    assert_eq!(rjiter.next_str().unwrap(), "v\u{e9}1");
+    assert_eq!(rjiter.next_key_bytes().unwrap(), Some(&b"key2"[..]));
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::new(b'1')));
+    assert_eq!(rjiter.next_number_bytes().unwrap(), b"12.5");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.known_bytes().unwrap(), b"bytes\\t");
+    assert_eq!(rjiter.array_step().unwrap(), None);
+    assert_eq!(rjiter.next_key().unwrap(), None);
+    rjiter.finish().unwrap();
+}
+
+#[test]
+fn long_strings_over_small_buffer() {
+    let input = r#"["a\"béc😀 long text", "tail"]"#;
+    let mut reader = OneByteReader::new(input.bytes());
+    let mut buffer = [0u8; 10];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    let mut writer = Vec::new();
+    rjiter.write_long_str(&mut writer).unwrap();
+    assert_eq!(writer, "a\"b\u{e9}c\u{1F600} long text".as_bytes());
+
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    let mut chunks = String::new();
+    loop {
+        let (chunk, is_final) = rjiter.next_str_partial().unwrap();
+        chunks.push_str(chunk);
+        if is_final {
+            break;
+        }
+    }
+    assert_eq!(chunks, "tail");
+}
+
+#[test]
+fn values_from_fed_bytes() {
+    let mut no_reader: &[u8] = &[];
+    let mut buffer = [0u8; 12];
+    let mut rjiter = RJiter::new(&mut no_reader, &mut buffer).with_feed_mode();
+
+    assert_eq!(rjiter.feed(br#"["abc", "de"#), 11);
+    assert_eq!(rjiter.next_array().unwrap(), Some(Peek::String));
+    assert_eq!(rjiter.next_str().unwrap(), "abc");
+    assert_eq!(rjiter.array_step().unwrap(), Some(Peek::String));
+    assert!(rjiter.next_str().is_err());
+    assert_eq!(rjiter.feed(br#"f"]"#), 3);
+    assert_eq!(rjiter.next_str().unwrap(), "def");
+    assert_eq!(rjiter.array_step().unwrap(), None);
+}
@@ -0,0 +1,84 @@
+use embedded_io::Read;
+use rjiter::jiter::Peek;
+use rjiter::testing::{ChunkReader, Chunking, OneByteReader};
+use rjiter::RJiter;
+
+fn read_chunks<R: Read>(reader: &mut R, buf_len: usize) -> Vec<Vec<u8>> {
+    let mut buf = vec![0u8; buf_len];
+    let mut chunks = Vec::new();
+    loop {
+        let n = reader.read(&mut buf).unwrap();
+        if n == 0 {
+            return chunks;
+        }
+        chunks.push(buf[..n].to_vec());
+    }
+}
+
+#[test]
+fn one_byte_reader() {
+    let mut reader = OneByteReader::new(b"abc".iter().copied());
+    assert_eq!(read_chunks(&mut reader, 10), [b"a", b"b", b"c"]);
+}
+
+#[test]
+fn chunk_reader_at_delimiter() {
+    let data = vec![1, 2, 3, 0, 4, 5, 0, 6];
+    let mut reader = ChunkReader::new(&data, 0);
+    assert_eq!(
+        read_chunks(&mut reader, 10),
+        [vec![1, 2, 3], vec![4, 5], vec![6]]
+    );
+
+    // The buffer of `read` is smaller than the chunk
+    let mut reader = ChunkReader::new(&data, 0);
+    assert_eq!(
+        read_chunks(&mut reader, 2),
+        [vec![1, 2], vec![3], vec![4, 5], vec![6]]
+    );
+}
+
+#[test]
+fn chunk_reader_fixed() {
+    let mut reader = ChunkReader::with_chunking(b"abcdefg", Chunking::Fixed(3));
+    assert_eq!(
+        read_chunks(&mut reader, 10),
+        [b"abc".to_vec(), b"def".to_vec(), b"g".to_vec()]
+    );
+}
+
+#[test]
+fn chunk_reader_random_is_reproducible() {
+    let data = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let chunking = Chunking::Random {
+        seed: 42,
+        max_len: 5,
+    };
+    let chunks = read_chunks(&mut ChunkReader::with_chunking(data, chunking), 10);
+
+    assert!(chunks.iter().all(|chunk| (1..=5).contains(&chunk.len())));
+    assert_eq!(chunks.concat(), data);
+    assert_eq!(
+        read_chunks(&mut ChunkReader::with_chunking(data, chunking), 10),
+        chunks
+    );
+}
+
+#[test]
+fn rjiter_with_random_chunks() {
+    let input = r#"{"name": "a long enough string value", "list": [1, 2.5, true, null]}"#;
+    for seed in 0..20 {
+        let mut reader =
+            ChunkReader::with_chunking(input.as_bytes(), Chunking::Random { seed, max_len: 7 });
+        let mut buffer = [0u8; 40];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+
+        assert_eq!(rjiter.next_object().unwrap(), Some("name"));
+        assert_eq!(rjiter.next_str().unwrap(), "a long enough string value");
+        assert_eq!(rjiter.next_key().unwrap(), Some("list"));
+        assert_eq!(rjiter.peek().unwrap(), Peek::Array);
+        rjiter.next_skip().unwrap();
+        assert_eq!(rjiter.next_key().unwrap(), None);
+        rjiter.finish().unwrap();
+    }
+}
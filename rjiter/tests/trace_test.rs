@@ -0,0 +1,144 @@
+#![cfg(feature = "trace")]
+
+use std::cell::RefCell;
+
+use rjiter::jiter::{NumberInt, Peek};
+use rjiter::{Event, RJiter};
+
+thread_local! {
+    static EVENTS: RefCell<Vec<Event>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(event: Event) {
+    EVENTS.with(|events| events.borrow_mut().push(event));
+}
+
+fn take_events() -> Vec<Event> {
+    EVENTS.with(|events| events.take())
+}
+
+#[test]
+fn reads_are_traced() {
+    let input = br#"{"a": 1}"#;
+    let mut reader = &input[..];
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_trace_hook(record);
+    take_events();
+
+    assert_eq!(rjiter.next_object().unwrap(), Some("a"));
+    assert_eq!(rjiter.next_int().unwrap(), NumberInt::Int(1));
+    assert_eq!(rjiter.next_key().unwrap(), None);
+    rjiter.finish().unwrap();
+
+    let events = take_events();
+    assert_eq!(
+        events.first(),
+        Some(&Event::ReadStart { capacity: 16 }),
+        "{events:?}"
+    );
+    assert!(
+        events.contains(&Event::ReadEnd {
+            n_bytes: 8,
+            error: None
+        }),
+        "{events:?}"
+    );
+    assert!(
+        events.contains(&Event::NewJiter { n_bytes: 8 }),
+        "{events:?}"
+    );
+    // Each read is a pair of events
+    let n_starts = events
+        .iter()
+        .filter(|e| matches!(e, Event::ReadStart { .. }))
+        .count();
+    let n_ends = events
+        .iter()
+        .filter(|e| matches!(e, Event::ReadEnd { .. }))
+        .count();
+    assert_eq!(n_starts, n_ends, "{events:?}");
+}
+
+/// A reader which always fails
+struct BrokenReader;
+
+impl embedded_io::ErrorType for BrokenReader {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl embedded_io::Read for BrokenReader {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Err(embedded_io::ErrorKind::BrokenPipe)
+    }
+}
+
+#[test]
+fn failed_read_is_traced() {
+    let mut reader = BrokenReader;
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_trace_hook(record);
+    take_events();
+
+    assert!(rjiter.peek().is_err());
+    let events = take_events();
+    assert_eq!(
+        events.first(),
+        Some(&Event::ReadStart { capacity: 16 }),
+        "{events:?}"
+    );
+    assert_eq!(
+        events.get(1),
+        Some(&Event::ReadEnd {
+            n_bytes: 0,
+            error: Some(embedded_io::ErrorKind::BrokenPipe)
+        }),
+        "{events:?}"
+    );
+}
+
+#[test]
+fn long_string_segments_and_shifts_are_traced() {
+    let input = br#"  "abcdefghijklmnopqrstuvwxyz""#;
+    let mut reader = &input[..];
+    let mut buffer = [0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer).with_trace_hook(record);
+    take_events();
+
+    assert_eq!(rjiter.peek().unwrap(), Peek::String);
+    let mut writer = Vec::new();
+    rjiter.write_long_str(&mut writer).unwrap();
+    assert_eq!(writer, b"abcdefghijklmnopqrstuvwxyz");
+
+    let events = take_events();
+    let segments: Vec<(usize, usize)> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::LongSegment { index, len } => Some((*index, *len)),
+            _ => None,
+        })
+        .collect();
+    assert!(!segments.is_empty(), "{events:?}");
+    // The segments follow each other, starting after the quote
+    let mut expected_index = 3;
+    for (index, len) in &segments {
+        assert_eq!(*index, expected_index, "{segments:?}");
+        expected_index += len;
+    }
+    assert!(
+        events
+            .iter()
+            .any(|e| matches!(e, Event::Shift { n_shifted_out, .. } if *n_shifted_out > 0)),
+        "{events:?}"
+    );
+}
+
+#[test]
+fn no_events_without_hook() {
+    let input = br#"[1, 2]"#;
+    let mut reader = &input[..];
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    take_events();
+    rjiter.next_skip().unwrap();
+    assert!(take_events().is_empty());
+}
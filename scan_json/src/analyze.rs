@@ -0,0 +1,230 @@
+//! Dry-run analysis of triggers: which actions `find_action` returns for sample contexts.
+//!
+//! Useful to unit-test and document a complex trigger table without scanning JSON.
+
+extern crate alloc;
+
+use crate::matcher::{Action, StructuralPseudoname};
+use crate::scan::StructurePosition;
+use crate::stack::ContextIter;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use embedded_io::Read;
+use u8pool::{U8Pool, U8PoolError};
+
+/// A synthetic context for `analyze_triggers`
+#[derive(Debug, Clone, Copy)]
+pub struct SamplePath<'a> {
+    /// The structural event, or `StructuralPseudoname::None` for a key
+    pub structural_pseudoname: StructuralPseudoname,
+    /// The context names from the top to the current one, without `#top`.
+    /// For a key, the last name is the key. Use `#array` for array items.
+    pub path: &'a [&'a str],
+}
+
+impl<'a> SamplePath<'a> {
+    /// A sample for an object key, the last name of `path`
+    #[must_use]
+    pub fn key(path: &'a [&'a str]) -> Self {
+        Self {
+            structural_pseudoname: StructuralPseudoname::None,
+            path,
+        }
+    }
+
+    /// A sample for a structural event (begin of an object or an array, or an atom) in `path`
+    #[must_use]
+    pub fn structural(structural_pseudoname: StructuralPseudoname, path: &'a [&'a str]) -> Self {
+        Self {
+            structural_pseudoname,
+            path,
+        }
+    }
+}
+
+/// The result of `find_action` for one sample
+pub struct TriggerEntry<B, R: Read> {
+    /// The sample as text: the names joined with `/`, starting with `#top`.
+    /// A structural event is added as the last name: `#object`, `#array` or `#atom`.
+    pub path: String,
+    /// The action returned by `find_action`
+    pub action: Option<Action<B, R>>,
+    /// The number of the action: different actions get different numbers,
+    /// in the order of the first appearance in the report
+    pub action_id: Option<usize>,
+}
+
+impl<B, R: Read> core::fmt::Debug for TriggerEntry<B, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TriggerEntry")
+            .field("path", &self.path)
+            .field("action_id", &self.action_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Report of `analyze_triggers`, an entry for each sample, in the order of the samples
+pub struct TriggerReport<B, R: Read> {
+    /// The results for the samples
+    pub entries: Vec<TriggerEntry<B, R>>,
+}
+
+impl<B, R: Read> TriggerReport<B, R> {
+    /// The samples which don't trigger any action
+    pub fn unmatched(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.action.is_none())
+            .map(|entry| entry.path.as_str())
+    }
+
+    /// The samples which trigger `action`
+    pub fn matched_by(&self, action: Action<B, R>) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(move |entry| {
+                entry
+                    .action
+                    .is_some_and(|entry_action| is_same_action(entry_action, action))
+            })
+            .map(|entry| entry.path.as_str())
+    }
+}
+
+impl<B, R: Read> core::fmt::Debug for TriggerReport<B, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TriggerReport")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+/// One line per sample: `path => action #N`, or `path => (none)`
+impl<B, R: Read> core::fmt::Display for TriggerReport<B, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for entry in &self.entries {
+            match entry.action_id {
+                Some(id) => writeln!(f, "{} => action #{id}", entry.path)?,
+                None => writeln!(f, "{} => (none)", entry.path)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Compare the addresses, `core::ptr::fn_addr_eq` needs a newer Rust
+fn is_same_action<B, R: Read>(a: Action<B, R>, b: Action<B, R>) -> bool {
+    a as usize == b as usize
+}
+
+fn sample_to_text(sample: &SamplePath) -> String {
+    let mut text = String::from("#top");
+    for name in sample.path {
+        text.push('/');
+        text.push_str(name);
+    }
+    let structural = match sample.structural_pseudoname {
+        StructuralPseudoname::Object => "/#object",
+        StructuralPseudoname::Array => "/#array",
+        StructuralPseudoname::Atom => "/#atom",
+        StructuralPseudoname::None => "",
+    };
+    text.push_str(structural);
+    text
+}
+
+/// Evaluate `find_action` for synthetic contexts, without running `scan`.
+///
+/// For each sample, the context is built as `scan` would build it,
+/// and `find_action` is called with `baton`. The report tells which samples
+/// trigger which actions.
+///
+/// # Errors
+///
+/// `U8PoolError` if the context of a sample can't be built. It is not expected,
+/// the pool is sized for the sample.
+///
+/// # Example
+///
+/// ```
+/// use scan_json::analyze::{analyze_triggers, SamplePath};
+/// use scan_json::matcher::{iter_match, Action, StructuralPseudoname};
+/// use scan_json::stack::ContextIter;
+/// use scan_json::{RJiter, StreamOp};
+///
+/// fn on_name(_rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+///     StreamOp::None
+/// }
+///
+/// let find_action = |structural_pseudoname: StructuralPseudoname,
+///                    context: ContextIter,
+///                    _baton: ()|
+///  -> Option<Action<(), &[u8]>> {
+///     if iter_match(|| ["name", "user"], structural_pseudoname, context) {
+///         return Some(on_name);
+///     }
+///     None
+/// };
+///
+/// let report = analyze_triggers(
+///     find_action,
+///     (),
+///     &[
+///         SamplePath::key(&["user", "name"]),
+///         SamplePath::key(&["group", "name"]),
+///     ],
+/// )
+/// .unwrap();
+/// assert_eq!(report.matched_by(on_name).collect::<Vec<_>>(), ["#top/user/name"]);
+/// assert_eq!(report.unmatched().collect::<Vec<_>>(), ["#top/group/name"]);
+/// ```
+pub fn analyze_triggers<B: Copy, R: Read>(
+    find_action: impl Fn(StructuralPseudoname, ContextIter, B) -> Option<Action<B, R>>,
+    baton: B,
+    sample_paths: &[SamplePath],
+) -> Result<TriggerReport<B, R>, U8PoolError> {
+    let mut entries: Vec<TriggerEntry<B, R>> = Vec::with_capacity(sample_paths.len());
+    let mut known_actions: Vec<Action<B, R>> = Vec::new();
+
+    for sample in sample_paths {
+        // Room for the descriptors, the associated values with alignment, and the names
+        let n_slices = sample.path.len() + 1;
+        let names_len: usize = sample.path.iter().map(|name| name.len()).sum();
+        let assoc_len = core::mem::size_of::<StructurePosition>() + 8;
+        let mut buffer = vec![0u8; n_slices * (8 + assoc_len) + names_len + 4];
+
+        let mut context = U8Pool::new(&mut buffer, n_slices)?;
+        context.push_assoc(StructurePosition::Top, b"#top")?;
+        for name in sample.path {
+            let position = if *name == "#array" {
+                StructurePosition::ArrayMiddle
+            } else {
+                StructurePosition::ObjectMiddle
+            };
+            context.push_assoc(position, name.as_bytes())?;
+        }
+        let action = find_action(
+            sample.structural_pseudoname,
+            ContextIter::new(&context),
+            baton,
+        );
+
+        let action_id = action.map(|action| {
+            known_actions
+                .iter()
+                .position(|known| is_same_action(*known, action))
+                .unwrap_or_else(|| {
+                    known_actions.push(action);
+                    known_actions.len() - 1
+                })
+        });
+        entries.push(TriggerEntry {
+            path: sample_to_text(sample),
+            action,
+            action_id,
+        });
+    }
+
+    Ok(TriggerReport { entries })
+}
@@ -0,0 +1,109 @@
+//! Accounting of the bytes which actions store, with a limit per top-level value.
+//!
+//! Actions from different places share the pools of a baton. To keep the memory
+//! use deterministic, an action declares the bytes before storing them,
+//! and `scan` stops with `Error::DataBudgetExceeded` when the limit is reached.
+
+use core::cell::Cell;
+
+/// A failed reservation, see `DataBudget::reserve`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BudgetExceeded {
+    /// The limit of the budget
+    pub limit: usize,
+    /// The bytes reserved before the failed reservation
+    pub used: usize,
+    /// The size of the failed reservation
+    pub requested: usize,
+}
+
+/// A limit for the bytes which actions store while scanning a top-level value.
+///
+/// Give the budget to `scan` in `Options::data_budget` and to the actions in the baton.
+/// Before storing data, an action calls `reserve`. If the limit is exceeded,
+/// the action should not store the data, and `scan` returns
+/// `Error::DataBudgetExceeded` after the action, even if the action continues.
+///
+/// After each top-level value, `scan` resets the usage: the actions are expected
+/// to flush or clear their storage by then. If an action frees data earlier, it calls `release`.
+#[derive(Debug)]
+pub struct DataBudget {
+    limit: usize,
+    used: Cell<usize>,
+    exceeded: Cell<Option<BudgetExceeded>>,
+}
+
+impl DataBudget {
+    /// Creates a budget of `limit` bytes
+    #[must_use]
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: Cell::new(0),
+            exceeded: Cell::new(None),
+        }
+    }
+
+    /// Declare that `n_bytes` are going to be stored.
+    ///
+    /// # Errors
+    ///
+    /// If the limit would be exceeded. Nothing is reserved then, and the error
+    /// is remembered for `scan`.
+    pub fn reserve(&self, n_bytes: usize) -> Result<(), BudgetExceeded> {
+        let used = self.used.get();
+        match used.checked_add(n_bytes) {
+            Some(new_used) if new_used <= self.limit => {
+                self.used.set(new_used);
+                Ok(())
+            }
+            _ => {
+                let exceeded = BudgetExceeded {
+                    limit: self.limit,
+                    used,
+                    requested: n_bytes,
+                };
+                if self.exceeded.get().is_none() {
+                    self.exceeded.set(Some(exceeded));
+                }
+                Err(exceeded)
+            }
+        }
+    }
+
+    /// Declare that `n_bytes` are not stored anymore
+    pub fn release(&self, n_bytes: usize) {
+        self.used.set(self.used.get().saturating_sub(n_bytes));
+    }
+
+    /// The limit in bytes
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The reserved bytes
+    #[must_use]
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// The bytes which can be reserved
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.used.get())
+    }
+
+    /// The first failed reservation since the last `reset`
+    #[must_use]
+    pub fn exceeded(&self) -> Option<BudgetExceeded> {
+        self.exceeded.get()
+    }
+
+    /// Forget the reservations and the failed reservation
+    pub fn reset(&self) {
+        self.used.set(0);
+        self.exceeded.set(None);
+    }
+}
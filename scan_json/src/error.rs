@@ -1,7 +1,108 @@
 //! Error types for JSON stream processing.
 
+use crate::stack::ContextIter;
+
+/// The maximum length of the path in `ActionError`
+pub const ACTION_PATH_CAPACITY: usize = 64;
+
+/// The context path of a failed action: the names joined with `/`,
+/// starting with `#top`, for example `#top/user/name`.
+///
+/// If the path is longer than `ACTION_PATH_CAPACITY`, the names
+/// near the top are dropped and the path is marked as truncated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionPath {
+    bytes: [u8; ACTION_PATH_CAPACITY],
+    len: usize,
+    truncated: bool,
+}
+
+impl ActionPath {
+    /// Builds the path from the context, the innermost name is the last one
+    pub(crate) fn from_context(context: ContextIter) -> Self {
+        let mut bytes = [0u8; ACTION_PATH_CAPACITY];
+        // Fill from the end, as the context iterates from the innermost name
+        let mut start = ACTION_PATH_CAPACITY;
+        let mut truncated = false;
+        for name in context {
+            let needed = name.len() + usize::from(start < ACTION_PATH_CAPACITY);
+            let Some(new_start) = start.checked_sub(needed) else {
+                truncated = true;
+                break;
+            };
+            let name_end = new_start + name.len();
+            if let Some(dst) = bytes.get_mut(new_start..name_end) {
+                dst.copy_from_slice(name);
+            }
+            // The separator, if there is a name after this one
+            if let Some(slash) = bytes.get_mut(name_end..start) {
+                slash.fill(b'/');
+            }
+            start = new_start;
+        }
+        bytes.copy_within(start.., 0);
+        Self {
+            bytes,
+            len: ACTION_PATH_CAPACITY - start,
+            truncated,
+        }
+    }
+
+    /// The path as bytes
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.get(..self.len).unwrap_or(&[])
+    }
+
+    /// Whether the names near the top are dropped
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// The maximum length of the key in `Error::InvalidKeyUtf8`
+pub const KEY_BYTES_CAPACITY: usize = 32;
+
+/// The first bytes of an object key, see `Error::InvalidKeyUtf8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBytes {
+    bytes: [u8; KEY_BYTES_CAPACITY],
+    len: usize,
+    truncated: bool,
+}
+
+impl KeyBytes {
+    /// Keeps the first `KEY_BYTES_CAPACITY` bytes of the key
+    pub(crate) fn new(key: &[u8]) -> Self {
+        let mut bytes = [0u8; KEY_BYTES_CAPACITY];
+        let len = key.len().min(KEY_BYTES_CAPACITY);
+        if let (Some(dst), Some(src)) = (bytes.get_mut(..len), key.get(..len)) {
+            dst.copy_from_slice(src);
+        }
+        Self {
+            bytes,
+            len,
+            truncated: key.len() > KEY_BYTES_CAPACITY,
+        }
+    }
+
+    /// The key bytes
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.get(..self.len).unwrap_or(&[])
+    }
+
+    /// Whether the key is longer than `KEY_BYTES_CAPACITY`
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
 /// Error types for the JSON stream processor
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// Error from the underlying `RJiter` JSON parser
     RJiterError(rjiter::Error),
@@ -21,12 +122,18 @@ pub enum Error {
         /// Description of the internal error
         message: &'static str,
     },
-    /// Maximum nesting depth exceeded (current, max)
+    /// Maximum nesting depth exceeded, or the context pool has no space for the key
     MaxNestingExceeded {
         /// The byte position where the error occurred
         position: usize,
         /// The nesting level that exceeded the maximum
         level: usize,
+        /// The bytes needed in the context pool for the key, `#array` or `#top`
+        requested: usize,
+        /// The bytes left in the context pool
+        available: usize,
+        /// The slots left in the context pool, each nesting level takes one
+        free_slots: usize,
     },
     /// Error from user action at position
     ActionError {
@@ -34,7 +141,68 @@ pub enum Error {
         message: &'static str,
         /// The byte position where the error occurred
         position: usize,
+        /// The code from `StreamOp::ErrorWith`, or `None` for other action errors
+        code: Option<u32>,
+        /// The context path where the action was called. For an end-action
+        /// of a key, the key is already removed from the path
+        path: ActionPath,
+    },
+    /// An action returned `StreamOp::ValueIsConsumed`, but didn't consume the value,
+    /// or consumed less or more than the value
+    ValueConsumptionMismatch {
+        /// The byte position when the action was called, at the value or before it
+        value_position: usize,
+        /// The byte position after the action
+        position: usize,
+        /// The context path where the action was called
+        path: ActionPath,
     },
+    /// The actions reserved more bytes than `Options::data_budget` allows
+    DataBudgetExceeded {
+        /// The byte position where the error is detected, after the action
+        position: usize,
+        /// The limit of the budget
+        limit: usize,
+        /// The bytes reserved before the failed reservation
+        used: usize,
+        /// The size of the failed reservation
+        requested: usize,
+    },
+    /// `scan` has executed `Options::max_actions` actions
+    ActionLimitExceeded {
+        /// The byte position where the next action would be called
+        position: usize,
+        /// The limit of the actions
+        limit: usize,
+    },
+    /// More than `Options::max_actions_at_position` begin-actions are called
+    /// one after another at the same position
+    ActionLoop {
+        /// The byte position where the actions are called
+        position: usize,
+        /// The limit of the actions at the same position
+        limit: usize,
+        /// The context path where the next action would be called
+        path: ActionPath,
+    },
+    /// An object key is not valid UTF-8, see `KeyUtf8Policy::Validate`
+    InvalidKeyUtf8 {
+        /// The byte position after the key
+        position: usize,
+        /// The first bytes of the key
+        key: KeyBytes,
+        /// The offset of the first invalid byte in the key
+        valid_up_to: usize,
+    },
+    /// A top-level value is not an object or an array, see `Options::require_container_top`
+    TopLevelAtom {
+        /// The peek token of the value
+        peek: rjiter::jiter::Peek,
+        /// The byte position of the value
+        position: usize,
+    },
+    /// The options can't be used together, see `Options::validate`
+    InvalidOptions(OptionsError),
     /// IO error during processing
     IOError(embedded_io::ErrorKind),
 }
@@ -53,20 +221,201 @@ impl core::fmt::Display for Error {
             Error::InternalError { position, message } => {
                 write!(f, "Internal error at position {position}: {message}")
             }
-            Error::MaxNestingExceeded { position, level } => {
+            Error::MaxNestingExceeded {
+                position,
+                level,
+                requested,
+                available,
+                free_slots,
+            } => {
                 write!(
                     f,
-                    "Max nesting exceeded at position {position} with level {level}"
+                    "Max nesting exceeded at position {position} with level {level}: requested {requested} bytes, available {available} bytes and {free_slots} slots in the context pool"
                 )
             }
-            Error::ActionError { message, position } => {
+            Error::ActionError {
+                message,
+                position,
+                code: None,
+                ..
+            } => {
                 write!(f, "Action error: {message} at position {position}")
             }
+            Error::ActionError {
+                message,
+                position,
+                code: Some(code),
+                ..
+            } => {
+                write!(
+                    f,
+                    "Action error: {message} (code {code}) at position {position}"
+                )
+            }
+            Error::ValueConsumptionMismatch {
+                value_position,
+                position,
+                path,
+            } => write_consumption_mismatch(f, *value_position, *position, path),
+            Error::DataBudgetExceeded {
+                position,
+                limit,
+                used,
+                requested,
+            } => {
+                write!(
+                    f,
+                    "Data budget of {limit} bytes exceeded at position {position}: {used} bytes used, {requested} requested"
+                )
+            }
+            Error::ActionLimitExceeded { position, limit } => {
+                write!(
+                    f,
+                    "Limit of {limit} actions exceeded at position {position}"
+                )
+            }
+            Error::ActionLoop {
+                position,
+                limit,
+                path,
+            } => {
+                let path = core::str::from_utf8(path.as_bytes()).unwrap_or("(not UTF-8)");
+                write!(
+                    f,
+                    "More than {limit} actions at position {position}, the last at {path}: the actions don't advance the parser"
+                )
+            }
+            Error::InvalidKeyUtf8 {
+                position,
+                key,
+                valid_up_to,
+            } => write_invalid_key_utf8(f, *position, key, *valid_up_to),
+            Error::TopLevelAtom { peek, position } => {
+                write!(
+                    f,
+                    "Top-level value at position {position} is {peek:?}, not an object or an array"
+                )
+            }
+            Error::InvalidOptions(err) => write!(f, "Invalid options: {err}"),
             Error::IOError(kind) => write!(f, "IO error: {kind}"),
         }
     }
 }
 
+#[cfg(any(feature = "std", feature = "display"))]
+fn write_consumption_mismatch(
+    f: &mut core::fmt::Formatter<'_>,
+    value_position: usize,
+    position: usize,
+    path: &ActionPath,
+) -> core::fmt::Result {
+    let path = core::str::from_utf8(path.as_bytes()).unwrap_or("(not UTF-8)");
+    if position == value_position {
+        write!(
+            f,
+            "Action at {path} returned ValueIsConsumed, but didn't consume the value at position {value_position}"
+        )
+    } else {
+        write!(
+            f,
+            "Action at {path} returned ValueIsConsumed for the value at position {value_position}, but stopped at position {position}, not at the end of the value"
+        )
+    }
+}
+
+#[cfg(any(feature = "std", feature = "display"))]
+fn write_invalid_key_utf8(
+    f: &mut core::fmt::Formatter<'_>,
+    position: usize,
+    key: &KeyBytes,
+    valid_up_to: usize,
+) -> core::fmt::Result {
+    let ellipsis = if key.is_truncated() { "..." } else { "" };
+    write!(
+        f,
+        "Key \"{}{ellipsis}\" is not valid UTF-8 at byte {valid_up_to}, the key ends at position {position}",
+        key.as_bytes().escape_ascii()
+    )
+}
+
+impl Error {
+    /// Line and column in the input where the error occurred.
+    ///
+    /// Only `RJiterError` carries the line-column position. It is captured
+    /// by `RJiter` when the error is created, therefore it is correct even if
+    /// the input buffer is shifted afterwards, except for the retryable errors,
+    /// see `rjiter::Error::position`.
+    /// For the other errors, see `locate`.
+    #[must_use]
+    pub fn line_position(&self) -> Option<&rjiter::jiter::LinePosition> {
+        match self {
+            Error::RJiterError(err) => err.position(),
+            _ => None,
+        }
+    }
+
+    /// The byte position in the input where the error occurred, `None` for `IOError`
+    /// and `InvalidOptions`
+    #[must_use]
+    pub fn byte_position(&self) -> Option<usize> {
+        match self {
+            Error::RJiterError(err) => Some(err.index),
+            Error::UnbalancedJson(position)
+            | Error::UnhandledPeek { position, .. }
+            | Error::InternalError { position, .. }
+            | Error::MaxNestingExceeded { position, .. }
+            | Error::ActionError { position, .. }
+            | Error::ValueConsumptionMismatch { position, .. }
+            | Error::DataBudgetExceeded { position, .. }
+            | Error::ActionLimitExceeded { position, .. }
+            | Error::ActionLoop { position, .. }
+            | Error::InvalidKeyUtf8 { position, .. }
+            | Error::TopLevelAtom { position, .. } => Some(*position),
+            Error::InvalidOptions(_) | Error::IOError(_) => None,
+        }
+    }
+
+    /// Line and column in the input where the error occurred, for any error with
+    /// a byte position, not only for `RJiterError`.
+    ///
+    /// `RJiterError` has the position captured at creation. For the other errors,
+    /// the position is located in the buffer of `rjiter`: `scan` creates them at
+    /// the current index of the parser, therefore the position is found when
+    /// `locate` is called after `scan` returns, before reading from `rjiter` again.
+    /// If the position is already shifted out of the buffer, the result is `None`.
+    #[must_use]
+    pub fn locate<R: embedded_io::Read>(
+        &self,
+        rjiter: &rjiter::RJiter<R>,
+    ) -> Option<rjiter::jiter::LinePosition> {
+        match self {
+            Error::RJiterError(err) => err
+                .position()
+                .cloned()
+                .or_else(|| rjiter.buffered_position(err.index)),
+            _ => self
+                .byte_position()
+                .and_then(|position| rjiter.buffered_position(position)),
+        }
+    }
+
+    /// Whether the error is transient and a new attempt can succeed on the same input:
+    /// the retryable `RJiter` errors, see `rjiter::error::ErrorType::is_retryable`,
+    /// and the same I/O errors from the actions.
+    ///
+    /// The other errors repeat on the same input.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::RJiterError(err) => err.is_retryable(),
+            Error::IOError(kind) => {
+                rjiter::error::ErrorType::IoError { kind: *kind }.is_retryable()
+            }
+            _ => false,
+        }
+    }
+}
+
 impl From<rjiter::Error> for Error {
     fn from(error: rjiter::Error) -> Self {
         Error::RJiterError(error)
@@ -79,5 +428,48 @@ impl From<embedded_io::ErrorKind> for Error {
     }
 }
 
+/// Invalid `Options`, see `OptionsBuilder::build` and `Options::validate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OptionsError {
+    /// An SSE token is empty, `scan` would skip it forever
+    EmptySseToken,
+    /// More interned keys than can be referenced from the context
+    TooManyInternedKeys {
+        /// The number of the keys
+        count: usize,
+        /// The maximum, `MAX_INTERNED_KEYS`
+        max: usize,
+    },
+    /// A limit is zero, then nothing can be scanned
+    ZeroLimit {
+        /// The name of the option
+        option: &'static str,
+    },
+    /// Two options can't be used together
+    Conflict {
+        /// The name of the option
+        option: &'static str,
+        /// The name of the conflicting option
+        other: &'static str,
+    },
+}
+
+#[cfg(any(feature = "std", feature = "display"))]
+impl core::fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            OptionsError::EmptySseToken => write!(f, "An SSE token is empty"),
+            OptionsError::TooManyInternedKeys { count, max } => {
+                write!(f, "{count} interned keys, at most {max} are allowed")
+            }
+            OptionsError::ZeroLimit { option } => write!(f, "The limit {option} is zero"),
+            OptionsError::Conflict { option, other } => {
+                write!(f, "The option {option} can't be used together with {other}")
+            }
+        }
+    }
+}
+
 /// Type alias for Results with `scan_json` Error
 pub type Result<B> = core::result::Result<B, Error>;
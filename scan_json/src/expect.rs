@@ -0,0 +1,205 @@
+//! Validate the type of values, without writing peek-and-compare code in each action.
+//!
+//! The `expect_*` matchers return a validating action from `find_action`. If the value
+//! has another type, `scan` fails with `Error::ActionError`, which has the position
+//! and the context path of the value. Otherwise the value is processed as usual.
+//!
+//! ```rust
+//! use scan_json::expect::{expect_number, expect_string};
+//! use scan_json::matcher::{Action, StructuralPseudoname};
+//! use scan_json::stack::ContextIter;
+//!
+//! fn find_action<'a>(
+//!     structural_pseudoname: StructuralPseudoname,
+//!     context: ContextIter,
+//!     _baton: (),
+//! ) -> Option<Action<(), &'a [u8]>> {
+//!     expect_string("user/name", structural_pseudoname, context.clone())
+//!         .or_else(|| expect_number("user/age", structural_pseudoname, context))
+//! }
+//! ```
+//!
+//! An action which reads the value itself can call `expect_type` first.
+
+use crate::matcher::{path_match, Action, StreamOp, StructuralPseudoname};
+use crate::stack::ContextIter;
+use embedded_io::Read;
+use rjiter::jiter::Peek;
+use rjiter::RJiter;
+
+/// The type of a JSON value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    /// A string
+    String,
+    /// A number, including `NaN` and `Infinity`
+    Number,
+    /// `true` or `false`
+    Bool,
+    /// `null`
+    Null,
+    /// An object
+    Object,
+    /// An array
+    Array,
+}
+
+impl ValueType {
+    /// The type of the value which starts with the peeked token,
+    /// `None` if the token can't start a value
+    #[must_use]
+    pub fn of(peek: Peek) -> Option<Self> {
+        match peek {
+            Peek::String => Some(ValueType::String),
+            Peek::True | Peek::False => Some(ValueType::Bool),
+            Peek::Null => Some(ValueType::Null),
+            Peek::Object => Some(ValueType::Object),
+            Peek::Array => Some(ValueType::Array),
+            peek if peek.is_num() => Some(ValueType::Number),
+            _ => None,
+        }
+    }
+
+    /// The error message of `expect_type`
+    #[must_use]
+    pub fn error_message(self) -> &'static str {
+        match self {
+            ValueType::String => "Expected a string",
+            ValueType::Number => "Expected a number",
+            ValueType::Bool => "Expected a boolean",
+            ValueType::Null => "Expected null",
+            ValueType::Object => "Expected an object",
+            ValueType::Array => "Expected an array",
+        }
+    }
+}
+
+/// Peek the next value and check its type. To use in an action before reading the value.
+///
+/// # Errors
+///
+/// The `StreamOp` for the action to return:
+///
+/// - `StreamOp::Error` with `ValueType::error_message` if the value has another type
+/// - `StreamOp::None` if the value is malformed, then `scan` reports the parsing error
+pub fn expect_type<R: Read>(rjiter: &mut RJiter<R>, expected: ValueType) -> Result<Peek, StreamOp> {
+    let Ok(peek) = rjiter.peek() else {
+        return Err(StreamOp::None);
+    };
+    match ValueType::of(peek) {
+        Some(value_type) if value_type == expected => Ok(peek),
+        Some(_) => Err(StreamOp::Error(expected.error_message())),
+        None => Err(StreamOp::None),
+    }
+}
+
+fn check<R: Read>(rjiter: &mut RJiter<R>, expected: ValueType) -> StreamOp {
+    match expect_type(rjiter, expected) {
+        Ok(_) => StreamOp::None,
+        Err(op) => op,
+    }
+}
+
+fn check_string<B, R: Read>(rjiter: &mut RJiter<R>, _baton: B) -> StreamOp {
+    check(rjiter, ValueType::String)
+}
+
+fn check_number<B, R: Read>(rjiter: &mut RJiter<R>, _baton: B) -> StreamOp {
+    check(rjiter, ValueType::Number)
+}
+
+fn check_bool<B, R: Read>(rjiter: &mut RJiter<R>, _baton: B) -> StreamOp {
+    check(rjiter, ValueType::Bool)
+}
+
+fn check_null<B, R: Read>(rjiter: &mut RJiter<R>, _baton: B) -> StreamOp {
+    check(rjiter, ValueType::Null)
+}
+
+fn check_object<B, R: Read>(rjiter: &mut RJiter<R>, _baton: B) -> StreamOp {
+    check(rjiter, ValueType::Object)
+}
+
+fn check_array<B, R: Read>(rjiter: &mut RJiter<R>, _baton: B) -> StreamOp {
+    check(rjiter, ValueType::Array)
+}
+
+// The structural events of objects and arrays come after the opening bracket,
+// the type of the value is not visible anymore
+fn expect_at<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    action: Action<B, R>,
+) -> Option<Action<B, R>> {
+    match structural_pseudoname {
+        StructuralPseudoname::Object | StructuralPseudoname::Array => None,
+        StructuralPseudoname::Atom | StructuralPseudoname::None => {
+            path_match(pattern, structural_pseudoname, context).then_some(action)
+        }
+    }
+}
+
+/// If the context matches the pattern (see `path_match`), return an action which
+/// fails if the value is not a string.
+///
+/// The pattern should match a key, for example, `user/name`, or an atom in an array
+/// or at the top level, for example, `tags/#array/#atom`.
+#[must_use]
+pub fn expect_string<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+) -> Option<Action<B, R>> {
+    expect_at(pattern, structural_pseudoname, context, check_string)
+}
+
+/// As `expect_string`, for a number
+#[must_use]
+pub fn expect_number<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+) -> Option<Action<B, R>> {
+    expect_at(pattern, structural_pseudoname, context, check_number)
+}
+
+/// As `expect_string`, for `true` or `false`
+#[must_use]
+pub fn expect_bool<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+) -> Option<Action<B, R>> {
+    expect_at(pattern, structural_pseudoname, context, check_bool)
+}
+
+/// As `expect_string`, for `null`
+#[must_use]
+pub fn expect_null<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+) -> Option<Action<B, R>> {
+    expect_at(pattern, structural_pseudoname, context, check_null)
+}
+
+/// As `expect_string`, for an object. The pattern should match a key.
+#[must_use]
+pub fn expect_object<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+) -> Option<Action<B, R>> {
+    expect_at(pattern, structural_pseudoname, context, check_object)
+}
+
+/// As `expect_string`, for an array. The pattern should match a key.
+#[must_use]
+pub fn expect_array<B, R: Read>(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+) -> Option<Action<B, R>> {
+    expect_at(pattern, structural_pseudoname, context, check_array)
+}
@@ -0,0 +1,285 @@
+//! Extract the scalar values of several paths in one pass, without writing actions.
+//!
+//! Useful to grab a few fields from each record of a stream.
+
+use crate::error::Error as ScanError;
+use crate::matcher::{iter_match, Action, EndAction, StreamOp, StructuralPseudoname};
+use crate::preview::{push_str_preview, Preview};
+use crate::scan::{scan, Options};
+use crate::stack::ContextIter;
+use crate::{RJiter, Result as ScanResult};
+use core::cell::RefCell;
+use embedded_io::Read;
+use rjiter::jiter::Peek;
+use u8pool::U8Pool;
+
+/// The maximum number of paths for `extract_many`
+pub const MAX_EXTRACT_PATHS: usize = 64;
+
+/// The names from the top to the value, without `#top`. Use `#array` for array items.
+///
+/// For example, `&["user", "name"]` is the key `name` of the object `user`,
+/// and `&["tags", "#array"]` is an item of the array `tags`.
+pub type Path<'a> = &'a [&'a str];
+
+/// Which paths `extract_many` has found, and where their values are in the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractReport {
+    found: u64,
+    // Index of the value in the pool, for each found path
+    slots: [usize; MAX_EXTRACT_PATHS],
+}
+
+impl ExtractReport {
+    fn new() -> Self {
+        Self {
+            found: 0,
+            slots: [0; MAX_EXTRACT_PATHS],
+        }
+    }
+
+    /// Whether the value of the path with the index `path_index` is found
+    #[must_use]
+    pub fn is_found(&self, path_index: usize) -> bool {
+        path_index < MAX_EXTRACT_PATHS && self.found & (1 << path_index) != 0
+    }
+
+    /// The number of found paths
+    #[must_use]
+    pub fn found_count(&self) -> usize {
+        self.found.count_ones() as usize
+    }
+
+    /// Returns the value of the path with the index `path_index` from the pool
+    /// given to `extract_many`, or `None` if the value is not found.
+    #[must_use]
+    pub fn value<'p>(&self, pool: &'p U8Pool, path_index: usize) -> Option<&'p [u8]> {
+        if !self.is_found(path_index) {
+            return None;
+        }
+        let slot = self.slots.get(path_index)?;
+        pool.get(*slot)
+    }
+}
+
+struct ExtractState<'a, 'p, 'v> {
+    paths: &'a [Path<'a>],
+    values: &'p mut U8Pool<'v>,
+    report: ExtractReport,
+    // The path whose value the action should extract
+    current: usize,
+    rjiter_error: Option<rjiter::Error>,
+    preview: Option<Preview>,
+}
+
+impl ExtractState<'_, '_, '_> {
+    // The last value in the pool is the value of the current path
+    fn set_found(&mut self) -> StreamOp {
+        let current = self.current;
+        let slot = self.values.len() - 1;
+        if let Some(dst) = self.report.slots.get_mut(current) {
+            *dst = slot;
+        }
+        self.report.found |= 1 << current;
+        StreamOp::ValueIsConsumed
+    }
+}
+
+type ExtractBaton<'s, 'a, 'p, 'v> = &'s RefCell<ExtractState<'a, 'p, 'v>>;
+
+fn on_value<R: Read>(rjiter: &mut RJiter<R>, baton: ExtractBaton) -> StreamOp {
+    let mut state = baton.borrow_mut();
+
+    let peeked = match rjiter.peek() {
+        Ok(peeked) => peeked,
+        Err(e) => {
+            state.rjiter_error = Some(e);
+            return StreamOp::Error("Failed to peek the value");
+        }
+    };
+    // Not a scalar: look for the next occurrence of the path
+    if peeked == Peek::Object || peeked == Peek::Array {
+        return StreamOp::None;
+    }
+
+    let state = &mut *state;
+    if let (Peek::String, Some(preview)) = (peeked, state.preview) {
+        return match push_str_preview(rjiter, state.values, &preview) {
+            Ok(_) => state.set_found(),
+            Err(ScanError::RJiterError(e)) => {
+                state.rjiter_error = Some(e);
+                StreamOp::Error("Failed to read the value")
+            }
+            Err(_) => StreamOp::Error("No space for the value in the pool"),
+        };
+    }
+    let pushed = match peeked {
+        Peek::String => rjiter.next_str().map(|s| state.values.push(s.as_bytes())),
+        Peek::True | Peek::False => rjiter.next_bool().map(|b| {
+            let literal: &[u8] = if b { b"true" } else { b"false" };
+            state.values.push(literal)
+        }),
+        Peek::Null => rjiter.next_null().map(|()| state.values.push(b"null")),
+        _ => rjiter
+            .next_number_bytes()
+            .map(|bytes| state.values.push(bytes)),
+    };
+    match pushed {
+        Err(e) => {
+            state.rjiter_error = Some(e);
+            StreamOp::Error("Failed to read the value")
+        }
+        Ok(Err(_)) => StreamOp::Error("No space for the value in the pool"),
+        Ok(Ok(_)) => state.set_found(),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn find_action<'s, 'a, 'p, 'v, R: Read>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    baton: ExtractBaton<'s, 'a, 'p, 'v>,
+) -> Option<Action<ExtractBaton<'s, 'a, 'p, 'v>, R>> {
+    // Values are keys' values and array items (or a top-level atom)
+    if structural_pseudoname != StructuralPseudoname::None
+        && structural_pseudoname != StructuralPseudoname::Atom
+    {
+        return None;
+    }
+    let mut state = baton.borrow_mut();
+    let found = state.report.found;
+    let path_index = state.paths.iter().enumerate().position(|(i, path)| {
+        found & (1 << i) == 0
+            && iter_match(
+                || path.iter().rev().copied().chain(core::iter::once("#top")),
+                StructuralPseudoname::None,
+                context.clone(),
+            )
+    })?;
+    state.current = path_index;
+    Some(on_value)
+}
+
+fn find_end_action<B>(
+    _structural_pseudoname: StructuralPseudoname,
+    _context: ContextIter,
+    _baton: B,
+) -> Option<EndAction<B>> {
+    None
+}
+
+/// Extract the first scalar value of each path from the next top-level value.
+///
+/// The values are pushed to `values`, strings are decoded, other scalars are
+/// kept as JSON text (`42`, `true`, `null`). If a path is an object or an array,
+/// the later occurrences of the path are checked. The report tells which paths
+/// are found and gives their values. `values` is not cleared, the caller can
+/// clear it between records.
+///
+/// As with keys, a string value should fit into the `RJiter` buffer.
+///
+/// # Arguments
+///
+/// * `paths` - The paths to extract, at most `MAX_EXTRACT_PATHS`
+/// * `rjiter` - Mutable reference to the JSON iterator
+/// * `working_buffer` - Working buffer for context stack (see [`crate::scan()`] for details)
+/// * `values` - Pool for the extracted values
+///
+/// # Errors
+///
+/// If `scan` fails (malformed json, nesting too deep, etc), return `scan`'s error.
+/// Also, if there are too many paths, or `values` has no space for a value.
+///
+/// # Example
+///
+/// ```
+/// use scan_json::extract::extract_many;
+/// use scan_json::RJiter;
+/// use u8pool::U8Pool;
+///
+/// let json = r#"{"id": 7, "user": {"name": "Ann", "tags": ["a", "b"]}}"#;
+/// let mut reader = json.as_bytes();
+/// let mut buffer = [0u8; 64];
+/// let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+/// let mut context_buffer = [0u8; 512];
+/// let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+/// let mut values_buffer = [0u8; 256];
+/// let mut values = U8Pool::new(&mut values_buffer, 8).unwrap();
+///
+/// let paths: &[&[&str]] = &[&["user", "name"], &["id"], &["user", "tags", "#array"], &["age"]];
+/// let report = extract_many(paths, &mut rjiter, &mut context, &mut values).unwrap();
+///
+/// assert_eq!(report.value(&values, 0), Some(&b"Ann"[..]));
+/// assert_eq!(report.value(&values, 1), Some(&b"7"[..]));
+/// assert_eq!(report.value(&values, 2), Some(&b"a"[..]));
+/// assert!(!report.is_found(3));
+/// ```
+pub fn extract_many<R: Read>(
+    paths: &[Path],
+    rjiter: &mut RJiter<R>,
+    working_buffer: &mut U8Pool,
+    values: &mut U8Pool,
+) -> ScanResult<ExtractReport> {
+    extract_values(paths, rjiter, working_buffer, values, None)
+}
+
+/// Like `extract_many`, but string values are truncated as `push_str_preview` does.
+///
+/// A string value can be longer than the `RJiter` buffer, only its beginning is stored.
+///
+/// # Errors
+///
+/// The same as for `extract_many`.
+pub fn extract_many_preview<R: Read>(
+    paths: &[Path],
+    rjiter: &mut RJiter<R>,
+    working_buffer: &mut U8Pool,
+    values: &mut U8Pool,
+    preview: &Preview,
+) -> ScanResult<ExtractReport> {
+    extract_values(paths, rjiter, working_buffer, values, Some(*preview))
+}
+
+fn extract_values<R: Read>(
+    paths: &[Path],
+    rjiter: &mut RJiter<R>,
+    working_buffer: &mut U8Pool,
+    values: &mut U8Pool,
+    preview: Option<Preview>,
+) -> ScanResult<ExtractReport> {
+    if paths.len() > MAX_EXTRACT_PATHS {
+        return Err(ScanError::InternalError {
+            position: rjiter.current_index(),
+            message: "Too many paths for extract_many",
+        });
+    }
+    let state = RefCell::new(ExtractState {
+        paths,
+        values,
+        report: ExtractReport::new(),
+        current: 0,
+        rjiter_error: None,
+        preview,
+    });
+
+    let scan_result = scan(
+        find_action,
+        find_end_action,
+        rjiter,
+        &state,
+        working_buffer,
+        &Options {
+            stop_early: true,
+            ..Options::new()
+        },
+    );
+
+    let state = state.into_inner();
+    if let Err(scan_error) = scan_result {
+        if let Some(rjiter_error) = state.rjiter_error {
+            return Err(ScanError::RJiterError(rjiter_error));
+        }
+        return Err(scan_error);
+    }
+    Ok(state.report)
+}
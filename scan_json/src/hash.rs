@@ -0,0 +1,88 @@
+//! Feed JSON values to a hasher without materializing them.
+//!
+//! Useful to deduplicate or to detect changes of specific fields in a stream.
+
+use crate::{idtransform, RJiter, Result as ScanResult};
+use core::convert::Infallible;
+use embedded_io::{ErrorType, Read, Write};
+use u8pool::U8Pool;
+
+/// A consumer of the bytes of a JSON value, for example, a SHA-256 implementation.
+pub trait ValueHasher {
+    /// Add the bytes to the hash state.
+    fn update(&mut self, bytes: &[u8]);
+}
+
+// Adapter to use a hasher as the output of `idtransform`
+struct HashWriter<'a, H: ValueHasher> {
+    hasher: &'a mut H,
+}
+
+impl<H: ValueHasher> ErrorType for HashWriter<'_, H> {
+    type Error = Infallible;
+}
+
+impl<H: ValueHasher> Write for HashWriter<'_, H> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Stream the current JSON value into the hasher and consume the value.
+///
+/// The hasher receives the same bytes as [`crate::idtransform()`] produces: the value
+/// with the whitespace collapsed. Therefore the formatting of the input does not
+/// affect the hash, but the order of keys and the escapes in strings do.
+///
+/// Intended to be called from an action, which then returns `StreamOp::ValueIsConsumed`:
+///
+/// ```rust
+/// use scan_json::hash::{hash_value, ValueHasher};
+/// use scan_json::{RJiter, StreamOp};
+/// use std::cell::RefCell;
+/// use u8pool::U8Pool;
+///
+/// struct Fnv(u64);
+///
+/// impl ValueHasher for Fnv {
+///     fn update(&mut self, bytes: &[u8]) {
+///         for b in bytes {
+///             self.0 = (self.0 ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3);
+///         }
+///     }
+/// }
+///
+/// fn on_payload(rjiter: &mut RJiter<&[u8]>, hasher: &RefCell<Fnv>) -> StreamOp {
+///     let mut buffer = [0u8; 512];
+///     let Ok(mut working_buffer) = U8Pool::new(&mut buffer, 32) else {
+///         return StreamOp::Error("Failed to create the working buffer");
+///     };
+///     match hash_value(rjiter, &mut *hasher.borrow_mut(), &mut working_buffer) {
+///         Ok(()) => StreamOp::ValueIsConsumed,
+///         Err(_e) => StreamOp::Error("Failed to hash the value"),
+///     }
+/// }
+/// ```
+///
+/// # Arguments
+///
+/// * `rjiter` - Mutable reference to the JSON iterator, positioned before the value
+/// * `hasher` - The consumer of the value bytes
+/// * `working_buffer` - Working buffer for context stack (see [`crate::scan()`] for details)
+///
+/// # Errors
+///
+/// If the value is malformed or nested too deep, return the error of `idtransform`.
+pub fn hash_value<R: Read, H: ValueHasher>(
+    rjiter: &mut RJiter<R>,
+    hasher: &mut H,
+    working_buffer: &mut U8Pool,
+) -> ScanResult<()> {
+    let mut writer = HashWriter { hasher };
+    idtransform(rjiter, &mut writer, working_buffer)
+}
@@ -27,6 +27,7 @@
 //   to some unknown point in the future.
 //
 use crate::matcher::StructuralPseudoname;
+use crate::scan::Sink;
 use crate::stack::ContextIter;
 use crate::StreamOp;
 use crate::{
@@ -50,7 +51,13 @@ macro_rules! write_and_store_error {
 }
 
 /// Type alias for the baton type used in idtransform
-type IdtBaton<'a, 'workbuf, W> = &'a RefCell<IdTransform<'a, 'workbuf, W>>;
+type IdtBaton<'a, 'workbuf, 's, W> = &'a RefCell<IdTransform<'a, 'workbuf, 's, W>>;
+
+/// Raw JSON text which `idtransform_with_substitution` writes instead of a value
+pub type Replacement<'a> = &'a [u8];
+
+/// The substitution callback of `idtransform_with_substitution`
+type Substitute<'s> = dyn FnMut(ContextIter, StructuralPseudoname) -> Option<Replacement<'s>> + 's;
 
 /// Copy a JSON atom (string, number, boolean, or null) from the input to the output.
 /// Advances the input iterator to the next token.
@@ -122,7 +129,7 @@ enum IdtSequencePos<'a> {
 }
 
 // Main transformer structure that maintains the state of the transformation process.
-struct IdTransform<'a, 'workbuf, W: Write> {
+struct IdTransform<'a, 'workbuf, 's, W: Write> {
     writer: &'a mut W,
     // `seqpos`+`is_top_level` could be the own type `IdtFromMatcherToHandler`
     seqpos: IdtSequencePos<'workbuf>,
@@ -130,11 +137,14 @@ struct IdTransform<'a, 'workbuf, W: Write> {
     io_error: Option<embedded_io::ErrorKind>,
     rjiter_error: Option<rjiter::Error>,
     scan_error: Option<ScanError>,
+    // Substitution: the callback and the replacement from the matcher to the handler
+    substitute: Option<&'a mut Substitute<'s>>,
+    replacement: Option<Replacement<'s>>,
 }
 
 #[allow(clippy::elidable_lifetime_names)]
-impl<'a, 'workbuf, W: Write> IdTransform<'a, 'workbuf, W> {
-    fn new(writer: &'a mut W) -> Self {
+impl<'a, 'workbuf, 's, W: Write> IdTransform<'a, 'workbuf, 's, W> {
+    fn new(writer: &'a mut W, substitute: Option<&'a mut Substitute<'s>>) -> Self {
         Self {
             writer,
             seqpos: IdtSequencePos::AtBeginning,
@@ -142,9 +152,24 @@ impl<'a, 'workbuf, W: Write> IdTransform<'a, 'workbuf, W> {
             io_error: None,
             rjiter_error: None,
             scan_error: None,
+            substitute,
+            replacement: None,
         }
     }
 
+    // Ask the substitution callback for a replacement of the value
+    fn find_replacement(
+        &mut self,
+        structural_pseudoname: StructuralPseudoname,
+        context: ContextIter,
+    ) -> bool {
+        self.replacement = self
+            .substitute
+            .as_mut()
+            .and_then(|substitute| substitute(context, structural_pseudoname));
+        self.replacement.is_some()
+    }
+
     fn get_writer_mut(&mut self) -> &mut W {
         self.writer
     }
@@ -186,12 +211,19 @@ impl<'a, 'workbuf, W: Write> IdTransform<'a, 'workbuf, W> {
 
 // ---------------- Matchers
 
-fn find_action<'a, 'workbuf, R: Read, W: Write>(
+fn find_action<'a, 'workbuf, 's, R: Read, W: Write>(
     structural_pseudoname: StructuralPseudoname,
     mut context: ContextIter,
-    baton: IdtBaton<'a, 'workbuf, W>,
-) -> Option<Action<IdtBaton<'a, 'workbuf, W>, R>> {
+    baton: IdtBaton<'a, 'workbuf, 's, W>,
+) -> Option<Action<IdtBaton<'a, 'workbuf, 's, W>, R>> {
     let context_count = context.len();
+    if structural_pseudoname != StructuralPseudoname::None {
+        let mut idt = baton.borrow_mut();
+        if idt.find_replacement(structural_pseudoname, context.clone()) {
+            idt.is_top_level = context_count < 2;
+            return Some(on_replacement);
+        }
+    }
     match structural_pseudoname {
         StructuralPseudoname::Atom => {
             // Handle context for is_top_level
@@ -234,11 +266,11 @@ fn find_action<'a, 'workbuf, R: Read, W: Write>(
     }
 }
 
-fn find_end_action<'a, 'workbuf, W: Write>(
+fn find_end_action<'a, 'workbuf, 's, W: Write>(
     structural_pseudoname: StructuralPseudoname,
     _context: ContextIter,
-    _baton: IdtBaton<'a, 'workbuf, W>,
-) -> Option<EndAction<IdtBaton<'a, 'workbuf, W>>> {
+    _baton: IdtBaton<'a, 'workbuf, 's, W>,
+) -> Option<EndAction<IdtBaton<'a, 'workbuf, 's, W>>> {
     match structural_pseudoname {
         StructuralPseudoname::Object => Some(on_object_end),
         StructuralPseudoname::Array => Some(on_array_end),
@@ -250,7 +282,7 @@ fn find_end_action<'a, 'workbuf, W: Write>(
 
 fn on_key<R: Read, W: Write>(
     _rjiter: &mut RJiter<R>,
-    idt_cell: &RefCell<IdTransform<'_, '_, W>>,
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
 ) -> StreamOp {
     let mut idt = idt_cell.borrow_mut();
 
@@ -264,7 +296,7 @@ fn on_key<R: Read, W: Write>(
 
 fn on_atom<R: Read, W: Write>(
     rjiter: &mut RJiter<R>,
-    idt_cell: &RefCell<IdTransform<'_, '_, W>>,
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
 ) -> StreamOp {
     let mut idt = idt_cell.borrow_mut();
 
@@ -307,8 +339,32 @@ fn on_atom<R: Read, W: Write>(
     }
 }
 
+fn on_replacement<R: Read, W: Write>(
+    rjiter: &mut RJiter<R>,
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
+) -> StreamOp {
+    let mut idt = idt_cell.borrow_mut();
+
+    if let Err(message) = idt.write_seqpos() {
+        return StreamOp::Error(message);
+    }
+    // The replaced value can be longer than the buffer
+    if let Err(e) = rjiter.write_long_value(&mut Sink) {
+        if let rjiter::error::ErrorType::IoError { kind } = e.error_type {
+            idt.io_error = Some(kind);
+        }
+        idt.rjiter_error = Some(e);
+        return StreamOp::Error("RJiter error (stored in idt)");
+    }
+    let replacement = idt.replacement.take().unwrap_or_default();
+    if let Err(message) = write_and_store_error!(idt, replacement, "IO error writing replacement") {
+        return StreamOp::Error(message);
+    }
+    StreamOp::ValueIsConsumed
+}
+
 // "Struct" means "array" or "object"
-fn on_struct<W: Write>(bytes: &[u8], idt_cell: &RefCell<IdTransform<'_, '_, W>>) -> StreamOp {
+fn on_struct<W: Write>(bytes: &[u8], idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>) -> StreamOp {
     let mut idt = idt_cell.borrow_mut();
 
     if let Err(message) = idt.write_seqpos() {
@@ -324,7 +380,7 @@ fn on_struct<W: Write>(bytes: &[u8], idt_cell: &RefCell<IdTransform<'_, '_, W>>)
 
 fn on_struct_end<W: Write>(
     bytes: &[u8],
-    idt_cell: &RefCell<IdTransform<'_, '_, W>>,
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
 ) -> Result<(), &'static str> {
     let mut idt = idt_cell.borrow_mut();
     idt.seqpos = IdtSequencePos::InMiddle;
@@ -334,23 +390,27 @@ fn on_struct_end<W: Write>(
 
 fn on_array<R: Read, W: Write>(
     _rjiter: &mut RJiter<R>,
-    idt_cell: &RefCell<IdTransform<'_, '_, W>>,
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
 ) -> StreamOp {
     on_struct(b"[", idt_cell)
 }
 
-fn on_array_end<W: Write>(idt_cell: &RefCell<IdTransform<'_, '_, W>>) -> Result<(), &'static str> {
+fn on_array_end<W: Write>(
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
+) -> Result<(), &'static str> {
     on_struct_end(b"]", idt_cell)
 }
 
 fn on_object<R: Read, W: Write>(
     _rjiter: &mut RJiter<R>,
-    idt_cell: &RefCell<IdTransform<'_, '_, W>>,
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
 ) -> StreamOp {
     on_struct(b"{", idt_cell)
 }
 
-fn on_object_end<W: Write>(idt_cell: &RefCell<IdTransform<'_, '_, W>>) -> Result<(), &'static str> {
+fn on_object_end<W: Write>(
+    idt_cell: &RefCell<IdTransform<'_, '_, '_, W>>,
+) -> Result<(), &'static str> {
     on_struct_end(b"}", idt_cell)
 }
 
@@ -377,7 +437,72 @@ pub fn idtransform<R: Read, W: Write>(
     writer: &mut W,
     working_buffer: &mut U8Pool,
 ) -> ScanResult<()> {
-    let idt = IdTransform::new(writer);
+    run_idtransform(rjiter, writer, working_buffer, None)
+}
+
+/// Like `idtransform`, but the callback `substitute` can replace values.
+///
+/// The callback is called for each value (an atom, an object or an array) with the context,
+/// as for `find_action`, and the kind of the value. If the callback returns a replacement,
+/// the value is skipped in the input and the replacement is written to the output as is.
+/// The replacement should be valid JSON text, it is not checked.
+///
+/// # Arguments
+///
+/// * `rjiter` - Mutable reference to the JSON iterator
+/// * `writer` - Output writer for the transformed JSON
+/// * `working_buffer` - Working buffer for context stack (see [`crate::scan()`] for details)
+/// * `substitute` - Callback which returns the raw JSON text for a value, or `None` to copy the value
+///
+/// # Errors
+///
+/// The same as for `idtransform`.
+///
+/// # Example
+///
+/// ```
+/// use scan_json::idtransform::idtransform_with_substitution;
+/// use scan_json::matcher::{iter_match, StructuralPseudoname};
+/// use scan_json::RJiter;
+/// use u8pool::U8Pool;
+///
+/// let json = r#"{"user": {"name": "Ann", "password": "secret"}, "tags": [1, 2]}"#;
+/// let mut reader = json.as_bytes();
+/// let mut buffer = [0u8; 64];
+/// let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+/// let mut context_buffer = [0u8; 512];
+/// let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+/// let mut output = Vec::new();
+///
+/// idtransform_with_substitution(&mut rjiter, &mut output, &mut context, |context, _kind| {
+///     if iter_match(|| ["password", "user", "#top"], StructuralPseudoname::None, context.clone()) {
+///         return Some(b"\"***\"");
+///     }
+///     if iter_match(|| ["tags", "#top"], StructuralPseudoname::None, context) {
+///         return Some(b"[]");
+///     }
+///     None
+/// })
+/// .unwrap();
+///
+/// assert_eq!(output, br#"{"user":{"name":"Ann","password":"***"},"tags":[]}"#);
+/// ```
+pub fn idtransform_with_substitution<'s, R: Read, W: Write>(
+    rjiter: &mut RJiter<R>,
+    writer: &mut W,
+    working_buffer: &mut U8Pool,
+    mut substitute: impl FnMut(ContextIter, StructuralPseudoname) -> Option<Replacement<'s>> + 's,
+) -> ScanResult<()> {
+    run_idtransform(rjiter, writer, working_buffer, Some(&mut substitute))
+}
+
+fn run_idtransform<R: Read, W: Write>(
+    rjiter: &mut RJiter<R>,
+    writer: &mut W,
+    working_buffer: &mut U8Pool,
+    substitute: Option<&mut Substitute<'_>>,
+) -> ScanResult<()> {
+    let idt = IdTransform::new(writer, substitute);
     let idt_cell = RefCell::new(idt);
 
     let scan_result = scan(
@@ -387,8 +512,8 @@ pub fn idtransform<R: Read, W: Write>(
         &idt_cell,
         working_buffer,
         &Options {
-            sse_tokens: &[],
             stop_early: true,
+            ..Options::new()
         },
     );
 
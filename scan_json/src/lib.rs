@@ -1,16 +1,46 @@
 #![doc = include_str!("../README.md")]
 #![no_std]
 
+#[cfg(feature = "std")]
+pub mod analyze;
+pub mod budget;
 pub mod error;
+pub mod expect;
+pub mod extract;
+pub mod hash;
 pub mod idtransform;
 pub mod matcher;
+pub mod preview;
 pub mod scan;
+pub mod scan_mut;
+#[cfg(feature = "schema")]
+pub mod schema;
 pub mod stack;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
-pub use error::{Error, Result};
+#[cfg(feature = "std")]
+pub use analyze::{analyze_triggers, SamplePath, TriggerReport};
+pub use budget::{BudgetExceeded, DataBudget};
+pub use error::{ActionPath, Error, KeyBytes, OptionsError, Result};
+pub use expect::{
+    expect_array, expect_bool, expect_null, expect_number, expect_object, expect_string,
+    expect_type, ValueType,
+};
+pub use extract::{extract_many, extract_many_preview, ExtractReport};
+pub use hash::{hash_value, ValueHasher};
 pub use idtransform::idtransform;
-pub use matcher::{iter_match, Action, EndAction, StreamOp};
-pub use scan::{scan, Options};
+pub use matcher::{
+    iter_match, path_match, Action, ActionMut, EndAction, EndActionMut, StreamOp, ValueKind,
+};
+pub use preview::{push_str_preview, truncate_utf8, Preview};
+pub use scan::{
+    scan, KeyNormalization, KeyUtf8Policy, LongKeyPolicy, Options, OptionsBuilder, ScanReport,
+    StopWhen,
+};
+pub use scan_mut::scan_mut;
+#[cfg(feature = "schema")]
+pub use schema::{validate, Schema, SchemaError, Violation, ViolationKind};
 
 pub use rjiter;
 pub use rjiter::jiter;
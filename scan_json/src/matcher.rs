@@ -16,6 +16,18 @@ pub enum StructuralPseudoname {
     None,
 }
 
+/// Kind of the value of an object key, known to `find_action` when
+/// the option `defer_key_dispatch` of `scan` is set (see [`ContextIter::value_kind`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// The value is an object
+    Object,
+    /// The value is an array
+    Array,
+    /// The value is a string, a number, a boolean or null
+    Atom,
+}
+
 /// Return value from a callback to the `scan` function.
 #[derive(Debug)]
 pub enum StreamOp {
@@ -29,6 +41,24 @@ pub enum StreamOp {
     ValueIsConsumed,
     /// An error with a static error message
     Error(&'static str),
+    /// An error with a static error message and an application-defined code,
+    /// which `scan` returns in `Error::ActionError`
+    ErrorWith {
+        /// The error message
+        message: &'static str,
+        /// The error code, for example, an index in the caller's error table
+        code: u32,
+    },
+}
+
+impl StreamOp {
+    // The code of `ErrorWith`, `None` for the other operations
+    pub(crate) fn error_code(&self) -> Option<u32> {
+        match self {
+            StreamOp::ErrorWith { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
 }
 
 /// Type alias for action functions that can be called during JSON scanning.
@@ -47,6 +77,14 @@ pub type Action<B, R> = fn(&mut RJiter<R>, B) -> StreamOp;
 /// Returns `Ok(())` on success, or `Err(message)` where `message` is a static error message.
 pub type EndAction<B> = fn(B) -> Result<(), &'static str>;
 
+/// Type alias for action functions of `scan_mut`, which get the baton by mutable reference.
+pub type ActionMut<T, R> = fn(&mut RJiter<R>, &mut T) -> StreamOp;
+
+/// Type alias for end action functions of `scan_mut`, which get the baton by mutable reference.
+///
+/// Returns `Ok(())` on success, or `Err(message)` where `message` is a static error message.
+pub type EndActionMut<T> = fn(&mut T) -> Result<(), &'static str>;
+
 /// Match by name and ancestor names against the current JSON context.
 ///
 /// Additionally, the structural events (begin/end of array/object, primitive values in array/on top)
@@ -87,6 +125,12 @@ pub type EndAction<B> = fn(B) -> Result<(), &'static str>;
 /// - `#array` - Beginning or end of an array, matches `StructuralPseudoname::Array`
 /// - `#atom` - A primitive value in an array or at the top level, matches `StructuralPseudoname::Atom`
 ///
+/// # Wildcard
+///
+/// The name `*` matches exactly one level of the context, whatever the key name,
+/// or `#array` for an array item. It doesn't match `#top`.
+/// As a consequence, a key named `*` can't be matched literally.
+///
 /// # Returns
 ///
 /// * `true` if the node matches the criteria
@@ -133,6 +177,8 @@ where
     for expected_context in expected {
         match path.next() {
             Some(actual_context) if expected_context.as_ref() == actual_context => {}
+            Some(actual_context)
+                if expected_context.as_ref() == b"*" && actual_context != b"#top" => {}
             _ => return false,
         }
     }
@@ -140,3 +186,45 @@ where
     // Extra path elements are allowed - no need to check for them
     true
 }
+
+/// Match a `/`-separated pattern against the current JSON context, see `iter_match`.
+///
+/// The names go from the outer to the inner one, for example, `choices/*/message`
+/// matches the key `message` of any item of the array `choices`. As with `iter_match`,
+/// the pattern matches the end of the context. To match from the top, start the pattern
+/// with `#top`. A structural pseudo-name is the last name: `choices/#array`.
+/// Empty names are ignored, therefore an empty pattern matches everything.
+///
+/// # Example
+///
+/// ```
+/// use scan_json::matcher::{path_match, StructuralPseudoname};
+/// use scan_json::scan::StructurePosition;
+/// use scan_json::stack::ContextIter;
+/// use u8pool::U8Pool;
+///
+/// let mut buffer = [0u8; 256];
+/// let mut context = U8Pool::new(&mut buffer, 8).unwrap();
+/// for name in ["#top", "choices", "#array", "message"] {
+///     context.push_assoc(StructurePosition::ObjectMiddle, name.as_bytes()).unwrap();
+/// }
+///
+/// let matches = |pattern| {
+///     path_match(pattern, StructuralPseudoname::None, ContextIter::new(&context))
+/// };
+/// assert!(matches("choices/*/message"));
+/// assert!(matches("#top/*/*/message"));
+/// assert!(!matches("*/choices/*/message"));
+/// ```
+#[must_use]
+pub fn path_match(
+    pattern: &str,
+    structural_pseudoname: StructuralPseudoname,
+    path: ContextIter,
+) -> bool {
+    iter_match(
+        || pattern.rsplit('/').filter(|name| !name.is_empty()),
+        structural_pseudoname,
+        path,
+    )
+}
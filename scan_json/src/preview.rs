@@ -0,0 +1,153 @@
+//! Store the beginning of a string value, for example, for log previews.
+//!
+//! The string is decoded and truncated without splitting a UTF-8 code point.
+//! It can be longer than the `RJiter` buffer: the rest of it is skipped.
+
+use crate::error::Error as ScanError;
+use crate::{RJiter, Result as ScanResult};
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+use u8pool::{ElementWriter, U8Pool};
+
+/// Returns the longest prefix of `bytes` which is at most `max` bytes long
+/// and doesn't end in the middle of a UTF-8 code point.
+///
+/// The input is not validated, only the end of the prefix is checked.
+///
+/// ```
+/// use scan_json::preview::truncate_utf8;
+///
+/// assert_eq!(truncate_utf8(b"hello", 3), b"hel");
+/// // "é" is two bytes
+/// assert_eq!(truncate_utf8("caf\u{e9}".as_bytes(), 4), b"caf");
+/// assert_eq!(truncate_utf8(b"hi", 10), b"hi");
+/// ```
+#[must_use]
+pub fn truncate_utf8(bytes: &[u8], max: usize) -> &[u8] {
+    if bytes.len() <= max {
+        return bytes;
+    }
+    // A code point has at most 3 continuation bytes. If the byte at `max`
+    // is a continuation byte, step back to the leading byte.
+    let end = (max.saturating_sub(3)..=max)
+        .rev()
+        .find(|&pos| {
+            bytes
+                .get(pos)
+                .is_some_and(|&b| b & 0b1100_0000 != 0b1000_0000)
+        })
+        .unwrap_or(max);
+    bytes.get(..end).unwrap_or(bytes)
+}
+
+/// How much of a string `push_str_preview` stores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preview {
+    /// The maximum number of bytes of the string, without the ellipsis
+    pub max_len: usize,
+    /// The marker appended to a truncated string
+    pub ellipsis: &'static [u8],
+}
+
+impl Preview {
+    /// Keep at most `max_len` bytes, mark a truncated string with `...`
+    #[must_use]
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            max_len,
+            ellipsis: b"...",
+        }
+    }
+
+    /// Set the marker for a truncated string, an empty one to not mark
+    #[must_use]
+    pub fn with_ellipsis(mut self, ellipsis: &'static [u8]) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+}
+
+// Keeps the first bytes of the decoded string and drops the rest
+struct PreviewWriter<'w, 'p, 'a> {
+    element: &'w mut ElementWriter<'p, 'a>,
+    remaining: usize,
+    is_truncated: bool,
+    is_pool_full: bool,
+}
+
+impl ErrorType for PreviewWriter<'_, '_, '_> {
+    type Error = ErrorKind;
+}
+
+impl Write for PreviewWriter<'_, '_, '_> {
+    // `write_long_str` writes complete code points in each call
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if self.is_truncated {
+            return Ok(buf.len());
+        }
+        let head = truncate_utf8(buf, self.remaining);
+        // Without space in the pool, consume the rest of the string
+        self.is_pool_full = self.element.append(head).is_err();
+        self.remaining -= head.len();
+        self.is_truncated = self.is_pool_full || head.len() < buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Consume the string value and push its beginning to the pool.
+///
+/// At most `preview.max_len` bytes of the decoded string are stored, without
+/// splitting a code point. If the string is longer, `preview.ellipsis`
+/// is appended. The string may be longer than the `RJiter` buffer.
+///
+/// `RJiter` should be positioned at the string, for example, in an action for a key.
+///
+/// # Errors
+///
+/// - `Error::RJiterError` if the value is not a string or the json is malformed
+/// - `Error::IOError(ErrorKind::OutOfMemory)` if the pool has no space for the preview.
+///   Then the pool is not changed, but the string is consumed.
+///
+/// # Example
+///
+/// ```
+/// use scan_json::preview::{push_str_preview, Preview};
+/// use scan_json::RJiter;
+/// use u8pool::U8Pool;
+///
+/// let json = r#""a very long message""#;
+/// let mut reader = json.as_bytes();
+/// let mut buffer = [0u8; 8];
+/// let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+/// let mut pool_buffer = [0u8; 64];
+/// let mut pool = U8Pool::new(&mut pool_buffer, 4).unwrap();
+///
+/// let preview = push_str_preview(&mut rjiter, &mut pool, &Preview::new(6)).unwrap();
+/// assert_eq!(preview, b"a very...");
+/// ```
+pub fn push_str_preview<'p, R: Read>(
+    rjiter: &mut RJiter<R>,
+    pool: &'p mut U8Pool<'_>,
+    preview: &Preview,
+) -> ScanResult<&'p [u8]> {
+    let pool_full = || ScanError::IOError(ErrorKind::OutOfMemory);
+    let mut element = pool.begin_element().map_err(|_| pool_full())?;
+    let mut writer = PreviewWriter {
+        element: &mut element,
+        remaining: preview.max_len,
+        is_truncated: false,
+        is_pool_full: false,
+    };
+    rjiter.peek()?;
+    rjiter.write_long_str(&mut writer)?;
+    if writer.is_pool_full {
+        return Err(pool_full());
+    }
+    if writer.is_truncated {
+        element.append(preview.ellipsis).map_err(|_| pool_full())?;
+    }
+    element.commit().map_err(|_| pool_full())
+}
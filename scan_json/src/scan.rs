@@ -1,15 +1,18 @@
 //! Implementation of the `scan` function to scan a JSON stream.
 
-use crate::error::Error as ScanError;
+use crate::budget::DataBudget;
 use crate::error::Result as ScanResult;
-use crate::matcher::{Action, EndAction, StreamOp, StructuralPseudoname};
-use crate::stack::ContextIter;
+use crate::error::{ActionPath, Error as ScanError, KeyBytes, OptionsError};
+use crate::matcher::{Action, EndAction, StreamOp, StructuralPseudoname, ValueKind};
+use crate::stack::{
+    array_entry, array_entry_count, count_array_item, intern_key, ContextIter, MAX_INTERNED_KEYS,
+};
 use embedded_io::{Read, Write};
 use rjiter::jiter::Peek;
 use rjiter::RJiter;
 
 /// A sink writer that discards all written data
-struct Sink;
+pub(crate) struct Sink;
 
 impl embedded_io::ErrorType for Sink {
     type Error = embedded_io::ErrorKind;
@@ -26,13 +29,156 @@ impl Write for Sink {
 }
 use u8pool::{U8Pool, U8PoolError};
 
+/// Progress of `scan`, given to the `stop_when` predicate of `Options`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanReport {
+    /// Number of top-level values completed in this `scan` call
+    pub values: usize,
+    /// Position in the input after the last completed value
+    pub position: usize,
+}
+
+/// Predicate to stop scanning, see `Options::stop_when`
+pub type StopWhen<'options> = &'options dyn Fn(&ScanReport) -> bool;
+
+/// How to normalize object keys before they are put to the context stack.
+///
+/// The matchers see the normalized keys, therefore the names in
+/// the matchers should be normalized too (for example, lowercase).
+#[derive(Debug, Clone, Copy)]
+pub enum KeyNormalization {
+    /// Use the keys as is
+    None,
+    /// Convert ASCII letters to lowercase
+    AsciiLowercase,
+    /// Remove leading and trailing ASCII whitespace
+    AsciiTrim,
+    /// Custom normalization: writes the normalized `key` (arg 1) to `out` (arg 2)
+    /// and returns the number of bytes written. `out` has the length of `key`,
+    /// therefore the normalized key can't be longer than the original one.
+    Custom(fn(&[u8], &mut [u8]) -> usize),
+}
+
+impl KeyNormalization {
+    /// Writes the normalized `key` to `out`, which should be at least as long
+    /// as `key`, and returns the length of the normalized key.
+    #[must_use]
+    pub fn normalize(&self, key: &[u8], out: &mut [u8]) -> usize {
+        let copy = |src: &[u8], out: &mut [u8]| match out.get_mut(..src.len()) {
+            Some(dst) => {
+                dst.copy_from_slice(src);
+                src.len()
+            }
+            None => 0,
+        };
+        match self {
+            KeyNormalization::None => copy(key, out),
+            KeyNormalization::AsciiLowercase => {
+                let len = copy(key, out);
+                if let Some(dst) = out.get_mut(..len) {
+                    dst.make_ascii_lowercase();
+                }
+                len
+            }
+            KeyNormalization::AsciiTrim => copy(key.trim_ascii(), out),
+            KeyNormalization::Custom(normalize) => normalize(key, out),
+        }
+    }
+}
+
+/// What to do with object keys longer than the `RJiter` buffer.
+///
+/// With a policy other than `Error`, the keys are read with
+/// `RJiter::next_long_key`, which is slower than the default way.
+///
+/// The policy is only for keys. `scan` skips string values of any length
+/// if no action is called for them. A matched value is read by the action,
+/// which decides itself, for example, with `RJiter::write_long_str`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongKeyPolicy {
+    /// Fail with an error from `RJiter`
+    Error,
+    /// Keep only the first bytes of the keys longer than the given length
+    TruncateKey(usize),
+    /// Skip the keys longer than the given length, together with their values.
+    /// No actions are called for them.
+    SkipValue(usize),
+}
+
+/// How to handle object keys which are not valid UTF-8.
+///
+/// The keys are checked before the normalization. A key which is truncated
+/// by `LongKeyPolicy::TruncateKey` is cut at a code point boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUtf8Policy {
+    /// Put the keys to the context as is
+    Unchecked,
+    /// Fail with `Error::InvalidKeyUtf8`
+    Validate,
+    /// Replace each invalid sequence with U+FFFD, the replacement character
+    ReplaceInvalid,
+}
+
 /// Options for configuring the scan behavior
-#[derive(Debug)]
+///
+/// Create the options with `Options::new`, `Options::builder` or
+/// `Options::with_sse_tokens`, then set the fields or call the `with_*` functions.
+#[non_exhaustive]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Options<'options> {
     /// Slice of SSE tokens to ignore at the top level
     pub sse_tokens: &'options [&'options [u8]],
     /// Whether to stop scanning as soon as possible, or scan the complete JSON stream
     pub stop_early: bool,
+    /// Predicate evaluated after each completed top-level value.
+    /// If it returns `true`, `scan` stops as if `stop_early` were set.
+    pub stop_when: Option<StopWhen<'options>>,
+    /// How to normalize object keys before matching
+    pub key_normalization: KeyNormalization,
+    /// What to do with object keys longer than the `RJiter` buffer
+    pub long_key_policy: LongKeyPolicy,
+    /// What to do with object keys which are not valid UTF-8
+    pub key_utf8_policy: KeyUtf8Policy,
+    /// Peek the value of an object key before calling `find_action` for the key,
+    /// and give its kind in [`ContextIter::value_kind`]
+    pub defer_key_dispatch: bool,
+    /// Keep the context stack between `scan` calls, for a document which arrives
+    /// in segments. A segment can end after a complete value inside an object or an array
+    pub keep_context: bool,
+    /// Limit of the bytes which the actions reserve for each top-level value
+    pub data_budget: Option<&'options DataBudget>,
+    /// Limit of the actions (begin and end) which one `scan` call executes
+    pub max_actions: Option<usize>,
+    /// Limit of the begin-actions which are executed one after another at the same position
+    pub max_actions_at_position: Option<usize>,
+    /// Frequent keys which are stored in the context as a 2-byte reference
+    /// instead of the full bytes. The keys are compared after the normalization.
+    /// At most `MAX_INTERNED_KEYS` keys are used. Each pushed key longer than
+    /// the reference is compared with the list one by one
+    pub interned_keys: &'options [&'options [u8]],
+    /// Fail with `Error::TopLevelAtom` when a top-level value is not an object
+    /// or an array, instead of calling the `#atom` action at `#top`
+    pub require_container_top: bool,
+}
+
+impl core::fmt::Debug for Options<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Options")
+            .field("sse_tokens", &self.sse_tokens)
+            .field("stop_early", &self.stop_early)
+            .field("stop_when", &self.stop_when.is_some())
+            .field("key_normalization", &self.key_normalization)
+            .field("long_key_policy", &self.long_key_policy)
+            .field("key_utf8_policy", &self.key_utf8_policy)
+            .field("defer_key_dispatch", &self.defer_key_dispatch)
+            .field("keep_context", &self.keep_context)
+            .field("data_budget", &self.data_budget)
+            .field("max_actions", &self.max_actions)
+            .field("max_actions_at_position", &self.max_actions_at_position)
+            .field("interned_keys", &self.interned_keys)
+            .field("require_container_top", &self.require_container_top)
+            .finish()
+    }
 }
 
 impl<'options> Options<'options> {
@@ -43,6 +189,25 @@ impl<'options> Options<'options> {
         Self {
             sse_tokens: &[],
             stop_early: false,
+            stop_when: None,
+            key_normalization: KeyNormalization::None,
+            long_key_policy: LongKeyPolicy::Error,
+            key_utf8_policy: KeyUtf8Policy::Unchecked,
+            defer_key_dispatch: false,
+            keep_context: false,
+            data_budget: None,
+            max_actions: None,
+            max_actions_at_position: None,
+            interned_keys: &[],
+            require_container_top: false,
+        }
+    }
+
+    #[must_use]
+    /// Creates a builder which validates the options, see `OptionsBuilder`
+    pub fn builder() -> OptionsBuilder<'options> {
+        OptionsBuilder {
+            options: Self::new(),
         }
     }
 
@@ -51,8 +216,267 @@ impl<'options> Options<'options> {
     pub fn with_sse_tokens(tokens: &'options [&'options [u8]]) -> Self {
         Self {
             sse_tokens: tokens,
-            stop_early: false,
+            ..Self::new()
+        }
+    }
+
+    #[must_use]
+    /// Sets the predicate to stop scanning after a top-level value
+    pub fn stop_when(mut self, predicate: StopWhen<'options>) -> Self {
+        self.stop_when = Some(predicate);
+        self
+    }
+
+    #[must_use]
+    /// Sets the normalization of object keys
+    pub fn with_key_normalization(mut self, key_normalization: KeyNormalization) -> Self {
+        self.key_normalization = key_normalization;
+        self
+    }
+
+    #[must_use]
+    /// Sets the policy for object keys longer than the `RJiter` buffer
+    pub fn with_long_key_policy(mut self, long_key_policy: LongKeyPolicy) -> Self {
+        self.long_key_policy = long_key_policy;
+        self
+    }
+
+    #[must_use]
+    /// Sets the policy for object keys which are not valid UTF-8
+    pub fn with_key_utf8_policy(mut self, key_utf8_policy: KeyUtf8Policy) -> Self {
+        self.key_utf8_policy = key_utf8_policy;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether to peek the value of a key before calling `find_action` for the key
+    pub fn with_defer_key_dispatch(mut self, defer_key_dispatch: bool) -> Self {
+        self.defer_key_dispatch = defer_key_dispatch;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether to keep the context stack between `scan` calls
+    pub fn with_keep_context(mut self, keep_context: bool) -> Self {
+        self.keep_context = keep_context;
+        self
+    }
+
+    #[must_use]
+    /// Sets the limit of the bytes which the actions reserve for each top-level value
+    pub fn with_data_budget(mut self, data_budget: &'options DataBudget) -> Self {
+        self.data_budget = Some(data_budget);
+        self
+    }
+
+    #[must_use]
+    /// Sets the limit of the actions which one `scan` call executes
+    pub fn with_max_actions(mut self, max_actions: usize) -> Self {
+        self.max_actions = Some(max_actions);
+        self
+    }
+
+    #[must_use]
+    /// Sets the limit of the begin-actions executed one after another at the same position
+    pub fn with_max_actions_at_position(mut self, max_actions_at_position: usize) -> Self {
+        self.max_actions_at_position = Some(max_actions_at_position);
+        self
+    }
+
+    #[must_use]
+    /// Sets the keys which are stored in the context as a reference
+    pub fn with_interned_keys(mut self, interned_keys: &'options [&'options [u8]]) -> Self {
+        self.interned_keys = interned_keys;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether a top-level value which is not an object or an array is an error
+    pub fn with_require_container_top(mut self, require_container_top: bool) -> Self {
+        self.require_container_top = require_container_top;
+        self
+    }
+
+    /// Checks that the options can be used together, as `OptionsBuilder::build` does.
+    /// Useful for options which are constructed as a struct.
+    ///
+    /// # Errors
+    ///
+    /// - `EmptySseToken`: `scan` would skip an empty token forever
+    /// - `TooManyInternedKeys`: more than `MAX_INTERNED_KEYS` keys
+    /// - `ZeroLimit`: `max_actions` or `max_actions_at_position` is zero,
+    ///   or the length in `long_key_policy` is zero
+    /// - `Conflict`: `stop_early` with `stop_when`, which is then never used
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        if self.sse_tokens.iter().any(|token| token.is_empty()) {
+            return Err(OptionsError::EmptySseToken);
+        }
+        if self.interned_keys.len() > MAX_INTERNED_KEYS {
+            return Err(OptionsError::TooManyInternedKeys {
+                count: self.interned_keys.len(),
+                max: MAX_INTERNED_KEYS,
+            });
+        }
+        let zero_limit = if self.max_actions == Some(0) {
+            Some("max_actions")
+        } else if self.max_actions_at_position == Some(0) {
+            Some("max_actions_at_position")
+        } else if matches!(
+            self.long_key_policy,
+            LongKeyPolicy::TruncateKey(0) | LongKeyPolicy::SkipValue(0)
+        ) {
+            Some("long_key_policy")
+        } else {
+            None
+        };
+        if let Some(option) = zero_limit {
+            return Err(OptionsError::ZeroLimit { option });
+        }
+        if self.stop_early && self.stop_when.is_some() {
+            return Err(OptionsError::Conflict {
+                option: "stop_early",
+                other: "stop_when",
+            });
         }
+        Ok(())
+    }
+}
+
+/// Builder for `Options`, which checks the combination of the options in `build`.
+///
+/// Unlike a struct literal, the code with the builder doesn't change
+/// when new options are added.
+///
+/// ```
+/// use scan_json::{KeyNormalization, Options};
+///
+/// let sse_tokens: &[&[u8]] = &[b"data:", b"[DONE]"];
+/// let options = Options::builder()
+///     .sse_tokens(sse_tokens)
+///     .key_normalization(KeyNormalization::AsciiLowercase)
+///     .max_actions(1000)
+///     .build()
+///     .unwrap();
+/// assert!(!options.stop_early);
+///
+/// let conflict = Options::builder()
+///     .stop_early(true)
+///     .stop_when(&|report| report.values > 10)
+///     .build();
+/// assert!(conflict.is_err());
+/// ```
+pub struct OptionsBuilder<'options> {
+    options: Options<'options>,
+}
+
+impl core::fmt::Debug for OptionsBuilder<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptionsBuilder")
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<'options> OptionsBuilder<'options> {
+    #[must_use]
+    /// Sets the SSE tokens to ignore at the top level
+    pub fn sse_tokens(mut self, tokens: &'options [&'options [u8]]) -> Self {
+        self.options.sse_tokens = tokens;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether to stop after the first top-level value
+    pub fn stop_early(mut self, stop_early: bool) -> Self {
+        self.options.stop_early = stop_early;
+        self
+    }
+
+    #[must_use]
+    /// Sets the predicate to stop scanning after a top-level value
+    pub fn stop_when(mut self, predicate: StopWhen<'options>) -> Self {
+        self.options.stop_when = Some(predicate);
+        self
+    }
+
+    #[must_use]
+    /// Sets the normalization of object keys
+    pub fn key_normalization(mut self, key_normalization: KeyNormalization) -> Self {
+        self.options.key_normalization = key_normalization;
+        self
+    }
+
+    #[must_use]
+    /// Sets the policy for object keys longer than the `RJiter` buffer
+    pub fn long_key_policy(mut self, long_key_policy: LongKeyPolicy) -> Self {
+        self.options.long_key_policy = long_key_policy;
+        self
+    }
+
+    #[must_use]
+    /// Sets the policy for object keys which are not valid UTF-8
+    pub fn key_utf8_policy(mut self, key_utf8_policy: KeyUtf8Policy) -> Self {
+        self.options.key_utf8_policy = key_utf8_policy;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether to peek the value of a key before calling `find_action` for the key
+    pub fn defer_key_dispatch(mut self, defer_key_dispatch: bool) -> Self {
+        self.options.defer_key_dispatch = defer_key_dispatch;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether to keep the context stack between `scan` calls
+    pub fn keep_context(mut self, keep_context: bool) -> Self {
+        self.options.keep_context = keep_context;
+        self
+    }
+
+    #[must_use]
+    /// Sets the limit of the bytes which the actions reserve for each top-level value
+    pub fn data_budget(mut self, data_budget: &'options DataBudget) -> Self {
+        self.options.data_budget = Some(data_budget);
+        self
+    }
+
+    #[must_use]
+    /// Sets the limit of the actions which one `scan` call executes
+    pub fn max_actions(mut self, max_actions: usize) -> Self {
+        self.options.max_actions = Some(max_actions);
+        self
+    }
+
+    #[must_use]
+    /// Sets the limit of the begin-actions executed one after another at the same position
+    pub fn max_actions_at_position(mut self, max_actions_at_position: usize) -> Self {
+        self.options.max_actions_at_position = Some(max_actions_at_position);
+        self
+    }
+
+    #[must_use]
+    /// Sets the keys which are stored in the context as a reference
+    pub fn interned_keys(mut self, interned_keys: &'options [&'options [u8]]) -> Self {
+        self.options.interned_keys = interned_keys;
+        self
+    }
+
+    #[must_use]
+    /// Sets whether a top-level value which is not an object or an array is an error
+    pub fn require_container_top(mut self, require_container_top: bool) -> Self {
+        self.options.require_container_top = require_container_top;
+        self
+    }
+
+    /// Checks the options and returns them, see `Options::validate`
+    ///
+    /// # Errors
+    ///
+    /// `OptionsError` if the options can't be used together
+    pub fn build(self) -> Result<Options<'options>, OptionsError> {
+        self.options.validate()?;
+        Ok(self.options)
     }
 }
 
@@ -73,6 +497,64 @@ pub enum StructurePosition {
     ArrayMiddle,
 }
 
+// Counts the actions of a `scan` call for `Options::max_actions`
+// and `Options::max_actions_at_position`
+struct ActionCounter {
+    max_actions: Option<usize>,
+    max_actions_at_position: Option<usize>,
+    n_actions: usize,
+    last_position: usize,
+    n_at_position: usize,
+}
+
+impl ActionCounter {
+    fn new(options: &Options) -> Self {
+        Self {
+            max_actions: options.max_actions,
+            max_actions_at_position: options.max_actions_at_position,
+            n_actions: 0,
+            last_position: usize::MAX,
+            n_at_position: 0,
+        }
+    }
+
+    // Call before an action. Only the begin-actions (`is_begin`) are counted
+    // for the position, an end-action can be at the position of the next begin-action.
+    fn count(
+        &mut self,
+        position: usize,
+        is_begin: bool,
+        context: &U8Pool,
+        interned_keys: &[&[u8]],
+    ) -> ScanResult<()> {
+        self.n_actions += 1;
+        if let Some(limit) = self.max_actions {
+            if self.n_actions > limit {
+                return Err(ScanError::ActionLimitExceeded { position, limit });
+            }
+        }
+        if !is_begin {
+            return Ok(());
+        }
+        if position == self.last_position {
+            self.n_at_position += 1;
+        } else {
+            self.last_position = position;
+            self.n_at_position = 1;
+        }
+        if let Some(limit) = self.max_actions_at_position {
+            if self.n_at_position > limit {
+                return Err(ScanError::ActionLoop {
+                    position,
+                    limit,
+                    path: ActionPath::from_context(context_iter(context, interned_keys)),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 // Handle a JSON object key
 //
 // - Call the begin-action for the object
@@ -87,7 +569,7 @@ pub enum StructurePosition {
 // - On end of object, pop the last key
 // - Contract: The stack state after the end of the object is the same as before the begin of the object.
 //   The returned StructurePosition after the end is one from the top of the stack before the begin of the object.
-#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_lines, clippy::too_many_arguments)]
 fn handle_object<B: Copy, R: Read>(
     rjiter: &mut RJiter<R>,
     baton: B,
@@ -95,6 +577,8 @@ fn handle_object<B: Copy, R: Read>(
     find_end_action: &impl Fn(StructuralPseudoname, ContextIter, B) -> Option<EndAction<B>>,
     position: StructurePosition,
     context: &mut U8Pool,
+    options: &Options,
+    counter: &mut ActionCounter,
 ) -> ScanResult<StructurePosition> {
     //
     // Call the begin-trigger for the object
@@ -102,24 +586,38 @@ fn handle_object<B: Copy, R: Read>(
     if position == StructurePosition::ObjectBegin {
         if let Some(begin_action) = find_action(
             StructuralPseudoname::Object,
-            ContextIter::new(context),
+            context_iter(context, options.interned_keys),
             baton,
         ) {
-            match begin_action(rjiter, baton) {
+            let value_position = rjiter.current_index();
+            counter.count(value_position, true, context, options.interned_keys)?;
+            let op = begin_action(rjiter, baton);
+            match op {
                 StreamOp::None => (),
-                StreamOp::Error(message) => {
-                    return Err(ScanError::ActionError {
+                StreamOp::Error(message) | StreamOp::ErrorWith { message, .. } => {
+                    return Err(action_error(
                         message,
-                        position: rjiter.current_index(),
-                    })
+                        op.error_code(),
+                        rjiter.current_index(),
+                        context,
+                        options.interned_keys,
+                    ));
                 }
                 StreamOp::ValueIsConsumed => {
                     #[allow(unsafe_code)]
-                    return Ok(*unsafe { context.top_assoc_obj::<StructurePosition>() }
+                    let parent_position = *unsafe { context.top_assoc_obj::<StructurePosition>() }
                         .ok_or_else(|| ScanError::InternalError {
                             position: rjiter.current_index(),
                             message: "Context stack is empty when handling ValueIsConsumed",
-                        })?);
+                        })?;
+                    check_value_consumed(
+                        rjiter,
+                        value_position,
+                        parent_position,
+                        context,
+                        options.interned_keys,
+                    )?;
+                    return Ok(parent_position);
                 }
             }
         }
@@ -129,16 +627,28 @@ fn handle_object<B: Copy, R: Read>(
     // Call the end-trigger for the previous key
     //
     if position != StructurePosition::ObjectBegin {
-        let end_action =
-            find_end_action(StructuralPseudoname::None, ContextIter::new(context), baton);
+        let end_action = find_end_action(
+            StructuralPseudoname::None,
+            context_iter(context, options.interned_keys),
+            baton,
+        );
         #[allow(unsafe_code)]
         let _ = unsafe { context.pop_assoc::<StructurePosition>() };
         if let Some(end_action) = end_action {
+            counter.count(
+                rjiter.current_index(),
+                false,
+                context,
+                options.interned_keys,
+            )?;
             if let Err(message) = end_action(baton) {
-                return Err(ScanError::ActionError {
+                return Err(action_error(
                     message,
-                    position: rjiter.current_index(),
-                });
+                    None,
+                    rjiter.current_index(),
+                    context,
+                    options.interned_keys,
+                ));
             }
         }
     }
@@ -146,73 +656,152 @@ fn handle_object<B: Copy, R: Read>(
     //
     // Find the next key in the object or the end of the object
     //
-    let keyr = if position == StructurePosition::ObjectBegin {
-        rjiter.next_object_bytes()
-    } else {
-        rjiter.next_key_bytes()
-    }?;
-
-    match keyr {
-        None => {
-            //
-            // Call the end-trigger for the object
-            //
-            if let Some(end_action) = find_end_action(
-                StructuralPseudoname::Object,
-                ContextIter::new(context),
-                baton,
-            ) {
-                if let Err(message) = end_action(baton) {
-                    return Err(ScanError::ActionError {
-                        message,
-                        position: rjiter.current_index(),
-                    });
-                }
-            }
-            #[allow(unsafe_code)]
-            return Ok(
-                *unsafe { context.top_assoc_obj::<StructurePosition>() }.ok_or_else(|| {
-                    ScanError::InternalError {
-                        position: rjiter.current_index(),
-                        message: "Context stack is empty when ending object",
-                    }
-                })?,
-            );
-        }
-        Some(key) => {
-            //
-            // Remember the current key
-            //
-            context
-                .push_assoc(StructurePosition::ObjectMiddle, key)
-                .map_err(|e| match e {
-                    U8PoolError::SliceLimitExceeded { max_slices } => {
-                        ScanError::MaxNestingExceeded {
-                            position: rjiter.current_index(),
-                            level: max_slices,
+    let is_key_found = match options.long_key_policy {
+        LongKeyPolicy::Error => {
+            let keyr = if position == StructurePosition::ObjectBegin {
+                rjiter.next_object_bytes()
+            } else {
+                rjiter.next_key_bytes()
+            }?;
+            match keyr {
+                None => false,
+                Some(key) => {
+                    //
+                    // Remember the current key
+                    //
+                    let level = context.len();
+                    let invalid_utf8 = match options.key_utf8_policy {
+                        KeyUtf8Policy::Unchecked => None,
+                        KeyUtf8Policy::Validate | KeyUtf8Policy::ReplaceInvalid => {
+                            core::str::from_utf8(key).err().map(|e| e.valid_up_to())
                         }
+                    };
+                    if let (KeyUtf8Policy::Validate, Some(valid_up_to)) =
+                        (options.key_utf8_policy, invalid_utf8)
+                    {
+                        let key = KeyBytes::new(key);
+                        return Err(ScanError::InvalidKeyUtf8 {
+                            position: rjiter.current_index(),
+                            key,
+                            valid_up_to,
+                        });
                     }
-                    _ => ScanError::InternalError {
-                        position: rjiter.current_index(),
-                        message: "Failed to push key to context pool",
-                    },
-                })?;
+                    let is_replaced = invalid_utf8.is_some();
+                    let is_normalized =
+                        !matches!(options.key_normalization, KeyNormalization::None);
+                    let is_interned = !options.interned_keys.is_empty();
+                    let pushed = if is_replaced || is_normalized || is_interned {
+                        let (key_len, scratch_len) =
+                            key_reserve_len(key, is_replaced, is_normalized);
+                        // One more byte for an escaped key, see `intern_key`
+                        let key_len = key_len + usize::from(is_interned);
+                        context
+                            .push_assoc_with(
+                                StructurePosition::ObjectMiddle,
+                                key_len + scratch_len,
+                                |out| {
+                                    let (out, scratch) = out.split_at_mut(key_len.min(out.len()));
+                                    let len = store_key(
+                                        key,
+                                        out,
+                                        scratch,
+                                        is_replaced,
+                                        options.key_normalization,
+                                    );
+                                    intern_key(out, len, options.interned_keys)
+                                },
+                            )
+                            .map(|_| ())
+                    } else {
+                        context
+                            .push_assoc(StructurePosition::ObjectMiddle, key)
+                            .map(|_| ())
+                    };
+                    pushed.map_err(|e| push_context_error(&e, rjiter.current_index(), level))?;
+                    true
+                }
+            }
+        }
+        LongKeyPolicy::TruncateKey(max_len) | LongKeyPolicy::SkipValue(max_len) => push_long_key(
+            rjiter,
+            position == StructurePosition::ObjectBegin,
+            context,
+            options,
+            max_len,
+        )?,
+    };
+
+    if !is_key_found {
+        //
+        // Call the end-trigger for the object
+        //
+        if let Some(end_action) = find_end_action(
+            StructuralPseudoname::Object,
+            context_iter(context, options.interned_keys),
+            baton,
+        ) {
+            counter.count(
+                rjiter.current_index(),
+                false,
+                context,
+                options.interned_keys,
+            )?;
+            if let Err(message) = end_action(baton) {
+                return Err(action_error(
+                    message,
+                    None,
+                    rjiter.current_index(),
+                    context,
+                    options.interned_keys,
+                ));
+            }
         }
+        #[allow(unsafe_code)]
+        return Ok(
+            *unsafe { context.top_assoc_obj::<StructurePosition>() }.ok_or_else(|| {
+                ScanError::InternalError {
+                    position: rjiter.current_index(),
+                    message: "Context stack is empty when ending object",
+                }
+            })?,
+        );
     }
 
     //
     // Execute the action for the current key
     //
-    if let Some(action) = find_action(StructuralPseudoname::None, ContextIter::new(context), baton)
-    {
-        match action(rjiter, baton) {
-            StreamOp::Error(message) => {
-                return Err(ScanError::ActionError {
+    let key_context = if options.defer_key_dispatch {
+        let value_kind = match rjiter.peek()? {
+            Peek::Object => ValueKind::Object,
+            Peek::Array => ValueKind::Array,
+            _ => ValueKind::Atom,
+        };
+        ContextIter::with_value_kind(context, value_kind).with_interned_keys(options.interned_keys)
+    } else {
+        context_iter(context, options.interned_keys)
+    };
+    if let Some(action) = find_action(StructuralPseudoname::None, key_context, baton) {
+        let value_position = rjiter.current_index();
+        counter.count(value_position, true, context, options.interned_keys)?;
+        let op = action(rjiter, baton);
+        match op {
+            StreamOp::Error(message) | StreamOp::ErrorWith { message, .. } => {
+                return Err(action_error(
                     message,
-                    position: rjiter.current_index(),
-                });
+                    op.error_code(),
+                    rjiter.current_index(),
+                    context,
+                    options.interned_keys,
+                ));
             }
             StreamOp::ValueIsConsumed => {
+                check_value_consumed(
+                    rjiter,
+                    value_position,
+                    StructurePosition::ObjectMiddle,
+                    context,
+                    options.interned_keys,
+                )?;
                 return Ok(StructurePosition::ObjectMiddle);
             }
             StreamOp::None => (),
@@ -222,6 +811,341 @@ fn handle_object<B: Copy, R: Read>(
     Ok(StructurePosition::ObjectBetweenKV)
 }
 
+fn action_error(
+    message: &'static str,
+    code: Option<u32>,
+    position: usize,
+    context: &U8Pool,
+    interned_keys: &[&[u8]],
+) -> ScanError {
+    ScanError::ActionError {
+        message,
+        position,
+        code,
+        path: ActionPath::from_context(context_iter(context, interned_keys)),
+    }
+}
+
+// The context for the matchers, with the interned keys
+fn context_iter<'a>(context: &'a U8Pool, interned_keys: &'a [&'a [u8]]) -> ContextIter<'a> {
+    ContextIter::new(context).with_interned_keys(interned_keys)
+}
+
+// Check that an action which returned `StreamOp::ValueIsConsumed` consumed the value:
+// the parser has moved, and in an object or array, the next byte is a comma or the closing
+// bracket. At the top level, the next value is not looked at, it can be not yet in the stream.
+fn check_value_consumed<R: Read>(
+    rjiter: &mut RJiter<R>,
+    value_position: usize,
+    parent_position: StructurePosition,
+    context: &U8Pool,
+    interned_keys: &[&[u8]],
+) -> ScanResult<()> {
+    let position = rjiter.current_index();
+    let is_consumed = position > value_position
+        && match parent_position {
+            StructurePosition::Top => true,
+            StructurePosition::ObjectBegin
+            | StructurePosition::ObjectMiddle
+            | StructurePosition::ObjectBetweenKV => is_at_value_end(rjiter, b'}'),
+            StructurePosition::ArrayBegin | StructurePosition::ArrayMiddle => {
+                is_at_value_end(rjiter, b']')
+            }
+        };
+    if is_consumed {
+        return Ok(());
+    }
+    Err(ScanError::ValueConsumptionMismatch {
+        value_position,
+        position,
+        path: ActionPath::from_context(context_iter(context, interned_keys)),
+    })
+}
+
+// Whether the next byte after whitespace is a comma or the closing bracket.
+// If the bytes can't be looked at, the parser reports the problem later.
+fn is_at_value_end<R: Read>(rjiter: &mut RJiter<R>, closing: u8) -> bool {
+    let Ok(n_spaces) = rjiter
+        .lookahead_while(|b| b.is_ascii_whitespace())
+        .map(<[u8]>::len)
+    else {
+        return true;
+    };
+    match rjiter.lookahead_n(n_spaces + 1) {
+        Ok(bytes) => bytes
+            .get(n_spaces)
+            .is_none_or(|b| *b == b',' || *b == closing),
+        Err(_) => true,
+    }
+}
+
+// The space to reserve in the context for the key:
+// for the stored key, and for the replaced key before the normalization.
+fn key_reserve_len(key: &[u8], is_replaced: bool, is_normalized: bool) -> (usize, usize) {
+    let stored_len = if is_replaced {
+        key.utf8_chunks()
+            .map(|chunk| {
+                let replacement_len = if chunk.invalid().is_empty() {
+                    0
+                } else {
+                    char::REPLACEMENT_CHARACTER.len_utf8()
+                };
+                chunk.valid().len() + replacement_len
+            })
+            .sum()
+    } else {
+        key.len()
+    };
+    let scratch_len = if is_replaced && is_normalized {
+        stored_len
+    } else {
+        0
+    };
+    (stored_len, scratch_len)
+}
+
+// Write the key to `out`, replacing invalid UTF-8 if `is_replaced`, and normalized.
+// The replaced key is kept in `scratch` for the normalization.
+// Returns the length of the stored key.
+fn store_key(
+    key: &[u8],
+    out: &mut [u8],
+    scratch: &mut [u8],
+    is_replaced: bool,
+    key_normalization: KeyNormalization,
+) -> usize {
+    if !is_replaced {
+        return key_normalization.normalize(key, out);
+    }
+    if let KeyNormalization::None = key_normalization {
+        return replace_invalid_utf8(key, out);
+    }
+    let len = replace_invalid_utf8(key, scratch);
+    key_normalization.normalize(scratch.get(..len).unwrap_or_default(), out)
+}
+
+// Write `key` to `out` with each invalid UTF-8 sequence replaced by U+FFFD,
+// `out` should be as long as given by `key_reserve_len`. Returns the number of bytes written.
+fn replace_invalid_utf8(key: &[u8], out: &mut [u8]) -> usize {
+    let mut len = 0;
+    let mut put = |bytes: &[u8]| {
+        if let Some(dst) = out.get_mut(len..len + bytes.len()) {
+            dst.copy_from_slice(bytes);
+            len += bytes.len();
+        }
+    };
+    for chunk in key.utf8_chunks() {
+        put(chunk.valid().as_bytes());
+        if !chunk.invalid().is_empty() {
+            put(char::REPLACEMENT_CHARACTER
+                .encode_utf8(&mut [0u8; 4])
+                .as_bytes());
+        }
+    }
+    len
+}
+
+// The context pool is full: too deep nesting, too long key, or too small pool
+fn push_context_error(e: &U8PoolError, position: usize, level: usize) -> ScanError {
+    match *e {
+        U8PoolError::SliceLimitExceeded {
+            requested,
+            available,
+            ..
+        } => ScanError::MaxNestingExceeded {
+            position,
+            level,
+            requested,
+            available,
+            free_slots: 0,
+        },
+        U8PoolError::BufferOverflow {
+            requested,
+            available,
+            free_slices,
+        } => ScanError::MaxNestingExceeded {
+            position,
+            level,
+            requested,
+            available,
+            free_slots: free_slices,
+        },
+        _ => ScanError::InternalError {
+            position,
+            message: "Failed to push key to context pool",
+        },
+    }
+}
+
+/// A writer to a slice, which drops the bytes after the end of the slice
+/// and counts all the bytes
+struct TruncatingWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl embedded_io::ErrorType for TruncatingWriter<'_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+impl Write for TruncatingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if let Some(dst) = self.buf.get_mut(self.len..) {
+            let n = dst.len().min(buf.len());
+            if let (Some(dst), Some(src)) = (dst.get_mut(..n), buf.get(..n)) {
+                dst.copy_from_slice(src);
+            }
+        }
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Read the next key of any length and push its first `max_len` bytes (normalized)
+// to the context. With `LongKeyPolicy::SkipValue`, skip the longer keys
+// with their values, and continue with the next key.
+// Returns `false` at the end of the object, then nothing is pushed.
+fn push_long_key<R: Read>(
+    rjiter: &mut RJiter<R>,
+    is_object_begin: bool,
+    context: &mut U8Pool,
+    options: &Options,
+    max_len: usize,
+) -> ScanResult<bool> {
+    let mut is_object_begin = is_object_begin;
+    let is_normalized = !matches!(options.key_normalization, KeyNormalization::None);
+    let is_checked = options.key_utf8_policy != KeyUtf8Policy::Unchecked;
+    let may_replace = options.key_utf8_policy == KeyUtf8Policy::ReplaceInvalid;
+    // Without normalization, the key is written directly to the context.
+    // Otherwise, it is written after the space for the stored key.
+    // An invalid key to replace is kept raw, it gets more space after the push.
+    // One more byte for an escaped key, see `intern_key`
+    let intern_len = usize::from(!options.interned_keys.is_empty());
+    let stored_len = if is_normalized {
+        max_len + intern_len
+    } else {
+        0
+    };
+    loop {
+        let reserve_len = stored_len + max_len + intern_len;
+        let mut key_result = Ok(false);
+        let mut key_len = 0;
+        let mut invalid_key = None;
+        let mut is_replaced = false;
+        let level = context.len();
+        context
+            .push_assoc_with(StructurePosition::ObjectMiddle, reserve_len, |out| {
+                let (stored, raw) = out.split_at_mut(stored_len.min(out.len()));
+                let mut writer = TruncatingWriter { buf: raw, len: 0 };
+                key_result = if is_object_begin {
+                    rjiter.next_object_long_key(&mut writer)
+                } else {
+                    rjiter.next_long_key(&mut writer)
+                };
+                key_len = writer.len;
+                let mut raw = writer.buf.get(..key_len.min(max_len)).unwrap_or_default();
+                if is_checked {
+                    if let Err(e) = core::str::from_utf8(raw) {
+                        // Don't report the code point which is cut by the truncation
+                        if key_len > max_len && e.error_len().is_none() {
+                            raw = raw.get(..e.valid_up_to()).unwrap_or_default();
+                        } else {
+                            invalid_key = Some((KeyBytes::new(raw), e.valid_up_to()));
+                        }
+                    }
+                }
+                is_replaced = may_replace && invalid_key.is_some();
+                let raw_len = raw.len();
+                let len = if is_normalized && !is_replaced {
+                    options.key_normalization.normalize(raw, stored)
+                } else {
+                    raw_len
+                };
+                if is_replaced {
+                    out.copy_within(stored_len..stored_len + raw_len, 0);
+                    return raw_len;
+                }
+                intern_key(out, len, options.interned_keys)
+            })
+            .map_err(|e| push_context_error(&e, rjiter.current_index(), level))?;
+
+        let is_key_found = match key_result {
+            Ok(is_key_found) => is_key_found,
+            Err(e) => {
+                #[allow(unsafe_code)]
+                let _ = unsafe { context.pop_assoc::<StructurePosition>() };
+                return Err(e.into());
+            }
+        };
+        let is_skipped =
+            matches!(options.long_key_policy, LongKeyPolicy::SkipValue(_)) && key_len > max_len;
+        if is_key_found && !is_skipped {
+            if let (KeyUtf8Policy::Validate, Some((key, valid_up_to))) =
+                (options.key_utf8_policy, invalid_key)
+            {
+                #[allow(unsafe_code)]
+                let _ = unsafe { context.pop_assoc::<StructurePosition>() };
+                return Err(ScanError::InvalidKeyUtf8 {
+                    position: rjiter.current_index(),
+                    key,
+                    valid_up_to,
+                });
+            }
+            if is_replaced {
+                replace_top_key(context, options)
+                    .map_err(|e| push_context_error(&e, rjiter.current_index(), level))?;
+            }
+            return Ok(true);
+        }
+        #[allow(unsafe_code)]
+        let _ = unsafe { context.pop_assoc::<StructurePosition>() };
+        if !is_key_found {
+            return Ok(false);
+        }
+
+        let peeked = rjiter.peek()?;
+        if peeked == Peek::Object || peeked == Peek::Array {
+            rjiter.known_skip(peeked)?;
+        } else {
+            skip_basic_values(peeked, rjiter)?;
+        }
+        is_object_begin = false;
+    }
+}
+
+// Replace invalid UTF-8 in the raw key on the top of the context,
+// then normalize and intern it. On error, the key is popped.
+fn replace_top_key(context: &mut U8Pool, options: &Options) -> Result<(), U8PoolError> {
+    let raw = context
+        .top_assoc_bytes::<StructurePosition>()
+        .unwrap_or_default();
+    let raw_len = raw.len();
+    let is_normalized = !matches!(options.key_normalization, KeyNormalization::None);
+    let (stored_len, scratch_len) = key_reserve_len(raw, true, is_normalized);
+    // One more byte for an escaped key, see `intern_key`
+    let stored_len = stored_len + usize::from(!options.interned_keys.is_empty());
+    let rewritten = context.rewrite_top_assoc_bytes::<StructurePosition>(
+        stored_len + scratch_len + raw_len,
+        |space| {
+            space.copy_within(..raw_len, stored_len + scratch_len);
+            let (stored, rest) = space.split_at_mut(stored_len);
+            let (scratch, raw) = rest.split_at_mut(scratch_len);
+            let len = store_key(raw, stored, scratch, true, options.key_normalization);
+            intern_key(stored, len, options.interned_keys)
+        },
+    );
+    if let Err(e) = rewritten {
+        #[allow(unsafe_code)]
+        let _ = unsafe { context.pop_assoc::<StructurePosition>() };
+        return Err(e);
+    }
+    Ok(())
+}
+
 // Handle a JSON array item.
 //
 // - If at the beginning of the array
@@ -239,6 +1163,7 @@ fn handle_object<B: Copy, R: Read>(
 // - Contract: The stack state after the end of the array is the same as before the begin of the array.
 //   The returned StructurePosition after the end is one from the top of the stack before the begin of the array.
 //
+#[allow(clippy::too_many_lines, clippy::too_many_arguments)]
 fn handle_array<B: Copy, R: Read>(
     rjiter: &mut RJiter<R>,
     baton: B,
@@ -246,50 +1171,58 @@ fn handle_array<B: Copy, R: Read>(
     find_end_action: &impl Fn(StructuralPseudoname, ContextIter, B) -> Option<EndAction<B>>,
     position: StructurePosition,
     context: &mut U8Pool,
+    options: &Options,
+    counter: &mut ActionCounter,
 ) -> ScanResult<(Option<Peek>, StructurePosition)> {
+    let interned_keys = options.interned_keys;
     //
     // Call the begin-trigger at the beginning of the array
     //
     if position == StructurePosition::ArrayBegin {
         if let Some(begin_action) = find_action(
             StructuralPseudoname::Array,
-            ContextIter::new(context),
+            context_iter(context, interned_keys),
             baton,
         ) {
-            match begin_action(rjiter, baton) {
+            let value_position = rjiter.current_index();
+            counter.count(value_position, true, context, interned_keys)?;
+            let op = begin_action(rjiter, baton);
+            match op {
                 StreamOp::None => (),
                 StreamOp::ValueIsConsumed => {
-                    return Ok((
-                        None,
-                        #[allow(unsafe_code)]
-                        *unsafe { context.top_assoc_obj::<StructurePosition>() }.ok_or_else(
-                            || ScanError::InternalError {
-                                position: rjiter.current_index(),
-                                message:
-                                    "Context stack is empty when handling ValueIsConsumed in array",
-                            },
-                        )?,
-                    ));
+                    #[allow(unsafe_code)]
+                    let parent_position = *unsafe { context.top_assoc_obj::<StructurePosition>() }
+                        .ok_or_else(|| ScanError::InternalError {
+                            position: rjiter.current_index(),
+                            message:
+                                "Context stack is empty when handling ValueIsConsumed in array",
+                        })?;
+                    check_value_consumed(
+                        rjiter,
+                        value_position,
+                        parent_position,
+                        context,
+                        interned_keys,
+                    )?;
+                    return Ok((None, parent_position));
                 }
-                StreamOp::Error(message) => {
-                    return Err(ScanError::ActionError {
+                StreamOp::Error(message) | StreamOp::ErrorWith { message, .. } => {
+                    return Err(action_error(
                         message,
-                        position: rjiter.current_index(),
-                    });
+                        op.error_code(),
+                        rjiter.current_index(),
+                        context,
+                        interned_keys,
+                    ));
                 }
             }
         }
 
-        // Push to context with position "middle in array" and name "#array"
-        if context
-            .push_assoc(StructurePosition::ArrayMiddle, b"#array")
-            .is_err()
-        {
-            return Err(ScanError::MaxNestingExceeded {
-                position: rjiter.current_index(),
-                level: context.len(),
-            });
-        }
+        // Push to context with position "middle in array", name "#array" and no items
+        let level = context.len();
+        context
+            .push_assoc(StructurePosition::ArrayMiddle, &array_entry(0))
+            .map_err(|e| push_context_error(&e, rjiter.current_index(), level))?;
     }
 
     //
@@ -304,6 +1237,13 @@ fn handle_array<B: Copy, R: Read>(
     //
     // If at the end of the array
     //
+    let count = context
+        .top_assoc_bytes::<StructurePosition>()
+        .and_then(array_entry_count)
+        .ok_or_else(|| ScanError::InternalError {
+            position: rjiter.current_index(),
+            message: "Context stack has no array entry when handling array",
+        })?;
     if peeked.is_none() {
         //
         // Pop the context before calling the end-trigger
@@ -321,14 +1261,18 @@ fn handle_array<B: Copy, R: Read>(
         //
         if let Some(end_action) = find_end_action(
             StructuralPseudoname::Array,
-            ContextIter::new(context),
+            ContextIter::with_array_len(context, count as usize).with_interned_keys(interned_keys),
             baton,
         ) {
+            counter.count(rjiter.current_index(), false, context, interned_keys)?;
             if let Err(message) = end_action(baton) {
-                return Err(ScanError::ActionError {
+                return Err(action_error(
                     message,
-                    position: rjiter.current_index(),
-                });
+                    None,
+                    rjiter.current_index(),
+                    context,
+                    interned_keys,
+                ));
             }
         }
         return Ok((
@@ -342,6 +1286,18 @@ fn handle_array<B: Copy, R: Read>(
             })?,
         ));
     }
+
+    // Count the item, the array entry is on the top of the context
+    let is_counted = context
+        .top_assoc_bytes_mut::<StructurePosition>()
+        .is_some_and(count_array_item);
+    if !is_counted {
+        // A plain `#array` filled by the caller, add the place for the count
+        let level = context.len();
+        context
+            .replace_top_assoc_bytes::<StructurePosition>(&array_entry(count.saturating_add(1)))
+            .map_err(|e| push_context_error(&e, rjiter.current_index(), level))?;
+    }
     Ok((peeked, StructurePosition::ArrayMiddle))
 }
 
@@ -374,6 +1330,11 @@ fn skip_basic_values<R: Read>(peeked: Peek, rjiter: &mut RJiter<R>) -> ScanResul
     })
 }
 
+// Whether the peeked value is a string, number, boolean or null
+fn is_atom(peeked: Peek) -> bool {
+    matches!(peeked, Peek::String | Peek::Null | Peek::True | Peek::False) || peeked.is_num()
+}
+
 ///
 /// Parses JSON and executes callbacks based on patterns.
 /// See `README.md` for examples of how to use this function.
@@ -414,14 +1375,16 @@ fn skip_basic_values<R: Read>(peeked: Peek, rjiter: &mut RJiter<R>) -> ScanResul
 /// # Error Handling in Actions
 ///
 /// When an action encounters an error, it returns `StreamOp::Error(message)` with a static string message.
-/// The `scan` function converts this to an `ActionError` with the message and position.
+/// The `scan` function converts this to an `ActionError` with the message, position and
+/// the context path (`ActionPath`, for example `#top/user/name`).
 ///
-/// If handlers need to preserve detailed errors (like IO error kinds from `embedded_io::ErrorKind`
-/// or specific `RJiter` error details), they must store them in their baton and retrieve them after `scan()` returns.
-/// The `StreamOp::Error` message is only a generic indicator that an error occurred.
+/// To tell the errors apart without parsing the message, an action returns
+/// `StreamOp::ErrorWith { message, code }`. The code is passed to `ActionError` as is,
+/// the caller maps it back to its own error type.
 ///
-/// See the `idtransform` implementation for an example of storing detailed errors in the baton and retrieving
-/// them after `scan()` completes.
+/// Errors which don't fit into a code (like `RJiter` error details) should be stored in
+/// the baton and retrieved after `scan()` returns. See the `idtransform` implementation
+/// for an example.
 ///
 /// # Working Buffer Sizing
 ///
@@ -435,11 +1398,49 @@ fn skip_basic_values<R: Read>(peeked: Peek, rjiter: &mut RJiter<R>) -> ScanResul
 /// - `sse_tokens`: Tokens to ignore at the top level, useful for server-side
 ///   events tokens like `data:` or `[DONE]`
 /// - `stop_early`: By default, `scan` processes multiple JSON objects (like JSONL format).
-///   Set to `true` to stop after the first complete element
+///   Set to `true` to stop after the first complete element. The skipped SSE tokens
+///   are not elements
+/// - `stop_when`: A predicate evaluated after each completed top-level value,
+///   for example, to stop after a number of records or bytes. Skipped SSE tokens
+///   are not values and don't trigger the predicate
+/// - `key_normalization`: Normalize object keys (for example, lowercase)
+///   before they are put to the context and matched
+/// - `long_key_policy`: Fail, truncate or skip the object keys which are
+///   longer than the `RJiter` buffer
+/// - `key_utf8_policy`: Fail on the object keys which are not valid UTF-8, with
+///   the error `InvalidKeyUtf8`, or replace the invalid sequences. By default,
+///   the keys are not checked
+/// - `keep_context`: Scan a document which arrives in segments, see below
+/// - `data_budget`: Limit the bytes which the actions store for each top-level value,
+///   see [`crate::budget::DataBudget`]
+/// - `max_actions`: Limit the actions which one `scan` call executes,
+///   the error is `ActionLimitExceeded`
+/// - `max_actions_at_position`: Limit the begin-actions which are executed one after
+///   another without moving the parser, the error is `ActionLoop`. In a valid scan,
+///   there are at most two such actions: for a key and for the object or array
+///   which is its value. Use the limits to turn a bug in the actions, which makes
+///   `scan` loop, into an error
+/// - `require_container_top`: Fail with `TopLevelAtom` when a top-level value is
+///   a string, number, boolean or null. By default, the `#atom` action is called
+///   with the context `#top`. The SSE tokens are still skipped
+///
+/// # Segmented Documents
+///
+/// With the option `keep_context`, `scan` stops without an error at the end of
+/// the input inside an object or an array, if the last value is complete.
+/// The context stack stays in `working_buffer`, and the next `scan` call with
+/// the same working buffer and the next segment continues from there. The matchers
+/// see the same context as for the whole document.
+///
+/// A segment should end after a complete value, not after a comma, key or colon.
+/// To start from a known place of a document, the caller can also fill
+/// the working buffer: `#top` with `StructurePosition::Top`, the keys with
+/// `StructurePosition::ObjectMiddle` and `#array` with `StructurePosition::ArrayMiddle`.
+/// After an error, the kept context is not valid, clear the working buffer.
 ///
 /// # Errors
 ///
-/// Returns any error from [`crate::error::Error`].
+/// Returns any error from [`crate::error::Error`], `InvalidOptions` if `Options::validate` fails.
 ///
 #[allow(clippy::too_many_lines, clippy::elidable_lifetime_names)]
 pub fn scan<'options, B: Copy, R: Read>(
@@ -451,22 +1452,79 @@ pub fn scan<'options, B: Copy, R: Read>(
     options: &Options<'options>,
 ) -> ScanResult<()> {
     let context = working_buffer; // Alias for better readability in function body
+    options.validate().map_err(ScanError::InvalidOptions)?;
 
+    // Continue a segmented document from the kept context, or start at the top
+    #[allow(unsafe_code)]
+    let kept_position = if options.keep_context {
+        unsafe { context.top_assoc_obj::<StructurePosition>() }.copied()
+    } else {
+        None
+    };
     let mut position = StructurePosition::Top;
-    context
-        .push_assoc(position, b"#top")
-        .map_err(|_e| ScanError::MaxNestingExceeded {
-            position: rjiter.current_index(),
-            level: 0,
-        })?;
+    if let Some(kept_position) = kept_position {
+        position = kept_position;
+    } else {
+        if let Some(data_budget) = options.data_budget {
+            data_budget.reset();
+        }
+        let level = context.len();
+        context
+            .push_assoc(position, b"#top")
+            .map_err(|e| push_context_error(&e, rjiter.current_index(), level))?;
+    }
 
+    let mut counter = ActionCounter::new(options);
     let mut is_progressed = false;
+    let mut is_sse_token = false;
+    let mut report = ScanReport {
+        values: 0,
+        position: rjiter.current_index(),
+    };
 
     'main_loop: loop {
-        if is_progressed && options.stop_early && position == StructurePosition::Top {
-            break;
+        // An action has reserved more than the budget
+        if let Some(exceeded) = options.data_budget.and_then(DataBudget::exceeded) {
+            return Err(ScanError::DataBudgetExceeded {
+                position: rjiter.current_index(),
+                limit: exceeded.limit,
+                used: exceeded.used,
+                requested: exceeded.requested,
+            });
+        }
+        if is_progressed && position == StructurePosition::Top {
+            // The skipped SSE tokens are not values
+            if !is_sse_token {
+                if options.stop_early {
+                    break;
+                }
+                if let Some(data_budget) = options.data_budget {
+                    data_budget.reset();
+                }
+                report.values += 1;
+                report.position = rjiter.current_index();
+                if options
+                    .stop_when
+                    .is_some_and(|stop_when| stop_when(&report))
+                {
+                    break;
+                }
+            }
         }
         is_progressed = true;
+        is_sse_token = false;
+
+        // The end of a segment after a complete value, keep the context for the next segment.
+        // Usually the next comma or bracket is already buffered, and only
+        // a buffer without data is checked for the end of the input.
+        if options.keep_context
+            && (position == StructurePosition::ObjectMiddle
+                || position == StructurePosition::ArrayMiddle)
+            && !rjiter.has_buffered_data()
+            && rjiter.finish().is_ok()
+        {
+            break;
+        }
 
         let mut peeked = None;
 
@@ -482,6 +1540,8 @@ pub fn scan<'options, B: Copy, R: Read>(
                 &find_end_action,
                 position,
                 context,
+                options,
+                &mut counter,
             ) {
                 Ok(new_position) => {
                     position = new_position;
@@ -502,6 +1562,8 @@ pub fn scan<'options, B: Copy, R: Read>(
                 &find_end_action,
                 position,
                 context,
+                options,
+                &mut counter,
             ) {
                 Ok((Some(arr_peeked), StructurePosition::ArrayMiddle)) => {
                     position = StructurePosition::ArrayMiddle;
@@ -567,22 +1629,49 @@ pub fn scan<'options, B: Copy, R: Read>(
             continue 'main_loop;
         }
 
+        // A scalar at the top level, but an SSE token is not a value and is skipped below
+        if position == StructurePosition::Top && options.require_container_top && is_atom(peeked) {
+            return Err(ScanError::TopLevelAtom {
+                peek: peeked,
+                position: rjiter.current_index(),
+            });
+        }
+
         //
         // Call the action for the atom, then
         // - return if an error
         // - continue to the main loop if value is consumed, or
         // - pass through to the default handler
         //
-        let action = find_action(StructuralPseudoname::Atom, ContextIter::new(context), baton);
+        let action = find_action(
+            StructuralPseudoname::Atom,
+            context_iter(context, options.interned_keys),
+            baton,
+        );
         if let Some(action) = action {
-            match action(rjiter, baton) {
-                StreamOp::Error(message) => {
-                    return Err(ScanError::ActionError {
+            let value_position = rjiter.current_index();
+            counter.count(value_position, true, context, options.interned_keys)?;
+            let op = action(rjiter, baton);
+            match op {
+                StreamOp::Error(message) | StreamOp::ErrorWith { message, .. } => {
+                    return Err(action_error(
                         message,
-                        position: rjiter.current_index(),
-                    })
+                        op.error_code(),
+                        rjiter.current_index(),
+                        context,
+                        options.interned_keys,
+                    ));
+                }
+                StreamOp::ValueIsConsumed => {
+                    check_value_consumed(
+                        rjiter,
+                        value_position,
+                        position,
+                        context,
+                        options.interned_keys,
+                    )?;
+                    continue 'main_loop;
                 }
-                StreamOp::ValueIsConsumed => continue 'main_loop,
                 StreamOp::None => (),
             }
         }
@@ -603,6 +1692,7 @@ pub fn scan<'options, B: Copy, R: Read>(
         {
             for sse_token in options.sse_tokens {
                 if rjiter.known_skip_token(sse_token).is_ok() {
+                    is_sse_token = true;
                     continue 'main_loop;
                 }
             }
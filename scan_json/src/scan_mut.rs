@@ -0,0 +1,126 @@
+//! The `scan_mut` function: `scan` with a mutable baton instead of a `Copy` one.
+//!
+//! With `scan`, mutable state is usually passed as `&RefCell<State>`. With `scan_mut`,
+//! the state is passed as `&mut State`, therefore it doesn't need interior mutability,
+//! and a scanning pipeline with `Send` state can be moved between threads or tasks.
+
+use core::cell::RefCell;
+
+use crate::error::Result as ScanResult;
+use crate::matcher::{Action, ActionMut, EndAction, EndActionMut, StreamOp, StructuralPseudoname};
+use crate::scan::{scan, Options};
+use crate::stack::ContextIter;
+use embedded_io::Read;
+use rjiter::RJiter;
+use u8pool::U8Pool;
+
+// State of `scan_mut` during a `scan` call. `scan` requires a `Copy` baton and
+// plain function pointers as actions, therefore the actions found by the user's
+// matchers are stored here, and fixed trampoline functions call them.
+struct MutBaton<'b, T, R: Read> {
+    baton: &'b mut T,
+    action: Option<ActionMut<T, R>>,
+    end_action: Option<EndActionMut<T>>,
+}
+
+type MutBatonCell<'c, 'b, T, R> = &'c RefCell<MutBaton<'b, T, R>>;
+
+fn call_action<T, R: Read>(rjiter: &mut RJiter<R>, cell: MutBatonCell<'_, '_, T, R>) -> StreamOp {
+    let mut state = cell.borrow_mut();
+    match state.action.take() {
+        Some(action) => action(rjiter, state.baton),
+        None => StreamOp::Error("scan_mut: action is not set"),
+    }
+}
+
+fn call_end_action<T, R: Read>(cell: MutBatonCell<'_, '_, T, R>) -> Result<(), &'static str> {
+    let mut state = cell.borrow_mut();
+    match state.end_action.take() {
+        Some(end_action) => end_action(state.baton),
+        None => Err("scan_mut: end action is not set"),
+    }
+}
+
+/// Like `scan`, but the baton is a mutable reference.
+///
+/// The matchers get the baton by shared reference, the actions get it by
+/// mutable reference. See `scan` for the description of the arguments.
+///
+/// # Errors
+///
+/// The same as for `scan`.
+///
+/// # Example
+///
+/// ```
+/// use scan_json::{scan_mut, ActionMut, EndActionMut, Options, RJiter, StreamOp};
+/// use scan_json::matcher::StructuralPseudoname;
+/// use scan_json::stack::ContextIter;
+/// use u8pool::U8Pool;
+///
+/// fn count_key(_rjiter: &mut RJiter<&[u8]>, count: &mut usize) -> StreamOp {
+///     *count += 1;
+///     StreamOp::None
+/// }
+///
+/// let json = r#"{"a": {"a": 1}, "b": 2}"#;
+/// let mut reader = json.as_bytes();
+/// let mut buffer = [0u8; 16];
+/// let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+/// let mut working_buffer = [0u8; 256];
+/// let mut context = U8Pool::new(&mut working_buffer, 16).unwrap();
+///
+/// let find_action = |name: StructuralPseudoname, mut context: ContextIter, _count: &usize| {
+///     (name == StructuralPseudoname::None && context.next() == Some(b"a".as_slice()))
+///         .then_some(count_key as ActionMut<usize, &[u8]>)
+/// };
+/// let find_end_action = |_name: StructuralPseudoname, _context: ContextIter, _count: &usize|
+///     -> Option<EndActionMut<usize>> { None };
+///
+/// let mut count = 0;
+/// scan_mut(find_action, find_end_action, &mut rjiter, &mut count, &mut context, &Options::new())
+///     .unwrap();
+/// assert_eq!(count, 2);
+/// ```
+pub fn scan_mut<T, R: Read>(
+    find_action: impl Fn(StructuralPseudoname, ContextIter, &T) -> Option<ActionMut<T, R>>,
+    find_end_action: impl Fn(StructuralPseudoname, ContextIter, &T) -> Option<EndActionMut<T>>,
+    rjiter: &mut RJiter<R>,
+    baton: &mut T,
+    working_buffer: &mut U8Pool,
+    options: &Options<'_>,
+) -> ScanResult<()> {
+    let cell = RefCell::new(MutBaton {
+        baton,
+        action: None,
+        end_action: None,
+    });
+
+    let find_action_mut = |name: StructuralPseudoname,
+                           context: ContextIter,
+                           cell: MutBatonCell<'_, '_, T, R>|
+     -> Option<Action<MutBatonCell<'_, '_, T, R>, R>> {
+        let mut state = cell.borrow_mut();
+        let action = find_action(name, context, state.baton)?;
+        state.action = Some(action);
+        Some(call_action::<T, R>)
+    };
+    let find_end_action_mut = |name: StructuralPseudoname,
+                               context: ContextIter,
+                               cell: MutBatonCell<'_, '_, T, R>|
+     -> Option<EndAction<MutBatonCell<'_, '_, T, R>>> {
+        let mut state = cell.borrow_mut();
+        let end_action = find_end_action(name, context, state.baton)?;
+        state.end_action = Some(end_action);
+        Some(call_end_action::<T, R>)
+    };
+
+    scan(
+        find_action_mut,
+        find_end_action_mut,
+        rjiter,
+        &cell,
+        working_buffer,
+        options,
+    )
+}
@@ -0,0 +1,652 @@
+//! Validate a JSON stream against a small subset of JSON Schema, in one pass.
+//!
+//! `Schema::compile` turns the schema into a table of nodes. During `scan`,
+//! `find_action` looks up the node for the context path, and the actions check
+//! the values. All the violations are collected, with the context paths.
+//!
+//! Supported keywords: `type`, `properties`, `required`, `items`, `enum` (with
+//! scalar values) and `maxLength`. The annotations `$schema`, `$id`, `$comment`,
+//! `title`, `description`, `default` and `examples` are ignored, other keywords
+//! are rejected.
+//!
+//! ```
+//! use scan_json::schema::{validate, Schema, ViolationKind};
+//! use scan_json::RJiter;
+//! use u8pool::U8Pool;
+//!
+//! let schema = Schema::compile(r#"{
+//!     "type": "object",
+//!     "required": ["id", "name"],
+//!     "properties": {
+//!         "id": {"type": "integer"},
+//!         "tags": {"type": "array", "items": {"enum": ["a", "b"]}}
+//!     }
+//! }"#).unwrap();
+//!
+//! let json = r#"{"id": 1.5, "tags": ["a", "c"]}"#;
+//! let mut reader = json.as_bytes();
+//! let mut buffer = [0u8; 64];
+//! let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+//! let mut context_buffer = [0u8; 512];
+//! let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+//!
+//! let violations = validate(&schema, &mut rjiter, &mut context).unwrap();
+//! let paths: Vec<&str> = violations.iter().map(|v| v.path.as_str()).collect();
+//! assert_eq!(paths, ["#top/id", "#top/tags/#array", "#top"]);
+//! assert_eq!(violations[2].kind, ViolationKind::MissingRequired("name".to_string()));
+//! ```
+
+extern crate alloc;
+
+use crate::error::Error as ScanError;
+use crate::expect::ValueType;
+use crate::matcher::{Action, EndAction, StreamOp, StructuralPseudoname};
+use crate::scan::{scan, Options};
+use crate::stack::ContextIter;
+use crate::{RJiter, Result as ScanResult};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+use rjiter::jiter::{JsonError, JsonValue};
+use u8pool::U8Pool;
+
+/// Error of `Schema::compile`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The schema is not valid JSON
+    Json(JsonError),
+    /// The schema or a subschema is not an object
+    NotAnObject,
+    /// A keyword outside of the supported subset
+    UnsupportedKeyword(String),
+    /// A keyword has an invalid value, for example, `"required": "id"`
+    InvalidKeyword(String),
+}
+
+impl core::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SchemaError::Json(err) => write!(f, "Schema is not valid JSON: {err}"),
+            SchemaError::NotAnObject => write!(f, "Schema is not an object"),
+            SchemaError::UnsupportedKeyword(keyword) => {
+                write!(f, "Unsupported schema keyword \"{keyword}\"")
+            }
+            SchemaError::InvalidKeyword(keyword) => {
+                write!(f, "Invalid value of the schema keyword \"{keyword}\"")
+            }
+        }
+    }
+}
+
+// Bits of `Node::types`
+const TYPE_STRING: u8 = 1;
+const TYPE_NUMBER: u8 = 1 << 1;
+const TYPE_INTEGER: u8 = 1 << 2;
+const TYPE_BOOLEAN: u8 = 1 << 3;
+const TYPE_NULL: u8 = 1 << 4;
+const TYPE_OBJECT: u8 = 1 << 5;
+const TYPE_ARRAY: u8 = 1 << 6;
+
+fn type_bit(value_type: ValueType) -> u8 {
+    match value_type {
+        ValueType::String => TYPE_STRING,
+        ValueType::Number => TYPE_NUMBER,
+        ValueType::Bool => TYPE_BOOLEAN,
+        ValueType::Null => TYPE_NULL,
+        ValueType::Object => TYPE_OBJECT,
+        ValueType::Array => TYPE_ARRAY,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum EnumValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    // The allowed types, `0` if any type is allowed
+    types: u8,
+    properties: Vec<(String, usize)>,
+    required: Vec<String>,
+    items: Option<usize>,
+    enum_values: Option<Vec<EnumValue>>,
+    max_length: Option<usize>,
+}
+
+impl Node {
+    fn allows(&self, value_type: ValueType) -> bool {
+        self.types == 0
+            || self.types & type_bit(value_type) != 0
+            || (value_type == ValueType::Number && self.types & TYPE_INTEGER != 0)
+    }
+
+    // A number should be checked for a fraction or an exponent
+    fn needs_integer(&self) -> bool {
+        self.types != 0 && self.types & TYPE_NUMBER == 0 && self.types & TYPE_INTEGER != 0
+    }
+
+    fn allows_enum(&self, value: &EnumValue) -> bool {
+        self.enum_values
+            .as_ref()
+            .is_none_or(|values| values.contains(value))
+    }
+}
+
+/// A compiled schema, see the module documentation for the supported keywords
+#[derive(Debug)]
+pub struct Schema {
+    // The root node is the first one
+    nodes: Vec<Node>,
+}
+
+impl Schema {
+    /// Compile the schema from its JSON text.
+    ///
+    /// # Errors
+    ///
+    /// `SchemaError` if the schema is not valid JSON, uses an unsupported keyword,
+    /// or a keyword has an invalid value.
+    pub fn compile(json: &str) -> Result<Self, SchemaError> {
+        let value = JsonValue::parse(json.as_bytes(), false).map_err(SchemaError::Json)?;
+        let mut nodes = Vec::new();
+        compile_node(&mut nodes, &value)?;
+        Ok(Self { nodes })
+    }
+
+    // The node for the context names, from `#top` to the innermost name
+    fn resolve(&self, names: &[&[u8]]) -> Option<usize> {
+        let mut index = 0;
+        for name in names.iter().skip(1) {
+            let node = self.nodes.get(index)?;
+            index = if *name == b"#array" {
+                node.items?
+            } else {
+                node.properties
+                    .iter()
+                    .find(|(key, _)| key.as_bytes() == *name)?
+                    .1
+            };
+        }
+        Some(index)
+    }
+}
+
+fn compile_node(nodes: &mut Vec<Node>, value: &JsonValue) -> Result<usize, SchemaError> {
+    let JsonValue::Object(object) = value else {
+        return Err(SchemaError::NotAnObject);
+    };
+    let invalid = |keyword: &str| SchemaError::InvalidKeyword(keyword.to_owned());
+    // Reserve the index before the subschemas get theirs
+    let index = nodes.len();
+    nodes.push(Node::default());
+    let mut node = Node::default();
+    for (keyword, value) in object.iter() {
+        match keyword.as_ref() {
+            "type" => node.types = compile_types(value).ok_or_else(|| invalid("type"))?,
+            "properties" => {
+                let JsonValue::Object(properties) = value else {
+                    return Err(invalid("properties"));
+                };
+                for (name, subschema) in properties.iter() {
+                    let child = compile_node(nodes, subschema)?;
+                    node.properties.push((name.as_ref().to_owned(), child));
+                }
+            }
+            "required" => {
+                let JsonValue::Array(names) = value else {
+                    return Err(invalid("required"));
+                };
+                for name in names.iter() {
+                    let JsonValue::Str(name) = name else {
+                        return Err(invalid("required"));
+                    };
+                    node.required.push(name.as_ref().to_owned());
+                }
+            }
+            "items" => node.items = Some(compile_node(nodes, value)?),
+            "enum" => {
+                let JsonValue::Array(values) = value else {
+                    return Err(invalid("enum"));
+                };
+                let values = values
+                    .iter()
+                    .map(enum_value)
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid("enum"))?;
+                node.enum_values = Some(values);
+            }
+            "maxLength" => {
+                let JsonValue::Int(max_length) = value else {
+                    return Err(invalid("maxLength"));
+                };
+                node.max_length =
+                    Some(usize::try_from(*max_length).map_err(|_| invalid("maxLength"))?);
+            }
+            "$schema" | "$id" | "$comment" | "title" | "description" | "default" | "examples" => {}
+            other => return Err(SchemaError::UnsupportedKeyword(other.to_owned())),
+        }
+    }
+    if let Some(slot) = nodes.get_mut(index) {
+        *slot = node;
+    }
+    Ok(index)
+}
+
+// A type name or an array of them
+fn compile_types(value: &JsonValue) -> Option<u8> {
+    let type_name_bit = |name: &JsonValue| match name {
+        JsonValue::Str(name) => match name.as_ref() {
+            "string" => Some(TYPE_STRING),
+            "number" => Some(TYPE_NUMBER),
+            "integer" => Some(TYPE_INTEGER),
+            "boolean" => Some(TYPE_BOOLEAN),
+            "null" => Some(TYPE_NULL),
+            "object" => Some(TYPE_OBJECT),
+            "array" => Some(TYPE_ARRAY),
+            _ => None,
+        },
+        _ => None,
+    };
+    match value {
+        JsonValue::Array(names) => names
+            .iter()
+            .try_fold(0, |types, name| Some(types | type_name_bit(name)?)),
+        name => type_name_bit(name),
+    }
+}
+
+// Only scalar values are supported in `enum`
+fn enum_value(value: &JsonValue) -> Option<EnumValue> {
+    #[allow(clippy::cast_precision_loss)]
+    match value {
+        JsonValue::Null => Some(EnumValue::Null),
+        JsonValue::Bool(b) => Some(EnumValue::Bool(*b)),
+        JsonValue::Int(n) => Some(EnumValue::Number(*n as f64)),
+        JsonValue::Float(n) => Some(EnumValue::Number(*n)),
+        JsonValue::Str(s) => Some(EnumValue::String(s.as_ref().to_owned())),
+        _ => None,
+    }
+}
+
+/// What is wrong with a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The type is not allowed by `type`. A number with a fraction or an exponent
+    /// is not an `integer`, it is reported as `ValueType::Number`.
+    WrongType {
+        /// The type of the value
+        found: ValueType,
+    },
+    /// The object doesn't have the key from `required`
+    MissingRequired(String),
+    /// The value is not one of `enum`
+    NotInEnum,
+    /// The string has more code points than `maxLength`
+    TooLong {
+        /// The limit from the schema
+        max_length: usize,
+        /// The number of code points in the string
+        length: usize,
+    },
+}
+
+/// A violation of the schema found by `validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The context path of the value: the names joined with `/`, starting
+    /// with `#top`, for example `#top/user/name`. For a missing key, the path
+    /// of the object.
+    pub path: String,
+    /// The byte position of the value, for a missing key, of the object
+    pub position: usize,
+    /// What is wrong
+    pub kind: ViolationKind,
+}
+
+impl core::fmt::Display for Violation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Violation {
+            path,
+            position,
+            kind,
+        } = self;
+        write!(f, "{path} at position {position}: ")?;
+        match kind {
+            ViolationKind::WrongType { found } => write!(f, "type {found:?} is not allowed"),
+            ViolationKind::MissingRequired(key) => write!(f, "missing required key \"{key}\""),
+            ViolationKind::NotInEnum => write!(f, "value is not in enum"),
+            ViolationKind::TooLong { max_length, length } => {
+                write!(
+                    f,
+                    "string has {length} characters, maxLength is {max_length}"
+                )
+            }
+        }
+    }
+}
+
+// An object being validated, to check `required` at its end
+struct Frame {
+    node: usize,
+    seen: Vec<bool>,
+    path: String,
+    position: usize,
+}
+
+struct ValidateState<'a> {
+    schema: &'a Schema,
+    frames: Vec<Frame>,
+    violations: Vec<Violation>,
+    // The node and the path of the value for the action
+    current: usize,
+    current_path: String,
+    rjiter_error: Option<rjiter::Error>,
+}
+
+impl ValidateState<'_> {
+    fn report(&mut self, position: usize, kind: ViolationKind) {
+        self.violations.push(Violation {
+            path: self.current_path.clone(),
+            position,
+            kind,
+        });
+    }
+
+    fn fail(&mut self, error: rjiter::Error) -> StreamOp {
+        self.rjiter_error = Some(error);
+        StreamOp::Error("Failed to read the value")
+    }
+}
+
+type ValidateBaton<'s, 'a> = &'s RefCell<ValidateState<'a>>;
+
+// Counts the code points of the decoded string
+struct CharCounter {
+    count: usize,
+}
+
+impl ErrorType for CharCounter {
+    type Error = ErrorKind;
+}
+
+impl Write for CharCounter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.count += buf
+            .iter()
+            .filter(|&&b| b & 0b1100_0000 != 0b1000_0000)
+            .count();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn on_object<R: Read>(rjiter: &mut RJiter<R>, baton: ValidateBaton) -> StreamOp {
+    let mut state = baton.borrow_mut();
+    let position = rjiter.current_index();
+    let schema = state.schema;
+    let Some(node) = schema.nodes.get(state.current) else {
+        return StreamOp::Error("Schema node not found");
+    };
+    if !node.allows(ValueType::Object) {
+        state.report(
+            position,
+            ViolationKind::WrongType {
+                found: ValueType::Object,
+            },
+        );
+    }
+    let frame = Frame {
+        node: state.current,
+        seen: vec![false; node.required.len()],
+        path: core::mem::take(&mut state.current_path),
+        position,
+    };
+    state.frames.push(frame);
+    StreamOp::None
+}
+
+fn on_object_end(baton: ValidateBaton) -> Result<(), &'static str> {
+    let mut state = baton.borrow_mut();
+    let frame = state.frames.pop().ok_or("Object frame not found")?;
+    let schema = state.schema;
+    let node = schema
+        .nodes
+        .get(frame.node)
+        .ok_or("Schema node not found")?;
+    for (key, seen) in node.required.iter().zip(frame.seen) {
+        if !seen {
+            state.violations.push(Violation {
+                path: frame.path.clone(),
+                position: frame.position,
+                kind: ViolationKind::MissingRequired(key.clone()),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn on_array<R: Read>(rjiter: &mut RJiter<R>, baton: ValidateBaton) -> StreamOp {
+    let mut state = baton.borrow_mut();
+    let schema = state.schema;
+    let Some(node) = schema.nodes.get(state.current) else {
+        return StreamOp::Error("Schema node not found");
+    };
+    if !node.allows(ValueType::Array) {
+        let kind = ViolationKind::WrongType {
+            found: ValueType::Array,
+        };
+        state.report(rjiter.current_index(), kind);
+    }
+    StreamOp::None
+}
+
+// A key value or an atom. Objects and arrays are checked by `on_object` and `on_array`.
+fn on_value<R: Read>(rjiter: &mut RJiter<R>, baton: ValidateBaton) -> StreamOp {
+    let mut state = baton.borrow_mut();
+    let peeked = match rjiter.peek() {
+        Ok(peeked) => peeked,
+        Err(e) => return state.fail(e),
+    };
+    // Let `scan` report an unexpected token
+    let Some(found) = ValueType::of(peeked) else {
+        return StreamOp::None;
+    };
+    if found == ValueType::Object || found == ValueType::Array {
+        return StreamOp::None;
+    }
+    let position = rjiter.current_index();
+    let schema = state.schema;
+    let Some(node) = schema.nodes.get(state.current) else {
+        return StreamOp::Error("Schema node not found");
+    };
+    if !node.allows(found) {
+        state.report(position, ViolationKind::WrongType { found });
+        // Consume to not get the same value as an atom
+        return match rjiter.next_skip() {
+            Ok(()) => StreamOp::ValueIsConsumed,
+            Err(e) => state.fail(e),
+        };
+    }
+
+    let value = match found {
+        ValueType::Number if node.needs_integer() || node.enum_values.is_some() => {
+            let bytes = match rjiter.next_number_bytes() {
+                Ok(bytes) => bytes,
+                Err(e) => return state.fail(e),
+            };
+            if node.needs_integer() && bytes.iter().any(|b| matches!(b, b'.' | b'e' | b'E')) {
+                state.report(position, ViolationKind::WrongType { found });
+                return StreamOp::ValueIsConsumed;
+            }
+            let number = core::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok());
+            number.map(EnumValue::Number)
+        }
+        ValueType::String if node.enum_values.is_some() => {
+            let s = match rjiter.next_str() {
+                Ok(s) => s,
+                Err(e) => return state.fail(e),
+            };
+            if let Some(max_length) = node.max_length {
+                let length = s.chars().count();
+                if length > max_length {
+                    state.report(position, ViolationKind::TooLong { max_length, length });
+                }
+            }
+            Some(EnumValue::String(s.to_owned()))
+        }
+        // Without `enum`, the string can be longer than the `RJiter` buffer
+        ValueType::String if node.max_length.is_some() => {
+            let mut counter = CharCounter { count: 0 };
+            if let Err(e) = rjiter.write_long_str(&mut counter) {
+                return state.fail(e);
+            }
+            if let Some(max_length) = node.max_length.filter(|&max| counter.count > max) {
+                let length = counter.count;
+                state.report(position, ViolationKind::TooLong { max_length, length });
+            }
+            return StreamOp::ValueIsConsumed;
+        }
+        ValueType::Bool if node.enum_values.is_some() => match rjiter.next_bool() {
+            Ok(b) => Some(EnumValue::Bool(b)),
+            Err(e) => return state.fail(e),
+        },
+        ValueType::Null if node.enum_values.is_some() => match rjiter.next_null() {
+            Ok(()) => Some(EnumValue::Null),
+            Err(e) => return state.fail(e),
+        },
+        // Nothing to check, `scan` skips the value
+        _ => return StreamOp::None,
+    };
+    if !value.is_some_and(|value| node.allows_enum(&value)) {
+        state.report(position, ViolationKind::NotInEnum);
+    }
+    StreamOp::ValueIsConsumed
+}
+
+fn context_path(names: &[&[u8]]) -> String {
+    let mut path = String::new();
+    for name in names {
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(&String::from_utf8_lossy(name));
+    }
+    path
+}
+
+fn find_action<'s, 'a, R: Read>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    baton: ValidateBaton<'s, 'a>,
+) -> Option<Action<ValidateBaton<'s, 'a>, R>> {
+    let mut state = baton.borrow_mut();
+    // From `#top` to the innermost name
+    let mut names: Vec<&[u8]> = context.collect();
+    names.reverse();
+
+    if structural_pseudoname == StructuralPseudoname::None {
+        // Mark the key as seen in its object
+        let schema = state.schema;
+        let parent = names.get(..names.len().saturating_sub(1)).unwrap_or(&[]);
+        let parent_node = schema
+            .resolve(parent)
+            .and_then(|i| Some((i, schema.nodes.get(i)?)));
+        if let (Some(frame), Some(key), Some((parent_index, parent_node))) =
+            (state.frames.last_mut(), names.last(), parent_node)
+        {
+            if frame.node == parent_index {
+                for (name, seen) in parent_node.required.iter().zip(frame.seen.iter_mut()) {
+                    *seen |= name.as_bytes() == *key;
+                }
+            }
+        }
+    }
+
+    state.current = state.schema.resolve(&names)?;
+    state.current_path = context_path(&names);
+    match structural_pseudoname {
+        StructuralPseudoname::Object => Some(on_object),
+        StructuralPseudoname::Array => Some(on_array),
+        StructuralPseudoname::Atom | StructuralPseudoname::None => Some(on_value),
+    }
+}
+
+fn find_end_action<'s, 'a>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    baton: ValidateBaton<'s, 'a>,
+) -> Option<EndAction<ValidateBaton<'s, 'a>>> {
+    if structural_pseudoname != StructuralPseudoname::Object {
+        return None;
+    }
+    let mut names: Vec<&[u8]> = context.collect();
+    names.reverse();
+    baton.borrow().schema.resolve(&names)?;
+    Some(on_object_end)
+}
+
+/// Validate the next top-level value against the schema and return the violations.
+///
+/// The value is scanned once, all the violations are collected, in the order
+/// they are found. A missing required key is found at the end of its object.
+///
+/// A string value with `enum` should fit into the `RJiter` buffer, as keys do.
+/// For `maxLength` only, the string can be longer.
+///
+/// # Arguments
+///
+/// * `schema` - The compiled schema
+/// * `rjiter` - Mutable reference to the JSON iterator
+/// * `working_buffer` - Working buffer for context stack (see [`crate::scan()`] for details)
+///
+/// # Errors
+///
+/// If `scan` fails (malformed json, nesting too deep, etc), return `scan`'s error.
+/// Violations of the schema are not errors.
+pub fn validate<R: Read>(
+    schema: &Schema,
+    rjiter: &mut RJiter<R>,
+    working_buffer: &mut U8Pool,
+) -> ScanResult<Vec<Violation>> {
+    let state = RefCell::new(ValidateState {
+        schema,
+        frames: Vec::new(),
+        violations: Vec::new(),
+        current: 0,
+        current_path: String::new(),
+        rjiter_error: None,
+    });
+
+    let scan_result = scan(
+        find_action,
+        find_end_action,
+        rjiter,
+        &state,
+        working_buffer,
+        &Options {
+            stop_early: true,
+            ..Options::new()
+        },
+    );
+
+    let state = state.into_inner();
+    if let Err(scan_error) = scan_result {
+        if let Some(rjiter_error) = state.rjiter_error {
+            return Err(ScanError::RJiterError(rjiter_error));
+        }
+        return Err(scan_error);
+    }
+    Ok(state.violations)
+}
@@ -1,12 +1,106 @@
 //! Stack management for JSON parsing context
 
+use crate::matcher::ValueKind;
 use crate::scan::StructurePosition;
 use u8pool::{U8Pool, U8PoolAssocRevIter};
 
+// The name of an array in the context. `scan` stores the number of the items
+// of the array after the name, as `u32` little-endian. An entry filled by the
+// caller for `keep_context` can be the plain name, then the count starts at 0.
+const ARRAY_NAME: &[u8] = b"#array";
+const ARRAY_ENTRY_LEN: usize = ARRAY_NAME.len() + 4;
+
+// The context entry of an array with `count` items so far
+pub(crate) fn array_entry(count: u32) -> [u8; ARRAY_ENTRY_LEN] {
+    let mut entry = [0u8; ARRAY_ENTRY_LEN];
+    let (name, count_bytes) = entry.split_at_mut(ARRAY_NAME.len());
+    name.copy_from_slice(ARRAY_NAME);
+    count_bytes.copy_from_slice(&count.to_le_bytes());
+    entry
+}
+
+// The number of the items in the context entry of an array
+pub(crate) fn array_entry_count(entry: &[u8]) -> Option<u32> {
+    let count_bytes = entry.strip_prefix(ARRAY_NAME)?;
+    if count_bytes.is_empty() {
+        return Some(0);
+    }
+    Some(u32::from_le_bytes(count_bytes.try_into().ok()?))
+}
+
+// Add an item to the count in the context entry of an array, in place.
+// Returns `false` if the entry has no count.
+pub(crate) fn count_array_item(entry: &mut [u8]) -> bool {
+    let count_bytes = entry
+        .get_mut(ARRAY_NAME.len()..)
+        .and_then(|count_bytes| <&mut [u8; 4]>::try_from(count_bytes).ok());
+    let Some(count_bytes) = count_bytes else {
+        return false;
+    };
+    *count_bytes = u32::from_le_bytes(*count_bytes)
+        .saturating_add(1)
+        .to_le_bytes();
+    true
+}
+
+// The first byte of a context entry which refers to an interned key, the second
+// byte is the index in `Options::interned_keys`. The byte is never in UTF-8,
+// therefore only invalid keys can start with it. Such keys are stored with one
+// more marker byte in front.
+const INTERNED_MARKER: u8 = 0xFF;
+
+/// The maximum number of keys in `Options::interned_keys`, the other keys are not interned
+pub const MAX_INTERNED_KEYS: usize = 255;
+
+// Replace the stored key `out[..len]` by a reference if the key is interned,
+// or escape it if it starts with the marker. `out` should have one more byte
+// after the key. Returns the length of the context entry.
+// A key which is not longer than a reference is not looked up.
+pub(crate) fn intern_key(out: &mut [u8], len: usize, interned_keys: &[&[u8]]) -> usize {
+    if interned_keys.is_empty() {
+        return len;
+    }
+    let key = out.get(..len).unwrap_or_default();
+    let is_marked = key.first() == Some(&INTERNED_MARKER);
+    let index = if len > 2 {
+        interned_keys
+            .iter()
+            .take(MAX_INTERNED_KEYS)
+            .position(|interned| *interned == key)
+    } else {
+        None
+    };
+    if let (Some(index), Some(entry)) = (index, out.get_mut(..2)) {
+        #[allow(clippy::cast_possible_truncation)]
+        entry.copy_from_slice(&[INTERNED_MARKER, index as u8]);
+        return 2;
+    }
+    if is_marked && len < out.len() {
+        out.copy_within(..len, 1);
+        return len + 1;
+    }
+    len
+}
+
+// The key of a context entry, see `intern_key`
+fn resolve_key<'a>(entry: &'a [u8], interned_keys: &'a [&'a [u8]]) -> &'a [u8] {
+    if interned_keys.is_empty() {
+        return entry;
+    }
+    match entry {
+        [INTERNED_MARKER, INTERNED_MARKER, ..] => entry.get(1..).unwrap_or(entry),
+        [INTERNED_MARKER, index] => interned_keys.get(usize::from(*index)).unwrap_or(&entry),
+        _ => entry,
+    }
+}
+
 /// Wrapper around the `U8Pool` associated iterator for context iteration
 /// Provides a convenient interface with syntactic sugar for for-loops and `.next()`
 pub struct ContextIter<'a> {
     inner: U8PoolAssocRevIter<'a, StructurePosition>,
+    value_kind: Option<ValueKind>,
+    array_len: Option<usize>,
+    interned_keys: &'a [&'a [u8]],
 }
 
 impl<'a> ContextIter<'a> {
@@ -16,9 +110,57 @@ impl<'a> ContextIter<'a> {
         Self {
             #[allow(unsafe_code)]
             inner: unsafe { pool.iter_assoc_rev::<StructurePosition>() },
+            value_kind: None,
+            array_len: None,
+            interned_keys: &[],
         }
     }
 
+    /// Creates a new `ContextIter` for an object key whose value is of the given kind
+    #[must_use]
+    pub fn with_value_kind(pool: &'a U8Pool, value_kind: ValueKind) -> Self {
+        Self {
+            value_kind: Some(value_kind),
+            ..Self::new(pool)
+        }
+    }
+
+    /// Returns the kind of the value of the current key, if known.
+    /// It is known only for key events when `scan` is called
+    /// with the option `defer_key_dispatch`.
+    #[must_use]
+    pub fn value_kind(&self) -> Option<ValueKind> {
+        self.value_kind
+    }
+
+    /// Creates a new `ContextIter` for the end of an array with `array_len` items
+    #[must_use]
+    pub fn with_array_len(pool: &'a U8Pool, array_len: usize) -> Self {
+        Self {
+            array_len: Some(array_len),
+            ..Self::new(pool)
+        }
+    }
+
+    /// Returns the number of the items of the array which has just ended.
+    /// It is known only for the end event of an array (`StructuralPseudoname::Array`
+    /// in `find_end_action`).
+    ///
+    /// An end action doesn't get the context, the matcher can pass the
+    /// number to the action through the baton.
+    #[must_use]
+    pub fn array_len(&self) -> Option<usize> {
+        self.array_len
+    }
+
+    /// Sets the interned keys, the same as in `Options::interned_keys` of `scan`,
+    /// to get the keys instead of the references to them
+    #[must_use]
+    pub fn with_interned_keys(mut self, interned_keys: &'a [&'a [u8]]) -> Self {
+        self.interned_keys = interned_keys;
+        self
+    }
+
     /// Returns the number of items in the context
     #[must_use]
     pub fn len(&self) -> usize {
@@ -36,7 +178,14 @@ impl<'a> Iterator for ContextIter<'a> {
     type Item = &'a [u8];
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(_assoc, key_slice)| key_slice)
+        self.inner.next().map(|(assoc, key_slice)| {
+            if *assoc == StructurePosition::ArrayMiddle {
+                // Without the number of the items
+                key_slice.get(..ARRAY_NAME.len()).unwrap_or(key_slice)
+            } else {
+                resolve_key(key_slice, self.interned_keys)
+            }
+        })
     }
 }
 
@@ -44,6 +193,9 @@ impl Clone for ContextIter<'_> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            value_kind: self.value_kind,
+            array_len: self.array_len,
+            interned_keys: self.interned_keys,
         }
     }
 }
@@ -0,0 +1,232 @@
+//! Test support: capture the events of `scan` and compare them with a golden trace.
+//!
+//! Instead of writing actions for a test, give `run_and_capture` the patterns
+//! of interest (see [`crate::matcher::path_match()`]), and compare the captured events
+//! with the expected text or with a golden file.
+
+extern crate std;
+
+use crate::error::Result as ScanResult;
+use crate::matcher::{path_match, Action, EndAction, StructuralPseudoname};
+use crate::scan::{scan, Options};
+use crate::stack::ContextIter;
+use crate::RJiter;
+use core::cell::RefCell;
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+use u8pool::U8Pool;
+
+/// The environment variable to rewrite the golden files instead of comparing
+pub const UPDATE_GOLDEN_ENV: &str = "SCAN_JSON_UPDATE_GOLDEN";
+
+/// Whether `scan` is entering or leaving the element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// `find_action` is called: a key, the begin of an object or an array, or an atom
+    Begin,
+    /// `find_end_action` is called: after a key, or the end of an object or an array
+    End,
+}
+
+/// An event of `scan` which matches a trigger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRecord<'t> {
+    /// Begin or end
+    pub kind: EventKind,
+    /// The context names joined with `/`, starting with `#top`.
+    /// A structural event is added as the last name: `#object`, `#array` or `#atom`.
+    pub path: String,
+    /// The first trigger which matches the event
+    pub trigger: &'t str,
+}
+
+/// One line of a trace: `begin #top/user/name <- user/name`
+impl core::fmt::Display for EventRecord<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let kind = match self.kind {
+            EventKind::Begin => "begin",
+            EventKind::End => "end",
+        };
+        write!(f, "{kind} {} <- {}", self.path, self.trigger)
+    }
+}
+
+struct Capture<'t> {
+    triggers: &'t [&'t str],
+    events: Vec<EventRecord<'t>>,
+}
+
+type CaptureBaton<'a, 't> = &'a RefCell<Capture<'t>>;
+
+fn context_to_text(structural_pseudoname: StructuralPseudoname, context: ContextIter) -> String {
+    let mut names: Vec<&[u8]> = context.collect();
+    names.reverse();
+    let mut text = String::new();
+    for (i, name) in names.iter().enumerate() {
+        if i > 0 {
+            text.push('/');
+        }
+        text.push_str(&String::from_utf8_lossy(name));
+    }
+    let structural = match structural_pseudoname {
+        StructuralPseudoname::Object => "/#object",
+        StructuralPseudoname::Array => "/#array",
+        StructuralPseudoname::Atom => "/#atom",
+        StructuralPseudoname::None => "",
+    };
+    text.push_str(structural);
+    text
+}
+
+fn record(
+    kind: EventKind,
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    baton: CaptureBaton,
+) {
+    let mut capture = baton.borrow_mut();
+    let trigger = capture
+        .triggers
+        .iter()
+        .find(|trigger| path_match(trigger, structural_pseudoname, context.clone()));
+    if let Some(trigger) = trigger {
+        let event = EventRecord {
+            kind,
+            path: context_to_text(structural_pseudoname, context),
+            trigger,
+        };
+        capture.events.push(event);
+    }
+}
+
+fn find_action<'a, 't>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    baton: CaptureBaton<'a, 't>,
+) -> Option<Action<CaptureBaton<'a, 't>, &'a [u8]>> {
+    record(EventKind::Begin, structural_pseudoname, context, baton);
+    None
+}
+
+fn find_end_action<'a, 't>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    baton: CaptureBaton<'a, 't>,
+) -> Option<EndAction<CaptureBaton<'a, 't>>> {
+    record(EventKind::End, structural_pseudoname, context, baton);
+    None
+}
+
+/// Scan `json` and capture the events which match the triggers, in the order of `scan`.
+///
+/// A trigger is a pattern for `path_match`, for example, `user/name` or `items/#array`.
+/// No actions are executed, therefore `scan` skips all the values.
+///
+/// # Errors
+///
+/// The error of `scan`.
+pub fn run_and_capture_with<'t>(
+    json: &str,
+    triggers: &'t [&'t str],
+    options: &Options,
+) -> ScanResult<Vec<EventRecord<'t>>> {
+    let mut reader = json.as_bytes();
+    let mut rjiter_buffer = vec![0u8; 4096];
+    let mut rjiter = RJiter::new(&mut reader, &mut rjiter_buffer);
+    let mut context_buffer = vec![0u8; 8192];
+    let mut context =
+        U8Pool::new(&mut context_buffer, 128).map_err(|_| crate::Error::InternalError {
+            position: 0,
+            message: "Failed to create the context pool",
+        })?;
+    let capture = RefCell::new(Capture {
+        triggers,
+        events: Vec::new(),
+    });
+
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &capture,
+        &mut context,
+        options,
+    )?;
+    Ok(capture.into_inner().events)
+}
+
+/// Scan `json` with the default options and capture the events which match the triggers,
+/// see `run_and_capture_with`.
+///
+/// # Errors
+///
+/// The error of `scan`.
+pub fn run_and_capture<'t>(
+    json: &str,
+    triggers: &'t [&'t str],
+) -> ScanResult<Vec<EventRecord<'t>>> {
+    run_and_capture_with(json, triggers, &Options::new())
+}
+
+/// The events as text, one event per line
+#[must_use]
+pub fn format_trace(events: &[EventRecord]) -> String {
+    use core::fmt::Write as _;
+
+    let mut text = String::new();
+    for event in events {
+        let _ = writeln!(text, "{event}");
+    }
+    text
+}
+
+// The non-empty lines without the leading and trailing whitespace
+fn normalize(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Compare the events with the expected trace, see `format_trace`.
+///
+/// The leading and trailing whitespace of the lines and the empty lines
+/// are ignored, therefore the expected trace can be an indented string literal.
+///
+/// # Panics
+///
+/// If the trace is different, with both traces in the message.
+pub fn assert_trace(events: &[EventRecord], expected: &str) {
+    let actual = format_trace(events);
+    assert!(
+        normalize(&actual) == normalize(expected),
+        "trace mismatch\n--- expected:\n{expected}\n--- actual:\n{actual}"
+    );
+}
+
+/// Compare the events with the trace in a golden file.
+///
+/// If the environment variable `SCAN_JSON_UPDATE_GOLDEN` is set,
+/// the file is written instead. Use it to create or update the golden files.
+///
+/// # Errors
+///
+/// If the file can't be read or written. To create a missing file,
+/// set `SCAN_JSON_UPDATE_GOLDEN=1`.
+///
+/// # Panics
+///
+/// If the trace is different, see `assert_trace`.
+pub fn assert_golden_file(
+    events: &[EventRecord],
+    path: impl AsRef<std::path::Path>,
+) -> std::io::Result<()> {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        return std::fs::write(path, format_trace(events));
+    }
+    let expected = std::fs::read_to_string(path)?;
+    assert_trace(events, &expected);
+    Ok(())
+}
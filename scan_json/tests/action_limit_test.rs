@@ -0,0 +1,120 @@
+use core::cell::Cell;
+use scan_json::matcher::{iter_match, Action, EndAction, StructuralPseudoname};
+use scan_json::stack::ContextIter;
+use scan_json::{scan, Error, Options, RJiter, StreamOp};
+use u8pool::U8Pool;
+
+type Baton<'a> = &'a Cell<usize>;
+
+fn on_begin(_rjiter: &mut RJiter<&[u8]>, n_actions: Baton) -> StreamOp {
+    n_actions.set(n_actions.get() + 1);
+    StreamOp::None
+}
+
+fn on_end(n_actions: Baton) -> Result<(), &'static str> {
+    n_actions.set(n_actions.get() + 1);
+    Ok(())
+}
+
+// For the key `a` and for its value, and at the end of `a`
+fn find_action<'a>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    _baton: Baton<'a>,
+) -> Option<Action<Baton<'a>, &'a [u8]>> {
+    if iter_match(|| ["a"], structural_pseudoname, context.clone())
+        || iter_match(|| ["#object", "a"], structural_pseudoname, context.clone())
+        || iter_match(|| ["#array", "a"], structural_pseudoname, context)
+    {
+        return Some(on_begin);
+    }
+    None
+}
+
+fn find_end_action<'a>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    _baton: Baton<'a>,
+) -> Option<EndAction<Baton<'a>>> {
+    if iter_match(|| ["a"], structural_pseudoname, context) {
+        return Some(on_end);
+    }
+    None
+}
+
+fn scan_with(json: &str, options: &Options) -> (scan_json::Result<()>, usize) {
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 64];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let n_actions = Cell::new(0);
+
+    let result = scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &n_actions,
+        &mut context,
+        options,
+    );
+    (result, n_actions.get())
+}
+
+#[test]
+fn max_actions_limits_begin_and_end_actions() {
+    let json = r#"{"a": 1} {"a": 2} {"a": 3}"#;
+
+    let (result, n_actions) = scan_with(json, &Options::new().with_max_actions(6));
+    result.unwrap();
+    assert_eq!(n_actions, 6);
+
+    let (result, n_actions) = scan_with(json, &Options::new().with_max_actions(5));
+    assert!(
+        matches!(
+            result,
+            Err(Error::ActionLimitExceeded {
+                position: 25,
+                limit: 5
+            })
+        ),
+        "{result:?}"
+    );
+    assert_eq!(n_actions, 5);
+}
+
+#[test]
+fn key_and_its_value_are_two_actions_at_the_same_position() {
+    // Without a space after the colon, the actions for the key `a`
+    // and for the object or array are called at the same position
+    for json in [r#"{"a":{"b":1}}"#, r#"{"a":[1]}"#] {
+        let options = Options::new().with_max_actions_at_position(2);
+        let (result, n_actions) = scan_with(json, &options);
+        result.unwrap();
+        assert_eq!(n_actions, 3, "{json}");
+
+        let options = Options::new().with_max_actions_at_position(1);
+        let (result, n_actions) = scan_with(json, &options);
+        match result {
+            Err(Error::ActionLoop {
+                position,
+                limit,
+                path,
+            }) => {
+                assert_eq!((position, limit), (5, 1), "{json}");
+                assert_eq!(path.as_bytes(), b"#top/a", "{json}");
+            }
+            other => panic!("unexpected result for {json}: {other:?}"),
+        }
+        assert_eq!(n_actions, 1, "{json}");
+    }
+}
+
+#[test]
+fn actions_at_different_positions_are_not_a_loop() {
+    let json = r#"{"a": {"b": 1}} {"a": [1, 2]}"#;
+    let options = Options::new().with_max_actions_at_position(1);
+    let (result, n_actions) = scan_with(json, &options);
+    result.unwrap();
+    assert_eq!(n_actions, 6);
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "std")]
+
+use scan_json::analyze::{analyze_triggers, SamplePath};
+use scan_json::matcher::{iter_match, Action, StructuralPseudoname};
+use scan_json::stack::ContextIter;
+use scan_json::{RJiter, StreamOp};
+
+fn on_message(_rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+    StreamOp::None
+}
+
+fn on_item(_rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+    StreamOp::None
+}
+
+fn find_action(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    _baton: (),
+) -> Option<Action<(), &'static [u8]>> {
+    if iter_match(
+        || ["message", "#top"],
+        structural_pseudoname,
+        context.clone(),
+    ) {
+        return Some(on_message);
+    }
+    if iter_match(
+        || ["#object", "#array", "items"],
+        structural_pseudoname,
+        context,
+    ) {
+        return Some(on_item);
+    }
+    None
+}
+
+#[test]
+fn report_matches_samples_to_actions() {
+    let report = analyze_triggers(
+        find_action,
+        (),
+        &[
+            SamplePath::key(&["message"]),
+            SamplePath::key(&["nested", "message"]),
+            SamplePath::structural(StructuralPseudoname::Object, &["items", "#array"]),
+            SamplePath::structural(StructuralPseudoname::Atom, &["items", "#array"]),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(report.entries.len(), 4);
+    assert_eq!(
+        report.matched_by(on_message).collect::<Vec<_>>(),
+        ["#top/message"]
+    );
+    assert_eq!(
+        report.matched_by(on_item).collect::<Vec<_>>(),
+        ["#top/items/#array/#object"]
+    );
+    assert_eq!(
+        report.unmatched().collect::<Vec<_>>(),
+        ["#top/nested/message", "#top/items/#array/#atom"]
+    );
+}
+
+#[test]
+fn report_numbers_actions_by_first_appearance() {
+    let report = analyze_triggers(
+        find_action,
+        (),
+        &[
+            SamplePath::structural(StructuralPseudoname::Object, &["items", "#array"]),
+            SamplePath::key(&["message"]),
+            SamplePath::key(&["other"]),
+            SamplePath::structural(StructuralPseudoname::Object, &["items", "#array"]),
+        ],
+    )
+    .unwrap();
+
+    let ids: Vec<_> = report.entries.iter().map(|entry| entry.action_id).collect();
+    assert_eq!(ids, [Some(0), Some(1), None, Some(0)]);
+    assert_eq!(
+        report.to_string(),
+        "#top/items/#array/#object => action #0\n\
+         #top/message => action #1\n\
+         #top/other => (none)\n\
+         #top/items/#array/#object => action #0\n"
+    );
+}
+
+#[test]
+fn report_for_no_samples_is_empty() {
+    let report = analyze_triggers(find_action, (), &[]).unwrap();
+    assert!(report.entries.is_empty());
+    assert_eq!(report.to_string(), "");
+}
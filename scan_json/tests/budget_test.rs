@@ -0,0 +1,128 @@
+use core::cell::RefCell;
+use scan_json::matcher::{iter_match, Action, EndAction, StructuralPseudoname};
+use scan_json::stack::ContextIter;
+use scan_json::{scan, DataBudget, Error, Options, RJiter, StreamOp};
+use u8pool::U8Pool;
+
+type Baton<'a> = (&'a DataBudget, &'a RefCell<Vec<String>>);
+
+fn on_tag(rjiter: &mut RJiter<&[u8]>, baton: Baton) -> StreamOp {
+    let (budget, tags) = baton;
+    let Ok(tag) = rjiter.next_str() else {
+        return StreamOp::Error("Failed to read a tag");
+    };
+    // Ignore the result: `scan` stops anyway
+    if budget.reserve(tag.len()).is_ok() {
+        tags.borrow_mut().push(tag.to_string());
+    }
+    StreamOp::ValueIsConsumed
+}
+
+fn find_action<'a>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    _baton: Baton<'a>,
+) -> Option<Action<Baton<'a>, &'a [u8]>> {
+    if iter_match(
+        || ["#atom", "#array", "tags"],
+        structural_pseudoname,
+        context,
+    ) {
+        return Some(on_tag);
+    }
+    None
+}
+
+fn find_end_action<'a>(
+    _structural_pseudoname: StructuralPseudoname,
+    _context: ContextIter,
+    _baton: Baton<'a>,
+) -> Option<EndAction<Baton<'a>>> {
+    None
+}
+
+fn scan_tags(json: &str, budget: &DataBudget) -> (scan_json::Result<()>, Vec<String>) {
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let tags = RefCell::new(Vec::new());
+
+    let result = scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (budget, &tags),
+        &mut context,
+        &Options::new().with_data_budget(budget),
+    );
+    (result, tags.into_inner())
+}
+
+#[test]
+fn data_budget_is_per_top_level_value() {
+    let json = "{\"tags\": [\"ab\", \"cd\"]}\n{\"tags\": [\"efg\", \"hi\"]}\n";
+    let budget = DataBudget::new(5);
+
+    let (result, tags) = scan_tags(json, &budget);
+    result.unwrap();
+    assert_eq!(tags, ["ab", "cd", "efg", "hi"]);
+    // Reset after the last value
+    assert_eq!(budget.used(), 0);
+    assert_eq!(budget.remaining(), 5);
+}
+
+#[test]
+fn data_budget_exceeded() {
+    let json = "{\"tags\": [\"ab\", \"cd\"]}\n{\"tags\": [\"efg\", \"hij\", \"k\"]}\n";
+    let budget = DataBudget::new(5);
+
+    let (result, tags) = scan_tags(json, &budget);
+    assert_eq!(tags, ["ab", "cd", "efg"]);
+    match result {
+        Err(Error::DataBudgetExceeded {
+            position,
+            limit,
+            used,
+            requested,
+        }) => {
+            assert_eq!((limit, used, requested), (5, 3, 3));
+            // After the tag "hij"
+            assert_eq!(position, 45);
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert_eq!(
+        budget.exceeded().map(|exceeded| exceeded.requested),
+        Some(3)
+    );
+
+    // A new scan starts with a clean budget
+    let (result, _) = scan_tags("{\"tags\": [\"ab\"]}", &budget);
+    result.unwrap();
+}
+
+#[test]
+fn data_budget_reserve_and_release() {
+    let budget = DataBudget::new(10);
+    budget.reserve(6).unwrap();
+    let exceeded = budget.reserve(5).unwrap_err();
+    assert_eq!(
+        (exceeded.limit, exceeded.used, exceeded.requested),
+        (10, 6, 5)
+    );
+    // The failed reservation doesn't change the usage
+    assert_eq!(budget.used(), 6);
+
+    budget.release(2);
+    budget.reserve(5).unwrap();
+    assert_eq!(budget.remaining(), 1);
+    assert!(budget.reserve(usize::MAX).is_err());
+
+    // Only the first failed reservation is remembered
+    assert_eq!(budget.exceeded(), Some(exceeded));
+    budget.reset();
+    assert_eq!(budget.exceeded(), None);
+    assert_eq!(budget.used(), 0);
+}
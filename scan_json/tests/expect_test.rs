@@ -0,0 +1,132 @@
+use core::cell::RefCell;
+use scan_json::expect::{expect_array, expect_bool, expect_number, expect_string, expect_type};
+use scan_json::matcher::{iter_match, Action, EndAction, StructuralPseudoname};
+use scan_json::stack::ContextIter;
+use scan_json::{scan, Error, Options, RJiter, StreamOp, ValueType};
+use u8pool::U8Pool;
+
+type Baton<'a> = &'a RefCell<Vec<String>>;
+
+fn on_email(rjiter: &mut RJiter<&[u8]>, emails: Baton) -> StreamOp {
+    if let Err(op) = expect_type(rjiter, ValueType::String) {
+        return op;
+    }
+    let Ok(email) = rjiter.next_str() else {
+        return StreamOp::Error("Failed to read an email");
+    };
+    emails.borrow_mut().push(email.to_string());
+    StreamOp::ValueIsConsumed
+}
+
+fn find_action<'a>(
+    structural_pseudoname: StructuralPseudoname,
+    context: ContextIter,
+    _baton: Baton<'a>,
+) -> Option<Action<Baton<'a>, &'a [u8]>> {
+    if iter_match(|| ["email", "user"], structural_pseudoname, context.clone()) {
+        return Some(on_email);
+    }
+    expect_string("user/name", structural_pseudoname, context.clone())
+        .or_else(|| expect_number("user/age", structural_pseudoname, context.clone()))
+        .or_else(|| expect_bool("user/active", structural_pseudoname, context.clone()))
+        .or_else(|| expect_array("user/tags", structural_pseudoname, context.clone()))
+        .or_else(|| expect_string("tags/#array/#atom", structural_pseudoname, context))
+}
+
+fn find_end_action<'a>(
+    _structural_pseudoname: StructuralPseudoname,
+    _context: ContextIter,
+    _baton: Baton<'a>,
+) -> Option<EndAction<Baton<'a>>> {
+    None
+}
+
+fn scan_users(json: &str) -> (scan_json::Result<()>, Vec<String>) {
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 64];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let emails = RefCell::new(Vec::new());
+
+    let result = scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &emails,
+        &mut context,
+        &Options::new(),
+    );
+    (result, emails.into_inner())
+}
+
+#[test]
+fn expected_types_pass() {
+    let json = r#"{"user": {"name": "Ann", "age": 42, "active": true, "tags": ["a", "b"], "email": "ann@example.com"}}
+{"user": {"name": "Bob", "age": -1.5e3, "active": false, "email": "bob@example.com"}}"#;
+    let (result, emails) = scan_users(json);
+    result.unwrap();
+    assert_eq!(emails, ["ann@example.com", "bob@example.com"]);
+}
+
+fn expect_action_error(json: &str, expected: (&str, usize, &str)) {
+    let (result, _) = scan_users(json);
+    match result {
+        Err(Error::ActionError {
+            message,
+            position,
+            path,
+            ..
+        }) => {
+            assert_eq!(
+                (message, position, path.as_bytes()),
+                (expected.0, expected.1, expected.2.as_bytes())
+            );
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn unexpected_types_are_errors_with_path_and_position() {
+    expect_action_error(
+        r#"{"user": {"name": "Ann", "age": "42"}}"#,
+        ("Expected a number", 32, "#top/user/age"),
+    );
+    expect_action_error(
+        r#"{"user": {"active": 1}}"#,
+        ("Expected a boolean", 20, "#top/user/active"),
+    );
+    expect_action_error(
+        r#"{"user": {"tags": "a"}}"#,
+        ("Expected an array", 18, "#top/user/tags"),
+    );
+    expect_action_error(
+        r#"{"user": {"tags": ["a", null]}}"#,
+        ("Expected a string", 24, "#top/user/tags/#array"),
+    );
+    expect_action_error(
+        r#"{"user": {"email": {"local": "ann"}}}"#,
+        ("Expected a string", 19, "#top/user/email"),
+    );
+}
+
+#[test]
+fn malformed_value_is_reported_by_scan() {
+    let (result, _) = scan_users(r#"{"user": {"name": x}}"#);
+    assert!(
+        matches!(result, Err(Error::UnhandledPeek { position: 18, .. })),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn value_type_of_peek() {
+    use scan_json::jiter::Peek;
+
+    assert_eq!(ValueType::of(Peek::String), Some(ValueType::String));
+    assert_eq!(ValueType::of(Peek::Minus), Some(ValueType::Number));
+    assert_eq!(ValueType::of(Peek::new(b'7')), Some(ValueType::Number));
+    assert_eq!(ValueType::of(Peek::False), Some(ValueType::Bool));
+    assert_eq!(ValueType::of(Peek::new(b'x')), None);
+}
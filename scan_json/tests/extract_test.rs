@@ -0,0 +1,95 @@
+use scan_json::extract::{extract_many, extract_many_preview, MAX_EXTRACT_PATHS};
+use scan_json::preview::Preview;
+use scan_json::{Error, RJiter};
+use u8pool::U8Pool;
+
+#[test]
+fn extract_many_from_each_record() {
+    let json = r#"
+        {"id": 1, "user": {"name": "Ann", "active": true}, "score": 9.5}
+        {"user": {"name": "Bob\nB", "active": false}, "id": 2, "extra": [{"id": 3}]}
+        {"id": {"nested": 1}, "note": null, "id": 4}
+    "#;
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let mut values_buffer = [0u8; 256];
+    let mut values = U8Pool::new(&mut values_buffer, 8).unwrap();
+
+    let paths: &[&[&str]] = &[&["id"], &["user", "name"], &["user", "active"], &["note"]];
+    let mut records = Vec::new();
+    for _ in 0..3 {
+        values.clear();
+        context.clear();
+        let report = extract_many(paths, &mut rjiter, &mut context, &mut values).unwrap();
+        let record: Vec<Option<String>> = (0..paths.len())
+            .map(|i| {
+                report
+                    .value(&values, i)
+                    .map(|v| String::from_utf8(v.to_vec()).unwrap())
+            })
+            .collect();
+        records.push((report.found_count(), record));
+    }
+    rjiter.finish().unwrap();
+
+    let s = |v: &str| Some(v.to_string());
+    assert_eq!(
+        records,
+        vec![
+            (3, vec![s("1"), s("Ann"), s("true"), None]),
+            (3, vec![s("2"), s("Bob\nB"), s("false"), None]),
+            // The first `id` is an object, the next one is used
+            (2, vec![s("4"), None, None, s("null")]),
+        ]
+    );
+}
+
+#[test]
+fn extract_many_errors() {
+    let json = r#"{"a": "a value longer than the pool", "b": "x"}"#;
+    let mut context_buffer = [0u8; 512];
+    let mut values_buffer = [0u8; 256];
+
+    // No space in the pool for the value
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 64];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let mut values = U8Pool::new(&mut values_buffer[..32], 2).unwrap();
+    let err = extract_many(&[&["a"]], &mut rjiter, &mut context, &mut values).unwrap_err();
+    assert!(matches!(err, Error::ActionError { .. }));
+
+    // Too many paths
+    let mut reader = json.as_bytes();
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let mut values = U8Pool::new(&mut values_buffer, 8).unwrap();
+    let paths: Vec<&[&str]> = vec![&["a"]; MAX_EXTRACT_PATHS + 1];
+    let err = extract_many(&paths, &mut rjiter, &mut context, &mut values).unwrap_err();
+    assert!(matches!(err, Error::InternalError { .. }));
+}
+
+#[test]
+fn extract_many_preview_truncates_strings() {
+    let long_text = "x".repeat(100);
+    let json = format!(r#"{{"id": 12345678901, "msg": "{long_text}", "tag": "ok"}}"#);
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    let mut values_buffer = [0u8; 128];
+    let mut values = U8Pool::new(&mut values_buffer, 8).unwrap();
+
+    let paths: &[&[&str]] = &[&["id"], &["msg"], &["tag"]];
+    let preview = Preview::new(5);
+    let report =
+        extract_many_preview(paths, &mut rjiter, &mut context, &mut values, &preview).unwrap();
+    // Numbers are not truncated
+    assert_eq!(report.value(&values, 0), Some(&b"12345678901"[..]));
+    assert_eq!(report.value(&values, 1), Some(&b"xxxxx..."[..]));
+    assert_eq!(report.value(&values, 2), Some(&b"ok"[..]));
+}
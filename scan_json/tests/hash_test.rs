@@ -0,0 +1,112 @@
+use rjiter::RJiter;
+use scan_json::matcher::StructuralPseudoname;
+use scan_json::stack::ContextIter;
+use scan_json::{hash_value, iter_match, scan, Action, EndAction, Options, StreamOp, ValueHasher};
+use std::cell::RefCell;
+use u8pool::U8Pool;
+
+type Hashes = RefCell<Vec<Vec<u8>>>;
+
+#[derive(Default)]
+struct CollectingHasher {
+    bytes: Vec<u8>,
+}
+
+impl ValueHasher for CollectingHasher {
+    fn update(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+}
+
+#[test]
+fn hash_atom() {
+    let mut reader = r#"  "hello"  "#.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let mut hasher = CollectingHasher::default();
+
+    hash_value(&mut rjiter, &mut hasher, &mut scan_stack).unwrap();
+
+    assert_eq!(hasher.bytes, br#""hello""#);
+}
+
+#[test]
+fn hash_ignores_formatting() {
+    let input = r#"{"a": [1, 2, {"b": null}],
+        "c": "long string value that does not fit into the buffer"}"#;
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let mut hasher = CollectingHasher::default();
+
+    hash_value(&mut rjiter, &mut hasher, &mut scan_stack).unwrap();
+
+    assert_eq!(
+        String::from_utf8(hasher.bytes).unwrap(),
+        r#"{"a":[1,2,{"b":null}],"c":"long string value that does not fit into the buffer"}"#
+    );
+}
+
+fn on_payload(rjiter: &mut RJiter<&[u8]>, hashes: &Hashes) -> StreamOp {
+    let mut buffer = [0u8; 512];
+    let Ok(mut working_buffer) = U8Pool::new(&mut buffer, 20) else {
+        return StreamOp::Error("Failed to create the working buffer");
+    };
+    let mut hasher = CollectingHasher::default();
+    match hash_value(rjiter, &mut hasher, &mut working_buffer) {
+        Ok(()) => {
+            hashes.borrow_mut().push(hasher.bytes);
+            StreamOp::ValueIsConsumed
+        }
+        Err(_e) => StreamOp::Error("Failed to hash the value"),
+    }
+}
+
+#[test]
+fn hash_value_in_action() {
+    let input = r#"
+        {"id": 1, "payload": {"x": [1, 2]}}
+        {"id": 2, "payload": { "x" : [ 1 , 2 ] }}
+        {"id": 3, "payload": {"x": [2, 1]}}
+    "#;
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let hashes = RefCell::new(Vec::new());
+
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       _baton: &Hashes|
+     -> Option<Action<&Hashes, &[u8]>> {
+        if iter_match(|| [b"payload".as_slice()], structural_pseudoname, context) {
+            Some(on_payload)
+        } else {
+            None
+        }
+    };
+    let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: &Hashes|
+     -> Option<EndAction<&Hashes>> { None };
+
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &hashes,
+        &mut scan_stack,
+        &Options::new(),
+    )
+    .unwrap();
+
+    let hashes = hashes.into_inner();
+    assert_eq!(hashes.len(), 3);
+    assert_eq!(hashes[0], hashes[1]);
+    assert_ne!(hashes[0], hashes[2]);
+}
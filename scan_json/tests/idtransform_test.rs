@@ -1,6 +1,7 @@
 use embedded_io::Write;
 use rjiter::RJiter;
-use scan_json::idtransform::idtransform;
+use scan_json::idtransform::{idtransform, idtransform_with_substitution};
+use scan_json::matcher::{iter_match, StructuralPseudoname};
 use u8pool::U8Pool;
 
 #[test]
@@ -461,3 +462,97 @@ fn idt_preserves_rjiter_error() {
         _ => panic!("Expected RJiterError, got: {:?}", result),
     }
 }
+
+#[test]
+fn idt_substitutes_values() {
+    let input =
+        r#"{"id": 1, "items": [{"price": 10, "tags": ["a"]}, {"price": 20}], "meta": {"x": null}}"#;
+
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let mut writer = Vec::new();
+    let mut kinds = Vec::new();
+
+    let result = idtransform_with_substitution(
+        &mut rjiter,
+        &mut writer,
+        &mut scan_stack,
+        |context, kind| {
+            if iter_match(
+                || ["price", "#array", "items", "#top"],
+                StructuralPseudoname::None,
+                context.clone(),
+            ) {
+                kinds.push(kind);
+                return Some(b"0");
+            }
+            if iter_match(
+                || ["tags", "#array", "items", "#top"],
+                StructuralPseudoname::None,
+                context.clone(),
+            ) || iter_match(
+                || ["meta", "#top"],
+                StructuralPseudoname::None,
+                context.clone(),
+            ) {
+                kinds.push(kind);
+                return Some(br#"{"replaced":true}"#);
+            }
+            None
+        },
+    );
+
+    assert!(result.is_ok(), "{result:?}");
+    assert_eq!(
+        String::from_utf8(writer).unwrap(),
+        r#"{"id":1,"items":[{"price":0,"tags":{"replaced":true}},{"price":0}],"meta":{"replaced":true}}"#
+    );
+    assert_eq!(
+        kinds,
+        [
+            StructuralPseudoname::Atom,
+            StructuralPseudoname::Array,
+            StructuralPseudoname::Atom,
+            StructuralPseudoname::Object
+        ]
+    );
+}
+
+#[test]
+fn idt_substitutes_values_longer_than_buffer() {
+    let input = r#"{"a": "a string which is longer than the buffer", "b": [1, {"c": "another long string value"}, 3], "d": 12345678901234567890123}"#;
+
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let mut writer = Vec::new();
+
+    let result = idtransform_with_substitution(
+        &mut rjiter,
+        &mut writer,
+        &mut scan_stack,
+        |context, _kind| {
+            for key in ["a", "b", "d"] {
+                if iter_match(
+                    || [key, "#top"],
+                    StructuralPseudoname::None,
+                    context.clone(),
+                ) {
+                    return Some(b"null");
+                }
+            }
+            None
+        },
+    );
+
+    assert!(result.is_ok(), "{result:?}");
+    assert_eq!(
+        String::from_utf8(writer).unwrap(),
+        r#"{"a":null,"b":null,"d":null}"#
+    );
+}
@@ -1,4 +1,4 @@
-use scan_json::matcher::{iter_match, StructuralPseudoname};
+use scan_json::matcher::{iter_match, path_match, StructuralPseudoname};
 use scan_json::scan::StructurePosition;
 use scan_json::stack::ContextIter;
 use u8pool::U8Pool;
@@ -15,7 +15,7 @@ fn test_iter_match_empty_iterator() {
     pool.push_assoc(S, b"any").unwrap();
     let path = ContextIter::new(&pool);
     assert!(iter_match(
-        || std::iter::empty::<&[u8]>(),
+        std::iter::empty::<&[u8]>,
         StructuralPseudoname::None,
         path
     ));
@@ -26,7 +26,7 @@ fn test_iter_match_empty_iterator() {
     pool.push_assoc(S, b"name").unwrap();
     let path = ContextIter::new(&pool);
     assert!(iter_match(
-        || std::iter::empty::<&[u8]>(),
+        std::iter::empty::<&[u8]>,
         StructuralPseudoname::None,
         path
     ));
@@ -38,7 +38,7 @@ fn test_iter_match_empty_iterator() {
     pool.push_assoc(S, b"field").unwrap();
     let path = ContextIter::new(&pool);
     assert!(iter_match(
-        || std::iter::empty::<&[u8]>(),
+        std::iter::empty::<&[u8]>,
         StructuralPseudoname::None,
         path
     ));
@@ -336,21 +336,21 @@ fn test_iter_match_empty_iterator_structural_pseudonames() {
     // Empty iterator always returns true for all structural pseudonames
     let path = ContextIter::new(&pool);
     assert!(iter_match(
-        || std::iter::empty::<&[u8]>(),
+        std::iter::empty::<&[u8]>,
         StructuralPseudoname::Array,
         path
     ));
 
     let path = ContextIter::new(&pool);
     assert!(iter_match(
-        || std::iter::empty::<&[u8]>(),
+        std::iter::empty::<&[u8]>,
         StructuralPseudoname::Object,
         path
     ));
 
     let path = ContextIter::new(&pool);
     assert!(iter_match(
-        || std::iter::empty::<&[u8]>(),
+        std::iter::empty::<&[u8]>,
         StructuralPseudoname::Atom,
         path
     ));
@@ -518,3 +518,93 @@ fn test_iter_match_structural_pseudonames_with_context() {
         path
     ));
 }
+
+#[test]
+fn test_iter_match_wildcard() {
+    let mut buffer = [0u8; 1024];
+    let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+    pool.push_assoc(S, b"#top").unwrap();
+    pool.push_assoc(S, b"choices").unwrap();
+    pool.push_assoc(S, b"#array").unwrap();
+    pool.push_assoc(S, b"message").unwrap();
+
+    // One level: a key name or an array item
+    let path = ContextIter::new(&pool);
+    assert!(iter_match(
+        || ["message", "*", "choices"],
+        StructuralPseudoname::None,
+        path
+    ));
+    let path = ContextIter::new(&pool);
+    assert!(iter_match(
+        || ["*", "#array", "*"],
+        StructuralPseudoname::None,
+        path
+    ));
+
+    // Exactly one level
+    let path = ContextIter::new(&pool);
+    assert!(!iter_match(
+        || ["message", "*", "*", "choices"],
+        StructuralPseudoname::None,
+        path
+    ));
+
+    // Not the top level
+    let path = ContextIter::new(&pool);
+    assert!(!iter_match(
+        || ["*", "*", "*", "*"],
+        StructuralPseudoname::None,
+        path
+    ));
+    let path = ContextIter::new(&pool);
+    assert!(iter_match(
+        || ["*", "*", "*", "#top"],
+        StructuralPseudoname::None,
+        path
+    ));
+}
+
+#[test]
+fn test_path_match() {
+    let mut buffer = [0u8; 1024];
+    let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+    pool.push_assoc(S, b"#top").unwrap();
+    pool.push_assoc(S, b"choices").unwrap();
+    pool.push_assoc(S, b"#array").unwrap();
+
+    let path = ContextIter::new(&pool);
+    assert!(path_match(
+        "choices/#array/#object",
+        StructuralPseudoname::Object,
+        path
+    ));
+    let path = ContextIter::new(&pool);
+    assert!(path_match(
+        "#top/*/*/#object",
+        StructuralPseudoname::Object,
+        path
+    ));
+    let path = ContextIter::new(&pool);
+    assert!(!path_match(
+        "#top/*/#atom",
+        StructuralPseudoname::Object,
+        path
+    ));
+
+    pool.push_assoc(S, b"delta").unwrap();
+    let path = ContextIter::new(&pool);
+    assert!(path_match(
+        "choices/*/delta",
+        StructuralPseudoname::None,
+        path
+    ));
+    let path = ContextIter::new(&pool);
+    assert!(!path_match(
+        "choices/delta",
+        StructuralPseudoname::None,
+        path
+    ));
+    let path = ContextIter::new(&pool);
+    assert!(path_match("", StructuralPseudoname::None, path));
+}
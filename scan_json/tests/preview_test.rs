@@ -0,0 +1,96 @@
+use scan_json::preview::{push_str_preview, truncate_utf8, Preview};
+use scan_json::{Error, RJiter};
+use u8pool::U8Pool;
+
+#[test]
+fn truncate_utf8_does_not_split_code_point() {
+    let text = "a\u{e9}\u{4f60}\u{1f600}".as_bytes();
+    let prefixes: Vec<&[u8]> = (0..=text.len())
+        .map(|max| truncate_utf8(text, max))
+        .collect();
+    for (max, prefix) in prefixes.iter().enumerate() {
+        assert!(prefix.len() <= max, "{max}: {prefix:?}");
+        assert!(core::str::from_utf8(prefix).is_ok(), "{max}: {prefix:?}");
+    }
+    assert_eq!(prefixes[2], b"a");
+    assert_eq!(prefixes[3], "a\u{e9}".as_bytes());
+    assert_eq!(prefixes[9], "a\u{e9}\u{4f60}".as_bytes());
+    assert_eq!(prefixes[10], text);
+    assert_eq!(truncate_utf8(b"", 0), b"");
+}
+
+fn preview_of(json: &str, buffer_len: usize, preview: &Preview) -> Vec<u8> {
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; buffer_len];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut pool_buffer = [0u8; 128];
+    let mut pool = U8Pool::new(&mut pool_buffer, 4).unwrap();
+    let stored = push_str_preview(&mut rjiter, &mut pool, preview)
+        .unwrap()
+        .to_vec();
+    rjiter.finish().unwrap();
+    assert_eq!(pool.len(), 1);
+    stored
+}
+
+#[test]
+fn preview_of_short_and_long_strings() {
+    let preview = Preview::new(8);
+    assert_eq!(preview_of(r#" "short" "#, 32, &preview), b"short");
+    assert_eq!(preview_of(r#""exactly8""#, 32, &preview), b"exactly8");
+    assert_eq!(preview_of(r#""nine bytes""#, 32, &preview), b"nine byt...");
+    // Longer than the rjiter buffer, with escapes
+    let json = r#""line 1\nline 2\nline 3\nline 4""#;
+    assert_eq!(preview_of(json, 8, &preview), b"line 1\nl...");
+}
+
+#[test]
+fn preview_does_not_split_code_point() {
+    // "é" is two bytes at the offsets 3 and 4
+    let json = "\"caf\u{e9} au lait\"";
+    assert_eq!(preview_of(json, 32, &Preview::new(4)), b"caf...");
+    assert_eq!(
+        preview_of(json, 32, &Preview::new(5)),
+        "caf\u{e9}...".as_bytes()
+    );
+    // An escaped code point on the limit
+    let json = r#""ab\u00e9cd""#;
+    assert_eq!(preview_of(json, 32, &Preview::new(3)), b"ab...");
+}
+
+#[test]
+fn preview_with_custom_ellipsis() {
+    let json = r#""a long value""#;
+    let preview = Preview::new(6).with_ellipsis("\u{2026}".as_bytes());
+    assert_eq!(preview_of(json, 32, &preview), "a long\u{2026}".as_bytes());
+    let preview = Preview::new(6).with_ellipsis(b"");
+    assert_eq!(preview_of(json, 32, &preview), b"a long");
+}
+
+#[test]
+fn preview_errors() {
+    // Not a string
+    let mut reader = &b"42"[..];
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut pool_buffer = [0u8; 64];
+    let mut pool = U8Pool::new(&mut pool_buffer, 4).unwrap();
+    let result = push_str_preview(&mut rjiter, &mut pool, &Preview::new(4));
+    assert!(matches!(result, Err(Error::RJiterError(_))), "{result:?}");
+
+    // No space in the pool: the string is consumed, the pool is not changed
+    let mut reader = &br#""a long value" 7"#[..];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut pool = U8Pool::new(&mut pool_buffer[..20], 2).unwrap();
+    pool.push(b"abcdefgh").unwrap();
+    let result = push_str_preview(&mut rjiter, &mut pool, &Preview::new(100));
+    assert!(
+        matches!(
+            result,
+            Err(Error::IOError(embedded_io::ErrorKind::OutOfMemory))
+        ),
+        "{result:?}"
+    );
+    assert_eq!(pool.len(), 1);
+    assert_eq!(rjiter.next_int().unwrap(), rjiter::jiter::NumberInt::Int(7));
+}
@@ -0,0 +1,84 @@
+use ::scan_json::matcher::{iter_match, StructuralPseudoname};
+use ::scan_json::stack::ContextIter;
+use ::scan_json::{scan_mut, ActionMut, EndActionMut, Options, StreamOp};
+use rjiter::RJiter;
+use u8pool::U8Pool;
+
+/// State without interior mutability
+#[derive(Debug, Default)]
+struct Collected {
+    names: Vec<String>,
+    n_items_ended: usize,
+}
+
+fn on_name(rjiter: &mut RJiter<&[u8]>, collected: &mut Collected) -> StreamOp {
+    match rjiter.next_str() {
+        Ok(name) => {
+            collected.names.push(name.to_string());
+            StreamOp::ValueIsConsumed
+        }
+        Err(_) => StreamOp::Error("Expected a string"),
+    }
+}
+
+fn on_item_end(collected: &mut Collected) -> Result<(), &'static str> {
+    collected.n_items_ended += 1;
+    Ok(())
+}
+
+fn collect(json: &str) -> Collected {
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       _collected: &Collected|
+     -> Option<ActionMut<Collected, &[u8]>> {
+        iter_match(|| ["name".as_bytes()], structural_pseudoname, context)
+            .then_some(on_name as ActionMut<Collected, &[u8]>)
+    };
+    let find_end_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           _collected: &Collected|
+     -> Option<EndActionMut<Collected>> {
+        iter_match(
+            || ["#object".as_bytes(), "#array".as_bytes()],
+            structural_pseudoname,
+            context,
+        )
+        .then_some(on_item_end as EndActionMut<Collected>)
+    };
+
+    let mut collected = Collected::default();
+    scan_mut(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &mut collected,
+        &mut scan_stack,
+        &Options::new(),
+    )
+    .unwrap();
+    collected
+}
+
+#[test]
+fn scan_mut_actions_and_end_actions() {
+    let json = r#"[{"name": "first"}, {"id": 2, "name": "second"}] [{"name": "third"}]"#;
+    let collected = collect(json);
+
+    assert_eq!(collected.names, vec!["first", "second", "third"]);
+    assert_eq!(collected.n_items_ended, 3);
+}
+
+#[test]
+fn scan_mut_in_another_thread() {
+    let json = String::from(r#"[{"name": "in thread"}]"#);
+    let collected = std::thread::spawn(move || collect(&json)).join().unwrap();
+
+    assert_eq!(collected.names, vec!["in thread"]);
+    assert_eq!(collected.n_items_ended, 1);
+}
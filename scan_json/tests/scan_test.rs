@@ -1,9 +1,15 @@
+#![allow(clippy::type_complexity)]
+
 use embedded_io::Write;
 use std::cell::RefCell;
 
 use ::scan_json::matcher::{iter_match, Action, EndAction, StreamOp, StructuralPseudoname};
+use ::scan_json::scan::StructurePosition;
 use ::scan_json::stack::ContextIter;
-use ::scan_json::{scan, Options};
+use ::scan_json::{
+    scan, KeyNormalization, KeyUtf8Policy, LongKeyPolicy, Options, OptionsError, ScanReport,
+    ValueKind,
+};
 use rjiter::{jiter::Peek, RJiter};
 use u8pool::U8Pool;
 
@@ -243,6 +249,184 @@ fn test_skip_sse_tokens() {
     .unwrap();
 }
 
+#[test]
+fn options_builder_sets_options() {
+    let sse_tokens: &[&[u8]] = &[b"data:"];
+    let interned_keys: &[&[u8]] = &[b"foo"];
+    let options = Options::builder()
+        .sse_tokens(sse_tokens)
+        .key_normalization(KeyNormalization::AsciiLowercase)
+        .long_key_policy(LongKeyPolicy::TruncateKey(8))
+        .key_utf8_policy(KeyUtf8Policy::Validate)
+        .defer_key_dispatch(true)
+        .max_actions(100)
+        .max_actions_at_position(10)
+        .interned_keys(interned_keys)
+        .require_container_top(true)
+        .build()
+        .unwrap();
+    assert_eq!(options.sse_tokens, sse_tokens);
+    assert!(!options.stop_early);
+    assert!(matches!(
+        options.key_normalization,
+        KeyNormalization::AsciiLowercase
+    ));
+    assert_eq!(options.long_key_policy, LongKeyPolicy::TruncateKey(8));
+    assert_eq!(options.key_utf8_policy, KeyUtf8Policy::Validate);
+    assert!(options.defer_key_dispatch);
+    assert!(!options.keep_context);
+    assert_eq!(options.max_actions, Some(100));
+    assert_eq!(options.max_actions_at_position, Some(10));
+    assert_eq!(options.interned_keys, interned_keys);
+    assert!(options.require_container_top);
+
+    let json = r#"data: {"FOO": 1} data: {"Foo": 2}"#;
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let n_found = RefCell::new(0);
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       _baton: &RefCell<usize>|
+     -> Option<Action<&RefCell<usize>, &[u8]>> {
+        if iter_match(|| ["foo".as_bytes()], structural_pseudoname, context) {
+            Some(|_: &mut RJiter<&[u8]>, n_found: &RefCell<usize>| {
+                *n_found.borrow_mut() += 1;
+                StreamOp::None
+            })
+        } else {
+            None
+        }
+    };
+    let find_end_action = |_: StructuralPseudoname,
+                           _: ContextIter,
+                           _: &RefCell<usize>|
+     -> Option<EndAction<&RefCell<usize>>> { None };
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &n_found,
+        &mut scan_stack,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(*n_found.borrow(), 2);
+}
+
+#[test]
+fn options_builder_rejects_invalid_combinations() {
+    let sse_tokens: &[&[u8]] = &[b"data:", b""];
+    assert_eq!(
+        Options::builder()
+            .sse_tokens(sse_tokens)
+            .build()
+            .unwrap_err(),
+        OptionsError::EmptySseToken
+    );
+
+    let interned_keys: Vec<&[u8]> = vec![b"key"; 256];
+    assert_eq!(
+        Options::builder()
+            .interned_keys(&interned_keys)
+            .build()
+            .unwrap_err(),
+        OptionsError::TooManyInternedKeys {
+            count: 256,
+            max: 255
+        }
+    );
+
+    assert_eq!(
+        Options::builder().max_actions(0).build().unwrap_err(),
+        OptionsError::ZeroLimit {
+            option: "max_actions"
+        }
+    );
+    assert_eq!(
+        Options::builder()
+            .long_key_policy(LongKeyPolicy::SkipValue(0))
+            .build()
+            .unwrap_err(),
+        OptionsError::ZeroLimit {
+            option: "long_key_policy"
+        }
+    );
+
+    let stop_when = |_: &ScanReport| true;
+    assert_eq!(
+        Options::builder()
+            .stop_early(true)
+            .stop_when(&stop_when)
+            .build()
+            .unwrap_err(),
+        OptionsError::Conflict {
+            option: "stop_early",
+            other: "stop_when"
+        }
+    );
+
+    // The same checks for the options with the fields set directly, also in `scan`
+    let mut options = Options::new();
+    assert!(options.validate().is_ok());
+    options.max_actions_at_position = Some(0);
+    assert!(options.validate().is_err());
+
+    let mut reader = r#"{"a": 1}"#.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let err = scan(
+        |_: StructuralPseudoname, _: ContextIter, ()| -> Option<Action<(), &[u8]>> { None },
+        |_: StructuralPseudoname, _: ContextIter, ()| -> Option<EndAction<()>> { None },
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &options,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ::scan_json::Error::InvalidOptions(OptionsError::ZeroLimit {
+            option: "max_actions_at_position"
+        })
+    ));
+}
+
+#[test]
+fn stop_early_skips_sse_tokens() {
+    let input = r#"data: {"a": 1} data: [2]"#;
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+    let sse_tokens: &[&[u8]] = &[b"data:"];
+    let options = Options::builder()
+        .sse_tokens(sse_tokens)
+        .stop_early(true)
+        .build()
+        .unwrap();
+    // Each call skips the token and stops after the value
+    for expected_position in [14, 24] {
+        scan(
+            |_: StructuralPseudoname, _: ContextIter, ()| -> Option<Action<(), &[u8]>> { None },
+            |_: StructuralPseudoname, _: ContextIter, ()| -> Option<EndAction<()>> { None },
+            &mut rjiter,
+            (),
+            &mut scan_stack,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(rjiter.current_index(), expected_position);
+    }
+    rjiter.finish().unwrap();
+}
+
 #[test]
 fn test_call_begin_dont_touch_value() {
     let json = r#"{"foo": "bar", "baz": "qux"}"#;
@@ -266,7 +450,7 @@ fn test_call_begin_dont_touch_value() {
      -> Option<Action<&RefCell<bool>, &[u8]>> {
         if structural_pseudoname == StructuralPseudoname::None {
             if let Some(key) = context.into_iter().next() {
-                (key == b"foo").then(|| set_state_true as Action<&RefCell<bool>, &[u8]>)
+                (key == b"foo").then_some(set_state_true as Action<&RefCell<bool>, &[u8]>)
             } else {
                 None
             }
@@ -318,7 +502,7 @@ fn test_call_begin_consume_value() {
      -> Option<Action<&RefCell<bool>, &[u8]>> {
         if structural_pseudoname == StructuralPseudoname::None {
             if let Some(key) = context.into_iter().next() {
-                (key == b"foo").then(|| consume_foo_value as Action<&RefCell<bool>, &[u8]>)
+                (key == b"foo").then_some(consume_foo_value as Action<&RefCell<bool>, &[u8]>)
             } else {
                 None
             }
@@ -379,7 +563,7 @@ fn test_call_end() {
      -> Option<EndAction<&RefCell<i32>>> {
         if structural_pseudoname == StructuralPseudoname::None {
             if let Some(key) = context.into_iter().next() {
-                (key == b"foo").then(|| increment_counter as EndAction<&RefCell<i32>>)
+                (key == b"foo").then_some(increment_counter as EndAction<&RefCell<i32>>)
             } else {
                 None
             }
@@ -404,6 +588,72 @@ fn test_call_end() {
     );
 }
 
+#[test]
+fn array_len_for_end_action() {
+    #[derive(Default)]
+    struct State {
+        name: Vec<u8>,
+        len: usize,
+        summary: Vec<String>,
+    }
+    type Baton<'a> = &'a RefCell<State>;
+
+    fn summarize(state: Baton) -> Result<(), &'static str> {
+        let mut state = state.borrow_mut();
+        let line = format!("{} had {}", String::from_utf8_lossy(&state.name), state.len);
+        state.summary.push(line);
+        Ok(())
+    }
+
+    let json = r#"{"choices": [{"a": [1, [2, 3]]}, {"a": []}, 3], "empty": []} [[], 1, 2]"#;
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let state = RefCell::new(State::default());
+
+    let find_action = |_structural_pseudoname: StructuralPseudoname,
+                       _context: ContextIter,
+                       _baton: Baton|
+     -> Option<Action<Baton, &[u8]>> { None };
+    // The matcher passes the number of the items to the action
+    let find_end_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           baton: Baton|
+     -> Option<EndAction<Baton>> {
+        if structural_pseudoname != StructuralPseudoname::Array {
+            return None;
+        }
+        let mut state = baton.borrow_mut();
+        state.len = context.array_len()?;
+        state.name = context.clone().next()?.to_vec();
+        Some(summarize)
+    };
+
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &state,
+        &mut scan_stack,
+        &Options::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        state.borrow().summary,
+        vec![
+            "#array had 2",
+            "a had 2",
+            "a had 0",
+            "choices had 3",
+            "empty had 0",
+            "#array had 0",
+            "#top had 3",
+        ]
+    );
+}
+
 #[test]
 fn notify_for_top_level_object() {
     let json = r#"{}"#;
@@ -434,7 +684,7 @@ fn notify_for_top_level_object() {
             structural_pseudoname,
             context,
         )
-        .then(|| set_begin_called as Action<&RefCell<(bool, bool)>, &[u8]>)
+        .then_some(set_begin_called as Action<&RefCell<(bool, bool)>, &[u8]>)
     };
     // find_end_action that matches #object with parent #top
     let find_end_action = |structural_pseudoname: StructuralPseudoname,
@@ -446,7 +696,7 @@ fn notify_for_top_level_object() {
             structural_pseudoname,
             context,
         )
-        .then(|| set_end_called as EndAction<&RefCell<(bool, bool)>>)
+        .then_some(set_end_called as EndAction<&RefCell<(bool, bool)>>)
     };
 
     scan(
@@ -494,7 +744,7 @@ fn notify_for_object_in_array() {
             structural_pseudoname,
             context,
         )
-        .then(|| increment_begin_count as Action<&RefCell<(i32, i32)>, &[u8]>)
+        .then_some(increment_begin_count as Action<&RefCell<(i32, i32)>, &[u8]>)
     };
     // find_end_action that matches #object with parent #array and grandparent #top
     let find_end_action = |structural_pseudoname: StructuralPseudoname,
@@ -506,7 +756,7 @@ fn notify_for_object_in_array() {
             structural_pseudoname,
             context,
         )
-        .then(|| increment_end_count as EndAction<&RefCell<(i32, i32)>>)
+        .then_some(increment_end_count as EndAction<&RefCell<(i32, i32)>>)
     };
 
     scan(
@@ -563,7 +813,7 @@ fn notify_for_array() {
             structural_pseudoname,
             context,
         )
-        .then(|| set_array_begin_called as Action<&RefCell<(bool, bool)>, &[u8]>)
+        .then_some(set_array_begin_called as Action<&RefCell<(bool, bool)>, &[u8]>)
     };
     // find_end_action that matches #array with parent items
     let find_end_action = |structural_pseudoname: StructuralPseudoname,
@@ -575,7 +825,7 @@ fn notify_for_array() {
             structural_pseudoname,
             context,
         )
-        .then(|| set_array_end_called as EndAction<&RefCell<(bool, bool)>>)
+        .then_some(set_array_end_called as EndAction<&RefCell<(bool, bool)>>)
     };
 
     scan(
@@ -626,7 +876,7 @@ fn client_can_consume_array() {
             structural_pseudoname,
             context,
         )
-        .then(|| consume_array_and_write as Action<&RefCell<Vec<u8>>, &[u8]>)
+        .then_some(consume_array_and_write as Action<&RefCell<Vec<u8>>, &[u8]>)
     };
     // find_end_action that matches #array with parent items
     // Will not be called because the array is consumed in the begin action
@@ -639,7 +889,7 @@ fn client_can_consume_array() {
             structural_pseudoname,
             context,
         )
-        .then(|| write_array_end as EndAction<&RefCell<Vec<u8>>>)
+        .then_some(write_array_end as EndAction<&RefCell<Vec<u8>>>)
     };
 
     scan(
@@ -757,7 +1007,7 @@ fn max_nesting_array() {
     let e = result.unwrap_err();
     assert_eq!(
         format!("{e}"),
-        "Max nesting exceeded at position 2 with level 3"
+        "Max nesting exceeded at position 2 with level 3: requested 11 bytes, available 25 bytes and 0 slots in the context pool"
     );
 }
 
@@ -790,7 +1040,207 @@ fn max_nesting_object() {
     let e = result.unwrap_err();
     assert_eq!(
         format!("{e}"),
-        "Max nesting exceeded at position 15 with level 3"
+        "Max nesting exceeded at position 15 with level 3: requested 2 bytes, available 43 bytes and 0 slots in the context pool"
+    );
+}
+
+#[test]
+fn context_pool_too_small_for_key() {
+    let json = r#"{"a": {"a_rather_long_key_for_the_pool": 1}}"#;
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 64];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 64];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 8).unwrap();
+
+    let find_action = |_structural_pseudoname: StructuralPseudoname,
+                       _context: ContextIter,
+                       _baton: ()|
+     -> Option<Action<(), &[u8]>> { None };
+    let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: ()|
+     -> Option<EndAction<()>> { None };
+
+    let result = scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::new(),
+    );
+    // Not the depth, but the key length
+    match result.unwrap_err() {
+        scan_json::Error::MaxNestingExceeded {
+            level,
+            requested,
+            available,
+            free_slots,
+            ..
+        } => {
+            assert_eq!(level, 2);
+            assert_eq!(requested, 31);
+            assert_eq!(available, 25);
+            assert_eq!(free_slots, 6);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn line_position_of_rjiter_error() {
+    let json = "[1,\n 2,\n\n   3 4]";
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 8];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+    let find_action = |_structural_pseudoname: StructuralPseudoname,
+                       _context: ContextIter,
+                       _baton: ()|
+     -> Option<Action<(), &[u8]>> { None };
+    let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: ()|
+     -> Option<EndAction<()>> { None };
+
+    let result = scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::new(),
+    );
+    let e = result.unwrap_err();
+    let position = e
+        .line_position()
+        .expect("RJiter error should have a position");
+    assert_eq!(*position, rjiter::jiter::LinePosition::new(4, 6));
+    assert!(!e.is_retryable());
+
+    let interrupted = ::scan_json::Error::IOError(embedded_io::ErrorKind::Interrupted);
+    assert!(interrupted.is_retryable());
+    assert!(!::scan_json::Error::UnbalancedJson(0).is_retryable());
+}
+
+#[test]
+fn retryable_reader_errors() {
+    struct FailingReader(embedded_io::ErrorKind);
+    impl embedded_io::ErrorType for FailingReader {
+        type Error = embedded_io::ErrorKind;
+    }
+    impl embedded_io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Err(self.0)
+        }
+    }
+
+    for (kind, is_retryable) in [
+        (embedded_io::ErrorKind::Interrupted, true),
+        (embedded_io::ErrorKind::TimedOut, true),
+        (embedded_io::ErrorKind::BrokenPipe, false),
+    ] {
+        let mut reader = FailingReader(kind);
+        let mut buffer = vec![0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut scan_buffer = [0u8; 512];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+        let find_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: ()|
+         -> Option<Action<(), FailingReader>> { None };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: ()|
+         -> Option<EndAction<()>> { None };
+
+        let err = scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            (),
+            &mut scan_stack,
+            &Options::new(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&err, ::scan_json::Error::RJiterError(e)
+                if e.error_type == rjiter::error::ErrorType::IoError { kind }),
+            "{err:?}"
+        );
+        assert_eq!(err.is_retryable(), is_retryable, "{kind:?}");
+    }
+}
+
+#[test]
+fn locate_scan_errors() {
+    let json = "{\n  \"name\": \"a long enough value\",\n  \"bad\": 1\n}\n[\n  1,\n";
+    let scan_with_buffer = |failing_key: &'static str| {
+        let mut reader = json.as_bytes();
+        let mut buffer = vec![0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut scan_buffer = [0u8; 512];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+        fn fail_action(_: &mut RJiter<&[u8]>, _: ()) -> StreamOp {
+            StreamOp::Error("bad value")
+        }
+        let find_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           _baton: ()|
+         -> Option<Action<(), &[u8]>> {
+            if iter_match(|| [failing_key.as_bytes()], structural_pseudoname, context) {
+                Some(fail_action)
+            } else {
+                None
+            }
+        };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: ()|
+         -> Option<EndAction<()>> { None };
+
+        let err = scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            (),
+            &mut scan_stack,
+            &Options::new(),
+        )
+        .unwrap_err();
+        let position = err.locate(&rjiter);
+        // After reading further, the error position is shifted out of the buffer
+        let _ = rjiter.next_int();
+        let _ = rjiter.next_key();
+        let _ = rjiter.next_array();
+        let _ = rjiter.next_int();
+        let later_position = err.locate(&rjiter);
+        (err, position, later_position)
+    };
+
+    // The action is called after the colon, the buffer is shifted several times before
+    let (err, position, later_position) = scan_with_buffer("bad");
+    assert!(
+        matches!(err, ::scan_json::Error::ActionError { .. }),
+        "{err:?}"
+    );
+    assert_eq!(err.byte_position(), Some(43));
+    assert_eq!(position, Some(rjiter::jiter::LinePosition::new(3, 9)));
+    assert_eq!(later_position, None);
+
+    // Without the failing action, the parser fails at the end of the unfinished array
+    let (err, position, _) = scan_with_buffer("none");
+    assert!(matches!(err, ::scan_json::Error::RJiterError(_)), "{err:?}");
+    assert_eq!(position.as_ref(), err.line_position());
+    assert_eq!(position.map(|position| position.line), Some(7));
+
+    assert_eq!(
+        ::scan_json::Error::IOError(embedded_io::ErrorKind::Other).byte_position(),
+        None
     );
 }
 
@@ -899,64 +1349,183 @@ fn error_in_end_action() {
 }
 
 #[test]
-fn several_objects_top_level() {
-    let json = r#"{"foo":1}  {"foo":2}  {"foo":3}"#;
-    let mut reader = json.as_bytes();
-    let mut buffer = vec![0u8; 16];
-    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
-    let mut scan_buffer = [0u8; 512];
-    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
-    let writer_cell = RefCell::new(Vec::new());
-
-    // find_action that matches "foo"
-    let find_action = |structural_pseudoname: StructuralPseudoname,
-                       context: ContextIter,
-                       _baton: &RefCell<Vec<u8>>|
-     -> Option<Action<&RefCell<Vec<u8>>, &[u8]>> {
-        if iter_match(
-            || ["foo".as_bytes(), "#top".as_bytes()],
-            structural_pseudoname,
-            context,
-        ) {
-            fn write_foo_marker(_: &mut RJiter<&[u8]>, writer: &RefCell<Vec<u8>>) -> StreamOp {
-                writer.borrow_mut().write_all(b"<foo>").unwrap();
-                StreamOp::None
+fn error_with_code_and_path() {
+    fn scan_error(json: &str) -> scan_json::Error {
+        let mut reader = json.as_bytes();
+        let mut buffer = vec![0u8; 64];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut scan_buffer = [0u8; 512];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+        fn reject_name(_rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+            StreamOp::ErrorWith {
+                message: "Name is not allowed",
+                code: 42,
             }
-            let action: Action<&RefCell<Vec<u8>>, &[u8]> = write_foo_marker;
-            Some(action)
-        } else {
-            None
         }
-    };
-    // find_end_action that matches "foo"
-    let find_end_action = |structural_pseudoname: StructuralPseudoname,
+        let find_action = |structural_pseudoname: StructuralPseudoname,
                            context: ContextIter,
-                           _baton: &RefCell<Vec<u8>>|
-     -> Option<EndAction<&RefCell<Vec<u8>>>> {
-        if iter_match(
-            || ["foo".as_bytes(), "#top".as_bytes()],
-            structural_pseudoname,
-            context,
-        ) {
-            fn write_foo_end_marker(writer: &RefCell<Vec<u8>>) -> Result<(), &'static str> {
-                writer.borrow_mut().write_all(b"</foo>").unwrap();
-                Ok(())
+                           _baton: ()|
+         -> Option<Action<(), &[u8]>> {
+            if iter_match(|| ["name"], structural_pseudoname, context) {
+                let action: Action<(), &[u8]> = reject_name;
+                return Some(action);
             }
-            let action: EndAction<&RefCell<Vec<u8>>> = write_foo_end_marker;
-            Some(action)
-        } else {
             None
-        }
-    };
+        };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: ()|
+         -> Option<EndAction<()>> { None };
 
-    scan(
-        find_action,
-        find_end_action,
-        &mut rjiter,
-        &writer_cell,
-        &mut scan_stack,
-        &Options::new(),
-    )
+        scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            (),
+            &mut scan_stack,
+            &Options::new(),
+        )
+        .unwrap_err()
+    }
+
+    let err = scan_error(r#"{"user": {"name": "Ann"}}"#);
+    assert_eq!(
+        format!("{err}"),
+        "Action error: Name is not allowed (code 42) at position 17"
+    );
+    let scan_json::Error::ActionError {
+        message,
+        position,
+        code,
+        path,
+    } = err
+    else {
+        panic!("Expected ActionError, got {err:?}");
+    };
+    assert_eq!(message, "Name is not allowed");
+    assert_eq!(position, 17);
+    assert_eq!(code, Some(42));
+    assert_eq!(path.as_bytes(), b"#top/user/name");
+    assert!(!path.is_truncated());
+
+    // Only the innermost names are kept for a deep path
+    let long_key = "k".repeat(28);
+    let err = scan_error(&format!(
+        r#"{{"{long_key}": {{"{long_key}": {{"name": 1}}}}}}"#
+    ));
+    let scan_json::Error::ActionError { path, .. } = err else {
+        panic!("Expected ActionError, got {err:?}");
+    };
+    assert_eq!(
+        path.as_bytes(),
+        format!("{long_key}/{long_key}/name").as_bytes()
+    );
+    assert!(path.is_truncated());
+}
+
+#[test]
+fn error_in_end_action_has_parent_path() {
+    let json = r#"{"foo": 123}"#;
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+    fn failing_end_action(_: ()) -> Result<(), &'static str> {
+        Err("Test error in end-action")
+    }
+    let find_action = |_structural_pseudoname: StructuralPseudoname,
+                       _context: ContextIter,
+                       _baton: ()|
+     -> Option<Action<(), &[u8]>> { None };
+    let find_end_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           _baton: ()|
+     -> Option<EndAction<()>> {
+        if iter_match(|| ["foo"], structural_pseudoname, context) {
+            let action: EndAction<()> = failing_end_action;
+            return Some(action);
+        }
+        None
+    };
+
+    let err = scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::new(),
+    )
+    .unwrap_err();
+    let scan_json::Error::ActionError { code, path, .. } = err else {
+        panic!("Expected ActionError, got {err:?}");
+    };
+    assert_eq!(code, None);
+    assert_eq!(path.as_bytes(), b"#top");
+}
+
+#[test]
+fn several_objects_top_level() {
+    let json = r#"{"foo":1}  {"foo":2}  {"foo":3}"#;
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let writer_cell = RefCell::new(Vec::new());
+
+    // find_action that matches "foo"
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       _baton: &RefCell<Vec<u8>>|
+     -> Option<Action<&RefCell<Vec<u8>>, &[u8]>> {
+        if iter_match(
+            || ["foo".as_bytes(), "#top".as_bytes()],
+            structural_pseudoname,
+            context,
+        ) {
+            fn write_foo_marker(_: &mut RJiter<&[u8]>, writer: &RefCell<Vec<u8>>) -> StreamOp {
+                writer.borrow_mut().write_all(b"<foo>").unwrap();
+                StreamOp::None
+            }
+            let action: Action<&RefCell<Vec<u8>>, &[u8]> = write_foo_marker;
+            Some(action)
+        } else {
+            None
+        }
+    };
+    // find_end_action that matches "foo"
+    let find_end_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           _baton: &RefCell<Vec<u8>>|
+     -> Option<EndAction<&RefCell<Vec<u8>>>> {
+        if iter_match(
+            || ["foo".as_bytes(), "#top".as_bytes()],
+            structural_pseudoname,
+            context,
+        ) {
+            fn write_foo_end_marker(writer: &RefCell<Vec<u8>>) -> Result<(), &'static str> {
+                writer.borrow_mut().write_all(b"</foo>").unwrap();
+                Ok(())
+            }
+            let action: EndAction<&RefCell<Vec<u8>>> = write_foo_end_marker;
+            Some(action)
+        } else {
+            None
+        }
+    };
+
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &writer_cell,
+        &mut scan_stack,
+        &Options::new(),
+    )
     .unwrap();
 
     assert_eq!(*writer_cell.borrow(), b"<foo></foo><foo></foo><foo></foo>");
@@ -1468,10 +2037,7 @@ fn stop_early() {
             &mut rjiter,
             (),
             &mut scan_stack,
-            &Options {
-                sse_tokens: &[],
-                stop_early: true, // `true`
-            },
+            &Options::builder().stop_early(true).build().unwrap(),
         )
         .unwrap();
     }
@@ -1483,6 +2049,492 @@ fn stop_early() {
     );
 }
 
+#[test]
+fn stop_when() {
+    let input = r#"data: {} data: [] {"foo": "bar"} [{}, []] 777 true"#;
+    let mut reader = input.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+    let find_action = |_structural_pseudoname: StructuralPseudoname,
+                       _context: ContextIter,
+                       _baton: ()|
+     -> Option<Action<(), &[u8]>> { None };
+    let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: ()|
+     -> Option<EndAction<()>> { None };
+
+    // Stop after 3 values, the SSE tokens are not counted
+    let reports = RefCell::new(Vec::new());
+    let stop_after_3 = |report: &ScanReport| {
+        reports.borrow_mut().push(*report);
+        report.values == 3
+    };
+    let sse_tokens: &[&[u8]] = &[b"data:"];
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::with_sse_tokens(sse_tokens).stop_when(&stop_after_3),
+    )
+    .unwrap();
+
+    assert_eq!(
+        *reports.borrow(),
+        vec![
+            ScanReport {
+                values: 1,
+                position: 8
+            },
+            ScanReport {
+                values: 2,
+                position: 17
+            },
+            ScanReport {
+                values: 3,
+                position: 32
+            },
+        ]
+    );
+
+    // Continue until the position is beyond 40, then check the rest
+    let stop_on_position = |report: &ScanReport| report.position > 40;
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::new().stop_when(&stop_on_position),
+    )
+    .unwrap();
+
+    assert_eq!(
+        rjiter.next_int().unwrap(),
+        rjiter::jiter::NumberInt::Int(777)
+    );
+}
+
+fn scan_keys(json: &str, options: &Options) -> scan_json::Result<Vec<Vec<u8>>> {
+    scan_key_bytes(json.as_bytes(), options)
+}
+
+fn scan_key_bytes(json: &[u8], options: &Options) -> scan_json::Result<Vec<Vec<u8>>> {
+    let mut reader = json;
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let keys = RefCell::new(Vec::new());
+
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       keys: &RefCell<Vec<Vec<u8>>>|
+     -> Option<Action<&RefCell<Vec<Vec<u8>>>, &[u8]>> {
+        if structural_pseudoname == StructuralPseudoname::None {
+            if let Some(key) = context.into_iter().next() {
+                keys.borrow_mut().push(key.to_vec());
+            }
+        }
+        None
+    };
+    let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: &RefCell<Vec<Vec<u8>>>|
+     -> Option<EndAction<&RefCell<Vec<Vec<u8>>>>> { None };
+
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &keys,
+        &mut scan_stack,
+        options,
+    )?;
+    Ok(keys.into_inner())
+}
+
+#[test]
+fn key_normalization() {
+    let json = r#"{"Name": 1, " NAME ": {"In Ner": 2}}"#;
+
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new().with_key_normalization(KeyNormalization::None)
+        )
+        .unwrap(),
+        vec![b"Name".to_vec(), b" NAME ".to_vec(), b"In Ner".to_vec()]
+    );
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new().with_key_normalization(KeyNormalization::AsciiLowercase)
+        )
+        .unwrap(),
+        vec![b"name".to_vec(), b" name ".to_vec(), b"in ner".to_vec()]
+    );
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new().with_key_normalization(KeyNormalization::AsciiTrim)
+        )
+        .unwrap(),
+        vec![b"Name".to_vec(), b"NAME".to_vec(), b"In Ner".to_vec()]
+    );
+
+    fn remove_spaces_lowercase(key: &[u8], out: &mut [u8]) -> usize {
+        let mut len = 0;
+        for b in key.iter().filter(|b| !b.is_ascii_whitespace()) {
+            out[len] = b.to_ascii_lowercase();
+            len += 1;
+        }
+        len
+    }
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new()
+                .with_key_normalization(KeyNormalization::Custom(remove_spaces_lowercase))
+        )
+        .unwrap(),
+        vec![b"name".to_vec(), b"name".to_vec(), b"inner".to_vec()]
+    );
+}
+
+#[test]
+fn long_key_policy() {
+    // The rjiter buffer is 16 bytes
+    let json = r#"{"a": 1, "a_key_longer_than_the_buffer": {"b": "x"}, "c": [2]} {"d": 3}"#;
+
+    let err = scan_keys(json, &Options::new()).unwrap_err();
+    assert!(matches!(err, scan_json::Error::RJiterError(_)));
+
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new().with_long_key_policy(LongKeyPolicy::TruncateKey(5))
+        )
+        .unwrap(),
+        vec![
+            b"a".to_vec(),
+            b"a_key".to_vec(),
+            b"b".to_vec(),
+            b"c".to_vec(),
+            b"d".to_vec()
+        ]
+    );
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new().with_long_key_policy(LongKeyPolicy::SkipValue(5))
+        )
+        .unwrap(),
+        vec![b"a".to_vec(), b"c".to_vec(), b"d".to_vec()]
+    );
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new()
+                .with_long_key_policy(LongKeyPolicy::TruncateKey(5))
+                .with_key_normalization(KeyNormalization::AsciiLowercase)
+        )
+        .unwrap()[1],
+        b"a_key".to_vec()
+    );
+}
+
+#[test]
+fn key_utf8_policy() {
+    let json = b"{\"ok\": 1, \"A\xffB\": {\"c\": 2}}";
+
+    assert_eq!(
+        scan_key_bytes(json, &Options::new()).unwrap(),
+        vec![b"ok".to_vec(), b"A\xffB".to_vec(), b"c".to_vec()]
+    );
+
+    let err = scan_key_bytes(
+        json,
+        &Options::new().with_key_utf8_policy(KeyUtf8Policy::Validate),
+    )
+    .unwrap_err();
+    match err {
+        scan_json::Error::InvalidKeyUtf8 {
+            position,
+            key,
+            valid_up_to,
+        } => {
+            assert_eq!(position, 16);
+            assert_eq!(key.as_bytes(), b"A\xffB");
+            assert!(!key.is_truncated());
+            assert_eq!(valid_up_to, 1);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+
+    assert_eq!(
+        scan_key_bytes(
+            json,
+            &Options::new().with_key_utf8_policy(KeyUtf8Policy::ReplaceInvalid)
+        )
+        .unwrap(),
+        vec![
+            b"ok".to_vec(),
+            "A\u{fffd}B".as_bytes().to_vec(),
+            b"c".to_vec()
+        ]
+    );
+    assert_eq!(
+        scan_key_bytes(
+            json,
+            &Options::new()
+                .with_key_utf8_policy(KeyUtf8Policy::ReplaceInvalid)
+                .with_key_normalization(KeyNormalization::AsciiLowercase)
+        )
+        .unwrap()[1],
+        "a\u{fffd}b".as_bytes().to_vec()
+    );
+}
+
+#[test]
+fn key_utf8_policy_reserves_only_for_invalid_keys() {
+    let json = br#"{"a_key": {"b_key": {"c_key": {"d_key": 1}}}}"#;
+    let run = |pool_size: usize, options: &Options| {
+        let mut reader = &json[..];
+        let mut buffer = vec![0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut scan_buffer = vec![0u8; pool_size];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 8).unwrap();
+        let find_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: ()|
+         -> Option<Action<(), &[u8]>> { None };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: ()|
+         -> Option<EndAction<()>> { None };
+        scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            (),
+            &mut scan_stack,
+            options,
+        )
+    };
+
+    let all_options = |key_utf8_policy: KeyUtf8Policy| {
+        [
+            Options::new(),
+            Options::new().with_long_key_policy(LongKeyPolicy::TruncateKey(10)),
+            Options::new()
+                .with_long_key_policy(LongKeyPolicy::TruncateKey(10))
+                .with_key_normalization(KeyNormalization::AsciiLowercase),
+        ]
+        .map(|options| options.with_key_utf8_policy(key_utf8_policy))
+    };
+    let unchecked = all_options(KeyUtf8Policy::Unchecked);
+    for (i, options) in unchecked.iter().enumerate() {
+        let pool_size = (33..512).find(|size| run(*size, options).is_ok()).unwrap();
+        let err = run(pool_size - 1, options).unwrap_err();
+        assert!(
+            matches!(err, scan_json::Error::MaxNestingExceeded { .. }),
+            "{err:?}"
+        );
+        // The valid keys need no space for the replacement
+        for policy in [KeyUtf8Policy::Validate, KeyUtf8Policy::ReplaceInvalid] {
+            let result = run(pool_size, &all_options(policy)[i]);
+            assert!(result.is_ok(), "{policy:?}, {i}: {result:?}");
+        }
+    }
+}
+
+#[test]
+fn interned_keys() {
+    let interned: &[&[u8]] = &[b"choices", b"delta", b"\xffA", b"content"];
+    // The keys which start with the marker byte 0xFF are escaped in the context
+    let json =
+        b"{\"choices\": [{\"delta\": {\"content\": 1, \"\xff\": 2, \"\xffA\": 3, \"\xff\xffB\": 4}}], \"Content\": 5}";
+    let expected: Vec<Vec<u8>> = [
+        &b"choices"[..],
+        b"delta",
+        b"content",
+        b"\xff",
+        b"\xffA",
+        b"\xff\xffB",
+        b"Content",
+    ]
+    .iter()
+    .map(|key| key.to_vec())
+    .collect();
+
+    let options = Options::new().with_interned_keys(interned);
+    assert_eq!(scan_key_bytes(json, &options).unwrap(), expected);
+    let options = options.with_long_key_policy(LongKeyPolicy::TruncateKey(10));
+    assert_eq!(scan_key_bytes(json, &options).unwrap(), expected);
+
+    // Interned after the normalization
+    let options = Options::new()
+        .with_interned_keys(interned)
+        .with_key_normalization(KeyNormalization::AsciiLowercase);
+    assert_eq!(scan_key_bytes(json, &options).unwrap()[6], b"content");
+}
+
+#[test]
+fn interned_keys_save_context_space() {
+    const KEY: &str = "a_frequent_key_name";
+    let json = format!(r#"{{"{KEY}": {{"{KEY}": {{"{KEY}": {{"{KEY}": "x"}}}}}}}}"#);
+    let run = |options: &Options| {
+        let mut reader = json.as_bytes();
+        let mut buffer = vec![0u8; 32];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut scan_buffer = [0u8; 96];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 8).unwrap();
+
+        // Fail at the innermost value to get the path of the context
+        let find_action = |_structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           _baton: ()|
+         -> Option<Action<(), &[u8]>> {
+            if context.len() < 5 {
+                return None;
+            }
+            Some(|_rjiter: &mut RJiter<&[u8]>, _baton: ()| StreamOp::Error("innermost"))
+        };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: ()|
+         -> Option<EndAction<()>> { None };
+        scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            (),
+            &mut scan_stack,
+            options,
+        )
+        .unwrap_err()
+    };
+
+    let err = run(&Options::new());
+    assert!(
+        matches!(err, scan_json::Error::MaxNestingExceeded { .. }),
+        "{err:?}"
+    );
+
+    let err = run(&Options::new().with_interned_keys(&[KEY.as_bytes()]));
+    let scan_json::Error::ActionError { path, .. } = err else {
+        panic!("unexpected error {err:?}");
+    };
+    assert_eq!(
+        path.as_bytes(),
+        format!("{KEY}/{KEY}/{KEY}").as_bytes(),
+        "the path is truncated to the last names"
+    );
+    assert!(path.is_truncated());
+}
+
+#[test]
+fn require_container_top() {
+    let options = Options::new().with_require_container_top(true);
+    assert_eq!(
+        scan_keys(r#"{"a": 1} [{"b": "x"}]"#, &options).unwrap(),
+        vec![b"a".to_vec(), b"b".to_vec()]
+    );
+
+    for (json, peek, position) in [
+        (r#"{"a": 1} 42"#, Peek::new(b'4'), 9),
+        (r#""text""#, Peek::String, 0),
+        ("  null", Peek::Null, 2),
+        (r#"[1] true"#, Peek::True, 4),
+    ] {
+        let err = scan_keys(json, &options).unwrap_err();
+        assert!(
+            matches!(err, scan_json::Error::TopLevelAtom { peek: p, position: pos } if p == peek && pos == position),
+            "{json}: {err:?}"
+        );
+    }
+    assert_eq!(
+        scan_keys(r#"{"a": 1} 42"#, &Options::new()).unwrap(),
+        vec![b"a".to_vec()]
+    );
+
+    // The SSE tokens are not values
+    let sse_tokens: &[&[u8]] = &[b"data:", b"DONE"];
+    let options = Options::with_sse_tokens(sse_tokens).with_require_container_top(true);
+    assert_eq!(
+        scan_keys(r#"data: {"a": 1} data: [DONE]"#, &options).unwrap(),
+        vec![b"a".to_vec()]
+    );
+    let err = scan_keys(r#"data: "DONE""#, &options).unwrap_err();
+    assert!(
+        matches!(err, scan_json::Error::TopLevelAtom { position: 6, .. }),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn key_utf8_policy_for_long_keys() {
+    // The rjiter buffer is 16 bytes, the truncation cuts the two-byte code point
+    let json = "{\"a\": {\"abcd\u{e9}_longer_than_the_buffer\": 1}}";
+    assert_eq!(
+        scan_keys(
+            json,
+            &Options::new()
+                .with_long_key_policy(LongKeyPolicy::TruncateKey(5))
+                .with_key_utf8_policy(KeyUtf8Policy::Validate)
+        )
+        .unwrap(),
+        vec![b"a".to_vec(), b"abcd".to_vec()]
+    );
+
+    let json = b"{\"a\": {\"ab\xc0_longer_than_the_buffer\": 1}}";
+    let err = scan_key_bytes(
+        json,
+        &Options::new()
+            .with_long_key_policy(LongKeyPolicy::TruncateKey(5))
+            .with_key_utf8_policy(KeyUtf8Policy::Validate),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            scan_json::Error::InvalidKeyUtf8 { key, valid_up_to: 2, .. }
+                if key.as_bytes() == b"ab\xc0_l"
+        ),
+        "{err:?}"
+    );
+
+    assert_eq!(
+        scan_key_bytes(
+            json,
+            &Options::new()
+                .with_long_key_policy(LongKeyPolicy::TruncateKey(5))
+                .with_key_utf8_policy(KeyUtf8Policy::ReplaceInvalid)
+                .with_key_normalization(KeyNormalization::AsciiLowercase)
+        )
+        .unwrap(),
+        vec![b"a".to_vec(), "ab\u{fffd}_l".as_bytes().to_vec()]
+    );
+
+    // The skipped keys are not checked
+    assert_eq!(
+        scan_key_bytes(
+            json,
+            &Options::new()
+                .with_long_key_policy(LongKeyPolicy::SkipValue(5))
+                .with_key_utf8_policy(KeyUtf8Policy::Validate)
+        )
+        .unwrap(),
+        vec![b"a".to_vec()]
+    );
+}
+
 #[test]
 fn lookahead_repair() {
     let json = r#"{"f": 000000, "f": 0.0, "f": 001, "f": 0016, "f": 0017, "f": 0.42}"#;
@@ -1546,7 +2598,7 @@ fn lookahead_repair() {
      -> Option<Action<&RefCell<Vec<u8>>, &[u8]>> {
         // Check if the key is "f" (ignoring context)
         if let Some(key) = context.into_iter().next() {
-            (key == b"f").then(|| handle_f_number as Action<&RefCell<Vec<u8>>, &[u8]>)
+            (key == b"f").then_some(handle_f_number as Action<&RefCell<Vec<u8>>, &[u8]>)
         } else {
             None
         }
@@ -1570,3 +2622,294 @@ fn lookahead_repair() {
     let output = String::from_utf8(writer_cell.borrow().to_vec()).unwrap();
     assert_eq!(output, "[0][0][1][16][17][0.42]");
 }
+
+#[test]
+fn defer_key_dispatch_gives_value_kind() {
+    fn scan_kinds(json: &str, options: &Options) -> Vec<(Vec<u8>, Option<ValueKind>)> {
+        let mut reader = json.as_bytes();
+        let mut buffer = vec![0u8; 16];
+        let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+        let mut scan_buffer = [0u8; 512];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+        let kinds = RefCell::new(Vec::new());
+
+        type Baton<'a> = &'a RefCell<Vec<(Vec<u8>, Option<ValueKind>)>>;
+        let find_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           kinds: Baton|
+         -> Option<Action<Baton, &[u8]>> {
+            if structural_pseudoname == StructuralPseudoname::None {
+                let value_kind = context.value_kind();
+                if let Some(key) = context.into_iter().next() {
+                    kinds.borrow_mut().push((key.to_vec(), value_kind));
+                }
+            }
+            None
+        };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: Baton|
+         -> Option<EndAction<Baton>> { None };
+
+        scan(
+            find_action,
+            find_end_action,
+            &mut rjiter,
+            &kinds,
+            &mut scan_stack,
+            options,
+        )
+        .unwrap();
+        kinds.into_inner()
+    }
+
+    let json = r#"{"o": {"s": "x"}, "a":    [1], "n": null, "i": 42}"#;
+
+    assert_eq!(
+        scan_kinds(json, &Options::new().with_defer_key_dispatch(true)),
+        vec![
+            (b"o".to_vec(), Some(ValueKind::Object)),
+            (b"s".to_vec(), Some(ValueKind::Atom)),
+            (b"a".to_vec(), Some(ValueKind::Array)),
+            (b"n".to_vec(), Some(ValueKind::Atom)),
+            (b"i".to_vec(), Some(ValueKind::Atom)),
+        ]
+    );
+    assert!(scan_kinds(json, &Options::new())
+        .iter()
+        .all(|(_key, value_kind)| value_kind.is_none()));
+}
+
+#[test]
+fn keep_context_between_segments() {
+    type Baton<'a> = &'a RefCell<Vec<String>>;
+
+    fn scan_atoms(segments: &[&str], options: &Options) -> Vec<String> {
+        let atoms = RefCell::new(Vec::new());
+        let mut scan_buffer = [0u8; 512];
+        let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+        let find_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           atoms: Baton|
+         -> Option<Action<Baton, &[u8]>> {
+            if structural_pseudoname == StructuralPseudoname::Atom {
+                let mut names: Vec<String> = context
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+                    .collect();
+                names.reverse();
+                atoms.borrow_mut().push(names.join("/"));
+            }
+            None
+        };
+        let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                               _context: ContextIter,
+                               _baton: Baton|
+         -> Option<EndAction<Baton>> { None };
+
+        for segment in segments {
+            let mut reader = segment.as_bytes();
+            let mut buffer = vec![0u8; 16];
+            let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+            scan(
+                find_action,
+                find_end_action,
+                &mut rjiter,
+                &atoms,
+                &mut scan_stack,
+                options,
+            )
+            .unwrap();
+        }
+        atoms.into_inner()
+    }
+
+    let expected = vec![
+        "#top/items/#array",
+        "#top/items/#array/a",
+        "#top/items/#array",
+        "#top/b/c",
+        "#top",
+    ];
+    let document = r#"{"items": [1, {"a": 2}, 3], "b": {"c": 4}} true"#;
+    assert_eq!(scan_atoms(&[document], &Options::new()), expected);
+
+    let segments = [
+        r#"{"items": [1, {"a": 2}"#,
+        r#", 3], "b": {"c": 4}"#,
+        r#"} "#,
+        r#"true"#,
+    ];
+    assert_eq!(
+        scan_atoms(&segments, &Options::new().with_keep_context(true)),
+        expected
+    );
+
+    // Without the option, a segment in the middle of a document is an error
+    let mut reader = segments[0].as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    let result = scan(
+        |_: StructuralPseudoname, _: ContextIter, _: ()| -> Option<Action<(), &[u8]>> { None },
+        |_: StructuralPseudoname, _: ContextIter, _: ()| -> Option<EndAction<()>> { None },
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::new(),
+    );
+    assert!(result.is_err());
+}
+
+// Scan with the action at the path `#top/data/<key>`, which returns `ValueIsConsumed`
+fn scan_with_consuming_action<'a>(
+    json: &'a str,
+    key: &'static str,
+    action: Action<(), &'a [u8]>,
+) -> scan_json::Result<()> {
+    let mut reader = json.as_bytes();
+    let mut buffer = vec![0u8; 64];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       _baton: ()|
+     -> Option<Action<(), &'a [u8]>> {
+        iter_match(|| [key, "data"], structural_pseudoname, context).then_some(action)
+    };
+    let find_end_action = |_structural_pseudoname: StructuralPseudoname,
+                           _context: ContextIter,
+                           _baton: ()|
+     -> Option<EndAction<()>> { None };
+
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        (),
+        &mut scan_stack,
+        &Options::new(),
+    )
+}
+
+#[test]
+fn keep_context_prefilled_by_caller() {
+    type Baton<'a> = &'a RefCell<Vec<String>>;
+
+    // The caller starts inside `{"items": [...`, the items before are not known
+    let mut scan_buffer = [0u8; 512];
+    let mut scan_stack = U8Pool::new(&mut scan_buffer, 20).unwrap();
+    scan_stack
+        .push_assoc(StructurePosition::Top, b"#top")
+        .unwrap();
+    scan_stack
+        .push_assoc(StructurePosition::ObjectMiddle, b"items")
+        .unwrap();
+    scan_stack
+        .push_assoc(StructurePosition::ArrayMiddle, b"#array")
+        .unwrap();
+
+    let events = RefCell::new(Vec::new());
+    let find_action = |structural_pseudoname: StructuralPseudoname,
+                       context: ContextIter,
+                       events: Baton|
+     -> Option<Action<Baton, &[u8]>> {
+        if structural_pseudoname == StructuralPseudoname::Atom {
+            let mut names: Vec<String> = context
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .collect();
+            names.reverse();
+            events.borrow_mut().push(names.join("/"));
+        }
+        None
+    };
+    let find_end_action = |structural_pseudoname: StructuralPseudoname,
+                           context: ContextIter,
+                           events: Baton|
+     -> Option<EndAction<Baton>> {
+        if structural_pseudoname == StructuralPseudoname::Array {
+            let name = String::from_utf8_lossy(context.clone().next()?).into_owned();
+            let len = context.array_len()?;
+            events.borrow_mut().push(format!("{name} had {len}"));
+        }
+        None
+    };
+
+    let mut reader = r#", 3, [4]], "b": 5}"#.as_bytes();
+    let mut buffer = vec![0u8; 16];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    scan(
+        find_action,
+        find_end_action,
+        &mut rjiter,
+        &events,
+        &mut scan_stack,
+        &Options::new().with_keep_context(true),
+    )
+    .unwrap();
+    assert_eq!(
+        events.into_inner(),
+        vec![
+            "#top/items/#array",
+            "#top/items/#array/#array",
+            "#array had 1",
+            "items had 2",
+            "#top/b",
+        ]
+    );
+}
+
+#[test]
+fn action_consumes_value_incorrectly() {
+    fn consume_nothing(_rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+        StreamOp::ValueIsConsumed
+    }
+    fn consume_first_key(rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+        let _ = rjiter.next_object();
+        StreamOp::ValueIsConsumed
+    }
+    fn consume_value_and_next_key(rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+        let _ = rjiter.next_int();
+        let _ = rjiter.next_key();
+        StreamOp::ValueIsConsumed
+    }
+    fn consume_value(rjiter: &mut RJiter<&[u8]>, _baton: ()) -> StreamOp {
+        let _ = rjiter.next_skip();
+        StreamOp::ValueIsConsumed
+    }
+
+    let json = r#"{"data": {"a": 1, "b": {"c": 2}, "d": 3}}"#;
+    let cases: [(&'static str, Action<(), &[u8]>, usize, usize, &str); 3] = [
+        ("a", consume_nothing, 14, 14, "#top/data/a"),
+        ("b", consume_first_key, 22, 28, "#top/data/b"),
+        ("a", consume_value_and_next_key, 14, 22, "#top/data/a"),
+    ];
+    for (key, action, expected_value_position, expected_position, expected_path) in cases {
+        match scan_with_consuming_action(json, key, action).unwrap_err() {
+            scan_json::Error::ValueConsumptionMismatch {
+                value_position,
+                position,
+                path,
+            } => {
+                assert_eq!(
+                    (value_position, position, path.as_bytes()),
+                    (
+                        expected_value_position,
+                        expected_position,
+                        expected_path.as_bytes()
+                    ),
+                    "key {key}"
+                );
+            }
+            other => panic!("unexpected error for key {key}: {other:?}"),
+        }
+    }
+
+    // Consumed exactly the value, also with whitespace around it
+    let json = "{\"data\": {\"a\": 1 , \"b\": {\"c\": 2}\n}}";
+    scan_with_consuming_action(json, "a", consume_value).unwrap();
+    scan_with_consuming_action(json, "b", consume_value).unwrap();
+}
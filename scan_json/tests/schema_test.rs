@@ -0,0 +1,233 @@
+#![cfg(feature = "schema")]
+
+use scan_json::schema::{validate, Schema, SchemaError, Violation, ViolationKind};
+use scan_json::{RJiter, ValueType};
+use u8pool::U8Pool;
+
+fn validate_json(schema: &str, json: &str) -> Vec<Violation> {
+    let schema = Schema::compile(schema).unwrap();
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+    validate(&schema, &mut rjiter, &mut context).unwrap()
+}
+
+fn summary(violations: &[Violation]) -> Vec<(&str, ViolationKind)> {
+    violations
+        .iter()
+        .map(|v| (v.path.as_str(), v.kind.clone()))
+        .collect()
+}
+
+const USER_SCHEMA: &str = r#"{
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "title": "User",
+    "type": "object",
+    "required": ["id", "name"],
+    "properties": {
+        "id": {"type": "integer"},
+        "name": {"type": "string", "maxLength": 5},
+        "role": {"enum": ["admin", "user", null]},
+        "address": {
+            "type": "object",
+            "required": ["city"],
+            "properties": {"zip": {"type": ["string", "null"]}}
+        },
+        "scores": {"type": "array", "items": {"type": "number"}}
+    }
+}"#;
+
+#[test]
+fn valid_value_has_no_violations() {
+    let json = r#"{"id": 7, "name": "Ann", "role": null, "extra": [1, {"a": 2}],
+        "address": {"city": "Oslo", "zip": null}, "scores": [1, 2.5, -3e2]}"#;
+    assert_eq!(validate_json(USER_SCHEMA, json), vec![]);
+}
+
+#[test]
+fn reports_all_violations_with_paths() {
+    let json = r#"{"id": 7.5, "name": "Annabel", "role": "guest",
+        "address": {"zip": 123}, "scores": [1, "two", [3]]}"#;
+    let violations = validate_json(USER_SCHEMA, json);
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (
+                "#top/id",
+                ViolationKind::WrongType {
+                    found: ValueType::Number
+                }
+            ),
+            (
+                "#top/name",
+                ViolationKind::TooLong {
+                    max_length: 5,
+                    length: 7
+                }
+            ),
+            ("#top/role", ViolationKind::NotInEnum),
+            (
+                "#top/address/zip",
+                ViolationKind::WrongType {
+                    found: ValueType::Number
+                }
+            ),
+            (
+                "#top/address",
+                ViolationKind::MissingRequired("city".to_string())
+            ),
+            (
+                "#top/scores/#array",
+                ViolationKind::WrongType {
+                    found: ValueType::String
+                }
+            ),
+            (
+                "#top/scores/#array",
+                ViolationKind::WrongType {
+                    found: ValueType::Array
+                }
+            ),
+        ]
+    );
+    // The positions of the value and of the object with the missing key
+    assert_eq!(violations[0].position, 7);
+    assert_eq!(violations[4].position, 67);
+    assert_eq!(
+        violations[4].to_string(),
+        r#"#top/address at position 67: missing required key "city""#
+    );
+}
+
+#[test]
+fn wrong_container_type_and_missing_keys() {
+    let violations = validate_json(USER_SCHEMA, r#"{"address": [], "scores": {}}"#);
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (
+                "#top/address",
+                ViolationKind::WrongType {
+                    found: ValueType::Array
+                }
+            ),
+            (
+                "#top/scores",
+                ViolationKind::WrongType {
+                    found: ValueType::Object
+                }
+            ),
+            ("#top", ViolationKind::MissingRequired("id".to_string())),
+            ("#top", ViolationKind::MissingRequired("name".to_string())),
+        ]
+    );
+
+    let violations = validate_json(USER_SCHEMA, r#"["not", "an", "object"]"#);
+    assert_eq!(
+        summary(&violations),
+        vec![(
+            "#top",
+            ViolationKind::WrongType {
+                found: ValueType::Array
+            }
+        )]
+    );
+}
+
+#[test]
+fn max_length_counts_code_points_of_long_string() {
+    // Longer than the `RJiter` buffer
+    let long = "\u{e9}".repeat(40);
+    let schema = r#"{"properties": {"s": {"maxLength": 40}}, "items": {"maxLength": 3}}"#;
+    let json = format!(r#"{{"s": "{long}"}}"#);
+    assert_eq!(validate_json(schema, &json), vec![]);
+
+    let json = format!(r#"{{"s": "{long}x"}}"#);
+    assert_eq!(
+        summary(&validate_json(schema, &json)),
+        vec![(
+            "#top/s",
+            ViolationKind::TooLong {
+                max_length: 40,
+                length: 41
+            }
+        )]
+    );
+
+    // Escapes are decoded before counting
+    let violations = validate_json(schema, r#"["é\n\t", "abcd"]"#);
+    assert_eq!(
+        summary(&violations),
+        vec![(
+            "#top/#array",
+            ViolationKind::TooLong {
+                max_length: 3,
+                length: 4
+            }
+        )]
+    );
+}
+
+#[test]
+fn enum_compares_scalars() {
+    let schema = r#"{"items": {"enum": [1, "1", true, null]}}"#;
+    let violations = validate_json(schema, r#"[1.0, "1", true, null, 2, "2", false, {}]"#);
+    let positions: Vec<usize> = violations.iter().map(|v| v.position).collect();
+    assert_eq!(positions, vec![23, 26, 31]);
+    assert!(violations
+        .iter()
+        .all(|v| v.kind == ViolationKind::NotInEnum));
+}
+
+#[test]
+fn validates_next_top_level_value() {
+    let schema = Schema::compile(r#"{"required": ["id"]}"#).unwrap();
+    let json = r#"{"id": 1} {"name": "x"} {"id": 2}"#;
+    let mut reader = json.as_bytes();
+    let mut buffer = [0u8; 32];
+    let mut rjiter = RJiter::new(&mut reader, &mut buffer);
+    let mut context_buffer = [0u8; 512];
+    let mut context = U8Pool::new(&mut context_buffer, 20).unwrap();
+
+    let mut n_violations = Vec::new();
+    for _ in 0..3 {
+        context.clear();
+        let violations = validate(&schema, &mut rjiter, &mut context).unwrap();
+        n_violations.push(violations.len());
+    }
+    assert_eq!(n_violations, vec![0, 1, 0]);
+}
+
+#[test]
+fn compile_rejects_unsupported_schemas() {
+    assert_eq!(
+        Schema::compile(r#"{"minLength": 1}"#).unwrap_err(),
+        SchemaError::UnsupportedKeyword("minLength".to_string())
+    );
+    assert_eq!(
+        Schema::compile(r##"{"properties": {"a": {"$ref": "#"}}}"##).unwrap_err(),
+        SchemaError::UnsupportedKeyword("$ref".to_string())
+    );
+    assert_eq!(
+        Schema::compile(r#"{"type": "text"}"#).unwrap_err(),
+        SchemaError::InvalidKeyword("type".to_string())
+    );
+    assert_eq!(
+        Schema::compile(r#"{"required": "id"}"#).unwrap_err(),
+        SchemaError::InvalidKeyword("required".to_string())
+    );
+    assert_eq!(
+        Schema::compile(r#"{"enum": [[1]]}"#).unwrap_err(),
+        SchemaError::InvalidKeyword("enum".to_string())
+    );
+    assert_eq!(
+        Schema::compile(r#"{"items": true}"#).unwrap_err(),
+        SchemaError::NotAnObject
+    );
+    assert!(matches!(
+        Schema::compile(r#"{"type": "#),
+        Err(SchemaError::Json(_))
+    ));
+}
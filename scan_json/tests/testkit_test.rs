@@ -0,0 +1,71 @@
+#![cfg(feature = "testkit")]
+
+use scan_json::testkit::{
+    assert_golden_file, assert_trace, format_trace, run_and_capture, run_and_capture_with,
+    EventKind,
+};
+use scan_json::Options;
+
+#[test]
+fn capture_matching_events() {
+    let json = r#"{"user": {"name": "Ann", "tags": ["a", "b"]}, "name": "top"}"#;
+    let events = run_and_capture(json, &["user/name", "tags/#array", "tags/#array/#atom"]).unwrap();
+
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[0].kind, EventKind::Begin);
+    assert_eq!(events[0].path, "#top/user/name");
+    assert_eq!(events[0].trigger, "user/name");
+    assert_trace(
+        &events,
+        "
+        begin #top/user/name <- user/name
+        end #top/user/name <- user/name
+        begin #top/user/tags/#array <- tags/#array
+        begin #top/user/tags/#array/#atom <- tags/#array/#atom
+        begin #top/user/tags/#array/#atom <- tags/#array/#atom
+        end #top/user/tags/#array <- tags/#array
+        ",
+    );
+}
+
+#[test]
+#[should_panic(expected = "trace mismatch")]
+fn assert_trace_reports_mismatch() {
+    let events = run_and_capture(r#"{"a": 1}"#, &["a"]).unwrap();
+    assert_trace(&events, "begin #top/a <- a");
+}
+
+#[test]
+fn capture_with_options_and_errors() {
+    let json = "data: {\"a\": 1}\ndata: [DONE]\n";
+    let sse_tokens: &[&[u8]] = &[b"data:", b"DONE"];
+    let events = run_and_capture_with(
+        json,
+        &["#top/#object"],
+        &Options::with_sse_tokens(sse_tokens),
+    )
+    .unwrap();
+    assert_eq!(
+        format_trace(&events),
+        "begin #top/#object <- #top/#object\nend #top/#object <- #top/#object\n"
+    );
+
+    let result = run_and_capture_with(r#"{"a": [1, }"#, &["a"], &Options::new());
+    assert!(result.is_err());
+    assert!(run_and_capture(r#"{"a": [1, }"#, &["a"]).is_err());
+}
+
+#[test]
+fn compare_with_golden_file() {
+    let json =
+        r#"{"choices": [{"delta": {"content": "Hello"}}, {"delta": {"role": "assistant"}}]}"#;
+    let events = run_and_capture(json, &["choices/*/delta", "delta/content"]).unwrap();
+    let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/llm_chunk.trace");
+    assert_golden_file(&events, golden).unwrap();
+
+    let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/missing.trace");
+    if std::env::var_os(scan_json::testkit::UPDATE_GOLDEN_ENV).is_none() {
+        let err = assert_golden_file(&events, missing).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+}
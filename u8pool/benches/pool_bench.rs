@@ -0,0 +1,106 @@
+// Benchmarks of the stack operations and the iterators.
+// Run with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use u8pool::U8Pool;
+
+const SLICES: usize = 64;
+
+#[derive(Clone, Copy)]
+struct Position {
+    begin: usize,
+    is_object: bool,
+}
+
+fn fill(pool: &mut U8Pool) {
+    for _ in 0..SLICES {
+        pool.push(b"some-key").unwrap();
+    }
+}
+
+fn fill_assoc(pool: &mut U8Pool) {
+    for i in 0..SLICES {
+        let position = Position {
+            begin: i,
+            is_object: i % 2 == 0,
+        };
+        pool.push_assoc(position, b"some-key").unwrap();
+    }
+}
+
+fn bench_push_pop(c: &mut Criterion) {
+    let mut buffer = [0u8; 4096];
+    let mut pool = U8Pool::new(&mut buffer, SLICES).unwrap();
+
+    c.bench_function("push", |b| {
+        b.iter(|| {
+            pool.clear();
+            fill(&mut pool);
+        });
+    });
+
+    pool.clear();
+    c.bench_function("push_pop", |b| {
+        b.iter(|| {
+            fill(&mut pool);
+            while let Some(slice) = pool.pop() {
+                black_box(slice);
+            }
+        });
+    });
+}
+
+fn bench_iter(c: &mut Criterion) {
+    let mut buffer = [0u8; 4096];
+    let mut pool = U8Pool::new(&mut buffer, SLICES).unwrap();
+    fill(&mut pool);
+
+    c.bench_function("iter", |b| {
+        b.iter(|| pool.iter().map(<[u8]>::len).sum::<usize>());
+    });
+
+    c.bench_function("iter_rev", |b| {
+        b.iter(|| pool.iter_rev().map(<[u8]>::len).sum::<usize>());
+    });
+}
+
+fn bench_assoc_iter(c: &mut Criterion) {
+    let mut buffer = [0u8; 4096];
+    let mut pool = U8Pool::new(&mut buffer, SLICES).unwrap();
+    fill_assoc(&mut pool);
+
+    c.bench_function("iter_assoc", |b| {
+        b.iter(|| {
+            // Safe: all the slices are pushed with `push_assoc::<Position>`
+            unsafe { pool.iter_assoc::<Position>() }
+                .filter(|(position, _)| position.is_object)
+                .map(|(position, data)| position.begin + data.len())
+                .sum::<usize>()
+        });
+    });
+
+    c.bench_function("iter_assoc_rev", |b| {
+        b.iter(|| {
+            // Safe: all the slices are pushed with `push_assoc::<Position>`
+            unsafe { pool.iter_assoc_rev::<Position>() }
+                .filter(|(position, _)| position.is_object)
+                .map(|(position, data)| position.begin + data.len())
+                .sum::<usize>()
+        });
+    });
+
+    // A context walk stops near the top of the stack
+    c.bench_function("iter_assoc_rev_top", |b| {
+        b.iter(|| {
+            // Safe: all the slices are pushed with `push_assoc::<Position>`
+            unsafe { pool.iter_assoc_rev::<Position>() }
+                .take(2)
+                .map(|(position, _)| position.begin)
+                .sum::<usize>()
+        });
+    });
+}
+
+criterion_group!(benches, bench_push_pop, bench_iter, bench_assoc_iter);
+criterion_main!(benches);
@@ -1,8 +1,15 @@
+use crate::element::ElementWriter;
+#[cfg(feature = "embedded-io")]
+use crate::error::PushFromReaderError;
 use crate::error::U8PoolError;
+#[cfg(feature = "ffi")]
+use crate::ffi::U8PoolFfi;
 use crate::iter::{U8PoolAssocIter, U8PoolAssocRevIter, U8PoolIter, U8PoolPairIter, U8PoolRevIter};
-use crate::slice_descriptor::SliceDescriptor;
+use crate::report::MemoryReport;
+use crate::slice_descriptor::{
+    SliceDescriptor, COMPACT_SLICE_DESCRIPTOR_SIZE, SLICE_DESCRIPTOR_SIZE,
+};
 
-const SLICE_DESCRIPTOR_SIZE: usize = 4; // 2 bytes start + 2 bytes length
 const DEFAULT_MAX_SLICES: usize = 32;
 
 /// A zero-allocation stack for u8 slices copied to a client-provided buffer
@@ -23,7 +30,29 @@ impl<'a> U8Pool<'a> {
     /// - `max_slices` is 0
     /// - The buffer is empty
     /// - The buffer is too small to hold the metadata for `max_slices`
-    pub fn new(buffer: &'a mut [u8], max_slices: usize) -> Result<Self, U8PoolError> {
+    pub const fn new(buffer: &'a mut [u8], max_slices: usize) -> Result<Self, U8PoolError> {
+        Self::new_with_descriptors(buffer, max_slices, false)
+    }
+
+    /// Creates a new `U8Pool` with compact slice descriptors: 1 byte for the
+    /// offset and 1 byte for the length instead of 2 and 2.
+    ///
+    /// The metadata takes half the space, which matters for small buffers
+    /// with many short slices. The data section is limited to 255 bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::InvalidInitialization` for the same reasons as `new`,
+    /// and if the data section (the buffer without the metadata) is longer than 255 bytes.
+    pub const fn new_compact(buffer: &'a mut [u8], max_slices: usize) -> Result<Self, U8PoolError> {
+        Self::new_with_descriptors(buffer, max_slices, true)
+    }
+
+    const fn new_with_descriptors(
+        buffer: &'a mut [u8],
+        max_slices: usize,
+        compact: bool,
+    ) -> Result<Self, U8PoolError> {
         if max_slices == 0 {
             return Err(U8PoolError::InvalidInitialization {
                 reason: "max_slices cannot be zero",
@@ -36,7 +65,12 @@ impl<'a> U8Pool<'a> {
             });
         }
 
-        let metadata_space = max_slices * SLICE_DESCRIPTOR_SIZE;
+        let descriptor_size = if compact {
+            COMPACT_SLICE_DESCRIPTOR_SIZE
+        } else {
+            SLICE_DESCRIPTOR_SIZE
+        };
+        let metadata_space = max_slices * descriptor_size;
         let min_required = metadata_space + 1; // At least 1 byte for data
 
         if buffer.len() < min_required {
@@ -45,8 +79,18 @@ impl<'a> U8Pool<'a> {
             });
         }
 
+        if compact && buffer.len() - metadata_space > u8::MAX as usize {
+            return Err(U8PoolError::InvalidInitialization {
+                reason: "buffer too large for compact descriptors",
+            });
+        }
+
         let (descriptor_buffer, data) = buffer.split_at_mut(metadata_space);
-        let descriptor = SliceDescriptor::new(descriptor_buffer);
+        let descriptor = if compact {
+            SliceDescriptor::new_compact(descriptor_buffer)
+        } else {
+            SliceDescriptor::new(descriptor_buffer)
+        };
 
         Ok(Self {
             data,
@@ -61,10 +105,42 @@ impl<'a> U8Pool<'a> {
     /// # Errors
     ///
     /// Returns `U8PoolError::InvalidInitialization` if the buffer is too small.
-    pub fn with_default_max_slices(buffer: &'a mut [u8]) -> Result<Self, U8PoolError> {
+    pub const fn with_default_max_slices(buffer: &'a mut [u8]) -> Result<Self, U8PoolError> {
         Self::new(buffer, DEFAULT_MAX_SLICES)
     }
 
+    /// Creates a pool without a buffer, to be initialized later with `init`.
+    ///
+    /// The function is `const`, therefore the pool can be a `static`,
+    /// for example, in firmware which reserves the memory at build time.
+    /// Until `init`, the pool has no capacity and any push fails with `SliceLimitExceeded`.
+    #[must_use]
+    pub const fn new_uninit() -> Self {
+        Self {
+            data: &mut [],
+            count: 0,
+            max_slices: 0,
+            descriptor: SliceDescriptor::new(&mut []),
+        }
+    }
+
+    /// Attaches the buffer to the pool and removes all slices, see `new` and `new_uninit`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::InvalidInitialization` for the same reasons as `new`.
+    /// The pool is not changed in this case.
+    pub fn init(&mut self, buffer: &'a mut [u8], max_slices: usize) -> Result<(), U8PoolError> {
+        *self = Self::new(buffer, max_slices)?;
+        Ok(())
+    }
+
+    /// Returns `true` if the pool has a buffer: created with `new` or initialized with `init`.
+    #[must_use]
+    pub const fn is_initialized(&self) -> bool {
+        self.max_slices > 0
+    }
+
     /// Returns the number of slices currently stored in the pool.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -77,6 +153,101 @@ impl<'a> U8Pool<'a> {
         self.count == 0
     }
 
+    /// Describes how the buffer is used: the metadata (slice descriptors)
+    /// and the data (slices, associated values and alignment padding).
+    ///
+    /// Useful to choose the buffer size and `max_slices`.
+    #[must_use]
+    pub fn memory_report(&self) -> MemoryReport {
+        let descriptor_size = self.descriptor.size();
+        MemoryReport {
+            descriptor_size,
+            max_slices: self.max_slices,
+            slices: self.count,
+            metadata_capacity: self.max_slices * descriptor_size,
+            metadata_used: self.count * descriptor_size,
+            data_capacity: self.data.len(),
+            data_used: self.data_used(),
+        }
+    }
+
+    /// Returns the pointers and lengths of the metadata and data sections,
+    /// for example, to read the slices from C without copying.
+    ///
+    /// The layout of the descriptors is described in `U8PoolFfi`.
+    /// The pointers are valid while the pool is alive and not modified.
+    /// After a change of the pool, take a new view.
+    #[cfg(feature = "ffi")]
+    #[must_use]
+    pub fn as_ffi_parts(&self) -> U8PoolFfi {
+        let descriptor_size = self.descriptor.size();
+        U8PoolFfi {
+            metadata: self.descriptor.as_bytes().as_ptr(),
+            metadata_len: self.count * descriptor_size,
+            data: self.data.as_ptr(),
+            data_len: self.data.len(),
+            count: self.count,
+            descriptor_size,
+        }
+    }
+
+    /// Validates the metadata of the pool: the number of slices, the bounds
+    /// of the slice descriptors, their order and the accounting of the used data.
+    ///
+    /// The check is for tests of the pool and of the code which embeds it,
+    /// for example, after each operation of a random operation sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::InvariantViolation` with the index of the first bad slice.
+    #[cfg(feature = "testing")]
+    pub fn check_invariants(&self) -> Result<(), U8PoolError> {
+        let violation = |index, reason| Err(U8PoolError::InvariantViolation { index, reason });
+        let descriptor_size = self.descriptor.size();
+        if self.count > self.max_slices {
+            return violation(self.count, "more slices than max_slices");
+        }
+        if self.max_slices > 0 && self.descriptor.get(self.max_slices - 1).is_none() {
+            return violation(
+                self.max_slices,
+                "metadata section is too small for max_slices",
+            );
+        }
+        if descriptor_size == COMPACT_SLICE_DESCRIPTOR_SIZE && self.data.len() > u8::MAX as usize {
+            return violation(
+                self.count,
+                "data section is too large for compact descriptors",
+            );
+        }
+        let mut prev_end = 0;
+        for index in 0..self.count {
+            let Some((start, length)) = self.descriptor.get(index) else {
+                return violation(index, "descriptor is outside the metadata section");
+            };
+            let Some(end) = start.checked_add(length) else {
+                return violation(index, "slice end overflows");
+            };
+            if end > self.data.len() {
+                return violation(index, "slice is outside the data section");
+            }
+            if start < prev_end {
+                return violation(
+                    index,
+                    "slice overlaps the previous slice or is out of order",
+                );
+            }
+            prev_end = end;
+        }
+        let report = self.memory_report();
+        if report.metadata_used > report.metadata_capacity {
+            return violation(self.count, "used metadata exceeds the metadata section");
+        }
+        if report.data_used != prev_end || report.data_used > report.data_capacity {
+            return violation(self.count, "used data doesn't end at the last slice");
+        }
+        Ok(())
+    }
+
     /// Removes all slices from the pool, making it empty.
     ///
     /// This does not affect the underlying data buffer, only the slice count.
@@ -127,31 +298,95 @@ impl<'a> U8Pool<'a> {
         &mut self,
         data_size: usize,
     ) -> Result<(usize, usize), U8PoolError> {
+        let current_pos = self.data_used();
+        let aligned_start = current_pos.next_multiple_of(core::mem::align_of::<T>());
+        self.reserve_buffer_space(
+            current_pos,
+            aligned_start,
+            core::mem::size_of::<T>() + data_size,
+        )
+    }
+
+    /// Like `reserve_aligned_buffer_space`, but the start is aligned in memory:
+    /// the address of `self.data[aligned_start]` is a multiple of `align`.
+    ///
+    /// `align` must be a power of two.
+    fn reserve_address_aligned_buffer_space(
+        &mut self,
+        align: usize,
+        data_size: usize,
+    ) -> Result<(usize, usize), U8PoolError> {
+        let current_pos = self.data_used();
+        let address = (self.data.as_ptr() as usize).wrapping_add(current_pos);
+        let padding = address.wrapping_neg() & (align - 1);
+        self.reserve_buffer_space(current_pos, current_pos + padding, data_size)
+    }
+
+    /// Validates the capacity for the bytes `current_pos..aligned_start + size`,
+    /// see `reserve_aligned_buffer_space` for the result and the errors.
+    fn reserve_buffer_space(
+        &mut self,
+        current_pos: usize,
+        aligned_start: usize,
+        size: usize,
+    ) -> Result<(usize, usize), U8PoolError> {
+        let total_size = (aligned_start - current_pos) + size;
+        let end = aligned_start + size;
+
+        let available = self.data.len().saturating_sub(current_pos);
+
         // Check if we've reached the maximum number of slices
         if self.count >= self.max_slices {
             return Err(U8PoolError::SliceLimitExceeded {
                 max_slices: self.max_slices,
+                requested: total_size,
+                available,
             });
         }
 
-        let current_pos = self.data_used();
-        let aligned_start = current_pos.next_multiple_of(core::mem::align_of::<T>());
-        let total_size = (aligned_start - current_pos) + core::mem::size_of::<T>() + data_size;
-        let end = aligned_start + core::mem::size_of::<T>() + data_size;
-
-        let available = self.data.len().saturating_sub(current_pos);
-
         // Check if we have enough space for the aligned data
         if total_size > available {
             return Err(U8PoolError::BufferOverflow {
                 requested: total_size,
                 available,
+                free_slices: self.free_slices(),
             });
         }
 
         Ok((aligned_start, end))
     }
 
+    /// Number of slices which can still be added
+    pub(crate) fn free_slices(&self) -> usize {
+        self.max_slices.saturating_sub(self.count)
+    }
+
+    pub(crate) fn data_capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(crate) fn data_region(&self, start: usize, end: usize) -> Option<&[u8]> {
+        self.data.get(start..end)
+    }
+
+    pub(crate) fn data_region_mut(&mut self, start: usize, end: usize) -> Option<&mut [u8]> {
+        self.data.get_mut(start..end)
+    }
+
+    /// Adds the slice written by `ElementWriter`
+    pub(crate) fn finalize_element(
+        &mut self,
+        start: usize,
+        len: usize,
+    ) -> Result<&[u8], U8PoolError> {
+        self.finalize_push(start, len)?;
+        self.data_region(start, start + len)
+            .ok_or(U8PoolError::IndexOutOfBounds {
+                index: start + len,
+                length: self.data.len(),
+            })
+    }
+
     /// Finalizes a push operation by storing the slice descriptor.
     ///
     /// # Contract
@@ -192,6 +427,39 @@ impl<'a> U8Pool<'a> {
         Ok(&self.data[aligned_start..end])
     }
 
+    /// Pushes a slice which starts at a memory address aligned to `align`,
+    /// and returns a reference to the stored slice.
+    ///
+    /// Padding bytes may be inserted before the slice, as for `push_assoc`.
+    /// The alignment is of the address, not of the offset in the buffer,
+    /// therefore the stored bytes can be reinterpreted in place as a
+    /// `#[repr(C)]` struct with the alignment up to `align`.
+    ///
+    /// `retain` and `retain_assoc` move the slices and don't keep this alignment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::InvalidAlignment` if `align` is not a power of two.
+    /// Otherwise, the same as for `push`, with the padding included in the requested size.
+    ///
+    pub fn push_aligned(&mut self, data: &[u8], align: usize) -> Result<&[u8], U8PoolError> {
+        if !align.is_power_of_two() {
+            return Err(U8PoolError::InvalidAlignment { align });
+        }
+        let (aligned_start, end) = self.reserve_address_aligned_buffer_space(align, data.len())?;
+
+        // Safe: reserve_address_aligned_buffer_space() guarantees the range is within bounds
+        #[allow(clippy::indexing_slicing)]
+        let data_slice = &mut self.data[aligned_start..end];
+        data_slice.copy_from_slice(data);
+
+        self.finalize_push(aligned_start, end - aligned_start)?;
+
+        // Safe: The range is guaranteed to be within bounds and finalized
+        #[allow(clippy::indexing_slicing)]
+        Ok(&self.data[aligned_start..end])
+    }
+
     /// Removes and returns the last slice from the vector.
     ///
     /// Returns `None` if the vector is empty.
@@ -234,6 +502,62 @@ impl<'a> U8Pool<'a> {
         self.get(self.count - 1)
     }
 
+    /// Reads up to `max_len` bytes from `reader` directly into the pool as a new slice,
+    /// and returns a reference to the stored slice.
+    ///
+    /// Reading stops when `max_len` bytes are read or the reader reports the end of input.
+    /// The unused part of the reserved space is released. On error, nothing is pushed.
+    ///
+    /// # Errors
+    ///
+    /// - `PushFromReaderError::Pool` if the pool can't reserve `max_len` bytes,
+    ///   see `push` for details
+    /// - `PushFromReaderError::Read` if the reader fails
+    ///
+    #[cfg(feature = "embedded-io")]
+    pub fn push_from_reader<R: embedded_io::Read>(
+        &mut self,
+        reader: &mut R,
+        max_len: usize,
+    ) -> Result<&[u8], PushFromReaderError<R::Error>> {
+        let (start, max_end) = self.reserve_aligned_buffer_space::<()>(max_len)?;
+
+        let mut end = start;
+        while end < max_end {
+            // Safe: reserve_aligned_buffer_space() guarantees the range is within bounds
+            #[allow(clippy::indexing_slicing)]
+            let n = reader
+                .read(&mut self.data[end..max_end])
+                .map_err(PushFromReaderError::Read)?;
+            if n == 0 {
+                break;
+            }
+            end += n;
+        }
+
+        self.finalize_push(start, end - start)?;
+
+        // Safe: The range is guaranteed to be within bounds and finalized
+        #[allow(clippy::indexing_slicing)]
+        Ok(&self.data[start..end])
+    }
+
+    /// Starts a new slice which is written piece by piece, without a staging buffer.
+    ///
+    /// The returned `ElementWriter` appends bytes to the free space of the pool,
+    /// and implements `embedded_io::Write` with the `embedded-io` feature.
+    /// The slice is added by `ElementWriter::commit`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::SliceLimitExceeded` if the maximum number of slices
+    /// has been reached.
+    ///
+    pub fn begin_element(&mut self) -> Result<ElementWriter<'_, 'a>, U8PoolError> {
+        let (start, _end) = self.reserve_aligned_buffer_space::<()>(0)?;
+        Ok(ElementWriter::new(self, start))
+    }
+
     // -------------------------------------------------------------------------
     // Associated push/pop/get methods
     //
@@ -302,6 +626,59 @@ impl<'a> U8Pool<'a> {
         }
     }
 
+    /// Pushes an associated value followed by a data slice, which is filled in place.
+    ///
+    /// Reserves `max_len` bytes for the data and calls `fill` with them.
+    /// `fill` returns the number of bytes it has written, the rest of the
+    /// reserved space is released. The result is clamped to `max_len`.
+    ///
+    /// Useful to store a transformed copy of some data without an intermediate buffer.
+    ///
+    /// # Errors
+    ///
+    /// The same as for `push_assoc`, for the reserved size `max_len`.
+    ///
+    pub fn push_assoc_with<T: Sized>(
+        &mut self,
+        assoc: T,
+        max_len: usize,
+        fill: impl FnOnce(&mut [u8]) -> usize,
+    ) -> Result<(&T, &[u8]), U8PoolError> {
+        let (aligned_start, max_end) = self.reserve_aligned_buffer_space::<T>(max_len)?;
+
+        let assoc_size = core::mem::size_of::<T>();
+        let assoc_end = aligned_start + assoc_size;
+
+        // Safe: reserve_aligned_buffer_space() guarantees all ranges are within bounds
+        #[allow(clippy::indexing_slicing)]
+        let assoc_slice = &mut self.data[aligned_start..assoc_end];
+        #[allow(unsafe_code)]
+        unsafe {
+            let assoc_ptr = assoc_slice.as_mut_ptr().cast::<T>();
+            core::ptr::write(assoc_ptr, assoc);
+        }
+
+        // Safe: reserve_aligned_buffer_space() guarantees all ranges are within bounds
+        #[allow(clippy::indexing_slicing)]
+        let data_slice = &mut self.data[assoc_end..max_end];
+        let end = assoc_end + fill(data_slice).min(max_len);
+
+        self.finalize_push(aligned_start, end - aligned_start)?;
+
+        // Safe: All ranges are guaranteed to be within bounds and finalized
+        #[allow(clippy::indexing_slicing)]
+        let stored_assoc_slice = &self.data[aligned_start..assoc_end];
+        #[allow(clippy::indexing_slicing)]
+        let stored_data_slice = &self.data[assoc_end..end];
+
+        #[allow(unsafe_code)]
+        unsafe {
+            let assoc_ptr = stored_assoc_slice.as_ptr().cast::<T>();
+            let assoc_ref = &*assoc_ptr;
+            Ok((assoc_ref, stored_data_slice))
+        }
+    }
+
     /// Helper function to validate and compute buffer positions for associated data access.
     ///
     /// Validates that the index is within bounds and that the stored data is large enough
@@ -492,6 +869,82 @@ impl<'a> U8Pool<'a> {
         Some(data_slice)
     }
 
+    /// Returns a mutable reference to the top data bytes, to change them in place.
+    ///
+    /// Returns `None` if the stack is empty.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the last pushed item was indeed pushed with `push_assoc`
+    /// and that the type `T` matches the original associated type.
+    #[must_use]
+    pub fn top_assoc_bytes_mut<'b, T: Sized + 'b>(&'b mut self) -> Option<&'b mut [u8]> {
+        if self.count == 0 {
+            return None;
+        }
+        let last_index = self.count - 1;
+        let (_start, assoc_end, data_end) = self.get_validated_assoc_positions::<T>(last_index)?;
+
+        // Safe: get_validated_assoc_positions() guarantees all positions are within bounds
+        #[allow(clippy::indexing_slicing)]
+        let data_slice = &mut self.data[assoc_end..data_end];
+        Some(data_slice)
+    }
+
+    /// Rewrites the data bytes of the top associated item in place, with the space for
+    /// `max_len` bytes, which can be more than the current data.
+    ///
+    /// `fill` gets the space, which starts with the current data bytes, and returns
+    /// the new length of the data. The result is clamped to `max_len`. Useful to compute
+    /// the new data from the old one, for example, to expand it, without a second buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::IndexOutOfBounds` if the pool is empty.
+    /// Returns `U8PoolError::BufferOverflow` if there is insufficient space in the buffer for `max_len` bytes.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the last pushed item was indeed pushed with `push_assoc`
+    /// and that the type `T` matches the original associated type.
+    pub fn rewrite_top_assoc_bytes<T: Sized>(
+        &mut self,
+        max_len: usize,
+        fill: impl FnOnce(&mut [u8]) -> usize,
+    ) -> Result<&[u8], U8PoolError> {
+        if self.count == 0 {
+            return Err(U8PoolError::IndexOutOfBounds {
+                index: 0,
+                length: 0,
+            });
+        }
+
+        let last_index = self.count - 1;
+        let (start, assoc_end, _data_end) = self
+            .get_validated_assoc_positions::<T>(last_index)
+            .ok_or(U8PoolError::InvalidInitialization {
+                reason: "failed to get validated positions for top item",
+            })?;
+
+        let max_end = assoc_end + max_len;
+        if max_end > self.data.len() {
+            return Err(U8PoolError::BufferOverflow {
+                requested: max_len,
+                available: self.data.len().saturating_sub(assoc_end),
+                free_slices: self.free_slices(),
+            });
+        }
+
+        // Safe: We've verified that max_end <= self.data.len()
+        #[allow(clippy::indexing_slicing)]
+        let end = assoc_end + fill(&mut self.data[assoc_end..max_end]).min(max_len);
+        self.descriptor.set(last_index, start, end - start)?;
+
+        // Safe: end <= max_end <= self.data.len()
+        #[allow(clippy::indexing_slicing)]
+        Ok(&self.data[assoc_end..end])
+    }
+
     /// Replaces the data bytes of the top associated item with new data, keeping the associated object unchanged.
     ///
     /// This function is optimized compared to `pop_assoc` followed by `push_assoc` by reusing the same
@@ -538,6 +991,7 @@ impl<'a> U8Pool<'a> {
             return Err(U8PoolError::BufferOverflow {
                 requested: new_data_size,
                 available: self.data.len().saturating_sub(assoc_end),
+                free_slices: self.free_slices(),
             });
         }
 
@@ -554,6 +1008,68 @@ impl<'a> U8Pool<'a> {
         Ok(data_slice)
     }
 
+    // -------------------------------------------------------------------------
+    // Filtering
+    //
+
+    /// Keeps only the slices for which `keep` returns `true`, in the same order.
+    ///
+    /// The kept slices are moved down in the buffer in one pass, without a second buffer.
+    /// The space of the removed slices becomes free.
+    ///
+    /// For slices pushed with `push_assoc`, use `retain_assoc`, which keeps the alignment.
+    pub fn retain(&mut self, mut keep: impl FnMut(&[u8]) -> bool) {
+        self.retain_aligned(1, |data| keep(data));
+    }
+
+    /// Like `retain`, but for slices pushed with `push_assoc`: `keep` gets
+    /// the associated value and the data slice.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that all items in the pool were pushed with `push_assoc`
+    /// and that the type `T` matches the original associated type for all items.
+    #[allow(unsafe_code)]
+    pub unsafe fn retain_assoc<T: Sized>(&mut self, mut keep: impl FnMut(&T, &[u8]) -> bool) {
+        let assoc_size = core::mem::size_of::<T>();
+        self.retain_aligned(core::mem::align_of::<T>(), |stored| {
+            let Some((assoc, data)) = stored.split_at_checked(assoc_size) else {
+                return false;
+            };
+            // Safe: the caller guarantees the type, the start of the stored bytes
+            // is aligned for `T` (the contract of `push_assoc` and `retain_aligned`)
+            let assoc_ref = unsafe { &*assoc.as_ptr().cast::<T>() };
+            keep(assoc_ref, data)
+        });
+    }
+
+    /// Moves each kept slice to the first position after the previous kept slice
+    /// which is a multiple of `align`, as `reserve_aligned_buffer_space` does.
+    /// The new position is never after the old one, therefore the slices don't overlap.
+    fn retain_aligned(&mut self, align: usize, mut keep: impl FnMut(&[u8]) -> bool) {
+        let mut n_kept = 0;
+        let mut write_pos: usize = 0;
+        for index in 0..self.count {
+            let Some((start, length)) = self.descriptor.get(index) else {
+                break;
+            };
+            if !self
+                .data_region(start, start + length)
+                .is_some_and(&mut keep)
+            {
+                continue;
+            }
+            let new_start = write_pos.next_multiple_of(align);
+            self.data.copy_within(start..start + length, new_start);
+            if self.descriptor.set(n_kept, new_start, length).is_err() {
+                break;
+            }
+            n_kept += 1;
+            write_pos = new_start + length;
+        }
+        self.count = n_kept;
+    }
+
     // -------------------------------------------------------------------------
     // Iterators
     //
@@ -564,6 +1080,14 @@ impl<'a> U8Pool<'a> {
         self.into_iter()
     }
 
+    /// Returns an iterator over the slices with their indices.
+    ///
+    /// The indices are stable: while iterating, and after pops from the end,
+    /// an index gives the same slice to `get`.
+    pub fn iter_indexed(&self) -> core::iter::Enumerate<U8PoolIter<'_>> {
+        self.iter().enumerate()
+    }
+
     /// Returns a reverse iterator over the slices in the vector.
     #[must_use]
     pub fn iter_rev(&self) -> U8PoolRevIter<'_> {
@@ -0,0 +1,55 @@
+use crate::U8Pool;
+
+/// A difference between two pools, reported by `U8Pool::diff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum U8PoolDiff<'a> {
+    /// The slice exists only in the other pool
+    Added {
+        /// Index of the slice
+        index: usize,
+        /// The slice in the other pool
+        data: &'a [u8],
+    },
+    /// The slice exists only in this pool
+    Removed {
+        /// Index of the slice
+        index: usize,
+        /// The slice in this pool
+        data: &'a [u8],
+    },
+    /// The slices at the same index are different
+    Changed {
+        /// Index of the slice
+        index: usize,
+        /// The slice in this pool
+        old: &'a [u8],
+        /// The slice in the other pool
+        new: &'a [u8],
+    },
+}
+
+impl U8Pool<'_> {
+    /// Compares the slices of this pool (old) and `other` (new) position by position,
+    /// and calls `cb` for each difference, in the order of the indexes.
+    ///
+    /// The slices after the end of the shorter pool are reported as added or removed.
+    /// Associated values are compared as the part of the slices.
+    pub fn diff<'s>(&'s self, other: &'s U8Pool<'_>, mut cb: impl FnMut(U8PoolDiff<'s>)) {
+        let mut old_iter = self.iter();
+        let mut new_iter = other.iter();
+        let mut index = 0;
+        loop {
+            match (old_iter.next(), new_iter.next()) {
+                (Some(old), Some(new)) => {
+                    if old != new {
+                        cb(U8PoolDiff::Changed { index, old, new });
+                    }
+                }
+                (Some(data), None) => cb(U8PoolDiff::Removed { index, data }),
+                (None, Some(data)) => cb(U8PoolDiff::Added { index, data }),
+                (None, None) => break,
+            }
+            index += 1;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::error::U8PoolError;
+use crate::U8Pool;
+
+/// Writer for a slice which is built piece by piece, see `U8Pool::begin_element`.
+///
+/// The bytes are written directly to the free space of the pool.
+/// The slice is added to the pool only on `commit`. If the writer is dropped
+/// or `abort` is called, the pool is not changed.
+#[derive(Debug)]
+pub struct ElementWriter<'p, 'a> {
+    pool: &'p mut U8Pool<'a>,
+    start: usize,
+    len: usize,
+}
+
+impl<'p, 'a> ElementWriter<'p, 'a> {
+    pub(crate) fn new(pool: &'p mut U8Pool<'a>, start: usize) -> Self {
+        Self {
+            pool,
+            start,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if nothing is written yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bytes written so far.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pool
+            .data_region(self.start, self.start + self.len)
+            .unwrap_or(&[])
+    }
+
+    /// Appends the bytes to the element.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::BufferOverflow` if there is insufficient space
+    /// in the buffer. Then nothing is appended.
+    pub fn append(&mut self, bytes: &[u8]) -> Result<(), U8PoolError> {
+        let begin = self.start + self.len;
+        let end = begin + bytes.len();
+        let available = self.pool.data_capacity().saturating_sub(begin);
+        // The element takes one of the free slices
+        let free_slices = self.pool.free_slices().saturating_sub(1);
+        let dst = self
+            .pool
+            .data_region_mut(begin, end)
+            .ok_or(U8PoolError::BufferOverflow {
+                requested: bytes.len(),
+                available,
+                free_slices,
+            })?;
+        dst.copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    /// Adds the element to the pool and returns a reference to the stored slice.
+    ///
+    /// # Errors
+    ///
+    /// Returns `U8PoolError::ValueTooLarge` if the slice position or length
+    /// exceeds `u16::MAX`. Then the pool is not changed.
+    pub fn commit(self) -> Result<&'p [u8], U8PoolError> {
+        let Self { pool, start, len } = self;
+        pool.finalize_element(start, len)
+    }
+
+    /// Discards the element, the pool is not changed.
+    pub fn abort(self) {}
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::ErrorType for ElementWriter<'_, '_> {
+    type Error = embedded_io::ErrorKind;
+}
+
+/// Each `write` writes the whole buffer or fails with `OutOfMemory`
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Write for ElementWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.append(buf)
+            .map_err(|_| embedded_io::ErrorKind::OutOfMemory)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
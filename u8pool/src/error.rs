@@ -1,12 +1,15 @@
 /// Error types for `U8Pool` operations
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum U8PoolError {
     /// Buffer has insufficient space for the requested operation
     BufferOverflow {
-        /// Number of bytes requested
+        /// Number of bytes requested, including the alignment and the associated value
         requested: usize,
         /// Number of bytes available
         available: usize,
+        /// Number of slices which can still be added
+        free_slices: usize,
     },
     /// Index is beyond the current vector length
     IndexOutOfBounds {
@@ -24,6 +27,10 @@ pub enum U8PoolError {
     SliceLimitExceeded {
         /// Maximum number of slices allowed
         max_slices: usize,
+        /// Number of bytes requested, including the alignment and the associated value
+        requested: usize,
+        /// Number of bytes available
+        available: usize,
     },
     /// Value too large for 2-byte storage
     ValueTooLarge {
@@ -32,6 +39,18 @@ pub enum U8PoolError {
         /// Maximum allowed value
         max: usize,
     },
+    /// The alignment for `U8Pool::push_aligned` is not a power of two
+    InvalidAlignment {
+        /// The requested alignment
+        align: usize,
+    },
+    /// The metadata of the pool is inconsistent, reported by `U8Pool::check_invariants`
+    InvariantViolation {
+        /// Index of the slice with the bad descriptor, or the number of slices
+        index: usize,
+        /// Description of the violated invariant
+        reason: &'static str,
+    },
 }
 
 #[cfg(any(feature = "std", feature = "display"))]
@@ -41,9 +60,10 @@ impl core::fmt::Display for U8PoolError {
             U8PoolError::BufferOverflow {
                 requested,
                 available,
+                free_slices,
             } => write!(
                 f,
-                "Buffer overflow: requested {requested} bytes, but only {available} bytes available"
+                "Buffer overflow: requested {requested} bytes, but only {available} bytes available ({free_slices} free slices)"
             ),
             U8PoolError::IndexOutOfBounds { index, length } => write!(
                 f,
@@ -52,15 +72,42 @@ impl core::fmt::Display for U8PoolError {
             U8PoolError::InvalidInitialization { reason } => {
                 write!(f, "Invalid U8Pool initialization: {reason}")
             }
-            U8PoolError::SliceLimitExceeded { max_slices } => {
+            U8PoolError::SliceLimitExceeded {
+                max_slices,
+                requested,
+                available,
+            } => {
                 write!(
                     f,
-                    "Slice limit exceeded: maximum {max_slices} slices allowed"
+                    "Slice limit exceeded: maximum {max_slices} slices allowed (requested {requested} bytes, {available} bytes available)"
                 )
             }
             U8PoolError::ValueTooLarge { value, max } => {
                 write!(f, "Value too large: {value} exceeds maximum of {max}")
             }
+            U8PoolError::InvalidAlignment { align } => {
+                write!(f, "Invalid alignment: {align} is not a power of two")
+            }
+            U8PoolError::InvariantViolation { index, reason } => {
+                write!(f, "Invariant violation at slice {index}: {reason}")
+            }
         }
     }
 }
+
+/// Error of `U8Pool::push_from_reader`
+#[cfg(feature = "embedded-io")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PushFromReaderError<E> {
+    /// The pool can't store the slice
+    Pool(U8PoolError),
+    /// The reader failed
+    Read(E),
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E> From<U8PoolError> for PushFromReaderError<E> {
+    fn from(e: U8PoolError) -> Self {
+        PushFromReaderError::Pool(e)
+    }
+}
@@ -0,0 +1,42 @@
+//! A `#[repr(C)]` view of the pool buffers, for code in other languages.
+//!
+//! The view is read-only and describes the buffer as it is, without copying.
+//! The matching C declaration:
+//!
+//! ```c
+//! typedef struct {
+//!     const uint8_t *metadata;
+//!     size_t metadata_len;
+//!     const uint8_t *data;
+//!     size_t data_len;
+//!     size_t count;
+//!     size_t descriptor_size;
+//! } U8PoolFfi;
+//! ```
+//!
+//! The descriptor of the slice `i` is at `metadata + i * descriptor_size`.
+//! With `descriptor_size` 4, it is the start offset in the data section and
+//! the length, both little-endian `u16`. With `descriptor_size` 2 (a pool from
+//! `U8Pool::new_compact`), they are one byte each. The slice is
+//! `data[start..start + length]`.
+//!
+//! For a slice with an associated value, the range covers the associated
+//! value, placed at an aligned address, followed by the data bytes.
+
+/// The raw parts of a pool, see `U8Pool::as_ffi_parts`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct U8PoolFfi {
+    /// The descriptors of the stored slices
+    pub metadata: *const u8,
+    /// The bytes of the descriptors of the stored slices: `count * descriptor_size`
+    pub metadata_len: usize,
+    /// The data section
+    pub data: *const u8,
+    /// The size of the data section, all descriptors point inside it
+    pub data_len: usize,
+    /// The number of slices in the pool
+    pub count: usize,
+    /// The size of one slice descriptor: 4 bytes, or 2 bytes for `U8Pool::new_compact`
+    pub descriptor_size: usize,
+}
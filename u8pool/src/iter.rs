@@ -107,6 +107,22 @@ impl<'a> Iterator for U8PoolPairIter<'a> {
 
 impl ExactSizeIterator for U8PoolPairIter<'_> {}
 
+/// Splits a slice stored with `push_assoc` into the associated value and the data.
+///
+/// # Safety
+///
+/// The slice should be stored with `push_assoc::<T>`: it starts at an address
+/// aligned for `T`, and its first bytes are the value of `T`.
+#[allow(unsafe_code)]
+unsafe fn split_assoc<T: Sized>(item: &[u8]) -> Option<(&T, &[u8])> {
+    let assoc_size = core::mem::size_of::<T>();
+    let assoc_slice = item.get(..assoc_size)?;
+    let data_slice = item.get(assoc_size..)?;
+    // Safe: forwarded to the caller
+    let assoc_ref = unsafe { &*assoc_slice.as_ptr().cast::<T>() };
+    Some((assoc_ref, data_slice))
+}
+
 /// Iterator over associated values and data slices in a `U8Pool`
 ///
 /// This iterator implements `Clone`.
@@ -149,19 +165,20 @@ impl<'a, T: Sized + 'a> ExactSizeIterator for U8PoolAssocIter<'a, T> {}
 
 /// Reverse iterator over associated values and data slices in a `U8Pool`
 ///
+/// Each step reads one slice descriptor, the cost doesn't depend
+/// on the number of slices in the pool.
+///
 /// This iterator implements `Clone`.
 #[derive(Clone)]
 pub struct U8PoolAssocRevIter<'a, T> {
-    pool: &'a U8Pool<'a>,
-    current_index: usize,
+    iter: U8PoolRevIter<'a>,
     _phantom: core::marker::PhantomData<T>,
 }
 
 impl<'a, T: Sized> U8PoolAssocRevIter<'a, T> {
     pub(crate) fn new(u8pool: &'a U8Pool<'a>) -> Self {
         Self {
-            pool: u8pool,
-            current_index: u8pool.len(),
+            iter: U8PoolRevIter::new(u8pool),
             _phantom: core::marker::PhantomData,
         }
     }
@@ -171,20 +188,17 @@ impl<'a, T: Sized + 'a> Iterator for U8PoolAssocRevIter<'a, T> {
     type Item = (&'a T, &'a [u8]);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_index == 0 {
-            return None;
-        }
-        self.current_index -= 1;
+        let item = self.iter.next()?;
         // Safe: The iterator was created via unsafe iter_assoc_rev() call, which established
         // that type T matches the stored associated type for all items in the pool
         #[allow(unsafe_code)]
         unsafe {
-            self.pool.get_assoc::<T>(self.current_index)
+            split_assoc::<T>(item)
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.current_index, Some(self.current_index))
+        self.iter.size_hint()
     }
 }
 
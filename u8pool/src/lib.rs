@@ -2,10 +2,22 @@
 #![doc = include_str!("../README.md")]
 
 mod core;
+mod diff;
+mod element;
 mod error;
+#[cfg(feature = "ffi")]
+mod ffi;
 mod iter;
+mod report;
 mod slice_descriptor;
 
 pub use core::U8Pool;
+pub use diff::U8PoolDiff;
+pub use element::ElementWriter;
+#[cfg(feature = "embedded-io")]
+pub use error::PushFromReaderError;
 pub use error::U8PoolError;
+#[cfg(feature = "ffi")]
+pub use ffi::U8PoolFfi;
 pub use iter::{U8PoolAssocIter, U8PoolAssocRevIter, U8PoolIter, U8PoolPairIter, U8PoolRevIter};
+pub use report::MemoryReport;
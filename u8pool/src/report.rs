@@ -0,0 +1,32 @@
+/// How a pool uses its buffer, see `U8Pool::memory_report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// The size of one slice descriptor: 4 bytes, or 2 bytes for `U8Pool::new_compact`
+    pub descriptor_size: usize,
+    /// The maximum number of slices
+    pub max_slices: usize,
+    /// The number of slices in the pool
+    pub slices: usize,
+    /// The bytes reserved for the descriptors: `max_slices * descriptor_size`
+    pub metadata_capacity: usize,
+    /// The bytes of the descriptors of the stored slices
+    pub metadata_used: usize,
+    /// The size of the data section, the rest of the buffer after the metadata
+    pub data_capacity: usize,
+    /// The bytes used in the data section, including associated values and alignment padding
+    pub data_used: usize,
+}
+
+impl MemoryReport {
+    /// The bytes left for the data of new slices
+    #[must_use]
+    pub fn data_free(&self) -> usize {
+        self.data_capacity.saturating_sub(self.data_used)
+    }
+
+    /// The size of the buffer: metadata and data
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.metadata_capacity + self.data_capacity
+    }
+}
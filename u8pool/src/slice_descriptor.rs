@@ -1,23 +1,58 @@
 use crate::error::U8PoolError;
 
-const SLICE_DESCRIPTOR_SIZE: usize = 4; // 2 bytes start + 2 bytes length
+pub const SLICE_DESCRIPTOR_SIZE: usize = 4; // 2 bytes start + 2 bytes length
+pub const COMPACT_SLICE_DESCRIPTOR_SIZE: usize = 2; // 1 byte start + 1 byte length
 
 /// Handles reading and writing slice descriptor data from/to buffer
-/// Uses 2-byte values for start and length positions
+/// Uses 2-byte values for start and length positions, or 1-byte values in the compact mode
 #[derive(Debug)]
 pub struct SliceDescriptor<'a> {
     buffer: &'a mut [u8],
+    compact: bool,
 }
 
 impl<'a> SliceDescriptor<'a> {
-    pub fn new(buffer: &'a mut [u8]) -> Self {
-        Self { buffer }
+    pub const fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            compact: false,
+        }
+    }
+
+    pub const fn new_compact(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            compact: true,
+        }
+    }
+
+    /// The number of bytes of one descriptor
+    pub const fn size(&self) -> usize {
+        if self.compact {
+            COMPACT_SLICE_DESCRIPTOR_SIZE
+        } else {
+            SLICE_DESCRIPTOR_SIZE
+        }
+    }
+
+    /// The descriptor buffer, for the raw view of the pool
+    #[cfg(feature = "ffi")]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buffer
+    }
+
+    const fn max_value(&self) -> usize {
+        if self.compact {
+            u8::MAX as usize
+        } else {
+            u16::MAX as usize
+        }
     }
 
     /// Retrieves the slice descriptor at the specified index.
     ///
     /// Reads the stored start position and length from the descriptor buffer
-    /// using little-endian byte order for both 16-bit values.
+    /// using little-endian byte order for both 16-bit values, or as two bytes in the compact mode.
     ///
     /// # Returns
     ///
@@ -33,11 +68,18 @@ impl<'a> SliceDescriptor<'a> {
     /// buffer bounds, but we rely on `set()` to enforce valid values when writing.
     #[allow(clippy::indexing_slicing)] // Bounds checked above
     pub fn get(&self, index: usize) -> Option<(usize, usize)> {
-        let offset = index * SLICE_DESCRIPTOR_SIZE;
-        if offset + SLICE_DESCRIPTOR_SIZE > self.buffer.len() {
+        let size = self.size();
+        let offset = index * size;
+        if offset + size > self.buffer.len() {
             return None;
         }
 
+        if self.compact {
+            return Some((
+                usize::from(self.buffer[offset]),
+                usize::from(self.buffer[offset + 1]),
+            ));
+        }
         let start = u16::from(self.buffer[offset]) | (u16::from(self.buffer[offset + 1]) << 8);
         let length = u16::from(self.buffer[offset + 2]) | (u16::from(self.buffer[offset + 3]) << 8);
 
@@ -46,27 +88,29 @@ impl<'a> SliceDescriptor<'a> {
 
     #[allow(clippy::cast_possible_truncation, clippy::indexing_slicing)]
     pub fn set(&mut self, index: usize, start: usize, length: usize) -> Result<(), U8PoolError> {
-        if start > u16::MAX as usize {
-            return Err(U8PoolError::ValueTooLarge {
-                value: start,
-                max: u16::MAX as usize,
-            });
+        let max = self.max_value();
+        if start > max {
+            return Err(U8PoolError::ValueTooLarge { value: start, max });
         }
-        if length > u16::MAX as usize {
-            return Err(U8PoolError::ValueTooLarge {
-                value: length,
-                max: u16::MAX as usize,
-            });
+        if length > max {
+            return Err(U8PoolError::ValueTooLarge { value: length, max });
         }
 
-        let offset = index * SLICE_DESCRIPTOR_SIZE;
-        if offset + SLICE_DESCRIPTOR_SIZE > self.buffer.len() {
+        let size = self.size();
+        let offset = index * size;
+        if offset + size > self.buffer.len() {
             return Err(U8PoolError::IndexOutOfBounds {
                 index,
-                length: self.buffer.len() / SLICE_DESCRIPTOR_SIZE,
+                length: self.buffer.len() / size,
             });
         }
 
+        if self.compact {
+            self.buffer[offset] = start as u8;
+            self.buffer[offset + 1] = length as u8;
+            return Ok(());
+        }
+
         let start_u16 = start as u16;
         let length_u16 = length as u16;
 
@@ -86,10 +130,16 @@ impl<'a> SliceDescriptor<'a> {
         }
     }
 
+    /// The bounds are checked once here, then each step takes
+    /// the last descriptor of the remaining bytes
     pub fn iter_rev(&self, count: usize) -> SliceDescriptorRevIter<'_> {
+        let entries = count
+            .checked_mul(self.size())
+            .and_then(|len| self.buffer.get(..len))
+            .unwrap_or(&[]);
         SliceDescriptorRevIter {
-            descriptor: self,
-            current: count,
+            entries,
+            compact: self.compact,
         }
     }
 }
@@ -126,24 +176,36 @@ impl ExactSizeIterator for SliceDescriptorIter<'_> {}
 /// Reverse iterator over slice descriptors
 #[derive(Clone)]
 pub struct SliceDescriptorRevIter<'a> {
-    descriptor: &'a SliceDescriptor<'a>,
-    current: usize,
+    /// The descriptors which are not yet visited
+    entries: &'a [u8],
+    compact: bool,
 }
 
 impl Iterator for SliceDescriptorRevIter<'_> {
     type Item = (usize, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current > 0 {
-            self.current -= 1;
-            self.descriptor.get(self.current)
-        } else {
-            None
+        if self.compact {
+            let (rest, &[start, length]) = self.entries.split_last_chunk::<2>()?;
+            self.entries = rest;
+            return Some((usize::from(start), usize::from(length)));
         }
+        let (rest, &[start_lo, start_hi, length_lo, length_hi]) =
+            self.entries.split_last_chunk::<4>()?;
+        self.entries = rest;
+        let start = u16::from_le_bytes([start_lo, start_hi]);
+        let length = u16::from_le_bytes([length_lo, length_hi]);
+        Some((usize::from(start), usize::from(length)))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.current, Some(self.current))
+        let size = if self.compact {
+            COMPACT_SLICE_DESCRIPTOR_SIZE
+        } else {
+            SLICE_DESCRIPTOR_SIZE
+        };
+        let remaining = self.entries.len() / size;
+        (remaining, Some(remaining))
     }
 }
 
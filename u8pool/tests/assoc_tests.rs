@@ -96,6 +96,48 @@ fn test_push_assoc_buffer_overflow() {
     assert!(matches!(result, Err(U8PoolError::BufferOverflow { .. })));
 }
 
+#[test]
+fn test_push_assoc_with() {
+    let mut buffer = [0u8; 256];
+    let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+
+    let source = b"  Hello ";
+    let (key, data) = pool
+        .push_assoc_with(Point { x: 1, y: 2 }, source.len(), |dst| {
+            let trimmed = source.trim_ascii();
+            dst[..trimmed.len()].copy_from_slice(trimmed);
+            dst[..trimmed.len()].make_ascii_lowercase();
+            trimmed.len()
+        })
+        .unwrap();
+    assert_eq!(*key, Point { x: 1, y: 2 });
+    assert_eq!(data, b"hello");
+
+    // The unused reserved space is released
+    pool.push(b"next").unwrap();
+    let (_, data) = unsafe { pool.get_assoc::<Point>(0) }.unwrap();
+    assert_eq!(data, b"hello");
+    assert_eq!(pool.get(1).unwrap(), b"next");
+
+    // The reported length is clamped to the reserved space
+    let (_, data) = pool
+        .push_assoc_with(Point { x: 3, y: 4 }, 2, |dst| {
+            dst.fill(b'z');
+            100
+        })
+        .unwrap();
+    assert_eq!(data, b"zz");
+}
+
+#[test]
+fn test_push_assoc_with_buffer_overflow() {
+    let mut buffer = [0u8; 64];
+    let mut pool = U8Pool::new(&mut buffer, 2).unwrap();
+
+    let result = pool.push_assoc_with(Point { x: 42, y: 84 }, 100, |_| 0);
+    assert!(matches!(result, Err(U8PoolError::BufferOverflow { .. })));
+}
+
 #[test]
 fn test_pop_assoc_empty() {
     let mut buffer = [0u8; 64];
@@ -230,11 +272,12 @@ fn test_assoc_slice_limit_exceeded() {
     let result = pool.push_assoc(Point { x: 3, y: 30 }, b"third");
     assert!(matches!(
         result,
-        Err(U8PoolError::SliceLimitExceeded { max_slices: 2 })
+        Err(U8PoolError::SliceLimitExceeded { max_slices: 2, .. })
     ));
 }
 
 #[test]
+#[allow(clippy::drop_non_drop, clippy::manual_is_multiple_of)]
 fn test_alignment_padding() {
     let mut buffer = [0xAA; 1024]; // Initialize with non-zero pattern to verify padding bytes
     let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
@@ -817,6 +860,69 @@ fn test_replace_top_assoc_bytes_basic() {
     assert_eq!(top_data, b"replaced"); // Data changed
 }
 
+#[test]
+fn test_top_assoc_bytes_mut() {
+    let mut buffer = [0u8; 256];
+    let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+
+    assert!(pool.top_assoc_bytes_mut::<Point>().is_none());
+
+    pool.push_assoc(Point { x: 1, y: 2 }, b"first").unwrap();
+    pool.push_assoc(Point { x: 3, y: 4 }, b"orig").unwrap();
+
+    // Change the data in place, the length stays
+    pool.top_assoc_bytes_mut::<Point>()
+        .unwrap()
+        .copy_from_slice(b"next");
+
+    let (top_key, top_data) = unsafe { pool.get_assoc::<Point>(1) }.unwrap();
+    assert_eq!(*top_key, Point { x: 3, y: 4 });
+    assert_eq!(top_data, b"next");
+    let (_key, first_data) = unsafe { pool.get_assoc::<Point>(0) }.unwrap();
+    assert_eq!(first_data, b"first");
+}
+
+#[test]
+fn test_rewrite_top_assoc_bytes() {
+    let mut buffer = [0u8; 128];
+    let mut pool = U8Pool::new(&mut buffer, 4).unwrap();
+
+    assert!(matches!(
+        pool.rewrite_top_assoc_bytes::<Point>(4, |_| 0),
+        Err(U8PoolError::IndexOutOfBounds { .. })
+    ));
+
+    pool.push_assoc(Point { x: 1, y: 2 }, b"first").unwrap();
+    pool.push_assoc(Point { x: 3, y: 4 }, b"ab").unwrap();
+
+    // Expand the data, the space starts with the old data
+    let data = pool
+        .rewrite_top_assoc_bytes::<Point>(6, |space| {
+            assert_eq!(space.len(), 6);
+            assert_eq!(space.get(..2), Some(&b"ab"[..]));
+            space.copy_within(0..2, 4);
+            space.copy_within(0..2, 2);
+            6
+        })
+        .unwrap();
+    assert_eq!(data, b"ababab");
+
+    // The result is clamped, the associated object is kept
+    let data = pool.rewrite_top_assoc_bytes::<Point>(3, |_| 10).unwrap();
+    assert_eq!(data, b"aba");
+    let (top_key, top_data) = unsafe { pool.get_assoc::<Point>(1) }.unwrap();
+    assert_eq!(*top_key, Point { x: 3, y: 4 });
+    assert_eq!(top_data, b"aba");
+    let (_key, first_data) = unsafe { pool.get_assoc::<Point>(0) }.unwrap();
+    assert_eq!(first_data, b"first");
+
+    // The space should fit into the buffer
+    assert!(matches!(
+        pool.rewrite_top_assoc_bytes::<Point>(1000, |_| 0),
+        Err(U8PoolError::BufferOverflow { .. })
+    ));
+}
+
 #[test]
 fn test_replace_top_assoc_bytes_empty_pool() {
     let mut buffer = [0u8; 256];
@@ -0,0 +1,73 @@
+use u8pool::{U8Pool, U8PoolDiff};
+
+fn collect_diff(old: &U8Pool, new: &U8Pool) -> Vec<(char, usize, Vec<u8>, Vec<u8>)> {
+    let mut diffs = Vec::new();
+    old.diff(new, |diff| {
+        diffs.push(match diff {
+            U8PoolDiff::Added { index, data } => ('+', index, Vec::new(), data.to_vec()),
+            U8PoolDiff::Removed { index, data } => ('-', index, data.to_vec(), Vec::new()),
+            U8PoolDiff::Changed { index, old, new } => ('~', index, old.to_vec(), new.to_vec()),
+        })
+    });
+    diffs
+}
+
+#[test]
+fn test_diff_equal_pools() {
+    let mut buffer1 = [0u8; 600];
+    let mut buffer2 = [0u8; 600];
+    let mut old = U8Pool::with_default_max_slices(&mut buffer1).unwrap();
+    let mut new = U8Pool::with_default_max_slices(&mut buffer2).unwrap();
+    for pool in [&mut old, &mut new] {
+        pool.push(b"key").unwrap();
+        pool.push(b"value").unwrap();
+    }
+
+    assert!(collect_diff(&old, &new).is_empty());
+}
+
+#[test]
+fn test_diff_changed_added_removed() {
+    let mut buffer1 = [0u8; 600];
+    let mut buffer2 = [0u8; 600];
+    let mut old = U8Pool::with_default_max_slices(&mut buffer1).unwrap();
+    let mut new = U8Pool::with_default_max_slices(&mut buffer2).unwrap();
+    old.push(b"name").unwrap();
+    old.push(b"alice").unwrap();
+    new.push(b"name").unwrap();
+    new.push(b"bob").unwrap();
+    new.push(b"age").unwrap();
+
+    assert_eq!(
+        collect_diff(&old, &new),
+        vec![
+            ('~', 1, b"alice".to_vec(), b"bob".to_vec()),
+            ('+', 2, Vec::new(), b"age".to_vec()),
+        ]
+    );
+    assert_eq!(
+        collect_diff(&new, &old),
+        vec![
+            ('~', 1, b"bob".to_vec(), b"alice".to_vec()),
+            ('-', 2, b"age".to_vec(), Vec::new()),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_with_empty_pool() {
+    let mut buffer1 = [0u8; 600];
+    let mut buffer2 = [0u8; 600];
+    let old = U8Pool::with_default_max_slices(&mut buffer1).unwrap();
+    let mut new = U8Pool::with_default_max_slices(&mut buffer2).unwrap();
+    new.push(b"a").unwrap();
+    new.push(b"").unwrap();
+
+    assert_eq!(
+        collect_diff(&old, &new),
+        vec![
+            ('+', 0, Vec::new(), b"a".to_vec()),
+            ('+', 1, Vec::new(), Vec::new()),
+        ]
+    );
+}
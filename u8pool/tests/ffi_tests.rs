@@ -0,0 +1,75 @@
+#![cfg(feature = "ffi")]
+
+use u8pool::{U8Pool, U8PoolFfi};
+
+// Reads the slices the way a C consumer does
+fn read_slices(view: &U8PoolFfi) -> Vec<Vec<u8>> {
+    #[allow(unsafe_code)]
+    let (metadata, data) = unsafe {
+        (
+            core::slice::from_raw_parts(view.metadata, view.metadata_len),
+            core::slice::from_raw_parts(view.data, view.data_len),
+        )
+    };
+    assert_eq!(metadata.len(), view.count * view.descriptor_size);
+    metadata
+        .chunks_exact(view.descriptor_size)
+        .map(|descriptor| {
+            let (start, len) = match *descriptor {
+                [start, len] => (usize::from(start), usize::from(len)),
+                [s0, s1, l0, l1] => (
+                    usize::from(u16::from_le_bytes([s0, s1])),
+                    usize::from(u16::from_le_bytes([l0, l1])),
+                ),
+                _ => panic!("unexpected descriptor size"),
+            };
+            data[start..start + len].to_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn ffi_view_reads_slices() {
+    let mut buffer = [0u8; 600];
+    let mut pool = U8Pool::new(&mut buffer, 4).unwrap();
+    pool.push(b"hello").unwrap();
+    pool.push(b"").unwrap();
+    pool.push(&[7u8; 300]).unwrap();
+
+    let view = pool.as_ffi_parts();
+    assert_eq!(view.count, 3);
+    assert_eq!(view.descriptor_size, 4);
+    assert_eq!(view.data_len, pool.memory_report().data_capacity);
+    assert_eq!(
+        read_slices(&view),
+        vec![b"hello".to_vec(), Vec::new(), vec![7u8; 300]]
+    );
+}
+
+#[test]
+fn ffi_view_of_compact_pool() {
+    let mut buffer = [0u8; 64];
+    let mut pool = U8Pool::new_compact(&mut buffer, 8).unwrap();
+    pool.push(b"ab").unwrap();
+    pool.push(b"cde").unwrap();
+    pool.pop();
+    pool.push(b"f").unwrap();
+
+    let view = pool.as_ffi_parts();
+    assert_eq!(view.descriptor_size, 2);
+    assert_eq!(view.data_len, 64 - 8 * 2);
+    assert_eq!(read_slices(&view), vec![b"ab".to_vec(), b"f".to_vec()]);
+}
+
+#[test]
+fn ffi_view_of_empty_pool() {
+    let mut buffer = [0u8; 64];
+    let pool = U8Pool::new(&mut buffer, 4).unwrap();
+    let view = pool.as_ffi_parts();
+    assert_eq!(view.count, 0);
+    assert_eq!(view.metadata_len, 0);
+    assert!(read_slices(&view).is_empty());
+
+    let pool = U8Pool::new_uninit();
+    assert_eq!(read_slices(&pool.as_ffi_parts()), Vec::<Vec<u8>>::new());
+}
@@ -0,0 +1,124 @@
+use proptest::prelude::*;
+use u8pool::U8Pool;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Push(Vec<u8>),
+    PushAssoc(u32, Vec<u8>),
+    Pop,
+    Retain(u32),
+    Element(Vec<Vec<u8>>, bool),
+    Clear,
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    let data = || prop::collection::vec(any::<u8>(), 0..24);
+    prop_oneof![
+        4 => data().prop_map(Op::Push),
+        2 => (any::<u32>(), data()).prop_map(|(assoc, data)| Op::PushAssoc(assoc, data)),
+        3 => Just(Op::Pop),
+        1 => any::<u32>().prop_map(Op::Retain),
+        1 => (prop::collection::vec(data(), 0..4), any::<bool>())
+            .prop_map(|(chunks, commit)| Op::Element(chunks, commit)),
+        1 => Just(Op::Clear),
+    ]
+}
+
+/// Applies the operation to the pool and to the model, the stored bytes of each slice
+fn apply(pool: &mut U8Pool, model: &mut Vec<Vec<u8>>, op: &Op) {
+    match op {
+        Op::Push(data) => {
+            if pool.push(data).is_ok() {
+                model.push(data.clone());
+            }
+        }
+        Op::PushAssoc(assoc, data) => {
+            if pool.push_assoc(*assoc, data).is_ok() {
+                let mut stored = assoc.to_ne_bytes().to_vec();
+                stored.extend_from_slice(data);
+                model.push(stored);
+            }
+        }
+        Op::Pop => {
+            assert_eq!(pool.pop().map(<[u8]>::to_vec), model.pop());
+        }
+        Op::Retain(mask) => {
+            let mut index = 0;
+            pool.retain(|_| {
+                let keep = mask & (1 << (index % 32)) != 0;
+                index += 1;
+                keep
+            });
+            let mut index = 0;
+            model.retain(|_| {
+                let keep = mask & (1 << (index % 32)) != 0;
+                index += 1;
+                keep
+            });
+        }
+        Op::Element(chunks, commit) => {
+            let Ok(mut element) = pool.begin_element() else {
+                return;
+            };
+            let mut stored = Vec::new();
+            for chunk in chunks {
+                if element.append(chunk).is_ok() {
+                    stored.extend_from_slice(chunk);
+                }
+            }
+            if *commit {
+                element.commit().unwrap();
+                model.push(stored);
+            } else {
+                element.abort();
+            }
+        }
+        Op::Clear => {
+            pool.clear();
+            model.clear();
+        }
+    }
+}
+
+fn run_ops(pool: &mut U8Pool, ops: &[Op]) -> Result<(), TestCaseError> {
+    let mut model = Vec::new();
+    for op in ops {
+        apply(pool, &mut model, op);
+        prop_assert_eq!(pool.check_invariants(), Ok(()), "after {:?}", op);
+        let stored: Vec<Vec<u8>> = pool.iter().map(<[u8]>::to_vec).collect();
+        prop_assert_eq!(&stored, &model, "after {:?}", op);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn random_operations_keep_invariants(
+        ops in prop::collection::vec(op_strategy(), 0..64),
+        buffer_size in 40usize..400,
+        max_slices in 1usize..8,
+    ) {
+        let mut buffer = vec![0u8; buffer_size];
+        let mut pool = U8Pool::new(&mut buffer, max_slices).unwrap();
+        run_ops(&mut pool, &ops)?;
+    }
+
+    #[test]
+    fn random_operations_keep_invariants_compact(
+        ops in prop::collection::vec(op_strategy(), 0..64),
+        data_size in 1usize..256,
+        max_slices in 1usize..16,
+    ) {
+        let mut buffer = vec![0u8; max_slices * 2 + data_size];
+        let mut pool = U8Pool::new_compact(&mut buffer, max_slices).unwrap();
+        run_ops(&mut pool, &ops)?;
+    }
+}
+
+#[test]
+fn new_and_uninit_pools_are_valid() {
+    let mut buffer = [0u8; 64];
+    let pool = U8Pool::new(&mut buffer, 4).unwrap();
+    assert_eq!(pool.check_invariants(), Ok(()));
+    assert_eq!(U8Pool::new_uninit().check_invariants(), Ok(()));
+}
@@ -318,3 +318,97 @@ fn test_assoc_reverse_iterator_clone() {
     assert_eq!(data, b"first");
     assert_eq!(iter1.next(), None);
 }
+
+#[test]
+fn test_iter_indexed() {
+    let mut buffer = [0u8; 256];
+    let mut pool = U8Pool::new(&mut buffer, 8).unwrap();
+    pool.push(b"a").unwrap();
+    pool.push(b"bb").unwrap();
+    pool.push(b"ccc").unwrap();
+
+    for (index, slice) in pool.iter_indexed() {
+        assert_eq!(pool.get(index), Some(slice));
+    }
+
+    let indexed: Vec<(usize, Vec<u8>)> = pool
+        .iter_indexed()
+        .map(|(index, slice)| (index, slice.to_vec()))
+        .collect();
+    pool.pop();
+    for (index, slice) in indexed.iter().take(2) {
+        assert_eq!(pool.get(*index), Some(slice.as_slice()));
+    }
+}
+
+#[test]
+fn test_retain() {
+    let mut buffer = [0u8; 64];
+    let mut pool = U8Pool::new(&mut buffer, 8).unwrap();
+    for word in [&b"keep1"[..], b"drop", b"keep2", b"drop", b"drop", b"keep3"] {
+        pool.push(word).unwrap();
+    }
+
+    pool.retain(|slice| slice.starts_with(b"keep"));
+
+    let kept: Vec<&[u8]> = pool.iter().collect();
+    assert_eq!(kept, [&b"keep1"[..], b"keep2", b"keep3"]);
+    // The space of the removed slices is free: 32 data bytes, 15 of them used
+    assert_eq!(pool.memory_report().data_used, 15);
+    pool.push(&[b'x'; 17]).unwrap();
+
+    pool.retain(|_| false);
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn test_retain_assoc_keeps_alignment() {
+    let mut buffer = [0u8; 256];
+    let mut pool = U8Pool::new(&mut buffer, 8).unwrap();
+    for (i, name) in [&b"a"[..], b"bcd", b"e", b"fgh"].iter().enumerate() {
+        let x = i32::try_from(i).unwrap();
+        pool.push_assoc(Point { x, y: -x }, name).unwrap();
+    }
+
+    unsafe { pool.retain_assoc::<Point>(|point, _name| point.x % 2 == 1) };
+
+    let kept: Vec<(Point, Vec<u8>)> = unsafe { pool.iter_assoc::<Point>() }
+        .map(|(point, name)| {
+            assert_eq!(
+                (point as *const Point as usize) % core::mem::align_of::<Point>(),
+                0
+            );
+            (*point, name.to_vec())
+        })
+        .collect();
+    assert_eq!(
+        kept,
+        [
+            (Point { x: 1, y: -1 }, b"bcd".to_vec()),
+            (Point { x: 3, y: -3 }, b"fgh".to_vec())
+        ]
+    );
+}
+
+#[test]
+fn test_reverse_iterators_compact_pool() {
+    let mut buffer = [0u8; 128];
+    let mut u8pool = U8Pool::new_compact(&mut buffer, 8).unwrap();
+
+    u8pool.push_assoc(Point { x: 1, y: 2 }, b"first").unwrap();
+    u8pool.push_assoc(Point { x: 3, y: 4 }, b"second").unwrap();
+    u8pool.push_assoc(Point { x: 5, y: 6 }, b"third").unwrap();
+    unsafe { u8pool.pop_assoc::<Point>() }.unwrap();
+
+    let items: Vec<_> = unsafe { u8pool.iter_assoc_rev::<Point>() }
+        .map(|(point, data)| (*point, data))
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            (Point { x: 3, y: 4 }, &b"second"[..]),
+            (Point { x: 1, y: 2 }, &b"first"[..]),
+        ]
+    );
+    assert_eq!(u8pool.iter_rev().len(), 2);
+}
@@ -1,4 +1,4 @@
-use u8pool::U8Pool;
+use u8pool::{U8Pool, U8PoolError};
 
 #[test]
 fn test_buffer_initialization() {
@@ -20,6 +20,41 @@ fn test_bounds_checking_empty_buffer() {
     assert!(u8pool.get(0).is_none());
 }
 
+#[test]
+fn test_uninit_then_init() {
+    let mut u8pool = U8Pool::new_uninit();
+    assert!(!u8pool.is_initialized());
+    assert!(u8pool.is_empty());
+    assert!(matches!(
+        u8pool.push(b"data"),
+        Err(U8PoolError::SliceLimitExceeded { max_slices: 0, .. })
+    ));
+
+    let mut small_buffer = [0u8; 4];
+    assert!(u8pool.init(&mut small_buffer, 8).is_err());
+    assert!(!u8pool.is_initialized());
+
+    let mut buffer = [0u8; 64];
+    u8pool.init(&mut buffer, 4).unwrap();
+    assert!(u8pool.is_initialized());
+    u8pool.push(b"data").unwrap();
+    assert_eq!(u8pool.top(), Some(b"data".as_slice()));
+}
+
+static STATIC_POOL: std::sync::Mutex<U8Pool<'static>> = std::sync::Mutex::new(U8Pool::new_uninit());
+
+#[test]
+fn test_static_pool() {
+    let buffer: &'static mut [u8] = Box::leak(vec![0u8; 128].into_boxed_slice());
+    let mut u8pool = STATIC_POOL.lock().unwrap();
+    u8pool.init(buffer, 8).unwrap();
+
+    u8pool.push(b"first").unwrap();
+    u8pool.push(b"second").unwrap();
+    assert_eq!(u8pool.len(), 2);
+    assert_eq!(u8pool.pop(), Some(b"second".as_slice()));
+}
+
 #[test]
 fn test_pop_empty_vector() {
     let mut buffer = [0u8; 600];
@@ -152,7 +187,14 @@ fn test_stack_buffer_overflow() {
 
     // Try to push data that won't fit
     let large_data = vec![b'x'; 20]; // Should exceed remaining space
-    assert!(u8pool.push(&large_data).is_err());
+    assert_eq!(
+        u8pool.push(&large_data).unwrap_err(),
+        U8PoolError::BufferOverflow {
+            requested: 20,
+            available: 12,
+            free_slices: 30,
+        }
+    );
 
     // Stack should be unchanged
     assert_eq!(u8pool.len(), 2);
@@ -358,3 +400,295 @@ fn test_top_pointer_equality_with_get() {
     assert_eq!(top_ref.as_ptr(), get_ref.as_ptr());
     assert_eq!(top_ref, get_ref);
 }
+
+#[test]
+fn test_element_writer_commit_and_abort() {
+    let mut buffer = [0u8; 256];
+    let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+    pool.push(b"first").unwrap();
+
+    let mut element = pool.begin_element().unwrap();
+    assert!(element.is_empty());
+    element.append(b"hello").unwrap();
+    element.append(b", ").unwrap();
+    element.append(b"world").unwrap();
+    assert_eq!(element.len(), 12);
+    assert_eq!(element.as_bytes(), b"hello, world");
+    assert_eq!(element.commit().unwrap(), b"hello, world");
+
+    let mut element = pool.begin_element().unwrap();
+    element.append(b"discarded").unwrap();
+    element.abort();
+    // Dropped without commit: also discarded
+    pool.begin_element().unwrap().append(b"dropped").unwrap();
+
+    pool.push(b"last").unwrap();
+    assert_eq!(pool.len(), 3);
+    assert_eq!(pool.get(0).unwrap(), b"first");
+    assert_eq!(pool.get(1).unwrap(), b"hello, world");
+    assert_eq!(pool.get(2).unwrap(), b"last");
+}
+
+#[test]
+fn test_element_writer_errors() {
+    let mut buffer = [0u8; 16];
+    let mut pool = U8Pool::new(&mut buffer, 2).unwrap();
+
+    // 8 bytes for data
+    let mut element = pool.begin_element().unwrap();
+    element.append(b"12345").unwrap();
+    assert_eq!(
+        element.append(b"6789"),
+        Err(U8PoolError::BufferOverflow {
+            requested: 4,
+            available: 3,
+            free_slices: 1,
+        })
+    );
+    assert_eq!(element.as_bytes(), b"12345");
+    assert_eq!(element.commit().unwrap(), b"12345");
+
+    pool.push(b"").unwrap();
+    assert_eq!(
+        pool.begin_element().unwrap_err(),
+        U8PoolError::SliceLimitExceeded {
+            max_slices: 2,
+            requested: 0,
+            available: 3,
+        }
+    );
+}
+
+#[cfg(feature = "embedded-io")]
+mod push_from_reader {
+    use u8pool::{PushFromReaderError, U8Pool, U8PoolError};
+
+    /// Reader returning at most 3 bytes per call
+    struct SmallChunksReader<'a>(&'a [u8]);
+
+    impl embedded_io::ErrorType for SmallChunksReader<'_> {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    impl embedded_io::Read for SmallChunksReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if self.0.is_empty() {
+                return Err(embedded_io::ErrorKind::BrokenPipe);
+            }
+            let n = buf.len().min(3).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_push_from_reader() {
+        let mut buffer = [0u8; 256];
+        let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+        let mut reader: &[u8] = b"hello world";
+
+        assert_eq!(pool.push_from_reader(&mut reader, 5).unwrap(), b"hello");
+        // Stops at the end of input
+        assert_eq!(pool.push_from_reader(&mut reader, 100).unwrap(), b" world");
+        assert_eq!(pool.push_from_reader(&mut reader, 100).unwrap(), b"");
+
+        pool.push(b"next").unwrap();
+        assert_eq!(pool.len(), 4);
+        assert_eq!(pool.get(0).unwrap(), b"hello");
+        assert_eq!(pool.get(1).unwrap(), b" world");
+        assert_eq!(pool.get(3).unwrap(), b"next");
+    }
+
+    #[test]
+    fn test_push_from_reader_several_reads() {
+        let mut buffer = [0u8; 256];
+        let mut pool = U8Pool::with_default_max_slices(&mut buffer).unwrap();
+        let mut reader = SmallChunksReader(b"0123456789");
+
+        assert_eq!(pool.push_from_reader(&mut reader, 8).unwrap(), b"01234567");
+    }
+
+    #[test]
+    fn test_push_from_reader_errors() {
+        let mut buffer = [0u8; 64];
+        let mut pool = U8Pool::new(&mut buffer, 2).unwrap();
+
+        let mut reader = SmallChunksReader(b"0123");
+        let result = pool.push_from_reader(&mut reader, 10);
+        assert_eq!(
+            result,
+            Err(PushFromReaderError::Read(
+                embedded_io::ErrorKind::BrokenPipe
+            ))
+        );
+        assert!(pool.is_empty());
+
+        let mut reader: &[u8] = b"0123";
+        let result = pool.push_from_reader(&mut reader, 100);
+        assert!(matches!(
+            result,
+            Err(PushFromReaderError::Pool(
+                U8PoolError::BufferOverflow { .. }
+            ))
+        ));
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+#[test]
+fn test_element_writer_as_embedded_io_write() {
+    use embedded_io::Write;
+
+    let mut buffer = [0u8; 64];
+    let mut pool = U8Pool::new(&mut buffer, 4).unwrap();
+
+    let mut element = pool.begin_element().unwrap();
+    let (number, text) = (12, "ab");
+    write!(element, "{number}-{text}").unwrap();
+    element.write_all(b"!").unwrap();
+    assert_eq!(element.commit().unwrap(), b"12-ab!");
+
+    let mut element = pool.begin_element().unwrap();
+    assert_eq!(
+        element.write_all(&[b'x'; 100]),
+        Err(embedded_io::ErrorKind::OutOfMemory)
+    );
+}
+
+#[test]
+fn test_compact_descriptors() {
+    let mut buffer = [0u8; 16 + 100];
+    let mut pool = U8Pool::new_compact(&mut buffer, 8).unwrap();
+
+    pool.push(b"name").unwrap();
+    pool.push(b"Alice").unwrap();
+    assert_eq!(pool.pop(), Some(&b"Alice"[..]));
+    pool.push(&[b'x'; 96]).unwrap();
+    assert_eq!(pool.get(0), Some(&b"name"[..]));
+    assert_eq!(pool.get(1), Some(&[b'x'; 96][..]));
+    assert!(matches!(
+        pool.push(b"y"),
+        Err(U8PoolError::BufferOverflow { .. })
+    ));
+
+    // The data section should fit into 1-byte offsets
+    let mut buffer = [0u8; 16 + 256];
+    assert!(matches!(
+        U8Pool::new_compact(&mut buffer, 8),
+        Err(U8PoolError::InvalidInitialization { .. })
+    ));
+    let mut buffer = [0u8; 16 + 255];
+    assert!(U8Pool::new_compact(&mut buffer, 8).is_ok());
+}
+
+#[test]
+fn test_memory_report() {
+    let mut buffer = [0u8; 200];
+    let mut pool = U8Pool::new(&mut buffer, 10).unwrap();
+    pool.push(b"abc").unwrap();
+    pool.push_assoc(7u32, b"de").unwrap();
+
+    let report = pool.memory_report();
+    assert_eq!(report.descriptor_size, 4);
+    assert_eq!(report.max_slices, 10);
+    assert_eq!(report.slices, 2);
+    assert_eq!(report.metadata_capacity, 40);
+    assert_eq!(report.metadata_used, 8);
+    assert_eq!(report.data_capacity, 160);
+    // "abc", 1 byte of padding, the u32 and "de"
+    assert_eq!(report.data_used, 10);
+    assert_eq!(report.data_free(), 150);
+    assert_eq!(report.total(), 200);
+
+    let mut buffer = [0u8; 200];
+    let report = U8Pool::new_compact(&mut buffer, 10)
+        .unwrap()
+        .memory_report();
+    assert_eq!(report.descriptor_size, 2);
+    assert_eq!(report.metadata_capacity, 20);
+    assert_eq!(report.data_capacity, 180);
+}
+
+#[test]
+fn test_push_aligned() {
+    #[repr(C)]
+    struct Descriptor {
+        id: u32,
+        len: u16,
+        flags: u16,
+    }
+
+    // Start the buffer at an odd address to check the alignment in memory
+    let mut storage = [0u64; 32];
+    let buffer = aligned_bytes(&mut storage);
+    let mut pool = U8Pool::new(&mut buffer[1..], 4).unwrap();
+    pool.push(b"x").unwrap();
+
+    let descriptor = Descriptor {
+        id: 0x0102_0304,
+        len: 5,
+        flags: 1,
+    };
+    let mut bytes = [0u8; 8];
+    bytes[..4].copy_from_slice(&descriptor.id.to_ne_bytes());
+    bytes[4..6].copy_from_slice(&descriptor.len.to_ne_bytes());
+    bytes[6..].copy_from_slice(&descriptor.flags.to_ne_bytes());
+
+    let stored = pool.push_aligned(&bytes, 8).unwrap();
+    assert_eq!(stored, &bytes);
+    assert_eq!(stored.as_ptr() as usize % 8, 0);
+    #[allow(unsafe_code)]
+    let view = unsafe { &*stored.as_ptr().cast::<Descriptor>() };
+    assert_eq!(
+        (view.id, view.len, view.flags),
+        (descriptor.id, descriptor.len, descriptor.flags)
+    );
+
+    // The padding is not a part of the slices
+    assert_eq!(pool.get(0).unwrap(), b"x");
+    assert_eq!(pool.pop().unwrap(), &bytes);
+    assert_eq!(pool.pop().unwrap(), b"x");
+
+    // Alignment 1 is the same as `push`
+    pool.push(b"ab").unwrap();
+    let stored = pool.push_aligned(b"cd", 1).unwrap();
+    assert_eq!(stored, b"cd");
+    assert_eq!(pool.memory_report().data_used, 4);
+}
+
+#[test]
+fn test_push_aligned_errors() {
+    let mut buffer = [0u8; 40];
+    let mut pool = U8Pool::new(&mut buffer, 2).unwrap();
+    for align in [0, 3, 12] {
+        assert_eq!(
+            pool.push_aligned(b"data", align),
+            Err(U8PoolError::InvalidAlignment { align })
+        );
+    }
+    assert!(pool.is_empty());
+
+    // The padding counts to the requested size
+    let mut storage = [0u64; 4];
+    let buffer = aligned_bytes(&mut storage);
+    let mut pool = U8Pool::new(&mut buffer[1..], 1).unwrap();
+    assert!(matches!(
+        pool.push_aligned(&[0u8; 25], 8),
+        Err(U8PoolError::BufferOverflow {
+            requested: 28,
+            available: 27,
+            ..
+        })
+    ));
+    assert_eq!(pool.push_aligned(&[7u8; 16], 8).unwrap(), &[7u8; 16]);
+}
+
+// An 8-aligned byte buffer
+fn aligned_bytes(storage: &mut [u64]) -> &mut [u8] {
+    let len = core::mem::size_of_val(storage);
+    #[allow(unsafe_code)]
+    unsafe {
+        core::slice::from_raw_parts_mut(storage.as_mut_ptr().cast::<u8>(), len)
+    }
+}